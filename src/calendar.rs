@@ -0,0 +1,783 @@
+//! Calendar module.
+//!
+//! Parses iCalendar (RFC 5545) data and expands recurring events, so
+//! scripts can drive on-call and maintenance-window logic from shared
+//! calendars.
+//!
+//! Supported: `VEVENT` components with `DTSTART`, `DTEND` or `DURATION`,
+//! `SUMMARY`, `DESCRIPTION`, `LOCATION`, `UID`, `EXDATE`, and `RRULE` with
+//! `FREQ` (`DAILY`, `WEEKLY`, `MONTHLY`, `YEARLY`), `INTERVAL`, `COUNT`,
+//! `UNTIL`, and plain weekday `BYDAY` lists for weekly rules.
+//!
+//! Times are Unix timestamps in seconds. `TZID` times are converted with
+//! chrono-tz when the `tz` feature is enabled and treated as UTC otherwise;
+//! floating times are treated as UTC. Recurrences are expanded in UTC.
+
+use std::collections::HashMap;
+
+use fusabi_host::ExecutionContext;
+use fusabi_host::Value;
+
+use crate::time::{civil_from_days, days_from_civil, month_length, weekday_of, SECS_PER_DAY};
+
+/// Upper bound on candidate occurrences examined per event.
+const MAX_EXPANSION: usize = 100_000;
+
+/// Parse iCalendar data.
+///
+/// # Arguments
+///
+/// * `args[0]` - iCalendar text
+///
+/// # Returns
+///
+/// Map with `events`: a list of maps with `uid`, `summary`, `description`,
+/// `location`, `start`, `end`, `all_day`, `rrule`, and `exdates`
+pub fn parse_ics(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let data = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("calendar.parse_ics: missing data"))?;
+
+    let calendar = Calendar::parse(data)
+        .map_err(|e| fusabi_host::Error::host_function(format!("calendar.parse_ics: {}", e)))?;
+
+    Ok(calendar.to_value())
+}
+
+/// Get the next event occurrences at or after a point in time.
+///
+/// Events still in progress at `from` are included, so a maintenance window
+/// that has already started is reported.
+///
+/// # Arguments
+///
+/// * `args[0]` - Calendar from `parse_ics`, or raw iCalendar text
+/// * `args[1]` - Unix timestamp in seconds
+/// * `args[2]` - Maximum number of occurrences (optional, default 10)
+///
+/// # Returns
+///
+/// List of event maps ordered by start time, with `start` and `end` set to
+/// the occurrence and `recurring` indicating whether it came from an `RRULE`
+pub fn next_events(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let calendar = match args.first() {
+        Some(Value::String(data)) => Calendar::parse(data),
+        Some(value @ Value::Map(_)) => Calendar::from_value(value),
+        _ => {
+            return Err(fusabi_host::Error::host_function(
+                "calendar.next_events: missing calendar",
+            ))
+        }
+    }
+    .map_err(|e| fusabi_host::Error::host_function(format!("calendar.next_events: {}", e)))?;
+
+    let from = args.get(1).and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("calendar.next_events: missing from timestamp")
+    })?;
+
+    let n = match args.get(2) {
+        Some(Value::Null) | None => 10,
+        Some(value) => value.as_int().filter(|n| *n >= 0).ok_or_else(|| {
+            fusabi_host::Error::host_function(
+                "calendar.next_events: count must be a non-negative integer",
+            )
+        })? as usize,
+    };
+
+    let occurrences = calendar
+        .next_events(from, n)
+        .map_err(|e| fusabi_host::Error::host_function(format!("calendar.next_events: {}", e)))?;
+
+    Ok(Value::List(
+        occurrences
+            .iter()
+            .map(|(event, recurring)| {
+                let mut value = event.to_map();
+                value.remove("rrule");
+                value.remove("exdates");
+                value.insert("recurring".to_string(), Value::Bool(*recurring));
+                Value::Map(value)
+            })
+            .collect(),
+    ))
+}
+
+/// A calendar event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    /// Unique identifier.
+    pub uid: Option<String>,
+    /// Summary (title).
+    pub summary: Option<String>,
+    /// Description.
+    pub description: Option<String>,
+    /// Location.
+    pub location: Option<String>,
+    /// Start as a Unix timestamp in seconds.
+    pub start: i64,
+    /// End as a Unix timestamp in seconds.
+    pub end: i64,
+    /// Whether the event uses whole dates rather than times.
+    pub all_day: bool,
+    /// Raw `RRULE` value, if the event recurs.
+    pub rrule: Option<String>,
+    /// Excluded occurrence start times.
+    pub exdates: Vec<i64>,
+}
+
+impl Event {
+    fn to_map(&self) -> HashMap<String, Value> {
+        let text = |v: &Option<String>| v.clone().map(Value::String).unwrap_or(Value::Null);
+
+        let mut map = HashMap::new();
+        map.insert("uid".to_string(), text(&self.uid));
+        map.insert("summary".to_string(), text(&self.summary));
+        map.insert("description".to_string(), text(&self.description));
+        map.insert("location".to_string(), text(&self.location));
+        map.insert("start".to_string(), Value::Int(self.start));
+        map.insert("end".to_string(), Value::Int(self.end));
+        map.insert("all_day".to_string(), Value::Bool(self.all_day));
+        map.insert("rrule".to_string(), text(&self.rrule));
+        map.insert(
+            "exdates".to_string(),
+            Value::List(self.exdates.iter().map(|t| Value::Int(*t)).collect()),
+        );
+        map
+    }
+
+    fn from_map(map: &HashMap<String, Value>) -> crate::Result<Self> {
+        let text = |key: &str| map.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        let int = |key: &str| {
+            map.get(key)
+                .and_then(|v| v.as_int())
+                .ok_or_else(|| crate::Error::InvalidValue(format!("event missing '{}'", key)))
+        };
+
+        Ok(Self {
+            uid: text("uid"),
+            summary: text("summary"),
+            description: text("description"),
+            location: text("location"),
+            start: int("start")?,
+            end: int("end")?,
+            all_day: map
+                .get("all_day")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+            rrule: text("rrule"),
+            exdates: map
+                .get("exdates")
+                .and_then(|v| v.as_list())
+                .map(|list| list.iter().filter_map(|v| v.as_int()).collect())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// A parsed calendar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calendar {
+    /// Events in document order.
+    pub events: Vec<Event>,
+}
+
+impl Calendar {
+    /// Parse iCalendar text.
+    pub fn parse(data: &str) -> crate::Result<Self> {
+        let mut events = Vec::new();
+        let mut current: Option<PendingEvent> = None;
+        // Depth of nested components (e.g. VALARM) inside the current event.
+        let mut nested = 0usize;
+
+        for (number, line) in unfold(data) {
+            let (name, params, value) = split_content_line(&line)
+                .ok_or_else(|| crate::Error::format(format!("line {}: malformed", number)))?;
+            let at = |e: String| crate::Error::format(format!("line {}: {}", number, e));
+
+            match (name.as_str(), value.as_str()) {
+                ("BEGIN", "VEVENT") if current.is_none() => {
+                    current = Some(PendingEvent::default());
+                    continue;
+                }
+                ("END", "VEVENT") if nested == 0 => {
+                    if let Some(pending) = current.take() {
+                        events.push(pending.finish().map_err(at)?);
+                    }
+                    continue;
+                }
+                ("BEGIN", _) if current.is_some() => {
+                    nested += 1;
+                    continue;
+                }
+                ("END", _) if nested > 0 => {
+                    nested -= 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let Some(pending) = current.as_mut().filter(|_| nested == 0) else {
+                continue;
+            };
+            let event = &mut pending.event;
+
+            match name.as_str() {
+                "UID" => event.uid = Some(unescape(&value)),
+                "SUMMARY" => event.summary = Some(unescape(&value)),
+                "DESCRIPTION" => event.description = Some(unescape(&value)),
+                "LOCATION" => event.location = Some(unescape(&value)),
+                "RRULE" => {
+                    Rule::parse(&value).map_err(at)?;
+                    event.rrule = Some(value.clone());
+                }
+                "DTSTART" => {
+                    let (start, all_day) = parse_date_time(&value, &params).map_err(at)?;
+                    pending.start = Some(start);
+                    event.all_day = all_day;
+                }
+                "DTEND" => pending.end = Some(parse_date_time(&value, &params).map_err(at)?.0),
+                "DURATION" => pending.duration = Some(parse_duration(&value).map_err(at)?),
+                "EXDATE" => {
+                    for item in value.split(',') {
+                        event
+                            .exdates
+                            .push(parse_date_time(item, &params).map_err(at)?.0);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self { events })
+    }
+
+    /// Rebuild a calendar from the value returned by `parse_ics`.
+    pub fn from_value(value: &Value) -> crate::Result<Self> {
+        let events = value
+            .as_map()
+            .and_then(|m| m.get("events"))
+            .and_then(|v| v.as_list())
+            .ok_or_else(|| crate::Error::InvalidValue("expected a map with 'events'".into()))?;
+
+        let events = events
+            .iter()
+            .map(|event| {
+                event
+                    .as_map()
+                    .ok_or_else(|| crate::Error::InvalidValue("event must be a map".into()))
+                    .and_then(Event::from_map)
+            })
+            .collect::<crate::Result<_>>()?;
+
+        Ok(Self { events })
+    }
+
+    /// Convert to a script value.
+    pub fn to_value(&self) -> Value {
+        let mut map = HashMap::new();
+        map.insert(
+            "events".to_string(),
+            Value::List(self.events.iter().map(|e| Value::Map(e.to_map())).collect()),
+        );
+        Value::Map(map)
+    }
+
+    /// Get up to `n` occurrences that end after (or start at) `from`.
+    ///
+    /// Each occurrence is returned with a flag telling whether it was
+    /// produced by a recurrence rule.
+    pub fn next_events(&self, from: i64, n: usize) -> crate::Result<Vec<(Event, bool)>> {
+        let mut found = Vec::new();
+
+        for event in &self.events {
+            let duration = event.end - event.start;
+            let rule = event
+                .rrule
+                .as_deref()
+                .map(Rule::parse)
+                .transpose()
+                .map_err(crate::Error::format)?;
+            let mut taken = 0;
+
+            expand(event.start, rule.as_ref(), |start| {
+                let end = start + duration;
+                if (end > from || start >= from) && !event.exdates.contains(&start) {
+                    found.push((
+                        Event {
+                            start,
+                            end,
+                            ..event.clone()
+                        },
+                        rule.is_some(),
+                    ));
+                    taken += 1;
+                }
+                taken < n
+            });
+        }
+
+        found.sort_by(|(a, _), (b, _)| a.start.cmp(&b.start).then_with(|| a.uid.cmp(&b.uid)));
+        found.truncate(n);
+        Ok(found)
+    }
+}
+
+/// An event whose properties are still being read.
+#[derive(Default)]
+struct PendingEvent {
+    event: Event,
+    start: Option<i64>,
+    end: Option<i64>,
+    duration: Option<i64>,
+}
+
+impl PendingEvent {
+    fn finish(mut self) -> Result<Event, String> {
+        let start = self.start.ok_or("event without DTSTART")?;
+        self.event.start = start;
+        self.event.end = match (self.end, self.duration) {
+            (Some(end), _) => end,
+            (None, Some(duration)) => start + duration,
+            // RFC 5545: a date-only event without an end lasts one day.
+            (None, None) if self.event.all_day => start + SECS_PER_DAY,
+            (None, None) => start,
+        };
+        Ok(self.event)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A parsed `RRULE`.
+#[derive(Debug, Clone)]
+struct Rule {
+    frequency: Frequency,
+    interval: i64,
+    count: Option<usize>,
+    until: Option<i64>,
+    /// ISO weekdays (1 = Monday), sorted.
+    by_day: Vec<i64>,
+}
+
+impl Rule {
+    fn parse(value: &str) -> Result<Self, String> {
+        let mut frequency = None;
+        let mut rule = Rule {
+            frequency: Frequency::Daily,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+        };
+
+        for part in value.split(';').filter(|p| !p.is_empty()) {
+            let (key, val) = part
+                .split_once('=')
+                .ok_or_else(|| format!("invalid RRULE part '{}'", part))?;
+            let invalid = || format!("invalid RRULE {} '{}'", key, val);
+
+            match key {
+                "FREQ" => {
+                    frequency = Some(match val {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        _ => return Err(format!("unsupported RRULE frequency '{}'", val)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = val.parse().ok().filter(|n| *n > 0).ok_or_else(invalid)?
+                }
+                "COUNT" => rule.count = Some(val.parse().map_err(|_| invalid())?),
+                "UNTIL" => rule.until = Some(parse_date_time(val, &[])?.0),
+                "BYDAY" => {
+                    for day in val.split(',') {
+                        rule.by_day.push(parse_weekday(day).ok_or_else(invalid)?);
+                    }
+                    rule.by_day.sort_unstable();
+                    rule.by_day.dedup();
+                }
+                "WKST" => {}
+                _ => return Err(format!("unsupported RRULE part '{}'", key)),
+            }
+        }
+
+        rule.frequency = frequency.ok_or("RRULE without FREQ")?;
+        if !rule.by_day.is_empty() && rule.frequency != Frequency::Weekly {
+            return Err("BYDAY is only supported for weekly rules".to_string());
+        }
+        Ok(rule)
+    }
+}
+
+/// Visit occurrence start times in order until `visit` returns false.
+fn expand(start: i64, rule: Option<&Rule>, mut visit: impl FnMut(i64) -> bool) {
+    let Some(rule) = rule else {
+        visit(start);
+        return;
+    };
+
+    let start_day = start.div_euclid(SECS_PER_DAY);
+    let time_of_day = start.rem_euclid(SECS_PER_DAY);
+    let (year, month, day) = civil_from_days(start_day);
+    let mut emitted = 0;
+
+    for step in 0..MAX_EXPANSION as i64 {
+        let k = step * rule.interval;
+        let candidates: Vec<i64> = match rule.frequency {
+            Frequency::Daily => vec![start + k * SECS_PER_DAY],
+            Frequency::Weekly if rule.by_day.is_empty() => vec![start + k * 7 * SECS_PER_DAY],
+            Frequency::Weekly => {
+                let week = start_day - (weekday_of(start_day) - 1) + k * 7;
+                rule.by_day
+                    .iter()
+                    .map(|wd| (week + wd - 1) * SECS_PER_DAY + time_of_day)
+                    .filter(|t| *t >= start)
+                    .collect()
+            }
+            Frequency::Monthly | Frequency::Yearly => {
+                let months = if rule.frequency == Frequency::Yearly {
+                    k * 12
+                } else {
+                    k
+                };
+                let total = year * 12 + (month as i64 - 1) + months;
+                let (y, m) = (total.div_euclid(12), (total.rem_euclid(12) + 1) as u32);
+                // Dates that do not exist in a period (e.g. the 31st) are skipped.
+                if day as i64 > month_length(y, m) {
+                    Vec::new()
+                } else {
+                    vec![days_from_civil(y, m, day) * SECS_PER_DAY + time_of_day]
+                }
+            }
+        };
+
+        for t in candidates {
+            if rule.until.is_some_and(|until| t > until) {
+                return;
+            }
+            if rule.count.is_some_and(|count| emitted >= count) {
+                return;
+            }
+            emitted += 1;
+            if !visit(t) {
+                return;
+            }
+        }
+    }
+}
+
+/// Unfold continuation lines, returning (line number, logical line).
+fn unfold(data: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, raw) in data.lines().enumerate() {
+        match raw.strip_prefix([' ', '\t']) {
+            Some(rest) if !lines.is_empty() => lines.last_mut().unwrap().1.push_str(rest),
+            _ if raw.trim().is_empty() => {}
+            _ => lines.push((index + 1, raw.to_string())),
+        }
+    }
+    lines
+}
+
+/// Property parameters as (upper-cased name, value) pairs.
+type Params = Vec<(String, String)>;
+
+/// Split `NAME;PARAM=VALUE:value`, honouring quoted parameter values.
+fn split_content_line(line: &str) -> Option<(String, Params, String)> {
+    let mut in_quotes = false;
+    let colon = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = head.split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    let params = parts
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_ascii_uppercase(), v.trim_matches('"').to_string()))
+        .collect();
+
+    Some((name, params, value.to_string()))
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Parse a DATE or DATE-TIME value, returning (timestamp, is_date).
+fn parse_date_time(value: &str, params: &[(String, String)]) -> Result<(i64, bool), String> {
+    let value = value.trim();
+    let invalid = || format!("invalid date '{}'", value);
+    let digits = |s: &str| -> Result<u32, String> {
+        if s.bytes().all(|b| b.is_ascii_digit()) {
+            s.parse().map_err(|_| invalid())
+        } else {
+            Err(invalid())
+        }
+    };
+
+    if value.len() < 8 || !value.is_char_boundary(8) {
+        return Err(invalid());
+    }
+    let year = digits(&value[0..4])? as i64;
+    let month = digits(&value[4..6])?;
+    let day = digits(&value[6..8])?;
+    if !(1..=12).contains(&month) || day == 0 || day as i64 > month_length(year, month) {
+        return Err(invalid());
+    }
+    let days = days_from_civil(year, month, day);
+
+    let is_date = value.len() == 8
+        || params
+            .iter()
+            .any(|(k, v)| k == "VALUE" && v.eq_ignore_ascii_case("DATE"));
+    if is_date {
+        return Ok((days * SECS_PER_DAY, true));
+    }
+
+    let time = value[8..].strip_prefix('T').ok_or_else(invalid)?;
+    let (time, utc) = match time.strip_suffix('Z') {
+        Some(t) => (t, true),
+        None => (time, false),
+    };
+    if time.len() != 6 || !time.is_char_boundary(2) || !time.is_char_boundary(4) {
+        return Err(invalid());
+    }
+    let (hour, minute, second) = (
+        digits(&time[0..2])?,
+        digits(&time[2..4])?,
+        digits(&time[4..6])?,
+    );
+    if hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+
+    let naive = days * SECS_PER_DAY + hour as i64 * 3600 + minute as i64 * 60 + second as i64;
+
+    if !utc {
+        if let Some((_, tzid)) = params.iter().find(|(k, _)| k == "TZID") {
+            return Ok((zoned_to_utc(naive, tzid), false));
+        }
+    }
+    Ok((naive, false))
+}
+
+#[cfg(feature = "tz")]
+fn zoned_to_utc(naive: i64, tzid: &str) -> i64 {
+    use chrono::TimeZone;
+
+    let local = chrono::DateTime::from_timestamp(naive, 0).map(|t| t.naive_utc());
+    match (tzid.parse::<chrono_tz::Tz>(), local) {
+        (Ok(tz), Some(local)) => tz
+            .from_local_datetime(&local)
+            .earliest()
+            .map(|t| t.timestamp())
+            .unwrap_or(naive),
+        _ => {
+            tracing::debug!("calendar: unknown TZID '{}', treating as UTC", tzid);
+            naive
+        }
+    }
+}
+
+#[cfg(not(feature = "tz"))]
+fn zoned_to_utc(naive: i64, _tzid: &str) -> i64 {
+    naive
+}
+
+/// Parse an RFC 5545 DURATION such as `PT1H30M` or `P1D`, in seconds.
+fn parse_duration(value: &str) -> Result<i64, String> {
+    let invalid = || format!("invalid duration '{}'", value);
+
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut total = 0i64;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if number.is_empty() => in_time = true,
+            _ => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                total += n * match (c, in_time) {
+                    ('W', false) => 7 * SECS_PER_DAY,
+                    ('D', false) => SECS_PER_DAY,
+                    ('H', true) => 3600,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return Err(invalid()),
+                };
+            }
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(sign * total)
+}
+
+fn parse_weekday(day: &str) -> Option<i64> {
+    Some(match day.trim() {
+        "MO" => 1,
+        "TU" => 2,
+        "WE" => 3,
+        "TH" => 4,
+        "FR" => 5,
+        "SA" => 6,
+        "SU" => 7,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::Capabilities;
+    use fusabi_host::Limits;
+    use fusabi_host::{Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    const ICS: &str = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+UID:oncall@example.com\r\n\
+SUMMARY:On-call handover\\, weekly\r\n\
+DESCRIPTION:Rotate the pager\\nand update the\r\n  runbook\r\n\
+DTSTART:20240101T090000Z\r\n\
+DURATION:PT30M\r\n\
+RRULE:FREQ=WEEKLY;BYDAY=MO,TH;COUNT=5\r\n\
+EXDATE:20240108T090000Z\r\n\
+BEGIN:VALARM\r\n\
+DESCRIPTION:Reminder\r\n\
+END:VALARM\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:freeze@example.com\r\n\
+SUMMARY:Change freeze\r\n\
+DTSTART;VALUE=DATE:20240105\r\n\
+DTEND;VALUE=DATE:20240107\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_ics() {
+        let calendar = Calendar::parse(ICS).unwrap();
+        assert_eq!(calendar.events.len(), 2);
+
+        let oncall = &calendar.events[0];
+        assert_eq!(oncall.summary.as_deref(), Some("On-call handover, weekly"));
+        assert_eq!(
+            oncall.description.as_deref(),
+            Some("Rotate the pager\nand update the runbook")
+        );
+        assert_eq!(oncall.start, 1704099600); // 2024-01-01 09:00 UTC
+        assert_eq!(oncall.end, 1704099600 + 1800);
+        assert_eq!(oncall.exdates, vec![1704704400]);
+
+        let freeze = &calendar.events[1];
+        assert!(freeze.all_day);
+        assert_eq!(freeze.end - freeze.start, 2 * SECS_PER_DAY);
+
+        assert!(Calendar::parse("BEGIN:VEVENT\nDTSTART:2024-01-01\nEND:VEVENT").is_err());
+        assert!(Calendar::parse("BEGIN:VEVENT\nRRULE:FREQ=HOURLY\nEND:VEVENT").is_err());
+
+        // Round-trips through the script representation.
+        assert_eq!(
+            Calendar::from_value(&calendar.to_value()).unwrap(),
+            calendar
+        );
+    }
+
+    #[test]
+    fn test_next_events() {
+        let ctx = create_test_ctx();
+        let cal = parse_ics(&[Value::String(ICS.into())], &ctx).unwrap();
+
+        // From 2024-01-05 00:00 UTC: freeze (in progress from 00:00), then
+        // handovers on Mon 8th (excluded), Thu 11th and Mon 15th.
+        let from = Value::Int(1704412800);
+        let result = next_events(&[cal.clone(), from.clone(), Value::Int(3)], &ctx).unwrap();
+        let list = result.as_list().unwrap();
+        assert_eq!(list.len(), 3);
+
+        let field = |i: usize, key: &str| list[i].as_map().unwrap().get(key).cloned().unwrap();
+        assert_eq!(field(0, "summary"), Value::String("Change freeze".into()));
+        assert_eq!(field(0, "recurring"), Value::Bool(false));
+        assert_eq!(field(1, "start"), Value::Int(1704963600)); // Thu 11th 09:00
+        assert_eq!(field(2, "start"), Value::Int(1705309200)); // Mon 15th 09:00
+        assert_eq!(field(2, "recurring"), Value::Bool(true));
+
+        // COUNT=5 ends the rule at Mon 15th: 1st, 4th, 8th, 11th, 15th.
+        let result = next_events(&[cal, Value::Int(1705400000), Value::Int(5)], &ctx).unwrap();
+        assert_eq!(result.as_list().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_monthly_rule_skips_missing_days() {
+        let mut visited = Vec::new();
+        let rule = Rule::parse("FREQ=MONTHLY;COUNT=3").unwrap();
+        expand(1706659200, Some(&rule), |t| {
+            visited.push(civil_from_days(t.div_euclid(SECS_PER_DAY)));
+            true
+        });
+        assert_eq!(visited, vec![(2024, 1, 31), (2024, 3, 31), (2024, 5, 31)]);
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_tzid_conversion() {
+        let ics = "BEGIN:VEVENT\nDTSTART;TZID=Europe/Berlin:20240701T090000\nEND:VEVENT\n";
+        let calendar = Calendar::parse(ics).unwrap();
+        assert_eq!(calendar.events[0].start, 1719817200); // 07:00 UTC
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT1H30M").unwrap(), 5400);
+        assert_eq!(parse_duration("P1W").unwrap(), 7 * SECS_PER_DAY);
+        assert_eq!(parse_duration("-P1DT2S").unwrap(), -(SECS_PER_DAY + 2));
+        assert!(parse_duration("P1H").is_err());
+    }
+}
@@ -2,8 +2,12 @@
 //!
 //! Provides string formatting and templating functions.
 
+#[cfg(feature = "qr")]
+pub mod qrcode;
+
 use fusabi_host::ExecutionContext;
 use fusabi_host::Value;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Sprintf-style string formatting.
 pub fn sprintf(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
@@ -23,59 +27,2417 @@ pub fn template(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<
         fusabi_host::Error::host_function("format.template: missing template string")
     })?;
 
-    let values = args
-        .get(1)
-        .and_then(|v| v.as_map())
-        .ok_or_else(|| fusabi_host::Error::host_function("format.template: missing values map"))?;
+    let values = args
+        .get(1)
+        .and_then(|v| v.as_map())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.template: missing values map"))?;
+
+    let mut result = template_str.to_string();
+
+    for (key, value) in values {
+        let placeholder = format!("{{{{{}}}}}", key); // {{key}}
+        let replacement = value_to_string(value);
+        result = result.replace(&placeholder, &replacement);
+    }
+
+    Ok(Value::String(result))
+}
+
+/// Encode a value to a JSON string.
+///
+/// Output is deterministic: object keys are sorted by byte order at every
+/// level (maps have no insertion order to preserve), so equal values always
+/// encode to identical text and can be diffed or signed. Bytes are encoded
+/// as Base64 strings and non-finite floats as `null`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Value to encode
+/// * `args[1]` - Options map (optional):
+///   - `key_order`: List of keys to place first, in the given order, in
+///     every object; remaining keys follow sorted
+///   - `pretty`: Indent nested values by two spaces (default false)
+///   - `indent`: Indent width in spaces; implies `pretty`
+///
+/// # Returns
+///
+/// The JSON text
+pub fn json_encode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let value = args
+        .first()
+        .ok_or_else(|| fusabi_host::Error::host_function("format.json_encode: missing value"))?;
+
+    let options = JsonOptions::from_value(args.get(1), "format.json_encode")?;
+    Ok(Value::String(encode_json(value, &options)))
+}
+
+/// Order of object keys in encoded JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// All keys sorted by byte order.
+    #[default]
+    Sorted,
+    /// The listed keys first, in the given order, then the rest sorted.
+    Preferred(Vec<String>),
+}
+
+/// Options for [`encode_json`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonOptions {
+    /// Order of object keys.
+    pub key_order: KeyOrder,
+    /// Indent width for pretty output; compact output if `None`.
+    pub indent: Option<usize>,
+}
+
+impl JsonOptions {
+    fn from_value(options: Option<&Value>, function: &str) -> fusabi_host::Result<Self> {
+        let options = match options {
+            None | Some(Value::Null) => return Ok(Self::default()),
+            Some(Value::Map(options)) => options,
+            Some(_) => {
+                return Err(fusabi_host::Error::host_function(format!(
+                    "{}: options must be a map",
+                    function
+                )))
+            }
+        };
+
+        let key_order = match options.get("key_order") {
+            None | Some(Value::Null) => KeyOrder::Sorted,
+            Some(Value::List(keys)) => KeyOrder::Preferred(
+                keys.iter()
+                    .map(|k| k.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| {
+                        fusabi_host::Error::host_function(format!(
+                            "{}: key_order must be a list of strings",
+                            function
+                        ))
+                    })?,
+            ),
+            Some(_) => {
+                return Err(fusabi_host::Error::host_function(format!(
+                    "{}: key_order must be a list of strings",
+                    function
+                )))
+            }
+        };
+
+        let indent = match options.get("indent") {
+            Some(Value::Int(n)) if (0..=16).contains(n) => Some(*n as usize),
+            Some(_) => {
+                return Err(fusabi_host::Error::host_function(format!(
+                    "{}: indent must be an integer from 0 to 16",
+                    function
+                )))
+            }
+            None => options
+                .get("pretty")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+                .then_some(2),
+        };
+
+        Ok(Self { key_order, indent })
+    }
+}
+
+/// Encode a value as JSON with a guaranteed key order.
+///
+/// Equal values always produce identical output. See [`json_encode`] for
+/// how non-JSON values are represented.
+pub fn encode_json(value: &Value, options: &JsonOptions) -> String {
+    let mut out = String::new();
+    write_json(&mut out, value, options, 0);
+    out
+}
+
+fn write_json(out: &mut String, value: &Value, options: &JsonOptions, depth: usize) {
+    match value {
+        Value::Null | Value::Function(_) => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Int(i) => out.push_str(&i.to_string()),
+        Value::Float(f) if !f.is_finite() => out.push_str("null"),
+        Value::Float(f) if f.fract() == 0.0 && f.abs() < 1e16 => out.push_str(&format!("{:.1}", f)),
+        Value::Float(f) => out.push_str(&f.to_string()),
+        Value::String(s) => write_json_string(out, s),
+        Value::Bytes(b) => write_json_string(out, &base64_string(b, false, true)),
+        Value::Error(e) => {
+            let error =
+                std::collections::HashMap::from([("error".to_string(), Value::String(e.clone()))]);
+            write_json(out, &Value::Map(error), options, depth);
+        }
+        Value::List(items) => {
+            if items.is_empty() {
+                out.push_str("[]");
+                return;
+            }
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_newline(out, options, depth + 1);
+                write_json(out, item, options, depth + 1);
+            }
+            write_newline(out, options, depth);
+            out.push(']');
+        }
+        Value::Map(map) => {
+            if map.is_empty() {
+                out.push_str("{}");
+                return;
+            }
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            if let KeyOrder::Preferred(preferred) = &options.key_order {
+                let rank = |key: &String| {
+                    preferred
+                        .iter()
+                        .position(|p| p == key)
+                        .unwrap_or(usize::MAX)
+                };
+                keys.sort_by_key(|key| rank(key));
+            }
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_newline(out, options, depth + 1);
+                write_json_string(out, key);
+                out.push(':');
+                if options.indent.is_some() {
+                    out.push(' ');
+                }
+                write_json(out, &map[key], options, depth + 1);
+            }
+            write_newline(out, options, depth);
+            out.push('}');
+        }
+    }
+}
+
+fn write_newline(out: &mut String, options: &JsonOptions, depth: usize) {
+    if let Some(indent) = options.indent {
+        out.push('\n');
+        out.extend(std::iter::repeat(' ').take(indent * depth));
+    }
+}
+
+fn write_json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Decode a JSON string to a value.
+#[cfg(feature = "serde-support")]
+pub fn json_decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let json_str = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_decode: missing JSON string")
+    })?;
+
+    Value::from_json_str(json_str)
+        .map_err(|e| fusabi_host::Error::host_function(format!("format.json_decode: {}", e)))
+}
+
+/// Decode a JSON string to a value.
+#[cfg(not(feature = "serde-support"))]
+pub fn json_decode(_args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    Err(fusabi_host::Error::host_function(
+        "json_decode requires serde-support feature",
+    ))
+}
+
+/// Render a list of maps as an aligned text table.
+///
+/// # Arguments
+///
+/// * `args[0]` - Rows (list of maps)
+/// * `args[1]` - Options map (optional):
+///   - `columns`: List of column names to include, in order (default: all keys, sorted)
+///   - `style`: `"ascii"` (default), `"unicode"`, or `"markdown"`
+///   - `max_width`: Maximum column width; longer cells are truncated
+///   - `ellipsis`: Truncation marker (default `"..."`, or `"…"` for unicode)
+///   - `header`: Whether to print the header row (default true; markdown
+///     tables always have one)
+///
+/// In markdown tables, `|` in cells is escaped as `\|` and line breaks
+/// become `<br>`.
+///
+/// # Returns
+///
+/// The rendered table as a string
+pub fn table(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let rows = args
+        .first()
+        .and_then(|v| v.as_list())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.table: missing rows list"))?;
+
+    let empty_options = std::collections::HashMap::new();
+    let options = args
+        .get(1)
+        .and_then(|v| v.as_map())
+        .unwrap_or(&empty_options);
+
+    let style = match options.get("style").and_then(|v| v.as_str()) {
+        None | Some("ascii") => TableStyle::Ascii,
+        Some("unicode") => TableStyle::Unicode,
+        Some("markdown") => TableStyle::Markdown,
+        Some(other) => {
+            return Err(fusabi_host::Error::host_function(format!(
+                "format.table: unknown style '{}'",
+                other
+            )))
+        }
+    };
+
+    let maps = rows
+        .iter()
+        .map(|row| {
+            row.as_map().ok_or_else(|| {
+                fusabi_host::Error::host_function("format.table: each row must be a map")
+            })
+        })
+        .collect::<fusabi_host::Result<Vec<_>>>()?;
+
+    let columns: Vec<String> = match options.get("columns").and_then(|v| v.as_list()) {
+        Some(cols) => cols
+            .iter()
+            .filter_map(|c| c.as_str().map(String::from))
+            .collect(),
+        None => maps
+            .iter()
+            .flat_map(|m| m.keys().cloned())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+    };
+
+    let max_width = options
+        .get("max_width")
+        .and_then(|v| v.as_int())
+        .filter(|w| *w > 0)
+        .map(|w| w as usize);
+
+    let ellipsis = options
+        .get("ellipsis")
+        .and_then(|v| v.as_str())
+        .unwrap_or(match style {
+            TableStyle::Unicode => "…",
+            _ => "...",
+        });
+
+    let show_header = options
+        .get("header")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    if !show_header && style == TableStyle::Markdown {
+        return Err(fusabi_host::Error::host_function(
+            "format.table: markdown tables need a header row",
+        ));
+    }
+
+    let fit = |text: String| {
+        let text = match max_width {
+            Some(width) => truncate_text(&text, width, ellipsis),
+            None => text,
+        };
+        match style {
+            TableStyle::Markdown => markdown_cell(&text),
+            _ => text,
+        }
+    };
+
+    let header: Vec<String> = columns.iter().map(|c| fit(c.clone())).collect();
+    let body: Vec<Vec<(String, bool)>> = maps
+        .iter()
+        .map(|m| {
+            columns
+                .iter()
+                .map(|c| match m.get(c) {
+                    Some(v) => {
+                        let numeric = matches!(v, Value::Int(_) | Value::Float(_));
+                        (fit(value_to_string(v)), numeric)
+                    }
+                    None => (String::new(), false),
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut widths: Vec<usize> = if show_header {
+        header.iter().map(|h| display_width(h)).collect()
+    } else {
+        vec![0; columns.len()]
+    };
+    for row in &body {
+        for (i, (cell, _)) in row.iter().enumerate() {
+            widths[i] = widths[i].max(display_width(cell));
+        }
+    }
+    if style == TableStyle::Markdown {
+        // Markdown separators need at least three dashes
+        for w in &mut widths {
+            *w = (*w).max(3);
+        }
+    }
+
+    let mut out = String::new();
+    let header_cells: Vec<(String, bool)> = header.into_iter().map(|h| (h, false)).collect();
+
+    match style {
+        TableStyle::Markdown => {
+            out.push_str(&table_row(&header_cells, &widths, "|", "|", "|"));
+            let sep: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+            out.push_str(&format!("| {} |\n", sep.join(" | ")));
+            for row in &body {
+                out.push_str(&table_row(row, &widths, "|", "|", "|"));
+            }
+        }
+        TableStyle::Ascii | TableStyle::Unicode => {
+            let (h, v, top, mid, bottom) = if style == TableStyle::Unicode {
+                ("─", "│", ["┌", "┬", "┐"], ["├", "┼", "┤"], ["└", "┴", "┘"])
+            } else {
+                ("-", "|", ["+", "+", "+"], ["+", "+", "+"], ["+", "+", "+"])
+            };
+            let rule = |parts: [&str; 3]| {
+                let segments: Vec<String> = widths.iter().map(|w| h.repeat(w + 2)).collect();
+                format!("{}{}{}\n", parts[0], segments.join(parts[1]), parts[2])
+            };
+
+            out.push_str(&rule(top));
+            if show_header {
+                out.push_str(&table_row(&header_cells, &widths, v, v, v));
+                out.push_str(&rule(mid));
+            }
+            for row in &body {
+                out.push_str(&table_row(row, &widths, v, v, v));
+            }
+            out.push_str(&rule(bottom));
+        }
+    }
+
+    Ok(Value::String(out))
+}
+
+/// Escape text for a markdown table cell.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TableStyle {
+    Ascii,
+    Unicode,
+    Markdown,
+}
+
+fn table_row(
+    cells: &[(String, bool)],
+    widths: &[usize],
+    left: &str,
+    sep: &str,
+    right: &str,
+) -> String {
+    let padded: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|((cell, numeric), width)| {
+            // Pad by display width: `format!` padding counts chars, which
+            // misaligns columns holding wide (CJK, emoji) characters.
+            let padding = " ".repeat(width.saturating_sub(display_width(cell)));
+            if *numeric {
+                format!("{}{}", padding, cell)
+            } else {
+                format!("{}{}", cell, padding)
+            }
+        })
+        .collect();
+    format!(
+        "{} {} {}\n",
+        left,
+        padded.join(&format!(" {} ", sep)),
+        right
+    )
+}
+
+/// Number of terminal columns `text` occupies.
+///
+/// Wide characters such as CJK ideographs and most emoji take two columns,
+/// combining marks and zero-width characters none. An emoji sequence
+/// (ZWJ sequences, skin tones, flags) counts as the single glyph it draws,
+/// and ANSI escape sequences such as colors take no columns.
+pub fn display_width(text: &str) -> usize {
+    Segments { rest: text }
+        .map(|segment| match segment {
+            Segment::Escape(_) => 0,
+            Segment::Cluster(cluster) => UnicodeWidthStr::width(cluster),
+        })
+        .sum()
+}
+
+/// Cut `text` to at most `width` columns, ending it with `ellipsis` when
+/// anything was cut. When `width` leaves no room for the ellipsis the text
+/// is cut without one.
+///
+/// Text is only cut between glyphs, never inside an emoji sequence or an
+/// ANSI escape sequence. If the kept part contains escape sequences, a
+/// reset (`ESC [0m`) is appended so styling does not run past the text.
+pub fn truncate_text(text: &str, width: usize, ellipsis: &str) -> String {
+    if display_width(text) <= width {
+        return text.to_string();
+    }
+    let marker_width = display_width(ellipsis);
+    let (head, marker) = if width <= marker_width {
+        (take_columns(text, width).0, "")
+    } else {
+        (take_columns(text, width - marker_width).0, ellipsis)
+    };
+    let mut out = format!("{}{}", head, marker);
+    if head.contains('\x1b') {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+/// Remove ANSI escape sequences from `text`.
+pub fn strip_ansi(text: &str) -> String {
+    Segments { rest: text }
+        .filter_map(|segment| match segment {
+            Segment::Escape(_) => None,
+            Segment::Cluster(cluster) => Some(cluster),
+        })
+        .collect()
+}
+
+/// Split `text` after the longest prefix that fits in `width` columns.
+/// Zero-width characters stay with the character before them, and escape
+/// sequences with the text after them.
+fn take_columns(text: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    let mut offset = 0;
+    let mut boundary = 0;
+    for segment in (Segments { rest: text }) {
+        match segment {
+            Segment::Escape(escape) => offset += escape.len(),
+            Segment::Cluster(cluster) => {
+                used += UnicodeWidthStr::width(cluster);
+                if used > width {
+                    return text.split_at(boundary);
+                }
+                offset += cluster.len();
+                boundary = offset;
+            }
+        }
+    }
+    (text, "")
+}
+
+/// A piece of text as drawn by a terminal.
+enum Segment<'a> {
+    /// An ANSI escape sequence.
+    Escape(&'a str),
+    /// A character with the combining marks, joiners, variation selectors
+    /// and emoji modifiers drawn together with it.
+    Cluster(&'a str),
+}
+
+struct Segments<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        let len = match escape_len(self.rest) {
+            Some(len) => len,
+            None => cluster_len(self.rest)?,
+        };
+        let (segment, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        Some(if segment.starts_with('\x1b') {
+            Segment::Escape(segment)
+        } else {
+            Segment::Cluster(segment)
+        })
+    }
+}
+
+/// Length of the ANSI escape sequence at the start of `text`, if any.
+///
+/// Covers CSI sequences (`ESC [`, used for colors and cursor movement),
+/// OSC sequences (`ESC ]` up to `BEL` or `ESC \`, used for hyperlinks and
+/// titles) and two-byte escapes. An unterminated sequence runs to the end
+/// of the text.
+fn escape_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    if bytes.first() != Some(&0x1b) {
+        return None;
+    }
+    let len = match bytes.get(1) {
+        Some(b'[') => {
+            let mut i = 2;
+            loop {
+                match bytes.get(i) {
+                    // Final byte.
+                    Some(0x40..=0x7e) => break i + 1,
+                    // Parameter and intermediate bytes.
+                    Some(0x20..=0x3f) => i += 1,
+                    // Malformed: end before the offending byte.
+                    _ => break i,
+                }
+            }
+        }
+        Some(b']') => {
+            let mut i = 2;
+            loop {
+                match bytes.get(i) {
+                    None => break i,
+                    Some(0x07) => break i + 1,
+                    Some(0x1b) if bytes.get(i + 1) == Some(&b'\\') => break i + 2,
+                    Some(_) => i += 1,
+                }
+            }
+        }
+        Some(b) if b.is_ascii() => 2,
+        _ => 1,
+    };
+    Some(len)
+}
+
+/// Length of the glyph cluster at the start of `text`.
+fn cluster_len(text: &str) -> Option<usize> {
+    fn is_regional_indicator(c: char) -> bool {
+        matches!(c, '\u{1F1E6}'..='\u{1F1FF}')
+    }
+
+    let mut chars = text.char_indices();
+    let (_, first) = chars.next()?;
+    let mut prev = first;
+    let mut end = first.len_utf8();
+    // Regional indicators pair up into flags.
+    let mut unpaired_flag = is_regional_indicator(first);
+    for (i, c) in chars {
+        let joins = c != '\x1b'
+            && (prev == '\u{200D}'
+                || c == '\u{200D}'
+                || matches!(c, '\u{FE00}'..='\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}')
+                || c.width() == Some(0)
+                || (unpaired_flag && is_regional_indicator(c)));
+        if !joins {
+            break;
+        }
+        unpaired_flag = false;
+        prev = c;
+        end = i + c.len_utf8();
+    }
+    Some(end)
+}
+
+/// Word-wrap `text` into lines of at most `width` columns.
+///
+/// Lines break at whitespace, which is collapsed to single spaces; words
+/// wider than `width` are split. Existing line breaks are kept, so blank
+/// lines survive as empty strings.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in paragraph.split_whitespace() {
+            let mut word = word;
+            let mut word_width = display_width(word);
+            if line_width > 0 && line_width + 1 + word_width <= width {
+                line.push(' ');
+                line.push_str(word);
+                line_width += 1 + word_width;
+                continue;
+            }
+            if line_width > 0 {
+                lines.push(std::mem::take(&mut line));
+            }
+            while word_width > width {
+                let (head, rest) = take_columns(word, width);
+                // A character wider than the line still has to go somewhere.
+                let (head, rest) = if head.is_empty() {
+                    let split = word.chars().next().map_or(word.len(), char::len_utf8);
+                    word.split_at(split)
+                } else {
+                    (head, rest)
+                };
+                if rest.is_empty() {
+                    break;
+                }
+                lines.push(head.to_string());
+                word = rest;
+                word_width = display_width(word);
+            }
+            line.push_str(word);
+            line_width = word_width;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn width_arg(args: &[Value], index: usize, function: &str) -> fusabi_host::Result<usize> {
+    args.get(index)
+        .and_then(|v| v.as_int())
+        .and_then(|n| usize::try_from(n).ok())
+        .ok_or_else(|| {
+            fusabi_host::Error::host_function(format!(
+                "{}: width must be a non-negative integer",
+                function
+            ))
+        })
+}
+
+/// Word-wrap text to a column width.
+///
+/// Widths are measured in terminal columns, so CJK and emoji count double.
+///
+/// # Arguments
+///
+/// * `args[0]` - Text
+/// * `args[1]` - Maximum line width in columns (at least 1)
+///
+/// # Returns
+///
+/// List of lines; existing line breaks are kept and overlong words split
+pub fn wrap(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.wrap: missing text"))?;
+    let width = width_arg(args, 1, "format.wrap")?;
+    if width == 0 {
+        return Err(fusabi_host::Error::host_function(
+            "format.wrap: width must be at least 1",
+        ));
+    }
+
+    Ok(Value::List(
+        wrap_text(text, width)
+            .into_iter()
+            .map(Value::String)
+            .collect(),
+    ))
+}
+
+/// Truncate text to a column width.
+///
+/// Emoji sequences and ANSI escape sequences are never split, and a reset
+/// is appended to cut text that contains escape sequences.
+///
+/// # Arguments
+///
+/// * `args[0]` - Text
+/// * `args[1]` - Maximum width in columns
+/// * `args[2]` - Marker appended when text is cut (optional, default `"…"`)
+///
+/// # Returns
+///
+/// The text, cut to fit with the marker counted in the width
+pub fn truncate(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.truncate: missing text"))?;
+    let width = width_arg(args, 1, "format.truncate")?;
+    let ellipsis = match args.get(2) {
+        None | Some(Value::Null) => "…",
+        Some(v) => v.as_str().ok_or_else(|| {
+            fusabi_host::Error::host_function("format.truncate: ellipsis must be a string")
+        })?,
+    };
+
+    Ok(Value::String(truncate_text(text, width, ellipsis)))
+}
+
+/// Measure the terminal columns text occupies.
+///
+/// # Arguments
+///
+/// * `args[0]` - Text
+///
+/// # Returns
+///
+/// Width in columns: wide characters and emoji sequences count 2,
+/// combining marks and ANSI escape sequences 0
+pub fn display_width_fn(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.display_width: missing text"))?;
+
+    Ok(Value::Int(display_width(text) as i64))
+}
+
+/// Remove ANSI escape sequences (colors, cursor movement, hyperlinks).
+///
+/// # Arguments
+///
+/// * `args[0]` - Text
+///
+/// # Returns
+///
+/// The text without escape sequences
+pub fn strip_ansi_fn(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.strip_ansi: missing text"))?;
+
+    Ok(Value::String(strip_ansi(text)))
+}
+
+/// Parse CSV text.
+///
+/// Quoted fields may contain delimiters, doubled quotes, and line breaks.
+/// Both `\n` and `\r\n` line endings are accepted.
+///
+/// # Arguments
+///
+/// * `args[0]` - CSV text
+/// * `args[1]` - Options map (optional):
+///   - `delimiter`: Field delimiter (default `","`)
+///   - `quote`: Quote character (default `"\""`)
+///   - `headers`: Treat the first record as a header row (default true)
+///
+/// # Returns
+///
+/// A list of maps keyed by header, or a list of lists when `headers` is false.
+/// Short records are padded with null; records longer than the header are an error.
+pub fn csv_decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.csv_decode: missing text"))?;
+
+    let options = CsvOptions::from_value(args.get(1), "format.csv_decode")?;
+    let records = parse_csv(text, options.delimiter, options.quote)?;
+
+    if !options.headers {
+        return Ok(Value::List(
+            records
+                .into_iter()
+                .map(|r| Value::List(r.into_iter().map(Value::String).collect()))
+                .collect(),
+        ));
+    }
+
+    let mut records = records.into_iter();
+    let header = match records.next() {
+        Some(header) => header,
+        None => return Ok(Value::List(Vec::new())),
+    };
+
+    let mut rows = Vec::new();
+    for (index, record) in records.enumerate() {
+        if record.len() > header.len() {
+            return Err(fusabi_host::Error::host_function(format!(
+                "format.csv_decode: record {} has {} fields, expected {}",
+                index + 2,
+                record.len(),
+                header.len()
+            )));
+        }
+        let mut fields = record.into_iter();
+        let row = header
+            .iter()
+            .map(|name| {
+                let value = fields.next().map(Value::String).unwrap_or(Value::Null);
+                (name.clone(), value)
+            })
+            .collect();
+        rows.push(Value::Map(row));
+    }
+
+    Ok(Value::List(rows))
+}
+
+/// Serialize rows as CSV.
+///
+/// # Arguments
+///
+/// * `args[0]` - Rows (list of maps, or list of lists)
+/// * `args[1]` - Options map (optional):
+///   - `delimiter`: Field delimiter (default `","`)
+///   - `quote`: Quote character (default `"\""`)
+///   - `headers`: Write a header row for map rows (default true)
+///   - `columns`: List of column names, in order (default: all keys, sorted)
+///   - `quote_all`: Quote every field rather than only those that need it (default false)
+///
+/// # Returns
+///
+/// The CSV text, with `\n` line endings and a trailing newline
+pub fn csv_encode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let rows = args
+        .first()
+        .and_then(|v| v.as_list())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.csv_encode: missing rows list"))?;
+
+    let options = CsvOptions::from_value(args.get(1), "format.csv_encode")?;
+    let quote_all = args
+        .get(1)
+        .and_then(|v| v.as_map())
+        .and_then(|m| m.get("quote_all"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let write_record = |out: &mut String, fields: &[String]| {
+        let encoded: Vec<String> = fields
+            .iter()
+            .map(|f| csv_field(f, options.delimiter, options.quote, quote_all))
+            .collect();
+        out.push_str(&encoded.join(&options.delimiter.to_string()));
+        out.push('\n');
+    };
+
+    let mut out = String::new();
+
+    if rows.iter().all(|r| matches!(r, Value::List(_))) {
+        for row in rows {
+            let fields: Vec<String> = row.as_list().unwrap_or(&[]).iter().map(csv_cell).collect();
+            write_record(&mut out, &fields);
+        }
+        return Ok(Value::String(out));
+    }
+
+    let maps = rows
+        .iter()
+        .map(|row| {
+            row.as_map().ok_or_else(|| {
+                fusabi_host::Error::host_function(
+                    "format.csv_encode: rows must be all maps or all lists",
+                )
+            })
+        })
+        .collect::<fusabi_host::Result<Vec<_>>>()?;
+
+    let columns: Vec<String> = match args
+        .get(1)
+        .and_then(|v| v.as_map())
+        .and_then(|m| m.get("columns"))
+        .and_then(|v| v.as_list())
+    {
+        Some(cols) => cols
+            .iter()
+            .filter_map(|c| c.as_str().map(String::from))
+            .collect(),
+        None => maps
+            .iter()
+            .flat_map(|m| m.keys().cloned())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+    };
+
+    if options.headers {
+        write_record(&mut out, &columns);
+    }
+    for map in maps {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|c| map.get(c).map(csv_cell).unwrap_or_default())
+            .collect();
+        write_record(&mut out, &fields);
+    }
+
+    Ok(Value::String(out))
+}
+
+struct CsvOptions {
+    delimiter: char,
+    quote: char,
+    headers: bool,
+}
+
+impl CsvOptions {
+    fn from_value(value: Option<&Value>, function: &str) -> fusabi_host::Result<Self> {
+        let options = value.and_then(|v| v.as_map());
+        let single_char = |key: &str, default: char| -> fusabi_host::Result<char> {
+            match options.and_then(|m| m.get(key)).and_then(|v| v.as_str()) {
+                None => Ok(default),
+                Some(s) => {
+                    let mut chars = s.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) if c != '\n' && c != '\r' => Ok(c),
+                        _ => Err(fusabi_host::Error::host_function(format!(
+                            "{}: {} must be a single character",
+                            function, key
+                        ))),
+                    }
+                }
+            }
+        };
+
+        let delimiter = single_char("delimiter", ',')?;
+        let quote = single_char("quote", '"')?;
+        if delimiter == quote {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: delimiter and quote must differ",
+                function
+            )));
+        }
+
+        let headers = options
+            .and_then(|m| m.get("headers"))
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        Ok(Self {
+            delimiter,
+            quote,
+            headers,
+        })
+    }
+}
+
+fn parse_csv(text: &str, delimiter: char, quote: char) -> fusabi_host::Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut field_started = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == quote {
+                if chars.peek() == Some(&quote) {
+                    field.push(quote);
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == quote && field.is_empty() {
+            in_quotes = true;
+            field_started = true;
+        } else if c == delimiter {
+            record.push(std::mem::take(&mut field));
+            field_started = false;
+        } else if c == '\n' || c == '\r' {
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            if field_started || !field.is_empty() || !record.is_empty() {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            field_started = false;
+        } else {
+            field.push(c);
+            field_started = true;
+        }
+    }
+
+    if in_quotes {
+        return Err(fusabi_host::Error::host_function(
+            "format.csv_decode: unterminated quoted field",
+        ));
+    }
+    if field_started || !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        other => value_to_string(other),
+    }
+}
+
+fn csv_field(text: &str, delimiter: char, quote: char, quote_all: bool) -> String {
+    let needs_quotes = quote_all || text.contains([delimiter, quote, '\n', '\r']);
+    if !needs_quotes {
+        return text.to_string();
+    }
+    let escaped = text.replace(quote, &format!("{}{}", quote, quote));
+    format!("{}{}{}", quote, escaped, quote)
+}
+
+/// Encode data as Base64.
+///
+/// # Arguments
+///
+/// * `args[0]` - Data (string or bytes)
+/// * `args[1]` - Options map (optional):
+///   - `url_safe`: Use the URL-safe alphabet (`-` and `_`) (default false)
+///   - `pad`: Append `=` padding (default true)
+///
+/// # Returns
+///
+/// The encoded string
+pub fn base64_encode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let data = encoding_input(args, "format.base64_encode")?;
+    let url_safe = option_bool(args.get(1), "url_safe", false);
+    let pad = option_bool(args.get(1), "pad", true);
+
+    Ok(Value::String(base64_string(data, url_safe, pad)))
+}
+
+fn base64_string(data: &[u8], url_safe: bool, pad: bool) -> String {
+    let alphabet = if url_safe {
+        BASE64_URL_ALPHABET
+    } else {
+        BASE64_ALPHABET
+    };
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..=chunk.len() {
+            out.push(alphabet[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+        if pad {
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode Base64 text.
+///
+/// Both the standard and URL-safe alphabets are accepted, padding is
+/// optional, and ASCII whitespace is ignored.
+///
+/// # Arguments
+///
+/// * `args[0]` - Encoded string
+///
+/// # Returns
+///
+/// The decoded data as a string if it is valid UTF-8, otherwise as bytes
+pub fn base64_decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.base64_decode: missing text"))?;
+
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    let mut padding = false;
+
+    for c in text.bytes() {
+        if c.is_ascii_whitespace() {
+            continue;
+        }
+        if c == b'=' {
+            padding = true;
+            continue;
+        }
+        if padding {
+            return Err(fusabi_host::Error::host_function(
+                "format.base64_decode: data after padding",
+            ));
+        }
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => {
+                return Err(fusabi_host::Error::host_function(format!(
+                    "format.base64_decode: invalid character '{}'",
+                    c as char
+                )))
+            }
+        };
+        buffer = (buffer << 6) | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+
+    if bits >= 6 {
+        return Err(fusabi_host::Error::host_function(
+            "format.base64_decode: truncated input",
+        ));
+    }
+
+    Ok(decoded_value(out))
+}
+
+/// Encode data as hexadecimal.
+///
+/// # Arguments
+///
+/// * `args[0]` - Data (string or bytes)
+/// * `args[1]` - Options map (optional):
+///   - `upper`: Use uppercase digits (default false)
+///
+/// # Returns
+///
+/// The encoded string
+pub fn hex_encode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let data = encoding_input(args, "format.hex_encode")?;
+    let digits: &[u8; 16] = if option_bool(args.get(1), "upper", false) {
+        b"0123456789ABCDEF"
+    } else {
+        b"0123456789abcdef"
+    };
+
+    let mut out = String::with_capacity(data.len() * 2);
+    for byte in data {
+        out.push(digits[(byte >> 4) as usize] as char);
+        out.push(digits[(byte & 0x0f) as usize] as char);
+    }
+
+    Ok(Value::String(out))
+}
+
+/// Decode hexadecimal text (case-insensitive).
+///
+/// # Arguments
+///
+/// * `args[0]` - Encoded string
+///
+/// # Returns
+///
+/// The decoded data as a string if it is valid UTF-8, otherwise as bytes
+pub fn hex_decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.hex_decode: missing text"))?;
+
+    if text.len() % 2 != 0 {
+        return Err(fusabi_host::Error::host_function(
+            "format.hex_decode: odd number of digits",
+        ));
+    }
+
+    let out = text
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match (hex_digit(pair[0]), hex_digit(pair[1])) {
+            (Some(hi), Some(lo)) => Ok((hi << 4) | lo),
+            _ => Err(fusabi_host::Error::host_function(format!(
+                "format.hex_decode: invalid digits '{}'",
+                String::from_utf8_lossy(pair)
+            ))),
+        })
+        .collect::<fusabi_host::Result<Vec<u8>>>()?;
+
+    Ok(decoded_value(out))
+}
+
+/// Percent-encode data for use in a URL.
+///
+/// Every byte outside the RFC 3986 unreserved set is escaped.
+///
+/// # Arguments
+///
+/// * `args[0]` - Data (string or bytes)
+/// * `args[1]` - Options map (optional):
+///   - `form`: Encode spaces as `+` (`application/x-www-form-urlencoded`) (default false)
+///
+/// # Returns
+///
+/// The encoded string
+pub fn url_encode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let data = encoding_input(args, "format.url_encode")?;
+    let form = option_bool(args.get(1), "form", false);
+
+    let mut out = String::with_capacity(data.len());
+    for &byte in data {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            b' ' if form => out.push('+'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    Ok(Value::String(out))
+}
+
+/// Decode percent-encoded URL text.
+///
+/// # Arguments
+///
+/// * `args[0]` - Encoded string
+/// * `args[1]` - Options map (optional):
+///   - `form`: Decode `+` as a space (default false)
+///
+/// # Returns
+///
+/// The decoded data as a string if it is valid UTF-8, otherwise as bytes
+pub fn url_decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.url_decode: missing text"))?;
+    let form = option_bool(args.get(1), "form", false);
+
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let escaped = bytes
+                    .get(i + 1..i + 3)
+                    .and_then(|pair| Some((hex_digit(pair[0])? << 4) | hex_digit(pair[1])?))
+                    .ok_or_else(|| {
+                        fusabi_host::Error::host_function(format!(
+                            "format.url_decode: invalid escape at offset {}",
+                            i
+                        ))
+                    })?;
+                out.push(escaped);
+                i += 3;
+            }
+            b'+' if form => {
+                out.push(b' ');
+                i += 1;
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    Ok(decoded_value(out))
+}
+
+/// Escape text for inclusion in HTML content or attribute values.
+///
+/// # Arguments
+///
+/// * `args[0]` - Text (string or UTF-8 bytes)
+///
+/// # Returns
+///
+/// The text with `&`, `<`, `>`, `"` and `'` replaced by entities
+pub fn html_escape(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let data = encoding_input(args, "format.html_escape")?;
+    let text = std::str::from_utf8(data).map_err(|_| {
+        fusabi_host::Error::host_function("format.html_escape: input is not valid UTF-8")
+    })?;
+
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+
+    Ok(Value::String(out))
+}
+
+/// Encode text as a QR code for display in the terminal or as an image.
+///
+/// # Arguments
+///
+/// * `args[0]` - Text or bytes to encode
+/// * `args[1]` - Optional options map:
+///   - `ecc`: error correction level, `"L"`, `"M"` (default), `"Q"` or `"H"`
+///   - `border`: quiet zone width in modules (default 4)
+///   - `invert`: draw dark modules as blocks, for light terminal backgrounds
+///     (default false)
+///   - `output`: `"text"` (default) or `"png"`
+///   - `scale`: PNG pixels per module, 1 - 32 (default 8)
+///
+/// # Returns
+///
+/// A string of Unicode half blocks, or PNG bytes when `output` is `"png"`
+#[cfg(feature = "qr")]
+pub fn qr(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "format.qr";
+
+    let data = encoding_input(args, FUNCTION)?;
+    let options = args.get(1);
+    let option = |key: &str| options.and_then(|v| v.as_map()).and_then(|m| m.get(key));
+
+    let ecc = match option("ecc") {
+        None | Some(Value::Null) => qrcode::EccLevel::default(),
+        Some(v) => v
+            .as_str()
+            .and_then(qrcode::EccLevel::parse)
+            .ok_or_else(|| {
+                fusabi_host::Error::host_function(format!(
+                    "{}: ecc must be one of \"L\", \"M\", \"Q\" or \"H\"",
+                    FUNCTION
+                ))
+            })?,
+    };
+    let border = match option("border").and_then(|v| v.as_int()) {
+        None => 4,
+        Some(n @ 0..=16) => n as usize,
+        Some(_) => {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: border must be between 0 and 16",
+                FUNCTION
+            )))
+        }
+    };
+    let invert = option_bool(options, "invert", false);
+
+    let code = qrcode::QrCode::encode_bytes(data, ecc)
+        .map_err(|e| fusabi_host::Error::host_function(format!("{}: {}", FUNCTION, e)))?;
+
+    match option("output").and_then(|v| v.as_str()).unwrap_or("text") {
+        "text" => Ok(Value::String(code.to_unicode(border, invert))),
+        "png" => {
+            let scale = match option("scale").and_then(|v| v.as_int()) {
+                None => 8,
+                Some(n @ 1..=32) => n as usize,
+                Some(_) => {
+                    return Err(fusabi_host::Error::host_function(format!(
+                        "{}: scale must be between 1 and 32",
+                        FUNCTION
+                    )))
+                }
+            };
+            Ok(Value::Bytes(code.to_png(scale, border)))
+        }
+        other => Err(fusabi_host::Error::host_function(format!(
+            "{}: unknown output {:?}, expected \"text\" or \"png\"",
+            FUNCTION, other
+        ))),
+    }
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn encoding_input<'a>(args: &'a [Value], function: &str) -> fusabi_host::Result<&'a [u8]> {
+    match args.first() {
+        Some(Value::String(s)) => Ok(s.as_bytes()),
+        Some(Value::Bytes(b)) => Ok(b),
+        _ => Err(fusabi_host::Error::host_function(format!(
+            "{}: expected string or bytes",
+            function
+        ))),
+    }
+}
+
+fn option_bool(options: Option<&Value>, key: &str, default: bool) -> bool {
+    options
+        .and_then(|v| v.as_map())
+        .and_then(|m| m.get(key))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(default)
+}
+
+fn decoded_value(bytes: Vec<u8>) -> Value {
+    match String::from_utf8(bytes) {
+        Ok(s) => Value::String(s),
+        Err(e) => Value::Bytes(e.into_bytes()),
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Resolve an RFC 6901 JSON Pointer against a value.
+///
+/// # Arguments
+///
+/// * `args[0]` - Value to query (typically from `json_decode`)
+/// * `args[1]` - Pointer, e.g. `"/items/0/name"` (`""` refers to the whole value)
+///
+/// # Returns
+///
+/// The referenced value, or null if it does not exist
+pub fn json_pointer(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let value = args
+        .first()
+        .ok_or_else(|| fusabi_host::Error::host_function("format.json_pointer: missing value"))?;
+
+    let pointer = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.json_pointer: missing pointer"))?;
+
+    if pointer.is_empty() {
+        return Ok(value.clone());
+    }
+    let rest = pointer.strip_prefix('/').ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_pointer: pointer must start with '/'")
+    })?;
+
+    let mut current = value;
+    for token in rest.split('/') {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        let next = match current {
+            Value::Map(map) => map.get(&token),
+            Value::List(list) => token
+                .parse::<usize>()
+                .ok()
+                .filter(|_| token == "0" || !token.starts_with('0'))
+                .and_then(|i| list.get(i)),
+            _ => None,
+        };
+        match next {
+            Some(v) => current = v,
+            None => return Ok(Value::Null),
+        }
+    }
+
+    Ok(current.clone())
+}
+
+/// Query a value with a JSONPath expression.
+///
+/// Supported syntax:
+///
+/// - `$` root, `.name` and `['name']` children, `[n]` indices (negative from the end)
+/// - `*` / `[*]` wildcards and `..` recursive descent
+/// - `[?(@.field)]` existence filters and `[?(@.field op literal)]` comparisons
+///   with `==`, `!=`, `<`, `<=`, `>`, `>=` against strings, numbers, booleans and null
+///
+/// # Arguments
+///
+/// * `args[0]` - Value to query (typically from `json_decode`)
+/// * `args[1]` - JSONPath expression, e.g. `"$.items[?(@.status=='Running')].name"`
+///
+/// # Returns
+///
+/// List of all matching values
+pub fn json_query(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let value = args
+        .first()
+        .ok_or_else(|| fusabi_host::Error::host_function("format.json_query: missing value"))?;
+
+    let path = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.json_query: missing path"))?;
+
+    let segments = parse_json_path(path)
+        .map_err(|e| fusabi_host::Error::host_function(format!("format.json_query: {}", e)))?;
+
+    let mut current = vec![value];
+    for segment in &segments {
+        let mut next = Vec::new();
+        for node in current {
+            match segment {
+                PathSegment::Descend(selector) => {
+                    let mut stack = vec![node];
+                    while let Some(n) = stack.pop() {
+                        select(n, selector, &mut next);
+                        let mut children = children_of(n);
+                        children.reverse();
+                        stack.extend(children);
+                    }
+                }
+                PathSegment::Select(selector) => select(node, selector, &mut next),
+            }
+        }
+        current = next;
+    }
+
+    Ok(Value::List(current.into_iter().cloned().collect()))
+}
+
+enum PathSegment {
+    Select(Selector),
+    Descend(Selector),
+}
+
+enum Selector {
+    Name(String),
+    Index(i64),
+    Wildcard,
+    Filter(Vec<String>, Option<(CompareOp, Value)>),
+}
+
+#[derive(Clone, Copy)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+fn children_of(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::List(list) => list.iter().collect(),
+        Value::Map(map) => {
+            // Sort keys so results are deterministic
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            keys.into_iter().map(|k| &map[k]).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn select<'a>(value: &'a Value, selector: &Selector, out: &mut Vec<&'a Value>) {
+    match selector {
+        Selector::Name(name) => {
+            if let Some(v) = value.as_map().and_then(|m| m.get(name)) {
+                out.push(v);
+            }
+        }
+        Selector::Index(index) => {
+            if let Some(list) = value.as_list() {
+                let resolved = if *index < 0 {
+                    list.len() as i64 + index
+                } else {
+                    *index
+                };
+                if let Some(v) = usize::try_from(resolved).ok().and_then(|i| list.get(i)) {
+                    out.push(v);
+                }
+            }
+        }
+        Selector::Wildcard => out.extend(children_of(value)),
+        Selector::Filter(path, comparison) => {
+            for child in children_of(value) {
+                let target = path
+                    .iter()
+                    .try_fold(child, |v, key| v.as_map().and_then(|m| m.get(key)));
+                let keep = match (target, comparison) {
+                    (Some(_), None) => true,
+                    (Some(target), Some((op, literal))) => compare_values(target, *op, literal),
+                    (None, _) => false,
+                };
+                if keep {
+                    out.push(child);
+                }
+            }
+        }
+    }
+}
+
+fn compare_values(left: &Value, op: CompareOp, right: &Value) -> bool {
+    use std::cmp::Ordering;
+
+    let ordering = match (left, right) {
+        (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+        (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => left
+            .as_float()
+            .unwrap()
+            .partial_cmp(&right.as_float().unwrap()),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        _ => None,
+    };
+
+    match (op, ordering) {
+        (CompareOp::Eq, Some(o)) => o == Ordering::Equal,
+        (CompareOp::Ne, Some(o)) => o != Ordering::Equal,
+        (CompareOp::Ne, None) => true,
+        (CompareOp::Lt, Some(o)) => o == Ordering::Less,
+        (CompareOp::Le, Some(o)) => o != Ordering::Greater,
+        (CompareOp::Gt, Some(o)) => o == Ordering::Greater,
+        (CompareOp::Ge, Some(o)) => o != Ordering::Less,
+        _ => false,
+    }
+}
+
+fn parse_json_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    let chars: Vec<char> = path.trim().chars().collect();
+    let mut pos = 0;
+    if chars.first() == Some(&'$') {
+        pos = 1;
+    }
+
+    let mut segments = Vec::new();
+    while pos < chars.len() {
+        match chars[pos] {
+            '.' if chars.get(pos + 1) == Some(&'.') => {
+                pos += 2;
+                let selector = if chars.get(pos) == Some(&'[') {
+                    parse_bracket(&chars, &mut pos)?
+                } else {
+                    parse_dot_name(&chars, &mut pos)?
+                };
+                segments.push(PathSegment::Descend(selector));
+            }
+            '.' => {
+                pos += 1;
+                segments.push(PathSegment::Select(parse_dot_name(&chars, &mut pos)?));
+            }
+            '[' => segments.push(PathSegment::Select(parse_bracket(&chars, &mut pos)?)),
+            c => return Err(format!("unexpected '{}' at offset {}", c, pos)),
+        }
+    }
+
+    Ok(segments)
+}
+
+fn parse_dot_name(chars: &[char], pos: &mut usize) -> Result<Selector, String> {
+    let start = *pos;
+    while *pos < chars.len() && chars[*pos] != '.' && chars[*pos] != '[' {
+        *pos += 1;
+    }
+    let name: String = chars[start..*pos].iter().collect();
+    match name.as_str() {
+        "" => Err(format!("expected name at offset {}", start)),
+        "*" => Ok(Selector::Wildcard),
+        _ => Ok(Selector::Name(name)),
+    }
+}
+
+fn parse_bracket(chars: &[char], pos: &mut usize) -> Result<Selector, String> {
+    let start = *pos;
+    *pos += 1;
+    skip_spaces(chars, pos);
+
+    let selector = match chars.get(*pos) {
+        Some('\'') | Some('"') => Selector::Name(parse_quoted(chars, pos)?),
+        Some('*') => {
+            *pos += 1;
+            Selector::Wildcard
+        }
+        Some('?') => {
+            *pos += 1;
+            parse_filter(chars, pos)?
+        }
+        _ => {
+            let begin = *pos;
+            while *pos < chars.len() && (chars[*pos] == '-' || chars[*pos].is_ascii_digit()) {
+                *pos += 1;
+            }
+            let text: String = chars[begin..*pos].iter().collect();
+            Selector::Index(
+                text.parse()
+                    .map_err(|_| format!("invalid index at offset {}", begin))?,
+            )
+        }
+    };
+
+    skip_spaces(chars, pos);
+    if chars.get(*pos) != Some(&']') {
+        return Err(format!("unclosed '[' at offset {}", start));
+    }
+    *pos += 1;
+    Ok(selector)
+}
+
+fn parse_filter(chars: &[char], pos: &mut usize) -> Result<Selector, String> {
+    let start = *pos;
+    if chars.get(*pos) != Some(&'(') {
+        return Err(format!("expected '(' at offset {}", start));
+    }
+    *pos += 1;
+    skip_spaces(chars, pos);
+    if chars.get(*pos) != Some(&'@') {
+        return Err(format!("filter must start with '@' at offset {}", *pos));
+    }
+    *pos += 1;
+
+    let mut path = Vec::new();
+    loop {
+        match chars.get(*pos) {
+            Some('.') => {
+                *pos += 1;
+                let begin = *pos;
+                while *pos < chars.len()
+                    && (chars[*pos].is_alphanumeric() || chars[*pos] == '_' || chars[*pos] == '-')
+                {
+                    *pos += 1;
+                }
+                if begin == *pos {
+                    return Err(format!("expected name at offset {}", begin));
+                }
+                path.push(chars[begin..*pos].iter().collect());
+            }
+            Some('[') => {
+                *pos += 1;
+                path.push(parse_quoted(chars, pos)?);
+                if chars.get(*pos) != Some(&']') {
+                    return Err(format!("expected ']' at offset {}", *pos));
+                }
+                *pos += 1;
+            }
+            _ => break,
+        }
+    }
+
+    skip_spaces(chars, pos);
+    let op = match (chars.get(*pos), chars.get(*pos + 1)) {
+        (Some('='), Some('=')) => Some((CompareOp::Eq, 2)),
+        (Some('!'), Some('=')) => Some((CompareOp::Ne, 2)),
+        (Some('<'), Some('=')) => Some((CompareOp::Le, 2)),
+        (Some('>'), Some('=')) => Some((CompareOp::Ge, 2)),
+        (Some('<'), _) => Some((CompareOp::Lt, 1)),
+        (Some('>'), _) => Some((CompareOp::Gt, 1)),
+        _ => None,
+    };
+
+    let comparison = match op {
+        Some((op, len)) => {
+            *pos += len;
+            skip_spaces(chars, pos);
+            Some((op, parse_literal(chars, pos)?))
+        }
+        None => None,
+    };
+
+    skip_spaces(chars, pos);
+    if chars.get(*pos) != Some(&')') {
+        return Err(format!("unclosed filter at offset {}", start));
+    }
+    *pos += 1;
+    Ok(Selector::Filter(path, comparison))
+}
+
+fn parse_literal(chars: &[char], pos: &mut usize) -> Result<Value, String> {
+    if matches!(chars.get(*pos), Some('\'') | Some('"')) {
+        return parse_quoted(chars, pos).map(Value::String);
+    }
+
+    let begin = *pos;
+    while *pos < chars.len() && !matches!(chars[*pos], ')' | ' ') {
+        *pos += 1;
+    }
+    let text: String = chars[begin..*pos].iter().collect();
+    match text.as_str() {
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        "null" => Ok(Value::Null),
+        _ => text
+            .parse::<i64>()
+            .map(Value::Int)
+            .or_else(|_| text.parse::<f64>().map(Value::Float))
+            .map_err(|_| format!("invalid literal '{}' at offset {}", text, begin)),
+    }
+}
+
+fn parse_quoted(chars: &[char], pos: &mut usize) -> Result<String, String> {
+    let start = *pos;
+    let quote = match chars.get(*pos) {
+        Some(&quote @ ('\'' | '"')) => quote,
+        _ => return Err(format!("expected quoted string at offset {}", start)),
+    };
+    *pos += 1;
+    let mut out = String::new();
+    while let Some(&c) = chars.get(*pos) {
+        *pos += 1;
+        match c {
+            '\\' => {
+                if let Some(&escaped) = chars.get(*pos) {
+                    out.push(escaped);
+                    *pos += 1;
+                }
+            }
+            c if c == quote => return Ok(out),
+            c => out.push(c),
+        }
+    }
+    Err(format!("unterminated string at offset {}", start))
+}
+
+fn skip_spaces(chars: &[char], pos: &mut usize) {
+    while chars.get(*pos) == Some(&' ') {
+        *pos += 1;
+    }
+}
+
+/// Open a JSON Lines (NDJSON) file for streaming reads.
+///
+/// The returned handle shares the `fs_stream` handle table; read records with
+/// `next_record` and release it with `jsonl_close`.
+///
+/// # Arguments
+///
+/// * `args[0]` - File path
+/// * `args[1]` - Read buffer size in bytes (optional, default 65536)
+///
+/// # Returns
+///
+/// Handle (integer) for the stream
+#[cfg(feature = "fs_stream")]
+pub fn jsonl_open(
+    safety: &std::sync::Arc<crate::safety::SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let path = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.jsonl_open: missing path"))?;
+
+    let buffer_size = args
+        .get(1)
+        .and_then(|v| v.as_int())
+        .filter(|n| *n > 0)
+        .unwrap_or(65536) as usize;
+
+    let path = std::path::Path::new(path);
+    safety
+        .paths
+        .check_read(path)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let handle = crate::fs_stream::open_lines(path, buffer_size)
+        .map_err(|e| fusabi_host::Error::host_function(format!("format.jsonl_open: {}", e)))?;
+
+    Ok(Value::Int(handle))
+}
+
+/// Read the next record from a JSON Lines stream.
+///
+/// Blank lines are skipped.
+///
+/// # Arguments
+///
+/// * `args[0]` - Handle from `jsonl_open`
+///
+/// # Returns
+///
+/// The decoded record, or null at end of file
+#[cfg(feature = "fs_stream")]
+pub fn next_record(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.next_record: missing handle"))?;
+
+    while let Some((line_no, line)) = crate::fs_stream::next_line(handle, "format.next_record")? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        return Value::from_json_str(&line).map_err(|e| {
+            fusabi_host::Error::host_function(format!(
+                "format.next_record: line {}: {}",
+                line_no, e
+            ))
+        });
+    }
+
+    Ok(Value::Null)
+}
+
+/// Close a JSON Lines stream.
+///
+/// # Arguments
+///
+/// * `args[0]` - Handle from `jsonl_open`
+#[cfg(feature = "fs_stream")]
+pub fn jsonl_close(args: &[Value], ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    crate::fs_stream::close(args, ctx)
+}
+
+/// Append a value to a JSON Lines file as a single line.
+///
+/// The file is created if it does not exist.
+///
+/// # Arguments
+///
+/// * `args[0]` - File path
+/// * `args[1]` - Value to append
+pub fn jsonl_append(
+    safety: &std::sync::Arc<crate::safety::SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    use std::io::Write;
+
+    let path = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("format.jsonl_append: missing path"))?;
+
+    let value = args
+        .get(1)
+        .ok_or_else(|| fusabi_host::Error::host_function("format.jsonl_append: missing value"))?;
+
+    let path = std::path::Path::new(path);
+    safety
+        .paths
+        .check_write(path)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let mut line = encode_json(value, &JsonOptions::default());
+    line.push('\n');
+
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .map_err(|e| fusabi_host::Error::host_function(format!("format.jsonl_append: {}", e)))?;
+
+    Ok(Value::Null)
+}
+
+/// Incremental decoder for a top-level JSON array.
+///
+/// Yields one element at a time from any [`std::io::BufRead`], holding only
+/// the current element in memory, so multi-gigabyte exports can be processed
+/// without loading the whole document.
+#[derive(Debug, Default)]
+pub struct JsonArrayDecoder {
+    state: ArrayState,
+    index: usize,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum ArrayState {
+    #[default]
+    Start,
+    First,
+    Rest,
+    Done,
+}
+
+impl JsonArrayDecoder {
+    /// Create a decoder positioned before the opening `[`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of elements decoded so far.
+    pub fn count(&self) -> usize {
+        self.index
+    }
+
+    /// Decode the next element, or `None` after the closing `]`.
+    pub fn next<R: std::io::BufRead>(&mut self, reader: &mut R) -> crate::Result<Option<Value>> {
+        loop {
+            match self.state {
+                ArrayState::Done => return Ok(None),
+                ArrayState::Start => {
+                    match next_non_space(reader)? {
+                        Some(b'[') => self.state = ArrayState::First,
+                        Some(b) => {
+                            return Err(crate::Error::format(format!(
+                                "expected '[' at start of JSON array, found '{}'",
+                                b as char
+                            )))
+                        }
+                        None => return Err(crate::Error::format("empty JSON document")),
+                    }
+                    continue;
+                }
+                ArrayState::First => {
+                    if peek_non_space(reader)? == Some(b']') {
+                        reader.consume(1);
+                        self.state = ArrayState::Done;
+                        return Ok(None);
+                    }
+                }
+                ArrayState::Rest => match next_non_space(reader)? {
+                    Some(b',') => {}
+                    Some(b']') => {
+                        self.state = ArrayState::Done;
+                        return Ok(None);
+                    }
+                    Some(b) => {
+                        return Err(crate::Error::format(format!(
+                            "expected ',' or ']' after element {}, found '{}'",
+                            self.index, b as char
+                        )))
+                    }
+                    None => return Err(crate::Error::format("unterminated JSON array")),
+                },
+            }
+
+            if peek_non_space(reader)?.is_none() {
+                return Err(crate::Error::format("unterminated JSON array"));
+            }
+            let bytes = read_json_element(reader)?;
+            let text = String::from_utf8(bytes).map_err(|_| {
+                crate::Error::format(format!("element {} is not valid UTF-8", self.index))
+            })?;
+            let value = Value::from_json_str(&text)
+                .map_err(|e| crate::Error::format(format!("element {}: {}", self.index, e)))?;
+            self.state = ArrayState::Rest;
+            self.index += 1;
+            return Ok(Some(value));
+        }
+    }
+}
+
+/// Peek the next non-whitespace byte without consuming it.
+fn peek_non_space<R: std::io::BufRead>(reader: &mut R) -> crate::Result<Option<u8>> {
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        match buf.iter().position(|b| !b.is_ascii_whitespace()) {
+            Some(i) => {
+                let b = buf[i];
+                reader.consume(i);
+                return Ok(Some(b));
+            }
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+/// Consume and return the next non-whitespace byte.
+fn next_non_space<R: std::io::BufRead>(reader: &mut R) -> crate::Result<Option<u8>> {
+    let b = peek_non_space(reader)?;
+    if b.is_some() {
+        reader.consume(1);
+    }
+    Ok(b)
+}
+
+/// Read the raw bytes of one JSON value, stopping before the delimiter that
+/// follows it.
+fn read_json_element<R: std::io::BufRead>(reader: &mut R) -> crate::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            if depth == 0 && !in_string && !out.is_empty() {
+                return Ok(out);
+            }
+            return Err(crate::Error::format("unterminated JSON array"));
+        }
+
+        let mut used = 0;
+        let mut complete = false;
+        for &b in buf {
+            if in_string {
+                used += 1;
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    in_string = false;
+                    if depth == 0 {
+                        complete = true;
+                        break;
+                    }
+                }
+                continue;
+            }
+            match b {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        used += 1;
+                        complete = true;
+                        break;
+                    }
+                }
+                // End of a scalar (or of the enclosing array).
+                b',' | b']' | b'}' if depth == 0 => {
+                    complete = true;
+                    break;
+                }
+                b if b.is_ascii_whitespace() && depth == 0 => {
+                    complete = true;
+                    break;
+                }
+                _ => {}
+            }
+            used += 1;
+        }
+
+        out.extend_from_slice(&buf[..used]);
+        reader.consume(used);
+        if complete {
+            return Ok(out);
+        }
+    }
+}
+
+/// Incremental encoder for a top-level JSON array.
+///
+/// Writes `[`, the comma-separated elements and `]` as values arrive, so
+/// large exports never need to be held in memory.
+pub struct JsonArrayWriter<W: std::io::Write> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: std::io::Write> JsonArrayWriter<W> {
+    /// Start an array, writing the opening `[`.
+    pub fn new(mut writer: W) -> crate::Result<Self> {
+        writer.write_all(b"[")?;
+        Ok(Self { writer, count: 0 })
+    }
+
+    /// Append an element.
+    pub fn write(&mut self, value: &Value) -> crate::Result<()> {
+        if self.count > 0 {
+            self.writer.write_all(b",")?;
+        }
+        self.writer.write_all(b"\n")?;
+        self.writer
+            .write_all(encode_json(value, &JsonOptions::default()).as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Number of elements written so far.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Close the array and flush, returning the underlying writer.
+    pub fn finish(mut self) -> crate::Result<W> {
+        self.writer
+            .write_all(if self.count > 0 { b"\n]\n" } else { b"]\n" })?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Array decoders for `fs_stream` handles.
+#[cfg(feature = "fs_stream")]
+static JSON_DECODERS: std::sync::OnceLock<
+    parking_lot::Mutex<std::collections::HashMap<i64, JsonArrayDecoder>>,
+> = std::sync::OnceLock::new();
+
+/// Open array writers by handle.
+type JsonWriterTable =
+    std::collections::HashMap<i64, JsonArrayWriter<std::io::BufWriter<std::fs::File>>>;
+
+static JSON_WRITERS: std::sync::OnceLock<parking_lot::Mutex<JsonWriterTable>> =
+    std::sync::OnceLock::new();
+
+static NEXT_WRITER: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(1);
+
+fn json_writers() -> &'static parking_lot::Mutex<JsonWriterTable> {
+    JSON_WRITERS.get_or_init(Default::default)
+}
+
+/// Number of open JSON array writers.
+#[cfg(feature = "observability")]
+pub(crate) fn open_json_writers() -> usize {
+    json_writers().lock().len()
+}
+
+/// Open a file holding a large JSON array for element-by-element reads.
+///
+/// The returned handle shares the `fs_stream` handle table; read elements
+/// with `json_stream_decode` and release it with `json_stream_close`.
+///
+/// # Arguments
+///
+/// * `args[0]` - File path
+/// * `args[1]` - Read buffer size in bytes (optional, default 65536)
+///
+/// # Returns
+///
+/// Handle (integer) for the stream
+#[cfg(feature = "fs_stream")]
+pub fn json_stream_open(
+    safety: &std::sync::Arc<crate::safety::SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let path = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_open: missing path")
+    })?;
+
+    let buffer_size = args
+        .get(1)
+        .and_then(|v| v.as_int())
+        .filter(|n| *n > 0)
+        .unwrap_or(65536) as usize;
+
+    let path = std::path::Path::new(path);
+    safety
+        .paths
+        .check_read(path)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let handle = crate::fs_stream::open_lines(path, buffer_size).map_err(|e| {
+        fusabi_host::Error::host_function(format!("format.json_stream_open: {}", e))
+    })?;
+
+    Ok(Value::Int(handle))
+}
+
+/// Decode the next element of a JSON array from a stream handle.
+///
+/// Works on any `fs_stream` reader handle, including those from
+/// `json_stream_open` and `jsonl_open`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Stream handle
+///
+/// # Returns
+///
+/// The next element, or null after the end of the array
+#[cfg(feature = "fs_stream")]
+pub fn json_stream_decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_decode: missing handle")
+    })?;
+
+    let reader = crate::fs_stream::reader(handle, "format.json_stream_decode")?;
+
+    let mut decoders = JSON_DECODERS.get_or_init(Default::default).lock();
+    let decoder = decoders.entry(handle).or_default();
+    let value = decoder.next(&mut *reader.lock()).map_err(|e| {
+        fusabi_host::Error::host_function(format!("format.json_stream_decode: {}", e))
+    })?;
+
+    Ok(value.unwrap_or(Value::Null))
+}
+
+/// Close a JSON array stream opened with `json_stream_open`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Stream handle
+#[cfg(feature = "fs_stream")]
+pub fn json_stream_close(args: &[Value], ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    if let Some(handle) = args.first().and_then(|v| v.as_int()) {
+        if let Some(decoders) = JSON_DECODERS.get() {
+            decoders.lock().remove(&handle);
+        }
+    }
+    crate::fs_stream::close(args, ctx)
+}
+
+/// Create a file and start writing a JSON array to it.
+///
+/// # Arguments
+///
+/// * `args[0]` - File path (created or truncated)
+///
+/// # Returns
+///
+/// Writer handle (integer)
+pub fn json_stream_writer(
+    safety: &std::sync::Arc<crate::safety::SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let path = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_writer: missing path")
+    })?;
 
-    let mut result = template_str.to_string();
+    let path = std::path::Path::new(path);
+    safety
+        .paths
+        .check_write(path)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
 
-    for (key, value) in values {
-        let placeholder = format!("{{{{{}}}}}", key); // {{key}}
-        let replacement = value_to_string(value);
-        result = result.replace(&placeholder, &replacement);
-    }
+    let writer = std::fs::File::create(path)
+        .map_err(crate::Error::from)
+        .and_then(|file| JsonArrayWriter::new(std::io::BufWriter::new(file)))
+        .map_err(|e| {
+            fusabi_host::Error::host_function(format!("format.json_stream_writer: {}", e))
+        })?;
 
-    Ok(Value::String(result))
+    let handle = NEXT_WRITER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    json_writers().lock().insert(handle, writer);
+    Ok(Value::Int(handle))
 }
 
-/// Encode a value to JSON string.
-pub fn json_encode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
-    let value = args
-        .first()
-        .ok_or_else(|| fusabi_host::Error::host_function("format.json_encode: missing value"))?;
+/// Append an element to a JSON array writer.
+///
+/// # Arguments
+///
+/// * `args[0]` - Writer handle
+/// * `args[1]` - Value to append
+pub fn json_stream_write(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_write: missing handle")
+    })?;
 
-    #[cfg(feature = "serde-support")]
-    {
-        let json = value.to_json_string();
-        Ok(Value::String(json))
-    }
+    let value = args.get(1).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_write: missing value")
+    })?;
 
-    #[cfg(not(feature = "serde-support"))]
-    {
-        // Simple serialization without serde
-        let json = value_to_json_simple(value);
-        Ok(Value::String(json))
-    }
+    let mut writers = json_writers().lock();
+    let writer = writers.get_mut(&handle).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_write: invalid handle")
+    })?;
+
+    writer.write(value).map_err(|e| {
+        fusabi_host::Error::host_function(format!("format.json_stream_write: {}", e))
+    })?;
+    Ok(Value::Null)
 }
 
-/// Decode a JSON string to a value.
-#[cfg(feature = "serde-support")]
-pub fn json_decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
-    let json_str = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
-        fusabi_host::Error::host_function("format.json_decode: missing JSON string")
+/// Close a JSON array writer, writing the closing `]`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Writer handle
+///
+/// # Returns
+///
+/// Number of elements written
+pub fn json_stream_finish(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_finish: missing handle")
     })?;
 
-    Value::from_json_str(json_str)
-        .map_err(|e| fusabi_host::Error::host_function(format!("format.json_decode: {}", e)))
-}
+    let writer = json_writers().lock().remove(&handle).ok_or_else(|| {
+        fusabi_host::Error::host_function("format.json_stream_finish: invalid handle")
+    })?;
 
-/// Decode a JSON string to a value.
-#[cfg(not(feature = "serde-support"))]
-pub fn json_decode(_args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
-    Err(fusabi_host::Error::host_function(
-        "json_decode requires serde-support feature",
-    ))
+    let count = writer.count();
+    writer.finish().map_err(|e| {
+        fusabi_host::Error::host_function(format!("format.json_stream_finish: {}", e))
+    })?;
+    Ok(Value::Int(count as i64))
 }
 
 // Helper functions
@@ -168,29 +2530,6 @@ fn value_to_string(value: &Value) -> String {
     }
 }
 
-#[cfg(not(feature = "serde-support"))]
-fn value_to_json_simple(value: &Value) -> String {
-    match value {
-        Value::Null => "null".to_string(),
-        Value::Bool(b) => b.to_string(),
-        Value::Int(i) => i.to_string(),
-        Value::Float(f) => f.to_string(),
-        Value::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
-        Value::List(l) => {
-            let items: Vec<String> = l.iter().map(value_to_json_simple).collect();
-            format!("[{}]", items.join(","))
-        }
-        Value::Map(m) => {
-            let items: Vec<String> = m
-                .iter()
-                .map(|(k, v)| format!("\"{}\":{}", k, value_to_json_simple(v)))
-                .collect();
-            format!("{{{}}}", items.join(","))
-        }
-        _ => "null".to_string(),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -224,6 +2563,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_sprintf_float() {
         let ctx = create_test_ctx();
 
@@ -259,6 +2599,164 @@ mod tests {
         assert_eq!(result.as_str().unwrap(), "Hello, Bob! You have 3 items.");
     }
 
+    fn table_rows() -> Value {
+        let mut alice = std::collections::HashMap::new();
+        alice.insert("name".to_string(), Value::String("Alice".into()));
+        alice.insert("age".to_string(), Value::Int(30));
+
+        let mut bob = std::collections::HashMap::new();
+        bob.insert("name".to_string(), Value::String("Bob".into()));
+        bob.insert("age".to_string(), Value::Int(4));
+
+        Value::List(vec![Value::Map(alice), Value::Map(bob)])
+    }
+
+    #[test]
+    fn test_table_ascii() {
+        let ctx = create_test_ctx();
+
+        let mut options = std::collections::HashMap::new();
+        options.insert(
+            "columns".to_string(),
+            Value::List(vec![
+                Value::String("name".into()),
+                Value::String("age".into()),
+            ]),
+        );
+
+        let result = table(&[table_rows(), Value::Map(options)], &ctx).unwrap();
+        assert_eq!(
+            result.as_str().unwrap(),
+            "+-------+-----+\n\
+             | name  | age |\n\
+             +-------+-----+\n\
+             | Alice |  30 |\n\
+             | Bob   |   4 |\n\
+             +-------+-----+\n"
+        );
+    }
+
+    #[test]
+    fn test_table_markdown_truncation() {
+        let ctx = create_test_ctx();
+
+        let mut options = std::collections::HashMap::new();
+        options.insert("style".to_string(), Value::String("markdown".into()));
+        options.insert("max_width".to_string(), Value::Int(4));
+        options.insert(
+            "columns".to_string(),
+            Value::List(vec![Value::String("name".into())]),
+        );
+
+        let result = table(&[table_rows(), Value::Map(options)], &ctx).unwrap();
+        assert_eq!(
+            result.as_str().unwrap(),
+            "| name |\n| ---- |\n| A... |\n| Bob  |\n"
+        );
+    }
+
+    #[test]
+    fn test_table_markdown_escaping() {
+        let ctx = create_test_ctx();
+
+        let mut row = std::collections::HashMap::new();
+        row.insert("a|b".to_string(), Value::String("x | y\nz".into()));
+        let rows = Value::List(vec![Value::Map(row)]);
+
+        let mut options = std::collections::HashMap::new();
+        options.insert("style".to_string(), Value::String("markdown".into()));
+        let result = table(&[rows.clone(), Value::Map(options.clone())], &ctx).unwrap();
+        assert_eq!(
+            result.as_str().unwrap(),
+            "| a\\|b        |\n| ----------- |\n| x \\| y<br>z |\n"
+        );
+
+        options.insert("header".to_string(), Value::Bool(false));
+        assert!(table(&[rows, Value::Map(options)], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_table_wide_characters() {
+        let ctx = create_test_ctx();
+
+        let mut wide = std::collections::HashMap::new();
+        wide.insert("name".to_string(), Value::String("東京🚀".into()));
+        let mut narrow = std::collections::HashMap::new();
+        narrow.insert("name".to_string(), Value::String("Oslo".into()));
+        let rows = Value::List(vec![Value::Map(wide), Value::Map(narrow)]);
+
+        let result = table(&[rows], &ctx).unwrap();
+        assert_eq!(
+            result.as_str().unwrap(),
+            "+--------+\n\
+             | name   |\n\
+             +--------+\n\
+             | 東京🚀 |\n\
+             | Oslo   |\n\
+             +--------+\n"
+        );
+    }
+
+    #[test]
+    fn test_text_layout() {
+        let ctx = create_test_ctx();
+
+        assert_eq!(display_width("東京🚀"), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(truncate_text("東京タワー", 7, "…"), "東京タ…");
+        assert_eq!(truncate_text("東京タワー", 6, "…"), "東京…");
+        assert_eq!(truncate_text("short", 10, "…"), "short");
+        assert_eq!(truncate_text("abcdef", 2, "..."), "ab");
+
+        // Emoji sequences are one glyph.
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        assert_eq!(display_width(family), 2);
+        assert_eq!(display_width("\u{1F44D}\u{1F3FD}\u{1F1F3}\u{1F1F4}"), 4);
+        assert_eq!(
+            truncate_text(&format!("{}{}!", family, family), 4, "…"),
+            format!("{}…", family)
+        );
+
+        // Escape sequences take no columns and are never split.
+        let red = "\x1b[31mhello\x1b[0m world";
+        assert_eq!(display_width(red), 11);
+        assert_eq!(strip_ansi(red), "hello world");
+        assert_eq!(truncate_text(red, 4, "…"), "\x1b[31mhel…\x1b[0m");
+        assert_eq!(truncate_text(red, 11, "…"), red);
+        let link = "\x1b]8;;https://example.com\x1b\\docs\x1b]8;;\x1b\\";
+        assert_eq!(display_width(link), 4);
+        assert_eq!(strip_ansi(link), "docs");
+        assert_eq!(
+            wrap_text("\x1b[1mbold\x1b[0m text", 4),
+            vec!["\x1b[1mbold\x1b[0m", "text"]
+        );
+
+        assert_eq!(
+            wrap_text("the quick  brown fox\n\njumps", 10),
+            vec!["the quick", "brown fox", "", "jumps"]
+        );
+        assert_eq!(wrap_text("東京タワー", 4), vec!["東京", "タワ", "ー"]);
+        assert_eq!(wrap_text("🚀", 1), vec!["🚀"]);
+
+        let lines = wrap(&[Value::String("a bb ccc".into()), Value::Int(4)], &ctx).unwrap();
+        assert_eq!(
+            lines,
+            Value::List(vec![
+                Value::String("a bb".into()),
+                Value::String("ccc".into())
+            ])
+        );
+        assert!(wrap(&[Value::String("a".into()), Value::Int(0)], &ctx).is_err());
+        assert_eq!(
+            truncate(&[Value::String("東京タワー".into()), Value::Int(5)], &ctx).unwrap(),
+            Value::String("東京…".into())
+        );
+        assert_eq!(
+            display_width_fn(&[Value::String("日本".into())], &ctx).unwrap(),
+            Value::Int(4)
+        );
+    }
+
     #[test]
     fn test_json_encode() {
         let ctx = create_test_ctx();
@@ -269,4 +2767,382 @@ mod tests {
         let result = json_encode(&[Value::String("hello".into())], &ctx).unwrap();
         assert!(result.as_str().unwrap().contains("hello"));
     }
+
+    #[test]
+    fn test_json_encode_key_order() {
+        let ctx = create_test_ctx();
+
+        let mut inner = std::collections::HashMap::new();
+        inner.insert("z".to_string(), Value::Float(1.0));
+        inner.insert("a".to_string(), Value::Bytes(b"hi".to_vec()));
+        let mut map = std::collections::HashMap::new();
+        for key in ["kind", "id", "body", "meta"] {
+            map.insert(key.to_string(), Value::Null);
+        }
+        map.insert("body".to_string(), Value::Map(inner));
+        map.insert("id".to_string(), Value::String("a\"b\n".into()));
+        let value = Value::Map(map);
+
+        let sorted = json_encode(std::slice::from_ref(&value), &ctx).unwrap();
+        assert_eq!(
+            sorted.as_str().unwrap(),
+            r#"{"body":{"a":"aGk=","z":1.0},"id":"a\"b\n","kind":null,"meta":null}"#
+        );
+
+        let mut options = std::collections::HashMap::new();
+        options.insert(
+            "key_order".to_string(),
+            Value::List(vec![
+                Value::String("kind".into()),
+                Value::String("z".into()),
+            ]),
+        );
+        options.insert("indent".to_string(), Value::Int(1));
+        let ordered = json_encode(&[value.clone(), Value::Map(options)], &ctx).unwrap();
+        assert_eq!(
+            ordered.as_str().unwrap(),
+            "{\n \"kind\": null,\n \"body\": {\n  \"z\": 1.0,\n  \"a\": \"aGk=\"\n },\n \"id\": \"a\\\"b\\n\",\n \"meta\": null\n}"
+        );
+
+        // Stable across independently built maps.
+        let rebuilt = Value::from_json_str(sorted.as_str().unwrap()).unwrap();
+        assert_eq!(
+            encode_json(&rebuilt, &JsonOptions::default()),
+            sorted.as_str().unwrap()
+        );
+
+        let bad = Value::Map(std::collections::HashMap::from([(
+            "key_order".to_string(),
+            Value::Int(1),
+        )]));
+        assert!(json_encode(&[value, bad], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_csv_decode_quoted_fields() {
+        let ctx = create_test_ctx();
+
+        let text = "name,note\r\nAlice,\"Hello, world\"\nBob,\"say \"\"hi\"\"\nthere\"\nCarol\n";
+        let result = csv_decode(&[Value::String(text.into())], &ctx).unwrap();
+        let rows = result.as_list().unwrap();
+        assert_eq!(rows.len(), 3);
+
+        let alice = rows[0].as_map().unwrap();
+        assert_eq!(alice.get("note").unwrap().as_str(), Some("Hello, world"));
+        let bob = rows[1].as_map().unwrap();
+        assert_eq!(bob.get("note").unwrap().as_str(), Some("say \"hi\"\nthere"));
+        let carol = rows[2].as_map().unwrap();
+        assert_eq!(carol.get("note"), Some(&Value::Null));
+
+        let err = csv_decode(&[Value::String("a\n1,2\n".into())], &ctx);
+        assert!(err.is_err());
+        let err = csv_decode(&[Value::String("a\n\"open\n".into())], &ctx);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_csv_decode_without_headers() {
+        let ctx = create_test_ctx();
+
+        let mut options = std::collections::HashMap::new();
+        options.insert("delimiter".to_string(), Value::String(";".into()));
+        options.insert("headers".to_string(), Value::Bool(false));
+
+        let result = csv_decode(
+            &[Value::String("1;2\n;x\n".into()), Value::Map(options)],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            result,
+            Value::List(vec![
+                Value::List(vec![Value::String("1".into()), Value::String("2".into())]),
+                Value::List(vec![Value::String("".into()), Value::String("x".into())]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_csv_encode_roundtrip() {
+        let ctx = create_test_ctx();
+
+        let mut row = std::collections::HashMap::new();
+        row.insert("name".to_string(), Value::String("Smith, J".into()));
+        row.insert("age".to_string(), Value::Int(42));
+        row.insert("quote".to_string(), Value::String("a \"b\"".into()));
+
+        let encoded = csv_encode(&[Value::List(vec![Value::Map(row.clone())])], &ctx).unwrap();
+        assert_eq!(
+            encoded.as_str().unwrap(),
+            "age,name,quote\n42,\"Smith, J\",\"a \"\"b\"\"\"\n"
+        );
+
+        let decoded = csv_decode(&[encoded], &ctx).unwrap();
+        let decoded = decoded.as_list().unwrap()[0].as_map().unwrap().clone();
+        assert_eq!(decoded.get("name"), row.get("name"));
+        assert_eq!(decoded.get("quote"), row.get("quote"));
+        assert_eq!(decoded.get("age").unwrap().as_str(), Some("42"));
+    }
+
+    #[test]
+    fn test_base64_roundtrip() {
+        let ctx = create_test_ctx();
+
+        let encoded = base64_encode(&[Value::String("hello?".into())], &ctx).unwrap();
+        assert_eq!(encoded, Value::String("aGVsbG8/".into()));
+        let encoded = base64_encode(&[Value::String("hi".into())], &ctx).unwrap();
+        assert_eq!(encoded, Value::String("aGk=".into()));
+
+        let mut options = std::collections::HashMap::new();
+        options.insert("url_safe".to_string(), Value::Bool(true));
+        options.insert("pad".to_string(), Value::Bool(false));
+        let encoded =
+            base64_encode(&[Value::Bytes(vec![0xfb, 0xff]), Value::Map(options)], &ctx).unwrap();
+        assert_eq!(encoded, Value::String("-_8".into()));
+
+        // Non-UTF-8 output comes back as bytes
+        let decoded = base64_decode(&[encoded], &ctx).unwrap();
+        assert_eq!(decoded, Value::Bytes(vec![0xfb, 0xff]));
+        let decoded = base64_decode(&[Value::String("aGk=".into())], &ctx).unwrap();
+        assert_eq!(decoded, Value::String("hi".into()));
+
+        assert!(base64_decode(&[Value::String("a$".into())], &ctx).is_err());
+        assert!(base64_decode(&[Value::String("a".into())], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_hex_and_url_encoding() {
+        let ctx = create_test_ctx();
+
+        let encoded = hex_encode(&[Value::Bytes(vec![0x00, 0xab, 0x10])], &ctx).unwrap();
+        assert_eq!(encoded, Value::String("00ab10".into()));
+        let decoded = hex_decode(&[Value::String("4869".into())], &ctx).unwrap();
+        assert_eq!(decoded, Value::String("Hi".into()));
+        assert!(hex_decode(&[Value::String("abc".into())], &ctx).is_err());
+        assert!(hex_decode(&[Value::String("zz".into())], &ctx).is_err());
+
+        let encoded = url_encode(&[Value::String("a b&c=d/é".into())], &ctx).unwrap();
+        assert_eq!(encoded, Value::String("a%20b%26c%3Dd%2F%C3%A9".into()));
+        let decoded = url_decode(&[encoded], &ctx).unwrap();
+        assert_eq!(decoded, Value::String("a b&c=d/é".into()));
+
+        let mut form = std::collections::HashMap::new();
+        form.insert("form".to_string(), Value::Bool(true));
+        let decoded = url_decode(&[Value::String("a+b".into()), Value::Map(form)], &ctx).unwrap();
+        assert_eq!(decoded, Value::String("a b".into()));
+        assert!(url_decode(&[Value::String("%zz".into())], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_html_escape() {
+        let ctx = create_test_ctx();
+
+        let escaped = html_escape(
+            &[Value::String("<a href=\"x\">Tom & Jerry's</a>".into())],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            escaped,
+            Value::String("&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;".into())
+        );
+    }
+
+    fn sample_pods() -> Value {
+        let pod = |name: &str, status: &str, restarts: i64| {
+            let mut map = std::collections::HashMap::new();
+            map.insert("name".to_string(), Value::String(name.into()));
+            map.insert("status".to_string(), Value::String(status.into()));
+            map.insert("restarts".to_string(), Value::Int(restarts));
+            Value::Map(map)
+        };
+        let mut root = std::collections::HashMap::new();
+        root.insert(
+            "items".to_string(),
+            Value::List(vec![
+                pod("api", "Running", 0),
+                pod("worker", "Pending", 3),
+                pod("db", "Running", 5),
+            ]),
+        );
+        Value::Map(root)
+    }
+
+    #[test]
+    fn test_json_pointer() {
+        let ctx = create_test_ctx();
+        let root = sample_pods();
+
+        let name =
+            json_pointer(&[root.clone(), Value::String("/items/1/name".into())], &ctx).unwrap();
+        assert_eq!(name, Value::String("worker".into()));
+
+        let missing =
+            json_pointer(&[root.clone(), Value::String("/items/9".into())], &ctx).unwrap();
+        assert_eq!(missing, Value::Null);
+
+        let whole = json_pointer(&[root.clone(), Value::String("".into())], &ctx).unwrap();
+        assert_eq!(whole, root);
+
+        assert!(json_pointer(&[root, Value::String("items".into())], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_json_query() {
+        let ctx = create_test_ctx();
+        let root = sample_pods();
+
+        let query =
+            |path: &str| json_query(&[root.clone(), Value::String(path.into())], &ctx).unwrap();
+        let names = |values: Value| -> Vec<String> {
+            values
+                .as_list()
+                .unwrap()
+                .iter()
+                .map(|v| v.as_str().unwrap().to_string())
+                .collect()
+        };
+
+        assert_eq!(
+            names(query("$.items[?(@.status=='Running')].name")),
+            vec!["api", "db"]
+        );
+        assert_eq!(
+            names(query("$.items[?(@.restarts > 1)].name")),
+            vec!["worker", "db"]
+        );
+        assert_eq!(names(query("$.items[-1].name")), vec!["db"]);
+        assert_eq!(
+            names(query("$['items'][*]['name']")),
+            vec!["api", "worker", "db"]
+        );
+        assert_eq!(names(query("$..name")), vec!["api", "worker", "db"]);
+        assert_eq!(query("$.missing").as_list().unwrap().len(), 0);
+
+        assert!(json_query(&[root, Value::String("$.items[?(@.x==".into())], &ctx).is_err());
+
+        // Truncated or unquoted bracket names in filters are errors, not panics
+        let empty = Value::List(vec![]);
+        for expr in ["$[?(@[", "$[?(@[0])]"] {
+            let result = json_query(&[empty.clone(), Value::String(expr.into())], &ctx);
+            assert!(result.is_err(), "{}", expr);
+        }
+    }
+
+    #[cfg(feature = "fs_stream")]
+    #[test]
+    fn test_jsonl_roundtrip() {
+        use crate::safety::{PathAllowlist, SafetyConfig};
+        use std::sync::Arc;
+
+        let ctx = create_test_ctx();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let path_value = Value::String(path.to_string_lossy().into_owned());
+
+        let safety =
+            Arc::new(SafetyConfig::new().with_paths(PathAllowlist::none().allow_rw(dir.path())));
+
+        let mut event = std::collections::HashMap::new();
+        event.insert("id".to_string(), Value::Int(1));
+        jsonl_append(&safety, &[path_value.clone(), Value::Map(event)], &ctx).unwrap();
+        jsonl_append(
+            &safety,
+            &[path_value.clone(), Value::String("two".into())],
+            &ctx,
+        )
+        .unwrap();
+
+        let handle = jsonl_open(&safety, std::slice::from_ref(&path_value), &ctx).unwrap();
+        let first = next_record(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert_eq!(first.as_map().unwrap().get("id"), Some(&Value::Int(1)));
+        let second = next_record(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert_eq!(second, Value::String("two".into()));
+        assert_eq!(
+            next_record(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Null
+        );
+        jsonl_close(&[handle], &ctx).unwrap();
+
+        let denied = Arc::new(SafetyConfig::strict());
+        assert!(jsonl_open(&denied, std::slice::from_ref(&path_value), &ctx).is_err());
+        assert!(jsonl_append(&denied, &[path_value, Value::Null], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_json_array_decoder() {
+        let text = r#" [ {"id": 1, "tags": ["a", "]"]}, "x,y", 3.5, true, null, [] ] "#;
+        // A tiny buffer forces elements to span buffer refills.
+        let mut reader = std::io::BufReader::with_capacity(4, text.as_bytes());
+        let mut decoder = JsonArrayDecoder::new();
+
+        let first = decoder.next(&mut reader).unwrap().unwrap();
+        assert_eq!(first.as_map().unwrap().get("id"), Some(&Value::Int(1)));
+        assert_eq!(
+            decoder.next(&mut reader).unwrap(),
+            Some(Value::String("x,y".into()))
+        );
+        assert_eq!(decoder.next(&mut reader).unwrap(), Some(Value::Float(3.5)));
+        assert_eq!(decoder.next(&mut reader).unwrap(), Some(Value::Bool(true)));
+        assert_eq!(decoder.next(&mut reader).unwrap(), Some(Value::Null));
+        assert_eq!(
+            decoder.next(&mut reader).unwrap(),
+            Some(Value::List(vec![]))
+        );
+        assert_eq!(decoder.next(&mut reader).unwrap(), None);
+        assert_eq!(decoder.count(), 6);
+
+        let mut empty = JsonArrayDecoder::new();
+        assert_eq!(empty.next(&mut "[ ]".as_bytes()).unwrap(), None);
+
+        assert!(JsonArrayDecoder::new()
+            .next(&mut r#"{"a": 1}"#.as_bytes())
+            .is_err());
+        let mut truncated = JsonArrayDecoder::new();
+        let mut reader = r#"[1, {"a": 2"#.as_bytes();
+        assert_eq!(truncated.next(&mut reader).unwrap(), Some(Value::Int(1)));
+        assert!(truncated.next(&mut reader).is_err());
+    }
+
+    #[cfg(feature = "fs_stream")]
+    #[test]
+    fn test_json_stream_roundtrip() {
+        use crate::safety::{PathAllowlist, SafetyConfig};
+        use std::sync::Arc;
+
+        let ctx = create_test_ctx();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.json");
+        let path_value = Value::String(path.to_string_lossy().into_owned());
+
+        let safety =
+            Arc::new(SafetyConfig::new().with_paths(PathAllowlist::none().allow_rw(dir.path())));
+
+        let writer = json_stream_writer(&safety, std::slice::from_ref(&path_value), &ctx).unwrap();
+        for i in 0..3 {
+            let mut row = std::collections::HashMap::new();
+            row.insert("id".to_string(), Value::Int(i));
+            json_stream_write(&[writer.clone(), Value::Map(row)], &ctx).unwrap();
+        }
+        assert_eq!(
+            json_stream_finish(std::slice::from_ref(&writer), &ctx).unwrap(),
+            Value::Int(3)
+        );
+        assert!(json_stream_write(&[writer, Value::Null], &ctx).is_err());
+
+        let handle = json_stream_open(&safety, std::slice::from_ref(&path_value), &ctx).unwrap();
+        for i in 0..3 {
+            let row = json_stream_decode(std::slice::from_ref(&handle), &ctx).unwrap();
+            assert_eq!(row.as_map().unwrap().get("id"), Some(&Value::Int(i)));
+        }
+        assert_eq!(
+            json_stream_decode(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Null
+        );
+        json_stream_close(&[handle], &ctx).unwrap();
+
+        let denied = Arc::new(SafetyConfig::strict());
+        assert!(json_stream_open(&denied, std::slice::from_ref(&path_value), &ctx).is_err());
+        assert!(json_stream_writer(&denied, &[path_value], &ctx).is_err());
+    }
 }
@@ -0,0 +1,761 @@
+//! QR code encoding and rendering.
+//!
+//! Encodes data in byte mode (ISO/IEC 18004) at the smallest version that
+//! fits, picks the mask with the lowest penalty, and renders the symbol
+//! either as Unicode half blocks for the terminal or as a 1-bit PNG.
+
+use crate::error::{Error, Result};
+
+/// Error correction level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EccLevel {
+    /// Recovers about 7% of the symbol.
+    Low,
+    /// Recovers about 15% of the symbol.
+    #[default]
+    Medium,
+    /// Recovers about 25% of the symbol.
+    Quartile,
+    /// Recovers about 30% of the symbol.
+    High,
+}
+
+impl EccLevel {
+    /// Parse a level from `"L"`, `"M"`, `"Q"` or `"H"` (case-insensitive).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_uppercase().as_str() {
+            "L" | "LOW" => Some(Self::Low),
+            "M" | "MEDIUM" => Some(Self::Medium),
+            "Q" | "QUARTILE" => Some(Self::Quartile),
+            "H" | "HIGH" => Some(Self::High),
+            _ => None,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Self::Low => 0,
+            Self::Medium => 1,
+            Self::Quartile => 2,
+            Self::High => 3,
+        }
+    }
+
+    fn format_bits(self) -> u32 {
+        match self {
+            Self::Low => 1,
+            Self::Medium => 0,
+            Self::Quartile => 3,
+            Self::High => 2,
+        }
+    }
+}
+
+/// Smallest symbol version.
+pub const MIN_VERSION: u8 = 1;
+/// Largest symbol version.
+pub const MAX_VERSION: u8 = 40;
+
+#[rustfmt::skip]
+const ECC_CODEWORDS_PER_BLOCK: [[u8; 41]; 4] = [
+    [0, 7, 10, 15, 20, 26, 18, 20, 24, 30, 18, 20, 24, 26, 30, 22, 24, 28, 30, 28, 28, 28, 28, 30, 30, 26, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26, 30, 22, 22, 24, 24, 28, 28, 26, 26, 26, 26, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28, 28],
+    [0, 13, 22, 18, 26, 18, 24, 18, 22, 20, 24, 28, 26, 24, 20, 30, 24, 28, 28, 26, 30, 28, 30, 30, 30, 30, 28, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+    [0, 17, 28, 22, 16, 22, 28, 26, 26, 24, 28, 24, 28, 22, 24, 24, 30, 28, 28, 26, 28, 30, 24, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30, 30],
+];
+
+#[rustfmt::skip]
+const NUM_ERROR_CORRECTION_BLOCKS: [[u8; 41]; 4] = [
+    [0, 1, 1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 4, 6, 6, 6, 6, 7, 8, 8, 9, 9, 10, 12, 12, 12, 13, 14, 15, 16, 17, 18, 19, 19, 20, 21, 22, 24, 25],
+    [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5, 5, 8, 9, 9, 10, 10, 11, 13, 14, 16, 17, 17, 18, 20, 21, 23, 25, 26, 28, 29, 31, 33, 35, 37, 38, 40, 43, 45, 47, 49],
+    [0, 1, 1, 2, 2, 4, 4, 6, 6, 8, 8, 8, 10, 12, 16, 12, 17, 16, 18, 21, 20, 23, 23, 25, 27, 29, 34, 34, 35, 38, 40, 43, 45, 48, 51, 53, 56, 59, 62, 65, 68],
+    [0, 1, 1, 2, 4, 4, 4, 5, 6, 8, 8, 11, 11, 16, 16, 18, 16, 19, 21, 25, 25, 25, 34, 30, 32, 35, 37, 40, 42, 45, 48, 51, 54, 57, 60, 63, 66, 70, 74, 77, 81],
+];
+
+/// An encoded QR code symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrCode {
+    version: u8,
+    ecc: EccLevel,
+    mask: u8,
+    size: usize,
+    modules: Vec<bool>,
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode text at the given error correction level.
+    pub fn encode_text(text: &str, ecc: EccLevel) -> Result<Self> {
+        Self::encode_bytes(text.as_bytes(), ecc)
+    }
+
+    /// Encode binary data at the given error correction level.
+    ///
+    /// Uses the smallest version that holds the data and fails if the
+    /// data does not fit in a version 40 symbol.
+    pub fn encode_bytes(data: &[u8], ecc: EccLevel) -> Result<Self> {
+        let version = (MIN_VERSION..=MAX_VERSION)
+            .find(|&v| data_bits_needed(data.len(), v) <= num_data_codewords(v, ecc) * 8)
+            .ok_or_else(|| {
+                Error::invalid_argument(format!(
+                    "{} bytes is too long for a QR code at this error correction level",
+                    data.len()
+                ))
+            })?;
+
+        let capacity = num_data_codewords(version, ecc);
+        let mut bits = BitBuffer::default();
+        bits.append(0b0100, 4);
+        bits.append(data.len() as u32, char_count_bits(version));
+        for &b in data {
+            bits.append(u32::from(b), 8);
+        }
+        let terminator = (capacity * 8 - bits.len()).min(4);
+        bits.append(0, terminator);
+        bits.append(0, (8 - bits.len() % 8) % 8);
+
+        let mut codewords = bits.into_bytes();
+        for pad in [0xEC, 0x11].into_iter().cycle() {
+            if codewords.len() >= capacity {
+                break;
+            }
+            codewords.push(pad);
+        }
+
+        let mut qr = Self::blank(version, ecc);
+        qr.draw_function_patterns();
+        let all = add_ecc_and_interleave(&codewords, version, ecc);
+        qr.draw_codewords(&all);
+
+        let mut mask = 0;
+        let mut lowest = u32::MAX;
+        for candidate in 0..8 {
+            qr.apply_mask(candidate);
+            qr.draw_format_bits(candidate);
+            let penalty = qr.penalty_score();
+            if penalty < lowest {
+                lowest = penalty;
+                mask = candidate;
+            }
+            qr.apply_mask(candidate);
+        }
+        qr.apply_mask(mask);
+        qr.draw_format_bits(mask);
+        qr.mask = mask;
+        Ok(qr)
+    }
+
+    /// Symbol version (1 - 40).
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Error correction level.
+    pub fn ecc(&self) -> EccLevel {
+        self.ecc
+    }
+
+    /// Mask pattern applied (0 - 7).
+    pub fn mask(&self) -> u8 {
+        self.mask
+    }
+
+    /// Width and height in modules, excluding the quiet zone.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at column `x`, row `y` is dark. Coordinates
+    /// outside the symbol are light.
+    pub fn get(&self, x: i64, y: i64) -> bool {
+        let size = self.size as i64;
+        (0..size).contains(&x) && (0..size).contains(&y) && self.modules[(y * size + x) as usize]
+    }
+
+    /// Render with Unicode half blocks, two module rows per line.
+    ///
+    /// `border` is the quiet zone width in modules. By default light
+    /// modules are drawn as blocks, which suits dark terminal backgrounds;
+    /// set `invert` to draw dark modules as blocks instead.
+    pub fn to_unicode(&self, border: usize, invert: bool) -> String {
+        let border = border as i64;
+        let start = -border;
+        let end = self.size as i64 + border;
+        let filled = |x: i64, y: i64| y < end && (self.get(x, y) == invert);
+
+        let mut out = String::new();
+        let mut y = start;
+        while y < end {
+            for x in start..end {
+                let ch = match (filled(x, y), filled(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                };
+                out.push(ch);
+            }
+            out.push('\n');
+            y += 2;
+        }
+        out
+    }
+
+    /// Render as a grayscale 1-bit PNG with `scale` pixels per module and
+    /// a `border` module quiet zone.
+    pub fn to_png(&self, scale: usize, border: usize) -> Vec<u8> {
+        let scale = scale.max(1);
+        let modules = self.size + border * 2;
+        let width = modules * scale;
+        let row_bytes = width.div_ceil(8);
+
+        let mut raw = Vec::with_capacity((row_bytes + 1) * width);
+        for py in 0..width {
+            raw.push(0);
+            let y = (py / scale) as i64 - border as i64;
+            let mut row = vec![0u8; row_bytes];
+            for px in 0..width {
+                let x = (px / scale) as i64 - border as i64;
+                if !self.get(x, y) {
+                    row[px / 8] |= 0x80 >> (px % 8);
+                }
+            }
+            raw.extend_from_slice(&row);
+        }
+
+        let mut ihdr = Vec::with_capacity(13);
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+        ihdr.extend_from_slice(&[1, 0, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_chunk(&mut png, b"IHDR", &ihdr);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    fn blank(version: u8, ecc: EccLevel) -> Self {
+        let size = version as usize * 4 + 17;
+        Self {
+            version,
+            ecc,
+            mask: 0,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        }
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        let i = y * self.size + x;
+        self.modules[i] = dark;
+        self.function[i] = true;
+    }
+
+    fn draw_function_patterns(&mut self) {
+        let size = self.size;
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size as i64 - 4, 3);
+        self.draw_finder(3, size as i64 - 4);
+
+        let positions = alignment_positions(self.version);
+        let last = positions.len().saturating_sub(1);
+        for (i, &x) in positions.iter().enumerate() {
+            for (j, &y) in positions.iter().enumerate() {
+                let corner = (i == 0 && (j == 0 || j == last)) || (i == last && j == 0);
+                if !corner {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+
+        // Reserve the format areas before data placement; the real bits
+        // are written once the mask is chosen.
+        self.draw_format_bits(0);
+        self.draw_version_bits();
+    }
+
+    fn draw_finder(&mut self, cx: i64, cy: i64) {
+        let size = self.size as i64;
+        for dy in -4..=4i64 {
+            for dx in -4..=4i64 {
+                let (x, y) = (cx + dx, cy + dy);
+                if (0..size).contains(&x) && (0..size).contains(&y) {
+                    let dist = dx.abs().max(dy.abs());
+                    self.set_function(x as usize, y as usize, dist != 2 && dist != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, cx: usize, cy: usize) {
+        for dy in -2..=2i64 {
+            for dx in -2..=2i64 {
+                let x = (cx as i64 + dx) as usize;
+                let y = (cy as i64 + dy) as usize;
+                self.set_function(x, y, dx.abs().max(dy.abs()) != 1);
+            }
+        }
+    }
+
+    fn draw_format_bits(&mut self, mask: u8) {
+        let bits = format_info(self.ecc, mask);
+        let bit = |i: u32| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        for i in 0..6 {
+            self.set_function(8, i, bit(i as u32));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i as u32));
+        }
+
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i as u32));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i as u32));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    fn draw_version_bits(&mut self) {
+        if self.version < 7 {
+            return;
+        }
+        let version = u32::from(self.version);
+        let mut rem = version;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+        }
+        let bits = (version << 12) | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let a = self.size - 11 + i % 3;
+            let b = i / 3;
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    fn draw_codewords(&mut self, data: &[u8]) {
+        let size = self.size as i64;
+        let total_bits = data.len() * 8;
+        let mut i = 0;
+        let mut right = size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            for vert in 0..size {
+                for j in 0..2 {
+                    let x = right - j;
+                    let upward = (right + 1) & 2 == 0;
+                    let y = if upward { size - 1 - vert } else { vert };
+                    let idx = (y * size + x) as usize;
+                    if !self.function[idx] && i < total_bits {
+                        self.modules[idx] = (data[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u8) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let i = y * self.size + x;
+                if invert && !self.function[i] {
+                    self.modules[i] = !self.modules[i];
+                }
+            }
+        }
+    }
+
+    fn penalty_score(&self) -> u32 {
+        const FINDER_LIKE: [bool; 7] = [true, false, true, true, true, false, true];
+
+        let size = self.size;
+        let at = |x: usize, y: usize| self.modules[y * size + x];
+        let mut score = 0;
+
+        for horizontal in [true, false] {
+            for a in 0..size {
+                let line: Vec<bool> = (0..size)
+                    .map(|b| if horizontal { at(b, a) } else { at(a, b) })
+                    .collect();
+
+                let mut run = 1;
+                for b in 1..=size {
+                    if b < size && line[b] == line[b - 1] {
+                        run += 1;
+                        continue;
+                    }
+                    if run >= 5 {
+                        score += 3 + (run - 5);
+                    }
+                    run = 1;
+                }
+
+                for b in 0..=size.saturating_sub(7) {
+                    if line[b..b + 7] != FINDER_LIKE {
+                        continue;
+                    }
+                    let light = |r: std::ops::Range<usize>| r.into_iter().all(|i| !line[i]);
+                    if (b >= 4 && light(b - 4..b)) || (b + 11 <= size && light(b + 7..b + 11)) {
+                        score += 40;
+                    }
+                }
+            }
+        }
+
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let c = at(x, y);
+                if c == at(x + 1, y) && c == at(x, y + 1) && c == at(x + 1, y + 1) {
+                    score += 3;
+                }
+            }
+        }
+
+        let total = (size * size) as i64;
+        let dark = self.modules.iter().filter(|&&m| m).count() as i64;
+        let k = ((dark * 20 - total * 10).abs() + total - 1) / total - 1;
+        score as u32 + k as u32 * 10
+    }
+}
+
+fn char_count_bits(version: u8) -> usize {
+    if version <= 9 {
+        8
+    } else {
+        16
+    }
+}
+
+fn data_bits_needed(len: usize, version: u8) -> usize {
+    if len >= 1 << char_count_bits(version) {
+        return usize::MAX;
+    }
+    4 + char_count_bits(version) + len * 8
+}
+
+fn num_raw_data_modules(version: u8) -> usize {
+    let v = version as usize;
+    let mut result = (16 * v + 128) * v + 64;
+    if v >= 2 {
+        let num_align = v / 7 + 2;
+        result -= (25 * num_align - 10) * num_align - 55;
+        if v >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn num_data_codewords(version: u8, ecc: EccLevel) -> usize {
+    let v = version as usize;
+    let e = ecc.index();
+    num_raw_data_modules(version) / 8
+        - ECC_CODEWORDS_PER_BLOCK[e][v] as usize * NUM_ERROR_CORRECTION_BLOCKS[e][v] as usize
+}
+
+fn alignment_positions(version: u8) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let v = version as usize;
+    let size = v * 4 + 17;
+    let num_align = v / 7 + 2;
+    let step = (v * 8 + num_align * 3 + 5) / (num_align * 4 - 4) * 2;
+    let mut positions: Vec<usize> = (0..num_align - 1).map(|i| size - 7 - i * step).collect();
+    positions.push(6);
+    positions.reverse();
+    positions
+}
+
+fn format_info(ecc: EccLevel, mask: u8) -> u32 {
+    let data = (ecc.format_bits() << 3) | u32::from(mask);
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    ((data << 10) | rem) ^ 0x5412
+}
+
+fn add_ecc_and_interleave(data: &[u8], version: u8, ecc: EccLevel) -> Vec<u8> {
+    let v = version as usize;
+    let e = ecc.index();
+    let num_blocks = NUM_ERROR_CORRECTION_BLOCKS[e][v] as usize;
+    let block_ecc_len = ECC_CODEWORDS_PER_BLOCK[e][v] as usize;
+    let raw_codewords = num_raw_data_modules(version) / 8;
+    let num_short_blocks = num_blocks - raw_codewords % num_blocks;
+    let short_block_len = raw_codewords / num_blocks;
+
+    let divisor = reed_solomon_divisor(block_ecc_len);
+    let mut blocks = Vec::with_capacity(num_blocks);
+    let mut k = 0;
+    for i in 0..num_blocks {
+        let len = short_block_len - block_ecc_len + usize::from(i >= num_short_blocks);
+        let mut block = data[k..k + len].to_vec();
+        k += len;
+        let ecc = reed_solomon_remainder(&block, &divisor);
+        if i < num_short_blocks {
+            block.push(0);
+        }
+        block.extend_from_slice(&ecc);
+        blocks.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw_codewords);
+    for i in 0..blocks[0].len() {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_block_len - block_ecc_len || j >= num_short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u32 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((u32::from(y) >> i) & 1) * u32::from(x);
+    }
+    z as u8
+}
+
+fn reed_solomon_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 0x02);
+    }
+    result
+}
+
+fn reed_solomon_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &b in data {
+        let factor = b ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_multiply(d, factor);
+        }
+    }
+    result
+}
+
+#[derive(Default)]
+struct BitBuffer {
+    bits: Vec<bool>,
+}
+
+impl BitBuffer {
+    fn append(&mut self, value: u32, len: usize) {
+        for i in (0..len).rev() {
+            self.bits.push((value >> i) & 1 != 0);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.bits
+            .chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |acc, (i, &b)| acc | (u8::from(b) << (7 - i)))
+            })
+            .collect()
+    }
+}
+
+fn write_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= u32::from(b);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Wrap data in a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut chunks = data.chunks(0xFFFF).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        out.push(u8::from(chunks.peek().is_none()));
+        let len = chunk.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + u32::from(byte)) % 65521;
+        b = (b + a) % 65521;
+    }
+    out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_selection_and_capacity() {
+        let qr = QrCode::encode_text("hello", EccLevel::Medium).unwrap();
+        assert_eq!(qr.version(), 1);
+        assert_eq!(qr.size(), 21);
+
+        // Version 1-M holds 14 bytes in byte mode.
+        assert_eq!(
+            QrCode::encode_bytes(&[b'a'; 14], EccLevel::Medium)
+                .unwrap()
+                .version(),
+            1
+        );
+        assert_eq!(
+            QrCode::encode_bytes(&[b'a'; 15], EccLevel::Medium)
+                .unwrap()
+                .version(),
+            2
+        );
+
+        assert_eq!(
+            QrCode::encode_bytes(&[0; 2953], EccLevel::Low)
+                .unwrap()
+                .version(),
+            40
+        );
+        assert!(QrCode::encode_bytes(&[0; 2954], EccLevel::Low).is_err());
+    }
+
+    #[test]
+    fn test_function_patterns_and_format_info() {
+        let qr = QrCode::encode_text(
+            "otpauth://totp/Example:alice?secret=JBSWY3DPEHPK3PXP",
+            EccLevel::Quartile,
+        )
+        .unwrap();
+        let last = qr.size() as i64 - 1;
+
+        // Finder pattern corners and the always-dark module.
+        for (x, y) in [(0, 0), (last, 0), (0, last)] {
+            assert!(qr.get(x, y));
+        }
+        assert!(qr.get(8, last - 7));
+        assert!(!qr.get(7, 7));
+
+        // Both copies of the format information agree and decode to the
+        // level and mask that were used.
+        let expected = format_info(EccLevel::Quartile, qr.mask());
+        let mut first = 0u32;
+        let mut second = 0u32;
+        for i in 0..15i64 {
+            let (fx, fy) = match i {
+                0..=5 => (8, i),
+                6 => (8, 7),
+                7 => (8, 8),
+                8 => (7, 8),
+                _ => (14 - i, 8),
+            };
+            let (sx, sy) = if i < 8 {
+                (last - i, 8)
+            } else {
+                (8, last - 14 + i)
+            };
+            first |= u32::from(qr.get(fx, fy)) << i;
+            second |= u32::from(qr.get(sx, sy)) << i;
+        }
+        assert_eq!(first, expected);
+        assert_eq!(second, expected);
+    }
+
+    #[test]
+    fn test_reed_solomon() {
+        // ISO/IEC 18004 Annex I: "01234567" at 1-M.
+        let data = [
+            0x10, 0x20, 0x0C, 0x56, 0x61, 0x80, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11, 0xEC, 0x11,
+            0xEC, 0x11,
+        ];
+        let ecc = reed_solomon_remainder(&data, &reed_solomon_divisor(10));
+        assert_eq!(
+            ecc,
+            [0xA5, 0x24, 0xD4, 0xC1, 0xED, 0x36, 0xC7, 0x87, 0x2C, 0x55]
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let qr = QrCode::encode_text("https://fusabi.dev", EccLevel::Low).unwrap();
+        let text = qr.to_unicode(2, false);
+        let lines: Vec<&str> = text.lines().collect();
+        let width = qr.size() + 4;
+        assert_eq!(lines.len(), width.div_ceil(2));
+        assert!(lines.iter().all(|l| l.chars().count() == width));
+        // The quiet zone is light, so it is drawn as blocks by default.
+        assert!(lines[0].chars().all(|c| c == '█'));
+
+        let png = qr.to_png(4, 4);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        let side = ((qr.size() + 8) * 4) as u32;
+        assert_eq!(&png[16..20], &side.to_be_bytes());
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+}
@@ -1,6 +1,9 @@
 //! Filesystem module.
 //!
 //! Provides functions for filesystem operations with safety controls.
+//! Change watching lives in [`watch`] behind the `fs-watch` feature, and
+//! random-access reads of large files in [`range`]. [`trash`] (feature
+//! `fs-trash`) moves paths to the trash instead of deleting them.
 
 use std::path::Path;
 use std::sync::Arc;
@@ -10,6 +13,14 @@ use fusabi_host::Value;
 
 use crate::safety::SafetyConfig;
 
+pub mod range;
+#[cfg(feature = "fs-trash")]
+pub mod trash;
+#[cfg(feature = "fs-watch")]
+pub mod watch;
+
+pub use range::{mmap_read, read_range};
+
 /// Read a file's contents.
 pub fn read_file(
     safety: &Arc<SafetyConfig>,
@@ -163,6 +174,19 @@ pub fn remove(
         .check_write(path)
         .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
 
+    if safety.trash_on_remove {
+        #[cfg(feature = "fs-trash")]
+        {
+            trash::move_to_trash(safety, path)
+                .map_err(|e| fusabi_host::Error::host_function(format!("fs.remove: {}", e)))?;
+            return Ok(Value::Null);
+        }
+        #[cfg(not(feature = "fs-trash"))]
+        return Err(fusabi_host::Error::host_function(
+            "fs.remove: trash_on_remove requires the fs-trash feature",
+        ));
+    }
+
     // Remove
     if path.is_dir() {
         std::fs::remove_dir_all(path)
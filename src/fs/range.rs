@@ -0,0 +1,321 @@
+//! Random-access reads of large files.
+//!
+//! [`read_range`] reads a byte range at any offset without touching the rest
+//! of the file, so scripts can sample sections of multi-gigabyte files.
+//! [`mmap_read`] is the same positioned read: a script value owns its bytes,
+//! so copying out of a mapping would gain nothing and would risk `SIGBUS`
+//! if the file shrank during the copy.
+//!
+//! Rust callers that want to use the pages in place can opt in to
+//! [`MappedRange`] with the `fs-mmap` feature on Unix.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use crate::safety::SafetyConfig;
+
+/// Largest range returned by a single read.
+pub const MAX_RANGE_LEN: u64 = 256 * 1024 * 1024;
+
+/// A validated range request.
+struct RangeRequest<'a> {
+    path: &'a Path,
+    offset: u64,
+    len: u64,
+    text: bool,
+}
+
+fn parse_request<'a>(
+    safety: &SafetyConfig,
+    args: &'a [Value],
+    function: &str,
+) -> Result<RangeRequest<'a>> {
+    let path = args
+        .first()
+        .and_then(|v| v.as_str())
+        .map(Path::new)
+        .ok_or_else(|| Error::host_function(format!("{}: missing path argument", function)))?;
+
+    safety
+        .paths
+        .check_read(path)
+        .map_err(|e| Error::host_function(e.to_string()))?;
+
+    let int_arg = |index: usize, name: &str| -> Result<u64> {
+        let value = args.get(index).and_then(|v| v.as_int()).ok_or_else(|| {
+            Error::host_function(format!("{}: missing {} argument", function, name))
+        })?;
+        u64::try_from(value).map_err(|_| {
+            Error::host_function(format!("{}: {} must not be negative", function, name))
+        })
+    };
+    let offset = int_arg(1, "offset")?;
+    let len = int_arg(2, "len")?;
+    if len > MAX_RANGE_LEN {
+        return Err(Error::host_function(format!(
+            "{}: len {} exceeds the maximum of {} bytes",
+            function, len, MAX_RANGE_LEN
+        )));
+    }
+
+    let text = match args.get(3).and_then(|v| v.as_str()) {
+        None | Some("bytes") => false,
+        Some("utf8") => true,
+        Some(other) => {
+            return Err(Error::host_function(format!(
+                "{}: unknown encoding '{}'",
+                function, other
+            )))
+        }
+    };
+
+    Ok(RangeRequest {
+        path,
+        offset,
+        len,
+        text,
+    })
+}
+
+fn to_value(bytes: Vec<u8>, text: bool) -> Value {
+    if text {
+        Value::String(String::from_utf8_lossy(&bytes).into_owned())
+    } else {
+        Value::Bytes(bytes)
+    }
+}
+
+/// Number of bytes of `[offset, offset + len)` that lie within the file.
+fn clamp_len(file: &File, offset: u64, len: u64) -> std::io::Result<u64> {
+    let size = file.metadata()?.len();
+    Ok(len.min(size.saturating_sub(offset)))
+}
+
+/// Read up to `len` bytes starting at `offset`.
+pub fn read_at(path: &Path, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = clamp_len(&file, offset, len)?;
+    let mut buf = Vec::with_capacity(len as usize);
+    if len > 0 {
+        file.seek(SeekFrom::Start(offset))?;
+        file.take(len).read_to_end(&mut buf)?;
+    }
+    Ok(buf)
+}
+
+/// A read-only memory mapping of a byte range of a file.
+///
+/// Dereferences to the bytes of the range; the pages are unmapped on drop.
+#[cfg(all(unix, feature = "fs-mmap"))]
+pub struct MappedRange {
+    ptr: *mut libc::c_void,
+    map_len: usize,
+    skip: usize,
+    len: usize,
+}
+
+// SAFETY: the mapping is read-only and owned by this value.
+#[cfg(all(unix, feature = "fs-mmap"))]
+unsafe impl Send for MappedRange {}
+// SAFETY: shared access only reads the mapping.
+#[cfg(all(unix, feature = "fs-mmap"))]
+unsafe impl Sync for MappedRange {}
+
+#[cfg(all(unix, feature = "fs-mmap"))]
+impl MappedRange {
+    /// Map up to `len` bytes starting at `offset`, covering only the pages
+    /// of the range.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be truncated or modified while the mapping is
+    /// alive. Truncation makes accesses past the new end raise `SIGBUS`,
+    /// and modifications change the bytes behind the returned slice.
+    pub unsafe fn new(path: &Path, offset: u64, len: u64) -> std::io::Result<Self> {
+        use std::os::unix::io::AsRawFd;
+
+        let file = File::open(path)?;
+        let len = clamp_len(&file, offset, len)? as usize;
+        if len == 0 {
+            return Ok(Self {
+                ptr: std::ptr::null_mut(),
+                map_len: 0,
+                skip: 0,
+                len: 0,
+            });
+        }
+
+        // SAFETY: sysconf has no preconditions.
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(1) as u64;
+        let aligned = offset - offset % page;
+        let skip = (offset - aligned) as usize;
+        let map_len = skip + len;
+        let map_offset = libc::off_t::try_from(aligned).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "offset too large")
+        })?;
+
+        // SAFETY: a fresh read-only private mapping of an open file; the
+        // arguments are checked by the kernel and failure is reported as
+        // MAP_FAILED.
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                map_offset,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            ptr,
+            map_len,
+            skip,
+            len,
+        })
+    }
+}
+
+#[cfg(all(unix, feature = "fs-mmap"))]
+impl std::ops::Deref for MappedRange {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: the mapping is `map_len` bytes long, `skip + len` equals
+        // `map_len`, and it lives as long as `self`.
+        unsafe { std::slice::from_raw_parts((self.ptr as *const u8).add(self.skip), self.len) }
+    }
+}
+
+#[cfg(all(unix, feature = "fs-mmap"))]
+impl Drop for MappedRange {
+    fn drop(&mut self) {
+        if self.map_len > 0 {
+            // SAFETY: `ptr` and `map_len` are exactly what mmap returned and
+            // took.
+            unsafe { libc::munmap(self.ptr, self.map_len) };
+        }
+    }
+}
+
+/// Read a byte range of a file.
+///
+/// # Arguments
+///
+/// * `args[0]` - File path
+/// * `args[1]` - Offset in bytes
+/// * `args[2]` - Number of bytes (at most 256 MiB)
+/// * `args[3]` - Encoding (optional): `"bytes"` (default) or `"utf8"`
+///   (invalid sequences replaced)
+///
+/// # Returns
+///
+/// The bytes read, shorter than requested at the end of the file
+pub fn read_range(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let request = parse_request(safety, args, "fs.read_range")?;
+    let bytes = read_at(request.path, request.offset, request.len)
+        .map_err(|e| Error::host_function(format!("fs.read_range: {}", e)))?;
+    Ok(to_value(bytes, request.text))
+}
+
+/// Read a byte range of a file.
+///
+/// Takes the same arguments as `fs.read_range` and is the same positioned
+/// read; see the [module documentation](self) for why it does not map the
+/// file.
+pub fn mmap_read(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let request = parse_request(safety, args, "fs.mmap_read")?;
+    let bytes = read_at(request.path, request.offset, request.len)
+        .map_err(|e| Error::host_function(format!("fs.mmap_read: {}", e)))?;
+    Ok(to_value(bytes, request.text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::PathAllowlist;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    #[test]
+    fn test_read_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.bin");
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &data).unwrap();
+
+        for (offset, len) in [(0, 10), (4095, 3), (8190, 5000), (19_990, 100), (30_000, 5)] {
+            let end = (offset + len).min(data.len());
+            let expected = data.get(offset..end).unwrap_or(&[]).to_vec();
+            assert_eq!(read_at(&path, offset as u64, len as u64).unwrap(), expected);
+            #[cfg(all(unix, feature = "fs-mmap"))]
+            {
+                // SAFETY: nothing modifies the file while it is mapped.
+                let mapped = unsafe { MappedRange::new(&path, offset as u64, len as u64) }.unwrap();
+                assert_eq!(&*mapped, expected.as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_host_fns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+        std::fs::write(&path, "header\nline one\nline two\n").unwrap();
+
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+        let path_arg = Value::String(path.to_string_lossy().into_owned());
+        let args = [
+            path_arg.clone(),
+            Value::Int(7),
+            Value::Int(8),
+            Value::String("utf8".into()),
+        ];
+
+        let denied = Arc::new(SafetyConfig::new());
+        assert!(read_range(&denied, &args, &ctx).is_err());
+
+        let safety =
+            Arc::new(SafetyConfig::new().with_paths(PathAllowlist::none().allow_read(dir.path())));
+        assert_eq!(
+            read_range(&safety, &args, &ctx).unwrap(),
+            Value::String("line one".into())
+        );
+        assert_eq!(
+            mmap_read(&safety, &args[..3], &ctx).unwrap(),
+            Value::Bytes(b"line one".to_vec())
+        );
+
+        let negative = [path_arg.clone(), Value::Int(-1), Value::Int(1)];
+        assert!(read_range(&safety, &negative, &ctx).is_err());
+        let huge = [
+            path_arg,
+            Value::Int(0),
+            Value::Int(MAX_RANGE_LEN as i64 + 1),
+        ];
+        assert!(mmap_read(&safety, &huge, &ctx).is_err());
+    }
+}
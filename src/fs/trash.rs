@@ -0,0 +1,378 @@
+//! Moving paths to the trash instead of deleting them.
+//!
+//! [`move_to_trash`] sends a file or directory to the platform trash: the
+//! freedesktop.org home trash on Linux and other Unix systems, `~/.Trash`
+//! on macOS and the Recycle Bin on Windows. When
+//! [`SafetyConfig::trash_dir`] is set, paths go to that quarantine
+//! directory instead, laid out like a freedesktop trash (`files/` holds the
+//! moved paths, `info/` a `.trashinfo` file recording where each came from
+//! and when), so cleanup scripts can be audited and undone.
+//!
+//! With [`SafetyConfig::trash_on_remove`], `fs.remove` trashes paths too.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use fusabi_host::{ExecutionContext, Value};
+
+use crate::safety::SafetyConfig;
+use crate::time::{civil_from_days, SECS_PER_DAY};
+
+/// Move `path` to the trash.
+///
+/// # Returns
+///
+/// Where the path now lives, or `None` for the Windows Recycle Bin, which
+/// does not report it.
+pub fn move_to_trash(safety: &SafetyConfig, path: &Path) -> crate::Result<Option<PathBuf>> {
+    safety.paths.check_write(path)?;
+    if std::fs::symlink_metadata(path).is_err() {
+        return Err(crate::Error::Filesystem(format!(
+            "no such file or directory: {}",
+            path.display()
+        )));
+    }
+
+    match &safety.trash_dir {
+        Some(dir) => trash_into(dir, path).map(Some),
+        None => platform_trash(path),
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_trash(path: &Path) -> crate::Result<Option<PathBuf>> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| home_dir().map(|home| home.join(".local/share")))
+        .ok_or_else(|| crate::Error::Environment("cannot locate the home trash".into()))?;
+    trash_into(&data_home.join("Trash"), path).map(Some)
+}
+
+#[cfg(target_os = "macos")]
+fn platform_trash(path: &Path) -> crate::Result<Option<PathBuf>> {
+    let trash = home_dir()
+        .map(|home| home.join(".Trash"))
+        .ok_or_else(|| crate::Error::Environment("cannot locate the trash".into()))?;
+    std::fs::create_dir_all(&trash)?;
+
+    let name = file_name(path)?;
+    let dest = (1..)
+        .map(|n| trash.join(numbered(&name, n)))
+        .find(|candidate| std::fs::symlink_metadata(candidate).is_err())
+        .expect("unbounded candidates");
+    move_path(path, &dest)?;
+    Ok(Some(dest))
+}
+
+#[cfg(windows)]
+fn platform_trash(path: &Path) -> crate::Result<Option<PathBuf>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::UI::Shell::{
+        SHFileOperationW, FOF_ALLOWUNDO, FOF_NOCONFIRMATION, FOF_NOERRORUI, FOF_SILENT, FO_DELETE,
+        SHFILEOPSTRUCTW,
+    };
+
+    let absolute = absolute(path)?;
+    // The source list is double NUL-terminated.
+    let mut from: Vec<u16> = absolute.as_os_str().encode_wide().collect();
+    from.extend([0, 0]);
+
+    let mut op = SHFILEOPSTRUCTW {
+        wFunc: FO_DELETE,
+        pFrom: from.as_ptr(),
+        fFlags: (FOF_ALLOWUNDO | FOF_NOCONFIRMATION | FOF_NOERRORUI | FOF_SILENT) as u16,
+        ..Default::default()
+    };
+    // SAFETY: `op` points at `from`, which outlives the call.
+    let status = unsafe { SHFileOperationW(&mut op) };
+    if status != 0 {
+        return Err(crate::Error::Filesystem(format!(
+            "moving {} to the Recycle Bin failed with code {}",
+            path.display(),
+            status
+        )));
+    }
+    if op.fAnyOperationsAborted != 0 {
+        return Err(crate::Error::Filesystem(format!(
+            "moving {} to the Recycle Bin was aborted",
+            path.display()
+        )));
+    }
+    Ok(None)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_trash(_path: &Path) -> crate::Result<Option<PathBuf>> {
+    Err(crate::Error::ModuleNotAvailable(
+        "no platform trash; set SafetyConfig::trash_dir".into(),
+    ))
+}
+
+#[cfg(unix)]
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+}
+
+/// Move a path into a freedesktop.org-style trash directory.
+fn trash_into(trash: &Path, path: &Path) -> crate::Result<PathBuf> {
+    let files = trash.join("files");
+    let info = trash.join("info");
+    std::fs::create_dir_all(&files)?;
+    std::fs::create_dir_all(&info)?;
+
+    let original = absolute(path)?;
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(&original.to_string_lossy()),
+        deletion_date(SystemTime::now())
+    );
+
+    // The info file is created first, exclusively, to claim the name.
+    let name = file_name(path)?;
+    let mut n = 1;
+    let (info_path, dest) = loop {
+        let candidate = numbered(&name, n);
+        let info_path = info.join(format!("{}.trashinfo", candidate));
+        let dest = files.join(&candidate);
+        n += 1;
+        if std::fs::symlink_metadata(&dest).is_ok() {
+            continue;
+        }
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(mut file) => {
+                use std::io::Write;
+                file.write_all(contents.as_bytes())?;
+                break (info_path, dest);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    if let Err(e) = move_path(path, &dest) {
+        let _ = std::fs::remove_file(&info_path);
+        return Err(e.into());
+    }
+    Ok(dest)
+}
+
+fn file_name(path: &Path) -> crate::Result<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| crate::Error::invalid_argument(format!("cannot trash {}", path.display())))
+}
+
+/// `name` for the first candidate, then `name.2`, `name.3`, ...
+fn numbered(name: &str, n: u32) -> String {
+    if n == 1 {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, n)
+    }
+}
+
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(std::env::current_dir()?.join(path))
+    }
+}
+
+/// Rename, or copy and delete when the trash is on another filesystem.
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match std::fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if is_cross_device(&e) => {
+            if let Err(e) = copy_recursive(from, to) {
+                let _ = remove_path(to);
+                return Err(e);
+            }
+            remove_path(from)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn is_cross_device(e: &io::Error) -> bool {
+    // EXDEV on Unix, ERROR_NOT_SAME_DEVICE on Windows.
+    let code = if cfg!(windows) { 17 } else { 18 };
+    e.raw_os_error() == Some(code)
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = std::fs::symlink_metadata(from)?;
+    if metadata.file_type().is_symlink() {
+        #[cfg(unix)]
+        return std::os::unix::fs::symlink(std::fs::read_link(from)?, to);
+        #[cfg(not(unix))]
+        return std::fs::copy(from, to).map(|_| ());
+    }
+    if metadata.is_dir() {
+        std::fs::create_dir(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        std::fs::set_permissions(to, metadata.permissions())
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if std::fs::symlink_metadata(path)?.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// Percent-encode a path for the `Path` key, keeping `/` and unreserved
+/// characters.
+fn percent_encode(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for b in path.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'/') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Format a `DeletionDate` value (`YYYY-MM-DDThh:mm:ss`, UTC).
+fn deletion_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+    let rem = secs.rem_euclid(SECS_PER_DAY);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Move a file or directory to the trash.
+///
+/// # Arguments
+///
+/// * `args[0]` - Path (must be writable under the path allowlist)
+///
+/// # Returns
+///
+/// Where the path was moved, or null when the platform does not report it
+/// (the Windows Recycle Bin)
+pub fn trash(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let path = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("fs.trash: missing path argument"))?;
+
+    let dest = move_to_trash(safety, Path::new(path))
+        .map_err(|e| fusabi_host::Error::host_function(format!("fs.trash: {}", e)))?;
+    Ok(dest.map_or(Value::Null, |p| {
+        Value::String(p.to_string_lossy().into_owned())
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::PathAllowlist;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_trash_into_quarantine() {
+        let ctx = create_test_ctx();
+        let work = tempfile::tempdir().unwrap();
+        let quarantine = tempfile::tempdir().unwrap();
+        let safety = Arc::new(
+            SafetyConfig::new()
+                .with_paths(PathAllowlist::none().allow_rw(work.path()))
+                .with_trash_dir(quarantine.path()),
+        );
+
+        let report = work.path().join("report 1.txt");
+        std::fs::write(&report, "old").unwrap();
+        let moved = trash(
+            &safety,
+            &[Value::String(report.display().to_string())],
+            &ctx,
+        )
+        .unwrap();
+        let moved = PathBuf::from(moved.as_str().unwrap());
+        assert!(!report.exists());
+        assert_eq!(moved, quarantine.path().join("files/report 1.txt"));
+        assert_eq!(std::fs::read_to_string(&moved).unwrap(), "old");
+
+        let info =
+            std::fs::read_to_string(quarantine.path().join("info/report 1.txt.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath="));
+        assert!(info.contains("/report%201.txt\n"));
+        assert!(info.contains("\nDeletionDate="));
+
+        // A second path with the same name gets a numbered slot.
+        std::fs::create_dir(&report).unwrap();
+        std::fs::write(report.join("nested"), "x").unwrap();
+        let moved = move_to_trash(&safety, &report).unwrap().unwrap();
+        assert_eq!(moved, quarantine.path().join("files/report 1.txt.2"));
+        assert!(moved.join("nested").exists());
+
+        let outside = quarantine.path().join("files/report 1.txt");
+        assert!(move_to_trash(&safety, &outside).is_err());
+        assert!(move_to_trash(&safety, &work.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_remove_redirects_to_trash() {
+        let ctx = create_test_ctx();
+        let work = tempfile::tempdir().unwrap();
+        let quarantine = tempfile::tempdir().unwrap();
+        let safety = Arc::new(
+            SafetyConfig::new()
+                .with_paths(PathAllowlist::none().allow_rw(work.path()))
+                .with_trash_dir(quarantine.path())
+                .with_trash_on_remove(true),
+        );
+
+        let file = work.path().join("scratch");
+        std::fs::write(&file, "keep me").unwrap();
+        crate::fs::remove(&safety, &[Value::String(file.display().to_string())], &ctx).unwrap();
+        assert!(!file.exists());
+        assert!(quarantine.path().join("files/scratch").exists());
+    }
+
+    #[test]
+    fn test_deletion_date() {
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        assert_eq!(deletion_date(time), "2023-11-14T22:13:20");
+        assert_eq!(percent_encode("/tmp/a b%"), "/tmp/a%20b%25");
+    }
+}
@@ -0,0 +1,699 @@
+//! Filesystem watching.
+//!
+//! Two backends share one event API. The native backend uses the operating
+//! system's change notifications (inotify, FSEvents, ReadDirectoryChangesW)
+//! through `notify`. The poll backend periodically rescans the watched tree
+//! and diffs sizes and modification times; it is meant for NFS mounts,
+//! container overlay filesystems and other places where native
+//! notifications are unreliable or missing.
+//!
+//! The backend is chosen per watch, defaulting to the `fs` module options
+//! (`watch_backend`, `watch_poll_interval_ms`, `watch_max_entries`).
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use parking_lot::Mutex;
+
+use fusabi_host::ExecutionContext;
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+
+/// Open watchers by handle.
+static WATCHERS: OnceLock<Mutex<HashMap<i64, Box<dyn Watcher>>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn watchers() -> &'static Mutex<HashMap<i64, Box<dyn Watcher>>> {
+    WATCHERS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Number of active watchers.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    watchers().lock().len()
+}
+
+/// Which mechanism a watcher uses to detect changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchBackend {
+    /// Operating system change notifications.
+    Native,
+    /// Periodic rescans of the watched tree.
+    Poll,
+}
+
+impl WatchBackend {
+    /// Parse a backend name (`native` or `poll`).
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "native" | "notify" => Some(Self::Native),
+            "poll" | "polling" => Some(Self::Poll),
+            _ => None,
+        }
+    }
+
+    /// Backend name as used in options.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Native => "native",
+            Self::Poll => "poll",
+        }
+    }
+}
+
+/// Watcher configuration.
+#[derive(Debug, Clone)]
+pub struct WatchConfig {
+    /// Backend to use.
+    pub backend: WatchBackend,
+    /// Whether to watch subdirectories.
+    pub recursive: bool,
+    /// Minimum time between rescans (poll backend).
+    pub poll_interval: Duration,
+    /// Maximum number of entries a single rescan may visit (poll backend).
+    pub max_entries: usize,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            backend: WatchBackend::Native,
+            recursive: true,
+            poll_interval: Duration::from_secs(2),
+            max_entries: 10_000,
+        }
+    }
+}
+
+impl WatchConfig {
+    /// Create a new watcher configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read defaults from `fs` module options.
+    ///
+    /// Recognizes `watch_backend`, `watch_poll_interval_ms` and
+    /// `watch_max_entries`; other options are ignored.
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self> {
+        let mut config = Self::default();
+
+        if let Some(name) = options.get("watch_backend") {
+            config.backend = WatchBackend::parse(name).ok_or_else(|| {
+                Error::invalid_argument(format!("unknown watch backend: {}", name))
+            })?;
+        }
+
+        if let Some(ms) = options.get("watch_poll_interval_ms") {
+            let ms: u64 = ms.parse().map_err(|_| {
+                Error::invalid_argument(format!("invalid watch_poll_interval_ms: {}", ms))
+            })?;
+            config.poll_interval = Duration::from_millis(ms);
+        }
+
+        if let Some(n) = options.get("watch_max_entries") {
+            config.max_entries = n.parse().map_err(|_| {
+                Error::invalid_argument(format!("invalid watch_max_entries: {}", n))
+            })?;
+        }
+
+        Ok(config)
+    }
+
+    /// Set the backend.
+    pub fn with_backend(mut self, backend: WatchBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Set whether subdirectories are watched.
+    pub fn with_recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Set the poll interval.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Set the rescan entry limit.
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+}
+
+/// Kind of filesystem change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEventKind {
+    /// A file or directory appeared.
+    Created,
+    /// A file's contents or metadata changed.
+    Modified,
+    /// A file or directory disappeared.
+    Removed,
+}
+
+impl WatchEventKind {
+    /// Kind name as exposed to scripts.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Modified => "modified",
+            Self::Removed => "removed",
+        }
+    }
+}
+
+/// A single filesystem change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchEvent {
+    /// What happened.
+    pub kind: WatchEventKind,
+    /// The affected path.
+    pub path: PathBuf,
+}
+
+impl WatchEvent {
+    /// Convert to a script value map with `kind` and `path`.
+    pub fn to_value(&self) -> Value {
+        let mut map = HashMap::new();
+        map.insert("kind".to_string(), Value::String(self.kind.as_str().into()));
+        map.insert(
+            "path".to_string(),
+            Value::String(self.path.to_string_lossy().into_owned()),
+        );
+        Value::Map(map)
+    }
+}
+
+/// A source of filesystem change events.
+pub trait Watcher: Send {
+    /// The backend this watcher uses.
+    fn backend(&self) -> WatchBackend;
+
+    /// Return the changes observed since the last call, without blocking.
+    fn poll_events(&mut self) -> Result<Vec<WatchEvent>>;
+}
+
+/// Open a watcher on `root` with the configured backend.
+pub fn open(root: &Path, config: &WatchConfig) -> Result<Box<dyn Watcher>> {
+    match config.backend {
+        WatchBackend::Native => Ok(Box::new(NativeWatcher::new(root, config)?)),
+        WatchBackend::Poll => Ok(Box::new(PollWatcher::new(root, config)?)),
+    }
+}
+
+/// Watcher backed by operating system notifications.
+pub struct NativeWatcher {
+    // Kept alive so notifications keep flowing into `events`.
+    _watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl NativeWatcher {
+    /// Start watching `root`.
+    pub fn new(root: &Path, config: &WatchConfig) -> Result<Self> {
+        use notify::Watcher as _;
+
+        let (tx, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            // The receiver is gone once the watcher is dropped.
+            let _ = tx.send(event);
+        })
+        .map_err(|e| Error::filesystem(format!("watch: {}", e)))?;
+
+        let mode = if config.recursive {
+            notify::RecursiveMode::Recursive
+        } else {
+            notify::RecursiveMode::NonRecursive
+        };
+        watcher
+            .watch(root, mode)
+            .map_err(|e| Error::filesystem(format!("watch {}: {}", root.display(), e)))?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+}
+
+impl Watcher for NativeWatcher {
+    fn backend(&self) -> WatchBackend {
+        WatchBackend::Native
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<WatchEvent>> {
+        use notify::event::{EventKind, ModifyKind, RenameMode};
+
+        let mut out = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            let event = event.map_err(|e| Error::filesystem(format!("watch: {}", e)))?;
+
+            let push = |out: &mut Vec<WatchEvent>, kind, path: &PathBuf| {
+                out.push(WatchEvent {
+                    kind,
+                    path: path.clone(),
+                })
+            };
+            match event.kind {
+                EventKind::Create(_) => {
+                    for path in &event.paths {
+                        push(&mut out, WatchEventKind::Created, path);
+                    }
+                }
+                EventKind::Remove(_) => {
+                    for path in &event.paths {
+                        push(&mut out, WatchEventKind::Removed, path);
+                    }
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                    for path in &event.paths {
+                        push(&mut out, WatchEventKind::Removed, path);
+                    }
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                    for path in &event.paths {
+                        push(&mut out, WatchEventKind::Created, path);
+                    }
+                }
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => {
+                    if let [from, to] = event.paths.as_slice() {
+                        push(&mut out, WatchEventKind::Removed, from);
+                        push(&mut out, WatchEventKind::Created, to);
+                    }
+                }
+                EventKind::Modify(_) => {
+                    for path in &event.paths {
+                        push(&mut out, WatchEventKind::Modified, path);
+                    }
+                }
+                EventKind::Access(_) | EventKind::Any | EventKind::Other => {}
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// What the poll backend remembers about each entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stamp {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Watcher that detects changes by rescanning the tree.
+///
+/// Rescans happen at most once per poll interval; calls in between return
+/// no events. A rescan that would visit more than `max_entries` entries
+/// fails instead of silently missing changes.
+pub struct PollWatcher {
+    root: PathBuf,
+    recursive: bool,
+    interval: Duration,
+    max_entries: usize,
+    last_scan: Instant,
+    entries: HashMap<PathBuf, Stamp>,
+}
+
+impl PollWatcher {
+    /// Start watching `root`, taking the initial scan immediately.
+    pub fn new(root: &Path, config: &WatchConfig) -> Result<Self> {
+        let mut watcher = Self {
+            root: root.to_path_buf(),
+            recursive: config.recursive,
+            interval: config.poll_interval,
+            max_entries: config.max_entries,
+            last_scan: Instant::now(),
+            entries: HashMap::new(),
+        };
+        watcher.entries = watcher.scan()?;
+        Ok(watcher)
+    }
+
+    /// Rescan now, regardless of the poll interval.
+    pub fn rescan(&mut self) -> Result<Vec<WatchEvent>> {
+        let current = self.scan()?;
+        self.last_scan = Instant::now();
+
+        let mut events = Vec::new();
+        for (path, stamp) in &current {
+            match self.entries.get(path) {
+                None => events.push(WatchEvent {
+                    kind: WatchEventKind::Created,
+                    path: path.clone(),
+                }),
+                // A directory's own mtime changes with its children; those
+                // are reported individually.
+                Some(old) if old != stamp && !(old.is_dir && stamp.is_dir) => {
+                    events.push(WatchEvent {
+                        kind: WatchEventKind::Modified,
+                        path: path.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        for path in self.entries.keys() {
+            if !current.contains_key(path) {
+                events.push(WatchEvent {
+                    kind: WatchEventKind::Removed,
+                    path: path.clone(),
+                });
+            }
+        }
+        events.sort_by(|a, b| a.path.cmp(&b.path));
+
+        self.entries = current;
+        Ok(events)
+    }
+
+    fn scan(&self) -> Result<HashMap<PathBuf, Stamp>> {
+        let mut entries = HashMap::new();
+
+        // A missing root is reported as removals, not an error.
+        let Ok(meta) = std::fs::metadata(&self.root) else {
+            return Ok(entries);
+        };
+        if !meta.is_dir() {
+            entries.insert(self.root.clone(), stamp(&meta));
+            return Ok(entries);
+        }
+
+        let mut pending = vec![self.root.clone()];
+        while let Some(dir) = pending.pop() {
+            // Directories may vanish mid-scan; the next scan catches up.
+            let Ok(read_dir) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in read_dir.flatten() {
+                let Ok(meta) = entry.metadata() else {
+                    continue;
+                };
+                if entries.len() >= self.max_entries {
+                    return Err(Error::filesystem(format!(
+                        "watch {}: more than {} entries; narrow the watch or raise max_entries",
+                        self.root.display(),
+                        self.max_entries
+                    )));
+                }
+                let path = entry.path();
+                if meta.is_dir() && self.recursive {
+                    pending.push(path.clone());
+                }
+                entries.insert(path, stamp(&meta));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+fn stamp(meta: &std::fs::Metadata) -> Stamp {
+    Stamp {
+        is_dir: meta.is_dir(),
+        len: meta.len(),
+        modified: meta.modified().ok(),
+    }
+}
+
+impl Watcher for PollWatcher {
+    fn backend(&self) -> WatchBackend {
+        WatchBackend::Poll
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<WatchEvent>> {
+        if self.last_scan.elapsed() < self.interval {
+            return Ok(Vec::new());
+        }
+        self.rescan()
+    }
+}
+
+/// A watcher that drops events for paths the script may not read, such as
+/// denied subdirectories of a recursive watch.
+struct AllowedWatcher {
+    inner: Box<dyn Watcher>,
+    safety: Arc<SafetyConfig>,
+}
+
+impl Watcher for AllowedWatcher {
+    fn backend(&self) -> WatchBackend {
+        self.inner.backend()
+    }
+
+    fn poll_events(&mut self) -> Result<Vec<WatchEvent>> {
+        let mut events = self.inner.poll_events()?;
+        events.retain(|event| self.safety.paths.can_read(&event.path));
+        Ok(events)
+    }
+}
+
+/// Start watching a path.
+///
+/// # Arguments
+///
+/// * `args[0]` - Path to watch (file or directory)
+/// * `args[1]` - Options map (optional): `backend` (`native` or `poll`),
+///   `recursive` (bool), `interval_ms` and `max_entries` (poll backend).
+///   The configured poll interval and entry limit are bounds: a shorter
+///   interval or a larger limit is clamped to them.
+///
+/// Events for paths the script may not read are not reported.
+///
+/// # Returns
+///
+/// Watch handle
+pub fn watch(
+    safety: &Arc<SafetyConfig>,
+    defaults: &WatchConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let path_str = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("fs.watch: missing path argument"))?;
+
+    let path = Path::new(path_str);
+
+    // Check safety
+    safety
+        .paths
+        .check_read(path)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let mut config = defaults.clone();
+    if let Some(options) = args.get(1).and_then(|v| v.as_map()) {
+        if let Some(name) = options.get("backend").and_then(|v| v.as_str()) {
+            config.backend = WatchBackend::parse(name).ok_or_else(|| {
+                fusabi_host::Error::host_function(format!("fs.watch: unknown backend: {}", name))
+            })?;
+        }
+        if let Some(recursive) = options.get("recursive").and_then(|v| v.as_bool()) {
+            config.recursive = recursive;
+        }
+        if let Some(ms) = options.get("interval_ms").and_then(|v| v.as_int()) {
+            config.poll_interval =
+                Duration::from_millis(ms.max(0) as u64).max(defaults.poll_interval);
+        }
+        if let Some(n) = options.get("max_entries").and_then(|v| v.as_int()) {
+            config.max_entries = (n.max(0) as usize).min(defaults.max_entries);
+        }
+    }
+
+    let watcher = open(path, &config)
+        .map_err(|e| fusabi_host::Error::host_function(format!("fs.watch: {}", e)))?;
+    let watcher = Box::new(AllowedWatcher {
+        inner: watcher,
+        safety: safety.clone(),
+    });
+
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    watchers().lock().insert(handle, watcher);
+    Ok(Value::Int(handle))
+}
+
+/// Drain pending events from a watch without blocking.
+///
+/// # Arguments
+///
+/// * `args[0]` - Watch handle
+///
+/// # Returns
+///
+/// List of maps with `kind` (`created`, `modified`, `removed`) and `path`
+pub fn watch_events(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| fusabi_host::Error::host_function("fs.watch_events: missing handle"))?;
+
+    let mut watchers = watchers().lock();
+    let watcher = watchers.get_mut(&handle).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("fs.watch_events: unknown handle {}", handle))
+    })?;
+
+    let events = watcher
+        .poll_events()
+        .map_err(|e| fusabi_host::Error::host_function(format!("fs.watch_events: {}", e)))?;
+
+    Ok(Value::List(
+        events.iter().map(WatchEvent::to_value).collect(),
+    ))
+}
+
+/// Stop a watch and release its resources.
+///
+/// # Returns
+///
+/// Whether the handle was open
+pub fn unwatch(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| fusabi_host::Error::host_function("fs.unwatch: missing handle"))?;
+
+    Ok(Value::Bool(watchers().lock().remove(&handle).is_some()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::PathAllowlist;
+    use fusabi_host::Capabilities;
+    use fusabi_host::Limits;
+    use fusabi_host::{Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_poll_watcher() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("keep.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("gone.txt"), "a").unwrap();
+        std::fs::create_dir(dir.path().join("sub")).unwrap();
+
+        let config = WatchConfig::new()
+            .with_backend(WatchBackend::Poll)
+            .with_poll_interval(Duration::from_secs(3600));
+        let mut watcher = PollWatcher::new(dir.path(), &config).unwrap();
+
+        std::fs::write(dir.path().join("keep.txt"), "changed").unwrap();
+        std::fs::remove_file(dir.path().join("gone.txt")).unwrap();
+        std::fs::write(dir.path().join("sub/new.txt"), "b").unwrap();
+
+        // Within the interval nothing is rescanned.
+        assert!(watcher.poll_events().unwrap().is_empty());
+
+        let events = watcher.rescan().unwrap();
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent {
+                    kind: WatchEventKind::Removed,
+                    path: dir.path().join("gone.txt"),
+                },
+                WatchEvent {
+                    kind: WatchEventKind::Modified,
+                    path: dir.path().join("keep.txt"),
+                },
+                WatchEvent {
+                    kind: WatchEventKind::Created,
+                    path: dir.path().join("sub/new.txt"),
+                },
+            ]
+        );
+        assert!(watcher.rescan().unwrap().is_empty());
+
+        let limited = config.with_max_entries(2);
+        assert!(PollWatcher::new(dir.path(), &limited).is_err());
+    }
+
+    #[test]
+    fn test_watch_config_options() {
+        let mut options = HashMap::new();
+        options.insert("watch_backend".to_string(), "poll".to_string());
+        options.insert("watch_poll_interval_ms".to_string(), "500".to_string());
+        let config = WatchConfig::from_options(&options).unwrap();
+        assert_eq!(config.backend, WatchBackend::Poll);
+        assert_eq!(config.poll_interval, Duration::from_millis(500));
+
+        options.insert("watch_backend".to_string(), "magic".to_string());
+        assert!(WatchConfig::from_options(&options).is_err());
+    }
+
+    #[test]
+    fn test_watch_host_functions() {
+        let ctx = create_test_ctx();
+        let dir = tempfile::tempdir().unwrap();
+        let path = Value::String(dir.path().to_string_lossy().into_owned());
+        let mut options = HashMap::new();
+        options.insert("backend".to_string(), Value::String("poll".into()));
+        options.insert("interval_ms".to_string(), Value::Int(0));
+        let args = [path, Value::Map(options)];
+
+        let defaults = WatchConfig::default().with_poll_interval(Duration::ZERO);
+        std::fs::create_dir(dir.path().join("secret")).unwrap();
+
+        let denied = Arc::new(SafetyConfig::new());
+        assert!(watch(&denied, &defaults, &args, &ctx).is_err());
+
+        let safety = Arc::new(
+            SafetyConfig::new().with_paths(
+                PathAllowlist::none()
+                    .allow_read(dir.path())
+                    .deny(dir.path().join("secret")),
+            ),
+        );
+        let handle = watch(&safety, &defaults, &args, &ctx).unwrap();
+
+        // Changes under the denied subdirectory are not reported
+        std::fs::write(dir.path().join("a.txt"), "x").unwrap();
+        std::fs::write(dir.path().join("secret/key.txt"), "x").unwrap();
+        let events = watch_events(std::slice::from_ref(&handle), &ctx).unwrap();
+        let events = events.as_list().unwrap();
+        assert_eq!(events.len(), 1);
+        let event = events[0].as_map().unwrap();
+        assert_eq!(event.get("kind"), Some(&Value::String("created".into())));
+        assert_eq!(
+            event.get("path"),
+            Some(&Value::String(
+                dir.path().join("a.txt").to_string_lossy().into_owned()
+            ))
+        );
+        assert_eq!(
+            unwatch(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Bool(true)
+        );
+        assert!(watch_events(&[handle], &ctx).is_err());
+
+        // Scripts cannot rescan more often or visit more entries than configured
+        let bounded = WatchConfig::default()
+            .with_poll_interval(Duration::from_secs(3600))
+            .with_max_entries(100);
+        let handle = watch(&safety, &bounded, &args, &ctx).unwrap();
+        std::fs::write(dir.path().join("b.txt"), "x").unwrap();
+        let events = watch_events(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert_eq!(events, Value::List(vec![]));
+        unwatch(std::slice::from_ref(&handle), &ctx).unwrap();
+        let mut options = HashMap::new();
+        options.insert("backend".to_string(), Value::String("poll".into()));
+        options.insert("max_entries".to_string(), Value::Int(1_000_000));
+        let args = [args[0].clone(), Value::Map(options)];
+        assert!(watch(&safety, &bounded.with_max_entries(2), &args, &ctx).is_err());
+    }
+}
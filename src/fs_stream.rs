@@ -33,6 +33,12 @@
 use fusabi_host::{Error, ExecutionContext, Result, Value};
 use parking_lot::Mutex;
 use std::collections::HashMap;
+#[cfg(feature = "format")]
+use std::fs::File;
+#[cfg(feature = "format")]
+use std::io::{BufRead, BufReader};
+#[cfg(feature = "format")]
+use std::path::Path;
 use std::sync::Arc;
 
 lazy_static::lazy_static! {
@@ -41,12 +47,85 @@ lazy_static::lazy_static! {
 
 static NEXT_HANDLE: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(1);
 
+/// Number of open streams.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    STREAMS.lock().len()
+}
+
 /// Represents an open file stream.
 #[derive(Clone)]
 struct FileStream {
     path: String,
     buffer_size: usize,
     position: usize,
+    #[cfg(feature = "format")]
+    reader: Option<Arc<Mutex<BufReader<File>>>>,
+}
+
+/// Open a line reader over a file, returning a handle in the stream table.
+///
+/// Callers are responsible for safety checks on `path`.
+#[cfg(feature = "format")]
+pub(crate) fn open_lines(path: &Path, buffer_size: usize) -> std::io::Result<i64> {
+    let file = File::open(path)?;
+    let handle = NEXT_HANDLE.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+    let stream = FileStream {
+        path: path.display().to_string(),
+        buffer_size,
+        position: 0,
+        reader: Some(Arc::new(Mutex::new(BufReader::with_capacity(
+            buffer_size.max(1),
+            file,
+        )))),
+    };
+
+    STREAMS.lock().insert(handle, stream);
+    Ok(handle)
+}
+
+/// Get the buffered reader behind a reader handle.
+#[cfg(feature = "format")]
+pub(crate) fn reader(handle: i64, function: &str) -> Result<Arc<Mutex<BufReader<File>>>> {
+    STREAMS
+        .lock()
+        .get(&handle)
+        .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))?
+        .reader
+        .clone()
+        .ok_or_else(|| Error::host_function(format!("{}: handle is not a reader", function)))
+}
+
+/// Read the next line from a line reader handle, without its terminator.
+///
+/// Returns the 1-based line number with the line, or `None` at end of file.
+#[cfg(feature = "format")]
+pub(crate) fn next_line(handle: i64, function: &str) -> Result<Option<(usize, String)>> {
+    let (reader, line_no) = {
+        let mut streams = STREAMS.lock();
+        let stream = streams
+            .get_mut(&handle)
+            .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))?;
+        let reader = stream.reader.clone().ok_or_else(|| {
+            Error::host_function(format!("{}: handle is not a line reader", function))
+        })?;
+        stream.position += 1;
+        (reader, stream.position)
+    };
+
+    let mut line = String::new();
+    let read = reader
+        .lock()
+        .read_line(&mut line)
+        .map_err(|e| Error::host_function(format!("{}: {}", function, e)))?;
+    if read == 0 {
+        return Ok(None);
+    }
+
+    let trimmed = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(trimmed);
+    Ok(Some((line_no, line)))
 }
 
 /// Open a file for tailing (like `tail -f`).
@@ -77,6 +156,8 @@ pub fn tail(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
         path: path.to_string(),
         buffer_size,
         position: 0,
+        #[cfg(feature = "format")]
+        reader: None,
     };
 
     STREAMS.lock().insert(handle, stream);
@@ -108,23 +189,43 @@ pub fn read_line(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
         .and_then(|v| v.as_int())
         .ok_or_else(|| Error::host_function("fs_stream.read_line: missing handle argument"))?;
 
+    Ok(poll_line(handle, "fs_stream.read_line")?.map_or(Value::Null, Value::String))
+}
+
+/// Read the next line of a stream without blocking, or `None` if no data
+/// is available.
+fn poll_line(handle: i64, function: &str) -> Result<Option<String>> {
     let mut streams = STREAMS.lock();
     let stream = streams
         .get_mut(&handle)
-        .ok_or_else(|| Error::host_function("fs_stream.read_line: invalid handle"))?;
+        .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))?;
 
     // TODO: Actually read from file
     // For now, return mock data occasionally
     stream.position += 1;
 
     if stream.position % 3 == 0 {
-        Ok(Value::String(format!(
+        Ok(Some(format!(
             "Mock line {} from {}",
             stream.position, stream.path
         )))
     } else {
-        Ok(Value::Null)
+        Ok(None)
+    }
+}
+
+/// Read up to `max` lines of a stream without blocking, stopping at the
+/// first poll with no data.
+#[cfg(feature = "terminal-ui")]
+pub(crate) fn poll_lines(handle: i64, max: usize, function: &str) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    while lines.len() < max {
+        match poll_line(handle, function)? {
+            Some(line) => lines.push(line),
+            None => break,
+        }
     }
+    Ok(lines)
 }
 
 /// Close a file stream and release resources.
@@ -201,6 +302,8 @@ pub fn open(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
         path: path.to_string(),
         buffer_size: chunk_size,
         position: 0,
+        #[cfg(feature = "format")]
+        reader: None,
     };
 
     STREAMS.lock().insert(handle, stream);
@@ -1,6 +1,6 @@
 //! GPU module.
 //!
-//! Provides GPU monitoring and metrics using NVML (NVIDIA Management Library).
+//! Provides GPU monitoring and metrics for NVIDIA, AMD and Apple GPUs.
 //! Useful for observability in GPU-intensive workloads like machine learning.
 //!
 //! ## Features
@@ -8,11 +8,24 @@
 //! - List available GPU devices
 //! - Query GPU utilization
 //! - Query memory usage
-//! - Query temperature
+//! - Query temperature, power draw and clock speeds
+//! - List the processes using a device and their memory
+//! - Query driver versions and device capabilities
+//! - Report NUMA affinity and the interconnects between devices
+//! - Account energy use per device from power samples ([`energy`])
+//! - Sample devices into metrics gauges in the background ([`sampler`],
+//!   with the `metrics` feature)
 //!
-//! ## Requirements
+//! ## Backends
 //!
-//! Requires NVML library (nvidia-smi) to be available on the system.
+//! Devices are read through a [`GpuBackend`] chosen at runtime (see
+//! [`backend()`]): NVML for NVIDIA, the `amdgpu` sysfs interface for AMD
+//! (ROCm), and the IOKit registry for Apple (Metal). Vendor libraries are
+//! loaded on first use, so the module builds and registers everywhere. On
+//! machines without a usable backend every function except
+//! `gpu.is_available` and `gpu.backend` fails with a "module not available"
+//! error; scripts can probe with `gpu.is_available()` first. Readings a
+//! backend does not provide fail the same way.
 //!
 //! ## Example
 //!
@@ -29,33 +42,91 @@
 //! let memory = gpu::memory_info(&[Value::Int(0)], &ctx)?;
 //! ```
 
+pub mod backend;
+pub mod energy;
+pub mod metal;
+pub mod nvml;
+pub mod rocm;
+#[cfg(feature = "metrics")]
+pub mod sampler;
+
 use fusabi_host::{Error, ExecutionContext, Result, Value};
 use std::collections::HashMap;
 
+pub use backend::{backend, DeviceTopology, GpuBackend, GpuLink, LinkKind};
+pub use nvml::nvml;
+
+use energy::EnergyMeter;
+
+fn backend_for(function: &str) -> Result<&'static dyn GpuBackend> {
+    backend().map_err(|e| Error::host_function(format!("{}: {}", function, e)))
+}
+
+fn backend_error(function: &str, e: crate::Error) -> Error {
+    Error::host_function(format!("{}: {}", function, e))
+}
+
+/// Look up the backend and the device index in `args[0]`.
+fn device(function: &str, args: &[Value]) -> Result<(&'static dyn GpuBackend, i64, u32)> {
+    let device_id = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing device_id argument", function)))?;
+    let index = u32::try_from(device_id).map_err(|_| {
+        Error::host_function(format!("{}: invalid device_id {}", function, device_id))
+    })?;
+    Ok((backend_for(function)?, device_id, index))
+}
+
+/// Check whether a GPU backend is available.
+///
+/// # Returns
+///
+/// True when a backend loaded, so the other `gpu` functions can return data
+pub fn is_available(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    Ok(Value::Bool(backend().is_ok()))
+}
+
+/// Get the name of the backend in use.
+///
+/// # Returns
+///
+/// `"nvml"`, `"rocm"` or `"metal"`, or null when no backend is available
+pub fn backend_name(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    Ok(backend().map_or(Value::Null, |b| Value::String(b.name().to_string())))
+}
+
 /// List all available GPU devices.
 ///
 /// Returns a list of maps containing device information:
 /// - `id`: Device index
 /// - `name`: Device name
-/// - `uuid`: Device UUID
+/// - `uuid`: Device UUID, or null if the vendor provides none
 ///
 /// # Returns
 ///
 /// List of device info maps
 pub fn list_devices(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    // TODO: Implement using NVML bindings
-    tracing::debug!("gpu.list_devices: returning mock data");
-
-    // Mock data for development
-    let mut device = HashMap::new();
-    device.insert("id".to_string(), Value::Int(0));
-    device.insert("name".to_string(), Value::String("Mock GPU".to_string()));
-    device.insert(
-        "uuid".to_string(),
-        Value::String("GPU-00000000-0000-0000-0000-000000000000".to_string()),
-    );
+    const FUNCTION: &str = "gpu.list_devices";
+    let devices = backend_for(FUNCTION)?
+        .devices()
+        .map_err(|e| backend_error(FUNCTION, e))?;
 
-    Ok(Value::List(vec![Value::Map(device)]))
+    Ok(Value::List(
+        devices
+            .into_iter()
+            .map(|device| {
+                let mut info = HashMap::new();
+                info.insert("id".to_string(), Value::Int(i64::from(device.index)));
+                info.insert("name".to_string(), Value::String(device.name));
+                info.insert(
+                    "uuid".to_string(),
+                    device.uuid.map_or(Value::Null, Value::String),
+                );
+                Value::Map(info)
+            })
+            .collect(),
+    ))
 }
 
 /// Get GPU utilization percentage.
@@ -68,19 +139,13 @@ pub fn list_devices(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 ///
 /// Float representing utilization percentage (0.0 - 100.0)
 pub fn utilization(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    let device_id = args
-        .first()
-        .and_then(|v| v.as_int())
-        .ok_or_else(|| Error::host_function("gpu.utilization: missing device_id argument"))?;
+    const FUNCTION: &str = "gpu.utilization";
+    let (backend, _, index) = device(FUNCTION, args)?;
+    let percent = backend
+        .utilization(index)
+        .map_err(|e| backend_error(FUNCTION, e))?;
 
-    // TODO: Implement using NVML bindings
-    tracing::debug!(
-        "gpu.utilization: device_id={}, returning mock data",
-        device_id
-    );
-
-    // Mock data
-    Ok(Value::Float(42.5))
+    Ok(Value::Float(percent))
 }
 
 /// Get GPU memory information.
@@ -98,26 +163,16 @@ pub fn utilization(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 ///
 /// Map with memory statistics
 pub fn memory_info(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    let device_id = args
-        .first()
-        .and_then(|v| v.as_int())
-        .ok_or_else(|| Error::host_function("gpu.memory_info: missing device_id argument"))?;
-
-    // TODO: Implement using NVML bindings
-    tracing::debug!(
-        "gpu.memory_info: device_id={}, returning mock data",
-        device_id
-    );
-
-    // Mock data (16GB GPU)
-    let total = 17179869184i64; // 16 GB
-    let used = 8589934592i64; // 8 GB
-    let free = total - used;
+    const FUNCTION: &str = "gpu.memory_info";
+    let (backend, _, index) = device(FUNCTION, args)?;
+    let memory = backend
+        .memory(index)
+        .map_err(|e| backend_error(FUNCTION, e))?;
 
     let mut info = HashMap::new();
-    info.insert("total".to_string(), Value::Int(total));
-    info.insert("used".to_string(), Value::Int(used));
-    info.insert("free".to_string(), Value::Int(free));
+    info.insert("total".to_string(), Value::Int(memory.total as i64));
+    info.insert("used".to_string(), Value::Int(memory.used as i64));
+    info.insert("free".to_string(), Value::Int(memory.free as i64));
 
     Ok(Value::Map(info))
 }
@@ -132,23 +187,20 @@ pub fn memory_info(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 ///
 /// Float representing temperature in Celsius
 pub fn temperature(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    let device_id = args
-        .first()
-        .and_then(|v| v.as_int())
-        .ok_or_else(|| Error::host_function("gpu.temperature: missing device_id argument"))?;
+    const FUNCTION: &str = "gpu.temperature";
+    let (backend, _, index) = device(FUNCTION, args)?;
+    let celsius = backend
+        .temperature(index)
+        .map_err(|e| backend_error(FUNCTION, e))?;
 
-    // TODO: Implement using NVML bindings
-    tracing::debug!(
-        "gpu.temperature: device_id={}, returning mock data",
-        device_id
-    );
-
-    // Mock data
-    Ok(Value::Float(65.0))
+    Ok(Value::Float(celsius))
 }
 
 /// Get GPU power usage in watts.
 ///
+/// The reading is recorded in `meter` as a power sample for
+/// `gpu.energy`.
+///
 /// # Arguments
 ///
 /// * `args[0]` - Device ID (integer)
@@ -156,28 +208,23 @@ pub fn temperature(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 /// # Returns
 ///
 /// Float representing power usage in watts
-pub fn power_usage(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    let device_id = args
-        .first()
-        .and_then(|v| v.as_int())
-        .ok_or_else(|| Error::host_function("gpu.power_usage: missing device_id argument"))?;
+pub fn power_usage(meter: &EnergyMeter, args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "gpu.power_usage";
+    let (backend, device_id, index) = device(FUNCTION, args)?;
+    let watts = backend
+        .power_watts(index)
+        .map_err(|e| backend_error(FUNCTION, e))?;
 
-    // TODO: Implement using NVML bindings
-    tracing::debug!(
-        "gpu.power_usage: device_id={}, returning mock data",
-        device_id
-    );
-
-    // Mock data (250W)
-    Ok(Value::Float(250.0))
+    meter.record(device_id, watts);
+    Ok(Value::Float(watts))
 }
 
 /// Get GPU clock speeds.
 ///
-/// Returns a map with:
+/// Returns a map with those of these clocks the device reports:
 /// - `graphics`: Graphics clock in MHz
 /// - `memory`: Memory clock in MHz
-/// - `sm`: SM (streaming multiprocessor) clock in MHz
+/// - `sm`: SM (streaming multiprocessor) clock in MHz (NVIDIA only)
 ///
 /// # Arguments
 ///
@@ -187,21 +234,284 @@ pub fn power_usage(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 ///
 /// Map with clock speeds
 pub fn clock_speeds(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    let device_id = args
-        .first()
-        .and_then(|v| v.as_int())
-        .ok_or_else(|| Error::host_function("gpu.clock_speeds: missing device_id argument"))?;
-
-    // TODO: Implement using NVML bindings
-    tracing::debug!(
-        "gpu.clock_speeds: device_id={}, returning mock data",
-        device_id
-    );
+    const FUNCTION: &str = "gpu.clock_speeds";
+    let (backend, _, index) = device(FUNCTION, args)?;
+    let speeds = backend
+        .clocks(index)
+        .map_err(|e| backend_error(FUNCTION, e))?;
 
     let mut clocks = HashMap::new();
-    clocks.insert("graphics".to_string(), Value::Int(1500));
-    clocks.insert("memory".to_string(), Value::Int(7000));
-    clocks.insert("sm".to_string(), Value::Int(1500));
+    for (key, mhz) in [
+        ("graphics", speeds.graphics),
+        ("memory", speeds.memory),
+        ("sm", speeds.sm),
+    ] {
+        if let Some(mhz) = mhz {
+            clocks.insert(key.to_string(), Value::Int(i64::from(mhz)));
+        }
+    }
 
     Ok(Value::Map(clocks))
 }
+
+/// List the processes using a device.
+///
+/// Returns a list of maps, largest memory user first:
+/// - `pid`: Process ID
+/// - `name`: Process name, or null if it cannot be read
+/// - `used_memory`: Device memory in bytes, or null if the driver does not
+///   report it (always the case for NVIDIA under Windows WDDM)
+/// - `type`: `"compute"`, `"graphics"`, or `"compute+graphics"`
+///
+/// # Arguments
+///
+/// * `args[0]` - Device ID (integer)
+///
+/// # Returns
+///
+/// List of process maps
+pub fn processes(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "gpu.processes";
+    let (backend, _, index) = device(FUNCTION, args)?;
+    let processes = backend
+        .processes(index)
+        .map_err(|e| backend_error(FUNCTION, e))?;
+
+    let list = processes
+        .into_iter()
+        .map(|process| {
+            let kind = match (process.compute, process.graphics) {
+                (true, true) => "compute+graphics",
+                (true, false) => "compute",
+                _ => "graphics",
+            };
+            let mut info = HashMap::new();
+            info.insert("pid".to_string(), Value::Int(i64::from(process.pid)));
+            info.insert(
+                "name".to_string(),
+                process.name.map_or(Value::Null, Value::String),
+            );
+            info.insert(
+                "used_memory".to_string(),
+                process
+                    .used_memory
+                    .map_or(Value::Null, |bytes| Value::Int(bytes as i64)),
+            );
+            info.insert("type".to_string(), Value::String(kind.to_string()));
+            Value::Map(info)
+        })
+        .collect();
+
+    Ok(Value::List(list))
+}
+
+/// Get driver version information.
+///
+/// Returns a map with `backend` (the backend name) and the backend's
+/// driver details:
+/// - NVML: `driver_version`, `cuda_version` (e.g. `"12.4"`), `cuda_major`,
+///   `cuda_minor`, and `nvml_version`
+/// - ROCm: `driver` (`"amdgpu"`) and `driver_version`
+///
+/// # Returns
+///
+/// Map with driver information
+pub fn driver_info(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "gpu.driver_info";
+    let backend = backend_for(FUNCTION)?;
+    let mut info = backend
+        .driver_info()
+        .map_err(|e| backend_error(FUNCTION, e))?;
+    info.insert(
+        "backend".to_string(),
+        Value::String(backend.name().to_string()),
+    );
+
+    Ok(Value::Map(info))
+}
+
+/// Get static hardware information for a device.
+///
+/// Returns a map with the backend's hardware details:
+/// - NVML: `compute_capability` (e.g. `"8.0"`), `compute_major`,
+///   `compute_minor`, `vbios_version`, and `pcie`
+/// - ROCm: `vbios_version` and `pcie`
+///
+/// where `pcie` is a map with `bus_id`, `generation`, `max_generation`,
+/// `link_width`, and `max_link_width`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Device ID (integer)
+///
+/// # Returns
+///
+/// Map with device information
+pub fn device_info(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "gpu.device_info";
+    let (backend, _, index) = device(FUNCTION, args)?;
+    let info = backend
+        .device_info(index)
+        .map_err(|e| backend_error(FUNCTION, e))?;
+
+    Ok(Value::Map(info))
+}
+
+/// Describe how devices connect to the CPUs and to each other, for
+/// placing work on well-connected device sets.
+///
+/// Returns a map with:
+/// - `devices`: List of maps with `id`, `numa_node` (null on single-node
+///   machines) and `cpus`, the CPU numbers local to the device
+/// - `links`: Matrix of connection types, where `links[a][b]` is one of
+///   `"nvlink"`, `"xgmi"`, `"pix"` (one PCIe switch), `"pxb"` (several
+///   switches), `"phb"` (host bridge), `"node"` (across host bridges) or
+///   `"sys"` (across NUMA nodes), and `"self"` on the diagonal
+/// - `bandwidth`: Matrix of peak one-way bandwidth in GB/s, or null where
+///   unknown and on the diagonal
+///
+/// # Returns
+///
+/// Map with the topology
+pub fn topology(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "gpu.topology";
+    let devices = backend_for(FUNCTION)?
+        .topology()
+        .map_err(|e| backend_error(FUNCTION, e))?;
+
+    Ok(topology_value(&devices))
+}
+
+/// Convert a topology to the `gpu.topology` map.
+fn topology_value(devices: &[DeviceTopology]) -> Value {
+    let position = |index: u32| devices.iter().position(|d| d.index == index);
+    let mut list = Vec::with_capacity(devices.len());
+    let mut links = Vec::with_capacity(devices.len());
+    let mut bandwidth = Vec::with_capacity(devices.len());
+    for (row, device) in devices.iter().enumerate() {
+        let mut info = HashMap::new();
+        info.insert("id".to_string(), Value::Int(i64::from(device.index)));
+        info.insert(
+            "numa_node".to_string(),
+            device
+                .numa_node
+                .map_or(Value::Null, |node| Value::Int(i64::from(node))),
+        );
+        info.insert(
+            "cpus".to_string(),
+            Value::List(
+                device
+                    .cpus
+                    .iter()
+                    .map(|cpu| Value::Int(i64::from(*cpu)))
+                    .collect(),
+            ),
+        );
+        list.push(Value::Map(info));
+
+        let mut kinds = vec![Value::Null; devices.len()];
+        let mut speeds = vec![Value::Null; devices.len()];
+        kinds[row] = Value::String("self".to_string());
+        for link in &device.links {
+            if let Some(column) = position(link.peer) {
+                kinds[column] = Value::String(link.kind.as_str().to_string());
+                speeds[column] = link.bandwidth_gbps.map_or(Value::Null, Value::Float);
+            }
+        }
+        links.push(Value::List(kinds));
+        bandwidth.push(Value::List(speeds));
+    }
+
+    let mut topology = HashMap::new();
+    topology.insert("devices".to_string(), Value::List(list));
+    topology.insert("links".to_string(), Value::List(links));
+    topology.insert("bandwidth".to_string(), Value::List(bandwidth));
+    Value::Map(topology)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_topology_value() {
+        let device = |index, peer, kind, bandwidth_gbps| DeviceTopology {
+            index,
+            numa_node: Some(index),
+            cpus: vec![index * 2, index * 2 + 1],
+            links: vec![GpuLink {
+                peer,
+                kind,
+                bandwidth_gbps,
+            }],
+        };
+        let value = topology_value(&[
+            device(0, 1, LinkKind::NvLink, Some(300.0)),
+            device(1, 0, LinkKind::NvLink, Some(300.0)),
+        ]);
+        let topology = value.as_map().unwrap();
+
+        let devices = topology["devices"].as_list().unwrap();
+        let second = devices[1].as_map().unwrap();
+        assert_eq!(second["numa_node"], Value::Int(1));
+        assert_eq!(
+            second["cpus"],
+            Value::List(vec![Value::Int(2), Value::Int(3)])
+        );
+        assert_eq!(
+            topology["links"],
+            Value::List(vec![
+                Value::List(vec![
+                    Value::String("self".into()),
+                    Value::String("nvlink".into())
+                ]),
+                Value::List(vec![
+                    Value::String("nvlink".into()),
+                    Value::String("self".into())
+                ]),
+            ])
+        );
+        assert_eq!(
+            topology["bandwidth"].as_list().unwrap()[0],
+            Value::List(vec![Value::Null, Value::Float(300.0)])
+        );
+    }
+
+    #[test]
+    fn test_unavailable_backend_is_an_error() {
+        let ctx = ctx();
+        let available = is_available(&[], &ctx).unwrap() == Value::Bool(true);
+        assert_eq!(available, backend().is_ok());
+        if available {
+            // Real hardware: the probe and the listing agree.
+            assert!(matches!(backend_name(&[], &ctx).unwrap(), Value::String(_)));
+            list_devices(&[], &ctx).unwrap();
+            return;
+        }
+
+        assert!(matches!(
+            backend(),
+            Err(crate::Error::ModuleNotAvailable(_))
+        ));
+        assert_eq!(backend_name(&[], &ctx).unwrap(), Value::Null);
+        let err = temperature(&[Value::Int(0)], &ctx).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("gpu.temperature: module not available"));
+        assert!(list_devices(&[], &ctx).is_err());
+
+        // Argument errors are still reported first.
+        let err = utilization(&[Value::Int(-1)], &ctx).unwrap_err();
+        assert!(err.to_string().contains("invalid device_id -1"));
+    }
+}
@@ -0,0 +1,320 @@
+//! GPU vendor backends.
+//!
+//! The `gpu` host functions read devices through a [`GpuBackend`]. One is
+//! chosen on first use by [`backend()`]:
+//!
+//! - [`NvmlBackend`](super::nvml::NvmlBackend) - NVIDIA, through NVML
+//! - [`RocmBackend`](super::rocm::RocmBackend) - AMD, through the `amdgpu`
+//!   driver's sysfs interface (the same files `rocm-smi` reads)
+//! - [`MetalBackend`](super::metal::MetalBackend) - Apple, through the
+//!   IOKit registry on macOS
+//!
+//! The first backend that finds a device wins, so the same script works on
+//! NVIDIA, AMD and Apple machines. Set `FUSABI_GPU_BACKEND` to `nvml`,
+//! `rocm` or `metal` to pick one explicitly.
+//!
+//! Readings a backend cannot provide (Apple GPUs report no temperature or
+//! power without root, for instance) fail with
+//! [`Error::ModuleNotAvailable`](crate::Error::ModuleNotAvailable).
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use fusabi_host::Value;
+
+/// Environment variable that selects a backend by name.
+pub const BACKEND_ENV: &str = "FUSABI_GPU_BACKEND";
+
+/// A device as listed by a backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSummary {
+    /// Index used to address the device.
+    pub index: u32,
+    /// Product name.
+    pub name: String,
+    /// Stable identifier, when the vendor provides one.
+    pub uuid: Option<String>,
+}
+
+/// Device memory, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryInfo {
+    /// Total memory.
+    pub total: u64,
+    /// Used memory.
+    pub used: u64,
+    /// Free memory.
+    pub free: u64,
+}
+
+/// Current clock speeds in MHz; clocks a device does not report are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClockSpeeds {
+    /// Graphics (shader) clock.
+    pub graphics: Option<u32>,
+    /// Memory clock.
+    pub memory: Option<u32>,
+    /// Streaming multiprocessor clock (NVIDIA).
+    pub sm: Option<u32>,
+}
+
+/// A process using a device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuProcess {
+    /// Process ID.
+    pub pid: u32,
+    /// Process name, if it could be read.
+    pub name: Option<String>,
+    /// Device memory in bytes, if the driver reports it.
+    pub used_memory: Option<u64>,
+    /// Whether the process runs compute work.
+    pub compute: bool,
+    /// Whether the process runs graphics work.
+    pub graphics: bool,
+}
+
+/// How two devices are connected, from the fastest path to the slowest.
+///
+/// The PCIe levels follow `nvidia-smi topo -m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LinkKind {
+    /// NVIDIA NVLink, directly or through an NVSwitch.
+    NvLink,
+    /// AMD Infinity Fabric (xGMI).
+    Xgmi,
+    /// PCIe through at most one switch (`PIX`).
+    PcieSwitch,
+    /// PCIe through several switches, without a host bridge (`PXB`).
+    PcieSwitches,
+    /// PCIe through a host bridge (`PHB`).
+    HostBridge,
+    /// PCIe across host bridges within a NUMA node (`NODE`).
+    Node,
+    /// PCIe across NUMA nodes, over the CPU interconnect (`SYS`).
+    System,
+}
+
+impl LinkKind {
+    /// Short name: `"nvlink"`, `"xgmi"`, `"pix"`, `"pxb"`, `"phb"`,
+    /// `"node"` or `"sys"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LinkKind::NvLink => "nvlink",
+            LinkKind::Xgmi => "xgmi",
+            LinkKind::PcieSwitch => "pix",
+            LinkKind::PcieSwitches => "pxb",
+            LinkKind::HostBridge => "phb",
+            LinkKind::Node => "node",
+            LinkKind::System => "sys",
+        }
+    }
+}
+
+/// The connection from a device to one of its peers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpuLink {
+    /// Index of the peer device.
+    pub peer: u32,
+    /// How the devices are connected.
+    pub kind: LinkKind,
+    /// Peak one-way bandwidth in GB/s, if known. For PCIe this is the
+    /// slower of the two devices' links; traffic crossing host bridges or
+    /// sockets may see less.
+    pub bandwidth_gbps: Option<f64>,
+}
+
+/// Where a device sits relative to the CPUs and the other devices.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceTopology {
+    /// Index of the device.
+    pub index: u32,
+    /// NUMA node the device is attached to, if the machine has several.
+    pub numa_node: Option<u32>,
+    /// CPUs local to the device, ascending.
+    pub cpus: Vec<u32>,
+    /// Connections to every other device.
+    pub links: Vec<GpuLink>,
+}
+
+/// A source of GPU readings.
+///
+/// Devices are addressed by index, from 0 to the number of devices listed
+/// by [`devices`](Self::devices). Only the device list, utilization and
+/// memory are required; the other readings default to "not supported".
+pub trait GpuBackend: Send + Sync {
+    /// Short backend name: `"nvml"`, `"rocm"` or `"metal"`.
+    fn name(&self) -> &'static str;
+
+    /// List devices.
+    fn devices(&self) -> crate::Result<Vec<DeviceSummary>>;
+
+    /// Utilization percentage (0.0 - 100.0).
+    fn utilization(&self, index: u32) -> crate::Result<f64>;
+
+    /// Memory usage.
+    fn memory(&self, index: u32) -> crate::Result<MemoryInfo>;
+
+    /// Temperature in Celsius.
+    fn temperature(&self, _index: u32) -> crate::Result<f64> {
+        Err(unsupported(self.name(), "temperature"))
+    }
+
+    /// Power draw in watts.
+    fn power_watts(&self, _index: u32) -> crate::Result<f64> {
+        Err(unsupported(self.name(), "power usage"))
+    }
+
+    /// Clock speeds.
+    fn clocks(&self, _index: u32) -> crate::Result<ClockSpeeds> {
+        Err(unsupported(self.name(), "clock speeds"))
+    }
+
+    /// Processes using the device, largest memory user first.
+    fn processes(&self, _index: u32) -> crate::Result<Vec<GpuProcess>> {
+        Err(unsupported(self.name(), "process listing"))
+    }
+
+    /// Driver information for `gpu.driver_info`; keys are vendor specific.
+    fn driver_info(&self) -> crate::Result<HashMap<String, Value>> {
+        Err(unsupported(self.name(), "driver information"))
+    }
+
+    /// Hardware information for `gpu.device_info`; keys are vendor
+    /// specific.
+    fn device_info(&self, _index: u32) -> crate::Result<HashMap<String, Value>> {
+        Err(unsupported(self.name(), "device information"))
+    }
+
+    /// NUMA affinity and interconnects of every device, in index order.
+    fn topology(&self) -> crate::Result<Vec<DeviceTopology>> {
+        Err(unsupported(self.name(), "topology"))
+    }
+}
+
+/// Error for a reading `backend` cannot provide.
+pub fn unsupported(backend: &str, what: &str) -> crate::Error {
+    crate::Error::ModuleNotAvailable(format!("gpu: {} not supported by {}", what, backend))
+}
+
+/// Peak one-way bandwidth in GB/s of a PCIe link of `generation` and
+/// `width` lanes, after line encoding.
+pub fn pcie_bandwidth(generation: u32, width: u32) -> Option<f64> {
+    const PER_LANE: [f64; 6] = [0.25, 0.5, 0.985, 1.969, 3.938, 7.563];
+    let per_lane = PER_LANE.get(usize::try_from(generation).ok()?.checked_sub(1)?)?;
+    (width > 0).then(|| per_lane * f64::from(width))
+}
+
+/// NUMA node and local CPUs of a PCI device, read from its sysfs
+/// directory (`numa_node` and `local_cpulist`).
+pub fn pci_locality(device: &Path) -> (Option<u32>, Vec<u32>) {
+    let read = |file| std::fs::read_to_string(device.join(file)).ok();
+    // -1 when the machine has a single node
+    let numa_node = read("numa_node").and_then(|node| node.trim().parse().ok());
+    let cpus = read("local_cpulist")
+        .and_then(|list| parse_cpulist(&list))
+        .unwrap_or_default();
+    (numa_node, cpus)
+}
+
+/// Parse a kernel CPU list such as `0-7,16-23`.
+pub fn parse_cpulist(list: &str) -> Option<Vec<u32>> {
+    let mut cpus = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => cpus.extend(first.parse::<u32>().ok()?..=last.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+/// Error for a device index a backend does not have.
+pub fn no_device(index: u32) -> crate::Error {
+    crate::Error::invalid_argument(format!("no GPU device {}", index))
+}
+
+/// The process-wide backend, selected on first use.
+///
+/// The selection is made once; if no backend is usable, every later call
+/// reports the same failure without probing again.
+///
+/// # Errors
+///
+/// [`crate::Error::ModuleNotAvailable`] when no backend can be loaded, with
+/// the reason each one failed.
+pub fn backend() -> crate::Result<&'static dyn GpuBackend> {
+    static BACKEND: OnceLock<std::result::Result<Box<dyn GpuBackend>, String>> = OnceLock::new();
+    BACKEND
+        .get_or_init(|| select(std::env::var(BACKEND_ENV).ok().as_deref()))
+        .as_ref()
+        .map(|b| b.as_ref())
+        .map_err(|e| crate::Error::ModuleNotAvailable(format!("gpu: {}", e)))
+}
+
+type Probe = fn() -> crate::Result<Box<dyn GpuBackend>>;
+
+fn probes() -> [(&'static str, Probe); 3] {
+    [
+        ("nvml", || {
+            Ok(Box::new(super::nvml::NvmlBackend::new()?) as Box<dyn GpuBackend>)
+        }),
+        ("rocm", || Ok(Box::new(super::rocm::RocmBackend::new()?))),
+        ("metal", || Ok(Box::new(super::metal::MetalBackend::new()?))),
+    ]
+}
+
+/// Load the backend named `preferred`, or else the first that finds a
+/// device, or else the first that loads at all.
+fn select(preferred: Option<&str>) -> std::result::Result<Box<dyn GpuBackend>, String> {
+    let preferred = preferred.map(str::trim).filter(|name| !name.is_empty());
+    if let Some(name) = preferred {
+        let (_, probe) = probes()
+            .into_iter()
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown backend {:?} in {}", name, BACKEND_ENV))?;
+        return probe().map_err(|e| format!("{}: {}", name, e));
+    }
+
+    let mut fallback = None;
+    let mut failures = Vec::new();
+    for (name, probe) in probes() {
+        match probe() {
+            Ok(backend) if backend.devices().is_ok_and(|d| !d.is_empty()) => return Ok(backend),
+            Ok(backend) => {
+                fallback.get_or_insert(backend);
+            }
+            Err(e) => failures.push(format!("{}: {}", name, e)),
+        }
+    }
+    fallback.ok_or_else(|| format!("no GPU backend available ({})", failures.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_unknown_backend() {
+        let err = select(Some("cuda")).err().unwrap();
+        assert!(err.contains("unknown backend \"cuda\""));
+    }
+
+    #[test]
+    fn test_parse_cpulist() {
+        assert_eq!(
+            parse_cpulist("0-3,8,10-11\n"),
+            Some(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpulist(""), Some(vec![]));
+        assert_eq!(parse_cpulist("0-x"), None);
+    }
+
+    #[test]
+    fn test_pcie_bandwidth() {
+        assert_eq!(pcie_bandwidth(4, 16), Some(1.969 * 16.0));
+        assert_eq!(pcie_bandwidth(0, 16), None);
+        assert_eq!(pcie_bandwidth(9, 16), None);
+        assert_eq!(pcie_bandwidth(3, 0), None);
+    }
+}
@@ -0,0 +1,359 @@
+//! GPU energy accounting.
+//!
+//! [`EnergyMeter`] integrates power samples (watts) over time into
+//! cumulative energy per device using the trapezoid rule, so energy use can
+//! be reported for any window (`gpu.energy(device, since)`) and charged back.
+//! Every `gpu.power_usage` reading is recorded as a sample.
+//!
+//! With the `metrics` feature, a meter can also count whole joules into a
+//! `gpu_energy_joules_total{device="N"}` counter.
+
+use std::collections::{HashMap, VecDeque};
+#[cfg(feature = "metrics")]
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+/// Joules per kilowatt-hour.
+pub const JOULES_PER_KWH: f64 = 3_600_000.0;
+
+/// Counter that energy is recorded into with the `metrics` feature.
+pub const ENERGY_COUNTER: &str = "gpu_energy_joules_total";
+
+/// A power sample with the energy accumulated up to it.
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at_ms: i64,
+    watts: f64,
+    joules: f64,
+    /// Whether the interval ending at this sample was integrated. Intervals
+    /// longer than the maximum gap count as missing data.
+    integrated: bool,
+}
+
+#[derive(Debug, Default)]
+struct DeviceSamples {
+    samples: VecDeque<Sample>,
+    /// Fraction of a joule not yet counted into the metrics counter.
+    #[cfg(feature = "metrics")]
+    uncounted: f64,
+}
+
+/// Energy used by a device over a window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnergyReport {
+    /// Start of the window covered by samples (Unix milliseconds).
+    pub since_ms: i64,
+    /// End of the window covered by samples (Unix milliseconds).
+    pub until_ms: i64,
+    /// Energy in joules.
+    pub joules: f64,
+    /// Number of samples in the window.
+    pub samples: usize,
+}
+
+impl EnergyReport {
+    /// Energy in kilowatt-hours.
+    pub fn kwh(&self) -> f64 {
+        self.joules / JOULES_PER_KWH
+    }
+
+    /// Average power over the window in watts.
+    pub fn average_watts(&self) -> f64 {
+        let seconds = (self.until_ms - self.since_ms) as f64 / 1000.0;
+        if seconds > 0.0 {
+            self.joules / seconds
+        } else {
+            0.0
+        }
+    }
+
+    /// Convert to a Value map with `joules`, `kwh`, `average_watts`,
+    /// `since_ms`, `until_ms`, and `samples`.
+    pub fn to_value(&self) -> Value {
+        let mut m = HashMap::new();
+        m.insert("joules".to_string(), Value::Float(self.joules));
+        m.insert("kwh".to_string(), Value::Float(self.kwh()));
+        m.insert(
+            "average_watts".to_string(),
+            Value::Float(self.average_watts()),
+        );
+        m.insert("since_ms".to_string(), Value::Int(self.since_ms));
+        m.insert("until_ms".to_string(), Value::Int(self.until_ms));
+        m.insert("samples".to_string(), Value::Int(self.samples as i64));
+        Value::Map(m)
+    }
+}
+
+/// Cumulative energy per device, integrated from power samples.
+pub struct EnergyMeter {
+    devices: Mutex<HashMap<i64, DeviceSamples>>,
+    max_gap: Duration,
+    retention: Duration,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<crate::metrics::MetricsRegistry>>,
+}
+
+impl Default for EnergyMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EnergyMeter {
+    /// Create a meter that bridges gaps of up to 5 minutes and keeps 24
+    /// hours of samples.
+    pub fn new() -> Self {
+        Self {
+            devices: Mutex::new(HashMap::new()),
+            max_gap: Duration::from_secs(300),
+            retention: Duration::from_secs(24 * 3600),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Longest interval between samples that is integrated. Longer gaps
+    /// (a stopped sampler, a suspended host) count as no energy rather than
+    /// extrapolating.
+    pub fn with_max_gap(mut self, max_gap: Duration) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    /// How long samples are kept. Windows starting earlier are reported
+    /// from the oldest sample kept.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Count energy into the `gpu_energy_joules_total` counter of
+    /// `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<crate::metrics::MetricsRegistry>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record a power sample taken now.
+    pub fn record(&self, device: i64, watts: f64) {
+        self.record_at(device, now_ms(), watts);
+    }
+
+    /// Record a power sample taken at `at_ms` (Unix milliseconds).
+    ///
+    /// Samples older than the device's latest sample are ignored.
+    pub fn record_at(&self, device: i64, at_ms: i64, watts: f64) {
+        if !watts.is_finite() || watts < 0.0 {
+            return;
+        }
+
+        let mut devices = self.devices.lock();
+        let entry = devices.entry(device).or_default();
+
+        let (joules, integrated) = match entry.samples.back() {
+            Some(last) if at_ms < last.at_ms => return,
+            Some(last) => {
+                let elapsed = Duration::from_millis((at_ms - last.at_ms) as u64);
+                if elapsed <= self.max_gap {
+                    let added = (last.watts + watts) / 2.0 * elapsed.as_secs_f64();
+                    (last.joules + added, true)
+                } else {
+                    (last.joules, false)
+                }
+            }
+            None => (0.0, false),
+        };
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            let previous = entry.samples.back().map_or(0.0, |s| s.joules);
+            entry.uncounted += joules - previous;
+            let whole = entry.uncounted.floor();
+            if whole >= 1.0 {
+                entry.uncounted -= whole;
+                let labels =
+                    crate::metrics::Labels::from([("device".to_string(), device.to_string())]);
+                metrics.counter_inc_with(ENERGY_COUNTER, &labels, whole as u64);
+            }
+        }
+
+        entry.samples.push_back(Sample {
+            at_ms,
+            watts,
+            joules,
+            integrated,
+        });
+
+        let cutoff = at_ms - self.retention.as_millis() as i64;
+        while entry.samples.len() > 2 && entry.samples[1].at_ms <= cutoff {
+            entry.samples.pop_front();
+        }
+    }
+
+    /// Energy used by a device from `since_ms` until now.
+    pub fn energy(&self, device: i64, since_ms: i64) -> Option<EnergyReport> {
+        self.energy_between(device, since_ms, i64::MAX)
+    }
+
+    /// Energy used by a device between two Unix millisecond timestamps,
+    /// clamped to the sampled range. Returns `None` if the device has no
+    /// samples.
+    pub fn energy_between(
+        &self,
+        device: i64,
+        since_ms: i64,
+        until_ms: i64,
+    ) -> Option<EnergyReport> {
+        let devices = self.devices.lock();
+        let samples = &devices.get(&device)?.samples;
+        let first = samples.front()?;
+        let last = samples.back()?;
+
+        let since_ms = since_ms.clamp(first.at_ms, last.at_ms);
+        let until_ms = until_ms.clamp(since_ms, last.at_ms);
+        let joules = cumulative_at(samples, until_ms) - cumulative_at(samples, since_ms);
+        let count = samples
+            .iter()
+            .filter(|s| s.at_ms >= since_ms && s.at_ms <= until_ms)
+            .count();
+
+        Some(EnergyReport {
+            since_ms,
+            until_ms,
+            joules,
+            samples: count,
+        })
+    }
+
+    /// Devices with samples, sorted.
+    pub fn devices(&self) -> Vec<i64> {
+        let mut devices: Vec<i64> = self.devices.lock().keys().copied().collect();
+        devices.sort_unstable();
+        devices
+    }
+}
+
+/// Energy accumulated up to `at_ms`, interpolating power linearly within
+/// an integrated interval.
+fn cumulative_at(samples: &VecDeque<Sample>, at_ms: i64) -> f64 {
+    let index = samples.partition_point(|s| s.at_ms <= at_ms);
+    if index == 0 {
+        return samples.front().map_or(0.0, |s| s.joules);
+    }
+    let before = samples[index - 1];
+    let Some(after) = samples.get(index).filter(|s| s.integrated) else {
+        return before.joules;
+    };
+
+    let span = (after.at_ms - before.at_ms) as f64;
+    let elapsed = (at_ms - before.at_ms) as f64;
+    let watts_at = before.watts + (after.watts - before.watts) * elapsed / span;
+    before.joules + (before.watts + watts_at) / 2.0 * elapsed / 1000.0
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Get the energy a device has used.
+///
+/// Energy is integrated from `gpu.power_usage` readings, so it only covers
+/// periods in which power was sampled.
+///
+/// # Arguments
+///
+/// * `args[0]` - Device ID (integer)
+/// * `args[1]` - Window start as a Unix timestamp in milliseconds (optional,
+///   default: the oldest sample kept)
+///
+/// # Returns
+///
+/// Map with `joules`, `kwh`, `average_watts`, `since_ms`, `until_ms`, and
+/// `samples`, or null if the device has not been sampled
+pub fn energy(meter: &EnergyMeter, args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let device_id = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function("gpu.energy: missing device_id argument"))?;
+
+    let since = match args.get(1) {
+        None | Some(Value::Null) => i64::MIN,
+        Some(value) => value
+            .as_int()
+            .ok_or_else(|| Error::host_function("gpu.energy: since must be a timestamp"))?,
+    };
+
+    Ok(meter
+        .energy(device_id, since)
+        .map_or(Value::Null, |report| report.to_value()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_integration() {
+        let meter = EnergyMeter::new().with_max_gap(Duration::from_secs(60));
+        // 100 W rising to 300 W over 10 s: 2000 J.
+        meter.record_at(0, 0, 100.0);
+        meter.record_at(0, 10_000, 300.0);
+        // Constant 300 W for 10 s: 3000 J.
+        meter.record_at(0, 20_000, 300.0);
+
+        let total = meter.energy(0, 0).unwrap();
+        assert!((total.joules - 5000.0).abs() < 1e-9);
+        assert_eq!(total.samples, 3);
+        assert_eq!(total.until_ms, 20_000);
+        assert!((total.average_watts() - 250.0).abs() < 1e-9);
+
+        // From 5 s: power 200 W at 5 s, so 1250 J then 3000 J.
+        let window = meter.energy(0, 5_000).unwrap();
+        assert!((window.joules - 4250.0).abs() < 1e-9);
+
+        // Gaps beyond the maximum are not integrated.
+        meter.record_at(0, 200_000, 300.0);
+        assert!((meter.energy(0, 0).unwrap().joules - 5000.0).abs() < 1e-9);
+
+        // Out-of-order and invalid samples are ignored.
+        meter.record_at(0, 100, 1_000.0);
+        meter.record_at(0, 210_000, f64::NAN);
+        assert_eq!(meter.energy(0, 0).unwrap().samples, 4);
+
+        assert!(meter.energy(1, 0).is_none());
+        assert_eq!(meter.devices(), vec![0]);
+    }
+
+    #[test]
+    fn test_energy_retention() {
+        let meter = EnergyMeter::new().with_retention(Duration::from_secs(10));
+        for i in 0..=30 {
+            meter.record_at(0, i * 1000, 100.0);
+        }
+        let report = meter.energy(0, 0).unwrap();
+        assert_eq!(report.since_ms, 20_000);
+        assert!((report.joules - 1000.0).abs() < 1e-9);
+        assert!((report.kwh() - 1000.0 / JOULES_PER_KWH).abs() < 1e-12);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_energy_counter() {
+        let metrics = Arc::new(crate::metrics::MetricsRegistry::new());
+        let meter = EnergyMeter::new().with_metrics(metrics.clone());
+        meter.record_at(3, 0, 10.0);
+        meter.record_at(3, 250, 10.0);
+        meter.record_at(3, 1_000, 10.0);
+
+        let labels = crate::metrics::Labels::from([("device".to_string(), "3".to_string())]);
+        assert_eq!(metrics.counter_get_with(ENERGY_COUNTER, &labels), 10);
+    }
+}
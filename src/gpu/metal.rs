@@ -0,0 +1,248 @@
+//! Apple backend, through the IOKit registry on macOS.
+//!
+//! Every Metal device is an `IOAccelerator` in the IOKit registry, whose
+//! `PerformanceStatistics` dictionary carries the utilization and memory
+//! counters Activity Monitor shows. They are read with `ioreg`, so no
+//! private frameworks are linked. Apple Silicon shares system memory with
+//! the GPU, so its total is the machine's memory.
+//!
+//! Temperature, power and clocks need root (`powermetrics`) and are not
+//! supported.
+
+use std::collections::HashMap;
+use std::process::Command;
+
+use super::backend::{no_device, unsupported, DeviceSummary, GpuBackend, MemoryInfo};
+
+/// An `IOAccelerator` registry entry.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Accelerator {
+    /// The `model` property, or the entry's class name.
+    name: String,
+    /// Numeric `PerformanceStatistics` values.
+    statistics: HashMap<String, u64>,
+}
+
+impl Accelerator {
+    fn statistic(&self, keys: &[&str]) -> Option<u64> {
+        keys.iter()
+            .find_map(|key| self.statistics.get(*key).copied())
+    }
+}
+
+/// Apple (and Intel Mac discrete) GPUs through IOKit.
+#[derive(Debug, Clone)]
+pub struct MetalBackend {
+    /// Machine memory, the GPU memory of unified-memory devices.
+    system_memory: Option<u64>,
+}
+
+impl MetalBackend {
+    /// Check for Metal devices in the IOKit registry.
+    ///
+    /// # Errors
+    ///
+    /// [`crate::Error::ModuleNotAvailable`] on other platforms or when the
+    /// registry lists no accelerator.
+    pub fn new() -> crate::Result<Self> {
+        if !cfg!(target_os = "macos") {
+            return Err(crate::Error::ModuleNotAvailable(
+                "gpu: IOKit is only available on macOS".into(),
+            ));
+        }
+        let backend = Self {
+            system_memory: run("/usr/sbin/sysctl", &["-n", "hw.memsize"])
+                .ok()
+                .and_then(|out| out.trim().parse().ok()),
+        };
+        if backend.accelerators()?.is_empty() {
+            return Err(crate::Error::ModuleNotAvailable(
+                "gpu: no IOAccelerator in the IOKit registry".into(),
+            ));
+        }
+        Ok(backend)
+    }
+
+    fn accelerators(&self) -> crate::Result<Vec<Accelerator>> {
+        let out = run(
+            "/usr/sbin/ioreg",
+            &["-r", "-d", "1", "-w", "0", "-c", "IOAccelerator"],
+        )?;
+        Ok(parse_ioreg(&out))
+    }
+
+    fn accelerator(&self, index: u32) -> crate::Result<Accelerator> {
+        self.accelerators()?
+            .into_iter()
+            .nth(index as usize)
+            .ok_or_else(|| no_device(index))
+    }
+}
+
+impl GpuBackend for MetalBackend {
+    fn name(&self) -> &'static str {
+        "metal"
+    }
+
+    fn devices(&self) -> crate::Result<Vec<DeviceSummary>> {
+        Ok(self
+            .accelerators()?
+            .into_iter()
+            .zip(0..)
+            .map(|(accelerator, index)| DeviceSummary {
+                index,
+                name: accelerator.name,
+                uuid: None,
+            })
+            .collect())
+    }
+
+    fn utilization(&self, index: u32) -> crate::Result<f64> {
+        self.accelerator(index)?
+            .statistic(&["Device Utilization %", "GPU Activity(%)"])
+            .map(|percent| percent as f64)
+            .ok_or_else(|| unsupported("metal", "utilization"))
+    }
+
+    fn memory(&self, index: u32) -> crate::Result<MemoryInfo> {
+        memory_info(&self.accelerator(index)?, self.system_memory)
+            .ok_or_else(|| unsupported("metal", "memory"))
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> crate::Result<String> {
+    let output = Command::new(program).args(args).output()?;
+    if !output.status.success() {
+        return Err(crate::Error::ModuleNotAvailable(format!(
+            "gpu: {} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Memory of a device: dedicated VRAM when it reports it (discrete GPUs in
+/// Intel Macs), otherwise the system memory it shares.
+fn memory_info(accelerator: &Accelerator, system_memory: Option<u64>) -> Option<MemoryInfo> {
+    if let (Some(used), Some(free)) = (
+        accelerator.statistic(&["vramUsedBytes"]),
+        accelerator.statistic(&["vramFreeBytes"]),
+    ) {
+        return Some(MemoryInfo {
+            total: used + free,
+            used,
+            free,
+        });
+    }
+    let used = accelerator.statistic(&["In use system memory", "Alloc system memory"])?;
+    let total = system_memory?;
+    Some(MemoryInfo {
+        total,
+        used,
+        free: total.saturating_sub(used),
+    })
+}
+
+/// Parse `ioreg -r -d 1 -c IOAccelerator` output, one entry per `+-o` line.
+fn parse_ioreg(output: &str) -> Vec<Accelerator> {
+    let mut accelerators: Vec<Accelerator> = Vec::new();
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(entry) = line.strip_prefix("+-o ") {
+            let class = entry.split_whitespace().next().unwrap_or_default();
+            accelerators.push(Accelerator {
+                name: class.to_string(),
+                statistics: HashMap::new(),
+            });
+            continue;
+        }
+        let Some(current) = accelerators.last_mut() else {
+            continue;
+        };
+        let Some((key, value)) = line.split_once(" = ") else {
+            continue;
+        };
+        match key.trim_matches('"') {
+            "model" => current.name = value.trim_matches('"').to_string(),
+            "PerformanceStatistics" => {
+                current.statistics = parse_dictionary(value);
+            }
+            _ => {}
+        }
+    }
+    accelerators
+}
+
+/// Numeric entries of a one-line `{"key"=value,...}` dictionary. Keys may
+/// contain commas and spaces but not quotes.
+fn parse_dictionary(text: &str) -> HashMap<String, u64> {
+    let mut values = HashMap::new();
+    let mut rest = text.trim().trim_start_matches('{');
+    while let Some(start) = rest.find('"') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('"') else {
+            break;
+        };
+        let key = &after[..end];
+        let value_text = after[end + 1..].trim_start().trim_start_matches('=');
+        let value_end = value_text.find([',', '}']).unwrap_or(value_text.len());
+        if let Ok(value) = value_text[..value_end].trim().parse::<u64>() {
+            values.insert(key.to_string(), value);
+        }
+        rest = &value_text[value_end..];
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IOREG: &str = r#"+-o AGXAcceleratorG13X  <class AGXAcceleratorG13X, id 0x1000003d3, registered, matched, active, busy 0 (0 ms), retain 94>
+    {
+      "IOClass" = "AGXAcceleratorG13X"
+      "model" = "Apple M1 Pro"
+      "gpu-core-count" = 16
+      "PerformanceStatistics" = {"In use system memory (driver)"=0,"Alloc system memory"=2147483648,"Tiler Utilization %"=5,"Renderer Utilization %"=4,"In use system memory"=536870912,"Device Utilization %"=7}
+    }
++-o AMDRadeonX6000_AMDNavi14GraphicsAccelerator  <class AMDRadeonX6000_AMDNavi14GraphicsAccelerator, id 0x100000880>
+    {
+      "PerformanceStatistics" = {"vramFreeBytes"=3221225472,"GPU Activity(%)"=12,"vramUsedBytes"=1073741824,"hardwareWaitTime"=0}
+    }
+"#;
+
+    #[test]
+    fn test_parse_ioreg() {
+        let accelerators = parse_ioreg(IOREG);
+        assert_eq!(accelerators.len(), 2);
+        assert_eq!(accelerators[0].name, "Apple M1 Pro");
+        assert_eq!(
+            accelerators[0].statistic(&["Device Utilization %"]),
+            Some(7)
+        );
+        assert_eq!(
+            accelerators[0].statistic(&["In use system memory (driver)"]),
+            Some(0)
+        );
+        assert_eq!(
+            memory_info(&accelerators[0], Some(16 << 30)),
+            Some(MemoryInfo {
+                total: 16 << 30,
+                used: 512 << 20,
+                free: (16 << 30) - (512 << 20),
+            })
+        );
+        assert_eq!(memory_info(&accelerators[0], None), None);
+
+        assert_eq!(
+            accelerators[1].name,
+            "AMDRadeonX6000_AMDNavi14GraphicsAccelerator"
+        );
+        assert_eq!(accelerators[1].statistic(&["GPU Activity(%)"]), Some(12));
+        assert_eq!(
+            memory_info(&accelerators[1], None).map(|m| m.total),
+            Some(4 << 30)
+        );
+    }
+}
@@ -0,0 +1,459 @@
+//! NVIDIA backend, through NVML.
+//!
+//! NVML (`libnvidia-ml`, installed with the NVIDIA driver) is loaded at
+//! runtime on first use, so this backend builds everywhere and is simply
+//! unavailable on machines without the driver.
+
+use std::collections::HashMap;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+use std::sync::OnceLock;
+
+use fusabi_host::Value;
+#[cfg(target_os = "linux")]
+use nvml_wrapper::enum_wrappers::device::TopologyLevel;
+use nvml_wrapper::enum_wrappers::device::{Clock, TemperatureSensor};
+use nvml_wrapper::enums::device::UsedGpuMemory;
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::struct_wrappers::device::ProcessInfo;
+use nvml_wrapper::{Device, Nvml};
+
+#[cfg(target_os = "linux")]
+use super::backend::{pci_locality, pcie_bandwidth, DeviceTopology, GpuLink, LinkKind};
+use super::backend::{ClockSpeeds, DeviceSummary, GpuBackend, GpuProcess, MemoryInfo};
+
+/// Highest NVLink link index, exclusive (`NVML_NVLINK_MAX_LINKS`).
+#[cfg(target_os = "linux")]
+const MAX_NVLINKS: u32 = 18;
+
+/// The process-wide NVML handle, initialized on first use.
+///
+/// NVML is loaded once; if it fails to load, every later call reports the
+/// same failure without retrying.
+///
+/// # Errors
+///
+/// [`crate::Error::ModuleNotAvailable`] when the NVML library cannot be
+/// loaded or initialized (no NVIDIA driver, or no permission to the device
+/// nodes).
+pub fn nvml() -> crate::Result<&'static Nvml> {
+    static NVML: OnceLock<std::result::Result<Nvml, String>> = OnceLock::new();
+    NVML.get_or_init(|| Nvml::init().map_err(|e| e.to_string()))
+        .as_ref()
+        .map_err(|e| crate::Error::ModuleNotAvailable(format!("gpu: NVML unavailable: {}", e)))
+}
+
+fn nvml_error(e: NvmlError) -> crate::Error {
+    match e {
+        NvmlError::InvalidArg => crate::Error::invalid_argument("gpu: invalid device"),
+        NvmlError::NotSupported => {
+            crate::Error::ModuleNotAvailable("gpu: not supported by this device".into())
+        }
+        e => crate::Error::Internal(format!("gpu: {}", e)),
+    }
+}
+
+/// NVIDIA devices through NVML.
+#[derive(Debug, Clone, Copy)]
+pub struct NvmlBackend {
+    nvml: &'static Nvml,
+}
+
+impl NvmlBackend {
+    /// Load NVML.
+    ///
+    /// # Errors
+    ///
+    /// As [`nvml()`].
+    pub fn new() -> crate::Result<Self> {
+        Ok(Self { nvml: nvml()? })
+    }
+
+    fn device(&self, index: u32) -> crate::Result<Device<'static>> {
+        self.nvml.device_by_index(index).map_err(nvml_error)
+    }
+}
+
+impl GpuBackend for NvmlBackend {
+    fn name(&self) -> &'static str {
+        "nvml"
+    }
+
+    fn devices(&self) -> crate::Result<Vec<DeviceSummary>> {
+        let count = self.nvml.device_count().map_err(nvml_error)?;
+        (0..count)
+            .map(|index| {
+                let device = self.device(index)?;
+                Ok(DeviceSummary {
+                    index,
+                    name: device.name().map_err(nvml_error)?,
+                    uuid: Some(device.uuid().map_err(nvml_error)?),
+                })
+            })
+            .collect()
+    }
+
+    fn utilization(&self, index: u32) -> crate::Result<f64> {
+        let rates = self
+            .device(index)?
+            .utilization_rates()
+            .map_err(nvml_error)?;
+        Ok(f64::from(rates.gpu))
+    }
+
+    fn memory(&self, index: u32) -> crate::Result<MemoryInfo> {
+        let memory = self.device(index)?.memory_info().map_err(nvml_error)?;
+        Ok(MemoryInfo {
+            total: memory.total,
+            used: memory.used,
+            free: memory.free,
+        })
+    }
+
+    fn temperature(&self, index: u32) -> crate::Result<f64> {
+        let celsius = self
+            .device(index)?
+            .temperature(TemperatureSensor::Gpu)
+            .map_err(nvml_error)?;
+        Ok(f64::from(celsius))
+    }
+
+    fn power_watts(&self, index: u32) -> crate::Result<f64> {
+        // NVML reports milliwatts.
+        let milliwatts = self.device(index)?.power_usage().map_err(nvml_error)?;
+        Ok(f64::from(milliwatts) / 1000.0)
+    }
+
+    fn clocks(&self, index: u32) -> crate::Result<ClockSpeeds> {
+        let device = self.device(index)?;
+        let clock = |clock| device.clock_info(clock).map_err(nvml_error);
+        Ok(ClockSpeeds {
+            graphics: Some(clock(Clock::Graphics)?),
+            memory: Some(clock(Clock::Memory)?),
+            sm: Some(clock(Clock::SM)?),
+        })
+    }
+
+    fn processes(&self, index: u32) -> crate::Result<Vec<GpuProcess>> {
+        let device = self.device(index)?;
+        let compute = device.running_compute_processes().map_err(nvml_error)?;
+        let graphics = device.running_graphics_processes().map_err(nvml_error)?;
+
+        let mut processes = merge_processes(compute, graphics);
+        for process in &mut processes {
+            process.name = self.nvml.sys_process_name(process.pid, 256).ok();
+        }
+        Ok(processes)
+    }
+
+    /// Returns a map with:
+    /// - `driver_version`: Driver version string (e.g. `"550.54.15"`)
+    /// - `cuda_version`: Highest CUDA version supported by the driver (e.g. `"12.4"`)
+    /// - `cuda_major`: CUDA major version (integer)
+    /// - `cuda_minor`: CUDA minor version (integer)
+    /// - `nvml_version`: NVML library version string
+    fn driver_info(&self) -> crate::Result<HashMap<String, Value>> {
+        let driver_version = self.nvml.sys_driver_version().map_err(nvml_error)?;
+        let nvml_version = self.nvml.sys_nvml_version().map_err(nvml_error)?;
+        // Encoded as major * 1000 + minor * 10, e.g. 12040 for 12.4.
+        let cuda = self.nvml.sys_cuda_driver_version().map_err(nvml_error)?;
+        let (cuda_major, cuda_minor) = (cuda / 1000, (cuda % 1000) / 10);
+
+        let mut info = HashMap::new();
+        info.insert("driver_version".to_string(), Value::String(driver_version));
+        info.insert(
+            "cuda_version".to_string(),
+            Value::String(format!("{}.{}", cuda_major, cuda_minor)),
+        );
+        info.insert("cuda_major".to_string(), Value::Int(i64::from(cuda_major)));
+        info.insert("cuda_minor".to_string(), Value::Int(i64::from(cuda_minor)));
+        info.insert("nvml_version".to_string(), Value::String(nvml_version));
+        Ok(info)
+    }
+
+    /// Returns a map with:
+    /// - `compute_capability`: Compute capability string (e.g. `"8.0"`)
+    /// - `compute_major`: Compute capability major version (integer)
+    /// - `compute_minor`: Compute capability minor version (integer)
+    /// - `vbios_version`: VBIOS version string
+    /// - `pcie`: Map with `bus_id`, `generation`, `max_generation`,
+    ///   `link_width`, and `max_link_width`
+    fn device_info(&self, index: u32) -> crate::Result<HashMap<String, Value>> {
+        let device = self.device(index)?;
+
+        let pci = device.pci_info().map_err(nvml_error)?;
+        let mut pcie = HashMap::new();
+        pcie.insert("bus_id".to_string(), Value::String(pci.bus_id));
+        for (key, value) in [
+            ("generation", device.current_pcie_link_gen()),
+            ("max_generation", device.max_pcie_link_gen()),
+            ("link_width", device.current_pcie_link_width()),
+            ("max_link_width", device.max_pcie_link_width()),
+        ] {
+            pcie.insert(
+                key.to_string(),
+                Value::Int(i64::from(value.map_err(nvml_error)?)),
+            );
+        }
+
+        let capability = device.cuda_compute_capability().map_err(nvml_error)?;
+        let mut info = HashMap::new();
+        info.insert(
+            "compute_capability".to_string(),
+            Value::String(format!("{}.{}", capability.major, capability.minor)),
+        );
+        info.insert(
+            "compute_major".to_string(),
+            Value::Int(i64::from(capability.major)),
+        );
+        info.insert(
+            "compute_minor".to_string(),
+            Value::Int(i64::from(capability.minor)),
+        );
+        info.insert(
+            "vbios_version".to_string(),
+            Value::String(device.vbios_version().map_err(nvml_error)?),
+        );
+        info.insert("pcie".to_string(), Value::Map(pcie));
+        Ok(info)
+    }
+
+    /// NVLink peers are found through each link's remote PCI address;
+    /// devices that both have links to NVSwitches are NVLink peers too.
+    /// Other pairs report NVML's common PCIe ancestor.
+    #[cfg(target_os = "linux")]
+    fn topology(&self) -> crate::Result<Vec<DeviceTopology>> {
+        let count = self.nvml.device_count().map_err(nvml_error)?;
+        let devices = (0..count)
+            .map(|index| self.device(index))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let bus_ids = devices
+            .iter()
+            .map(|device| Ok(device.pci_info().map_err(nvml_error)?.bus_id))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let nvlinks: Vec<NvLinks> = devices
+            .iter()
+            .map(|device| NvLinks::of(device, &bus_ids))
+            .collect();
+        // The maximum link, as idle links train down to save power.
+        let pcie: Vec<Option<f64>> = devices
+            .iter()
+            .map(|device| {
+                pcie_bandwidth(
+                    device.max_pcie_link_gen().ok()?,
+                    device.max_pcie_link_width().ok()?,
+                )
+            })
+            .collect();
+
+        let mut topology = Vec::with_capacity(devices.len());
+        for (index, device) in (0..count).zip(&devices) {
+            let i = index as usize;
+            let mut links = Vec::new();
+            for peer in (0..count).filter(|peer| *peer != index) {
+                let p = peer as usize;
+                let link = match nvlinks[i].bandwidth_to(p, &nvlinks[p]) {
+                    Some(bandwidth) => GpuLink {
+                        peer,
+                        kind: LinkKind::NvLink,
+                        bandwidth_gbps: Some(bandwidth),
+                    },
+                    None => GpuLink {
+                        peer,
+                        kind: link_kind(
+                            device
+                                .topology_common_ancestor(self.device(peer)?)
+                                .map_err(nvml_error)?,
+                        ),
+                        bandwidth_gbps: pcie[i].zip(pcie[p]).map(|(a, b)| a.min(b)),
+                    },
+                };
+                links.push(link);
+            }
+            let (numa_node, cpus) =
+                pci_locality(&Path::new("/sys/bus/pci/devices").join(sysfs_address(&bus_ids[i])));
+            topology.push(DeviceTopology {
+                index,
+                numa_node,
+                cpus,
+                links,
+            });
+        }
+        Ok(topology)
+    }
+}
+
+/// The active NVLinks of a device.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default)]
+struct NvLinks {
+    /// Bandwidth in GB/s to each directly linked device, by index.
+    peers: HashMap<usize, f64>,
+    /// Bandwidth in GB/s to NVSwitches.
+    switch: f64,
+}
+
+#[cfg(target_os = "linux")]
+impl NvLinks {
+    /// Find the active links of `device`, matching their remote ends to
+    /// the devices at `bus_ids`. A device without NVLink has none.
+    fn of(device: &Device<'_>, bus_ids: &[String]) -> Self {
+        let mut links = Self::default();
+        for link in (0..MAX_NVLINKS).map(|link| device.link_wrapper_for(link)) {
+            if !link.is_active().unwrap_or(false) {
+                continue;
+            }
+            let bandwidth = nvlink_bandwidth(link.version().unwrap_or(0));
+            let Ok(remote) = link.remote_pci_info() else {
+                continue;
+            };
+            match bus_ids
+                .iter()
+                .position(|id| id.eq_ignore_ascii_case(&remote.bus_id))
+            {
+                Some(peer) => *links.peers.entry(peer).or_default() += bandwidth,
+                None => links.switch += bandwidth,
+            }
+        }
+        links
+    }
+
+    /// NVLink bandwidth to device `peer`, whose links are `other`.
+    fn bandwidth_to(&self, peer: usize, other: &NvLinks) -> Option<f64> {
+        if let Some(bandwidth) = self.peers.get(&peer) {
+            return Some(*bandwidth);
+        }
+        (self.switch > 0.0 && other.switch > 0.0).then(|| self.switch.min(other.switch))
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// One-way bandwidth in GB/s of one NVLink of `version`.
+fn nvlink_bandwidth(version: u32) -> f64 {
+    if version <= 1 {
+        20.0
+    } else {
+        25.0
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn link_kind(level: TopologyLevel) -> LinkKind {
+    match level {
+        TopologyLevel::Internal | TopologyLevel::Single => LinkKind::PcieSwitch,
+        TopologyLevel::Multiple => LinkKind::PcieSwitches,
+        TopologyLevel::HostBridge => LinkKind::HostBridge,
+        TopologyLevel::Node => LinkKind::Node,
+        TopologyLevel::System => LinkKind::System,
+    }
+}
+
+#[cfg(target_os = "linux")]
+/// The sysfs name of an NVML bus id: `00000000:3B:00.0` is `0000:3b:00.0`.
+fn sysfs_address(bus_id: &str) -> String {
+    let bus_id = bus_id.to_ascii_lowercase();
+    match bus_id.split_once(':') {
+        Some((domain, rest)) if domain.len() > 4 => {
+            format!("{}:{}", &domain[domain.len() - 4..], rest)
+        }
+        _ => bus_id,
+    }
+}
+
+/// Merge the compute and graphics process lists into one entry per pid,
+/// largest memory user first.
+fn merge_processes(compute: Vec<ProcessInfo>, graphics: Vec<ProcessInfo>) -> Vec<GpuProcess> {
+    let mut merged: Vec<GpuProcess> = Vec::new();
+    let lists = compute
+        .into_iter()
+        .map(|p| (p, true))
+        .chain(graphics.into_iter().map(|p| (p, false)));
+    for (info, is_compute) in lists {
+        let used = match info.used_gpu_memory {
+            UsedGpuMemory::Used(bytes) => Some(bytes),
+            UsedGpuMemory::Unavailable => None,
+        };
+        let entry = match merged.iter_mut().find(|p| p.pid == info.pid) {
+            Some(entry) => entry,
+            None => {
+                merged.push(GpuProcess {
+                    pid: info.pid,
+                    name: None,
+                    used_memory: None,
+                    compute: false,
+                    graphics: false,
+                });
+                merged.last_mut().expect("just pushed")
+            }
+        };
+        // A process on both lists (or on several MIG instances) reports its
+        // memory per entry; keep the largest rather than double counting.
+        entry.used_memory = entry.used_memory.max(used);
+        entry.compute |= is_compute;
+        entry.graphics |= !is_compute;
+    }
+    merged.sort_by(|a, b| b.used_memory.cmp(&a.used_memory).then(a.pid.cmp(&b.pid)));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, used: UsedGpuMemory) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            used_gpu_memory: used,
+            gpu_instance_id: None,
+            compute_instance_id: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_processes() {
+        let merged = merge_processes(
+            vec![
+                process(10, UsedGpuMemory::Used(1 << 20)),
+                process(20, UsedGpuMemory::Used(8 << 30)),
+            ],
+            vec![
+                process(10, UsedGpuMemory::Used(1 << 20)),
+                process(30, UsedGpuMemory::Unavailable),
+            ],
+        );
+
+        assert_eq!(
+            merged.iter().map(|p| p.pid).collect::<Vec<_>>(),
+            vec![20, 10, 30]
+        );
+        assert_eq!(merged[0].used_memory, Some(8 << 30));
+        assert!(merged[1].compute && merged[1].graphics);
+        assert_eq!(merged[1].used_memory, Some(1 << 20));
+        assert_eq!(merged[2].used_memory, None);
+        assert!(!merged[2].compute && merged[2].graphics);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_nvlink_bandwidth_to() {
+        let direct = NvLinks {
+            peers: HashMap::from([(1, 100.0)]),
+            switch: 0.0,
+        };
+        let switched = NvLinks {
+            peers: HashMap::new(),
+            switch: 450.0,
+        };
+        assert_eq!(direct.bandwidth_to(1, &NvLinks::default()), Some(100.0));
+        assert_eq!(direct.bandwidth_to(2, &switched), None);
+        assert_eq!(switched.bandwidth_to(0, &switched), Some(450.0));
+        assert_eq!(nvlink_bandwidth(4) * 18.0, 450.0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sysfs_address() {
+        assert_eq!(sysfs_address("00000000:3B:00.0"), "0000:3b:00.0");
+        assert_eq!(sysfs_address("0000:3b:00.0"), "0000:3b:00.0");
+    }
+}
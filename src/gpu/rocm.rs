@@ -0,0 +1,722 @@
+//! AMD backend, through the `amdgpu` driver's sysfs interface.
+//!
+//! Reads the same files `rocm-smi` does, so no ROCm libraries need to be
+//! installed:
+//!
+//! - `/sys/class/drm/cardN/device/` for utilization (`gpu_busy_percent`),
+//!   VRAM (`mem_info_vram_*`), clocks (`pp_dpm_sclk`, `pp_dpm_mclk`), PCIe
+//!   links, VBIOS version, NUMA node and xGMI hive
+//! - its `hwmon/hwmonM/` directory for the edge temperature and power
+//! - `/proc/PID/fdinfo/` DRM client statistics for per-process VRAM
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fusabi_host::Value;
+
+use super::backend::{
+    no_device, pci_locality, pcie_bandwidth, unsupported, ClockSpeeds, DeviceSummary,
+    DeviceTopology, GpuBackend, GpuLink, GpuProcess, LinkKind, MemoryInfo,
+};
+
+/// PCI vendor ID of AMD.
+const AMD_VENDOR: &str = "0x1002";
+
+#[derive(Debug, Clone)]
+struct Card {
+    /// The card's `device` directory.
+    device: PathBuf,
+    /// PCI address, e.g. `0000:03:00.0`.
+    pci_slot: Option<String>,
+}
+
+/// AMD devices through sysfs.
+#[derive(Debug, Clone)]
+pub struct RocmBackend {
+    sysfs: PathBuf,
+    procfs: PathBuf,
+    cards: Vec<Card>,
+}
+
+impl RocmBackend {
+    /// Find the `amdgpu` devices under `/sys`.
+    ///
+    /// # Errors
+    ///
+    /// [`crate::Error::ModuleNotAvailable`] when there are none.
+    pub fn new() -> crate::Result<Self> {
+        Self::with_roots("/sys", "/proc")
+    }
+
+    /// Find the `amdgpu` devices under the given sysfs and procfs mounts.
+    pub fn with_roots(
+        sysfs: impl Into<PathBuf>,
+        procfs: impl Into<PathBuf>,
+    ) -> crate::Result<Self> {
+        let sysfs = sysfs.into();
+        let drm = sysfs.join("class/drm");
+        let mut cards: Vec<(u32, Card)> = Vec::new();
+        for entry in fs::read_dir(&drm).map_err(|e| {
+            crate::Error::ModuleNotAvailable(format!("gpu: cannot read {}: {}", drm.display(), e))
+        })? {
+            let entry = entry?;
+            let name = entry.file_name();
+            // Connectors (card0-DP-1) and render nodes are skipped.
+            let Some(number) = name
+                .to_str()
+                .and_then(|n| n.strip_prefix("card"))
+                .and_then(|n| n.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let device = entry.path().join("device");
+            if read_string(&device.join("vendor")).ok().as_deref() != Some(AMD_VENDOR) {
+                continue;
+            }
+            let pci_slot = read_string(&device.join("uevent")).ok().and_then(|uevent| {
+                uevent
+                    .lines()
+                    .find_map(|line| line.strip_prefix("PCI_SLOT_NAME="))
+                    .map(str::to_string)
+            });
+            cards.push((number, Card { device, pci_slot }));
+        }
+        if cards.is_empty() {
+            return Err(crate::Error::ModuleNotAvailable(format!(
+                "gpu: no amdgpu devices in {}",
+                drm.display()
+            )));
+        }
+        cards.sort_by_key(|(number, _)| *number);
+
+        Ok(Self {
+            sysfs,
+            procfs: procfs.into(),
+            cards: cards.into_iter().map(|(_, card)| card).collect(),
+        })
+    }
+
+    fn card(&self, index: u32) -> crate::Result<&Card> {
+        self.cards
+            .get(index as usize)
+            .ok_or_else(|| no_device(index))
+    }
+
+    /// Read `file` from the card's `device` directory.
+    fn read(&self, index: u32, file: &str, what: &str) -> crate::Result<String> {
+        read_supported(&self.card(index)?.device.join(file), what)
+    }
+
+    fn read_number(&self, index: u32, file: &str, what: &str) -> crate::Result<u64> {
+        parse_number(&self.read(index, file, what)?, what)
+    }
+
+    /// Read a hwmon attribute such as `temp1_input`.
+    fn read_hwmon(&self, index: u32, attributes: &[&str], what: &str) -> crate::Result<u64> {
+        let hwmon = self.card(index)?.device.join("hwmon");
+        let mut dirs: Vec<PathBuf> = fs::read_dir(&hwmon)
+            .map_err(|_| unsupported("rocm", what))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .collect();
+        dirs.sort();
+        for dir in dirs {
+            for attribute in attributes {
+                if let Ok(value) = read_string(&dir.join(attribute)) {
+                    return parse_number(&value, what);
+                }
+            }
+        }
+        Err(unsupported("rocm", what))
+    }
+}
+
+impl GpuBackend for RocmBackend {
+    fn name(&self) -> &'static str {
+        "rocm"
+    }
+
+    fn devices(&self) -> crate::Result<Vec<DeviceSummary>> {
+        (0..self.cards.len() as u32)
+            .map(|index| {
+                let name = match self.read(index, "product_name", "product name") {
+                    Ok(name) if !name.is_empty() => name,
+                    _ => format!("AMD GPU {}", self.read(index, "device", "device id")?),
+                };
+                Ok(DeviceSummary {
+                    index,
+                    name,
+                    uuid: self.read(index, "unique_id", "unique id").ok(),
+                })
+            })
+            .collect()
+    }
+
+    fn utilization(&self, index: u32) -> crate::Result<f64> {
+        Ok(self.read_number(index, "gpu_busy_percent", "utilization")? as f64)
+    }
+
+    fn memory(&self, index: u32) -> crate::Result<MemoryInfo> {
+        let total = self.read_number(index, "mem_info_vram_total", "memory")?;
+        let used = self.read_number(index, "mem_info_vram_used", "memory")?;
+        Ok(MemoryInfo {
+            total,
+            used,
+            free: total.saturating_sub(used),
+        })
+    }
+
+    fn temperature(&self, index: u32) -> crate::Result<f64> {
+        // Millidegrees; temp1 is the edge sensor, as rocm-smi reports.
+        Ok(self.read_hwmon(index, &["temp1_input"], "temperature")? as f64 / 1000.0)
+    }
+
+    fn power_watts(&self, index: u32) -> crate::Result<f64> {
+        // Microwatts; older boards only report the average.
+        let microwatts =
+            self.read_hwmon(index, &["power1_input", "power1_average"], "power usage")?;
+        Ok(microwatts as f64 / 1_000_000.0)
+    }
+
+    fn clocks(&self, index: u32) -> crate::Result<ClockSpeeds> {
+        let current = |file| {
+            self.read(index, file, "clock speeds")
+                .ok()
+                .and_then(|levels| current_dpm_level(&levels))
+        };
+        let clocks = ClockSpeeds {
+            graphics: current("pp_dpm_sclk"),
+            memory: current("pp_dpm_mclk"),
+            sm: None,
+        };
+        if clocks.graphics.is_none() && clocks.memory.is_none() {
+            return Err(unsupported("rocm", "clock speeds"));
+        }
+        Ok(clocks)
+    }
+
+    fn processes(&self, index: u32) -> crate::Result<Vec<GpuProcess>> {
+        let slot = self
+            .card(index)?
+            .pci_slot
+            .clone()
+            .ok_or_else(|| unsupported("rocm", "process listing"))?;
+        let entries =
+            fs::read_dir(&self.procfs).map_err(|_| unsupported("rocm", "process listing"))?;
+
+        let mut processes = Vec::new();
+        for entry in entries.flatten() {
+            let Some(pid) = entry
+                .file_name()
+                .to_str()
+                .and_then(|p| p.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            // Processes can exit, or belong to other users, mid-scan.
+            let Ok(fds) = fs::read_dir(entry.path().join("fdinfo")) else {
+                continue;
+            };
+            let mut clients: HashMap<String, DrmClient> = HashMap::new();
+            for fd in fds.flatten() {
+                let Ok(info) = fs::read_to_string(fd.path()) else {
+                    continue;
+                };
+                if let Some((id, client)) = parse_drm_fdinfo(&info, &slot) {
+                    clients.insert(id, client);
+                }
+            }
+            if clients.is_empty() {
+                continue;
+            }
+
+            // Several descriptors can share a DRM client; each client is
+            // counted once.
+            let vram: u64 = clients.values().map(|c| c.vram).sum();
+            let graphics = clients.values().any(|c| c.graphics);
+            let compute = clients.values().any(|c| c.compute) || !graphics;
+            processes.push(GpuProcess {
+                pid,
+                name: read_string(&entry.path().join("comm")).ok(),
+                used_memory: Some(vram),
+                compute,
+                graphics,
+            });
+        }
+        processes.sort_by(|a, b| b.used_memory.cmp(&a.used_memory).then(a.pid.cmp(&b.pid)));
+        Ok(processes)
+    }
+
+    /// Returns a map with:
+    /// - `driver`: `"amdgpu"`
+    /// - `driver_version`: The amdgpu module version, or the kernel release
+    ///   for the in-tree driver
+    fn driver_info(&self) -> crate::Result<HashMap<String, Value>> {
+        let version = read_string(&self.sysfs.join("module/amdgpu/version"))
+            .or_else(|_| read_string(&self.procfs.join("sys/kernel/osrelease")))
+            .map_err(|_| unsupported("rocm", "driver information"))?;
+
+        let mut info = HashMap::new();
+        info.insert("driver".to_string(), Value::String("amdgpu".to_string()));
+        info.insert("driver_version".to_string(), Value::String(version));
+        Ok(info)
+    }
+
+    /// Returns a map with:
+    /// - `vbios_version`: VBIOS version string, or null
+    /// - `pcie`: Map with `bus_id`, `generation`, `max_generation`,
+    ///   `link_width`, and `max_link_width` (each null if not reported)
+    fn device_info(&self, index: u32) -> crate::Result<HashMap<String, Value>> {
+        let card = self.card(index)?;
+        let text = |file: &str| read_string(&card.device.join(file)).ok();
+        let int = |value: Option<u64>| value.map_or(Value::Null, |v| Value::Int(v as i64));
+
+        let mut pcie = HashMap::new();
+        pcie.insert(
+            "bus_id".to_string(),
+            card.pci_slot.clone().map_or(Value::Null, Value::String),
+        );
+        for (key, file) in [
+            ("generation", "current_link_speed"),
+            ("max_generation", "max_link_speed"),
+        ] {
+            pcie.insert(
+                key.to_string(),
+                int(text(file).and_then(|speed| pcie_generation(&speed))),
+            );
+        }
+        for (key, file) in [
+            ("link_width", "current_link_width"),
+            ("max_link_width", "max_link_width"),
+        ] {
+            pcie.insert(
+                key.to_string(),
+                int(text(file).and_then(|width| width.parse().ok())),
+            );
+        }
+
+        let mut info = HashMap::new();
+        info.insert(
+            "vbios_version".to_string(),
+            text("vbios_version").map_or(Value::Null, Value::String),
+        );
+        info.insert("pcie".to_string(), Value::Map(pcie));
+        Ok(info)
+    }
+
+    /// Cards in the same xGMI hive are linked by Infinity Fabric, whose
+    /// bandwidth sysfs does not report. Other pairs are placed from their
+    /// paths in the sysfs PCI hierarchy.
+    fn topology(&self) -> crate::Result<Vec<DeviceTopology>> {
+        let cards: Vec<CardTopology> = self.cards.iter().map(CardTopology::read).collect();
+        Ok((0..cards.len() as u32)
+            .zip(&cards)
+            .map(|(index, card)| DeviceTopology {
+                index,
+                numa_node: card.numa_node,
+                cpus: card.cpus.clone(),
+                links: (0..cards.len() as u32)
+                    .zip(&cards)
+                    .filter(|(peer, _)| *peer != index)
+                    .map(|(peer, other)| card.link_to(peer, other))
+                    .collect(),
+            })
+            .collect())
+    }
+}
+
+/// What [`RocmBackend::topology`] reads of a card.
+#[derive(Debug, Clone, Default)]
+struct CardTopology {
+    numa_node: Option<u32>,
+    cpus: Vec<u32>,
+    /// PCI root and bridges above the card, e.g. `pci0000:00`,
+    /// `0000:00:01.1`; empty if unknown.
+    bridges: Vec<String>,
+    /// Maximum PCIe bandwidth in GB/s.
+    pcie: Option<f64>,
+    /// xGMI hive id, if the card is in one.
+    hive: Option<String>,
+}
+
+impl CardTopology {
+    fn read(card: &Card) -> Self {
+        let text = |file: &str| read_string(&card.device.join(file)).ok();
+        let (numa_node, cpus) = pci_locality(&card.device);
+        let generation = text("max_link_speed")
+            .or_else(|| text("current_link_speed"))
+            .and_then(|speed| pcie_generation(&speed));
+        let width = text("max_link_width")
+            .or_else(|| text("current_link_width"))
+            .and_then(|width| width.parse().ok());
+        Self {
+            numa_node,
+            cpus,
+            bridges: fs::canonicalize(&card.device)
+                .map(|path| pci_bridges(&path))
+                .unwrap_or_default(),
+            pcie: generation
+                .zip(width)
+                .and_then(|(generation, width)| pcie_bandwidth(generation as u32, width)),
+            hive: text("xgmi_hive_info/xgmi_hive_id").filter(|id| id != "0"),
+        }
+    }
+
+    fn link_to(&self, peer: u32, other: &CardTopology) -> GpuLink {
+        if self.hive.is_some() && self.hive == other.hive {
+            return GpuLink {
+                peer,
+                kind: LinkKind::Xgmi,
+                bandwidth_gbps: None,
+            };
+        }
+        GpuLink {
+            peer,
+            kind: pcie_link_kind(self, other),
+            bandwidth_gbps: self.pcie.zip(other.pcie).map(|(a, b)| a.min(b)),
+        }
+    }
+}
+
+/// The PCI root and bridges above the device at `path`, a resolved sysfs
+/// device directory such as
+/// `/sys/devices/pci0000:00/0000:00:01.1/0000:01:00.0/0000:03:00.0`.
+fn pci_bridges(path: &Path) -> Vec<String> {
+    let components: Vec<String> = path
+        .iter()
+        .filter_map(|c| c.to_str().map(str::to_string))
+        .collect();
+    let Some(root) = components
+        .iter()
+        .position(|c| c.starts_with("pci") && c.contains(':'))
+    else {
+        return Vec::new();
+    };
+    components[root..components.len() - 1].to_vec()
+}
+
+/// Classify a PCIe path the way `nvidia-smi topo -m` does, treating a
+/// pair of bridges shared below the root port as a switch.
+fn pcie_link_kind(a: &CardTopology, b: &CardTopology) -> LinkKind {
+    let node = if a.numa_node == b.numa_node {
+        LinkKind::Node
+    } else {
+        LinkKind::System
+    };
+    match (a.bridges.first(), b.bridges.first()) {
+        (Some(root_a), Some(root_b)) if root_a == root_b => {}
+        _ => return node,
+    }
+    let shared = a
+        .bridges
+        .iter()
+        .zip(&b.bridges)
+        .skip(1)
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (below_a, below_b) = (a.bridges.len() - 1 - shared, b.bridges.len() - 1 - shared);
+    if shared == 0 {
+        LinkKind::HostBridge
+    } else if below_a <= 1 && below_b <= 1 {
+        LinkKind::PcieSwitch
+    } else {
+        LinkKind::PcieSwitches
+    }
+}
+
+fn read_string(path: &Path) -> io::Result<String> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+/// Read a sysfs file, reporting a missing one as an unsupported reading.
+fn read_supported(path: &Path, what: &str) -> crate::Result<String> {
+    read_string(path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => unsupported("rocm", what),
+        _ => crate::Error::Io(e),
+    })
+}
+
+fn parse_number(value: &str, what: &str) -> crate::Result<u64> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| crate::Error::Internal(format!("gpu: unreadable {} {:?}", what, value)))
+}
+
+/// Current level of a `pp_dpm_*` file, whose lines look like
+/// `1: 1800Mhz *` with the active level starred.
+fn current_dpm_level(levels: &str) -> Option<u32> {
+    let line = levels.lines().find(|line| line.trim_end().ends_with('*'))?;
+    let (_, speed) = line.split_once(':')?;
+    let speed = speed.trim().trim_end_matches('*').trim();
+    speed
+        .strip_suffix("Mhz")
+        .or_else(|| speed.strip_suffix("MHz"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// PCIe generation from a link speed such as `16.0 GT/s PCIe`.
+fn pcie_generation(speed: &str) -> Option<u64> {
+    let rate: f64 = speed.split_whitespace().next()?.parse().ok()?;
+    [2.5, 5.0, 8.0, 16.0, 32.0, 64.0]
+        .iter()
+        .position(|gen_rate| (rate - gen_rate).abs() < 0.1)
+        .map(|gen| gen as u64 + 1)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct DrmClient {
+    /// VRAM in bytes.
+    vram: u64,
+    graphics: bool,
+    compute: bool,
+}
+
+/// Parse a DRM fdinfo file, returning its client id and usage if it is an
+/// amdgpu client of the device at `slot`.
+fn parse_drm_fdinfo(info: &str, slot: &str) -> Option<(String, DrmClient)> {
+    let mut fields: HashMap<&str, &str> = HashMap::new();
+    for line in info.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key.trim(), value.trim());
+        }
+    }
+    if fields.get("drm-driver") != Some(&"amdgpu") || fields.get("drm-pdev") != Some(&slot) {
+        return None;
+    }
+    let id = fields.get("drm-client-id")?.to_string();
+
+    // `drm-memory-vram: 1024 KiB`
+    let vram = fields.get("drm-memory-vram").and_then(|value| {
+        let mut parts = value.split_whitespace();
+        let amount: u64 = parts.next()?.parse().ok()?;
+        let unit = match parts.next() {
+            None => 1,
+            Some("KiB") => 1 << 10,
+            Some("MiB") => 1 << 20,
+            Some("GiB") => 1 << 30,
+            Some(_) => return None,
+        };
+        Some(amount * unit)
+    });
+    // Engine time in nanoseconds, e.g. `drm-engine-gfx: 12345 ns`.
+    let busy = |engine: &str| {
+        fields
+            .get(engine)
+            .and_then(|v| v.split_whitespace().next())
+            .and_then(|ns| ns.parse::<u64>().ok())
+            .is_some_and(|ns| ns > 0)
+    };
+
+    Some((
+        id,
+        DrmClient {
+            vram: vram.unwrap_or(0),
+            graphics: busy("drm-engine-gfx"),
+            compute: busy("drm-engine-compute"),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_rocm_sysfs() {
+        let dir = tempfile::tempdir().unwrap();
+        let sys = dir.path().join("sys");
+        let proc = dir.path().join("proc");
+
+        let card = sys.join("class/drm/card1/device");
+        write(&card.join("vendor"), "0x1002\n");
+        write(&card.join("device"), "0x744c\n");
+        write(
+            &card.join("uevent"),
+            "DRIVER=amdgpu\nPCI_SLOT_NAME=0000:03:00.0\n",
+        );
+        write(&card.join("gpu_busy_percent"), "37\n");
+        write(&card.join("mem_info_vram_total"), "25753026560\n");
+        write(&card.join("mem_info_vram_used"), "1073741824\n");
+        write(&card.join("pp_dpm_sclk"), "0: 500Mhz\n1: 2371Mhz *\n");
+        write(&card.join("current_link_speed"), "16.0 GT/s PCIe\n");
+        write(&card.join("current_link_width"), "16\n");
+        write(&card.join("hwmon/hwmon4/temp1_input"), "48000\n");
+        write(&card.join("hwmon/hwmon4/power1_average"), "35000000\n");
+        // An NVIDIA card and a connector are ignored.
+        write(&sys.join("class/drm/card0/device/vendor"), "0x10de\n");
+        write(&sys.join("class/drm/card1-DP-1/status"), "connected\n");
+
+        write(
+            &proc.join("4242/fdinfo/7"),
+            "drm-driver:\tamdgpu\ndrm-client-id:\t12\ndrm-pdev:\t0000:03:00.0\n\
+             drm-memory-vram:\t2048 KiB\ndrm-engine-compute:\t99 ns\n",
+        );
+        write(
+            &proc.join("4242/fdinfo/8"),
+            "drm-driver:\tamdgpu\ndrm-client-id:\t12\ndrm-pdev:\t0000:03:00.0\n\
+             drm-memory-vram:\t2048 KiB\n",
+        );
+        write(&proc.join("4242/comm"), "python\n");
+        write(
+            &proc.join("77/fdinfo/3"),
+            "drm-driver:\ti915\ndrm-client-id:\t1\ndrm-pdev:\t0000:00:02.0\n",
+        );
+
+        let rocm = RocmBackend::with_roots(&sys, &proc).unwrap();
+        let devices = rocm.devices().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "AMD GPU 0x744c");
+        assert_eq!(rocm.utilization(0).unwrap(), 37.0);
+        assert_eq!(rocm.memory(0).unwrap().free, 25753026560 - 1073741824);
+        assert_eq!(rocm.temperature(0).unwrap(), 48.0);
+        assert_eq!(rocm.power_watts(0).unwrap(), 35.0);
+        assert_eq!(
+            rocm.clocks(0).unwrap(),
+            ClockSpeeds {
+                graphics: Some(2371),
+                memory: None,
+                sm: None,
+            }
+        );
+        assert!(matches!(
+            rocm.utilization(1),
+            Err(crate::Error::InvalidArgument(_))
+        ));
+
+        let processes = rocm.processes(0).unwrap();
+        assert_eq!(processes.len(), 1);
+        assert_eq!(processes[0].pid, 4242);
+        assert_eq!(processes[0].name.as_deref(), Some("python"));
+        assert_eq!(processes[0].used_memory, Some(2048 * 1024));
+        assert!(processes[0].compute && !processes[0].graphics);
+
+        let info = rocm.device_info(0).unwrap();
+        let pcie = info["pcie"].as_map().unwrap();
+        assert_eq!(pcie["generation"], Value::Int(4));
+        assert_eq!(pcie["max_link_width"], Value::Null);
+
+        assert!(RocmBackend::with_roots(dir.path().join("none"), &proc).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rocm_topology() {
+        let dir = tempfile::tempdir().unwrap();
+        let sys = dir.path().join("sys");
+        let cards = [
+            (
+                "pci0000:00/0000:00:01.1/0000:01:00.0/0000:02:00.0/0000:03:00.0",
+                "0",
+                "16.0 GT/s PCIe",
+                None,
+            ),
+            (
+                "pci0000:00/0000:00:01.1/0000:01:00.0/0000:02:01.0/0000:04:00.0",
+                "0",
+                "8.0 GT/s PCIe",
+                Some("42"),
+            ),
+            (
+                "pci0000:80/0000:80:01.1/0000:81:00.0",
+                "1",
+                "16.0 GT/s PCIe",
+                Some("42"),
+            ),
+        ];
+        for (number, (path, node, speed, hive)) in cards.into_iter().enumerate() {
+            let device = sys.join("devices").join(path);
+            write(&device.join("vendor"), "0x1002\n");
+            write(&device.join("numa_node"), node);
+            write(
+                &device.join("local_cpulist"),
+                &format!("{}-{}", number * 4, number * 4 + 3),
+            );
+            write(&device.join("max_link_speed"), speed);
+            write(&device.join("max_link_width"), "16");
+            if let Some(hive) = hive {
+                write(&device.join("xgmi_hive_info/xgmi_hive_id"), hive);
+            }
+            let card = sys.join(format!("class/drm/card{}", number));
+            fs::create_dir_all(&card).unwrap();
+            std::os::unix::fs::symlink(&device, card.join("device")).unwrap();
+        }
+
+        let rocm = RocmBackend::with_roots(&sys, dir.path().join("proc")).unwrap();
+        let topology = rocm.topology().unwrap();
+        assert_eq!(topology.len(), 3);
+        assert_eq!(topology[1].numa_node, Some(0));
+        assert_eq!(topology[1].cpus, vec![4, 5, 6, 7]);
+
+        let kinds = |index: usize| {
+            topology[index]
+                .links
+                .iter()
+                .map(|link| (link.peer, link.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(0),
+            vec![(1, LinkKind::PcieSwitch), (2, LinkKind::System)]
+        );
+        assert_eq!(
+            kinds(1),
+            vec![(0, LinkKind::PcieSwitch), (2, LinkKind::Xgmi)]
+        );
+        assert_eq!(topology[0].links[0].bandwidth_gbps, pcie_bandwidth(3, 16));
+        assert_eq!(topology[1].links[1].bandwidth_gbps, None);
+    }
+
+    #[test]
+    fn test_pcie_link_kind() {
+        let card = |node: Option<u32>, bridges: &[&str]| CardTopology {
+            numa_node: node,
+            bridges: bridges.iter().map(|b| b.to_string()).collect(),
+            ..CardTopology::default()
+        };
+        let a = card(None, &["pci0000:00", "0000:00:01.1"]);
+        let b = card(None, &["pci0000:00", "0000:00:03.1"]);
+        let c = card(
+            None,
+            &[
+                "pci0000:00",
+                "0000:00:01.1",
+                "0000:01:00.0",
+                "0000:02:00.0",
+                "0000:03:00.0",
+            ],
+        );
+        let d = card(
+            None,
+            &[
+                "pci0000:00",
+                "0000:00:01.1",
+                "0000:01:00.0",
+                "0000:02:01.0",
+                "0000:05:00.0",
+            ],
+        );
+        let e = card(None, &["pci0000:40", "0000:40:01.1"]);
+        assert_eq!(pcie_link_kind(&a, &b), LinkKind::HostBridge);
+        assert_eq!(pcie_link_kind(&c, &d), LinkKind::PcieSwitches);
+        assert_eq!(pcie_link_kind(&a, &e), LinkKind::Node);
+        assert_eq!(pcie_link_kind(&card(Some(1), &[]), &a), LinkKind::System);
+    }
+
+    #[test]
+    fn test_current_dpm_level() {
+        assert_eq!(current_dpm_level("0: 96Mhz\n1: 456Mhz *\n"), Some(456));
+        assert_eq!(current_dpm_level("0: 1000MHz *"), Some(1000));
+        assert_eq!(current_dpm_level("0: 96Mhz\n"), None);
+        assert_eq!(pcie_generation("8.0 GT/s PCIe"), Some(3));
+        assert_eq!(pcie_generation("Unknown"), None);
+    }
+}
@@ -0,0 +1,333 @@
+//! Background sampling of GPU readings into metrics gauges.
+//!
+//! A [`GpuSampler`] runs one thread per sampled device that reads the
+//! device every interval and sets these gauges, labelled `device="N"`:
+//!
+//! - `gpu_utilization` - utilization percentage
+//! - `gpu_memory_used_bytes` and `gpu_memory_total_bytes`
+//! - `gpu_temperature_celsius`
+//! - `gpu_power_watts`
+//!
+//! Power readings are also recorded in the sampler's [`EnergyMeter`], so
+//! `gpu.energy` covers sampled devices without scripts polling
+//! `gpu.power_usage`.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use crate::metrics::{Labels, MetricsRegistry};
+
+use super::energy::EnergyMeter;
+
+/// Gauge of the utilization percentage.
+pub const UTILIZATION_GAUGE: &str = "gpu_utilization";
+/// Gauge of the used device memory in bytes.
+pub const MEMORY_USED_GAUGE: &str = "gpu_memory_used_bytes";
+/// Gauge of the total device memory in bytes.
+pub const MEMORY_TOTAL_GAUGE: &str = "gpu_memory_total_bytes";
+/// Gauge of the temperature in Celsius.
+pub const TEMPERATURE_GAUGE: &str = "gpu_temperature_celsius";
+/// Gauge of the power draw in watts.
+pub const POWER_GAUGE: &str = "gpu_power_watts";
+
+/// Interval used when none is given.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Shortest interval accepted, to keep sampling from loading the driver.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// One reading of a device.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeviceReading {
+    /// Utilization percentage (0.0 - 100.0).
+    pub utilization: f64,
+    /// Used memory in bytes.
+    pub memory_used: u64,
+    /// Total memory in bytes.
+    pub memory_total: u64,
+    /// Temperature in Celsius, if the device reports it.
+    pub temperature: Option<f64>,
+    /// Power draw in watts, if the device reports it.
+    pub power_watts: Option<f64>,
+}
+
+/// Reads a device by index.
+pub type DeviceReader = dyn Fn(i64) -> crate::Result<DeviceReading> + Send + Sync;
+
+/// Read a device through the selected [`backend`](super::backend()).
+///
+/// Utilization and memory are required; temperature and power are left out
+/// when the backend does not report them.
+pub fn read_device(device_id: i64) -> crate::Result<DeviceReading> {
+    let index = u32::try_from(device_id)
+        .map_err(|_| crate::Error::invalid_argument(format!("invalid device_id {}", device_id)))?;
+    let backend = super::backend()?;
+
+    let memory = backend.memory(index)?;
+    Ok(DeviceReading {
+        utilization: backend.utilization(index)?,
+        memory_used: memory.used,
+        memory_total: memory.total,
+        temperature: backend.temperature(index).ok(),
+        power_watts: backend.power_watts(index).ok(),
+    })
+}
+
+struct Running {
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+/// Samples devices into a metrics registry on background threads.
+pub struct GpuSampler {
+    metrics: Arc<MetricsRegistry>,
+    meter: Arc<EnergyMeter>,
+    reader: Arc<DeviceReader>,
+    running: Mutex<HashMap<i64, Running>>,
+}
+
+impl GpuSampler {
+    /// Create a sampler that reads devices through the selected backend.
+    pub fn new(metrics: Arc<MetricsRegistry>, meter: Arc<EnergyMeter>) -> Self {
+        Self::with_reader(metrics, meter, Arc::new(read_device))
+    }
+
+    /// Create a sampler that reads devices with `reader`.
+    pub fn with_reader(
+        metrics: Arc<MetricsRegistry>,
+        meter: Arc<EnergyMeter>,
+        reader: Arc<DeviceReader>,
+    ) -> Self {
+        Self {
+            metrics,
+            meter,
+            reader,
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Start sampling a device every `interval`, replacing any sampling of
+    /// it already running.
+    ///
+    /// The device is read once before the thread starts, so a missing
+    /// device or driver is reported here rather than logged later. Later
+    /// read failures are logged and sampling continues.
+    pub fn start(&self, device_id: i64, interval: Duration) -> crate::Result<()> {
+        if interval < MIN_INTERVAL {
+            return Err(crate::Error::invalid_argument(format!(
+                "sampling interval must be at least {}ms",
+                MIN_INTERVAL.as_millis()
+            )));
+        }
+        let reading = (self.reader)(device_id)?;
+        self.record(device_id, &reading);
+
+        let (stop, stopped) = mpsc::channel::<()>();
+        let (metrics, meter, reader) = (
+            self.metrics.clone(),
+            self.meter.clone(),
+            self.reader.clone(),
+        );
+        let thread = std::thread::Builder::new()
+            .name(format!("fusabi-gpu-sampler-{}", device_id))
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    match reader(device_id) {
+                        Ok(reading) => record(&metrics, &meter, device_id, &reading),
+                        Err(e) => tracing::debug!(device_id, "gpu sampler read failed: {}", e),
+                    }
+                }
+            })?;
+
+        let previous = self
+            .running
+            .lock()
+            .insert(device_id, Running { stop, thread });
+        if let Some(previous) = previous {
+            drop(previous.stop);
+            let _ = previous.thread.join();
+        }
+        Ok(())
+    }
+
+    /// Stop sampling a device. Returns whether it was being sampled.
+    pub fn stop(&self, device_id: i64) -> bool {
+        let running = self.running.lock().remove(&device_id);
+        match running {
+            Some(running) => {
+                drop(running.stop);
+                let _ = running.thread.join();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stop sampling every device.
+    pub fn stop_all(&self) {
+        let running: Vec<Running> = self.running.lock().drain().map(|(_, r)| r).collect();
+        for running in running {
+            drop(running.stop);
+            let _ = running.thread.join();
+        }
+    }
+
+    /// Devices being sampled, in ascending order.
+    pub fn devices(&self) -> Vec<i64> {
+        let mut devices: Vec<i64> = self.running.lock().keys().copied().collect();
+        devices.sort_unstable();
+        devices
+    }
+
+    fn record(&self, device_id: i64, reading: &DeviceReading) {
+        record(&self.metrics, &self.meter, device_id, reading);
+    }
+}
+
+impl Drop for GpuSampler {
+    fn drop(&mut self) {
+        self.stop_all();
+    }
+}
+
+impl std::fmt::Debug for GpuSampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GpuSampler")
+            .field("devices", &self.devices())
+            .finish()
+    }
+}
+
+fn record(metrics: &MetricsRegistry, meter: &EnergyMeter, device_id: i64, r: &DeviceReading) {
+    let labels = Labels::from([("device".to_string(), device_id.to_string())]);
+    metrics.gauge_set_with(UTILIZATION_GAUGE, &labels, r.utilization);
+    metrics.gauge_set_with(MEMORY_USED_GAUGE, &labels, r.memory_used as f64);
+    metrics.gauge_set_with(MEMORY_TOTAL_GAUGE, &labels, r.memory_total as f64);
+    if let Some(celsius) = r.temperature {
+        metrics.gauge_set_with(TEMPERATURE_GAUGE, &labels, celsius);
+    }
+    if let Some(watts) = r.power_watts {
+        metrics.gauge_set_with(POWER_GAUGE, &labels, watts);
+        meter.record(device_id, watts);
+    }
+}
+
+/// Start sampling a device into metrics gauges.
+///
+/// # Arguments
+///
+/// * `args[0]` - Device ID (integer)
+/// * `args[1]` - Interval in milliseconds (optional, default 5000, at least 100)
+///
+/// # Returns
+///
+/// True once sampling has started
+pub fn start_sampler(
+    sampler: &Arc<GpuSampler>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let device_id = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function("gpu.start_sampler: missing device_id argument"))?;
+    let interval = match args.get(1) {
+        None | Some(Value::Null) => DEFAULT_INTERVAL,
+        Some(v) => v
+            .as_int()
+            .and_then(|ms| u64::try_from(ms).ok())
+            .map(Duration::from_millis)
+            .ok_or_else(|| {
+                Error::host_function("gpu.start_sampler: interval_ms must be an integer")
+            })?,
+    };
+
+    sampler
+        .start(device_id, interval)
+        .map_err(|e| Error::host_function(format!("gpu.start_sampler: {}", e)))?;
+    Ok(Value::Bool(true))
+}
+
+/// Stop sampling a device.
+///
+/// # Arguments
+///
+/// * `args[0]` - Device ID (integer)
+///
+/// # Returns
+///
+/// True if the device was being sampled
+pub fn stop_sampler(
+    sampler: &Arc<GpuSampler>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let device_id = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function("gpu.stop_sampler: missing device_id argument"))?;
+
+    Ok(Value::Bool(sampler.stop(device_id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    #[test]
+    fn test_sampler_records_gauges() {
+        let metrics = Arc::new(MetricsRegistry::new());
+        let meter = Arc::new(EnergyMeter::new());
+        let reads = Arc::new(AtomicU64::new(0));
+        let counter = reads.clone();
+        let sampler = GpuSampler::with_reader(
+            metrics.clone(),
+            meter.clone(),
+            Arc::new(move |device_id| {
+                if device_id > 0 {
+                    return Err(crate::Error::ModuleNotAvailable("no device".into()));
+                }
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                Ok(DeviceReading {
+                    utilization: 50.0 + n as f64,
+                    memory_used: 1 << 30,
+                    memory_total: 16 << 30,
+                    temperature: Some(61.0),
+                    power_watts: Some(300.0),
+                })
+            }),
+        );
+
+        assert!(sampler.start(0, Duration::from_millis(10)).is_err());
+        sampler.start(0, MIN_INTERVAL).unwrap();
+        assert!(sampler.start(1, MIN_INTERVAL).is_err());
+        assert_eq!(sampler.devices(), vec![0]);
+
+        let labels = Labels::from([("device".to_string(), "0".to_string())]);
+        // The first reading is recorded before start returns.
+        assert!(metrics.gauge_get_with(UTILIZATION_GAUGE, &labels) >= 50.0);
+        assert_eq!(
+            metrics.gauge_get_with(MEMORY_TOTAL_GAUGE, &labels),
+            (16u64 << 30) as f64
+        );
+        assert_eq!(metrics.gauge_get_with(POWER_GAUGE, &labels), 300.0);
+        assert_eq!(meter.devices(), vec![0]);
+
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(sampler.stop(0));
+        let sampled = reads.load(Ordering::SeqCst);
+        assert!(sampled >= 2, "only {} reads", sampled);
+        assert!(!sampler.stop(0));
+
+        std::thread::sleep(Duration::from_millis(150));
+        assert_eq!(reads.load(Ordering::SeqCst), sampled);
+    }
+}
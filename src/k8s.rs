@@ -1,21 +1,62 @@
 //! Kubernetes API bindings for Fusabi.
 //!
-//! Provides access to Kubernetes resources and operations.
+//! Provides access to Kubernetes resources and operations. [`K8sClient`]
+//! is the async Rust API; the `k8s` host functions below call it through a
+//! [`K8sHost`], which blocks on a Tokio runtime, once registered with
+//! `StdlibRegistry::with_k8s`. Every call is checked with
+//! [`SafetyConfig::check_k8s`] against its RBAC verb, resource and
+//! namespace.
+
+pub mod apply;
+pub mod capacity;
+pub mod cluster;
+pub mod dynamic;
+pub mod exec;
+pub mod job;
+pub mod leader;
+pub mod logs;
+pub mod port_forward;
+pub mod watch;
+pub mod workload;
 
 use k8s_openapi::api::core::v1::{ConfigMap, Namespace, Pod, Secret};
 use kube::{
-    api::{Api, ListParams},
+    api::{Api, AttachParams, AttachedProcess, ListParams},
+    config::{KubeConfigOptions, Kubeconfig},
     Client, Config,
 };
-use std::collections::HashMap;
+use parking_lot::RwLock;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
 use crate::error::{Error, Result};
-use fusabi_host::Value;
+use crate::safety::{PathAllowlist, SafetyConfig};
+use fusabi_host::{ExecutionContext, Value};
+
+pub use apply::{ApplyOptions, ResolvedObject, ResourceRef};
+pub use capacity::{
+    CapacityOptions, CapacityReport, CapacityStatus, ResourceCapacity, WorkloadCapacity,
+};
+pub use cluster::ClusterRegistry;
+pub use dynamic::ListOptions;
+pub use exec::ExecOutput;
+pub use job::{JobOutcome, RunJobOptions};
+pub use leader::{LeaderElection, LeaderElectionConfig};
+pub use logs::PodLogOptions;
+pub use port_forward::{ForwardedPort, PortForward};
+pub use watch::{ResourceEvent, ResourceEventType, WatchOptions};
+pub use workload::{RolloutStatus, WorkloadKind};
 
 /// Kubernetes client wrapper for Fusabi.
+#[derive(Clone)]
 pub struct K8sClient {
     client: Client,
     namespace: String,
+    context: Option<String>,
 }
 
 impl K8sClient {
@@ -29,6 +70,7 @@ impl K8sClient {
         Ok(Self {
             client,
             namespace: "default".to_string(),
+            context: None,
         })
     }
 
@@ -43,9 +85,48 @@ impl K8sClient {
         Ok(Self {
             client,
             namespace: "default".to_string(),
+            context: None,
         })
     }
 
+    /// Create a client for a named kubeconfig context.
+    ///
+    /// Reads the kubeconfig at `path`, or the one `kubectl` would use
+    /// (`$KUBECONFIG` or `~/.kube/config`). The default namespace is the
+    /// context's, or `default` if it sets none.
+    pub async fn from_kubeconfig_context(path: Option<&Path>, context: &str) -> Result<Self> {
+        let kubeconfig = match path {
+            Some(path) => Kubeconfig::read_from(path),
+            None => Kubeconfig::read(),
+        }
+        .map_err(|e| Error::K8s(format!("kubeconfig read failed: {}", e)))?;
+        let options = KubeConfigOptions {
+            context: Some(context.to_string()),
+            ..KubeConfigOptions::default()
+        };
+        let config = Config::from_custom_kubeconfig(kubeconfig, &options)
+            .await
+            .map_err(|e| Error::K8s(format!("kubeconfig context {}: {}", context, e)))?;
+        let namespace = config.default_namespace.clone();
+        let client = Client::try_from(config)
+            .map_err(|e| Error::K8s(format!("client creation failed: {}", e)))?;
+
+        Ok(Self {
+            client,
+            namespace,
+            context: Some(context.to_string()),
+        })
+    }
+
+    /// Wrap an existing client, using the `default` namespace.
+    pub fn from_client(client: Client) -> Self {
+        Self {
+            client,
+            namespace: "default".to_string(),
+            context: None,
+        }
+    }
+
     /// Set the default namespace for operations.
     pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
         self.namespace = namespace.into();
@@ -57,6 +138,12 @@ impl K8sClient {
         &self.namespace
     }
 
+    /// The kubeconfig context the client was created for, if it was
+    /// created with [`from_kubeconfig_context`](Self::from_kubeconfig_context).
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
     /// List pods in the current namespace.
     pub async fn list_pods(&self, label_selector: Option<&str>) -> Result<Vec<PodInfo>> {
         let api: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
@@ -125,6 +212,242 @@ impl K8sClient {
     }
 }
 
+/// Direction of a pod file copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyDirection {
+    /// Copy from the local filesystem into the pod.
+    ToPod,
+    /// Copy from the pod to the local filesystem.
+    FromPod,
+}
+
+impl CopyDirection {
+    /// Parse from string representation (`"to"`/`"upload"` or `"from"`/`"download"`).
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "to" | "to_pod" | "upload" => Some(CopyDirection::ToPod),
+            "from" | "from_pod" | "download" => Some(CopyDirection::FromPod),
+            _ => None,
+        }
+    }
+}
+
+impl K8sClient {
+    /// Copy a file or directory to or from a pod container, like `kubectl cp`.
+    ///
+    /// Files are streamed as a tar archive over `exec`, so the container must
+    /// have `tar` on its `PATH`. The local path is checked against `paths`:
+    /// read access for uploads, write access for downloads. Local symlinks
+    /// are sent as links rather than followed, and downloads containing
+    /// links or other special entries are rejected.
+    ///
+    /// Returns the number of archive bytes transferred.
+    pub async fn cp(
+        &self,
+        pod: &str,
+        container: Option<&str>,
+        local_path: &Path,
+        remote_path: &str,
+        direction: CopyDirection,
+        paths: &PathAllowlist,
+    ) -> Result<u64> {
+        let (remote_dir, remote_name) = split_remote_path(remote_path)?;
+        let api: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
+
+        let mut params = AttachParams::default()
+            .stdin(direction == CopyDirection::ToPod)
+            .stdout(direction == CopyDirection::FromPod)
+            .stderr(true);
+        if let Some(container) = container {
+            params = params.container(container);
+        }
+
+        match direction {
+            CopyDirection::ToPod => {
+                paths.check_read(local_path)?;
+
+                let local = local_path.to_path_buf();
+                let name = remote_name.clone();
+                let archive = tokio::task::spawn_blocking(move || build_archive(&local, &name))
+                    .await
+                    .map_err(|e| Error::Internal(e.to_string()))??;
+
+                let mut process = api
+                    .exec(
+                        pod,
+                        vec!["tar", "xf", "-", "-C", &remote_dir, "--"],
+                        &params,
+                    )
+                    .await
+                    .map_err(|e| Error::K8s(format!("exec failed: {}", e)))?;
+
+                let mut stdin = process
+                    .stdin()
+                    .ok_or_else(|| Error::K8s("exec stdin unavailable".to_string()))?;
+                stdin.write_all(&archive).await?;
+                stdin.shutdown().await?;
+                drop(stdin);
+
+                finish_exec(process, "cp").await?;
+                Ok(archive.len() as u64)
+            }
+            CopyDirection::FromPod => {
+                paths.check_write(local_path)?;
+
+                let mut process = api
+                    .exec(
+                        pod,
+                        vec!["tar", "cf", "-", "-C", &remote_dir, "--", &remote_name],
+                        &params,
+                    )
+                    .await
+                    .map_err(|e| Error::K8s(format!("exec failed: {}", e)))?;
+
+                let mut stdout = process
+                    .stdout()
+                    .ok_or_else(|| Error::K8s("exec stdout unavailable".to_string()))?;
+
+                // Spool to disk so large files are never held in memory
+                let spool = spool_path(local_path);
+                let mut file = tokio::fs::File::create(&spool).await?;
+                let copied = tokio::io::copy(&mut stdout, &mut file).await;
+                drop(stdout);
+                file.flush().await?;
+                drop(file);
+
+                let result = match (copied, finish_exec(process, "cp").await) {
+                    (Ok(bytes), Ok(())) => {
+                        let (spool_for_unpack, local) = (spool.clone(), local_path.to_path_buf());
+                        tokio::task::spawn_blocking(move || {
+                            let archive = std::fs::File::open(&spool_for_unpack)?;
+                            unpack_archive(archive, &remote_name, &local)
+                        })
+                        .await
+                        .map_err(|e| Error::Internal(e.to_string()))
+                        .and_then(|r| r)
+                        .map(|_| bytes)
+                    }
+                    (Err(e), _) => Err(e.into()),
+                    (_, Err(e)) => Err(e),
+                };
+
+                let _ = tokio::fs::remove_file(&spool).await;
+                result
+            }
+        }
+    }
+}
+
+/// Wait for an exec'd process and turn a non-success status into an error.
+async fn finish_exec(mut process: AttachedProcess, operation: &str) -> Result<()> {
+    let mut stderr_output = String::new();
+    if let Some(mut stderr) = process.stderr() {
+        let _ = stderr.read_to_string(&mut stderr_output).await;
+    }
+
+    let status = match process.take_status() {
+        Some(status) => status.await,
+        None => None,
+    };
+    process
+        .join()
+        .await
+        .map_err(|e| Error::K8s(format!("{} failed: {}", operation, e)))?;
+
+    match status {
+        Some(status) if status.status.as_deref() != Some("Success") => Err(Error::K8s(format!(
+            "{} failed: {} {}",
+            operation,
+            status.message.unwrap_or_default(),
+            stderr_output.trim()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Split an absolute or relative remote path into its directory and final component.
+fn split_remote_path(remote_path: &str) -> Result<(String, String)> {
+    let trimmed = remote_path.trim_end_matches('/');
+    let (dir, name) = match trimmed.rfind('/') {
+        Some(0) => ("/", &trimmed[1..]),
+        Some(i) => (&trimmed[..i], &trimmed[i + 1..]),
+        None => (".", trimmed),
+    };
+
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(Error::InvalidArgument(format!(
+            "remote path '{}' must name a file or directory",
+            remote_path
+        )));
+    }
+
+    Ok((dir.to_string(), name.to_string()))
+}
+
+fn spool_path(local_path: &Path) -> PathBuf {
+    let name = local_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "download".to_string());
+    local_path.with_file_name(format!(".{}.k8s-cp.tar", name))
+}
+
+/// Archive a local file or directory under `name`.
+fn build_archive(local_path: &Path, name: &str) -> Result<Vec<u8>> {
+    let mut builder = tar::Builder::new(Vec::new());
+    builder.follow_symlinks(false);
+    if local_path.is_dir() {
+        builder.append_dir_all(name, local_path)?;
+    } else {
+        builder.append_path_with_name(local_path, name)?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+/// Unpack an archive whose entries live under `name`, placing `name` at `dest`.
+///
+/// Only regular files and directories are accepted; links could point the
+/// writes of later entries outside `dest`.
+fn unpack_archive(reader: impl Read, name: &str, dest: &Path) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+
+        let entry_type = entry.header().entry_type();
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            return Err(Error::K8s(format!(
+                "unsupported archive entry '{}' ({:?})",
+                entry_path.display(),
+                entry_type
+            )));
+        }
+
+        let relative = entry_path.strip_prefix(name).map_err(|_| {
+            Error::K8s(format!(
+                "unexpected archive entry '{}'",
+                entry_path.display()
+            ))
+        })?;
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            return Err(Error::K8s(format!(
+                "unsafe archive entry '{}'",
+                entry_path.display()
+            )));
+        }
+
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&target)?;
+    }
+    Ok(())
+}
+
 /// Simplified pod information.
 #[derive(Debug, Clone)]
 pub struct PodInfo {
@@ -194,10 +517,663 @@ impl PodInfo {
     }
 }
 
+/// Log lines of a `k8s.run_job` call kept for the result; older lines are
+/// dropped.
+const MAX_JOB_LOG_LINES: usize = 10_000;
+
+/// A [`K8sClient`] and the Tokio runtime its requests run on, for the
+/// synchronous `k8s` host functions.
+#[derive(Clone)]
+pub struct K8sHost {
+    client: K8sClient,
+    runtime: tokio::runtime::Handle,
+    clusters: Arc<ClusterRegistry>,
+    current: Arc<RwLock<Option<String>>>,
+}
+
+impl K8sHost {
+    /// Run `client`'s requests on `runtime`.
+    pub fn new(client: K8sClient, runtime: tokio::runtime::Handle) -> Self {
+        Self {
+            client,
+            runtime,
+            clusters: Arc::new(ClusterRegistry::new()),
+            current: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Make the clusters in `registry` selectable with `k8s.use_cluster`.
+    pub fn with_clusters(mut self, registry: ClusterRegistry) -> Self {
+        self.clusters = Arc::new(registry);
+        self
+    }
+
+    /// The registered clusters.
+    pub fn clusters(&self) -> &ClusterRegistry {
+        &self.clusters
+    }
+
+    /// The name of the cluster selected with `k8s.use_cluster`, if any.
+    pub fn current_cluster(&self) -> Option<String> {
+        self.current.read().clone()
+    }
+
+    /// The client for the selected cluster, with its default namespace, or
+    /// the host's own client if none is selected.
+    pub fn client(&self) -> K8sClient {
+        self.current
+            .read()
+            .as_deref()
+            .and_then(|name| self.clusters.get(name))
+            .unwrap_or(&self.client)
+            .clone()
+    }
+
+    /// Run a future to completion on the runtime.
+    ///
+    /// On a worker thread of a multi-threaded runtime the thread is first
+    /// handed off with `block_in_place`. A current-thread runtime cannot be
+    /// blocked from inside, so scripts driven from one must run on another
+    /// thread.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        use tokio::runtime::{Handle, RuntimeFlavor};
+
+        match Handle::try_current() {
+            Ok(current) if current.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| self.runtime.block_on(future))
+            }
+            _ => self.runtime.block_on(future),
+        }
+    }
+
+    /// The client for `namespace`, or the default one.
+    fn client_for(&self, namespace: Option<String>) -> K8sClient {
+        match namespace {
+            Some(namespace) => self.client().with_namespace(namespace),
+            None => self.client(),
+        }
+    }
+
+    fn select_cluster(&self, name: Option<String>) {
+        *self.current.write() = name;
+    }
+}
+
+fn host_error(function: &str, e: impl std::fmt::Display) -> fusabi_host::Error {
+    fusabi_host::Error::host_function(format!("{}: {}", function, e))
+}
+
+fn string_arg<'a>(
+    args: &'a [Value],
+    index: usize,
+    name: &str,
+    function: &str,
+) -> fusabi_host::Result<&'a str> {
+    args.get(index)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| host_error(function, format!("{} must be a string", name)))
+}
+
+/// Optional string argument; null and missing are `None`.
+fn optional_string(
+    args: &[Value],
+    index: usize,
+    name: &str,
+    function: &str,
+) -> fusabi_host::Result<Option<String>> {
+    match args.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => v
+            .as_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or_else(|| host_error(function, format!("{} must be a string", name))),
+    }
+}
+
+/// List pods.
+///
+/// # Arguments
+///
+/// * `args[0]` - Label selector such as `"app=web"`, or null for all pods
+/// * `args[1]` - Namespace (optional, defaults to the client's)
+///
+/// # Returns
+///
+/// List of maps with `name`, `namespace`, `phase`, `pod_ip`, `node_name`
+/// and `labels`
+pub fn list_pods(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.list_pods";
+
+    let selector = optional_string(args, 0, "label selector", FUNCTION)?;
+    let client = host.client_for(optional_string(args, 1, "namespace", FUNCTION)?);
+    safety
+        .check_k8s("list", "pods", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let pods = host
+        .block_on(client.list_pods(selector.as_deref()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::List(pods.iter().map(PodInfo::to_value).collect()))
+}
+
+/// Read a config map's data.
+///
+/// # Arguments
+///
+/// * `args[0]` - Config map name
+/// * `args[1]` - Namespace (optional, defaults to the client's)
+pub fn get_configmap(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.get_configmap";
+
+    let name = string_arg(args, 0, "name", FUNCTION)?;
+    let client = host.client_for(optional_string(args, 1, "namespace", FUNCTION)?);
+    safety
+        .check_k8s("get", "configmaps", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let data = host
+        .block_on(client.get_configmap(name))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(string_map(data))
+}
+
+/// Read a secret's data, decoded as UTF-8; binary values are left out.
+///
+/// # Arguments
+///
+/// * `args[0]` - Secret name
+/// * `args[1]` - Namespace (optional, defaults to the client's)
+pub fn get_secret(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.get_secret";
+
+    let name = string_arg(args, 0, "name", FUNCTION)?;
+    let client = host.client_for(optional_string(args, 1, "namespace", FUNCTION)?);
+    safety
+        .check_k8s("get", "secrets", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let data = host
+        .block_on(client.get_secret(name))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(string_map(data))
+}
+
+/// List namespace names.
+pub fn list_namespaces(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.list_namespaces";
+
+    safety
+        .check_k8s("list", "namespaces", None)
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let namespaces = host
+        .block_on(host.client().list_namespaces())
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::List(
+        namespaces.into_iter().map(Value::String).collect(),
+    ))
+}
+
+/// Copy a file or directory to or from a pod container, like `kubectl cp`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pod name
+/// * `args[1]` - Local path, checked against the path allowlist
+/// * `args[2]` - Path in the container
+/// * `args[3]` - Direction: `"to"` (upload) or `"from"` (download)
+/// * `args[4]` - Options map with `container` and `namespace` (optional)
+///
+/// # Returns
+///
+/// Number of archive bytes transferred
+pub fn cp(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.cp";
+
+    let pod = string_arg(args, 0, "pod", FUNCTION)?;
+    let local = string_arg(args, 1, "local path", FUNCTION)?;
+    let remote = string_arg(args, 2, "remote path", FUNCTION)?;
+    let direction = string_arg(args, 3, "direction", FUNCTION)?;
+    let direction = CopyDirection::parse(direction)
+        .ok_or_else(|| host_error(FUNCTION, format!("unknown direction '{}'", direction)))?;
+    let options = args.get(4).and_then(|v| v.as_map());
+    let option = |key: &str| -> fusabi_host::Result<Option<String>> {
+        match options.and_then(|o| o.get(key)) {
+            None | Some(Value::Null) => Ok(None),
+            Some(v) => v
+                .as_str()
+                .map(|s| Some(s.to_string()))
+                .ok_or_else(|| host_error(FUNCTION, format!("{} must be a string", key))),
+        }
+    };
+    let container = option("container")?;
+    let client = host.client_for(option("namespace")?);
+    safety
+        .check_k8s("create", "pods/exec", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let bytes = host
+        .block_on(client.cp(
+            pod,
+            container.as_deref(),
+            Path::new(local),
+            remote,
+            direction,
+            &safety.paths,
+        ))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::Int(bytes as i64))
+}
+
+/// Run a command in a container image as a Job and wait for it.
+///
+/// The timeout is capped at the safety configuration's maximum.
+///
+/// # Arguments
+///
+/// * `args[0]` - Container image
+/// * `args[1]` - Command and arguments, as a list of strings
+/// * `args[2]` - Options map read by [`RunJobOptions::from_value`]
+///   (optional)
+///
+/// # Returns
+///
+/// The [`JobOutcome`] map plus `logs`, the container's log lines (at most
+/// the last 10,000)
+pub fn run_job(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.run_job";
+
+    let image = string_arg(args, 0, "image", FUNCTION)?;
+    let command = args
+        .get(1)
+        .and_then(|v| v.as_list())
+        .and_then(|items| {
+            items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| host_error(FUNCTION, "command must be a list of strings"))?;
+    let mut options = RunJobOptions::from_value(args.get(2).unwrap_or(&Value::Null))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    options.timeout = safety.clamp_timeout(options.timeout);
+    let namespace = options
+        .namespace
+        .clone()
+        .unwrap_or_else(|| host.client().namespace().to_string());
+    safety
+        .check_k8s("create", "jobs", Some(&namespace))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let mut logs = VecDeque::new();
+    let outcome = host
+        .block_on(host.client().run_job(image, &command, &options, |line| {
+            if logs.len() == MAX_JOB_LOG_LINES {
+                logs.pop_front();
+            }
+            logs.push_back(Value::String(line.to_string()));
+        }))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let mut result = outcome.to_value();
+    if let Value::Map(map) = &mut result {
+        map.insert("logs".to_string(), Value::List(logs.into()));
+    }
+    Ok(result)
+}
+
+/// Resolve the objects of a manifest argument and check each against the
+/// safety configuration for `verb`.
+fn resolve_manifest(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    verb: &str,
+    args: &[Value],
+    function: &str,
+) -> fusabi_host::Result<(Vec<ResolvedObject>, ApplyOptions)> {
+    let manifest = args
+        .first()
+        .ok_or_else(|| host_error(function, "manifest is required"))?;
+    let objects = apply::manifests_from_value(manifest).map_err(|e| host_error(function, e))?;
+    let options = ApplyOptions::from_value(args.get(1).unwrap_or(&Value::Null))
+        .map_err(|e| host_error(function, e))?;
+    safety
+        .check_k8s_verb(verb)
+        .map_err(|e| host_error(function, e))?;
+
+    let client = host.client();
+    let mut resolved = Vec::with_capacity(objects.len());
+    for object in objects {
+        let object = host
+            .block_on(client.resolve(object))
+            .map_err(|e| host_error(function, e))?;
+        safety
+            .check_k8s(verb, &object.resource.plural, object.namespace.as_deref())
+            .map_err(|e| host_error(function, e))?;
+        resolved.push(object);
+    }
+    Ok((resolved, options))
+}
+
+/// Server-side apply a manifest, like `kubectl apply --server-side`.
+///
+/// Every object is resolved and checked with the `patch` verb before the
+/// first is applied.
+///
+/// # Arguments
+///
+/// * `args[0]` - YAML or JSON manifest string, object map, or list of
+///   either
+/// * `args[1]` - Options map with `field_manager` (default `"fusabi"`),
+///   `force` and `dry_run` (optional)
+///
+/// # Returns
+///
+/// List of [`ResourceRef`] maps of the applied objects
+pub fn apply(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.apply";
+
+    let (objects, options) = resolve_manifest(host, safety, "patch", args, FUNCTION)?;
+    let mut applied = Vec::with_capacity(objects.len());
+    for object in &objects {
+        let resource = host
+            .block_on(host.client().apply_resolved(object, &options))
+            .map_err(|e| host_error(FUNCTION, e))?;
+        applied.push(resource.to_value());
+    }
+    Ok(Value::List(applied))
+}
+
+/// Create the objects of a manifest; fails on the first that already
+/// exists.
+///
+/// Takes the same arguments as [`apply`](fn@apply) and checks the `create` verb.
+pub fn create(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.create";
+
+    let (objects, options) = resolve_manifest(host, safety, "create", args, FUNCTION)?;
+    let mut created = Vec::with_capacity(objects.len());
+    for object in &objects {
+        let resource = host
+            .block_on(host.client().create_resolved(object, &options))
+            .map_err(|e| host_error(FUNCTION, e))?;
+        created.push(resource.to_value());
+    }
+    Ok(Value::List(created))
+}
+
+/// Delete an object.
+///
+/// # Arguments
+///
+/// * `args[0]` - Kind: `"Deployment"`, `"apps/v1/Deployment"` or
+///   `"v1/ConfigMap"`
+/// * `args[1]` - Object name
+/// * `args[2]` - Namespace (optional, defaults to the client's; ignored
+///   for cluster-scoped kinds)
+///
+/// # Returns
+///
+/// Whether the object existed
+pub fn delete(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.delete";
+
+    let kind = string_arg(args, 0, "kind", FUNCTION)?;
+    let name = string_arg(args, 1, "name", FUNCTION)?;
+    let client = host.client_for(optional_string(args, 2, "namespace", FUNCTION)?);
+    safety
+        .check_k8s_verb("delete")
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let (resource, namespaced) = host
+        .block_on(client.resolve_kind(kind))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_k8s(
+            "delete",
+            &resource.plural,
+            namespaced.then_some(client.namespace()),
+        )
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let existed = host
+        .block_on(client.delete_resolved(&resource, namespaced, name))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::Bool(existed))
+}
+
+fn string_map(data: HashMap<String, String>) -> Value {
+    Value::Map(
+        data.into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect(),
+    )
+}
+
+/// A host whose client talks to a stub API server that serves discovery for
+/// the `v1` resources `pods` (namespaced) and `nodes` (cluster-scoped), lists
+/// no nodes and no pods in `default`, and answers every other request with 404.
+#[cfg(test)]
+pub(crate) fn stub_discovery_host(runtime: &tokio::runtime::Runtime) -> K8sHost {
+    use std::io::Write;
+
+    const DISCOVERY: &str = r#"{"kind":"APIResourceList","apiVersion":"v1","groupVersion":"v1","resources":[
+        {"name":"pods","singularName":"pod","namespaced":true,"kind":"Pod","verbs":["get","list","watch"]},
+        {"name":"nodes","singularName":"node","namespaced":false,"kind":"Node","verbs":["get","list","watch"]}]}"#;
+    const EMPTY_LIST: &str = r#"{"kind":"List","apiVersion":"v1","metadata":{},"items":[]}"#;
+    const NOT_FOUND: &str =
+        r#"{"kind":"Status","apiVersion":"v1","status":"Failure","reason":"NotFound","code":404}"#;
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request);
+            let path = request.split_whitespace().nth(1).unwrap_or_default();
+            let (status, body) = match path.split('?').next().unwrap_or_default() {
+                "/api/v1" => ("200 OK", DISCOVERY),
+                "/api/v1/nodes" | "/api/v1/namespaces/default/pods" if !path.contains("watch=") => {
+                    ("200 OK", EMPTY_LIST)
+                }
+                _ => ("404 Not Found", NOT_FOUND),
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+
+    let client = {
+        let _guard = runtime.enter();
+        Client::try_from(Config::new(url.parse().unwrap())).unwrap()
+    };
+    K8sHost::new(K8sClient::from_client(client), runtime.handle().clone())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_split_remote_path() {
+        assert_eq!(
+            split_remote_path("/tmp/heap.hprof").unwrap(),
+            ("/tmp".to_string(), "heap.hprof".to_string())
+        );
+        assert_eq!(
+            split_remote_path("/opt/tools/").unwrap(),
+            ("/opt".to_string(), "tools".to_string())
+        );
+        assert_eq!(
+            split_remote_path("/data").unwrap(),
+            ("/".to_string(), "data".to_string())
+        );
+        assert!(split_remote_path("/").is_err());
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::create_dir(src.path().join("bin")).unwrap();
+        std::fs::write(src.path().join("bin/tool"), b"#!/bin/sh").unwrap();
+
+        let archive = build_archive(src.path(), "tools").unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let target = dest.path().join("restored");
+        unpack_archive(archive.as_slice(), "tools", &target).unwrap();
+        assert_eq!(
+            std::fs::read(target.join("bin/tool")).unwrap(),
+            b"#!/bin/sh"
+        );
+
+        // Entries outside the requested name are rejected
+        assert!(unpack_archive(archive.as_slice(), "other", &target).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_archive_rejects_links() {
+        // Local symlinks are archived as links, not followed
+        let src = tempfile::tempdir().unwrap();
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(outside.path(), b"secret").unwrap();
+        std::os::unix::fs::symlink(outside.path(), src.path().join("link")).unwrap();
+
+        let archive = build_archive(src.path(), "tools").unwrap();
+        let mut entries = tar::Archive::new(archive.as_slice());
+        assert!(entries
+            .entries()
+            .unwrap()
+            .any(|e| e.unwrap().header().entry_type().is_symlink()));
+
+        let dest = tempfile::tempdir().unwrap();
+        let target = dest.path().join("restored");
+        assert!(unpack_archive(archive.as_slice(), "tools", &target).is_err());
+
+        // Remote archives may not plant links either
+        for entry_type in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let mut builder = tar::Builder::new(Vec::new());
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(entry_type);
+            header.set_size(0);
+            builder
+                .append_link(&mut header, "tools/escape", "/etc")
+                .unwrap();
+            let archive = builder.into_inner().unwrap();
+            assert!(unpack_archive(archive.as_slice(), "tools", &target).is_err());
+            assert!(!target.join("escape").exists());
+        }
+    }
+
+    #[test]
+    fn test_host_functions_check_safety() {
+        use crate::safety::K8sAllowlist;
+        use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let client = {
+            let _guard = runtime.enter();
+            Client::try_from(Config::new("http://127.0.0.1:9".parse().unwrap())).unwrap()
+        };
+        let host = K8sHost::new(
+            K8sClient::from_client(client).with_namespace("apps"),
+            runtime.handle().clone(),
+        );
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+        let message = |result: fusabi_host::Result<Value>| result.unwrap_err().to_string();
+
+        let denied = SafetyConfig::new();
+        assert!(message(list_pods(&host, &denied, &[], &ctx)).contains("kubernetes access"));
+
+        let safety = SafetyConfig::new().with_allow_k8s(true).with_k8s(
+            K8sAllowlist::all()
+                .with_verbs(["get"])
+                .with_namespaces(["apps"]),
+        );
+        assert!(message(list_pods(&host, &safety, &[], &ctx)).contains("verb not allowed"));
+        assert!(message(list_namespaces(&host, &safety, &[], &ctx)).contains("verb not allowed"));
+        let args = [
+            Value::String("db".into()),
+            Value::String("kube-system".into()),
+        ];
+        assert!(message(get_secret(&host, &safety, &args, &ctx)).contains("namespace not allowed"));
+        assert!(message(get_secret(&host, &safety, &[], &ctx)).contains("name must be a string"));
+
+        // Manifest verbs are refused before any discovery request
+        let manifest = [Value::String(
+            "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\n".into(),
+        )];
+        assert!(message(apply(&host, &safety, &manifest, &ctx)).contains("verb not allowed: patch"));
+        let args = [
+            Value::String("v1/ConfigMap".into()),
+            Value::String("x".into()),
+        ];
+        assert!(message(delete(&host, &safety, &args, &ctx)).contains("verb not allowed: delete"));
+    }
+
     #[test]
     fn test_pod_info_to_value() {
         let info = PodInfo {
@@ -0,0 +1,597 @@
+//! Applying, creating and deleting arbitrary resources.
+//!
+//! Manifests are YAML or JSON strings, possibly with several `---`
+//! separated documents or a `kind: List`, or Value trees of the same
+//! shape. Each object's kind is resolved through API discovery, so custom
+//! resources work the same as built-in ones:
+//!
+//! ```rust,ignore
+//! let applied = client
+//!     .apply_yaml(&std::fs::read_to_string("deploy.yaml")?, &ApplyOptions::default())
+//!     .await?;
+//! for resource in &applied {
+//!     println!("{} {} applied", resource.kind, resource.name);
+//! }
+//! client.delete("apps/v1/Deployment", "old-web").await?;
+//! ```
+//!
+//! [`K8sClient::apply_yaml`] uses server-side apply: the server merges the
+//! manifest into the live object and records the fields it sets as owned
+//! by the field manager, so later applies of a smaller manifest remove the
+//! fields that were dropped.
+
+use std::collections::HashMap;
+
+use kube::api::{
+    Api, ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams, PostParams,
+};
+use kube::core::GroupVersion;
+use kube::discovery::{self, Discovery, Scope};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use super::K8sClient;
+use crate::error::{Error, Result};
+use fusabi_host::Value;
+
+/// Field manager recorded for server-side applies unless another is set.
+pub const DEFAULT_FIELD_MANAGER: &str = "fusabi";
+
+/// Options for [`K8sClient::apply_yaml`] and [`K8sClient::create_yaml`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApplyOptions {
+    /// Field manager owning the applied fields.
+    pub field_manager: String,
+    /// Take ownership of fields owned by other managers instead of failing
+    /// with a conflict. Applies only to server-side apply.
+    pub force: bool,
+    /// Validate and run admission without persisting anything.
+    pub dry_run: bool,
+}
+
+impl Default for ApplyOptions {
+    fn default() -> Self {
+        Self {
+            field_manager: DEFAULT_FIELD_MANAGER.to_string(),
+            force: false,
+            dry_run: false,
+        }
+    }
+}
+
+impl ApplyOptions {
+    /// Set the field manager.
+    pub fn with_field_manager(mut self, manager: impl Into<String>) -> Self {
+        self.field_manager = manager.into();
+        self
+    }
+
+    /// Force ownership of conflicting fields.
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Only validate on the server.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Read options from a map with `field_manager`, `force` and
+    /// `dry_run`.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let mut options = Self::default();
+        let Some(map) = value.as_map() else {
+            return match value {
+                Value::Null => Ok(options),
+                _ => Err(Error::invalid_argument("apply options must be a map")),
+            };
+        };
+
+        let flag = |key: &str| -> Result<Option<bool>> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(v) => v
+                    .as_bool()
+                    .map(Some)
+                    .ok_or_else(|| Error::invalid_argument(format!("{} must be a boolean", key))),
+            }
+        };
+
+        match map.get("field_manager") {
+            None | Some(Value::Null) => {}
+            Some(Value::String(manager)) if !manager.is_empty() => {
+                options.field_manager = manager.clone();
+            }
+            Some(_) => {
+                return Err(Error::invalid_argument(
+                    "field_manager must be a non-empty string",
+                ))
+            }
+        }
+        if let Some(force) = flag("force")? {
+            options.force = force;
+        }
+        if let Some(dry_run) = flag("dry_run")? {
+            options.dry_run = dry_run;
+        }
+        Ok(options)
+    }
+}
+
+/// An object from a manifest with its API resource found by discovery.
+#[derive(Debug, Clone)]
+pub struct ResolvedObject {
+    /// The object as written in the manifest.
+    pub object: DynamicObject,
+    /// Its API resource, including the plural name RBAC checks use.
+    pub resource: ApiResource,
+    /// Namespace it goes in, or `None` for cluster-scoped resources.
+    pub namespace: Option<String>,
+}
+
+impl ResolvedObject {
+    /// The object's name.
+    pub fn name(&self) -> &str {
+        self.object.metadata.name.as_deref().unwrap_or_default()
+    }
+}
+
+/// Identity of an object the server returned after a write.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceRef {
+    /// API version such as `apps/v1`.
+    pub api_version: String,
+    /// Kind such as `Deployment`.
+    pub kind: String,
+    /// Object name.
+    pub name: String,
+    /// Namespace, or `None` for cluster-scoped objects.
+    pub namespace: Option<String>,
+    /// Server-assigned UID.
+    pub uid: Option<String>,
+    /// Resource version after the write.
+    pub resource_version: Option<String>,
+}
+
+impl ResourceRef {
+    fn new(object: &DynamicObject, resource: &ApiResource) -> Self {
+        let metadata = &object.metadata;
+        Self {
+            api_version: object
+                .types
+                .as_ref()
+                .map_or_else(|| resource.api_version.clone(), |t| t.api_version.clone()),
+            kind: object
+                .types
+                .as_ref()
+                .map_or_else(|| resource.kind.clone(), |t| t.kind.clone()),
+            name: metadata.name.clone().unwrap_or_default(),
+            namespace: metadata.namespace.clone(),
+            uid: metadata.uid.clone(),
+            resource_version: metadata.resource_version.clone(),
+        }
+    }
+
+    /// Convert to a Value map with `api_version`, `kind`, `name`,
+    /// `namespace`, `uid` and `resource_version`.
+    pub fn to_value(&self) -> Value {
+        let optional = |v: &Option<String>| v.clone().map_or(Value::Null, Value::String);
+        let mut map = HashMap::new();
+        map.insert(
+            "api_version".to_string(),
+            Value::String(self.api_version.clone()),
+        );
+        map.insert("kind".to_string(), Value::String(self.kind.clone()));
+        map.insert("name".to_string(), Value::String(self.name.clone()));
+        map.insert("namespace".to_string(), optional(&self.namespace));
+        map.insert("uid".to_string(), optional(&self.uid));
+        map.insert(
+            "resource_version".to_string(),
+            optional(&self.resource_version),
+        );
+        Value::Map(map)
+    }
+}
+
+/// Parse a YAML or JSON manifest into its objects.
+///
+/// Documents are separated by `---`; empty documents are skipped and
+/// `kind: List` documents are replaced by their items. Every object needs
+/// `apiVersion`, `kind` and `metadata.name`.
+pub fn parse_manifests(manifest: &str) -> Result<Vec<DynamicObject>> {
+    let mut objects = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(manifest) {
+        let json = JsonValue::deserialize(document)
+            .map_err(|e| Error::invalid_argument(format!("invalid manifest: {}", e)))?;
+        collect_objects(json, &mut objects)?;
+    }
+    Ok(objects)
+}
+
+/// Read objects from a Value: a manifest string, one object map, or a list
+/// of either.
+pub fn manifests_from_value(value: &Value) -> Result<Vec<DynamicObject>> {
+    match value {
+        Value::String(manifest) => parse_manifests(manifest),
+        Value::List(items) => {
+            let mut objects = Vec::new();
+            for item in items {
+                objects.extend(manifests_from_value(item)?);
+            }
+            Ok(objects)
+        }
+        Value::Map(_) => {
+            let json: JsonValue = fusabi_host::from_value_serde(value.clone())
+                .map_err(|e| Error::invalid_argument(format!("invalid manifest: {}", e)))?;
+            let mut objects = Vec::new();
+            collect_objects(json, &mut objects)?;
+            Ok(objects)
+        }
+        _ => Err(Error::invalid_argument(
+            "manifest must be a string, map or list",
+        )),
+    }
+}
+
+fn collect_objects(json: JsonValue, objects: &mut Vec<DynamicObject>) -> Result<()> {
+    match json {
+        JsonValue::Null => Ok(()),
+        JsonValue::Object(mut map) if map.get("kind").and_then(|k| k.as_str()) == Some("List") => {
+            match map.remove("items") {
+                Some(JsonValue::Array(items)) => {
+                    for item in items {
+                        collect_objects(item, objects)?;
+                    }
+                    Ok(())
+                }
+                None | Some(JsonValue::Null) => Ok(()),
+                Some(_) => Err(Error::invalid_argument("List items must be a list")),
+            }
+        }
+        JsonValue::Object(_) => {
+            let object: DynamicObject = serde_json::from_value(json)
+                .map_err(|e| Error::invalid_argument(format!("invalid manifest: {}", e)))?;
+            let Some(types) = &object.types else {
+                return Err(Error::invalid_argument(
+                    "manifest object needs apiVersion and kind",
+                ));
+            };
+            if types.api_version.is_empty() || types.kind.is_empty() {
+                return Err(Error::invalid_argument(
+                    "manifest object needs apiVersion and kind",
+                ));
+            }
+            if object.metadata.name.as_deref().map_or(true, str::is_empty) {
+                return Err(Error::invalid_argument(format!(
+                    "{} in manifest has no metadata.name",
+                    types.kind
+                )));
+            }
+            objects.push(object);
+            Ok(())
+        }
+        _ => Err(Error::invalid_argument(
+            "manifest documents must be objects",
+        )),
+    }
+}
+
+impl K8sClient {
+    /// Find an object's API resource and the namespace it goes in.
+    ///
+    /// Namespaced objects without `metadata.namespace` go in the client's
+    /// namespace.
+    pub async fn resolve(&self, object: DynamicObject) -> Result<ResolvedObject> {
+        let types = object
+            .types
+            .as_ref()
+            .ok_or_else(|| Error::invalid_argument("object needs apiVersion and kind"))?;
+        let gvk = GroupVersionKind::try_from(types)
+            .map_err(|e| Error::invalid_argument(format!("invalid apiVersion: {}", e.0)))?;
+        let (resource, capabilities) = discovery::pinned_kind(&self.client, &gvk)
+            .await
+            .map_err(|e| Error::K8s(format!("unknown kind {}: {}", types.kind, e)))?;
+
+        let namespace = match capabilities.scope {
+            Scope::Namespaced => Some(
+                object
+                    .metadata
+                    .namespace
+                    .clone()
+                    .unwrap_or_else(|| self.namespace.clone()),
+            ),
+            Scope::Cluster => None,
+        };
+        Ok(ResolvedObject {
+            object,
+            resource,
+            namespace,
+        })
+    }
+
+    /// Find the API resource for a kind, given as `group/version/Kind`,
+    /// `version/Kind` for the core group, or a bare `Kind`.
+    ///
+    /// A bare kind is looked up in every API group, core first.
+    pub async fn resolve_kind(&self, kind: &str) -> Result<(ApiResource, bool)> {
+        let unknown =
+            |e: &dyn std::fmt::Display| Error::K8s(format!("unknown kind {}: {}", kind, e));
+
+        if let Some((group_version, name)) = kind.rsplit_once('/') {
+            let gvk = group_version
+                .parse::<GroupVersion>()
+                .map_err(|e| Error::invalid_argument(format!("invalid kind '{}': {}", kind, e.0)))?
+                .with_kind(name);
+            let (resource, capabilities) = discovery::pinned_kind(&self.client, &gvk)
+                .await
+                .map_err(|e| unknown(&e))?;
+            return Ok((resource, capabilities.scope == Scope::Namespaced));
+        }
+
+        let discovery = Discovery::new(self.client.clone())
+            .run()
+            .await
+            .map_err(|e| unknown(&e))?;
+        let mut groups: Vec<_> = discovery.groups().collect();
+        groups.sort_by_key(|group| !group.name().is_empty());
+        groups
+            .into_iter()
+            .find_map(|group| group.recommended_kind(kind))
+            .map(|(resource, capabilities)| (resource, capabilities.scope == Scope::Namespaced))
+            .ok_or_else(|| unknown(&"not served by the cluster"))
+    }
+
+    pub(super) fn dynamic_api(
+        &self,
+        resource: &ApiResource,
+        namespace: Option<&str>,
+    ) -> Api<DynamicObject> {
+        match namespace {
+            Some(namespace) => Api::namespaced_with(self.client.clone(), namespace, resource),
+            None => Api::all_with(self.client.clone(), resource),
+        }
+    }
+
+    /// Server-side apply a resolved object.
+    pub async fn apply_resolved(
+        &self,
+        resolved: &ResolvedObject,
+        options: &ApplyOptions,
+    ) -> Result<ResourceRef> {
+        let applied = self.apply_object(resolved, options).await?;
+        Ok(ResourceRef::new(&applied, &resolved.resource))
+    }
+
+    /// Server-side apply a resolved object, returning the whole object the
+    /// server stored.
+    pub async fn apply_object(
+        &self,
+        resolved: &ResolvedObject,
+        options: &ApplyOptions,
+    ) -> Result<DynamicObject> {
+        let api = self.dynamic_api(&resolved.resource, resolved.namespace.as_deref());
+        let mut params = PatchParams::apply(&options.field_manager);
+        if options.force {
+            params = params.force();
+        }
+        params.dry_run = options.dry_run;
+
+        let mut object = resolved.object.clone();
+        object.metadata.namespace = resolved.namespace.clone();
+        api.patch(resolved.name(), &params, &Patch::Apply(&object))
+            .await
+            .map_err(|e| {
+                Error::K8s(format!(
+                    "apply {} {} failed: {}",
+                    resolved.resource.kind,
+                    resolved.name(),
+                    e
+                ))
+            })
+    }
+
+    /// Create a resolved object; fails if it already exists.
+    pub async fn create_resolved(
+        &self,
+        resolved: &ResolvedObject,
+        options: &ApplyOptions,
+    ) -> Result<ResourceRef> {
+        let api = self.dynamic_api(&resolved.resource, resolved.namespace.as_deref());
+        let params = PostParams {
+            dry_run: options.dry_run,
+            field_manager: Some(options.field_manager.clone()),
+        };
+
+        let mut object = resolved.object.clone();
+        object.metadata.namespace = resolved.namespace.clone();
+        let created = api.create(&params, &object).await.map_err(|e| {
+            Error::K8s(format!(
+                "create {} {} failed: {}",
+                resolved.resource.kind,
+                resolved.name(),
+                e
+            ))
+        })?;
+        Ok(ResourceRef::new(&created, &resolved.resource))
+    }
+
+    /// Server-side apply every object in a manifest, in order.
+    ///
+    /// All objects are resolved before the first is applied, so an unknown
+    /// kind fails the call without partial changes.
+    pub async fn apply_yaml(
+        &self,
+        manifest: &str,
+        options: &ApplyOptions,
+    ) -> Result<Vec<ResourceRef>> {
+        let mut resolved = Vec::new();
+        for object in parse_manifests(manifest)? {
+            resolved.push(self.resolve(object).await?);
+        }
+        let mut applied = Vec::with_capacity(resolved.len());
+        for object in &resolved {
+            applied.push(self.apply_resolved(object, options).await?);
+        }
+        Ok(applied)
+    }
+
+    /// Create every object in a manifest, in order.
+    pub async fn create_yaml(
+        &self,
+        manifest: &str,
+        options: &ApplyOptions,
+    ) -> Result<Vec<ResourceRef>> {
+        let mut resolved = Vec::new();
+        for object in parse_manifests(manifest)? {
+            resolved.push(self.resolve(object).await?);
+        }
+        let mut created = Vec::with_capacity(resolved.len());
+        for object in &resolved {
+            created.push(self.create_resolved(object, options).await?);
+        }
+        Ok(created)
+    }
+
+    /// Delete an object by kind (see [`resolve_kind`](Self::resolve_kind))
+    /// and name, in the client's namespace for namespaced kinds.
+    ///
+    /// Dependents are deleted in the background. Returns `false` if the
+    /// object did not exist.
+    pub async fn delete(&self, kind: &str, name: &str) -> Result<bool> {
+        let (resource, namespaced) = self.resolve_kind(kind).await?;
+        self.delete_resolved(&resource, namespaced, name).await
+    }
+
+    /// Delete an object of a kind already found with
+    /// [`resolve_kind`](Self::resolve_kind).
+    pub async fn delete_resolved(
+        &self,
+        resource: &ApiResource,
+        namespaced: bool,
+        name: &str,
+    ) -> Result<bool> {
+        let api = self.dynamic_api(resource, namespaced.then_some(self.namespace.as_str()));
+        match api.delete(name, &DeleteParams::background()).await {
+            Ok(_) => Ok(true),
+            Err(kube::Error::Api(response)) if response.code == 404 => Ok(false),
+            Err(e) => Err(Error::K8s(format!(
+                "delete {} {} failed: {}",
+                resource.kind, name, e
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifests() {
+        let manifest = r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: settings
+  namespace: apps
+data:
+  mode: fast
+---
+# comment-only documents are skipped
+---
+apiVersion: v1
+kind: List
+items:
+  - apiVersion: apps/v1
+    kind: Deployment
+    metadata:
+      name: web
+  - apiVersion: v1
+    kind: Service
+    metadata:
+      name: web
+"#;
+        let objects = parse_manifests(manifest).unwrap();
+        let names: Vec<_> = objects
+            .iter()
+            .map(|o| {
+                (
+                    o.types.as_ref().unwrap().kind.as_str(),
+                    o.metadata.name.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("ConfigMap", "settings"),
+                ("Deployment", "web"),
+                ("Service", "web")
+            ]
+        );
+        assert_eq!(objects[0].metadata.namespace.as_deref(), Some("apps"));
+        assert_eq!(objects[0].data["data"]["mode"], "fast");
+
+        let json = r#"{"apiVersion": "v1", "kind": "Namespace", "metadata": {"name": "ci"}}"#;
+        assert_eq!(parse_manifests(json).unwrap().len(), 1);
+
+        let missing_kind = "apiVersion: v1\nmetadata:\n  name: x\n";
+        assert!(parse_manifests(missing_kind).is_err());
+        let missing_name = "apiVersion: v1\nkind: ConfigMap\nmetadata: {}\n";
+        assert!(parse_manifests(missing_name)
+            .unwrap_err()
+            .to_string()
+            .contains("ConfigMap in manifest has no metadata.name"));
+        assert!(parse_manifests("- 1\n- 2\n").is_err());
+    }
+
+    #[test]
+    fn test_manifests_from_value() {
+        let object = Value::Map(HashMap::from([
+            ("apiVersion".to_string(), Value::String("v1".into())),
+            ("kind".to_string(), Value::String("ConfigMap".into())),
+            (
+                "metadata".to_string(),
+                Value::Map(HashMap::from([(
+                    "name".to_string(),
+                    Value::String("settings".into()),
+                )])),
+            ),
+        ]));
+        let yaml = Value::String("apiVersion: v1\nkind: Secret\nmetadata:\n  name: db\n".into());
+
+        let objects = manifests_from_value(&Value::List(vec![object, yaml])).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[1].types.as_ref().unwrap().kind, "Secret");
+        assert!(manifests_from_value(&Value::Int(1)).is_err());
+    }
+
+    #[test]
+    fn test_apply_options_from_value() {
+        let options = ApplyOptions::from_value(&Value::Map(HashMap::from([
+            (
+                "field_manager".to_string(),
+                Value::String("deployer".into()),
+            ),
+            ("force".to_string(), Value::Bool(true)),
+        ])))
+        .unwrap();
+        assert_eq!(
+            options,
+            ApplyOptions::default()
+                .with_field_manager("deployer")
+                .with_force(true)
+        );
+        assert_eq!(
+            ApplyOptions::from_value(&Value::Null).unwrap(),
+            ApplyOptions::default()
+        );
+        assert!(ApplyOptions::from_value(&Value::Map(HashMap::from([(
+            "dry_run".to_string(),
+            Value::Int(1)
+        )])))
+        .is_err());
+    }
+}
@@ -0,0 +1,746 @@
+//! Resource usage against requests and limits.
+//!
+//! [`K8sClient::capacity_report`] joins the container requests and limits
+//! of a namespace's running pods with their live usage from the metrics
+//! API (`metrics.k8s.io`, served by metrics-server), grouped by the
+//! workload that owns each pod:
+//!
+//! ```rust,ignore
+//! let report = client.capacity_report(&CapacityOptions::default()).await?;
+//! for workload in &report.workloads {
+//!     if workload.status == CapacityStatus::OverProvisioned {
+//!         println!("{} {}: {:?}", workload.kind, workload.name, workload.issues);
+//!     }
+//! }
+//! ```
+//!
+//! Usage is a point-in-time sample, so a weekly report should be built from
+//! several runs rather than one.
+
+use std::collections::{BTreeMap, HashMap};
+
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::api::{Api, ApiResource, DynamicObject, GroupVersionKind, ListParams};
+
+use super::{host_error, optional_string, K8sClient, K8sHost};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// Options for [`K8sClient::capacity_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityOptions {
+    /// Only pods matching this label selector.
+    pub label_selector: Option<String>,
+    /// Usage below this fraction of the request is over-provisioned.
+    pub over_provisioned_below: f64,
+    /// Usage above this fraction of the request is under-provisioned.
+    pub under_provisioned_above: f64,
+    /// Usage above this fraction of the limit is near the limit.
+    pub near_limit_above: f64,
+}
+
+impl Default for CapacityOptions {
+    fn default() -> Self {
+        Self {
+            label_selector: None,
+            over_provisioned_below: 0.5,
+            under_provisioned_above: 1.0,
+            near_limit_above: 0.9,
+        }
+    }
+}
+
+impl CapacityOptions {
+    /// Only report pods matching a label selector.
+    pub fn with_label_selector(mut self, selector: impl Into<String>) -> Self {
+        self.label_selector = Some(selector.into());
+        self
+    }
+
+    /// Read options from a map with `label_selector`,
+    /// `over_provisioned_below`, `under_provisioned_above` and
+    /// `near_limit_above`.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let mut options = Self::default();
+        let Some(map) = value.as_map() else {
+            return match value {
+                Value::Null => Ok(options),
+                _ => Err(Error::invalid_argument("capacity options must be a map")),
+            };
+        };
+        match map.get("label_selector") {
+            None | Some(Value::Null) => {}
+            Some(v) => {
+                options.label_selector = Some(
+                    v.as_str()
+                        .ok_or_else(|| Error::invalid_argument("label_selector must be a string"))?
+                        .to_string(),
+                )
+            }
+        }
+        for (key, field) in [
+            (
+                "over_provisioned_below",
+                &mut options.over_provisioned_below,
+            ),
+            (
+                "under_provisioned_above",
+                &mut options.under_provisioned_above,
+            ),
+            ("near_limit_above", &mut options.near_limit_above),
+        ] {
+            let value = match map.get(key) {
+                None | Some(Value::Null) => continue,
+                Some(Value::Int(n)) => *n as f64,
+                Some(Value::Float(f)) => *f,
+                Some(_) => {
+                    return Err(Error::invalid_argument(format!("{} must be a number", key)))
+                }
+            };
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(Error::invalid_argument(format!("{} must be positive", key)));
+            }
+            *field = value;
+        }
+        Ok(options)
+    }
+}
+
+/// How a workload's usage compares with what it reserves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapacityStatus {
+    /// Usage is within the thresholds.
+    Ok,
+    /// Usage is well below the requests.
+    OverProvisioned,
+    /// Usage exceeds the requests or is near the limits.
+    UnderProvisioned,
+    /// No container sets CPU or memory requests.
+    NoRequests,
+    /// The metrics API reported no usage for the workload's pods.
+    NoMetrics,
+}
+
+impl CapacityStatus {
+    /// Snake-case name, such as `"over_provisioned"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::OverProvisioned => "over_provisioned",
+            Self::UnderProvisioned => "under_provisioned",
+            Self::NoRequests => "no_requests",
+            Self::NoMetrics => "no_metrics",
+        }
+    }
+}
+
+/// Requests, limits and usage of one resource, summed over a workload's
+/// containers. CPU is in cores, memory in bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceCapacity {
+    /// Sum of the requests, if any container sets one.
+    pub request: Option<f64>,
+    /// Sum of the limits, or `None` if any container is unlimited.
+    pub limit: Option<f64>,
+    /// Current usage, if the metrics API reported it.
+    pub usage: Option<f64>,
+}
+
+impl ResourceCapacity {
+    /// Usage as a fraction of the request.
+    pub fn utilization(&self) -> Option<f64> {
+        let request = self.request.filter(|r| *r > 0.0)?;
+        Some(self.usage? / request)
+    }
+
+    /// Requested but unused capacity.
+    pub fn idle(&self) -> Option<f64> {
+        Some((self.request? - self.usage?).max(0.0))
+    }
+
+    fn to_value(self, integer: bool) -> Value {
+        let number = |v: Option<f64>| match v {
+            None => Value::Null,
+            Some(v) if integer => Value::Int(v.round() as i64),
+            Some(v) => Value::Float(v),
+        };
+        let mut map = HashMap::new();
+        map.insert("request".to_string(), number(self.request));
+        map.insert("limit".to_string(), number(self.limit));
+        map.insert("usage".to_string(), number(self.usage));
+        map.insert(
+            "utilization".to_string(),
+            self.utilization().map_or(Value::Null, Value::Float),
+        );
+        Value::Map(map)
+    }
+}
+
+/// Capacity of one workload: the pods owned by a Deployment, StatefulSet,
+/// DaemonSet, Job or other controller, or a bare pod.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkloadCapacity {
+    /// Owner kind, such as `Deployment`, or `Pod` for a bare pod.
+    pub kind: String,
+    /// Owner name.
+    pub name: String,
+    /// Running pods.
+    pub pods: usize,
+    /// CPU, in cores.
+    pub cpu: ResourceCapacity,
+    /// Memory, in bytes.
+    pub memory: ResourceCapacity,
+    /// Overall status.
+    pub status: CapacityStatus,
+    /// Findings such as `cpu_over_provisioned` or `memory_near_limit`.
+    pub issues: Vec<String>,
+}
+
+impl WorkloadCapacity {
+    /// Convert to a Value map with `kind`, `name`, `pods`, `cpu` and
+    /// `memory` (maps with `request`, `limit`, `usage` and
+    /// `utilization`), `status` and `issues`.
+    pub fn to_value(&self) -> Value {
+        let mut map = HashMap::new();
+        map.insert("kind".to_string(), Value::String(self.kind.clone()));
+        map.insert("name".to_string(), Value::String(self.name.clone()));
+        map.insert("pods".to_string(), Value::Int(self.pods as i64));
+        map.insert("cpu".to_string(), self.cpu.to_value(false));
+        map.insert("memory".to_string(), self.memory.to_value(true));
+        map.insert(
+            "status".to_string(),
+            Value::String(self.status.as_str().to_string()),
+        );
+        map.insert(
+            "issues".to_string(),
+            Value::List(self.issues.iter().cloned().map(Value::String).collect()),
+        );
+        Value::Map(map)
+    }
+}
+
+/// Result of [`K8sClient::capacity_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacityReport {
+    /// Namespace reported on.
+    pub namespace: String,
+    /// Whether the metrics API answered; without it every workload is
+    /// [`CapacityStatus::NoMetrics`].
+    pub metrics_available: bool,
+    /// Workloads, sorted by kind and name.
+    pub workloads: Vec<WorkloadCapacity>,
+}
+
+impl CapacityReport {
+    /// Convert to a Value map with `namespace`, `metrics_available`,
+    /// `workloads` and `totals`: namespace-wide `cpu` and `memory` maps as
+    /// for a workload, plus `idle_cpu` (cores) and `idle_memory` (bytes)
+    /// requested but unused by over-provisioned workloads.
+    pub fn to_value(&self) -> Value {
+        let total = |resource: fn(&WorkloadCapacity) -> ResourceCapacity| {
+            let mut sum = ResourceCapacity::default();
+            let add = |a: Option<f64>, b: Option<f64>| match (a, b) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            };
+            for workload in &self.workloads {
+                let r = resource(workload);
+                sum.request = add(sum.request, r.request);
+                sum.usage = add(sum.usage, r.usage);
+            }
+            sum
+        };
+        let idle = |resource: fn(&WorkloadCapacity) -> ResourceCapacity| -> f64 {
+            self.workloads
+                .iter()
+                .filter(|w| w.status == CapacityStatus::OverProvisioned)
+                .filter_map(|w| resource(w).idle())
+                .sum()
+        };
+
+        let mut totals = HashMap::new();
+        totals.insert("cpu".to_string(), total(|w| w.cpu).to_value(false));
+        totals.insert("memory".to_string(), total(|w| w.memory).to_value(true));
+        totals.insert("idle_cpu".to_string(), Value::Float(idle(|w| w.cpu)));
+        totals.insert(
+            "idle_memory".to_string(),
+            Value::Int(idle(|w| w.memory).round() as i64),
+        );
+
+        let mut map = HashMap::new();
+        map.insert(
+            "namespace".to_string(),
+            Value::String(self.namespace.clone()),
+        );
+        map.insert(
+            "metrics_available".to_string(),
+            Value::Bool(self.metrics_available),
+        );
+        map.insert(
+            "workloads".to_string(),
+            Value::List(self.workloads.iter().map(|w| w.to_value()).collect()),
+        );
+        map.insert("totals".to_string(), Value::Map(totals));
+        Value::Map(map)
+    }
+}
+
+/// Parse a Kubernetes quantity such as `250m`, `1.5`, `128Mi` or `1e3`
+/// into base units (cores or bytes).
+pub fn parse_quantity(quantity: &str) -> Option<f64> {
+    const SUFFIXES: [(&str, f64); 15] = [
+        ("Ki", 1024.0),
+        ("Mi", 1_048_576.0),
+        ("Gi", 1_073_741_824.0),
+        ("Ti", 1_099_511_627_776.0),
+        ("Pi", 1_125_899_906_842_624.0),
+        ("Ei", 1_152_921_504_606_846_976.0),
+        ("n", 1e-9),
+        ("u", 1e-6),
+        ("m", 1e-3),
+        ("k", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+        ("P", 1e15),
+        ("E", 1e18),
+    ];
+    let quantity = quantity.trim();
+    for (suffix, scale) in SUFFIXES {
+        if let Some(number) = quantity.strip_suffix(suffix) {
+            if let Ok(number) = number.parse::<f64>() {
+                return Some(number * scale);
+            }
+        }
+    }
+    // Plain numbers and exponents such as `1e3`
+    quantity.parse().ok().filter(|n: &f64| n.is_finite())
+}
+
+/// The workload owning a pod: its controller, with a ReplicaSet replaced by
+/// its Deployment, or the pod itself.
+fn pod_owner(pod: &Pod) -> (String, String) {
+    let name = pod.metadata.name.clone().unwrap_or_default();
+    let Some(owner) = pod
+        .metadata
+        .owner_references
+        .iter()
+        .flatten()
+        .find(|owner| owner.controller == Some(true))
+    else {
+        return ("Pod".to_string(), name);
+    };
+    if owner.kind == "ReplicaSet" {
+        // Deployment ReplicaSets are named `<deployment>-<pod-template-hash>`
+        let hash = pod
+            .metadata
+            .labels
+            .as_ref()
+            .and_then(|labels| labels.get("pod-template-hash"));
+        if let Some(deployment) = hash.and_then(|hash| {
+            owner
+                .name
+                .strip_suffix(hash.as_str())
+                .and_then(|n| n.strip_suffix('-'))
+        }) {
+            return ("Deployment".to_string(), deployment.to_string());
+        }
+    }
+    (owner.kind.clone(), owner.name.clone())
+}
+
+/// Usage of a pod's containers by name, as (cores, bytes).
+type ContainerUsage = HashMap<String, (Option<f64>, Option<f64>)>;
+
+/// Container usage from a `PodMetrics` object.
+fn container_usage(metrics: &DynamicObject) -> ContainerUsage {
+    let containers = metrics.data.get("containers").and_then(|c| c.as_array());
+    containers
+        .into_iter()
+        .flatten()
+        .filter_map(|container| {
+            let name = container.get("name")?.as_str()?.to_string();
+            let usage = container.get("usage");
+            let quantity = |resource: &str| {
+                usage
+                    .and_then(|u| u.get(resource))
+                    .and_then(|q| q.as_str())
+                    .and_then(parse_quantity)
+            };
+            Some((name, (quantity("cpu"), quantity("memory"))))
+        })
+        .collect()
+}
+
+/// Group running pods into workloads and compare their usage with their
+/// requests and limits. `usage` maps pod names to their container usage.
+fn build_workloads(
+    pods: &[Pod],
+    usage: &HashMap<String, ContainerUsage>,
+    options: &CapacityOptions,
+) -> Vec<WorkloadCapacity> {
+    #[derive(Default)]
+    struct Totals {
+        pods: usize,
+        cpu: ResourceCapacity,
+        memory: ResourceCapacity,
+        unlimited_cpu: bool,
+        unlimited_memory: bool,
+    }
+
+    fn add(total: &mut Option<f64>, value: Option<f64>) {
+        if let Some(value) = value {
+            *total = Some(total.unwrap_or(0.0) + value);
+        }
+    }
+
+    let mut workloads: BTreeMap<(String, String), Totals> = BTreeMap::new();
+    for pod in pods {
+        let running = pod.status.as_ref().and_then(|s| s.phase.as_deref()) == Some("Running");
+        let Some(spec) = pod.spec.as_ref().filter(|_| running) else {
+            continue;
+        };
+        let totals = workloads.entry(pod_owner(pod)).or_default();
+        totals.pods += 1;
+        let pod_usage = pod.metadata.name.as_ref().and_then(|name| usage.get(name));
+        for container in &spec.containers {
+            let resources = container.resources.clone().unwrap_or_default();
+            let quantity = |map: &Option<BTreeMap<String, Quantity>>, resource: &str| {
+                map.as_ref()
+                    .and_then(|m| m.get(resource))
+                    .and_then(|q| parse_quantity(&q.0))
+            };
+            add(
+                &mut totals.cpu.request,
+                quantity(&resources.requests, "cpu"),
+            );
+            add(
+                &mut totals.memory.request,
+                quantity(&resources.requests, "memory"),
+            );
+            match quantity(&resources.limits, "cpu") {
+                Some(limit) => add(&mut totals.cpu.limit, Some(limit)),
+                None => totals.unlimited_cpu = true,
+            }
+            match quantity(&resources.limits, "memory") {
+                Some(limit) => add(&mut totals.memory.limit, Some(limit)),
+                None => totals.unlimited_memory = true,
+            }
+            if let Some((cpu, memory)) = pod_usage.and_then(|u| u.get(&container.name)) {
+                add(&mut totals.cpu.usage, *cpu);
+                add(&mut totals.memory.usage, *memory);
+            }
+        }
+    }
+
+    workloads
+        .into_iter()
+        .map(|((kind, name), mut totals)| {
+            if totals.unlimited_cpu {
+                totals.cpu.limit = None;
+            }
+            if totals.unlimited_memory {
+                totals.memory.limit = None;
+            }
+            let (status, issues) = assess(&totals.cpu, &totals.memory, options);
+            WorkloadCapacity {
+                kind,
+                name,
+                pods: totals.pods,
+                cpu: totals.cpu,
+                memory: totals.memory,
+                status,
+                issues,
+            }
+        })
+        .collect()
+}
+
+/// Classify a workload's CPU and memory against the thresholds.
+fn assess(
+    cpu: &ResourceCapacity,
+    memory: &ResourceCapacity,
+    options: &CapacityOptions,
+) -> (CapacityStatus, Vec<String>) {
+    if cpu.usage.is_none() && memory.usage.is_none() {
+        return (CapacityStatus::NoMetrics, Vec::new());
+    }
+    let mut issues = Vec::new();
+    let (mut over, mut under) = (false, false);
+    for (resource, capacity) in [("cpu", cpu), ("memory", memory)] {
+        let Some(usage) = capacity.usage else {
+            continue;
+        };
+        match capacity.utilization() {
+            None => issues.push(format!("{}_no_request", resource)),
+            Some(u) if u < options.over_provisioned_below => {
+                over = true;
+                issues.push(format!("{}_over_provisioned", resource));
+            }
+            Some(u) if u > options.under_provisioned_above => {
+                under = true;
+                issues.push(format!("{}_under_provisioned", resource));
+            }
+            Some(_) => {}
+        }
+        if capacity
+            .limit
+            .is_some_and(|limit| usage > limit * options.near_limit_above)
+        {
+            under = true;
+            issues.push(format!("{}_near_limit", resource));
+        }
+    }
+
+    let status = if cpu.request.is_none() && memory.request.is_none() {
+        CapacityStatus::NoRequests
+    } else if under {
+        CapacityStatus::UnderProvisioned
+    } else if over {
+        CapacityStatus::OverProvisioned
+    } else {
+        CapacityStatus::Ok
+    };
+    (status, issues)
+}
+
+impl K8sClient {
+    /// Compare the requests and limits of the namespace's running pods with
+    /// their usage, per workload.
+    ///
+    /// When the metrics API is not installed the report is still built,
+    /// with [`CapacityReport::metrics_available`] unset.
+    pub async fn capacity_report(&self, options: &CapacityOptions) -> Result<CapacityReport> {
+        let mut params = ListParams::default();
+        if let Some(selector) = &options.label_selector {
+            params = params.labels(selector);
+        }
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
+        let pods = pods
+            .list(&params)
+            .await
+            .map_err(|e| Error::K8s(format!("list pods failed: {}", e)))?
+            .items;
+
+        let resource = ApiResource::from_gvk_with_plural(
+            &GroupVersionKind::gvk("metrics.k8s.io", "v1beta1", "PodMetrics"),
+            "pods",
+        );
+        let metrics: Api<DynamicObject> =
+            Api::namespaced_with(self.client.clone(), &self.namespace, &resource);
+        let (metrics_available, usage) = match metrics.list(&params).await {
+            Ok(list) => (
+                true,
+                list.items
+                    .iter()
+                    .filter_map(|m| Some((m.metadata.name.clone()?, container_usage(m))))
+                    .collect(),
+            ),
+            // Not installed, or metrics-server not ready
+            Err(kube::Error::Api(e)) if e.code == 404 || e.code == 503 => {
+                tracing::debug!("k8s.capacity_report: metrics API unavailable: {}", e);
+                (false, HashMap::new())
+            }
+            Err(e) => return Err(Error::K8s(format!("list pod metrics failed: {}", e))),
+        };
+
+        Ok(CapacityReport {
+            namespace: self.namespace.clone(),
+            metrics_available,
+            workloads: build_workloads(&pods, &usage, options),
+        })
+    }
+}
+
+/// Report over- and under-provisioned workloads in a namespace by comparing
+/// container requests and limits with live usage from the metrics API.
+///
+/// Checked as `list` on `pods`, which also covers `pods` in the
+/// `metrics.k8s.io` group.
+///
+/// # Arguments
+///
+/// * `args[0]` - Namespace (optional, defaults to the client's)
+/// * `args[1]` - Options map with `label_selector`,
+///   `over_provisioned_below` (default 0.5), `under_provisioned_above`
+///   (default 1.0) and `near_limit_above` (default 0.9), as fractions of
+///   the request or limit (optional)
+///
+/// # Returns
+///
+/// The [`CapacityReport`] map. Workload `status` is `"ok"`,
+/// `"over_provisioned"`, `"under_provisioned"`, `"no_requests"` or
+/// `"no_metrics"`.
+pub fn capacity_report(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.capacity_report";
+
+    let client = host.client_for(optional_string(args, 0, "namespace", FUNCTION)?);
+    let options = CapacityOptions::from_value(args.get(1).unwrap_or(&Value::Null))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_k8s("list", "pods", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let report = host
+        .block_on(client.capacity_report(&options))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(report.to_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pod(
+        name: &str,
+        owner: Option<(&str, &str)>,
+        hash: &str,
+        resources: serde_json::Value,
+    ) -> Pod {
+        let owners = owner.map(|(kind, owner)| {
+            json!([{ "apiVersion": "apps/v1", "kind": kind, "name": owner, "uid": "1", "controller": true }])
+        });
+        serde_json::from_value(json!({
+            "metadata": {
+                "name": name,
+                "labels": { "pod-template-hash": hash },
+                "ownerReferences": owners,
+            },
+            "spec": { "containers": [{ "name": "app", "resources": resources }] },
+            "status": { "phase": "Running" },
+        }))
+        .unwrap()
+    }
+
+    fn usage(entries: &[(&str, f64, f64)]) -> HashMap<String, ContainerUsage> {
+        entries
+            .iter()
+            .map(|(pod, cpu, memory)| {
+                (
+                    pod.to_string(),
+                    HashMap::from([("app".to_string(), (Some(*cpu), Some(*memory)))]),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_quantity() {
+        assert_eq!(parse_quantity("250m"), Some(0.25));
+        assert_eq!(parse_quantity("2"), Some(2.0));
+        assert_eq!(parse_quantity("128Mi"), Some(134_217_728.0));
+        assert_eq!(parse_quantity("1G"), Some(1e9));
+        assert_eq!(parse_quantity("1e3"), Some(1000.0));
+        assert_eq!(parse_quantity("1E"), Some(1e18));
+        assert!((parse_quantity("1500000n").unwrap() - 0.0015).abs() < 1e-12);
+        assert_eq!(parse_quantity("lots"), None);
+    }
+
+    #[test]
+    fn test_pod_owner() {
+        let web = pod(
+            "web-7d9f-abcde",
+            Some(("ReplicaSet", "web-7d9f")),
+            "7d9f",
+            json!({}),
+        );
+        assert_eq!(pod_owner(&web), ("Deployment".into(), "web".into()));
+        let db = pod("db-0", Some(("StatefulSet", "db")), "x", json!({}));
+        assert_eq!(pod_owner(&db), ("StatefulSet".into(), "db".into()));
+        let bare = pod("debug", None, "x", json!({}));
+        assert_eq!(pod_owner(&bare), ("Pod".into(), "debug".into()));
+    }
+
+    #[test]
+    fn test_build_workloads() {
+        let requests = json!({
+            "requests": { "cpu": "1", "memory": "1Gi" },
+            "limits": { "cpu": "2", "memory": "1Gi" },
+        });
+        let pods = [
+            pod(
+                "web-1-a",
+                Some(("ReplicaSet", "web-1")),
+                "1",
+                requests.clone(),
+            ),
+            pod(
+                "web-1-b",
+                Some(("ReplicaSet", "web-1")),
+                "1",
+                requests.clone(),
+            ),
+            pod("db-0", Some(("StatefulSet", "db")), "x", requests),
+            pod("debug", None, "x", json!({})),
+        ];
+        let gib = 1_073_741_824.0;
+        let usage = usage(&[
+            ("web-1-a", 0.1, 0.2 * gib),
+            ("web-1-b", 0.2, 0.2 * gib),
+            ("db-0", 0.8, 0.95 * gib),
+            ("debug", 0.05, 1e6),
+        ]);
+        let workloads = build_workloads(&pods, &usage, &CapacityOptions::default());
+        let find = |name: &str| workloads.iter().find(|w| w.name == name).unwrap();
+
+        let web = find("web");
+        assert_eq!((web.kind.as_str(), web.pods), ("Deployment", 2));
+        assert_eq!(web.cpu.request, Some(2.0));
+        assert_eq!(web.cpu.limit, Some(4.0));
+        assert!((web.cpu.usage.unwrap() - 0.3).abs() < 1e-9);
+        assert_eq!(web.status, CapacityStatus::OverProvisioned);
+        assert_eq!(
+            web.issues,
+            vec!["cpu_over_provisioned", "memory_over_provisioned"]
+        );
+
+        let db = find("db");
+        assert_eq!(db.status, CapacityStatus::UnderProvisioned);
+        assert_eq!(db.issues, vec!["memory_near_limit"]);
+
+        let debug = find("debug");
+        assert_eq!(debug.status, CapacityStatus::NoRequests);
+        assert_eq!(debug.cpu.limit, None);
+
+        let no_metrics = build_workloads(&pods, &HashMap::new(), &CapacityOptions::default());
+        assert!(no_metrics
+            .iter()
+            .all(|w| w.status == CapacityStatus::NoMetrics));
+
+        let report = CapacityReport {
+            namespace: "apps".into(),
+            metrics_available: true,
+            workloads,
+        };
+        let value = report.to_value();
+        let totals = value.as_map().unwrap()["totals"].as_map().unwrap();
+        assert_eq!(totals["idle_cpu"], Value::Float(2.0 - (0.1 + 0.2)));
+    }
+
+    #[test]
+    fn test_capacity_options_from_value() {
+        let options = CapacityOptions::from_value(&Value::Map(HashMap::from([
+            ("over_provisioned_below".to_string(), Value::Float(0.3)),
+            (
+                "label_selector".to_string(),
+                Value::String("app=web".into()),
+            ),
+        ])))
+        .unwrap();
+        assert_eq!(options.over_provisioned_below, 0.3);
+        assert_eq!(options.label_selector.as_deref(), Some("app=web"));
+        assert!(CapacityOptions::from_value(&Value::Map(HashMap::from([(
+            "near_limit_above".to_string(),
+            Value::Int(0),
+        )])))
+        .is_err());
+    }
+}
@@ -0,0 +1,304 @@
+//! Selecting between several clusters.
+//!
+//! A [`ClusterRegistry`] names one [`K8sClient`] per cluster, each with its
+//! own default namespace. Rust callers can [`fan_out`](ClusterRegistry::fan_out)
+//! an operation across all of them; scripts switch the cluster the other
+//! `k8s` functions use with `k8s.use_cluster`, which only selects clusters
+//! allowed by [`K8sAllowlist::contexts`](crate::safety::K8sAllowlist::contexts):
+//!
+//! ```rust,ignore
+//! let clusters = ClusterRegistry::from_kubeconfig_contexts(None, ["staging", "prod"]).await?;
+//! for (cluster, pods) in clusters.fan_out(|client| async move { client.list_pods().await }).await {
+//!     println!("{}: {} pods", cluster, pods?.len());
+//! }
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::Path;
+
+use super::{host_error, K8sClient, K8sHost};
+use crate::error::Result;
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// Clients for several clusters, by name.
+#[derive(Clone, Default)]
+pub struct ClusterRegistry {
+    clusters: BTreeMap<String, K8sClient>,
+}
+
+impl ClusterRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with a client for each kubeconfig context, named
+    /// after the context.
+    pub async fn from_kubeconfig_contexts<I, S>(path: Option<&Path>, contexts: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut registry = Self::new();
+        for context in contexts {
+            let context = context.as_ref();
+            let client = K8sClient::from_kubeconfig_context(path, context).await?;
+            registry = registry.with_cluster(context, client);
+        }
+        Ok(registry)
+    }
+
+    /// Add a cluster, replacing any with the same name.
+    pub fn with_cluster(mut self, name: impl Into<String>, client: K8sClient) -> Self {
+        self.clusters.insert(name.into(), client);
+        self
+    }
+
+    /// The client for a cluster.
+    pub fn get(&self, name: &str) -> Option<&K8sClient> {
+        self.clusters.get(name)
+    }
+
+    /// The cluster names, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.clusters.keys().map(String::as_str)
+    }
+
+    /// The number of clusters.
+    pub fn len(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Whether no clusters are registered.
+    pub fn is_empty(&self) -> bool {
+        self.clusters.is_empty()
+    }
+
+    /// Run `f` against every cluster concurrently, returning each result
+    /// with its cluster's name, in name order.
+    pub async fn fan_out<F, Fut, T>(&self, f: F) -> Vec<(String, Result<T>)>
+    where
+        F: Fn(K8sClient) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let results =
+            futures::future::join_all(self.clusters.values().map(|client| f(client.clone()))).await;
+        self.clusters.keys().cloned().zip(results).collect()
+    }
+}
+
+fn cluster_value(name: &str, client: &K8sClient, current: bool) -> Value {
+    let mut map = HashMap::new();
+    map.insert("name".to_string(), Value::String(name.to_string()));
+    map.insert(
+        "context".to_string(),
+        client
+            .context()
+            .map_or(Value::Null, |context| Value::String(context.to_string())),
+    );
+    map.insert(
+        "namespace".to_string(),
+        Value::String(client.namespace().to_string()),
+    );
+    map.insert("current".to_string(), Value::Bool(current));
+    Value::Map(map)
+}
+
+/// List the registered clusters that may be selected.
+///
+/// # Returns
+///
+/// A list of maps with `name`, `context`, `namespace` (the cluster's
+/// default) and `current` (whether it is selected)
+pub fn clusters(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.clusters";
+
+    safety
+        .check_k8s_verb("get")
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let current = host.current_cluster();
+    let clusters = host
+        .clusters()
+        .names()
+        .filter(|name| safety.check_k8s_context(name).is_ok())
+        .filter_map(|name| {
+            let client = host.clusters().get(name)?;
+            Some(cluster_value(
+                name,
+                client,
+                current.as_deref() == Some(name),
+            ))
+        })
+        .collect();
+    Ok(Value::List(clusters))
+}
+
+/// Select the cluster later `k8s` calls use.
+///
+/// # Arguments
+///
+/// * `args[0]` - Registered cluster name, or `null` for the host's default
+///   client
+///
+/// # Returns
+///
+/// The previously selected cluster name, or `null`
+pub fn use_cluster(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.use_cluster";
+
+    let name = match args.first() {
+        None | Some(Value::Null) => None,
+        Some(Value::String(name)) => Some(name.clone()),
+        Some(_) => {
+            return Err(host_error(
+                FUNCTION,
+                "cluster name must be a string or null",
+            ))
+        }
+    };
+    if let Some(name) = &name {
+        safety
+            .check_k8s_context(name)
+            .map_err(|e| host_error(FUNCTION, e))?;
+        if host.clusters().get(name).is_none() {
+            return Err(host_error(FUNCTION, format!("unknown cluster: {}", name)));
+        }
+    }
+
+    let previous = host.current_cluster();
+    host.select_cluster(name);
+    Ok(previous.map_or(Value::Null, Value::String))
+}
+
+/// The name of the selected cluster, or `null` if the host's default client
+/// is in use.
+pub fn current_cluster(
+    host: &K8sHost,
+    _safety: &SafetyConfig,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    Ok(host.current_cluster().map_or(Value::Null, Value::String))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::K8sAllowlist;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    const KUBECONFIG: &str = r#"
+apiVersion: v1
+kind: Config
+current-context: staging
+clusters:
+- name: staging
+  cluster:
+    server: https://staging.example.com:6443
+- name: prod
+  cluster:
+    server: https://prod.example.com:6443
+users:
+- name: ops
+  user:
+    token: secret
+contexts:
+- name: staging
+  context:
+    cluster: staging
+    user: ops
+    namespace: apps
+- name: prod
+  context:
+    cluster: prod
+    user: ops
+"#;
+
+    fn kubeconfig() -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), KUBECONFIG).unwrap();
+        file
+    }
+
+    #[tokio::test]
+    async fn test_from_kubeconfig_context() {
+        let file = kubeconfig();
+
+        let client = K8sClient::from_kubeconfig_context(Some(file.path()), "staging")
+            .await
+            .unwrap();
+        assert_eq!(client.context(), Some("staging"));
+        assert_eq!(client.namespace(), "apps");
+
+        let client = K8sClient::from_kubeconfig_context(Some(file.path()), "prod")
+            .await
+            .unwrap();
+        assert_eq!(client.namespace(), "default");
+
+        assert!(K8sClient::from_kubeconfig_context(Some(file.path()), "dev")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_use_cluster() {
+        let file = kubeconfig();
+        let registry =
+            ClusterRegistry::from_kubeconfig_contexts(Some(file.path()), ["staging", "prod"])
+                .await
+                .unwrap();
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["prod", "staging"]);
+
+        let default = registry.get("prod").unwrap().clone().with_namespace("ops");
+        let host = K8sHost::new(default, tokio::runtime::Handle::current()).with_clusters(registry);
+        let safety = SafetyConfig::new()
+            .with_allow_k8s(true)
+            .with_k8s(K8sAllowlist::all().with_contexts(["staging"]));
+        let ctx = ctx();
+
+        let listed = clusters(&host, &safety, &[], &ctx).unwrap();
+        let Value::List(listed) = listed else {
+            panic!("expected a list");
+        };
+        assert_eq!(listed.len(), 1);
+
+        assert!(use_cluster(&host, &safety, &[Value::String("prod".into())], &ctx).is_err());
+        assert!(use_cluster(&host, &safety, &[Value::String("dev".into())], &ctx).is_err());
+        assert_eq!(host.client().namespace(), "ops");
+
+        let previous = use_cluster(&host, &safety, &[Value::String("staging".into())], &ctx);
+        assert_eq!(previous.unwrap(), Value::Null);
+        assert_eq!(host.client().context(), Some("staging"));
+        assert_eq!(host.client().namespace(), "apps");
+        assert_eq!(
+            current_cluster(&host, &safety, &[], &ctx).unwrap(),
+            Value::String("staging".into())
+        );
+
+        let previous = use_cluster(&host, &safety, &[Value::Null], &ctx).unwrap();
+        assert_eq!(previous, Value::String("staging".into()));
+        assert_eq!(host.client().namespace(), "ops");
+    }
+}
@@ -0,0 +1,539 @@
+//! Reading and applying objects of any kind without compile-time types.
+//!
+//! Kinds are named by `apiVersion` and `kind` as they appear in manifests
+//! and resolved through API discovery, so an operator's custom resources
+//! (ArgoCD `Application`s, cert-manager `Certificate`s) are read and
+//! written as [`DynamicObject`]s:
+//!
+//! ```rust,ignore
+//! let certificate = client
+//!     .get_dynamic("cert-manager.io/v1", "Certificate", "web-tls")
+//!     .await?;
+//! let apps = client
+//!     .list_dynamic("argoproj.io/v1alpha1", "Application", &ListOptions::default())
+//!     .await?;
+//! ```
+
+use kube::api::{ApiResource, DynamicObject, ListParams};
+
+use super::apply::{self, ApplyOptions};
+use super::{host_error, optional_string, string_arg, K8sClient, K8sHost};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// Objects requested per page when listing.
+const LIST_PAGE_SIZE: u32 = 500;
+
+/// Options for [`K8sClient::list_dynamic`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListOptions {
+    /// Only list objects with matching labels, such as `app=web`.
+    pub label_selector: Option<String>,
+    /// Only list objects with matching fields, such as
+    /// `metadata.name=web`.
+    pub field_selector: Option<String>,
+    /// List every namespace instead of the client's.
+    pub all_namespaces: bool,
+    /// Return at most this many objects.
+    pub limit: Option<usize>,
+}
+
+impl ListOptions {
+    /// Filter by label selector.
+    pub fn with_label_selector(mut self, selector: impl Into<String>) -> Self {
+        self.label_selector = Some(selector.into());
+        self
+    }
+
+    /// Filter by field selector.
+    pub fn with_field_selector(mut self, selector: impl Into<String>) -> Self {
+        self.field_selector = Some(selector.into());
+        self
+    }
+
+    /// List every namespace.
+    pub fn with_all_namespaces(mut self, all: bool) -> Self {
+        self.all_namespaces = all;
+        self
+    }
+
+    /// Return at most `limit` objects.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Read options from a label selector string, or a map with
+    /// `label_selector`, `field_selector`, `all_namespaces` and `limit`;
+    /// null gives the defaults.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let map = match value {
+            Value::Null => return Ok(Self::default()),
+            Value::String(selector) => return Ok(Self::default().with_label_selector(selector)),
+            Value::Map(map) => map,
+            _ => {
+                return Err(Error::invalid_argument(
+                    "list options must be a selector string or a map",
+                ))
+            }
+        };
+
+        let mut options = Self::default();
+        for (key, field) in [
+            ("label_selector", &mut options.label_selector),
+            ("field_selector", &mut options.field_selector),
+        ] {
+            match map.get(key) {
+                None | Some(Value::Null) => {}
+                Some(Value::String(selector)) => *field = Some(selector.clone()),
+                Some(_) => {
+                    return Err(Error::invalid_argument(format!("{} must be a string", key)))
+                }
+            }
+        }
+        match map.get("all_namespaces") {
+            None | Some(Value::Null) => {}
+            Some(Value::Bool(all)) => options.all_namespaces = *all,
+            Some(_) => return Err(Error::invalid_argument("all_namespaces must be a boolean")),
+        }
+        match map.get("limit") {
+            None | Some(Value::Null) => {}
+            Some(Value::Int(limit)) if *limit > 0 => options.limit = Some(*limit as usize),
+            Some(_) => return Err(Error::invalid_argument("limit must be a positive integer")),
+        }
+        Ok(options)
+    }
+
+    fn list_params(&self) -> ListParams {
+        let page = self.limit.map_or(LIST_PAGE_SIZE, |limit| {
+            limit.min(LIST_PAGE_SIZE as usize) as u32
+        });
+        let mut params = ListParams::default().limit(page);
+        if let Some(selector) = &self.label_selector {
+            params = params.labels(selector);
+        }
+        if let Some(selector) = &self.field_selector {
+            params = params.fields(selector);
+        }
+        params
+    }
+}
+
+/// The kind path [`K8sClient::resolve_kind`] takes for an `apiVersion`
+/// and `kind`, such as `cert-manager.io/v1/Certificate`.
+fn kind_path(api_version: &str, kind: &str) -> Result<String> {
+    if api_version.is_empty() || api_version.split('/').count() > 2 {
+        return Err(Error::invalid_argument(format!(
+            "invalid apiVersion '{}'",
+            api_version
+        )));
+    }
+    if kind.is_empty() || kind.contains('/') {
+        return Err(Error::invalid_argument(format!("invalid kind '{}'", kind)));
+    }
+    Ok(format!("{}/{}", api_version, kind))
+}
+
+impl K8sClient {
+    /// Get an object by `apiVersion`, `kind` and name, in the client's
+    /// namespace for namespaced kinds. Returns `None` if it does not exist.
+    pub async fn get_dynamic(
+        &self,
+        api_version: &str,
+        kind: &str,
+        name: &str,
+    ) -> Result<Option<DynamicObject>> {
+        let (resource, namespaced) = self.resolve_kind(&kind_path(api_version, kind)?).await?;
+        self.get_resolved(&resource, namespaced, name).await
+    }
+
+    /// Get an object of a kind already found with
+    /// [`resolve_kind`](Self::resolve_kind).
+    pub async fn get_resolved(
+        &self,
+        resource: &ApiResource,
+        namespaced: bool,
+        name: &str,
+    ) -> Result<Option<DynamicObject>> {
+        self.dynamic_api(resource, namespaced.then_some(self.namespace.as_str()))
+            .get_opt(name)
+            .await
+            .map_err(|e| Error::K8s(format!("get {} {} failed: {}", resource.kind, name, e)))
+    }
+
+    /// List objects by `apiVersion` and `kind`, in the client's namespace
+    /// for namespaced kinds unless `all_namespaces` is set.
+    pub async fn list_dynamic(
+        &self,
+        api_version: &str,
+        kind: &str,
+        options: &ListOptions,
+    ) -> Result<Vec<DynamicObject>> {
+        let (resource, namespaced) = self.resolve_kind(&kind_path(api_version, kind)?).await?;
+        self.list_resolved(&resource, namespaced, options).await
+    }
+
+    /// List objects of a kind already found with
+    /// [`resolve_kind`](Self::resolve_kind), following continue tokens
+    /// until every page is read or the limit is reached.
+    pub async fn list_resolved(
+        &self,
+        resource: &ApiResource,
+        namespaced: bool,
+        options: &ListOptions,
+    ) -> Result<Vec<DynamicObject>> {
+        let namespace = (namespaced && !options.all_namespaces).then_some(self.namespace.as_str());
+        let api = self.dynamic_api(resource, namespace);
+        let mut params = options.list_params();
+        let mut objects = Vec::new();
+        loop {
+            let page = api
+                .list(&params)
+                .await
+                .map_err(|e| Error::K8s(format!("list {} failed: {}", resource.plural, e)))?;
+            objects.extend(page.items);
+            if let Some(limit) = options.limit {
+                if objects.len() >= limit {
+                    objects.truncate(limit);
+                    break;
+                }
+            }
+            match page.metadata.continue_ {
+                Some(token) if !token.is_empty() => params = params.continue_token(&token),
+                _ => break,
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Server-side apply one object, resolving its kind through discovery,
+    /// and return the object the server stored.
+    pub async fn apply_dynamic(
+        &self,
+        object: DynamicObject,
+        options: &ApplyOptions,
+    ) -> Result<DynamicObject> {
+        let resolved = self.resolve(object).await?;
+        self.apply_object(&resolved, options).await
+    }
+}
+
+fn object_value(object: &DynamicObject, function: &str) -> fusabi_host::Result<Value> {
+    fusabi_host::to_value_serde(object)
+        .map_err(|e| host_error(function, format!("failed to convert object: {}", e)))
+}
+
+/// Namespace from an options map, if set.
+fn namespace_option(value: &Value, function: &str) -> fusabi_host::Result<Option<String>> {
+    match value.as_map().and_then(|map| map.get("namespace")) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(namespace)) => Ok(Some(namespace.clone())),
+        Some(_) => Err(host_error(function, "namespace must be a string")),
+    }
+}
+
+/// Get an object of any kind, including custom resources.
+///
+/// # Arguments
+///
+/// * `args[0]` - API version: `"v1"`, `"apps/v1"` or `"cert-manager.io/v1"`
+/// * `args[1]` - Kind, such as `"Certificate"`
+/// * `args[2]` - Object name
+/// * `args[3]` - Namespace (optional, defaults to the client's; ignored
+///   for cluster-scoped kinds)
+///
+/// # Returns
+///
+/// The object as a map in the API's camelCase field names, or null if it
+/// does not exist
+pub fn get_dynamic(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.get_dynamic";
+
+    let api_version = string_arg(args, 0, "api_version", FUNCTION)?;
+    let kind = string_arg(args, 1, "kind", FUNCTION)?;
+    let name = string_arg(args, 2, "name", FUNCTION)?;
+    let client = host.client_for(optional_string(args, 3, "namespace", FUNCTION)?);
+    let path = kind_path(api_version, kind).map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_k8s_verb("get")
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let (resource, namespaced) = host
+        .block_on(client.resolve_kind(&path))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_k8s(
+            "get",
+            &resource.plural,
+            namespaced.then_some(client.namespace()),
+        )
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    match host
+        .block_on(client.get_resolved(&resource, namespaced, name))
+        .map_err(|e| host_error(FUNCTION, e))?
+    {
+        Some(object) => object_value(&object, FUNCTION),
+        None => Ok(Value::Null),
+    }
+}
+
+/// List objects of any kind, including custom resources.
+///
+/// Checked as the `list` verb in the client's namespace or, with
+/// `all_namespaces`, cluster-wide; a cluster-wide list of a namespaced kind
+/// is denied unless the namespace allowlist is unrestricted or has `"*"`.
+///
+/// # Arguments
+///
+/// * `args[0]` - API version, such as `"argoproj.io/v1alpha1"`
+/// * `args[1]` - Kind, such as `"Application"`
+/// * `args[2]` - Label selector string, or options map (optional) read by
+///   [`ListOptions::from_value`], plus `namespace`
+///
+/// # Returns
+///
+/// List of object maps
+pub fn list_dynamic(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.list_dynamic";
+
+    let api_version = string_arg(args, 0, "api_version", FUNCTION)?;
+    let kind = string_arg(args, 1, "kind", FUNCTION)?;
+    let options = args.get(2).unwrap_or(&Value::Null);
+    let list_options = ListOptions::from_value(options).map_err(|e| host_error(FUNCTION, e))?;
+    let client = host.client_for(namespace_option(options, FUNCTION)?);
+    let path = kind_path(api_version, kind).map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_k8s_verb("list")
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let (resource, namespaced) = host
+        .block_on(client.resolve_kind(&path))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    match (namespaced, list_options.all_namespaces) {
+        (true, true) => safety.check_k8s_all_namespaces("list", &resource.plural),
+        (true, false) => safety.check_k8s("list", &resource.plural, Some(client.namespace())),
+        (false, _) => safety.check_k8s("list", &resource.plural, None),
+    }
+    .map_err(|e| host_error(FUNCTION, e))?;
+
+    let objects = host
+        .block_on(client.list_resolved(&resource, namespaced, &list_options))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    objects
+        .iter()
+        .map(|object| object_value(object, FUNCTION))
+        .collect::<fusabi_host::Result<_>>()
+        .map(Value::List)
+}
+
+/// Server-side apply one object of any kind, including custom resources.
+///
+/// Checked as the `patch` verb on the object's resource.
+///
+/// # Arguments
+///
+/// * `args[0]` - Object map with `apiVersion`, `kind` and `metadata.name`,
+///   or a YAML or JSON string holding one object
+/// * `args[1]` - Options map with `field_manager` (default `"fusabi"`),
+///   `force`, `dry_run` and `namespace`, used when the object has none
+///   (optional)
+///
+/// # Returns
+///
+/// The object the server stored, as a map
+pub fn apply_dynamic(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.apply_dynamic";
+
+    let object = args
+        .first()
+        .ok_or_else(|| host_error(FUNCTION, "object is required"))?;
+    let mut objects = apply::manifests_from_value(object).map_err(|e| host_error(FUNCTION, e))?;
+    if objects.len() != 1 {
+        return Err(host_error(
+            FUNCTION,
+            format!("expected one object, got {}", objects.len()),
+        ));
+    }
+    let object = objects.remove(0);
+    let options = args.get(1).unwrap_or(&Value::Null);
+    let apply_options = ApplyOptions::from_value(options).map_err(|e| host_error(FUNCTION, e))?;
+    let client = host.client_for(namespace_option(options, FUNCTION)?);
+    safety
+        .check_k8s_verb("patch")
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let resolved = host
+        .block_on(client.resolve(object))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_k8s(
+            "patch",
+            &resolved.resource.plural,
+            resolved.namespace.as_deref(),
+        )
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let applied = host
+        .block_on(client.apply_object(&resolved, &apply_options))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    object_value(&applied, FUNCTION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::K8sAllowlist;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+    use kube::{Client, Config};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_kind_path() {
+        assert_eq!(kind_path("v1", "ConfigMap").unwrap(), "v1/ConfigMap");
+        assert_eq!(
+            kind_path("cert-manager.io/v1", "Certificate").unwrap(),
+            "cert-manager.io/v1/Certificate"
+        );
+        assert!(kind_path("", "ConfigMap").is_err());
+        assert!(kind_path("a/b/c", "ConfigMap").is_err());
+        assert!(kind_path("v1", "apps/Deployment").is_err());
+    }
+
+    #[test]
+    fn test_list_options_from_value() {
+        assert_eq!(
+            ListOptions::from_value(&Value::String("app=web".into())).unwrap(),
+            ListOptions::default().with_label_selector("app=web")
+        );
+        let options = ListOptions::from_value(&Value::Map(HashMap::from([
+            (
+                "field_selector".to_string(),
+                Value::String("metadata.name=web".into()),
+            ),
+            ("all_namespaces".to_string(), Value::Bool(true)),
+            ("limit".to_string(), Value::Int(20)),
+        ])))
+        .unwrap();
+        assert_eq!(
+            options,
+            ListOptions::default()
+                .with_field_selector("metadata.name=web")
+                .with_all_namespaces(true)
+                .with_limit(20)
+        );
+        assert_eq!(
+            ListOptions::from_value(&Value::Null).unwrap(),
+            ListOptions::default()
+        );
+        assert!(ListOptions::from_value(&Value::Int(1)).is_err());
+        assert!(ListOptions::from_value(&Value::Map(HashMap::from([(
+            "limit".to_string(),
+            Value::Int(0)
+        )])))
+        .is_err());
+    }
+
+    #[test]
+    fn test_list_all_namespaces_checked() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let host = crate::k8s::stub_discovery_host(&runtime);
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+        let safety = SafetyConfig::new()
+            .with_allow_k8s(true)
+            .with_k8s(K8sAllowlist::all().with_namespaces(["default"]));
+        let all = Value::Map(HashMap::from([(
+            "all_namespaces".to_string(),
+            Value::Bool(true),
+        )]));
+        let args = |kind: &str, options: Value| {
+            [
+                Value::String("v1".into()),
+                Value::String(kind.into()),
+                options,
+            ]
+        };
+
+        let err = list_dynamic(&host, &safety, &args("Pod", all.clone()), &ctx).unwrap_err();
+        assert!(err.to_string().contains("across all namespaces"));
+
+        let listed = list_dynamic(&host, &safety, &args("Node", all.clone()), &ctx).unwrap();
+        assert_eq!(listed, Value::List(vec![]));
+        let listed = list_dynamic(&host, &safety, &args("Pod", Value::Null), &ctx).unwrap();
+        assert_eq!(listed, Value::List(vec![]));
+
+        let safety = safety.with_k8s(K8sAllowlist::all().with_namespaces(["*"]));
+        assert!(list_dynamic(&host, &safety, &args("Pod", all), &ctx)
+            .unwrap_err()
+            .to_string()
+            .contains("404"));
+    }
+
+    #[test]
+    fn test_host_functions_check_safety() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let client = {
+            let _guard = runtime.enter();
+            Client::try_from(Config::new("http://127.0.0.1:9".parse().unwrap())).unwrap()
+        };
+        let host = K8sHost::new(K8sClient::from_client(client), runtime.handle().clone());
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+        let message = |result: fusabi_host::Result<Value>| result.unwrap_err().to_string();
+
+        let safety = SafetyConfig::new()
+            .with_allow_k8s(true)
+            .with_k8s(K8sAllowlist::all().with_verbs(["get"]));
+        let kind = [
+            Value::String("argoproj.io/v1alpha1".into()),
+            Value::String("Application".into()),
+        ];
+        assert!(message(list_dynamic(&host, &safety, &kind, &ctx)).contains("verb not allowed"));
+        assert!(message(get_dynamic(&host, &safety, &kind, &ctx)).contains("name must be"));
+        let invalid = [
+            Value::String("v1".into()),
+            Value::String("apps/Deployment".into()),
+            Value::String("web".into()),
+        ];
+        assert!(message(get_dynamic(&host, &safety, &invalid, &ctx)).contains("invalid kind"));
+
+        let object = Value::String(
+            "apiVersion: cert-manager.io/v1\nkind: Certificate\nmetadata:\n  name: web-tls\n"
+                .into(),
+        );
+        assert!(
+            message(apply_dynamic(&host, &safety, &[object], &ctx)).contains("verb not allowed")
+        );
+        let two = Value::String(
+            "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: a\n---\n\
+             apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: b\n"
+                .into(),
+        );
+        assert!(message(apply_dynamic(&host, &safety, &[two], &ctx)).contains("expected one"));
+    }
+}
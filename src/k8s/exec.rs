@@ -0,0 +1,251 @@
+//! Running commands in pod containers.
+//!
+//! [`K8sClient::exec`] runs a command over the exec websocket API, like
+//! `kubectl exec`, and collects its output and exit code:
+//!
+//! ```rust,ignore
+//! let output = client
+//!     .exec("web-0", Some("app"), &["cat".into(), "/etc/hostname".into()], None)
+//!     .await?;
+//! assert!(output.success);
+//! ```
+
+use std::collections::HashMap;
+
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use kube::api::{Api, AttachParams};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use super::{host_error, string_arg, K8sClient, K8sHost};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// Bytes of stdout and of stderr kept from an exec; the rest is discarded.
+pub const MAX_EXEC_OUTPUT: u64 = 16 * 1024 * 1024;
+
+/// Result of a command run with [`K8sClient::exec`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecOutput {
+    /// Standard output, lossily decoded as UTF-8.
+    pub stdout: String,
+    /// Standard error, lossily decoded as UTF-8.
+    pub stderr: String,
+    /// Exit code, if the command ran and exited.
+    pub exit_code: Option<i32>,
+    /// Whether the command exited with status 0.
+    pub success: bool,
+}
+
+impl ExecOutput {
+    /// Convert to a Value map with `stdout`, `stderr`, `exit_code` and
+    /// `success`.
+    pub fn to_value(&self) -> Value {
+        let mut map = HashMap::new();
+        map.insert("stdout".to_string(), Value::String(self.stdout.clone()));
+        map.insert("stderr".to_string(), Value::String(self.stderr.clone()));
+        map.insert(
+            "exit_code".to_string(),
+            self.exit_code
+                .map_or(Value::Null, |code| Value::Int(i64::from(code))),
+        );
+        map.insert("success".to_string(), Value::Bool(self.success));
+        Value::Map(map)
+    }
+}
+
+/// The exit code reported in an exec status: 0 on success, otherwise the
+/// `ExitCode` cause of a `NonZeroExitCode` failure.
+fn exit_code(status: &Status) -> Option<i32> {
+    if status.status.as_deref() == Some("Success") {
+        return Some(0);
+    }
+    status
+        .details
+        .as_ref()?
+        .causes
+        .as_ref()?
+        .iter()
+        .find(|cause| cause.reason.as_deref() == Some("ExitCode"))?
+        .message
+        .as_deref()?
+        .parse()
+        .ok()
+}
+
+async fn read_limited(reader: Option<impl AsyncRead + Unpin>) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    if let Some(mut reader) = reader {
+        (&mut reader)
+            .take(MAX_EXEC_OUTPUT)
+            .read_to_end(&mut output)
+            .await?;
+        // Drain the rest so the command is not blocked on a full pipe
+        tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    }
+    Ok(output)
+}
+
+impl K8sClient {
+    /// Run a command in a pod container and wait for it to exit.
+    ///
+    /// `container` may be left out for single-container pods. `stdin`, if
+    /// given, is written to the command and then closed. A command that
+    /// exits non-zero is not an error; check [`ExecOutput::success`].
+    pub async fn exec(
+        &self,
+        pod: &str,
+        container: Option<&str>,
+        command: &[String],
+        stdin: Option<&[u8]>,
+    ) -> Result<ExecOutput> {
+        if command.is_empty() {
+            return Err(Error::invalid_argument("exec command is empty"));
+        }
+        let api: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
+        let mut params = AttachParams::default()
+            .stdin(stdin.is_some())
+            .stdout(true)
+            .stderr(true);
+        if let Some(container) = container {
+            params = params.container(container);
+        }
+
+        let mut process = api
+            .exec(pod, command.to_vec(), &params)
+            .await
+            .map_err(|e| Error::K8s(format!("exec failed: {}", e)))?;
+
+        if let Some(input) = stdin {
+            let mut writer = process
+                .stdin()
+                .ok_or_else(|| Error::K8s("exec stdin unavailable".to_string()))?;
+            writer.write_all(input).await?;
+            writer.shutdown().await?;
+        }
+
+        let status = process.take_status();
+        let (stdout, stderr) = tokio::try_join!(
+            read_limited(process.stdout()),
+            read_limited(process.stderr())
+        )?;
+        let status = match status {
+            Some(status) => status.await,
+            None => None,
+        };
+        process
+            .join()
+            .await
+            .map_err(|e| Error::K8s(format!("exec failed: {}", e)))?;
+
+        let exit_code = status.as_ref().and_then(exit_code);
+        Ok(ExecOutput {
+            stdout: String::from_utf8_lossy(&stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr).into_owned(),
+            exit_code,
+            success: exit_code == Some(0),
+        })
+    }
+}
+
+/// Run a command in a pod container, like `kubectl exec`.
+///
+/// The program must pass the process command allowlist as well as the
+/// `create` verb on `pods/exec`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pod name
+/// * `args[1]` - Command and arguments, as a list of strings
+/// * `args[2]` - Options map with `container`, `namespace` and `stdin`
+///   (string) (optional)
+///
+/// # Returns
+///
+/// The [`ExecOutput`] map
+pub fn exec(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.exec";
+
+    let pod = string_arg(args, 0, "pod", FUNCTION)?;
+    let command = args
+        .get(1)
+        .and_then(|v| v.as_list())
+        .and_then(|items| {
+            items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+        })
+        .filter(|command| !command.is_empty())
+        .ok_or_else(|| host_error(FUNCTION, "command must be a non-empty list of strings"))?;
+    let options = args.get(2).and_then(|v| v.as_map());
+    let option = |key: &str| -> fusabi_host::Result<Option<String>> {
+        match options.and_then(|o| o.get(key)) {
+            None | Some(Value::Null) => Ok(None),
+            Some(v) => v
+                .as_str()
+                .map(|s| Some(s.to_string()))
+                .ok_or_else(|| host_error(FUNCTION, format!("{} must be a string", key))),
+        }
+    };
+    let container = option("container")?;
+    let stdin = option("stdin")?;
+    let client = host.client_for(option("namespace")?);
+
+    safety
+        .check_k8s("create", "pods/exec", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_execute(&command[0])
+        .map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_privilege_escalation(&command[0], &command[1..])
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let output = host
+        .block_on(client.exec(
+            pod,
+            container.as_deref(),
+            &command,
+            stdin.as_deref().map(str::as_bytes),
+        ))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(output.to_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{StatusCause, StatusDetails};
+
+    #[test]
+    fn test_exit_code() {
+        let success = Status {
+            status: Some("Success".into()),
+            ..Status::default()
+        };
+        assert_eq!(exit_code(&success), Some(0));
+
+        let failure = Status {
+            status: Some("Failure".into()),
+            reason: Some("NonZeroExitCode".into()),
+            details: Some(StatusDetails {
+                causes: Some(vec![StatusCause {
+                    reason: Some("ExitCode".into()),
+                    message: Some("3".into()),
+                    ..StatusCause::default()
+                }]),
+                ..StatusDetails::default()
+            }),
+            ..Status::default()
+        };
+        assert_eq!(exit_code(&failure), Some(3));
+        assert_eq!(exit_code(&Status::default()), None);
+    }
+}
@@ -0,0 +1,565 @@
+//! One-call batch Jobs.
+//!
+//! [`K8sClient::run_job`] creates a Job running one container, streams the
+//! container's log lines to a callback as they are written, waits for the
+//! Job to finish within a timeout, and deletes the Job and its pod, leaving
+//! only the exit status:
+//!
+//! ```rust,ignore
+//! let outcome = client
+//!     .run_job(
+//!         "rust:1.75",
+//!         &["cargo".into(), "test".into()],
+//!         &RunJobOptions::default().with_timeout(Duration::from_secs(1800)),
+//!         |line| println!("{}", line),
+//!     )
+//!     .await?;
+//! std::process::exit(outcome.exit_code.unwrap_or(1));
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use futures::{AsyncBufReadExt, StreamExt};
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use k8s_openapi::api::core::v1::{Container, EnvVar, Pod, PodSpec, PodTemplateSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, ListParams, LogParams, PostParams};
+
+use super::K8sClient;
+use crate::error::{Error, Result};
+use fusabi_host::Value;
+
+/// Label put on every Job and pod created by [`K8sClient::run_job`].
+pub const MANAGED_BY_LABEL: &str = "app.kubernetes.io/managed-by";
+
+/// Options for [`K8sClient::run_job`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunJobOptions {
+    /// Prefix of the generated Job name.
+    pub name_prefix: String,
+    /// Namespace to run in (default: the client's namespace).
+    pub namespace: Option<String>,
+    /// Environment variables of the container.
+    pub env: BTreeMap<String, String>,
+    /// Extra labels on the Job and its pod.
+    pub labels: BTreeMap<String, String>,
+    /// Service account the pod runs as.
+    pub service_account: Option<String>,
+    /// How long to wait for the Job to finish. Also set as the Job's
+    /// `activeDeadlineSeconds`, so the cluster stops it too.
+    pub timeout: Duration,
+    /// Whether to delete the Job and its pod afterwards, including after a
+    /// failure or timeout.
+    pub cleanup: bool,
+}
+
+impl Default for RunJobOptions {
+    fn default() -> Self {
+        Self {
+            name_prefix: "fusabi-job".to_string(),
+            namespace: None,
+            env: BTreeMap::new(),
+            labels: BTreeMap::new(),
+            service_account: None,
+            timeout: Duration::from_secs(600),
+            cleanup: true,
+        }
+    }
+}
+
+impl RunJobOptions {
+    /// Set the Job name prefix.
+    pub fn with_name_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.name_prefix = prefix.into();
+        self
+    }
+
+    /// Run in a namespace other than the client's.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Set an environment variable.
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Add a label.
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.insert(key.into(), value.into());
+        self
+    }
+
+    /// Run as a service account.
+    pub fn with_service_account(mut self, account: impl Into<String>) -> Self {
+        self.service_account = Some(account.into());
+        self
+    }
+
+    /// Set the timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Keep the Job and pod afterwards, for debugging.
+    pub fn with_cleanup(mut self, cleanup: bool) -> Self {
+        self.cleanup = cleanup;
+        self
+    }
+
+    /// Read options from a map with `namespace`, `name_prefix`, `env`
+    /// (map), `labels` (map), `service_account`, `timeout_ms` and `cleanup`.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let mut options = Self::default();
+        let Some(map) = value.as_map() else {
+            return match value {
+                Value::Null => Ok(options),
+                _ => Err(Error::invalid_argument("job options must be a map")),
+            };
+        };
+
+        let string = |key: &str| -> Result<Option<String>> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(v) => v
+                    .as_str()
+                    .map(|s| Some(s.to_string()))
+                    .ok_or_else(|| Error::invalid_argument(format!("{} must be a string", key))),
+            }
+        };
+        let string_map = |key: &str| -> Result<BTreeMap<String, String>> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(BTreeMap::new()),
+                Some(Value::Map(entries)) => entries
+                    .iter()
+                    .map(|(k, v)| match v {
+                        Value::String(s) => Ok((k.clone(), s.clone())),
+                        other => Ok((k.clone(), other.to_string())),
+                    })
+                    .collect(),
+                Some(_) => Err(Error::invalid_argument(format!("{} must be a map", key))),
+            }
+        };
+
+        if let Some(prefix) = string("name_prefix")? {
+            options.name_prefix = prefix;
+        }
+        options.namespace = string("namespace")?;
+        options.service_account = string("service_account")?;
+        options.env = string_map("env")?;
+        options.labels = string_map("labels")?;
+        if let Some(ms) = map.get("timeout_ms") {
+            let ms = ms
+                .as_int()
+                .filter(|ms| *ms > 0)
+                .ok_or_else(|| Error::invalid_argument("timeout_ms must be a positive integer"))?;
+            options.timeout = Duration::from_millis(ms as u64);
+        }
+        if let Some(cleanup) = map.get("cleanup") {
+            options.cleanup = cleanup
+                .as_bool()
+                .ok_or_else(|| Error::invalid_argument("cleanup must be a boolean"))?;
+        }
+        Ok(options)
+    }
+}
+
+/// How a Job run ended.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobOutcome {
+    /// Generated Job name.
+    pub name: String,
+    /// Pod that ran the container, if one was scheduled.
+    pub pod: Option<String>,
+    /// Whether the container exited with status 0.
+    pub succeeded: bool,
+    /// Container exit code, if it terminated.
+    pub exit_code: Option<i32>,
+    /// Why the container or Job stopped (such as `Error`, `OOMKilled` or
+    /// `DeadlineExceeded`), if it did not succeed.
+    pub reason: Option<String>,
+    /// Whether the run hit the timeout.
+    pub timed_out: bool,
+    /// Time from creating the Job to its end.
+    pub duration: Duration,
+}
+
+impl JobOutcome {
+    /// Convert to a Value map with `name`, `pod`, `succeeded`, `exit_code`,
+    /// `reason`, `timed_out` and `duration_ms`.
+    pub fn to_value(&self) -> Value {
+        let optional = |v: &Option<String>| v.clone().map_or(Value::Null, Value::String);
+        let mut map = HashMap::new();
+        map.insert("name".to_string(), Value::String(self.name.clone()));
+        map.insert("pod".to_string(), optional(&self.pod));
+        map.insert("succeeded".to_string(), Value::Bool(self.succeeded));
+        map.insert(
+            "exit_code".to_string(),
+            self.exit_code
+                .map_or(Value::Null, |code| Value::Int(code.into())),
+        );
+        map.insert("reason".to_string(), optional(&self.reason));
+        map.insert("timed_out".to_string(), Value::Bool(self.timed_out));
+        map.insert(
+            "duration_ms".to_string(),
+            Value::Int(self.duration.as_millis() as i64),
+        );
+        Value::Map(map)
+    }
+}
+
+/// Build the Job manifest.
+fn job_manifest(image: &str, command: &[String], options: &RunJobOptions) -> Job {
+    let mut labels = options.labels.clone();
+    labels.insert(MANAGED_BY_LABEL.to_string(), "fusabi".to_string());
+
+    let env: Vec<EnvVar> = options
+        .env
+        .iter()
+        .map(|(name, value)| EnvVar {
+            name: name.clone(),
+            value: Some(value.clone()),
+            ..EnvVar::default()
+        })
+        .collect();
+
+    Job {
+        metadata: ObjectMeta {
+            generate_name: Some(format!("{}-", options.name_prefix)),
+            labels: Some(labels.clone()),
+            ..ObjectMeta::default()
+        },
+        spec: Some(JobSpec {
+            // One attempt: the caller sees the exit status of that attempt.
+            backoff_limit: Some(0),
+            active_deadline_seconds: Some(options.timeout.as_secs().max(1) as i64),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    ..ObjectMeta::default()
+                }),
+                spec: Some(PodSpec {
+                    restart_policy: Some("Never".to_string()),
+                    service_account_name: options.service_account.clone(),
+                    containers: vec![Container {
+                        name: "job".to_string(),
+                        image: Some(image.to_string()),
+                        command: (!command.is_empty()).then(|| command.to_vec()),
+                        env: (!env.is_empty()).then_some(env),
+                        ..Container::default()
+                    }],
+                    ..PodSpec::default()
+                }),
+            },
+            ..JobSpec::default()
+        }),
+        ..Job::default()
+    }
+}
+
+/// Exit code and reason of the job container, once it has terminated.
+fn terminated_state(pod: &Pod) -> Option<(i32, Option<String>)> {
+    pod.status
+        .as_ref()?
+        .container_statuses
+        .as_ref()?
+        .iter()
+        .find(|status| status.name == "job")?
+        .state
+        .as_ref()?
+        .terminated
+        .as_ref()
+        .map(|terminated| (terminated.exit_code, terminated.reason.clone()))
+}
+
+/// Reason the Job itself failed, such as `DeadlineExceeded`.
+fn job_failure(job: &Job) -> Option<String> {
+    job.status
+        .as_ref()?
+        .conditions
+        .as_ref()?
+        .iter()
+        .find(|c| c.type_ == "Failed" && c.status == "True")
+        .map(|c| c.reason.clone().unwrap_or_else(|| "Failed".to_string()))
+}
+
+/// Whether the pod has started its container or already finished, so its
+/// logs can be followed.
+fn pod_started(pod: &Pod) -> bool {
+    matches!(
+        pod.status.as_ref().and_then(|s| s.phase.as_deref()),
+        Some("Running" | "Succeeded" | "Failed")
+    )
+}
+
+/// Interval between status polls.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+impl K8sClient {
+    /// Run `command` in `image` as a Job and wait for it to finish.
+    ///
+    /// Each log line of the container is passed to `on_log` as it is
+    /// written. The Job gets one attempt (`backoff_limit: 0`) and
+    /// `activeDeadlineSeconds` equal to the timeout. Unless
+    /// [`cleanup`](RunJobOptions::cleanup) is off, the Job and its pod are
+    /// deleted before returning, also after a timeout or error.
+    ///
+    /// A container that fails is not an error: the outcome reports its exit
+    /// code. Errors are API failures.
+    pub async fn run_job(
+        &self,
+        image: &str,
+        command: &[String],
+        options: &RunJobOptions,
+        on_log: impl FnMut(&str) + Send,
+    ) -> Result<JobOutcome> {
+        let namespace = options.namespace.as_deref().unwrap_or(&self.namespace);
+        let jobs: Api<Job> = Api::namespaced(self.client.clone(), namespace);
+        let started = Instant::now();
+
+        let job = jobs
+            .create(
+                &PostParams::default(),
+                &job_manifest(image, command, options),
+            )
+            .await
+            .map_err(|e| Error::K8s(format!("create job failed: {}", e)))?;
+        let name = job
+            .metadata
+            .name
+            .ok_or_else(|| Error::K8s("created job has no name".to_string()))?;
+
+        let waited =
+            tokio::time::timeout(options.timeout, self.follow_job(namespace, &name, on_log)).await;
+
+        if options.cleanup {
+            if let Err(e) = jobs.delete(&name, &DeleteParams::background()).await {
+                tracing::warn!(job = %name, "k8s.run_job: cleanup failed: {}", e);
+            }
+        }
+
+        match waited {
+            Ok(Ok((pod, exit_code, reason))) => Ok(JobOutcome {
+                name,
+                pod,
+                succeeded: exit_code == Some(0),
+                exit_code,
+                reason: if exit_code == Some(0) { None } else { reason },
+                timed_out: false,
+                duration: started.elapsed(),
+            }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Ok(JobOutcome {
+                name,
+                pod: None,
+                succeeded: false,
+                exit_code: None,
+                reason: Some("DeadlineExceeded".to_string()),
+                timed_out: true,
+                duration: started.elapsed(),
+            }),
+        }
+    }
+
+    /// Follow a Job's pod logs and wait for it to end, returning the pod
+    /// name, exit code and failure reason.
+    async fn follow_job(
+        &self,
+        namespace: &str,
+        name: &str,
+        mut on_log: impl FnMut(&str) + Send,
+    ) -> Result<(Option<String>, Option<i32>, Option<String>)> {
+        let jobs: Api<Job> = Api::namespaced(self.client.clone(), namespace);
+        let pods: Api<Pod> = Api::namespaced(self.client.clone(), namespace);
+        let selector = ListParams::default().labels(&format!("job-name={}", name));
+
+        // Wait for the pod to start, or for the Job to fail without one
+        // (for example an invalid image that never pulls past the deadline).
+        let pod_name = loop {
+            let listed = pods
+                .list(&selector)
+                .await
+                .map_err(|e| Error::K8s(format!("list job pods failed: {}", e)))?;
+            if let Some(pod) = listed.items.iter().find(|p| pod_started(p)) {
+                break pod.metadata.name.clone().unwrap_or_default();
+            }
+            let job = jobs
+                .get(name)
+                .await
+                .map_err(|e| Error::K8s(format!("get job failed: {}", e)))?;
+            if let Some(reason) = job_failure(&job) {
+                return Ok((
+                    listed.items.first().and_then(|p| p.metadata.name.clone()),
+                    None,
+                    Some(reason),
+                ));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        };
+
+        // Following ends when the container exits.
+        let params = LogParams {
+            follow: true,
+            container: Some("job".to_string()),
+            ..LogParams::default()
+        };
+        let stream = pods
+            .log_stream(&pod_name, &params)
+            .await
+            .map_err(|e| Error::K8s(format!("log stream failed: {}", e)))?;
+        let mut lines = stream.lines();
+        while let Some(line) = lines.next().await {
+            match line {
+                Ok(line) => on_log(&line),
+                Err(e) => {
+                    tracing::debug!(pod = %pod_name, "k8s.run_job: log stream ended: {}", e);
+                    break;
+                }
+            }
+        }
+
+        loop {
+            let pod = pods
+                .get(&pod_name)
+                .await
+                .map_err(|e| Error::K8s(format!("get job pod failed: {}", e)))?;
+            if let Some((exit_code, reason)) = terminated_state(&pod) {
+                return Ok((Some(pod_name), Some(exit_code), reason));
+            }
+            let job = jobs
+                .get(name)
+                .await
+                .map_err(|e| Error::K8s(format!("get job failed: {}", e)))?;
+            if let Some(reason) = job_failure(&job) {
+                return Ok((Some(pod_name), None, Some(reason)));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::batch::v1::{JobCondition, JobStatus};
+    use k8s_openapi::api::core::v1::{
+        ContainerState, ContainerStateTerminated, ContainerStatus, PodStatus,
+    };
+
+    #[test]
+    fn test_job_manifest() {
+        let options = RunJobOptions::default()
+            .with_env("CI", "true")
+            .with_label("team", "infra")
+            .with_timeout(Duration::from_secs(90));
+        let job = job_manifest(
+            "alpine:3",
+            &["sh".into(), "-c".into(), "true".into()],
+            &options,
+        );
+
+        assert_eq!(job.metadata.generate_name.as_deref(), Some("fusabi-job-"));
+        let spec = job.spec.unwrap();
+        assert_eq!(spec.backoff_limit, Some(0));
+        assert_eq!(spec.active_deadline_seconds, Some(90));
+        let labels = spec.template.metadata.unwrap().labels.unwrap();
+        assert_eq!(labels[MANAGED_BY_LABEL], "fusabi");
+        assert_eq!(labels["team"], "infra");
+        let pod = spec.template.spec.unwrap();
+        assert_eq!(pod.restart_policy.as_deref(), Some("Never"));
+        let container = &pod.containers[0];
+        assert_eq!(container.image.as_deref(), Some("alpine:3"));
+        assert_eq!(container.command.as_ref().unwrap().len(), 3);
+        assert_eq!(container.env.as_ref().unwrap()[0].name, "CI");
+    }
+
+    #[test]
+    fn test_job_status_helpers() {
+        let mut pod = Pod::default();
+        assert!(!pod_started(&pod));
+        assert_eq!(terminated_state(&pod), None);
+
+        pod.status = Some(PodStatus {
+            phase: Some("Failed".to_string()),
+            container_statuses: Some(vec![ContainerStatus {
+                name: "job".to_string(),
+                state: Some(ContainerState {
+                    terminated: Some(ContainerStateTerminated {
+                        exit_code: 137,
+                        reason: Some("OOMKilled".to_string()),
+                        ..ContainerStateTerminated::default()
+                    }),
+                    ..ContainerState::default()
+                }),
+                ..ContainerStatus::default()
+            }]),
+            ..PodStatus::default()
+        });
+        assert!(pod_started(&pod));
+        assert_eq!(
+            terminated_state(&pod),
+            Some((137, Some("OOMKilled".to_string())))
+        );
+
+        let job = Job {
+            status: Some(JobStatus {
+                conditions: Some(vec![JobCondition {
+                    type_: "Failed".to_string(),
+                    status: "True".to_string(),
+                    reason: Some("DeadlineExceeded".to_string()),
+                    ..JobCondition::default()
+                }]),
+                ..JobStatus::default()
+            }),
+            ..Job::default()
+        };
+        assert_eq!(job_failure(&job).as_deref(), Some("DeadlineExceeded"));
+    }
+
+    #[test]
+    fn test_options_from_value() {
+        let value = Value::Map(HashMap::from([
+            ("namespace".to_string(), Value::String("ci".into())),
+            ("timeout_ms".to_string(), Value::Int(5000)),
+            ("cleanup".to_string(), Value::Bool(false)),
+            (
+                "env".to_string(),
+                Value::Map(HashMap::from([("RETRIES".to_string(), Value::Int(3))])),
+            ),
+        ]));
+        let options = RunJobOptions::from_value(&value).unwrap();
+        assert_eq!(options.namespace.as_deref(), Some("ci"));
+        assert_eq!(options.timeout, Duration::from_secs(5));
+        assert!(!options.cleanup);
+        assert_eq!(options.env["RETRIES"], "3");
+
+        assert_eq!(
+            RunJobOptions::from_value(&Value::Null).unwrap(),
+            RunJobOptions::default()
+        );
+        let bad = Value::Map(HashMap::from([("timeout_ms".to_string(), Value::Int(0))]));
+        assert!(RunJobOptions::from_value(&bad).is_err());
+    }
+
+    #[test]
+    fn test_outcome_to_value() {
+        let outcome = JobOutcome {
+            name: "fusabi-job-x7k2p".to_string(),
+            pod: Some("fusabi-job-x7k2p-abcde".to_string()),
+            succeeded: false,
+            exit_code: Some(2),
+            reason: Some("Error".to_string()),
+            timed_out: false,
+            duration: Duration::from_millis(1500),
+        };
+        let value = outcome.to_value();
+        let map = value.as_map().unwrap();
+        assert_eq!(map["exit_code"], Value::Int(2));
+        assert_eq!(map["duration_ms"], Value::Int(1500));
+        assert_eq!(map["succeeded"], Value::Bool(false));
+    }
+}
@@ -0,0 +1,420 @@
+//! Leader election over `coordination.k8s.io` Leases.
+//!
+//! Replicas of an automation that share a lease name and namespace take turns
+//! holding the lease; only the holder should act. The holder renews the lease
+//! every retry period and loses it if it cannot renew within the renew
+//! deadline, at which point another replica takes over once the lease
+//! duration has passed.
+//!
+//! ```rust,ignore
+//! let mut election = client
+//!     .leader_elect("nightly-sync", "automation", &pod_name, LeaderElectionConfig::default())
+//!     .await?;
+//! election.wait_for_leadership().await?;
+//! while election.is_leader() {
+//!     run_once().await?;
+//! }
+//! election.step_down().await?;
+//! ```
+
+use std::time::{Duration, Instant};
+
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{MicroTime, ObjectMeta};
+use k8s_openapi::chrono::{DateTime, Utc};
+use kube::api::{Api, PostParams};
+use tokio::sync::watch;
+
+use super::K8sClient;
+use crate::error::{Error, Result};
+
+/// Timing of a leader election.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeaderElectionConfig {
+    /// How long a lease is valid after its last renewal.
+    pub lease_duration: Duration,
+    /// How long the holder keeps acting on failed renewals before it steps
+    /// down. Must be shorter than `lease_duration`.
+    pub renew_deadline: Duration,
+    /// Interval between acquire or renew attempts.
+    pub retry_period: Duration,
+}
+
+impl Default for LeaderElectionConfig {
+    fn default() -> Self {
+        Self {
+            lease_duration: Duration::from_secs(15),
+            renew_deadline: Duration::from_secs(10),
+            retry_period: Duration::from_secs(2),
+        }
+    }
+}
+
+impl LeaderElectionConfig {
+    /// Set the lease duration.
+    pub fn with_lease_duration(mut self, duration: Duration) -> Self {
+        self.lease_duration = duration;
+        self
+    }
+
+    /// Set the renew deadline.
+    pub fn with_renew_deadline(mut self, deadline: Duration) -> Self {
+        self.renew_deadline = deadline;
+        self
+    }
+
+    /// Set the retry period.
+    pub fn with_retry_period(mut self, period: Duration) -> Self {
+        self.retry_period = period;
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.retry_period.is_zero() || self.renew_deadline >= self.lease_duration {
+            return Err(Error::InvalidArgument(
+                "leader election needs a non-zero retry period and a renew deadline shorter than the lease duration".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn lease_duration_seconds(&self) -> i32 {
+        self.lease_duration.as_secs().clamp(1, i32::MAX as u64) as i32
+    }
+}
+
+/// What to do with an existing lease.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LeaseAction {
+    /// We hold the lease; extend it.
+    Renew,
+    /// The lease is free or expired; take it.
+    Acquire,
+    /// Another identity holds a valid lease.
+    Wait(String),
+}
+
+fn lease_action(spec: &LeaseSpec, identity: &str, now: DateTime<Utc>) -> LeaseAction {
+    let holder = match spec.holder_identity.as_deref() {
+        Some(holder) if !holder.is_empty() => holder,
+        _ => return LeaseAction::Acquire,
+    };
+    if holder == identity {
+        return LeaseAction::Renew;
+    }
+
+    let renewed = spec.renew_time.as_ref().or(spec.acquire_time.as_ref());
+    let duration = k8s_openapi::chrono::Duration::seconds(
+        spec.lease_duration_seconds.unwrap_or_default().into(),
+    );
+    match renewed {
+        Some(MicroTime(renewed)) if *renewed + duration > now => {
+            LeaseAction::Wait(holder.to_string())
+        }
+        _ => LeaseAction::Acquire,
+    }
+}
+
+fn is_conflict(error: &kube::Error) -> bool {
+    matches!(error, kube::Error::Api(response) if response.code == 409)
+}
+
+impl K8sClient {
+    /// Make one attempt to acquire or renew a lease for `identity`.
+    ///
+    /// Creates the lease if it does not exist. Returns whether `identity`
+    /// holds the lease afterwards; losing a race to another replica is not an
+    /// error.
+    pub async fn try_acquire_lease(
+        &self,
+        lease_name: &str,
+        namespace: &str,
+        identity: &str,
+        config: &LeaderElectionConfig,
+    ) -> Result<bool> {
+        let api: Api<Lease> = Api::namespaced(self.client.clone(), namespace);
+        let now = Utc::now();
+
+        let existing = api
+            .get_opt(lease_name)
+            .await
+            .map_err(|e| Error::K8s(format!("get lease failed: {}", e)))?;
+
+        let Some(mut lease) = existing else {
+            let lease = Lease {
+                metadata: ObjectMeta {
+                    name: Some(lease_name.to_string()),
+                    namespace: Some(namespace.to_string()),
+                    ..Default::default()
+                },
+                spec: Some(LeaseSpec {
+                    holder_identity: Some(identity.to_string()),
+                    lease_duration_seconds: Some(config.lease_duration_seconds()),
+                    acquire_time: Some(MicroTime(now)),
+                    renew_time: Some(MicroTime(now)),
+                    lease_transitions: Some(0),
+                }),
+            };
+            return match api.create(&PostParams::default(), &lease).await {
+                Ok(_) => Ok(true),
+                Err(e) if is_conflict(&e) => Ok(false),
+                Err(e) => Err(Error::K8s(format!("create lease failed: {}", e))),
+            };
+        };
+
+        let spec = lease.spec.get_or_insert_with(Default::default);
+        match lease_action(spec, identity, now) {
+            LeaseAction::Wait(_) => return Ok(false),
+            LeaseAction::Renew => {}
+            LeaseAction::Acquire => {
+                spec.holder_identity = Some(identity.to_string());
+                spec.acquire_time = Some(MicroTime(now));
+                spec.lease_transitions = Some(spec.lease_transitions.unwrap_or_default() + 1);
+            }
+        }
+        spec.renew_time = Some(MicroTime(now));
+        spec.lease_duration_seconds = Some(config.lease_duration_seconds());
+
+        // The lease carries its resourceVersion, so a concurrent update by
+        // another replica makes this replace fail with a conflict.
+        match api
+            .replace(lease_name, &PostParams::default(), &lease)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_conflict(&e) => Ok(false),
+            Err(e) => Err(Error::K8s(format!("update lease failed: {}", e))),
+        }
+    }
+
+    /// Give up a lease held by `identity` so another replica can take it
+    /// without waiting for it to expire.
+    ///
+    /// Returns whether the lease was released; a lease held by someone else
+    /// is left alone.
+    pub async fn release_lease(
+        &self,
+        lease_name: &str,
+        namespace: &str,
+        identity: &str,
+    ) -> Result<bool> {
+        let api: Api<Lease> = Api::namespaced(self.client.clone(), namespace);
+        let Some(mut lease) = api
+            .get_opt(lease_name)
+            .await
+            .map_err(|e| Error::K8s(format!("get lease failed: {}", e)))?
+        else {
+            return Ok(false);
+        };
+
+        let Some(spec) = lease.spec.as_mut() else {
+            return Ok(false);
+        };
+        if spec.holder_identity.as_deref() != Some(identity) {
+            return Ok(false);
+        }
+        spec.holder_identity = None;
+        spec.lease_duration_seconds = Some(1);
+        spec.renew_time = Some(MicroTime(Utc::now()));
+
+        match api
+            .replace(lease_name, &PostParams::default(), &lease)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if is_conflict(&e) => Ok(false),
+            Err(e) => Err(Error::K8s(format!("release lease failed: {}", e))),
+        }
+    }
+
+    /// Start competing for leadership of `lease_name` in `namespace` as
+    /// `identity`.
+    ///
+    /// A background task acquires and renews the lease until the returned
+    /// [`LeaderElection`] is stepped down or dropped. Identities must be
+    /// unique per replica, such as the pod name.
+    pub async fn leader_elect(
+        &self,
+        lease_name: &str,
+        namespace: &str,
+        identity: &str,
+        config: LeaderElectionConfig,
+    ) -> Result<LeaderElection> {
+        config.validate()?;
+
+        let (leader_tx, leader_rx) = watch::channel(false);
+        let (stop_tx, stop_rx) = watch::channel(false);
+        let task = tokio::spawn(run_election(
+            self.clone(),
+            lease_name.to_string(),
+            namespace.to_string(),
+            identity.to_string(),
+            config,
+            leader_tx,
+            stop_rx,
+        ));
+
+        Ok(LeaderElection {
+            leader: leader_rx,
+            stop: stop_tx,
+            task: Some(task),
+        })
+    }
+}
+
+async fn run_election(
+    client: K8sClient,
+    lease_name: String,
+    namespace: String,
+    identity: String,
+    config: LeaderElectionConfig,
+    leader: watch::Sender<bool>,
+    mut stop: watch::Receiver<bool>,
+) {
+    let mut last_renewed: Option<Instant> = None;
+
+    loop {
+        match client
+            .try_acquire_lease(&lease_name, &namespace, &identity, &config)
+            .await
+        {
+            Ok(true) => last_renewed = Some(Instant::now()),
+            Ok(false) => last_renewed = None,
+            Err(e) => {
+                tracing::warn!(lease = %lease_name, identity = %identity, "lease renewal failed: {}", e);
+            }
+        }
+
+        // Keep acting through transient failures until the renew deadline.
+        let is_leader = last_renewed.is_some_and(|at| at.elapsed() < config.renew_deadline);
+        if !is_leader {
+            last_renewed = None;
+        }
+        leader.send_if_modified(|current| std::mem::replace(current, is_leader) != is_leader);
+
+        if tokio::time::timeout(config.retry_period, stop.changed())
+            .await
+            .is_ok()
+        {
+            break;
+        }
+    }
+
+    if last_renewed.is_some() {
+        leader.send_replace(false);
+        if let Err(e) = client
+            .release_lease(&lease_name, &namespace, &identity)
+            .await
+        {
+            tracing::warn!(lease = %lease_name, identity = %identity, "lease release failed: {}", e);
+        }
+    }
+}
+
+/// A running leader election, created by [`K8sClient::leader_elect`].
+///
+/// Dropping it stops renewing without releasing the lease, which then
+/// expires after the lease duration; use [`LeaderElection::step_down`] to
+/// hand over immediately.
+pub struct LeaderElection {
+    leader: watch::Receiver<bool>,
+    stop: watch::Sender<bool>,
+    task: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl LeaderElection {
+    /// Whether this replica currently holds the lease.
+    pub fn is_leader(&self) -> bool {
+        *self.leader.borrow()
+    }
+
+    /// Wait until this replica holds the lease.
+    pub async fn wait_for_leadership(&mut self) -> Result<()> {
+        self.leader
+            .wait_for(|leader| *leader)
+            .await
+            .map(|_| ())
+            .map_err(|_| Error::K8s("leader election stopped".to_string()))
+    }
+
+    /// Wait for leadership to be gained or lost, returning the new state.
+    pub async fn changed(&mut self) -> Result<bool> {
+        self.leader
+            .changed()
+            .await
+            .map_err(|_| Error::K8s("leader election stopped".to_string()))?;
+        Ok(*self.leader.borrow_and_update())
+    }
+
+    /// Stop competing and release the lease if it is held.
+    pub async fn step_down(mut self) -> Result<()> {
+        self.stop.send_replace(true);
+        if let Some(task) = self.task.take() {
+            task.await.map_err(|e| Error::Internal(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LeaderElection {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(holder: Option<&str>, renewed_secs_ago: i64) -> LeaseSpec {
+        LeaseSpec {
+            holder_identity: holder.map(str::to_string),
+            lease_duration_seconds: Some(15),
+            renew_time: Some(MicroTime(
+                Utc::now() - k8s_openapi::chrono::Duration::seconds(renewed_secs_ago),
+            )),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lease_action() {
+        let now = Utc::now();
+        assert_eq!(
+            lease_action(&spec(Some("a"), 5), "a", now),
+            LeaseAction::Renew
+        );
+        assert_eq!(
+            lease_action(&spec(Some("b"), 5), "a", now),
+            LeaseAction::Wait("b".to_string())
+        );
+        assert_eq!(
+            lease_action(&spec(Some("b"), 60), "a", now),
+            LeaseAction::Acquire
+        );
+        assert_eq!(lease_action(&spec(None, 1), "a", now), LeaseAction::Acquire);
+        assert_eq!(
+            lease_action(&spec(Some(""), 1), "a", now),
+            LeaseAction::Acquire
+        );
+        assert_eq!(
+            lease_action(&LeaseSpec::default(), "a", now),
+            LeaseAction::Acquire
+        );
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(LeaderElectionConfig::default().validate().is_ok());
+        assert!(LeaderElectionConfig::default()
+            .with_renew_deadline(Duration::from_secs(20))
+            .validate()
+            .is_err());
+        assert!(LeaderElectionConfig::default()
+            .with_retry_period(Duration::ZERO)
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,472 @@
+//! Pod log streaming.
+//!
+//! [`K8sClient::pod_logs`] returns a container's log as a stream of lines,
+//! optionally following it as it is written. The `k8s.pod_logs` host
+//! function reads that stream in the background into a handle that
+//! scripts poll like an `fs_stream` handle:
+//!
+//! ```text
+//! let logs = k8s.pod_logs("web-0", { follow: true, tail_lines: 100 })
+//! while !k8s.logs_finished(logs) {
+//!     let line = k8s.logs_read_line(logs)
+//!     if line != null { print(line) }
+//! }
+//! k8s.logs_close(logs)
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use futures::stream::BoxStream;
+use futures::{AsyncBufReadExt, StreamExt};
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::{Api, LogParams};
+use parking_lot::Mutex;
+
+use super::{host_error, string_arg, K8sClient, K8sHost};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// Lines a log handle buffers before dropping the oldest.
+pub const DEFAULT_BUFFER_LINES: usize = 10_000;
+
+/// Options for [`K8sClient::pod_logs`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PodLogOptions {
+    /// Keep the stream open and return lines as they are written.
+    pub follow: bool,
+    /// Start this many lines from the end of the log.
+    pub tail_lines: Option<i64>,
+    /// Only return lines written in the last this many seconds.
+    pub since_seconds: Option<i64>,
+    /// Read the previous, terminated instance of the container, such as
+    /// the one before a crash-loop restart.
+    pub previous: bool,
+    /// Prefix each line with its RFC 3339 timestamp.
+    pub timestamps: bool,
+}
+
+impl PodLogOptions {
+    /// Follow the log.
+    pub fn with_follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// Start `lines` lines from the end.
+    pub fn with_tail_lines(mut self, lines: i64) -> Self {
+        self.tail_lines = Some(lines);
+        self
+    }
+
+    /// Only return recent lines.
+    pub fn with_since_seconds(mut self, seconds: i64) -> Self {
+        self.since_seconds = Some(seconds);
+        self
+    }
+
+    /// Read the previous container instance.
+    pub fn with_previous(mut self, previous: bool) -> Self {
+        self.previous = previous;
+        self
+    }
+
+    /// Prefix lines with timestamps.
+    pub fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    /// Read options from a map with `follow`, `tail_lines`,
+    /// `since_seconds`, `previous` and `timestamps`.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let mut options = Self::default();
+        let Some(map) = value.as_map() else {
+            return match value {
+                Value::Null => Ok(options),
+                _ => Err(Error::invalid_argument("log options must be a map")),
+            };
+        };
+
+        let flag = |key: &str| -> Result<bool> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(false),
+                Some(v) => v
+                    .as_bool()
+                    .ok_or_else(|| Error::invalid_argument(format!("{} must be a boolean", key))),
+            }
+        };
+        let count = |key: &str| -> Result<Option<i64>> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(v) => v.as_int().filter(|n| *n >= 0).map(Some).ok_or_else(|| {
+                    Error::invalid_argument(format!("{} must be a non-negative integer", key))
+                }),
+            }
+        };
+
+        options.follow = flag("follow")?;
+        options.previous = flag("previous")?;
+        options.timestamps = flag("timestamps")?;
+        options.tail_lines = count("tail_lines")?;
+        options.since_seconds = count("since_seconds")?;
+        Ok(options)
+    }
+}
+
+impl K8sClient {
+    /// Stream a pod container's log, line by line.
+    ///
+    /// `container` may be left out for single-container pods. Without
+    /// `follow` the stream ends at the current end of the log.
+    pub async fn pod_logs(
+        &self,
+        name: &str,
+        container: Option<&str>,
+        options: &PodLogOptions,
+    ) -> Result<BoxStream<'static, Result<String>>> {
+        let api: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
+        let params = LogParams {
+            container: container.map(str::to_string),
+            follow: options.follow,
+            previous: options.previous,
+            since_seconds: options.since_seconds,
+            tail_lines: options.tail_lines,
+            timestamps: options.timestamps,
+            ..LogParams::default()
+        };
+
+        let reader = api
+            .log_stream(name, &params)
+            .await
+            .map_err(|e| Error::K8s(format!("pod logs failed: {}", e)))?;
+        Ok(reader
+            .lines()
+            .map(|line| line.map_err(|e| Error::K8s(format!("pod logs failed: {}", e))))
+            .boxed())
+    }
+}
+
+/// Lines read from a log stream and not yet taken by the script.
+#[derive(Debug, Default)]
+struct LogBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+    dropped: u64,
+    finished: bool,
+    error: Option<String>,
+}
+
+impl LogBuffer {
+    fn push(&mut self, line: String) {
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+}
+
+/// An open log handle and the task filling its buffer.
+struct LogStream {
+    buffer: Arc<Mutex<LogBuffer>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+static STREAMS: OnceLock<Mutex<HashMap<i64, LogStream>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn streams() -> &'static Mutex<HashMap<i64, LogStream>> {
+    STREAMS.get_or_init(Default::default)
+}
+
+/// Number of open log handles.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    streams().lock().len()
+}
+
+/// Read `stream` on `runtime` into a new handle buffering up to
+/// `capacity` lines.
+fn open_stream(
+    runtime: &tokio::runtime::Handle,
+    mut stream: BoxStream<'static, Result<String>>,
+    capacity: usize,
+) -> i64 {
+    let buffer = Arc::new(Mutex::new(LogBuffer {
+        capacity: capacity.max(1),
+        ..LogBuffer::default()
+    }));
+
+    let filling = buffer.clone();
+    let task = runtime.spawn(async move {
+        while let Some(line) = stream.next().await {
+            match line {
+                Ok(line) => filling.lock().push(line),
+                Err(e) => {
+                    filling.lock().error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        filling.lock().finished = true;
+    });
+
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    streams().lock().insert(handle, LogStream { buffer, task });
+    handle
+}
+
+fn buffer(args: &[Value], function: &str) -> fusabi_host::Result<Arc<Mutex<LogBuffer>>> {
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| host_error(function, "missing handle argument"))?;
+    streams()
+        .lock()
+        .get(&handle)
+        .map(|stream| stream.buffer.clone())
+        .ok_or_else(|| host_error(function, "invalid handle"))
+}
+
+/// Open a pod container's log as a stream handle.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pod name
+/// * `args[1]` - Options map (optional) read by
+///   [`PodLogOptions::from_value`], plus `container`, `namespace` and
+///   `buffer_lines` (default 10,000; older lines are dropped when a
+///   script falls behind)
+///
+/// # Returns
+///
+/// Handle for `k8s.logs_read_line`, `k8s.logs_read_available`,
+/// `k8s.logs_finished` and `k8s.logs_close`
+pub fn pod_logs(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.pod_logs";
+
+    let pod = string_arg(args, 0, "pod", FUNCTION)?;
+    let options = args.get(1).unwrap_or(&Value::Null);
+    let log_options = PodLogOptions::from_value(options).map_err(|e| host_error(FUNCTION, e))?;
+    let map = options.as_map();
+    let option = |key: &str| -> fusabi_host::Result<Option<String>> {
+        match map.and_then(|m| m.get(key)) {
+            None | Some(Value::Null) => Ok(None),
+            Some(v) => v
+                .as_str()
+                .map(|s| Some(s.to_string()))
+                .ok_or_else(|| host_error(FUNCTION, format!("{} must be a string", key))),
+        }
+    };
+    let container = option("container")?;
+    let namespace = option("namespace")?;
+    let capacity = match map.and_then(|m| m.get("buffer_lines")) {
+        None | Some(Value::Null) => DEFAULT_BUFFER_LINES,
+        Some(Value::Int(n)) if *n > 0 => *n as usize,
+        Some(_) => {
+            return Err(host_error(
+                FUNCTION,
+                "buffer_lines must be a positive integer",
+            ))
+        }
+    };
+    let client = host.client_for(namespace);
+    safety
+        .check_k8s("get", "pods/log", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let stream = host
+        .block_on(client.pod_logs(pod, container.as_deref(), &log_options))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::Int(open_stream(&host.runtime, stream, capacity)))
+}
+
+/// Read the next buffered log line without blocking.
+///
+/// Returns null if no line is available yet. Once the buffered lines are
+/// read, an error that ended the stream is raised.
+///
+/// # Arguments
+///
+/// * `args[0]` - Log handle
+pub fn read_line(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.logs_read_line";
+
+    let buffer = buffer(args, FUNCTION)?;
+    let mut buffer = buffer.lock();
+    if let Some(line) = buffer.lines.pop_front() {
+        return Ok(Value::String(line));
+    }
+    match buffer.error.take() {
+        Some(e) => Err(host_error(FUNCTION, e)),
+        None => Ok(Value::Null),
+    }
+}
+
+/// Read every buffered log line without blocking.
+///
+/// # Arguments
+///
+/// * `args[0]` - Log handle
+///
+/// # Returns
+///
+/// List of lines (may be empty)
+pub fn read_available(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.logs_read_available";
+
+    let buffer = buffer(args, FUNCTION)?;
+    let mut buffer = buffer.lock();
+    if buffer.lines.is_empty() {
+        if let Some(e) = buffer.error.take() {
+            return Err(host_error(FUNCTION, e));
+        }
+    }
+    Ok(Value::List(
+        buffer.lines.drain(..).map(Value::String).collect(),
+    ))
+}
+
+/// Whether a log stream has ended and every line has been read.
+///
+/// # Arguments
+///
+/// * `args[0]` - Log handle
+pub fn finished(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let buffer = buffer(args, "k8s.logs_finished")?;
+    let buffer = buffer.lock();
+    Ok(Value::Bool(
+        buffer.finished && buffer.lines.is_empty() && buffer.error.is_none(),
+    ))
+}
+
+/// Stop reading a log stream and release its handle.
+///
+/// # Arguments
+///
+/// * `args[0]` - Log handle
+///
+/// # Returns
+///
+/// Number of lines dropped because the buffer was full
+pub fn close(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.logs_close";
+
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| host_error(FUNCTION, "missing handle argument"))?;
+    let stream = streams()
+        .lock()
+        .remove(&handle)
+        .ok_or_else(|| host_error(FUNCTION, "invalid handle"))?;
+    stream.task.abort();
+
+    let dropped = stream.buffer.lock().dropped;
+    Ok(Value::Int(dropped as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_pod_log_options_from_value() {
+        let options = PodLogOptions::from_value(&Value::Map(HashMap::from([
+            ("follow".to_string(), Value::Bool(true)),
+            ("tail_lines".to_string(), Value::Int(50)),
+            ("previous".to_string(), Value::Bool(true)),
+        ])))
+        .unwrap();
+        assert_eq!(
+            options,
+            PodLogOptions::default()
+                .with_follow(true)
+                .with_tail_lines(50)
+                .with_previous(true)
+        );
+        assert!(PodLogOptions::from_value(&Value::Map(HashMap::from([(
+            "since_seconds".to_string(),
+            Value::Int(-1)
+        )])))
+        .is_err());
+    }
+
+    #[test]
+    fn test_log_handle() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let ctx = ctx();
+        let lines = ["one", "two", "three"].map(|l| Ok(l.to_string()));
+        let handle = Value::Int(open_stream(
+            runtime.handle(),
+            futures::stream::iter(lines).boxed(),
+            2,
+        ));
+        let args = [handle];
+
+        let buffer = buffer(&args, "test").unwrap();
+        while !buffer.lock().finished {
+            std::thread::yield_now();
+        }
+
+        // The buffer kept the newest two lines
+        assert_eq!(read_line(&args, &ctx).unwrap(), Value::String("two".into()));
+        assert_eq!(
+            read_available(&args, &ctx).unwrap(),
+            Value::List(vec![Value::String("three".into())])
+        );
+        assert_eq!(read_line(&args, &ctx).unwrap(), Value::Null);
+        assert_eq!(close(&args, &ctx).unwrap(), Value::Int(1));
+        assert!(read_line(&args, &ctx).is_err());
+    }
+
+    #[test]
+    fn test_log_stream_error() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let ctx = ctx();
+        let lines = vec![
+            Ok("starting".to_string()),
+            Err(Error::K8s("connection reset".into())),
+        ];
+        let args = [Value::Int(open_stream(
+            runtime.handle(),
+            futures::stream::iter(lines).boxed(),
+            10,
+        ))];
+
+        let buffer = buffer(&args, "test").unwrap();
+        while !buffer.lock().finished {
+            std::thread::yield_now();
+        }
+        assert_eq!(finished(&args, &ctx).unwrap(), Value::Bool(false));
+        assert_eq!(
+            read_line(&args, &ctx).unwrap(),
+            Value::String("starting".into())
+        );
+        assert!(read_line(&args, &ctx)
+            .unwrap_err()
+            .to_string()
+            .contains("connection reset"));
+        assert_eq!(finished(&args, &ctx).unwrap(), Value::Bool(true));
+        close(&args, &ctx).unwrap();
+    }
+}
@@ -0,0 +1,318 @@
+//! Forwarding local ports to pods.
+//!
+//! [`K8sClient::port_forward`] listens on loopback ports and tunnels each
+//! accepted connection to a pod port over the port-forward websocket API,
+//! like `kubectl port-forward`. Forwarding runs in the background until
+//! the returned [`PortForward`] is stopped or dropped:
+//!
+//! ```rust,ignore
+//! let forward = client.port_forward("postgres-0", &[(0, 5432)]).await?;
+//! let url = format!("postgres://localhost:{}/app", forward.local_port(5432).unwrap());
+//! ```
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::OnceLock;
+
+use k8s_openapi::api::core::v1::Pod;
+use kube::api::Api;
+use parking_lot::Mutex;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+
+use super::{host_error, string_arg, K8sClient, K8sHost};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// A local port forwarded to a pod port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ForwardedPort {
+    /// Port listening on 127.0.0.1.
+    pub local: u16,
+    /// Port on the pod.
+    pub remote: u16,
+}
+
+/// Port forwarding started by [`K8sClient::port_forward`].
+///
+/// Forwarding stops, closing open connections, when this is stopped or
+/// dropped.
+#[derive(Debug)]
+pub struct PortForward {
+    ports: Vec<ForwardedPort>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl PortForward {
+    /// The forwarded ports, in the order they were requested.
+    pub fn ports(&self) -> &[ForwardedPort] {
+        &self.ports
+    }
+
+    /// The local port forwarded to pod port `remote`.
+    pub fn local_port(&self, remote: u16) -> Option<u16> {
+        self.ports
+            .iter()
+            .find(|port| port.remote == remote)
+            .map(|port| port.local)
+    }
+
+    /// Stop listening and close open connections.
+    pub fn stop(&self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for PortForward {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Tunnel one accepted connection to `remote` on the pod.
+async fn forward_connection(api: Api<Pod>, pod: String, remote: u16, mut conn: TcpStream) {
+    let mut forwarder = match api.portforward(&pod, &[remote]).await {
+        Ok(forwarder) => forwarder,
+        Err(e) => {
+            tracing::warn!(pod = %pod, port = remote, "k8s.port_forward: connect failed: {}", e);
+            return;
+        }
+    };
+    let Some(mut upstream) = forwarder.take_stream(remote) else {
+        return;
+    };
+    if let Err(e) = tokio::io::copy_bidirectional(&mut conn, &mut upstream).await {
+        tracing::debug!(pod = %pod, port = remote, "k8s.port_forward: connection closed: {}", e);
+    }
+    drop(upstream);
+    if let Err(e) = forwarder.join().await {
+        tracing::debug!(pod = %pod, port = remote, "k8s.port_forward: tunnel closed: {}", e);
+    }
+}
+
+/// Accept connections on `listener` until aborted. Open connections are
+/// aborted with it, as they are owned by the task's [`JoinSet`].
+async fn accept_loop(listener: TcpListener, api: Api<Pod>, pod: String, remote: u16) {
+    let mut connections = JoinSet::new();
+    loop {
+        match listener.accept().await {
+            Ok((conn, _)) => {
+                while connections.try_join_next().is_some() {}
+                connections.spawn(forward_connection(api.clone(), pod.clone(), remote, conn));
+            }
+            Err(e) => {
+                tracing::warn!(pod = %pod, port = remote, "k8s.port_forward: accept failed: {}", e);
+                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            }
+        }
+    }
+}
+
+impl K8sClient {
+    /// Forward local ports to ports on a pod.
+    ///
+    /// Each `(local, remote)` pair listens on `127.0.0.1:local`, or on an
+    /// ephemeral port if `local` is 0, and forwards connections to
+    /// `remote` on the pod. Must be called within a Tokio runtime, which
+    /// runs the forwarding.
+    pub async fn port_forward(&self, pod: &str, ports: &[(u16, u16)]) -> Result<PortForward> {
+        if ports.is_empty() {
+            return Err(Error::invalid_argument("no ports to forward"));
+        }
+        let api: Api<Pod> = Api::namespaced(self.client.clone(), &self.namespace);
+        // Fail now on a missing pod rather than on the first connection
+        api.get(pod)
+            .await
+            .map_err(|e| Error::K8s(format!("failed to get pod {}: {}", pod, e)))?;
+
+        let mut listeners = Vec::with_capacity(ports.len());
+        for &(local, remote) in ports {
+            let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, local)))
+                .await
+                .map_err(|e| Error::K8s(format!("failed to listen on port {}: {}", local, e)))?;
+            let local = listener.local_addr()?.port();
+            listeners.push((listener, ForwardedPort { local, remote }));
+        }
+
+        let mut forward = PortForward {
+            ports: Vec::with_capacity(listeners.len()),
+            tasks: Vec::with_capacity(listeners.len()),
+        };
+        for (listener, port) in listeners {
+            forward.ports.push(port);
+            forward.tasks.push(tokio::spawn(accept_loop(
+                listener,
+                api.clone(),
+                pod.to_string(),
+                port.remote,
+            )));
+        }
+        Ok(forward)
+    }
+}
+
+static FORWARDS: OnceLock<Mutex<HashMap<i64, PortForward>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn forwards() -> &'static Mutex<HashMap<i64, PortForward>> {
+    FORWARDS.get_or_init(Default::default)
+}
+
+/// Number of open port-forward handles.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    forwards().lock().len()
+}
+
+fn port(value: Option<&Value>, allow_zero: bool) -> Option<u16> {
+    u16::try_from(value?.as_int()?)
+        .ok()
+        .filter(|port| allow_zero || *port != 0)
+}
+
+/// Parse the `ports` argument: a list of pod ports, forwarded from
+/// ephemeral local ports, or of `{local, remote}` maps.
+fn parse_ports(value: Option<&Value>) -> std::result::Result<Vec<(u16, u16)>, String> {
+    let items = value
+        .and_then(|v| v.as_list())
+        .filter(|items| !items.is_empty())
+        .ok_or("ports must be a non-empty list")?;
+    items
+        .iter()
+        .map(|item| match item {
+            Value::Int(_) => port(Some(item), false)
+                .map(|remote| (0, remote))
+                .ok_or_else(|| format!("invalid port {}", item)),
+            Value::Map(map) => {
+                let remote = port(map.get("remote"), false)
+                    .ok_or("remote must be a port between 1 and 65535")?;
+                let local = match map.get("local") {
+                    None | Some(Value::Null) => 0,
+                    local => port(local, true).ok_or("local must be a port between 0 and 65535")?,
+                };
+                Ok((local, remote))
+            }
+            _ => Err("ports must be integers or {local, remote} maps".to_string()),
+        })
+        .collect()
+}
+
+/// Forward local ports to a pod, like `kubectl port-forward`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pod name
+/// * `args[1]` - List of pod ports, each forwarded from an ephemeral local
+///   port, or of `{local, remote}` maps
+/// * `args[2]` - Options map with `namespace` (optional)
+///
+/// # Returns
+///
+/// Map with `handle`, for `k8s.port_forward_close`, and `ports`, a list of
+/// `{local, remote}` maps
+pub fn port_forward(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.port_forward";
+
+    let pod = string_arg(args, 0, "pod", FUNCTION)?;
+    let ports = parse_ports(args.get(1)).map_err(|e| host_error(FUNCTION, e))?;
+    let namespace = match args
+        .get(2)
+        .and_then(|v| v.as_map())
+        .and_then(|o| o.get("namespace"))
+    {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(
+            v.as_str()
+                .ok_or_else(|| host_error(FUNCTION, "namespace must be a string"))?
+                .to_string(),
+        ),
+    };
+    let client = host.client_for(namespace);
+    safety
+        .check_k8s("create", "pods/portforward", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let forward = host
+        .block_on(client.port_forward(pod, &ports))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    let ports = forward
+        .ports()
+        .iter()
+        .map(|port| {
+            Value::Map(HashMap::from([
+                ("local".to_string(), Value::Int(i64::from(port.local))),
+                ("remote".to_string(), Value::Int(i64::from(port.remote))),
+            ]))
+        })
+        .collect();
+
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    forwards().lock().insert(handle, forward);
+    Ok(Value::Map(HashMap::from([
+        ("handle".to_string(), Value::Int(handle)),
+        ("ports".to_string(), Value::List(ports)),
+    ])))
+}
+
+/// Stop port forwarding and release its handle.
+///
+/// # Arguments
+///
+/// * `args[0]` - Port-forward handle
+pub fn port_forward_close(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.port_forward_close";
+
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| host_error(FUNCTION, "missing handle argument"))?;
+    forwards()
+        .lock()
+        .remove(&handle)
+        .ok_or_else(|| host_error(FUNCTION, "invalid handle"))?
+        .stop();
+    Ok(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ports() {
+        let ports = Value::List(vec![
+            Value::Int(8080),
+            Value::Map(HashMap::from([
+                ("local".to_string(), Value::Int(15432)),
+                ("remote".to_string(), Value::Int(5432)),
+            ])),
+            Value::Map(HashMap::from([("remote".to_string(), Value::Int(9090))])),
+        ]);
+        assert_eq!(
+            parse_ports(Some(&ports)).unwrap(),
+            vec![(0, 8080), (15432, 5432), (0, 9090)]
+        );
+
+        assert!(parse_ports(None).is_err());
+        assert!(parse_ports(Some(&Value::List(vec![]))).is_err());
+        assert!(parse_ports(Some(&Value::List(vec![Value::Int(0)]))).is_err());
+        assert!(parse_ports(Some(&Value::List(vec![Value::Int(70000)]))).is_err());
+        assert!(
+            parse_ports(Some(&Value::List(vec![Value::Map(HashMap::from([(
+                "local".to_string(),
+                Value::Int(8080)
+            )]))])))
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,749 @@
+//! Watching resources for changes.
+//!
+//! [`K8sClient::watch`] works like an informer: it lists the matching
+//! objects, reporting each as `Added`, then watches from the list's
+//! resource version. When the watch ends it is resumed where it left off,
+//! and when the server reports the version as expired (`410 Gone`) the
+//! objects are listed again and compared with those already seen, so
+//! missed changes are still reported as `Added`, `Modified` or `Deleted`.
+//! The `k8s.watch` host function reads the events in the background into
+//! a handle:
+//!
+//! ```text
+//! let pods = k8s.watch("Pod", "app=web")
+//! loop {
+//!     for event in k8s.watch_drain(pods) {
+//!         print(event.type + " " + event.object.metadata.name)
+//!     }
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use kube::api::{Api, ApiResource, DynamicObject, ListParams, WatchParams};
+use kube::core::WatchEvent;
+use parking_lot::Mutex;
+
+use super::{host_error, string_arg, K8sClient, K8sHost};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// Events a watch handle buffers before dropping the oldest.
+pub const DEFAULT_BUFFER_EVENTS: usize = 10_000;
+
+/// Objects requested per page when listing.
+const LIST_PAGE_SIZE: u32 = 500;
+
+/// Seconds the server keeps a watch open before it is resumed.
+const WATCH_TIMEOUT_SECS: u32 = 290;
+
+/// Delay before the first retry after an error; doubled on each failure.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest delay between retries.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Options for [`K8sClient::watch`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatchOptions {
+    /// Only watch objects with matching labels, such as `app=web`.
+    pub label_selector: Option<String>,
+    /// Only watch objects with matching fields, such as
+    /// `status.phase=Running`.
+    pub field_selector: Option<String>,
+    /// Watch every namespace instead of the client's.
+    pub all_namespaces: bool,
+}
+
+impl WatchOptions {
+    /// Filter by label selector.
+    pub fn with_label_selector(mut self, selector: impl Into<String>) -> Self {
+        self.label_selector = Some(selector.into());
+        self
+    }
+
+    /// Filter by field selector.
+    pub fn with_field_selector(mut self, selector: impl Into<String>) -> Self {
+        self.field_selector = Some(selector.into());
+        self
+    }
+
+    /// Watch every namespace.
+    pub fn with_all_namespaces(mut self, all: bool) -> Self {
+        self.all_namespaces = all;
+        self
+    }
+
+    /// Read options from a label selector string, or a map with
+    /// `label_selector`, `field_selector` and `all_namespaces`; null gives
+    /// the defaults.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        let map = match value {
+            Value::Null => return Ok(Self::default()),
+            Value::String(selector) => return Ok(Self::default().with_label_selector(selector)),
+            Value::Map(map) => map,
+            _ => {
+                return Err(Error::invalid_argument(
+                    "watch options must be a selector string or a map",
+                ))
+            }
+        };
+
+        let mut options = Self::default();
+        for (key, field) in [
+            ("label_selector", &mut options.label_selector),
+            ("field_selector", &mut options.field_selector),
+        ] {
+            match map.get(key) {
+                None | Some(Value::Null) => {}
+                Some(Value::String(selector)) => *field = Some(selector.clone()),
+                Some(_) => {
+                    return Err(Error::invalid_argument(format!("{} must be a string", key)))
+                }
+            }
+        }
+        match map.get("all_namespaces") {
+            None | Some(Value::Null) => {}
+            Some(Value::Bool(all)) => options.all_namespaces = *all,
+            Some(_) => return Err(Error::invalid_argument("all_namespaces must be a boolean")),
+        }
+        Ok(options)
+    }
+
+    fn list_params(&self) -> ListParams {
+        let mut params = ListParams::default().limit(LIST_PAGE_SIZE);
+        if let Some(selector) = &self.label_selector {
+            params = params.labels(selector);
+        }
+        if let Some(selector) = &self.field_selector {
+            params = params.fields(selector);
+        }
+        params
+    }
+
+    fn watch_params(&self) -> WatchParams {
+        let mut params = WatchParams::default().timeout(WATCH_TIMEOUT_SECS);
+        if let Some(selector) = &self.label_selector {
+            params = params.labels(selector);
+        }
+        if let Some(selector) = &self.field_selector {
+            params = params.fields(selector);
+        }
+        params
+    }
+}
+
+/// Kind of change reported by a watch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceEventType {
+    /// The object was created, or was found by the initial list.
+    Added,
+    /// The object changed.
+    Modified,
+    /// The object was deleted.
+    Deleted,
+}
+
+impl ResourceEventType {
+    /// The name used in event maps: `Added`, `Modified` or `Deleted`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "Added",
+            Self::Modified => "Modified",
+            Self::Deleted => "Deleted",
+        }
+    }
+}
+
+/// A change to a watched object.
+#[derive(Debug, Clone)]
+pub struct ResourceEvent {
+    /// Kind of change.
+    pub event_type: ResourceEventType,
+    /// The object after the change, or its last state when deleted.
+    pub object: DynamicObject,
+}
+
+impl ResourceEvent {
+    /// Convert to a Value map with `type` and `object`.
+    pub fn to_value(&self) -> Result<Value> {
+        let object = fusabi_host::to_value_serde(&self.object)
+            .map_err(|e| Error::K8s(format!("failed to convert object: {}", e)))?;
+        Ok(Value::Map(HashMap::from([
+            (
+                "type".to_string(),
+                Value::String(self.event_type.as_str().to_string()),
+            ),
+            ("object".to_string(), object),
+        ])))
+    }
+}
+
+type ObjectKey = (Option<String>, String);
+
+fn object_key(object: &DynamicObject) -> ObjectKey {
+    (
+        object.metadata.namespace.clone(),
+        object.metadata.name.clone().unwrap_or_default(),
+    )
+}
+
+/// List-then-watch loop behind [`K8sClient::watch`].
+struct Watcher {
+    api: Api<DynamicObject>,
+    list_params: ListParams,
+    watch_params: WatchParams,
+    /// Last seen state of every object, for diffing after a re-list.
+    known: HashMap<ObjectKey, DynamicObject>,
+    pending: VecDeque<ResourceEvent>,
+    stream: Option<BoxStream<'static, kube::Result<WatchEvent<DynamicObject>>>>,
+    /// Version to resume the watch from; `None` when a list is needed.
+    resource_version: Option<String>,
+    listed: bool,
+    backoff: Duration,
+    done: bool,
+}
+
+impl Watcher {
+    fn new(api: Api<DynamicObject>, options: &WatchOptions) -> Self {
+        Self {
+            api,
+            list_params: options.list_params(),
+            watch_params: options.watch_params(),
+            known: HashMap::new(),
+            pending: VecDeque::new(),
+            stream: None,
+            resource_version: None,
+            listed: false,
+            backoff: INITIAL_BACKOFF,
+            done: false,
+        }
+    }
+
+    /// List every object and queue the differences from the known set.
+    async fn relist(&mut self) -> kube::Result<()> {
+        let mut params = self.list_params.clone();
+        let mut objects = Vec::new();
+        let resource_version = loop {
+            let page = self.api.list(&params).await?;
+            objects.extend(page.items);
+            match page.metadata.continue_.filter(|token| !token.is_empty()) {
+                Some(token) => params = params.continue_token(&token),
+                None => break page.metadata.resource_version.unwrap_or_default(),
+            }
+        };
+
+        let listed: HashSet<ObjectKey> = objects.iter().map(object_key).collect();
+        let mut known = std::mem::take(&mut self.known);
+        for (key, object) in known.iter() {
+            if !listed.contains(key) {
+                self.pending.push_back(ResourceEvent {
+                    event_type: ResourceEventType::Deleted,
+                    object: object.clone(),
+                });
+            }
+        }
+        for object in objects {
+            let key = object_key(&object);
+            let event_type = match known.remove(&key) {
+                None => Some(ResourceEventType::Added),
+                Some(old) if old.metadata.resource_version != object.metadata.resource_version => {
+                    Some(ResourceEventType::Modified)
+                }
+                Some(_) => None,
+            };
+            if let Some(event_type) = event_type {
+                self.pending.push_back(ResourceEvent {
+                    event_type,
+                    object: object.clone(),
+                });
+            }
+            self.known.insert(key, object);
+        }
+        self.resource_version = Some(resource_version);
+        self.listed = true;
+        Ok(())
+    }
+
+    /// List if needed, then start watching.
+    async fn connect(&mut self) -> kube::Result<()> {
+        if self.resource_version.is_none() {
+            self.relist().await?;
+        }
+        let version = self.resource_version.clone().unwrap_or_default();
+        match self.api.watch(&self.watch_params, &version).await {
+            Ok(stream) => {
+                self.stream = Some(stream.boxed());
+                Ok(())
+            }
+            // Too old to resume from; list again.
+            Err(kube::Error::Api(e)) if e.code == 410 => {
+                self.resource_version = None;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn handle(&mut self, event: WatchEvent<DynamicObject>) {
+        let (event_type, object) = match event {
+            WatchEvent::Added(object) => (ResourceEventType::Added, object),
+            WatchEvent::Modified(object) => (ResourceEventType::Modified, object),
+            WatchEvent::Deleted(object) => (ResourceEventType::Deleted, object),
+            WatchEvent::Bookmark(bookmark) => {
+                self.resource_version = Some(bookmark.metadata.resource_version);
+                return;
+            }
+            WatchEvent::Error(e) => {
+                self.stream = None;
+                if e.code == 410 {
+                    self.resource_version = None;
+                } else {
+                    tracing::warn!("k8s.watch: {}", e.message);
+                }
+                return;
+            }
+        };
+
+        if let Some(version) = &object.metadata.resource_version {
+            self.resource_version = Some(version.clone());
+        }
+        let key = object_key(&object);
+        if event_type == ResourceEventType::Deleted {
+            self.known.remove(&key);
+        } else {
+            self.known.insert(key, object.clone());
+        }
+        self.backoff = INITIAL_BACKOFF;
+        self.pending.push_back(ResourceEvent { event_type, object });
+    }
+
+    async fn wait(&mut self) {
+        tokio::time::sleep(self.backoff).await;
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+    }
+
+    /// The next event. Errors after the first successful list are retried
+    /// with backoff; an error before it ends the stream.
+    async fn next(&mut self) -> Option<Result<ResourceEvent>> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.done {
+                return None;
+            }
+
+            let Some(stream) = self.stream.as_mut() else {
+                if let Err(e) = self.connect().await {
+                    if !self.listed {
+                        self.done = true;
+                        return Some(Err(Error::K8s(format!("watch failed: {}", e))));
+                    }
+                    tracing::warn!("k8s.watch: {}; retrying in {:?}", e, self.backoff);
+                    self.wait().await;
+                }
+                continue;
+            };
+            match stream.next().await {
+                Some(Ok(event)) => self.handle(event),
+                Some(Err(e)) => {
+                    tracing::warn!("k8s.watch: {}; resuming in {:?}", e, self.backoff);
+                    self.stream = None;
+                    self.wait().await;
+                }
+                // The server closed the watch; resume it.
+                None => self.stream = None,
+            }
+        }
+    }
+}
+
+impl K8sClient {
+    /// Watch objects of a kind, as named for [`K8sClient::delete`].
+    ///
+    /// The stream first reports every matching object as `Added`, then
+    /// each change. It runs until dropped, resuming and re-listing as
+    /// needed; only a failure of the first list ends it with an error.
+    pub async fn watch(
+        &self,
+        kind: &str,
+        options: &WatchOptions,
+    ) -> Result<BoxStream<'static, Result<ResourceEvent>>> {
+        let (resource, namespaced) = self.resolve_kind(kind).await?;
+        Ok(self.watch_resolved(&resource, namespaced, options))
+    }
+
+    /// Watch objects of a resolved kind.
+    pub fn watch_resolved(
+        &self,
+        resource: &ApiResource,
+        namespaced: bool,
+        options: &WatchOptions,
+    ) -> BoxStream<'static, Result<ResourceEvent>> {
+        let namespace = (namespaced && !options.all_namespaces).then_some(self.namespace.as_str());
+        let watcher = Watcher::new(self.dynamic_api(resource, namespace), options);
+        futures::stream::unfold(watcher, |mut watcher| async move {
+            watcher.next().await.map(|event| (event, watcher))
+        })
+        .boxed()
+    }
+}
+
+/// Events read from a watch and not yet taken by the script.
+#[derive(Debug, Default)]
+struct EventBuffer {
+    events: VecDeque<Value>,
+    capacity: usize,
+    dropped: u64,
+    finished: bool,
+    error: Option<String>,
+}
+
+impl EventBuffer {
+    fn push(&mut self, event: Value) {
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+        self.events.push_back(event);
+    }
+}
+
+/// An open watch handle and the task filling its buffer.
+struct WatchStream {
+    buffer: Arc<Mutex<EventBuffer>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+static STREAMS: OnceLock<Mutex<HashMap<i64, WatchStream>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn streams() -> &'static Mutex<HashMap<i64, WatchStream>> {
+    STREAMS.get_or_init(Default::default)
+}
+
+/// Number of open watch handles.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    streams().lock().len()
+}
+
+/// Read `stream` on `runtime` into a new handle buffering up to
+/// `capacity` events.
+fn open_stream(
+    runtime: &tokio::runtime::Handle,
+    mut stream: BoxStream<'static, Result<ResourceEvent>>,
+    capacity: usize,
+) -> i64 {
+    let buffer = Arc::new(Mutex::new(EventBuffer {
+        capacity: capacity.max(1),
+        ..EventBuffer::default()
+    }));
+
+    let filling = buffer.clone();
+    let task = runtime.spawn(async move {
+        while let Some(event) = stream.next().await {
+            match event.and_then(|event| event.to_value()) {
+                Ok(event) => filling.lock().push(event),
+                Err(e) => {
+                    filling.lock().error = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+        filling.lock().finished = true;
+    });
+
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    streams()
+        .lock()
+        .insert(handle, WatchStream { buffer, task });
+    handle
+}
+
+fn buffer(args: &[Value], function: &str) -> fusabi_host::Result<Arc<Mutex<EventBuffer>>> {
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| host_error(function, "missing handle argument"))?;
+    streams()
+        .lock()
+        .get(&handle)
+        .map(|stream| stream.buffer.clone())
+        .ok_or_else(|| host_error(function, "invalid handle"))
+}
+
+/// Watch objects of a kind as an event stream handle.
+///
+/// Checked as the `list` and `watch` verbs on the kind's resource, in the
+/// client's namespace or, with `all_namespaces`, cluster-wide, which a
+/// restricted namespace allowlist denies unless it has a `"*"` entry.
+///
+/// # Arguments
+///
+/// * `args[0]` - Kind: `"Pod"`, `"apps/v1/Deployment"` or a custom kind
+/// * `args[1]` - Label selector string, or options map (optional) read by
+///   [`WatchOptions::from_value`], plus `namespace` and `buffer` (default
+///   10,000 events; older events are dropped when a script falls behind)
+///
+/// # Returns
+///
+/// Handle for `k8s.watch_poll`, `k8s.watch_drain`, `k8s.watch_finished`
+/// and `k8s.watch_close`, yielding `{type, object}` maps with `type`
+/// `"Added"`, `"Modified"` or `"Deleted"`
+pub fn watch(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.watch";
+
+    let kind = string_arg(args, 0, "kind", FUNCTION)?;
+    let options = args.get(1).unwrap_or(&Value::Null);
+    let watch_options = WatchOptions::from_value(options).map_err(|e| host_error(FUNCTION, e))?;
+    let map = options.as_map();
+    let namespace = match map.and_then(|m| m.get("namespace")) {
+        None | Some(Value::Null) => None,
+        Some(Value::String(namespace)) => Some(namespace.clone()),
+        Some(_) => return Err(host_error(FUNCTION, "namespace must be a string")),
+    };
+    let capacity = match map.and_then(|m| m.get("buffer")) {
+        None | Some(Value::Null) => DEFAULT_BUFFER_EVENTS,
+        Some(Value::Int(n)) if *n > 0 => *n as usize,
+        Some(_) => return Err(host_error(FUNCTION, "buffer must be a positive integer")),
+    };
+    let client = host.client_for(namespace);
+    safety
+        .check_k8s_verb("watch")
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let (resource, namespaced) = host
+        .block_on(client.resolve_kind(kind))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    for verb in ["list", "watch"] {
+        let checked = match (namespaced, watch_options.all_namespaces) {
+            (true, true) => safety.check_k8s_all_namespaces(verb, &resource.plural),
+            (true, false) => safety.check_k8s(verb, &resource.plural, Some(client.namespace())),
+            (false, _) => safety.check_k8s(verb, &resource.plural, None),
+        };
+        checked.map_err(|e| host_error(FUNCTION, e))?;
+    }
+
+    let stream = client.watch_resolved(&resource, namespaced, &watch_options);
+    Ok(Value::Int(open_stream(&host.runtime, stream, capacity)))
+}
+
+/// Take the next buffered watch event without blocking.
+///
+/// Returns null if no event is available yet. Once the buffered events
+/// are taken, an error that ended the watch is raised.
+///
+/// # Arguments
+///
+/// * `args[0]` - Watch handle
+pub fn watch_poll(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.watch_poll";
+
+    let buffer = buffer(args, FUNCTION)?;
+    let mut buffer = buffer.lock();
+    if let Some(event) = buffer.events.pop_front() {
+        return Ok(event);
+    }
+    match buffer.error.take() {
+        Some(e) => Err(host_error(FUNCTION, e)),
+        None => Ok(Value::Null),
+    }
+}
+
+/// Take every buffered watch event without blocking.
+///
+/// # Arguments
+///
+/// * `args[0]` - Watch handle
+///
+/// # Returns
+///
+/// List of events (may be empty)
+pub fn watch_drain(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.watch_drain";
+
+    let buffer = buffer(args, FUNCTION)?;
+    let mut buffer = buffer.lock();
+    if buffer.events.is_empty() {
+        if let Some(e) = buffer.error.take() {
+            return Err(host_error(FUNCTION, e));
+        }
+    }
+    Ok(Value::List(buffer.events.drain(..).collect()))
+}
+
+/// Whether a watch has ended and every event has been taken.
+///
+/// A watch only ends when its first list fails.
+///
+/// # Arguments
+///
+/// * `args[0]` - Watch handle
+pub fn watch_finished(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let buffer = buffer(args, "k8s.watch_finished")?;
+    let buffer = buffer.lock();
+    Ok(Value::Bool(
+        buffer.finished && buffer.events.is_empty() && buffer.error.is_none(),
+    ))
+}
+
+/// Stop a watch and release its handle.
+///
+/// # Arguments
+///
+/// * `args[0]` - Watch handle
+///
+/// # Returns
+///
+/// Number of events dropped because the buffer was full
+pub fn watch_close(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.watch_close";
+
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| host_error(FUNCTION, "missing handle argument"))?;
+    let stream = streams()
+        .lock()
+        .remove(&handle)
+        .ok_or_else(|| host_error(FUNCTION, "invalid handle"))?;
+    stream.task.abort();
+
+    let dropped = stream.buffer.lock().dropped;
+    Ok(Value::Int(dropped as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    fn pod(name: &str) -> DynamicObject {
+        let resource = ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&());
+        DynamicObject::new(name, &resource).within("default")
+    }
+
+    #[test]
+    fn test_watch_options_from_value() {
+        assert_eq!(
+            WatchOptions::from_value(&Value::String("app=web".into())).unwrap(),
+            WatchOptions::default().with_label_selector("app=web")
+        );
+        let options = WatchOptions::from_value(&Value::Map(HashMap::from([
+            (
+                "field_selector".to_string(),
+                Value::String("status.phase=Running".into()),
+            ),
+            ("all_namespaces".to_string(), Value::Bool(true)),
+        ])))
+        .unwrap();
+        assert_eq!(
+            options,
+            WatchOptions::default()
+                .with_field_selector("status.phase=Running")
+                .with_all_namespaces(true)
+        );
+        assert!(WatchOptions::from_value(&Value::Int(1)).is_err());
+        assert!(WatchOptions::from_value(&Value::Map(HashMap::from([(
+            "label_selector".to_string(),
+            Value::Int(1)
+        )])))
+        .is_err());
+    }
+
+    #[test]
+    fn test_watch_all_namespaces_checked() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let host = crate::k8s::stub_discovery_host(&runtime);
+        let ctx = ctx();
+        let safety = SafetyConfig::new()
+            .with_allow_k8s(true)
+            .with_k8s(crate::safety::K8sAllowlist::all().with_namespaces(["default"]));
+        let all = Value::Map(HashMap::from([(
+            "all_namespaces".to_string(),
+            Value::Bool(true),
+        )]));
+
+        let pods = [Value::String("v1/Pod".into()), all.clone()];
+        let err = watch(&host, &safety, &pods, &ctx).unwrap_err();
+        assert!(err.to_string().contains("across all namespaces"));
+
+        // Cluster-scoped kinds and the allowed namespace are still watched
+        for args in [
+            [Value::String("v1/Node".into()), all],
+            [Value::String("v1/Pod".into()), Value::Null],
+        ] {
+            let handle = watch(&host, &safety, &args, &ctx).unwrap();
+            watch_close(&[handle], &ctx).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_watch_handle() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let ctx = ctx();
+        let events = vec![
+            Ok(ResourceEvent {
+                event_type: ResourceEventType::Added,
+                object: pod("web-0"),
+            }),
+            Ok(ResourceEvent {
+                event_type: ResourceEventType::Deleted,
+                object: pod("web-1"),
+            }),
+            Err(Error::K8s("watch failed: forbidden".into())),
+        ];
+        let args = [Value::Int(open_stream(
+            runtime.handle(),
+            futures::stream::iter(events).boxed(),
+            1,
+        ))];
+
+        let buffer = buffer(&args, "test").unwrap();
+        while !buffer.lock().finished {
+            std::thread::yield_now();
+        }
+
+        // The buffer holds one event, so the first was dropped
+        let event = watch_poll(&args, &ctx).unwrap();
+        let fields = event.as_map().unwrap();
+        assert_eq!(fields["type"], Value::String("Deleted".into()));
+        assert_eq!(
+            fields["object"].as_map().unwrap()["metadata"]
+                .as_map()
+                .unwrap()["name"],
+            Value::String("web-1".into())
+        );
+        assert_eq!(watch_finished(&args, &ctx).unwrap(), Value::Bool(false));
+        assert!(watch_drain(&args, &ctx)
+            .unwrap_err()
+            .to_string()
+            .contains("forbidden"));
+        assert_eq!(watch_finished(&args, &ctx).unwrap(), Value::Bool(true));
+        assert_eq!(watch_close(&args, &ctx).unwrap(), Value::Int(1));
+        assert!(watch_poll(&args, &ctx).is_err());
+    }
+}
@@ -0,0 +1,1050 @@
+//! Scaling, rolling out and restarting workloads, and creating Jobs.
+//!
+//! Deployments, StatefulSets and DaemonSets are addressed by
+//! [`WorkloadKind`]. [`K8sClient::rollout_status`] reports progress the way
+//! `kubectl rollout status` does, and [`K8sClient::restart`] triggers a
+//! rolling restart like `kubectl rollout restart`:
+//!
+//! ```rust,ignore
+//! client.scale(WorkloadKind::Deployment, "web", 5).await?;
+//! client.restart(WorkloadKind::Deployment, "web").await?;
+//! let status = client
+//!     .wait_for_rollout(WorkloadKind::Deployment, "web", Duration::from_secs(300))
+//!     .await?;
+//! assert!(status.complete, "{}", status.message);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+use k8s_openapi::api::apps::v1::{DaemonSet, Deployment, StatefulSet};
+use k8s_openapi::api::batch::v1::{CronJob, Job, JobSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ObjectMeta, OwnerReference};
+use k8s_openapi::chrono::{SecondsFormat, Utc};
+use k8s_openapi::NamespaceResourceScope;
+use kube::api::{Api, Patch, PatchParams, PostParams};
+use kube::Resource;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value as JsonValue};
+
+use super::{host_error, optional_string, string_arg, K8sClient, K8sHost};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::{ExecutionContext, Value};
+
+/// Annotation `kubectl rollout restart` sets on the pod template.
+pub const RESTARTED_AT_ANNOTATION: &str = "kubectl.kubernetes.io/restartedAt";
+
+/// How often [`K8sClient::wait_for_rollout`] checks the rollout.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Default wait of `k8s.rollout_status` with `wait` set.
+const DEFAULT_ROLLOUT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// An apps/v1 workload kind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkloadKind {
+    /// `Deployment`.
+    Deployment,
+    /// `StatefulSet`.
+    StatefulSet,
+    /// `DaemonSet`.
+    DaemonSet,
+}
+
+impl WorkloadKind {
+    /// Parse a kind as kubectl accepts it: `Deployment`, `deployments`,
+    /// `deploy` or `apps/v1/Deployment`, and likewise `sts` and `ds`.
+    pub fn parse(kind: &str) -> Option<Self> {
+        let kind = kind.strip_prefix("apps/v1/").unwrap_or(kind);
+        match kind.to_ascii_lowercase().as_str() {
+            "deployment" | "deployments" | "deploy" => Some(Self::Deployment),
+            "statefulset" | "statefulsets" | "sts" => Some(Self::StatefulSet),
+            "daemonset" | "daemonsets" | "ds" => Some(Self::DaemonSet),
+            _ => None,
+        }
+    }
+
+    /// The kind name, such as `Deployment`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deployment => "Deployment",
+            Self::StatefulSet => "StatefulSet",
+            Self::DaemonSet => "DaemonSet",
+        }
+    }
+
+    /// The resource name used by RBAC, such as `deployments`.
+    pub fn plural(self) -> &'static str {
+        match self {
+            Self::Deployment => "deployments",
+            Self::StatefulSet => "statefulsets",
+            Self::DaemonSet => "daemonsets",
+        }
+    }
+}
+
+/// Progress of a rollout, as reported by [`K8sClient::rollout_status`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RolloutStatus {
+    /// Whether every replica runs the current template and is available.
+    pub complete: bool,
+    /// Whether the rollout stopped progressing (a Deployment past its
+    /// `progressDeadlineSeconds`).
+    pub failed: bool,
+    /// Human-readable state, as `kubectl rollout status` prints it.
+    pub message: String,
+    /// Desired replicas (scheduled pods for a DaemonSet).
+    pub desired: i32,
+    /// Replicas running the current template.
+    pub updated: i32,
+    /// Ready replicas.
+    pub ready: i32,
+    /// Available replicas.
+    pub available: i32,
+}
+
+impl RolloutStatus {
+    fn waiting(message: String, desired: i32, updated: i32, ready: i32, available: i32) -> Self {
+        Self {
+            complete: false,
+            failed: false,
+            message,
+            desired,
+            updated,
+            ready,
+            available,
+        }
+    }
+
+    /// Convert to a Value map with `complete`, `failed`, `message`,
+    /// `desired`, `updated`, `ready` and `available`.
+    pub fn to_value(&self) -> Value {
+        let mut map = HashMap::new();
+        map.insert("complete".to_string(), Value::Bool(self.complete));
+        map.insert("failed".to_string(), Value::Bool(self.failed));
+        map.insert("message".to_string(), Value::String(self.message.clone()));
+        for (key, count) in [
+            ("desired", self.desired),
+            ("updated", self.updated),
+            ("ready", self.ready),
+            ("available", self.available),
+        ] {
+            map.insert(key.to_string(), Value::Int(i64::from(count)));
+        }
+        Value::Map(map)
+    }
+}
+
+/// Whether the controller has seen the latest spec.
+fn observed(metadata: &ObjectMeta, observed_generation: Option<i64>) -> bool {
+    observed_generation.is_some_and(|observed| metadata.generation.unwrap_or(0) <= observed)
+}
+
+fn deployment_rollout(deployment: &Deployment) -> RolloutStatus {
+    let name = deployment.metadata.name.as_deref().unwrap_or_default();
+    let status = deployment.status.clone().unwrap_or_default();
+    let desired = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let updated = status.updated_replicas.unwrap_or(0);
+    let ready = status.ready_replicas.unwrap_or(0);
+    let available = status.available_replicas.unwrap_or(0);
+    let waiting =
+        |message: String| RolloutStatus::waiting(message, desired, updated, ready, available);
+
+    if !observed(&deployment.metadata, status.observed_generation) {
+        return waiting("waiting for deployment spec update to be observed".to_string());
+    }
+    let stalled = status.conditions.iter().flatten().any(|condition| {
+        condition.type_ == "Progressing"
+            && condition.reason.as_deref() == Some("ProgressDeadlineExceeded")
+    });
+    if stalled {
+        return RolloutStatus {
+            failed: true,
+            ..waiting(format!(
+                "deployment {:?} exceeded its progress deadline",
+                name
+            ))
+        };
+    }
+    if updated < desired {
+        return waiting(format!(
+            "{} out of {} new replicas have been updated",
+            updated, desired
+        ));
+    }
+    let total = status.replicas.unwrap_or(0);
+    if total > updated {
+        return waiting(format!(
+            "{} old replicas are pending termination",
+            total - updated
+        ));
+    }
+    if available < updated {
+        return waiting(format!(
+            "{} of {} updated replicas are available",
+            available, updated
+        ));
+    }
+    RolloutStatus {
+        complete: true,
+        ..waiting(format!("deployment {:?} successfully rolled out", name))
+    }
+}
+
+fn statefulset_rollout(statefulset: &StatefulSet) -> Result<RolloutStatus> {
+    let spec = statefulset.spec.clone().unwrap_or_default();
+    let status = statefulset.status.clone().unwrap_or_default();
+    let strategy = spec.update_strategy.unwrap_or_default();
+    if strategy
+        .type_
+        .as_deref()
+        .is_some_and(|t| t != "RollingUpdate")
+    {
+        return Err(Error::invalid_argument(
+            "rollout status is only available for the RollingUpdate strategy",
+        ));
+    }
+    let desired = spec.replicas.unwrap_or(1);
+    let updated = status.updated_replicas.unwrap_or(0);
+    let ready = status.ready_replicas.unwrap_or(0);
+    let available = status.available_replicas.unwrap_or(0);
+    let waiting =
+        |message: String| RolloutStatus::waiting(message, desired, updated, ready, available);
+    let complete = |message: String| RolloutStatus {
+        complete: true,
+        ..waiting(message)
+    };
+
+    if !observed(&statefulset.metadata, status.observed_generation) {
+        return Ok(waiting(
+            "waiting for statefulset spec update to be observed".to_string(),
+        ));
+    }
+    if ready < desired {
+        return Ok(waiting(format!(
+            "waiting for {} pods to be ready",
+            desired - ready
+        )));
+    }
+    if let Some(partition) = strategy.rolling_update.and_then(|update| update.partition) {
+        // Only pods at or above the partition ordinal are updated.
+        let target = (desired - partition).max(0);
+        if updated < target {
+            return Ok(waiting(format!(
+                "partitioned roll out: {} out of {} new pods have been updated",
+                updated, target
+            )));
+        }
+        return Ok(complete(format!(
+            "partitioned roll out complete: {} new pods have been updated",
+            updated
+        )));
+    }
+    if status.update_revision != status.current_revision {
+        return Ok(waiting(format!(
+            "waiting for rolling update to complete {} pods at revision {}",
+            updated,
+            status.update_revision.unwrap_or_default()
+        )));
+    }
+    Ok(complete(format!(
+        "rolling update complete {} pods at revision {}",
+        status.current_replicas.unwrap_or(0),
+        status.current_revision.unwrap_or_default()
+    )))
+}
+
+fn daemonset_rollout(daemonset: &DaemonSet) -> Result<RolloutStatus> {
+    let name = daemonset.metadata.name.as_deref().unwrap_or_default();
+    let strategy = daemonset
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.update_strategy.as_ref())
+        .and_then(|strategy| strategy.type_.as_deref());
+    if strategy.is_some_and(|t| t != "RollingUpdate") {
+        return Err(Error::invalid_argument(
+            "rollout status is only available for the RollingUpdate strategy",
+        ));
+    }
+    let status = daemonset.status.clone().unwrap_or_default();
+    let desired = status.desired_number_scheduled;
+    let updated = status.updated_number_scheduled.unwrap_or(0);
+    let ready = status.number_ready;
+    let available = status.number_available.unwrap_or(0);
+    let waiting =
+        |message: String| RolloutStatus::waiting(message, desired, updated, ready, available);
+
+    if !observed(&daemonset.metadata, status.observed_generation) {
+        return Ok(waiting(
+            "waiting for daemon set spec update to be observed".to_string(),
+        ));
+    }
+    if updated < desired {
+        return Ok(waiting(format!(
+            "{} out of {} new pods have been updated",
+            updated, desired
+        )));
+    }
+    if available < desired {
+        return Ok(waiting(format!(
+            "{} of {} updated pods are available",
+            available, desired
+        )));
+    }
+    Ok(RolloutStatus {
+        complete: true,
+        ..waiting(format!("daemon set {:?} successfully rolled out", name))
+    })
+}
+
+/// Read a Job from a Value: a whole Job object, or only its spec (a map
+/// with `template`). A Job without a name gets `generateName`
+/// `fusabi-job-`.
+pub fn job_from_value(value: &Value) -> Result<Job> {
+    if value.as_map().is_none() {
+        return Err(Error::invalid_argument("job must be a map"));
+    }
+    let json: JsonValue = fusabi_host::from_value_serde(value.clone())
+        .map_err(|e| Error::invalid_argument(format!("invalid job: {}", e)))?;
+    let is_object = ["apiVersion", "kind", "metadata", "spec"]
+        .iter()
+        .any(|key| json.get(key).is_some());
+    let mut job = if is_object {
+        if let Some(kind) = json.get("kind").and_then(|k| k.as_str()) {
+            if kind != "Job" {
+                return Err(Error::invalid_argument(format!(
+                    "expected a Job, got {}",
+                    kind
+                )));
+            }
+        }
+        serde_json::from_value::<Job>(json)
+    } else {
+        serde_json::from_value::<JobSpec>(json).map(|spec| Job {
+            spec: Some(spec),
+            ..Job::default()
+        })
+    }
+    .map_err(|e| Error::invalid_argument(format!("invalid job: {}", e)))?;
+
+    if job.spec.is_none() {
+        return Err(Error::invalid_argument("job has no spec"));
+    }
+    if job.metadata.name.is_none() && job.metadata.generate_name.is_none() {
+        job.metadata.generate_name = Some("fusabi-job-".to_string());
+    }
+    Ok(job)
+}
+
+/// The Job `kubectl create job --from=cronjob/NAME` creates.
+fn job_from_cronjob(cronjob: &CronJob, name: Option<&str>) -> Result<Job> {
+    let cronjob_name = cronjob
+        .metadata
+        .name
+        .clone()
+        .ok_or_else(|| Error::K8s("cronjob has no name".to_string()))?;
+    let template = cronjob
+        .spec
+        .as_ref()
+        .map(|spec| spec.job_template.clone())
+        .unwrap_or_default();
+    let template_metadata = template.metadata.unwrap_or_default();
+
+    let mut annotations = template_metadata.annotations.unwrap_or_default();
+    annotations.insert(
+        "cronjob.kubernetes.io/instantiate".to_string(),
+        "manual".to_string(),
+    );
+    let owner = cronjob.metadata.uid.clone().map(|uid| OwnerReference {
+        api_version: "batch/v1".to_string(),
+        kind: "CronJob".to_string(),
+        name: cronjob_name.clone(),
+        uid,
+        controller: Some(true),
+        ..OwnerReference::default()
+    });
+
+    Ok(Job {
+        metadata: ObjectMeta {
+            name: name.map(str::to_string),
+            generate_name: match name {
+                Some(_) => None,
+                None => Some(format!("{}-manual-", cronjob_name)),
+            },
+            labels: template_metadata.labels,
+            annotations: Some(annotations),
+            owner_references: owner.map(|owner| vec![owner]),
+            ..ObjectMeta::default()
+        },
+        spec: template.spec,
+        ..Job::default()
+    })
+}
+
+impl K8sClient {
+    fn namespaced<K>(&self) -> Api<K>
+    where
+        K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>,
+    {
+        Api::namespaced(self.client.clone(), &self.namespace)
+    }
+
+    async fn patch_workload<K>(&self, name: &str, patch: &JsonValue) -> kube::Result<()>
+    where
+        K: Resource<Scope = NamespaceResourceScope, DynamicType = ()>
+            + Clone
+            + DeserializeOwned
+            + Debug,
+    {
+        self.namespaced::<K>()
+            .patch(name, &PatchParams::default(), &Patch::Merge(patch))
+            .await
+            .map(|_| ())
+    }
+
+    /// Set the replica count of a Deployment or StatefulSet through its
+    /// `scale` subresource, returning the previous count.
+    pub async fn scale(&self, kind: WorkloadKind, name: &str, replicas: i32) -> Result<i32> {
+        if replicas < 0 {
+            return Err(Error::invalid_argument("replicas must not be negative"));
+        }
+        let patch = json!({ "spec": { "replicas": replicas } });
+        let params = PatchParams::default();
+        let result = match kind {
+            WorkloadKind::Deployment => {
+                let api = self.namespaced::<Deployment>();
+                match api.get_scale(name).await {
+                    Ok(previous) => api
+                        .patch_scale(name, &params, &Patch::Merge(&patch))
+                        .await
+                        .map(|_| previous),
+                    Err(e) => Err(e),
+                }
+            }
+            WorkloadKind::StatefulSet => {
+                let api = self.namespaced::<StatefulSet>();
+                match api.get_scale(name).await {
+                    Ok(previous) => api
+                        .patch_scale(name, &params, &Patch::Merge(&patch))
+                        .await
+                        .map(|_| previous),
+                    Err(e) => Err(e),
+                }
+            }
+            WorkloadKind::DaemonSet => {
+                return Err(Error::invalid_argument(
+                    "daemon sets run one pod per node and cannot be scaled",
+                ))
+            }
+        };
+        let previous = result
+            .map_err(|e| Error::K8s(format!("scale {} {} failed: {}", kind.as_str(), name, e)))?;
+        Ok(previous
+            .spec
+            .and_then(|spec| spec.replicas)
+            .unwrap_or_default())
+    }
+
+    /// Check the rollout of a workload once.
+    ///
+    /// # Errors
+    ///
+    /// An invalid-argument error for a StatefulSet or DaemonSet with the
+    /// `OnDelete` update strategy, which has no rollout to follow.
+    pub async fn rollout_status(&self, kind: WorkloadKind, name: &str) -> Result<RolloutStatus> {
+        let failed =
+            |e: kube::Error| Error::K8s(format!("get {} {} failed: {}", kind.as_str(), name, e));
+        match kind {
+            WorkloadKind::Deployment => Ok(deployment_rollout(
+                &self
+                    .namespaced::<Deployment>()
+                    .get(name)
+                    .await
+                    .map_err(failed)?,
+            )),
+            WorkloadKind::StatefulSet => statefulset_rollout(
+                &self
+                    .namespaced::<StatefulSet>()
+                    .get(name)
+                    .await
+                    .map_err(failed)?,
+            ),
+            WorkloadKind::DaemonSet => daemonset_rollout(
+                &self
+                    .namespaced::<DaemonSet>()
+                    .get(name)
+                    .await
+                    .map_err(failed)?,
+            ),
+        }
+    }
+
+    /// Wait for a rollout to complete or fail, like `kubectl rollout
+    /// status`. After `timeout` the last status is returned with
+    /// `complete` unset.
+    pub async fn wait_for_rollout(
+        &self,
+        kind: WorkloadKind,
+        name: &str,
+        timeout: Duration,
+    ) -> Result<RolloutStatus> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let status = self.rollout_status(kind, name).await?;
+            let now = Instant::now();
+            if status.complete || status.failed || now >= deadline {
+                return Ok(status);
+            }
+            tokio::time::sleep(POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
+    /// Restart every pod of a workload with a rolling update, like
+    /// `kubectl rollout restart`, by stamping the pod template with
+    /// [`RESTARTED_AT_ANNOTATION`].
+    pub async fn restart(&self, kind: WorkloadKind, name: &str) -> Result<()> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let patch = json!({
+            "spec": { "template": { "metadata": { "annotations": {
+                RESTARTED_AT_ANNOTATION: now,
+            } } } }
+        });
+        match kind {
+            WorkloadKind::Deployment => self.patch_workload::<Deployment>(name, &patch).await,
+            WorkloadKind::StatefulSet => self.patch_workload::<StatefulSet>(name, &patch).await,
+            WorkloadKind::DaemonSet => self.patch_workload::<DaemonSet>(name, &patch).await,
+        }
+        .map_err(|e| Error::K8s(format!("restart {} {} failed: {}", kind.as_str(), name, e)))
+    }
+
+    /// Create a Job in the client's namespace, returning its name.
+    pub async fn create_job(&self, job: &Job) -> Result<String> {
+        let created = self
+            .namespaced::<Job>()
+            .create(&PostParams::default(), job)
+            .await
+            .map_err(|e| Error::K8s(format!("create job failed: {}", e)))?;
+        created
+            .metadata
+            .name
+            .ok_or_else(|| Error::K8s("created job has no name".to_string()))
+    }
+
+    /// Run a CronJob now, like `kubectl create job --from=cronjob/NAME`,
+    /// returning the Job name. Without `name` one is generated from the
+    /// CronJob's.
+    pub async fn trigger_cronjob(&self, cronjob: &str, name: Option<&str>) -> Result<String> {
+        let cronjob = self
+            .namespaced::<CronJob>()
+            .get(cronjob)
+            .await
+            .map_err(|e| Error::K8s(format!("get cronjob {} failed: {}", cronjob, e)))?;
+        self.create_job(&job_from_cronjob(&cronjob, name)?).await
+    }
+}
+
+/// The workload kind argument.
+fn workload_kind(args: &[Value], function: &str) -> fusabi_host::Result<WorkloadKind> {
+    let kind = string_arg(args, 0, "kind", function)?;
+    WorkloadKind::parse(kind).ok_or_else(|| {
+        host_error(
+            function,
+            format!(
+                "unsupported kind {:?}; expected Deployment, StatefulSet or DaemonSet",
+                kind
+            ),
+        )
+    })
+}
+
+/// Set the replica count of a Deployment or StatefulSet.
+///
+/// Checked as `patch` on the `scale` subresource, e.g.
+/// `deployments/scale`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Kind: `"Deployment"` or `"StatefulSet"` (or `deploy`,
+///   `sts`)
+/// * `args[1]` - Name
+/// * `args[2]` - Replica count
+/// * `args[3]` - Namespace (optional, defaults to the client's)
+///
+/// # Returns
+///
+/// The previous replica count
+pub fn scale(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.scale";
+
+    let kind = workload_kind(args, FUNCTION)?;
+    let name = string_arg(args, 1, "name", FUNCTION)?;
+    let replicas = args
+        .get(2)
+        .and_then(|v| v.as_int())
+        .and_then(|n| i32::try_from(n).ok())
+        .filter(|n| *n >= 0)
+        .ok_or_else(|| host_error(FUNCTION, "replicas must be a non-negative integer"))?;
+    let client = host.client_for(optional_string(args, 3, "namespace", FUNCTION)?);
+    safety
+        .check_k8s(
+            "patch",
+            &format!("{}/scale", kind.plural()),
+            Some(client.namespace()),
+        )
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let previous = host
+        .block_on(client.scale(kind, name, replicas))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::Int(i64::from(previous)))
+}
+
+/// Report the rollout of a Deployment, StatefulSet or DaemonSet, like
+/// `kubectl rollout status`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Kind: `"Deployment"`, `"StatefulSet"` or `"DaemonSet"`
+/// * `args[1]` - Name
+/// * `args[2]` - Options map with `namespace`, `wait` (wait for the rollout
+///   to complete or fail, default false) and `timeout_ms` (default
+///   300,000) (optional)
+///
+/// # Returns
+///
+/// The [`RolloutStatus`] map
+pub fn rollout_status(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.rollout_status";
+
+    let kind = workload_kind(args, FUNCTION)?;
+    let name = string_arg(args, 1, "name", FUNCTION)?;
+    let options = match args.get(2) {
+        None | Some(Value::Null) => None,
+        Some(Value::Map(map)) => Some(map),
+        Some(_) => return Err(host_error(FUNCTION, "options must be a map")),
+    };
+    let option = |key: &str| options.and_then(|o| o.get(key)).filter(|v| !v.is_null());
+    let namespace = match option("namespace") {
+        None => None,
+        Some(v) => Some(
+            v.as_str()
+                .ok_or_else(|| host_error(FUNCTION, "namespace must be a string"))?
+                .to_string(),
+        ),
+    };
+    let wait = match option("wait") {
+        None => false,
+        Some(v) => v
+            .as_bool()
+            .ok_or_else(|| host_error(FUNCTION, "wait must be a boolean"))?,
+    };
+    let timeout = match option("timeout_ms") {
+        None => DEFAULT_ROLLOUT_TIMEOUT,
+        Some(v) => v
+            .as_int()
+            .filter(|ms| *ms > 0)
+            .map(|ms| Duration::from_millis(ms as u64))
+            .ok_or_else(|| host_error(FUNCTION, "timeout_ms must be a positive integer"))?,
+    };
+    let client = host.client_for(namespace);
+    safety
+        .check_k8s("get", kind.plural(), Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let status = if wait {
+        host.block_on(client.wait_for_rollout(kind, name, safety.clamp_timeout(timeout)))
+    } else {
+        host.block_on(client.rollout_status(kind, name))
+    }
+    .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(status.to_value())
+}
+
+/// Restart the pods of a Deployment, StatefulSet or DaemonSet with a
+/// rolling update, like `kubectl rollout restart`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Kind: `"Deployment"`, `"StatefulSet"` or `"DaemonSet"`
+/// * `args[1]` - Name
+/// * `args[2]` - Namespace (optional, defaults to the client's)
+pub fn restart(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.restart";
+
+    let kind = workload_kind(args, FUNCTION)?;
+    let name = string_arg(args, 1, "name", FUNCTION)?;
+    let client = host.client_for(optional_string(args, 2, "namespace", FUNCTION)?);
+    safety
+        .check_k8s("patch", kind.plural(), Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    host.block_on(client.restart(kind, name))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::Null)
+}
+
+/// Create a Job from a spec.
+///
+/// # Arguments
+///
+/// * `args[0]` - A Job object map, or only its spec (a map with
+///   `template`), in the API's camelCase field names
+/// * `args[1]` - Options map with `name`, overriding the Job's (default: a
+///   generated `fusabi-job-` name), `namespace` and `labels` (map)
+///   (optional)
+///
+/// # Returns
+///
+/// The Job name
+pub fn create_job(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.create_job";
+
+    let mut job = job_from_value(args.first().unwrap_or(&Value::Null))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    let options = JobOptions::from_value(args.get(1)).map_err(|e| host_error(FUNCTION, e))?;
+    if let Some(name) = options.name {
+        job.metadata.name = Some(name);
+        job.metadata.generate_name = None;
+    }
+    if !options.labels.is_empty() {
+        job.metadata
+            .labels
+            .get_or_insert_with(BTreeMap::new)
+            .extend(options.labels);
+    }
+    let client = host.client_for(options.namespace.or_else(|| job.metadata.namespace.take()));
+    safety
+        .check_k8s("create", "jobs", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let name = host
+        .block_on(client.create_job(&job))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::String(name))
+}
+
+/// Run a CronJob now, like `kubectl create job --from=cronjob/NAME`.
+///
+/// Checked as `get` on `cronjobs` and `create` on `jobs`.
+///
+/// # Arguments
+///
+/// * `args[0]` - CronJob name
+/// * `args[1]` - Options map with `name` (default: generated from the
+///   CronJob's) and `namespace` (optional)
+///
+/// # Returns
+///
+/// The Job name
+pub fn trigger_cronjob(
+    host: &K8sHost,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "k8s.trigger_cronjob";
+
+    let cronjob = string_arg(args, 0, "cronjob", FUNCTION)?;
+    let options = JobOptions::from_value(args.get(1)).map_err(|e| host_error(FUNCTION, e))?;
+    let client = host.client_for(options.namespace);
+    safety
+        .check_k8s("get", "cronjobs", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    safety
+        .check_k8s("create", "jobs", Some(client.namespace()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+
+    let name = host
+        .block_on(client.trigger_cronjob(cronjob, options.name.as_deref()))
+        .map_err(|e| host_error(FUNCTION, e))?;
+    Ok(Value::String(name))
+}
+
+/// Options of `k8s.create_job` and `k8s.trigger_cronjob`.
+#[derive(Debug, Default)]
+struct JobOptions {
+    name: Option<String>,
+    namespace: Option<String>,
+    labels: BTreeMap<String, String>,
+}
+
+impl JobOptions {
+    fn from_value(value: Option<&Value>) -> std::result::Result<Self, String> {
+        let map = match value {
+            None | Some(Value::Null) => return Ok(Self::default()),
+            Some(Value::Map(map)) => map,
+            Some(_) => return Err("options must be a map".to_string()),
+        };
+        let string = |key: &str| -> std::result::Result<Option<String>, String> {
+            match map.get(key) {
+                None | Some(Value::Null) => Ok(None),
+                Some(v) => v
+                    .as_str()
+                    .map(|s| Some(s.to_string()))
+                    .ok_or_else(|| format!("{} must be a string", key)),
+            }
+        };
+        let labels = match map.get("labels") {
+            None | Some(Value::Null) => BTreeMap::new(),
+            Some(Value::Map(labels)) => labels
+                .iter()
+                .map(|(k, v)| match v {
+                    Value::String(s) => Ok((k.clone(), s.clone())),
+                    _ => Err(format!("label {} must be a string", k)),
+                })
+                .collect::<std::result::Result<_, _>>()?,
+            Some(_) => return Err("labels must be a map".to_string()),
+        };
+        Ok(Self {
+            name: string("name")?,
+            namespace: string("namespace")?,
+            labels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::{
+        DeploymentCondition, DeploymentSpec, DeploymentStatus, StatefulSetSpec, StatefulSetStatus,
+        StatefulSetUpdateStrategy,
+    };
+
+    fn deployment(generation: i64, status: DeploymentStatus) -> Deployment {
+        Deployment {
+            metadata: ObjectMeta {
+                name: Some("web".into()),
+                generation: Some(generation),
+                ..ObjectMeta::default()
+            },
+            spec: Some(DeploymentSpec {
+                replicas: Some(3),
+                ..DeploymentSpec::default()
+            }),
+            status: Some(status),
+        }
+    }
+
+    #[test]
+    fn test_workload_kind_parse() {
+        assert_eq!(
+            WorkloadKind::parse("deploy"),
+            Some(WorkloadKind::Deployment)
+        );
+        assert_eq!(
+            WorkloadKind::parse("apps/v1/StatefulSet"),
+            Some(WorkloadKind::StatefulSet)
+        );
+        assert_eq!(WorkloadKind::parse("DS"), Some(WorkloadKind::DaemonSet));
+        assert_eq!(WorkloadKind::parse("Pod"), None);
+    }
+
+    #[test]
+    fn test_deployment_rollout() {
+        let status = |updated, total, available| DeploymentStatus {
+            observed_generation: Some(2),
+            replicas: Some(total),
+            updated_replicas: Some(updated),
+            available_replicas: Some(available),
+            ready_replicas: Some(available),
+            ..DeploymentStatus::default()
+        };
+
+        let unobserved = deployment_rollout(&deployment(3, status(3, 3, 3)));
+        assert!(!unobserved.complete);
+        assert!(unobserved.message.contains("to be observed"));
+
+        let progressing = deployment_rollout(&deployment(2, status(1, 4, 3)));
+        assert!(!progressing.complete);
+        assert_eq!(
+            progressing.message,
+            "1 out of 3 new replicas have been updated"
+        );
+        assert_eq!(
+            deployment_rollout(&deployment(2, status(3, 4, 3))).message,
+            "1 old replicas are pending termination"
+        );
+        assert_eq!(
+            deployment_rollout(&deployment(2, status(3, 3, 2))).message,
+            "2 of 3 updated replicas are available"
+        );
+
+        let done = deployment_rollout(&deployment(2, status(3, 3, 3)));
+        assert!(done.complete && !done.failed);
+        assert_eq!(done.available, 3);
+
+        let mut stalled = status(1, 3, 2);
+        stalled.conditions = Some(vec![DeploymentCondition {
+            type_: "Progressing".into(),
+            reason: Some("ProgressDeadlineExceeded".into()),
+            status: "False".into(),
+            ..DeploymentCondition::default()
+        }]);
+        let stalled = deployment_rollout(&deployment(2, stalled));
+        assert!(stalled.failed && !stalled.complete);
+    }
+
+    #[test]
+    fn test_statefulset_rollout() {
+        let statefulset = |partition: Option<i32>, updated, revision: &str| StatefulSet {
+            metadata: ObjectMeta {
+                generation: Some(1),
+                ..ObjectMeta::default()
+            },
+            spec: Some(StatefulSetSpec {
+                replicas: Some(3),
+                update_strategy: Some(StatefulSetUpdateStrategy {
+                    type_: Some("RollingUpdate".into()),
+                    rolling_update: partition.map(|partition| {
+                        k8s_openapi::api::apps::v1::RollingUpdateStatefulSetStrategy {
+                            partition: Some(partition),
+                            ..Default::default()
+                        }
+                    }),
+                }),
+                ..StatefulSetSpec::default()
+            }),
+            status: Some(StatefulSetStatus {
+                observed_generation: Some(1),
+                replicas: 3,
+                ready_replicas: Some(3),
+                updated_replicas: Some(updated),
+                current_replicas: Some(3),
+                current_revision: Some("web-1".into()),
+                update_revision: Some(revision.into()),
+                ..StatefulSetStatus::default()
+            }),
+        };
+
+        let rolling = statefulset_rollout(&statefulset(None, 1, "web-2")).unwrap();
+        assert!(!rolling.complete);
+        assert!(rolling.message.contains("revision web-2"));
+        assert!(
+            statefulset_rollout(&statefulset(None, 3, "web-1"))
+                .unwrap()
+                .complete
+        );
+        assert!(
+            statefulset_rollout(&statefulset(Some(2), 1, "web-2"))
+                .unwrap()
+                .complete
+        );
+        assert!(
+            !statefulset_rollout(&statefulset(Some(1), 1, "web-2"))
+                .unwrap()
+                .complete
+        );
+
+        let mut on_delete = statefulset(None, 3, "web-1");
+        on_delete.spec.as_mut().unwrap().update_strategy = Some(StatefulSetUpdateStrategy {
+            type_: Some("OnDelete".into()),
+            rolling_update: None,
+        });
+        assert!(statefulset_rollout(&on_delete).is_err());
+    }
+
+    #[test]
+    fn test_job_from_value() {
+        let template = Value::Map(HashMap::from([(
+            "spec".to_string(),
+            Value::Map(HashMap::from([(
+                "restartPolicy".to_string(),
+                Value::String("Never".into()),
+            )])),
+        )]));
+        let spec = Value::Map(HashMap::from([
+            ("template".to_string(), template.clone()),
+            ("backoffLimit".to_string(), Value::Int(2)),
+        ]));
+        let job = job_from_value(&spec).unwrap();
+        assert_eq!(job.metadata.generate_name.as_deref(), Some("fusabi-job-"));
+        assert_eq!(job.spec.unwrap().backoff_limit, Some(2));
+
+        let object = Value::Map(HashMap::from([
+            ("apiVersion".to_string(), Value::String("batch/v1".into())),
+            ("kind".to_string(), Value::String("Job".into())),
+            (
+                "metadata".to_string(),
+                Value::Map(HashMap::from([(
+                    "name".to_string(),
+                    Value::String("migrate".into()),
+                )])),
+            ),
+            (
+                "spec".to_string(),
+                Value::Map(HashMap::from([("template".to_string(), template)])),
+            ),
+        ]));
+        let job = job_from_value(&object).unwrap();
+        assert_eq!(job.metadata.name.as_deref(), Some("migrate"));
+        assert_eq!(job.metadata.generate_name, None);
+
+        let mut wrong = object.as_map().unwrap().clone();
+        wrong.insert("kind".to_string(), Value::String("Pod".into()));
+        assert!(job_from_value(&Value::Map(wrong)).is_err());
+        assert!(job_from_value(&Value::Null).is_err());
+    }
+
+    #[test]
+    fn test_job_from_cronjob() {
+        let cronjob: CronJob = serde_json::from_value(json!({
+            "metadata": { "name": "backup", "uid": "1234" },
+            "spec": {
+                "schedule": "0 3 * * *",
+                "jobTemplate": {
+                    "metadata": { "labels": { "app": "backup" } },
+                    "spec": { "template": { "spec": { "containers": [] } } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let job = job_from_cronjob(&cronjob, None).unwrap();
+        assert_eq!(
+            job.metadata.generate_name.as_deref(),
+            Some("backup-manual-")
+        );
+        assert_eq!(job.metadata.labels.unwrap()["app"], "backup");
+        assert_eq!(
+            job.metadata.annotations.unwrap()["cronjob.kubernetes.io/instantiate"],
+            "manual"
+        );
+        let owner = &job.metadata.owner_references.unwrap()[0];
+        assert_eq!(
+            (owner.kind.as_str(), owner.uid.as_str()),
+            ("CronJob", "1234")
+        );
+        assert!(job.spec.is_some());
+
+        let named = job_from_cronjob(&cronjob, Some("backup-now")).unwrap();
+        assert_eq!(named.metadata.name.as_deref(), Some("backup-now"));
+        assert_eq!(named.metadata.generate_name, None);
+    }
+}
@@ -21,6 +21,9 @@
 //! - **GPU** - GPU metrics via NVML (utilization, memory, temperature)
 //! - **FsStream** - File streaming with backpressure (tail, chunked reads)
 //! - **NetHttp** - Enhanced HTTP client (retries, streaming, custom options)
+//! - **Patch** - Unified diff creation and conflict-checked application
+//! - **Calendar** - iCalendar parsing and recurring event expansion
+//! - **Units** - Byte size, duration and SI number parsing and formatting
 //!
 //! ## Domain Packs
 //!
@@ -96,6 +99,27 @@ pub mod fs_stream;
 #[cfg(feature = "net_http")]
 pub mod net_http;
 
+#[cfg(feature = "calendar")]
+pub mod calendar;
+
+#[cfg(feature = "units")]
+pub mod units;
+
+#[cfg(feature = "locale")]
+pub mod locale;
+
+#[cfg(feature = "system")]
+pub mod system;
+
+#[cfg(feature = "stats")]
+pub mod stats;
+
+#[cfg(feature = "patch")]
+pub mod patch;
+
+#[cfg(feature = "sigilforge")]
+pub mod sigilforge;
+
 // Domain packs
 #[cfg(feature = "terminal-ui")]
 pub mod terminal_ui;
@@ -111,8 +135,13 @@ pub mod mcp;
 
 pub use config::{ModuleConfig, StdlibConfig};
 pub use error::{Error, Result};
-pub use registry::StdlibRegistry;
-pub use safety::{HostAllowlist, PathAllowlist, SafetyConfig};
+pub use registry::{ExecutionMode, PlannedOperation, ScriptInvoker, StdlibRegistry};
+pub use safety::{
+    is_internal_address, is_privilege_escalation, is_secret_key, AuditEvent, AuditLog, AuditQuery,
+    AuditSink, CallbackAuditSink, Grant, GrantStore, HostAllowlist, JsonlAuditSink, K8sAllowlist,
+    MemoryAuditSink, PathAllowlist, PinnedHost, SafetyConfig, TracingAuditSink,
+    DEFAULT_AUDIT_CAPACITY, PRIVILEGE_ESCALATION_COMMANDS,
+};
 
 /// Crate version for compatibility checks.
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
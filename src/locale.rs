@@ -0,0 +1,1296 @@
+//! Locale module.
+//!
+//! Formats numbers, currency amounts and dates for a locale, and looks up
+//! translated messages, so user-facing scripts (TUIs in particular) can be
+//! localized:
+//!
+//! ```text
+//! locale.format_number(1234567.891, "de-DE")     // "1.234.567,891"
+//! locale.format_currency(1234.5, "EUR", "fr-FR") // "1 234,50 €"
+//! locale.format_date(0, {locale: "en-US", style: "long"}) // "January 1, 1970"
+//! locale.load("/app/i18n")                       // de.json, fr-FR.json, ...
+//! locale.t("files.deleted", {count: 3})          // "3 Dateien gelöscht"
+//! ```
+//!
+//! Formatting covers a built-in set of locales (see [`supported_locales`])
+//! with patterns following CLDR; other tags fall back to their language and
+//! then to `en-US`. Currency symbols are the same in every locale.
+//!
+//! Message catalogs are JSON files named after their locale (`de.json`,
+//! `pt-BR.json`), loaded only from paths the [`SafetyConfig`] allows
+//! reading. Nested objects become dotted keys; an object with only `zero`,
+//! `one` and `other` keys is a plural message chosen by the `count`
+//! argument. `{name}` placeholders are filled from the arguments, with
+//! numbers formatted for the locale. Lookups fall back from `de-AT` to `de`
+//! to English, and finally to the key itself.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use parking_lot::RwLock;
+use serde_json::Value as JsonValue;
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use crate::safety::{PathAllowlist, SafetyConfig};
+use crate::time::{civil_from_days, weekday_of, SECS_PER_DAY};
+
+/// Locale used when none is configured or a tag is not supported.
+pub const DEFAULT_LOCALE: &str = "en-US";
+
+/// Language whose catalog is consulted when the current one lacks a key.
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+/// Narrow no-break space, the French group separator.
+const NNBSP: &str = "\u{202f}";
+/// No-break space, between amounts and currency symbols.
+const NBSP: &str = "\u{a0}";
+
+const EN_MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+const EN_MONTHS_SHORT: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const EN_WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+const CJK_MONTHS: [&str; 12] = [
+    "1月", "2月", "3月", "4月", "5月", "6月", "7月", "8月", "9月", "10月", "11月", "12月",
+];
+
+/// Formatting conventions of a locale.
+#[derive(Debug)]
+pub struct LocaleData {
+    /// BCP 47 tag, e.g. `"de-DE"`.
+    pub tag: &'static str,
+    decimal: &'static str,
+    group: &'static str,
+    /// Fewest integer digits that get grouped (5 where `1234` stays
+    /// ungrouped).
+    min_grouping: usize,
+    currency_prefix: bool,
+    currency_space: &'static str,
+    /// Short, medium, long and full date patterns.
+    dates: [&'static str; 4],
+    time: &'static str,
+    months: [&'static str; 12],
+    months_short: [&'static str; 12],
+    /// Monday first.
+    weekdays: [&'static str; 7],
+}
+
+static LOCALES: &[LocaleData] = &[
+    LocaleData {
+        tag: "en-US",
+        decimal: ".",
+        group: ",",
+        min_grouping: 4,
+        currency_prefix: true,
+        currency_space: "",
+        dates: ["M/d/yy", "MMM d, y", "MMMM d, y", "EEEE, MMMM d, y"],
+        time: "h:mm a",
+        months: EN_MONTHS,
+        months_short: EN_MONTHS_SHORT,
+        weekdays: EN_WEEKDAYS,
+    },
+    LocaleData {
+        tag: "en-GB",
+        decimal: ".",
+        group: ",",
+        min_grouping: 4,
+        currency_prefix: true,
+        currency_space: "",
+        dates: ["dd/MM/y", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+        time: "HH:mm",
+        months: EN_MONTHS,
+        months_short: EN_MONTHS_SHORT,
+        weekdays: EN_WEEKDAYS,
+    },
+    LocaleData {
+        tag: "de-DE",
+        decimal: ",",
+        group: ".",
+        min_grouping: 4,
+        currency_prefix: false,
+        currency_space: NBSP,
+        dates: ["dd.MM.yy", "dd.MM.y", "d. MMMM y", "EEEE, d. MMMM y"],
+        time: "HH:mm",
+        months: [
+            "Januar",
+            "Februar",
+            "März",
+            "April",
+            "Mai",
+            "Juni",
+            "Juli",
+            "August",
+            "September",
+            "Oktober",
+            "November",
+            "Dezember",
+        ],
+        months_short: [
+            "Jan.", "Feb.", "März", "Apr.", "Mai", "Juni", "Juli", "Aug.", "Sept.", "Okt.", "Nov.",
+            "Dez.",
+        ],
+        weekdays: [
+            "Montag",
+            "Dienstag",
+            "Mittwoch",
+            "Donnerstag",
+            "Freitag",
+            "Samstag",
+            "Sonntag",
+        ],
+    },
+    LocaleData {
+        tag: "fr-FR",
+        decimal: ",",
+        group: NNBSP,
+        min_grouping: 4,
+        currency_prefix: false,
+        currency_space: NBSP,
+        dates: ["dd/MM/y", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+        time: "HH:mm",
+        months: [
+            "janvier",
+            "février",
+            "mars",
+            "avril",
+            "mai",
+            "juin",
+            "juillet",
+            "août",
+            "septembre",
+            "octobre",
+            "novembre",
+            "décembre",
+        ],
+        months_short: [
+            "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+            "nov.", "déc.",
+        ],
+        weekdays: [
+            "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+        ],
+    },
+    LocaleData {
+        tag: "es-ES",
+        decimal: ",",
+        group: ".",
+        min_grouping: 5,
+        currency_prefix: false,
+        currency_space: NBSP,
+        dates: [
+            "d/M/yy",
+            "d MMM y",
+            "d 'de' MMMM 'de' y",
+            "EEEE, d 'de' MMMM 'de' y",
+        ],
+        time: "H:mm",
+        months: [
+            "enero",
+            "febrero",
+            "marzo",
+            "abril",
+            "mayo",
+            "junio",
+            "julio",
+            "agosto",
+            "septiembre",
+            "octubre",
+            "noviembre",
+            "diciembre",
+        ],
+        months_short: [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+        ],
+        weekdays: [
+            "lunes",
+            "martes",
+            "miércoles",
+            "jueves",
+            "viernes",
+            "sábado",
+            "domingo",
+        ],
+    },
+    LocaleData {
+        tag: "pt-BR",
+        decimal: ",",
+        group: ".",
+        min_grouping: 4,
+        currency_prefix: true,
+        currency_space: NBSP,
+        dates: [
+            "dd/MM/y",
+            "d 'de' MMM 'de' y",
+            "d 'de' MMMM 'de' y",
+            "EEEE, d 'de' MMMM 'de' y",
+        ],
+        time: "HH:mm",
+        months: [
+            "janeiro",
+            "fevereiro",
+            "março",
+            "abril",
+            "maio",
+            "junho",
+            "julho",
+            "agosto",
+            "setembro",
+            "outubro",
+            "novembro",
+            "dezembro",
+        ],
+        months_short: [
+            "jan.", "fev.", "mar.", "abr.", "mai.", "jun.", "jul.", "ago.", "set.", "out.", "nov.",
+            "dez.",
+        ],
+        weekdays: [
+            "segunda-feira",
+            "terça-feira",
+            "quarta-feira",
+            "quinta-feira",
+            "sexta-feira",
+            "sábado",
+            "domingo",
+        ],
+    },
+    LocaleData {
+        tag: "it-IT",
+        decimal: ",",
+        group: ".",
+        min_grouping: 4,
+        currency_prefix: false,
+        currency_space: NBSP,
+        dates: ["dd/MM/yy", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+        time: "HH:mm",
+        months: [
+            "gennaio",
+            "febbraio",
+            "marzo",
+            "aprile",
+            "maggio",
+            "giugno",
+            "luglio",
+            "agosto",
+            "settembre",
+            "ottobre",
+            "novembre",
+            "dicembre",
+        ],
+        months_short: [
+            "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+        ],
+        weekdays: [
+            "lunedì",
+            "martedì",
+            "mercoledì",
+            "giovedì",
+            "venerdì",
+            "sabato",
+            "domenica",
+        ],
+    },
+    LocaleData {
+        tag: "nl-NL",
+        decimal: ",",
+        group: ".",
+        min_grouping: 4,
+        currency_prefix: true,
+        currency_space: NBSP,
+        dates: ["dd-MM-y", "d MMM y", "d MMMM y", "EEEE d MMMM y"],
+        time: "HH:mm",
+        months: [
+            "januari",
+            "februari",
+            "maart",
+            "april",
+            "mei",
+            "juni",
+            "juli",
+            "augustus",
+            "september",
+            "oktober",
+            "november",
+            "december",
+        ],
+        months_short: [
+            "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+        ],
+        weekdays: [
+            "maandag",
+            "dinsdag",
+            "woensdag",
+            "donderdag",
+            "vrijdag",
+            "zaterdag",
+            "zondag",
+        ],
+    },
+    LocaleData {
+        tag: "ja-JP",
+        decimal: ".",
+        group: ",",
+        min_grouping: 4,
+        currency_prefix: true,
+        currency_space: "",
+        dates: ["y/MM/dd", "y/MM/dd", "y年M月d日", "y年M月d日EEEE"],
+        time: "H:mm",
+        months: CJK_MONTHS,
+        months_short: CJK_MONTHS,
+        weekdays: [
+            "月曜日",
+            "火曜日",
+            "水曜日",
+            "木曜日",
+            "金曜日",
+            "土曜日",
+            "日曜日",
+        ],
+    },
+    LocaleData {
+        tag: "zh-CN",
+        decimal: ".",
+        group: ",",
+        min_grouping: 4,
+        currency_prefix: true,
+        currency_space: "",
+        dates: ["y/M/d", "y年M月d日", "y年M月d日", "y年M月d日EEEE"],
+        time: "HH:mm",
+        months: CJK_MONTHS,
+        months_short: CJK_MONTHS,
+        weekdays: [
+            "星期一",
+            "星期二",
+            "星期三",
+            "星期四",
+            "星期五",
+            "星期六",
+            "星期日",
+        ],
+    },
+];
+
+/// Currency symbols and minor-unit digits; other codes are shown as the
+/// code with two decimals.
+const CURRENCIES: &[(&str, &str, usize)] = &[
+    ("USD", "$", 2),
+    ("EUR", "€", 2),
+    ("GBP", "£", 2),
+    ("JPY", "¥", 0),
+    ("CNY", "¥", 2),
+    ("KRW", "₩", 0),
+    ("INR", "₹", 2),
+    ("BRL", "R$", 2),
+    ("CHF", "CHF", 2),
+    ("CAD", "CA$", 2),
+    ("AUD", "A$", 2),
+];
+
+/// Tags of the locales with built-in formatting data.
+pub fn supported_locales() -> Vec<&'static str> {
+    LOCALES.iter().map(|l| l.tag).collect()
+}
+
+/// Normalize a locale tag: `de_DE.UTF-8` becomes `de-DE`, `EN-us` becomes
+/// `en-US`, and the POSIX `C` locale becomes [`DEFAULT_LOCALE`].
+pub fn normalize_tag(tag: &str) -> String {
+    let tag = tag.split(['.', '@']).next().unwrap_or_default().trim();
+    if tag.is_empty() || tag == "C" || tag == "POSIX" {
+        return DEFAULT_LOCALE.to_string();
+    }
+    let mut parts = tag.split(['_', '-']);
+    let language = parts.next().unwrap_or_default().to_ascii_lowercase();
+    match parts.next() {
+        Some(region) if !region.is_empty() => {
+            format!("{}-{}", language, region.to_ascii_uppercase())
+        }
+        _ => language,
+    }
+}
+
+/// Formatting data for `tag`, or for the first supported locale of its
+/// language.
+pub fn resolve(tag: &str) -> Option<&'static LocaleData> {
+    let tag = normalize_tag(tag);
+    let language = tag.split('-').next().unwrap_or_default();
+    LOCALES.iter().find(|l| l.tag == tag).or_else(|| {
+        LOCALES
+            .iter()
+            .find(|l| l.tag.split('-').next() == Some(language))
+    })
+}
+
+/// Format a number with the locale's separators.
+///
+/// Without `decimals`, integers get none and other numbers up to three,
+/// with trailing zeros dropped.
+pub fn format_number_in(value: f64, decimals: Option<usize>, locale: &LocaleData) -> String {
+    if !value.is_finite() {
+        return value.to_string();
+    }
+    let fixed = match decimals {
+        Some(decimals) => format!("{:.*}", decimals, value.abs()),
+        None => {
+            let text = format!("{:.3}", value.abs());
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+    };
+    let (integer, fraction) = fixed.split_once('.').unwrap_or((&fixed, ""));
+
+    let mut out = String::new();
+    // `-0.0` and values rounding to zero are not negative.
+    if value < 0.0 && fixed.bytes().any(|b| b.is_ascii_digit() && b != b'0') {
+        out.push('-');
+    }
+    if integer.len() >= locale.min_grouping {
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                out.push_str(locale.group);
+            }
+            out.push(digit);
+        }
+    } else {
+        out.push_str(integer);
+    }
+    if !fraction.is_empty() {
+        out.push_str(locale.decimal);
+        out.push_str(fraction);
+    }
+    out
+}
+
+/// Format an amount of an ISO 4217 currency.
+pub fn format_currency_in(amount: f64, currency: &str, locale: &LocaleData) -> String {
+    let code = currency.to_ascii_uppercase();
+    let (symbol, digits) = CURRENCIES
+        .iter()
+        .find(|(c, _, _)| *c == code)
+        .map_or((code.as_str(), 2), |(_, symbol, digits)| (*symbol, *digits));
+
+    let number = format_number_in(amount, Some(digits), locale);
+    let (sign, number) = match number.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", number.as_str()),
+    };
+    // Codes used as symbols are always spaced from the number.
+    let space = if symbol.chars().all(|c| c.is_ascii_uppercase()) {
+        NBSP
+    } else {
+        locale.currency_space
+    };
+    if locale.currency_prefix {
+        format!("{}{}{}{}", sign, symbol, space, number)
+    } else {
+        format!("{}{}{}{}", sign, number, space, symbol)
+    }
+}
+
+/// Length of a date format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateStyle {
+    /// Numeric, e.g. `1/5/24`.
+    Short,
+    /// Abbreviated month, e.g. `Jan 5, 2024`.
+    Medium,
+    /// Full month, e.g. `January 5, 2024`.
+    Long,
+    /// With the weekday, e.g. `Friday, January 5, 2024`.
+    Full,
+}
+
+impl DateStyle {
+    /// Parse `"short"`, `"medium"`, `"long"` or `"full"`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "short" => Some(DateStyle::Short),
+            "medium" => Some(DateStyle::Medium),
+            "long" => Some(DateStyle::Long),
+            "full" => Some(DateStyle::Full),
+            _ => None,
+        }
+    }
+}
+
+/// Format a Unix timestamp, shifted by `offset` seconds from UTC, as a
+/// date and optionally a time of day.
+pub fn format_date_in(
+    timestamp: i64,
+    offset: i64,
+    style: DateStyle,
+    with_time: bool,
+    locale: &LocaleData,
+) -> String {
+    let local = timestamp + offset;
+    let days = local.div_euclid(SECS_PER_DAY);
+    let seconds = local.rem_euclid(SECS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let fields = DateFields {
+        year,
+        month,
+        day,
+        weekday: weekday_of(days),
+        hour: (seconds / 3600) as u32,
+        minute: (seconds % 3600 / 60) as u32,
+    };
+
+    let pattern = locale.dates[style as usize];
+    let mut out = expand_pattern(pattern, &fields, locale);
+    if with_time {
+        out.push(' ');
+        out.push_str(&expand_pattern(locale.time, &fields, locale));
+    }
+    out
+}
+
+struct DateFields {
+    year: i64,
+    month: u32,
+    day: u32,
+    /// ISO, 1 = Monday.
+    weekday: i64,
+    hour: u32,
+    minute: u32,
+}
+
+/// Expand a CLDR-style pattern: letters repeat to select the field width and
+/// text in single quotes is literal.
+fn expand_pattern(pattern: &str, fields: &DateFields, locale: &LocaleData) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\'' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|c| *c == '\'')
+                .map_or(chars.len(), |p| i + 1 + p);
+            out.extend(&chars[i + 1..end]);
+            i = end + 1;
+            continue;
+        }
+        if !c.is_ascii_alphabetic() {
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        let run = chars[i..].iter().take_while(|d| **d == c).count();
+        let month = fields.month as usize - 1;
+        match (c, run) {
+            ('y', 2) => out.push_str(&format!("{:02}", fields.year.rem_euclid(100))),
+            ('y', _) => out.push_str(&fields.year.to_string()),
+            ('M', 1) => out.push_str(&fields.month.to_string()),
+            ('M', 2) => out.push_str(&format!("{:02}", fields.month)),
+            ('M', 3) => out.push_str(locale.months_short[month]),
+            ('M', _) => out.push_str(locale.months[month]),
+            ('d', 1) => out.push_str(&fields.day.to_string()),
+            ('d', _) => out.push_str(&format!("{:02}", fields.day)),
+            ('E', _) => out.push_str(locale.weekdays[fields.weekday as usize - 1]),
+            ('H', 1) => out.push_str(&fields.hour.to_string()),
+            ('H', _) => out.push_str(&format!("{:02}", fields.hour)),
+            ('h', _) => out.push_str(&((fields.hour + 11) % 12 + 1).to_string()),
+            ('m', _) => out.push_str(&format!("{:02}", fields.minute)),
+            ('a', _) => out.push_str(if fields.hour < 12 { "AM" } else { "PM" }),
+            _ => out.extend(std::iter::repeat(c).take(run)),
+        }
+        i += run;
+    }
+    out
+}
+
+/// A translated message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// A single text.
+    Text(String),
+    /// Texts chosen by a count.
+    Plural {
+        /// For a count of zero, if it differs from `other`.
+        zero: Option<String>,
+        /// For a count of one, if it differs from `other`.
+        one: Option<String>,
+        /// For every other count.
+        other: String,
+    },
+}
+
+impl Message {
+    fn select(&self, count: Option<f64>) -> &str {
+        match self {
+            Message::Text(text) => text,
+            Message::Plural { zero, one, other } => {
+                let form = match count {
+                    Some(n) if n.abs() < f64::EPSILON => zero,
+                    Some(n) if (n - 1.0).abs() < f64::EPSILON => one,
+                    _ => &None,
+                };
+                form.as_deref().unwrap_or(other)
+            }
+        }
+    }
+}
+
+/// Messages of one locale, by dotted key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    messages: HashMap<String, Message>,
+}
+
+impl Catalog {
+    /// Create an empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a JSON catalog.
+    pub fn from_json(text: &str) -> crate::Result<Self> {
+        let json: JsonValue = serde_json::from_str(text)
+            .map_err(|e| crate::Error::Format(format!("invalid message catalog: {}", e)))?;
+        let object = json.as_object().ok_or_else(|| {
+            crate::Error::Format("message catalog must be a JSON object".to_string())
+        })?;
+        let mut catalog = Self::new();
+        catalog.flatten("", object)?;
+        Ok(catalog)
+    }
+
+    fn flatten(
+        &mut self,
+        prefix: &str,
+        object: &serde_json::Map<String, JsonValue>,
+    ) -> crate::Result<()> {
+        for (key, value) in object {
+            let key = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            match value {
+                JsonValue::String(text) => {
+                    self.messages.insert(key, Message::Text(text.clone()));
+                }
+                JsonValue::Object(forms)
+                    if forms.contains_key("other")
+                        && forms
+                            .keys()
+                            .all(|k| matches!(k.as_str(), "zero" | "one" | "other")) =>
+                {
+                    let form =
+                        |name: &str| forms.get(name).and_then(|v| v.as_str()).map(str::to_string);
+                    let other = form("other").ok_or_else(|| {
+                        crate::Error::Format(format!("plural message {} must be text", key))
+                    })?;
+                    self.messages.insert(
+                        key,
+                        Message::Plural {
+                            zero: form("zero"),
+                            one: form("one"),
+                            other,
+                        },
+                    );
+                }
+                JsonValue::Object(nested) => self.flatten(&key, nested)?,
+                _ => {
+                    return Err(crate::Error::Format(format!(
+                        "message {} must be text or an object",
+                        key
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add or replace a message.
+    pub fn insert(&mut self, key: impl Into<String>, message: Message) {
+        self.messages.insert(key.into(), message);
+    }
+
+    /// Look up a message.
+    pub fn get(&self, key: &str) -> Option<&Message> {
+        self.messages.get(key)
+    }
+
+    /// Number of messages.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Whether the catalog has no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// The current locale and the loaded message catalogs.
+#[derive(Debug)]
+pub struct Localizer {
+    locale: RwLock<String>,
+    catalogs: RwLock<HashMap<String, Catalog>>,
+}
+
+impl Default for Localizer {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOCALE)
+    }
+}
+
+impl Localizer {
+    /// Create a localizer for `locale` with no catalogs.
+    pub fn new(locale: &str) -> Self {
+        Self {
+            locale: RwLock::new(normalize_tag(locale)),
+            catalogs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Create a localizer for the locale in `LC_ALL`, `LC_MESSAGES` or
+    /// `LANG`, the first one set.
+    pub fn from_env() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+        Self::new(&locale)
+    }
+
+    /// The current locale tag.
+    pub fn locale(&self) -> String {
+        self.locale.read().clone()
+    }
+
+    /// Change the current locale.
+    pub fn set_locale(&self, locale: &str) {
+        *self.locale.write() = normalize_tag(locale);
+    }
+
+    /// Formatting data for the current locale, falling back to
+    /// [`DEFAULT_LOCALE`].
+    pub fn data(&self) -> &'static LocaleData {
+        resolve(&self.locale()).unwrap_or(&LOCALES[0])
+    }
+
+    /// Merge `catalog` into the messages of `locale`.
+    pub fn add_catalog(&self, locale: &str, catalog: Catalog) {
+        self.catalogs
+            .write()
+            .entry(normalize_tag(locale))
+            .or_default()
+            .messages
+            .extend(catalog.messages);
+    }
+
+    /// Locales with loaded catalogs, sorted.
+    pub fn catalog_locales(&self) -> Vec<String> {
+        let mut locales: Vec<String> = self.catalogs.read().keys().cloned().collect();
+        locales.sort();
+        locales
+    }
+
+    /// Load a JSON catalog file, or every `*.json` file in a directory,
+    /// after checking `paths` allows reading them. A file's locale is
+    /// `locale` if given, else its name (`de.json` is `de`). Returns the
+    /// number of messages loaded.
+    pub fn load(
+        &self,
+        path: &Path,
+        locale: Option<&str>,
+        paths: &PathAllowlist,
+    ) -> crate::Result<usize> {
+        let path = std::fs::canonicalize(path)?;
+        paths.check_read(&path)?;
+
+        let files: Vec<PathBuf> = if path.is_dir() {
+            let mut files: Vec<PathBuf> = std::fs::read_dir(&path)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+                .collect();
+            files.sort();
+            files
+        } else {
+            vec![path]
+        };
+
+        let mut loaded = 0;
+        for file in files {
+            let file = std::fs::canonicalize(&file)?;
+            paths.check_read(&file)?;
+            let tag = match locale {
+                Some(tag) => tag.to_string(),
+                None => file
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .map(str::to_string)
+                    .ok_or_else(|| {
+                        crate::Error::invalid_argument(format!(
+                            "cannot tell the locale of {}",
+                            file.display()
+                        ))
+                    })?,
+            };
+            let catalog = Catalog::from_json(&std::fs::read_to_string(&file)?)
+                .map_err(|e| crate::Error::Format(format!("{}: {}", file.display(), e)))?;
+            loaded += catalog.len();
+            self.add_catalog(&tag, catalog);
+        }
+        Ok(loaded)
+    }
+
+    /// Translate `key` into the current locale, filling `{name}`
+    /// placeholders from `args`.
+    ///
+    /// Falls back from the locale (`de-AT`) to its language (`de`), then to
+    /// English, and finally returns the key itself.
+    pub fn translate(&self, key: &str, args: &HashMap<String, Value>) -> String {
+        let locale = self.locale();
+        let language = locale.split('-').next().unwrap_or_default().to_string();
+        let catalogs = self.catalogs.read();
+        let message = [
+            locale.as_str(),
+            language.as_str(),
+            FALLBACK_LANGUAGE,
+            DEFAULT_LOCALE,
+        ]
+        .iter()
+        .find_map(|tag| catalogs.get(*tag).and_then(|c| c.get(key)));
+        let Some(message) = message else {
+            tracing::debug!("locale: no message for {} in {}", key, locale);
+            return key.to_string();
+        };
+
+        let count = args.get("count").and_then(|v| v.as_float());
+        interpolate(message.select(count), args, self.data())
+    }
+}
+
+/// Replace `{name}` with the argument's value; `{{` and `}}` are literal
+/// braces and unknown placeholders are kept.
+fn interpolate(template: &str, args: &HashMap<String, Value>, locale: &LocaleData) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        if let Some(end) = tail.find('}').filter(|_| tail.starts_with('{')) {
+            let name = tail[1..end].trim();
+            match args.get(name) {
+                Some(Value::Int(n)) => out.push_str(&format_number_in(*n as f64, None, locale)),
+                Some(Value::Float(f)) => out.push_str(&format_number_in(*f, None, locale)),
+                Some(Value::String(s)) => out.push_str(s),
+                Some(value) => out.push_str(&value.to_string()),
+                None => out.push_str(&tail[..=end]),
+            }
+            rest = &tail[end + 1..];
+        } else {
+            out.push_str(&tail[..1]);
+            rest = &tail[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn number_arg(args: &[Value], function: &str) -> Result<f64> {
+    args.first()
+        .and_then(|v| v.as_float())
+        .ok_or_else(|| Error::host_function(format!("{}: missing number", function)))
+}
+
+/// Read the locale argument, a tag or an options map with a `locale` key,
+/// returning the formatting data and the options.
+fn locale_arg<'a>(
+    localizer: &Localizer,
+    arg: Option<&'a Value>,
+    function: &str,
+) -> Result<(&'static LocaleData, Option<&'a HashMap<String, Value>>)> {
+    let (tag, options) = match arg {
+        None | Some(Value::Null) => (None, None),
+        Some(Value::String(tag)) => (Some(tag.as_str()), None),
+        Some(Value::Map(options)) => (
+            options.get("locale").and_then(|v| v.as_str()),
+            Some(options),
+        ),
+        Some(_) => {
+            return Err(Error::host_function(format!(
+                "{}: locale must be a string or an options map",
+                function
+            )))
+        }
+    };
+    let data = match tag {
+        Some(tag) => resolve(tag).ok_or_else(|| {
+            Error::host_function(format!("{}: unsupported locale '{}'", function, tag))
+        })?,
+        None => localizer.data(),
+    };
+    Ok((data, options))
+}
+
+/// Format a number for a locale.
+///
+/// # Arguments
+///
+/// * `args[0]` - Number
+/// * `args[1]` - Locale tag, or options map (optional): `locale`,
+///   `decimals` (fixed number of decimal places)
+///
+/// # Returns
+///
+/// Formatted string, e.g. `"1.234,5"` for `de-DE`
+pub fn format_number(
+    localizer: &Localizer,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    const FUNCTION: &str = "locale.format_number";
+    let value = number_arg(args, FUNCTION)?;
+    let (data, options) = locale_arg(localizer, args.get(1), FUNCTION)?;
+    let decimals = match options.and_then(|o| o.get("decimals")) {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(v.as_int().filter(|d| (0..=20).contains(d)).ok_or_else(|| {
+            Error::host_function(format!(
+                "{}: decimals must be an integer from 0 to 20",
+                FUNCTION
+            ))
+        })? as usize),
+    };
+
+    Ok(Value::String(format_number_in(value, decimals, data)))
+}
+
+/// Format a currency amount for a locale.
+///
+/// # Arguments
+///
+/// * `args[0]` - Amount
+/// * `args[1]` - ISO 4217 currency code, e.g. `"EUR"`
+/// * `args[2]` - Locale tag, or options map with `locale` (optional)
+///
+/// # Returns
+///
+/// Formatted string, e.g. `"$1,234.50"` for `en-US`
+pub fn format_currency(
+    localizer: &Localizer,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    const FUNCTION: &str = "locale.format_currency";
+    let amount = number_arg(args, FUNCTION)?;
+    let currency = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .filter(|c| c.len() == 3 && c.chars().all(|c| c.is_ascii_alphabetic()))
+        .ok_or_else(|| {
+            Error::host_function(format!("{}: expected a 3-letter currency code", FUNCTION))
+        })?;
+    let (data, _) = locale_arg(localizer, args.get(2), FUNCTION)?;
+
+    Ok(Value::String(format_currency_in(amount, currency, data)))
+}
+
+/// Format a Unix timestamp as a date for a locale.
+///
+/// # Arguments
+///
+/// * `args[0]` - Unix timestamp (seconds)
+/// * `args[1]` - Locale tag, or options map (optional): `locale`, `style`
+///   (`"short"`, `"medium"` (default), `"long"` or `"full"`), `time`
+///   (include the time of day, default false), `offset` (minutes east of
+///   UTC, default 0) and, with the `tz` feature, `zone` (IANA name)
+///
+/// # Returns
+///
+/// Formatted string, e.g. `"Jan 5, 2024"` for `en-US`
+pub fn format_date(
+    localizer: &Localizer,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    const FUNCTION: &str = "locale.format_date";
+    let timestamp = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing timestamp", FUNCTION)))?;
+    let (data, options) = locale_arg(localizer, args.get(1), FUNCTION)?;
+    let option = |name: &str| options.and_then(|o| o.get(name));
+
+    let style = match option("style").and_then(|v| v.as_str()) {
+        Some(name) => DateStyle::parse(name).ok_or_else(|| {
+            Error::host_function(format!("{}: unknown style '{}'", FUNCTION, name))
+        })?,
+        None => DateStyle::Medium,
+    };
+    let with_time = option("time").and_then(|v| v.as_bool()).unwrap_or(false);
+    #[allow(unused_mut)]
+    let mut offset = option("offset").and_then(|v| v.as_int()).unwrap_or(0) * 60;
+    #[cfg(feature = "tz")]
+    if let Some(zone) = option("zone").and_then(|v| v.as_str()) {
+        use chrono::{Offset, TimeZone};
+
+        let zone = crate::time::parse_zone(zone, FUNCTION)?;
+        let utc = chrono::DateTime::from_timestamp(timestamp, 0)
+            .ok_or_else(|| Error::host_function(format!("{}: timestamp out of range", FUNCTION)))?;
+        offset = i64::from(
+            zone.offset_from_utc_datetime(&utc.naive_utc())
+                .fix()
+                .local_minus_utc(),
+        );
+    }
+
+    Ok(Value::String(format_date_in(
+        timestamp, offset, style, with_time, data,
+    )))
+}
+
+/// Translate a message key into the current locale.
+///
+/// # Arguments
+///
+/// * `args[0]` - Message key, e.g. `"files.deleted"`
+/// * `args[1]` - Placeholder values (map, optional); `count` also selects
+///   the plural form
+///
+/// # Returns
+///
+/// The translated text, or the key if no catalog has it
+pub fn t(localizer: &Localizer, args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let key = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function("locale.t: missing key"))?;
+    let empty = HashMap::new();
+    let values = match args.get(1) {
+        None | Some(Value::Null) => &empty,
+        Some(Value::Map(values)) => values,
+        Some(_) => return Err(Error::host_function("locale.t: args must be a map")),
+    };
+
+    Ok(Value::String(localizer.translate(key, values)))
+}
+
+/// Load message catalogs from an allowlisted JSON file or directory.
+///
+/// # Arguments
+///
+/// * `args[0]` - Path of a catalog file, or of a directory of `*.json`
+///   catalogs named after their locales
+/// * `args[1]` - Locale of a single file (optional, default from its name)
+///
+/// # Returns
+///
+/// Number of messages loaded
+pub fn load(
+    localizer: &Localizer,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let path = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function("locale.load: missing path"))?;
+    let locale = args.get(1).and_then(|v| v.as_str());
+
+    let loaded = localizer
+        .load(Path::new(path), locale, &safety.paths)
+        .map_err(|e| Error::host_function(format!("locale.load: {}", e)))?;
+    Ok(Value::Int(loaded as i64))
+}
+
+/// Get the current locale.
+///
+/// # Returns
+///
+/// Locale tag, e.g. `"en-US"`
+pub fn current(localizer: &Localizer, _args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    Ok(Value::String(localizer.locale()))
+}
+
+/// Set the current locale.
+///
+/// # Arguments
+///
+/// * `args[0]` - Locale tag, e.g. `"de-DE"` or `"fr_FR.UTF-8"`
+///
+/// # Returns
+///
+/// The normalized tag
+pub fn set(localizer: &Localizer, args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let tag = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function("locale.set: missing locale"))?;
+    localizer.set_locale(tag);
+    Ok(Value::String(localizer.locale()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locale(tag: &str) -> &'static LocaleData {
+        resolve(tag).unwrap()
+    }
+
+    #[test]
+    fn test_format_number_and_currency() {
+        assert_eq!(
+            format_number_in(1234567.891, None, locale("en-US")),
+            "1,234,567.891"
+        );
+        assert_eq!(
+            format_number_in(1234567.891, None, locale("de_DE.UTF-8")),
+            "1.234.567,891"
+        );
+        assert_eq!(
+            format_number_in(-1234.5, Some(2), locale("fr")),
+            "-1\u{202f}234,50"
+        );
+        assert_eq!(format_number_in(1234.0, None, locale("es-ES")), "1234");
+        assert_eq!(format_number_in(12345.0, None, locale("es-ES")), "12.345");
+        assert_eq!(format_number_in(-0.0001, None, locale("en-US")), "0");
+
+        assert_eq!(
+            format_currency_in(1234.5, "usd", locale("en-US")),
+            "$1,234.50"
+        );
+        assert_eq!(
+            format_currency_in(-1234.5, "EUR", locale("de-DE")),
+            "-1.234,50\u{a0}€"
+        );
+        assert_eq!(format_currency_in(1234.0, "JPY", locale("ja-JP")), "¥1,234");
+        assert_eq!(
+            format_currency_in(10.0, "BRL", locale("pt-BR")),
+            "R$\u{a0}10,00"
+        );
+        assert_eq!(
+            format_currency_in(10.0, "SEK", locale("en-GB")),
+            "SEK\u{a0}10.00"
+        );
+    }
+
+    #[test]
+    fn test_format_date() {
+        // 2024-01-05 14:30 UTC, a Friday.
+        let t = 1_704_465_000;
+        let en = locale("en-US");
+        assert_eq!(format_date_in(t, 0, DateStyle::Short, false, en), "1/5/24");
+        assert_eq!(
+            format_date_in(t, 0, DateStyle::Medium, true, en),
+            "Jan 5, 2024 2:30 PM"
+        );
+        assert_eq!(
+            format_date_in(t, 0, DateStyle::Full, false, en),
+            "Friday, January 5, 2024"
+        );
+        assert_eq!(
+            format_date_in(t, 0, DateStyle::Long, false, locale("es")),
+            "5 de enero de 2024"
+        );
+        assert_eq!(
+            format_date_in(t, 0, DateStyle::Full, false, locale("de-AT")),
+            "Freitag, 5. Januar 2024"
+        );
+        assert_eq!(
+            format_date_in(t, 10 * 3600, DateStyle::Long, true, locale("ja")),
+            "2024年1月6日 0:30"
+        );
+        assert!(resolve("sw-KE").is_none());
+    }
+
+    #[test]
+    fn test_translate() {
+        let localizer = Localizer::new("de_AT.UTF-8");
+        assert_eq!(localizer.locale(), "de-AT");
+        localizer.add_catalog(
+            "de",
+            Catalog::from_json(
+                r#"{"files": {"deleted": {"one": "Eine Datei gelöscht", "other": "{count} Dateien gelöscht"}},
+                    "greeting": "Hallo, {name}! {{literal}}"}"#,
+            )
+            .unwrap(),
+        );
+        localizer.add_catalog(
+            "en",
+            Catalog::from_json(r#"{"only_english": "Only in English", "greeting": "Hello"}"#)
+                .unwrap(),
+        );
+
+        let args = |pairs: &[(&str, Value)]| -> HashMap<String, Value> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect()
+        };
+        assert_eq!(
+            localizer.translate("files.deleted", &args(&[("count", Value::Int(1))])),
+            "Eine Datei gelöscht"
+        );
+        assert_eq!(
+            localizer.translate("files.deleted", &args(&[("count", Value::Int(1500))])),
+            "1.500 Dateien gelöscht"
+        );
+        assert_eq!(
+            localizer.translate("greeting", &args(&[("name", Value::String("Ada".into()))])),
+            "Hallo, Ada! {literal}"
+        );
+        assert_eq!(
+            localizer.translate("greeting", &HashMap::new()),
+            "Hallo, {name}! {literal}"
+        );
+        assert_eq!(
+            localizer.translate("only_english", &HashMap::new()),
+            "Only in English"
+        );
+        assert_eq!(
+            localizer.translate("missing.key", &HashMap::new()),
+            "missing.key"
+        );
+
+        assert!(Catalog::from_json("[]").is_err());
+        assert!(Catalog::from_json(r#"{"n": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_load_respects_allowlist() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("fr.json"), r#"{"hello": "Bonjour"}"#).unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+        let localizer = Localizer::new("fr-FR");
+
+        let denied = PathAllowlist::none();
+        assert!(localizer.load(dir.path(), None, &denied).is_err());
+
+        let allowed = PathAllowlist::none().allow_read(dir.path().canonicalize().unwrap());
+        assert_eq!(localizer.load(dir.path(), None, &allowed).unwrap(), 1);
+        assert_eq!(localizer.catalog_locales(), vec!["fr"]);
+        assert_eq!(localizer.translate("hello", &HashMap::new()), "Bonjour");
+    }
+}
@@ -1,6 +1,13 @@
 //! MCP (Model Context Protocol) helpers for Fusabi.
 //!
-//! Provides utilities for building MCP servers and clients.
+//! Provides utilities for building MCP servers and clients, including a
+//! hardened stdio transport ([`stdio`]) and resources served from files and
+//! URLs ([`resources`]).
+
+pub mod prompts;
+pub mod resources;
+pub mod stdio;
+pub mod tools;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -0,0 +1,443 @@
+//! File-based MCP prompt packs.
+//!
+//! A prompt pack is a directory of `.prompt` (or `.md`) files, each holding a
+//! front-matter header followed by a template body, in the style of dotprompt:
+//!
+//! ```text
+//! ---
+//! name: summarize
+//! description: Summarize a document for a given audience
+//! arguments:
+//!   - name: document
+//!     description: Text to summarize
+//!     required: true
+//!   - name: audience
+//! ---
+//! Summarize the following for {{ audience }}:
+//!
+//! {{ document }}
+//! ```
+//!
+//! `name` defaults to the file stem. Arguments may also be listed as bare
+//! names (`- audience`), which are required. Unknown header keys such as
+//! `model` are ignored, so existing dotprompt files load unchanged.
+//!
+//! ```rust,ignore
+//! use fusabi_stdlib_ext::mcp::prompts::PromptPack;
+//!
+//! let pack = PromptPack::load_dir("prompts", &safety.paths)?;
+//! let definitions = pack.definitions(); // for prompts/list
+//! let result = pack.get(&params)?;      // for prompts/get
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::tools::ToolContent;
+use super::{GetPromptParams, PromptArgument, PromptDefinition};
+use crate::error::{Error, Result};
+use crate::safety::PathAllowlist;
+
+/// File extensions recognised as prompt templates.
+const PROMPT_EXTENSIONS: &[&str] = &["prompt", "md"];
+
+/// Message returned by `prompts/get`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptMessage {
+    /// Message role, `"user"` or `"assistant"`.
+    pub role: String,
+    /// Message content.
+    pub content: ToolContent,
+}
+
+/// Result of a `prompts/get` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetPromptResult {
+    /// Prompt description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Rendered messages.
+    pub messages: Vec<PromptMessage>,
+}
+
+/// A prompt template loaded from a file.
+#[derive(Debug, Clone)]
+pub struct PromptTemplate {
+    definition: PromptDefinition,
+    role: String,
+    body: String,
+    path: PathBuf,
+}
+
+impl PromptTemplate {
+    /// Parse a template from its source text.
+    ///
+    /// `default_name` is used when the header has no `name` key.
+    pub fn parse(source: &str, default_name: &str) -> Result<Self> {
+        let (header, body) = split_front_matter(source);
+        let mut template = Self {
+            definition: PromptDefinition {
+                name: default_name.to_string(),
+                description: None,
+                arguments: Vec::new(),
+            },
+            role: "user".to_string(),
+            body: body.to_string(),
+            path: PathBuf::new(),
+        };
+
+        if let Some(header) = header {
+            template.apply_header(header)?;
+        }
+
+        Ok(template)
+    }
+
+    /// Load a template from a file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)?;
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        let mut template = Self::parse(&source, stem)
+            .map_err(|e| Error::invalid_argument(format!("{}: {}", path.display(), e)))?;
+        template.path = path.to_path_buf();
+        Ok(template)
+    }
+
+    /// Get the MCP prompt definition.
+    pub fn definition(&self) -> &PromptDefinition {
+        &self.definition
+    }
+
+    /// Get the template body.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Get the file the template was loaded from (empty if parsed from text).
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Render the body, substituting `{{ name }}` placeholders.
+    ///
+    /// Missing required arguments are an error; missing optional arguments
+    /// render as empty strings.
+    pub fn render(&self, arguments: &HashMap<String, String>) -> Result<String> {
+        for arg in &self.definition.arguments {
+            if arg.required && !arguments.contains_key(&arg.name) {
+                return Err(Error::invalid_argument(format!(
+                    "prompt '{}': missing required argument '{}'",
+                    self.definition.name, arg.name
+                )));
+            }
+        }
+
+        let mut out = String::with_capacity(self.body.len());
+        let mut rest = self.body.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let key = rest[start + 2..start + 2 + len].trim();
+            if let Some(value) = arguments.get(key) {
+                out.push_str(value);
+            }
+            rest = &rest[start + 2 + len + 2..];
+        }
+        out.push_str(rest);
+
+        Ok(out)
+    }
+
+    fn apply_header(&mut self, header: &str) -> Result<()> {
+        let mut in_arguments = false;
+
+        for (index, line) in header.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let indented = line.starts_with([' ', '\t']);
+            if !indented {
+                in_arguments = false;
+                let (key, value) = split_key_value(trimmed).ok_or_else(|| {
+                    Error::invalid_argument(format!(
+                        "front matter line {}: expected key: value",
+                        index + 1
+                    ))
+                })?;
+                match key {
+                    "name" => self.definition.name = value.to_string(),
+                    "description" => self.definition.description = Some(value.to_string()),
+                    "role" => self.role = value.to_string(),
+                    "arguments" => in_arguments = true,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if !in_arguments {
+                continue;
+            }
+
+            if let Some(item) = trimmed.strip_prefix('-') {
+                let item = item.trim();
+                let mut argument = PromptArgument {
+                    name: String::new(),
+                    description: None,
+                    required: false,
+                };
+                match split_key_value(item) {
+                    Some((key, value)) => apply_argument_field(&mut argument, key, value),
+                    None => {
+                        argument.name = unquote(item).to_string();
+                        argument.required = true;
+                    }
+                }
+                self.definition.arguments.push(argument);
+            } else if let (Some(argument), Some((key, value))) = (
+                self.definition.arguments.last_mut(),
+                split_key_value(trimmed),
+            ) {
+                apply_argument_field(argument, key, value);
+            }
+        }
+
+        if let Some(arg) = self.definition.arguments.iter().find(|a| a.name.is_empty()) {
+            return Err(Error::invalid_argument(format!(
+                "prompt '{}': argument without a name{}",
+                self.definition.name,
+                arg.description
+                    .as_deref()
+                    .map(|d| format!(" ({})", d))
+                    .unwrap_or_default()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// A collection of prompt templates, keyed by name.
+#[derive(Debug, Clone, Default)]
+pub struct PromptPack {
+    prompts: BTreeMap<String, PromptTemplate>,
+}
+
+impl PromptPack {
+    /// Create an empty prompt pack.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every prompt file in a directory.
+    ///
+    /// The directory and each file must be readable under `paths`; symlinks
+    /// are resolved before checking so they cannot escape the allowlist.
+    /// Duplicate prompt names are an error.
+    pub fn load_dir(dir: impl AsRef<Path>, paths: &PathAllowlist) -> Result<Self> {
+        let dir = std::fs::canonicalize(dir.as_ref())?;
+        paths.check_read(&dir)?;
+
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| PROMPT_EXTENSIONS.contains(&e))
+            })
+            .collect();
+        entries.sort();
+
+        let mut pack = Self::new();
+        for path in entries {
+            let path = std::fs::canonicalize(&path)?;
+            paths.check_read(&path)?;
+            if !path.is_file() {
+                continue;
+            }
+            pack.insert(PromptTemplate::load(&path)?)?;
+        }
+
+        tracing::debug!(
+            "mcp: loaded {} prompt(s) from {}",
+            pack.len(),
+            dir.display()
+        );
+        Ok(pack)
+    }
+
+    /// Add a template, rejecting duplicate names.
+    pub fn insert(&mut self, template: PromptTemplate) -> Result<()> {
+        let name = template.definition.name.clone();
+        if self.prompts.contains_key(&name) {
+            return Err(Error::invalid_argument(format!(
+                "duplicate prompt name '{}'",
+                name
+            )));
+        }
+        self.prompts.insert(name, template);
+        Ok(())
+    }
+
+    /// Get a template by name.
+    pub fn template(&self, name: &str) -> Option<&PromptTemplate> {
+        self.prompts.get(name)
+    }
+
+    /// Number of prompts in the pack.
+    pub fn len(&self) -> usize {
+        self.prompts.len()
+    }
+
+    /// Whether the pack is empty.
+    pub fn is_empty(&self) -> bool {
+        self.prompts.is_empty()
+    }
+
+    /// Prompt definitions, sorted by name, for `prompts/list`.
+    pub fn definitions(&self) -> Vec<PromptDefinition> {
+        self.prompts
+            .values()
+            .map(|t| t.definition.clone())
+            .collect()
+    }
+
+    /// Render a prompt for `prompts/get`.
+    pub fn get(&self, params: &GetPromptParams) -> Result<GetPromptResult> {
+        let template = self
+            .template(&params.name)
+            .ok_or_else(|| Error::invalid_argument(format!("unknown prompt '{}'", params.name)))?;
+
+        let text = template.render(&params.arguments)?;
+        Ok(GetPromptResult {
+            description: template.definition.description.clone(),
+            messages: vec![PromptMessage {
+                role: template.role.clone(),
+                content: ToolContent::Text { text },
+            }],
+        })
+    }
+}
+
+/// Split `---`-delimited front matter from the body.
+fn split_front_matter(source: &str) -> (Option<&str>, &str) {
+    let Some(rest) = source
+        .strip_prefix("---\n")
+        .or_else(|| source.strip_prefix("---\r\n"))
+    else {
+        return (None, source);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let body = &rest[offset + line.len()..];
+            return (Some(&rest[..offset]), body);
+        }
+        offset += line.len();
+    }
+
+    // Unterminated header: treat the whole file as the body.
+    (None, source)
+}
+
+fn split_key_value(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || key.contains(' ') {
+        return None;
+    }
+    Some((key, unquote(value.trim())))
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+fn apply_argument_field(argument: &mut PromptArgument, key: &str, value: &str) {
+    match key {
+        "name" => argument.name = value.to_string(),
+        "description" => argument.description = Some(value.to_string()),
+        "required" => argument.required = matches!(value, "true" | "yes"),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUMMARIZE: &str = "---\nname: summarize\ndescription: \"Summarize a document\"\nmodel: any\narguments:\n  - name: document\n    description: Text to summarize\n    required: true\n  - name: audience\n---\nSummarize for {{ audience }}:\n\n{{document}}\n";
+
+    #[test]
+    fn test_parse_and_render() {
+        let template = PromptTemplate::parse(SUMMARIZE, "fallback").unwrap();
+        let def = template.definition();
+        assert_eq!(def.name, "summarize");
+        assert_eq!(def.description.as_deref(), Some("Summarize a document"));
+        assert_eq!(def.arguments.len(), 2);
+        assert!(def.arguments[0].required);
+        assert!(!def.arguments[1].required);
+
+        let args = HashMap::from([("document".to_string(), "text".to_string())]);
+        assert_eq!(template.render(&args).unwrap(), "Summarize for :\n\ntext\n");
+        assert!(template.render(&HashMap::new()).is_err());
+
+        let bare =
+            PromptTemplate::parse("---\narguments:\n  - topic\n---\nAbout {{topic}}", "about")
+                .unwrap();
+        assert_eq!(bare.definition().name, "about");
+        assert!(bare.definition().arguments[0].required);
+
+        let plain = PromptTemplate::parse("No header {{ here", "plain").unwrap();
+        assert_eq!(plain.render(&HashMap::new()).unwrap(), "No header {{ here");
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("summarize.prompt"), SUMMARIZE).unwrap();
+        std::fs::write(dir.path().join("greet.md"), "Hello {{ name }}").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let denied = PromptPack::load_dir(dir.path(), &PathAllowlist::none());
+        assert!(matches!(denied, Err(Error::PathNotAllowed(_))));
+
+        let paths = PathAllowlist::none().allow_read(std::fs::canonicalize(dir.path()).unwrap());
+        let pack = PromptPack::load_dir(dir.path(), &paths).unwrap();
+        let names: Vec<_> = pack.definitions().into_iter().map(|d| d.name).collect();
+        assert_eq!(names, vec!["greet", "summarize"]);
+
+        let params = GetPromptParams {
+            name: "greet".to_string(),
+            arguments: HashMap::from([("name".to_string(), "Ada".to_string())]),
+        };
+        let result = pack.get(&params).unwrap();
+        assert_eq!(result.messages[0].role, "user");
+        assert_eq!(
+            result.messages[0].content,
+            ToolContent::Text {
+                text: "Hello Ada".to_string()
+            }
+        );
+
+        std::fs::write(dir.path().join("dup.prompt"), "---\nname: greet\n---\nHi").unwrap();
+        assert!(PromptPack::load_dir(dir.path(), &paths).is_err());
+    }
+}
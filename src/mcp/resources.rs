@@ -0,0 +1,778 @@
+//! Declarative MCP resources backed by files.
+//!
+//! A [`ResourceSet`] serves `resources/list` and `resources/read` from
+//! [`ResourceSpec`]s naming a file or a directory (every file under it
+//! becomes a resource), so exposing a docs directory takes configuration
+//! rather than handlers:
+//!
+//! ```json
+//! {
+//!   "resources": [
+//!     { "dir": "/srv/docs", "uri": "docs://" },
+//!     { "path": "/srv/CHANGELOG.md", "description": "Release notes" }
+//!   ],
+//!   "max_size": 1048576,
+//!   "cache_ttl_ms": 60000
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! use fusabi_stdlib_ext::mcp::resources::{ResourceSet, ResourcesConfig};
+//!
+//! let resources = ResourceSet::from_config(&ResourcesConfig::from_json(&text)?, safety)?;
+//! let definitions = resources.definitions(); // for resources/list
+//! let result = resources.read(&params)?;     // for resources/read
+//! ```
+//!
+//! Files must be readable under the [`SafetyConfig`] path allowlist, with
+//! symlinks resolved before checking. URL specs are refused with
+//! [`Error::ModuleNotAvailable`] until `net` has a real HTTP client, so
+//! clients are never offered resources that cannot be read. MIME types come
+//! from the spec or the file extension, falling back to sniffing the content; text is returned as `text` and anything else
+//! base64-encoded as `blob`. Contents larger than the size cap are refused,
+//! and read contents are cached for the TTL, or until a file changes.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::{fusabi_to_json, ReadResourceParams, ResourceDefinition};
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+use fusabi_host::Value;
+
+/// Default largest resource, in bytes.
+pub const DEFAULT_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Default time read contents are cached.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// MIME types by file extension.
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("md", "text/markdown"),
+    ("markdown", "text/markdown"),
+    ("txt", "text/plain"),
+    ("log", "text/plain"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("js", "text/javascript"),
+    ("rs", "text/x-rust"),
+    ("py", "text/x-python"),
+    ("fsx", "text/x-fsharp"),
+    ("json", "application/json"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("toml", "application/toml"),
+    ("xml", "application/xml"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+];
+
+/// A resource, or directory of resources, to serve.
+///
+/// Exactly one of `path`, `dir` and `url` must be set. URLs are not served
+/// yet: [`ResourceSet::with_resource`] refuses them. For a directory,
+/// `uri` is the prefix its files' relative paths are appended to (default
+/// `file://` and the directory's path).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSpec {
+    /// File to serve.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Directory whose files to serve.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// URL to serve (not supported yet).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Resource URI, or URI prefix for a directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    /// Resource name (default: the file name).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Resource description.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// MIME type, overriding detection.
+    #[serde(default, alias = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+impl ResourceSpec {
+    /// Serve a file.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Serve every file under a directory.
+    pub fn dir(path: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: Some(path.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Serve a URL (not supported yet).
+    pub fn url(url: impl Into<String>) -> Self {
+        Self {
+            url: Some(url.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Set the URI, or URI prefix for a directory.
+    pub fn with_uri(mut self, uri: impl Into<String>) -> Self {
+        self.uri = Some(uri.into());
+        self
+    }
+
+    /// Set the name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the MIME type.
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+}
+
+/// Resources and limits for [`ResourceSet::from_config`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourcesConfig {
+    /// Resources to serve.
+    #[serde(default)]
+    pub resources: Vec<ResourceSpec>,
+    /// Largest resource in bytes (default [`DEFAULT_MAX_SIZE`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<u64>,
+    /// Cache lifetime in milliseconds, 0 to disable (default
+    /// [`DEFAULT_CACHE_TTL`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_ttl_ms: Option<u64>,
+}
+
+impl ResourcesConfig {
+    /// Parse a JSON configuration.
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::invalid_argument(format!("resources config: {}", e)))
+    }
+
+    /// Convert a configuration map from a script.
+    pub fn from_value(value: &Value) -> Result<Self> {
+        serde_json::from_value(fusabi_to_json(value))
+            .map_err(|e| Error::invalid_argument(format!("resources config: {}", e)))
+    }
+}
+
+/// Contents of one resource in a `resources/read` result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceContents {
+    /// Resource URI.
+    pub uri: String,
+    /// MIME type.
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    /// Text content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Base64-encoded binary content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// Result of a `resources/read` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReadResourceResult {
+    /// Resource contents.
+    pub contents: Vec<ResourceContents>,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    File(PathBuf),
+    Directory(PathBuf),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    source: Source,
+    /// The URI, or the URI prefix of a directory.
+    uri: String,
+    name: Option<String>,
+    description: Option<String>,
+    mime_type: Option<String>,
+}
+
+struct Cached {
+    fetched: Instant,
+    modified: SystemTime,
+    contents: ResourceContents,
+}
+
+/// MCP resources served from files and directories.
+pub struct ResourceSet {
+    safety: Arc<SafetyConfig>,
+    entries: Vec<Entry>,
+    max_size: u64,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<String, Cached>>,
+}
+
+impl ResourceSet {
+    /// Create an empty set whose files are checked against `safety`.
+    pub fn new(safety: Arc<SafetyConfig>) -> Self {
+        Self {
+            safety,
+            entries: Vec::new(),
+            max_size: DEFAULT_MAX_SIZE,
+            cache_ttl: DEFAULT_CACHE_TTL,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Create a set from a configuration.
+    pub fn from_config(config: &ResourcesConfig, safety: Arc<SafetyConfig>) -> Result<Self> {
+        let mut set = Self::new(safety);
+        if let Some(max_size) = config.max_size {
+            set = set.with_max_size(max_size);
+        }
+        if let Some(ttl) = config.cache_ttl_ms {
+            set = set.with_cache_ttl(Duration::from_millis(ttl));
+        }
+        for spec in &config.resources {
+            set = set.with_resource(spec.clone())?;
+        }
+        Ok(set)
+    }
+
+    /// Add a resource.
+    ///
+    /// Files and directories must exist and be readable under the path
+    /// allowlist. URLs fail with [`Error::ModuleNotAvailable`] until `net`
+    /// has a real HTTP client.
+    pub fn with_resource(mut self, spec: ResourceSpec) -> Result<Self> {
+        let (source, uri) = match (spec.path, spec.dir, spec.url) {
+            (Some(path), None, None) => {
+                let path = std::fs::canonicalize(&path)
+                    .map_err(|e| Error::filesystem(format!("{}: {}", path.display(), e)))?;
+                self.safety.paths.check_read(&path)?;
+                if !path.is_file() {
+                    return Err(Error::invalid_argument(format!(
+                        "resource path is not a file: {}",
+                        path.display()
+                    )));
+                }
+                let uri = spec.uri.unwrap_or_else(|| file_uri(&path));
+                (Source::File(path), uri)
+            }
+            (None, Some(dir), None) => {
+                let dir = std::fs::canonicalize(&dir)
+                    .map_err(|e| Error::filesystem(format!("{}: {}", dir.display(), e)))?;
+                self.safety.paths.check_read(&dir)?;
+                if !dir.is_dir() {
+                    return Err(Error::invalid_argument(format!(
+                        "resource dir is not a directory: {}",
+                        dir.display()
+                    )));
+                }
+                let uri = spec
+                    .uri
+                    .unwrap_or_else(|| format!("{}/", file_uri(&dir).trim_end_matches('/')));
+                (Source::Directory(dir), uri)
+            }
+            (None, None, Some(url)) => {
+                // `net` requests are still simulated, so a URL resource
+                // could be listed but never read.
+                return Err(Error::ModuleNotAvailable(format!(
+                    "HTTP client (required for URL resource {})",
+                    url
+                )));
+            }
+            _ => {
+                return Err(Error::invalid_argument(
+                    "resource needs exactly one of path, dir and url",
+                ))
+            }
+        };
+
+        self.entries.push(Entry {
+            source,
+            uri,
+            name: spec.name,
+            description: spec.description,
+            mime_type: spec.mime_type,
+        });
+        Ok(self)
+    }
+
+    /// Refuse resources larger than `bytes`.
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Cache read contents for `ttl`; zero disables caching.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Drop all cached contents.
+    pub fn clear_cache(&self) {
+        self.cache.lock().clear();
+    }
+
+    /// Definitions for a `resources/list` response.
+    ///
+    /// Directories are listed afresh on every call, skipping hidden files
+    /// and anything the path allowlist does not allow.
+    pub fn definitions(&self) -> Vec<ResourceDefinition> {
+        let mut definitions = Vec::new();
+        for entry in &self.entries {
+            match &entry.source {
+                Source::File(path) => definitions.push(ResourceDefinition {
+                    uri: entry.uri.clone(),
+                    name: entry.name.clone().unwrap_or_else(|| file_name(path)),
+                    description: entry.description.clone(),
+                    mime_type: entry.mime_type.clone().or_else(|| guess_mime(&entry.uri)),
+                }),
+                Source::Directory(dir) => {
+                    for relative in self.walk(dir) {
+                        let uri = format!("{}{}", entry.uri, relative);
+                        definitions.push(ResourceDefinition {
+                            name: relative,
+                            description: entry.description.clone(),
+                            mime_type: entry.mime_type.clone().or_else(|| guess_mime(&uri)),
+                            uri,
+                        });
+                    }
+                }
+            }
+        }
+        definitions
+    }
+
+    /// Handle a `resources/read` request.
+    pub fn read(&self, params: &ReadResourceParams) -> Result<ReadResourceResult> {
+        let uri = params.uri.as_str();
+        let (entry, path) = self.resolve(uri)?;
+
+        let modified = std::fs::metadata(&path)?.modified()?;
+        if let Some(cached) = self.cache.lock().get(uri) {
+            if cached.fetched.elapsed() < self.cache_ttl && cached.modified == modified {
+                return Ok(ReadResourceResult {
+                    contents: vec![cached.contents.clone()],
+                });
+            }
+        }
+
+        let bytes = self.read_file(&path)?;
+        let mime_type = entry
+            .mime_type
+            .clone()
+            .or_else(|| guess_mime(uri))
+            .unwrap_or_else(|| sniff_mime(&bytes).to_string());
+        let contents = resource_contents(uri, mime_type, bytes);
+
+        if !self.cache_ttl.is_zero() {
+            self.cache.lock().insert(
+                uri.to_string(),
+                Cached {
+                    fetched: Instant::now(),
+                    modified,
+                    contents: contents.clone(),
+                },
+            );
+        }
+        Ok(ReadResourceResult {
+            contents: vec![contents],
+        })
+    }
+
+    /// Find the entry serving `uri`, and the file to read.
+    fn resolve(&self, uri: &str) -> Result<(&Entry, PathBuf)> {
+        for entry in &self.entries {
+            match &entry.source {
+                Source::File(path) if entry.uri == uri => return Ok((entry, path.clone())),
+                _ => {}
+            }
+        }
+
+        for entry in &self.entries {
+            let Source::Directory(dir) = &entry.source else {
+                continue;
+            };
+            let Some(relative) = uri.strip_prefix(entry.uri.as_str()) else {
+                continue;
+            };
+            return Ok((entry, self.directory_file(dir, relative)?));
+        }
+
+        Err(Error::invalid_argument(format!(
+            "unknown resource '{}'",
+            uri
+        )))
+    }
+
+    /// Resolve a path relative to a served directory, refusing anything
+    /// outside it, hidden or not allowed.
+    fn directory_file(&self, dir: &Path, relative: &str) -> Result<PathBuf> {
+        let relative = Path::new(relative);
+        let valid = relative.components().count() > 0
+            && relative.components().all(|c| match c {
+                Component::Normal(part) => !part.to_string_lossy().starts_with('.'),
+                _ => false,
+            });
+        if !valid {
+            return Err(Error::invalid_argument(format!(
+                "invalid resource path '{}'",
+                relative.display()
+            )));
+        }
+
+        let path = std::fs::canonicalize(dir.join(relative))?;
+        if !path.starts_with(dir) {
+            return Err(Error::path_not_allowed(path.display().to_string()));
+        }
+        self.safety.paths.check_read(&path)?;
+        if !path.is_file() {
+            return Err(Error::invalid_argument(format!(
+                "resource is not a file: {}",
+                path.display()
+            )));
+        }
+        Ok(path)
+    }
+
+    /// Relative paths, with `/` separators, of the files under `dir`.
+    fn walk(&self, dir: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            let Ok(entries) = std::fs::read_dir(&current) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let Ok(path) = std::fs::canonicalize(entry.path()) else {
+                    continue;
+                };
+                if !path.starts_with(dir) || self.safety.paths.check_read(&path).is_err() {
+                    continue;
+                }
+                if path.is_dir() {
+                    pending.push(path);
+                } else if path.is_file() {
+                    let Ok(relative) = entry.path().strip_prefix(dir).map(Path::to_path_buf) else {
+                        continue;
+                    };
+                    let parts: Vec<_> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                        .collect();
+                    files.push(parts.join("/"));
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    fn read_file(&self, path: &Path) -> Result<Vec<u8>> {
+        let size = std::fs::metadata(path)?.len();
+        self.check_size(path.display(), size)?;
+        Ok(std::fs::read(path)?)
+    }
+
+    fn check_size(&self, what: impl std::fmt::Display, size: u64) -> Result<()> {
+        if size > self.max_size {
+            return Err(Error::invalid_argument(format!(
+                "{} is {} bytes, over the {} byte resource limit",
+                what, size, self.max_size
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn file_uri(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// MIME type for the extension of a path or URI.
+pub fn guess_mime(path: &str) -> Option<String> {
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let (_, extension) = name.rsplit_once('.')?;
+    let extension = extension.to_ascii_lowercase();
+    MIME_TYPES
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| mime.to_string())
+}
+
+/// MIME type for content of unknown type: `text/plain` for UTF-8 text,
+/// otherwise `application/octet-stream`.
+pub fn sniff_mime(bytes: &[u8]) -> &'static str {
+    match std::str::from_utf8(bytes) {
+        Ok(text) if !text.contains('\0') => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+fn is_text_mime(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime,
+            "application/json"
+                | "application/yaml"
+                | "application/toml"
+                | "application/xml"
+                | "application/javascript"
+        )
+}
+
+fn resource_contents(uri: &str, mime_type: String, bytes: Vec<u8>) -> ResourceContents {
+    let (text, blob) = if is_text_mime(&mime_type) {
+        match String::from_utf8(bytes) {
+            Ok(text) => (Some(text), None),
+            Err(e) => (None, Some(base64(e.as_bytes()))),
+        }
+    } else {
+        (None, Some(base64(&bytes)))
+    };
+    ResourceContents {
+        uri: uri.to_string(),
+        mime_type,
+        text,
+        blob,
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::{HostAllowlist, PathAllowlist};
+
+    fn docs() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("guide.md"), "# Guide\n").unwrap();
+        std::fs::write(dir.path().join(".secret"), "token").unwrap();
+        std::fs::write(dir.path().join("logo.png"), [0x89, b'P', b'N', b'G', 0]).unwrap();
+        std::fs::create_dir(dir.path().join("api")).unwrap();
+        std::fs::write(dir.path().join("api/index.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("api/notes"), "plain").unwrap();
+        dir
+    }
+
+    fn safety(dir: &Path) -> Arc<SafetyConfig> {
+        let root = std::fs::canonicalize(dir).unwrap();
+        Arc::new(SafetyConfig::new().with_paths(PathAllowlist::none().allow_read(root)))
+    }
+
+    fn read(set: &ResourceSet, uri: &str) -> Result<ResourceContents> {
+        let params = ReadResourceParams {
+            uri: uri.to_string(),
+        };
+        set.read(&params)
+            .map(|mut result| result.contents.remove(0))
+    }
+
+    #[test]
+    fn test_mime_detection() {
+        assert_eq!(
+            guess_mime("file:///docs/README.MD").unwrap(),
+            "text/markdown"
+        );
+        assert_eq!(
+            guess_mime("https://example.com/a.json?v=1").unwrap(),
+            "application/json"
+        );
+        assert_eq!(guess_mime("docs://notes"), None);
+        assert_eq!(sniff_mime(b"hello"), "text/plain");
+        assert_eq!(sniff_mime(&[0xff, 0x00]), "application/octet-stream");
+        assert!(is_text_mime("application/ld+json"));
+        assert!(!is_text_mime("image/png"));
+    }
+
+    #[test]
+    fn test_directory_resources() {
+        let dir = docs();
+        let set = ResourceSet::new(safety(dir.path()))
+            .with_resource(ResourceSpec::dir(dir.path()).with_uri("docs://"))
+            .unwrap();
+
+        let definitions = set.definitions();
+        let uris: Vec<_> = definitions.iter().map(|d| d.uri.as_str()).collect();
+        assert_eq!(
+            uris,
+            [
+                "docs://api/index.json",
+                "docs://api/notes",
+                "docs://guide.md",
+                "docs://logo.png"
+            ]
+        );
+        assert_eq!(
+            definitions[0].mime_type.as_deref(),
+            Some("application/json")
+        );
+        assert_eq!(definitions[1].mime_type, None);
+
+        let guide = read(&set, "docs://guide.md").unwrap();
+        assert_eq!(guide.mime_type, "text/markdown");
+        assert_eq!(guide.text.as_deref(), Some("# Guide\n"));
+
+        let notes = read(&set, "docs://api/notes").unwrap();
+        assert_eq!(notes.mime_type, "text/plain");
+
+        let logo = read(&set, "docs://logo.png").unwrap();
+        assert_eq!(logo.mime_type, "image/png");
+        assert_eq!(logo.text, None);
+        assert_eq!(logo.blob.as_deref(), Some("iVBORwA="));
+
+        assert!(read(&set, "docs://.secret").is_err());
+        assert!(read(&set, "docs://../guide.md").is_err());
+        assert!(read(&set, "docs://missing.md").is_err());
+        assert!(read(&set, "other://guide.md").is_err());
+    }
+
+    #[test]
+    fn test_allowlist_and_size_cap() {
+        let dir = docs();
+        let denied = ResourceSet::new(Arc::new(
+            SafetyConfig::new().with_paths(PathAllowlist::none()),
+        ))
+        .with_resource(ResourceSpec::file(dir.path().join("guide.md")));
+        assert!(matches!(denied, Err(Error::PathNotAllowed(_))));
+
+        let set = ResourceSet::new(safety(dir.path()))
+            .with_max_size(4)
+            .with_resource(
+                ResourceSpec::file(dir.path().join("guide.md")).with_description("The guide"),
+            )
+            .unwrap();
+        let definition = &set.definitions()[0];
+        assert_eq!(definition.name, "guide.md");
+        assert!(definition.uri.starts_with("file://"));
+        assert!(read(&set, &definition.uri.clone()).is_err());
+
+        assert!(ResourceSet::new(safety(dir.path()))
+            .with_resource(ResourceSpec::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_cached_contents_follow_file_changes() {
+        let dir = docs();
+        let path = dir.path().join("guide.md");
+        let set = ResourceSet::new(safety(dir.path()))
+            .with_resource(ResourceSpec::file(&path).with_uri("docs://guide"))
+            .unwrap();
+
+        assert_eq!(
+            read(&set, "docs://guide").unwrap().text.as_deref(),
+            Some("# Guide\n")
+        );
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_len(0).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(
+            read(&set, "docs://guide").unwrap().text.as_deref(),
+            Some("")
+        );
+    }
+
+    #[test]
+    fn test_resources_config() {
+        let dir = docs();
+        let config = ResourcesConfig::from_json(&format!(
+            r#"{{
+                "resources": [
+                    {{ "dir": {:?}, "uri": "docs://" }},
+                    {{ "path": {:?}, "name": "Guide", "mimeType": "text/plain" }}
+                ],
+                "max_size": 1024,
+                "cache_ttl_ms": 0
+            }}"#,
+            dir.path(),
+            dir.path().join("guide.md"),
+        ))
+        .unwrap();
+        assert_eq!(config.resources.len(), 2);
+        assert_eq!(config.resources[1].mime_type.as_deref(), Some("text/plain"));
+
+        let set = ResourceSet::from_config(&config, safety(dir.path())).unwrap();
+        assert_eq!(set.definitions().len(), 5);
+        assert_eq!(set.definitions()[4].name, "Guide");
+        assert!(ResourcesConfig::from_json(r#"{ "resources": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn test_url_resources_refused() {
+        let safety = Arc::new(
+            SafetyConfig::new().with_hosts(HostAllowlist::none().allow("docs.example.com")),
+        );
+        let refused = ResourceSet::new(safety)
+            .with_resource(ResourceSpec::url("https://docs.example.com/guide.md"));
+        assert!(matches!(refused, Err(Error::ModuleNotAvailable(_))));
+    }
+}
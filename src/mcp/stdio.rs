@@ -0,0 +1,861 @@
+//! Hardened stdio transport for MCP servers.
+//!
+//! MCP servers launched as subprocesses talk JSON-RPC 2.0 over stdin and
+//! stdout. Clients are not always well behaved, so this transport treats
+//! its input as untrusted:
+//!
+//! - messages are framed strictly, either one per line ([`Framing::Newline`],
+//!   the MCP stdio transport) or with LSP-style `Content-Length` headers
+//!   ([`Framing::ContentLength`]);
+//! - messages over [`StdioConfig::max_message_size`] are discarded without
+//!   being buffered;
+//! - anything that is not a JSON-RPC 2.0 object is rejected with a
+//!   [`FrameError`] that can be answered with
+//!   [`error_response`](FrameError::error_response), and reading continues
+//!   with the next message;
+//! - only input that leaves the framing unrecoverable (a missing or invalid
+//!   `Content-Length`, a truncated body) is fatal.
+//!
+//! [`StdioTransport`] adds a read timeout and keepalive pings for
+//! long-lived servers, and answers the client's pings itself:
+//!
+//! ```rust,ignore
+//! use fusabi_stdlib_ext::mcp::stdio::{StdioConfig, StdioTransport};
+//!
+//! let config = StdioConfig::default().with_keepalive(Duration::from_secs(30));
+//! let mut transport = StdioTransport::stdio(config)?;
+//! while let Some(frame) = transport.recv()? {
+//!     match frame {
+//!         Ok(message) => transport.send(&handle(message))?,
+//!         Err(rejected) => transport.send(&rejected.error_response())?,
+//!     }
+//! }
+//! ```
+//!
+//! [`FrameReader`] is the framing layer alone, for embedders that run their
+//! own read loop.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::error::{Error, Result};
+
+/// JSON-RPC parse error code.
+pub const PARSE_ERROR: i64 = -32700;
+
+/// JSON-RPC invalid request code.
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// Longest header line accepted with [`Framing::ContentLength`].
+const MAX_HEADER_LINE: usize = 1024;
+
+/// Most header lines accepted before a body.
+const MAX_HEADERS: usize = 32;
+
+/// Prefix of the ids of keepalive pings sent by [`StdioTransport`].
+const PING_ID_PREFIX: &str = "fusabi-keepalive-";
+
+/// Frames queued between the reader thread and [`StdioTransport::recv`].
+const READ_AHEAD: usize = 16;
+
+/// How messages are delimited on the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// One JSON message per line, as in the MCP stdio transport. Blank
+    /// lines are skipped and a trailing `\r` is stripped.
+    #[default]
+    Newline,
+    /// `Content-Length: N` headers, terminated by `\r\n\r\n`, before each
+    /// body, as in the Language Server Protocol.
+    ContentLength,
+}
+
+/// Stdio transport settings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StdioConfig {
+    /// How messages are delimited.
+    pub framing: Framing,
+    /// Largest message accepted, in bytes.
+    pub max_message_size: usize,
+    /// Fail [`StdioTransport::recv`] with [`Error::Timeout`] after this long
+    /// without any input; `None` waits forever.
+    pub read_timeout: Option<Duration>,
+    /// Send a `ping` request after this long without input; `None` sends
+    /// none.
+    pub keepalive: Option<Duration>,
+}
+
+impl Default for StdioConfig {
+    fn default() -> Self {
+        Self {
+            framing: Framing::Newline,
+            max_message_size: 4 * 1024 * 1024,
+            read_timeout: None,
+            keepalive: None,
+        }
+    }
+}
+
+impl StdioConfig {
+    /// Set the framing.
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Set the largest message accepted.
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Set the read timeout.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the keepalive interval.
+    pub fn with_keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = Some(interval);
+        self
+    }
+}
+
+/// Why a message was rejected. The stream stays usable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameError {
+    /// The message exceeded the size limit and was discarded.
+    TooLarge {
+        /// Size of the message, or of the part read before giving up.
+        size: usize,
+        /// The limit.
+        limit: usize,
+    },
+    /// The message was not valid UTF-8 JSON.
+    InvalidJson(String),
+    /// The message was JSON but not a JSON-RPC 2.0 message.
+    InvalidMessage {
+        /// The message's id, if it had a usable one.
+        id: Option<JsonValue>,
+        /// What was wrong.
+        reason: String,
+    },
+}
+
+impl FrameError {
+    /// JSON-RPC error code for the rejection.
+    pub fn code(&self) -> i64 {
+        match self {
+            FrameError::InvalidJson(_) => PARSE_ERROR,
+            FrameError::TooLarge { .. } | FrameError::InvalidMessage { .. } => INVALID_REQUEST,
+        }
+    }
+
+    /// JSON-RPC error response to send back, addressed to the message's id
+    /// when it could be read and to `null` otherwise.
+    pub fn error_response(&self) -> JsonValue {
+        let id = match self {
+            FrameError::InvalidMessage { id: Some(id), .. } => id.clone(),
+            _ => JsonValue::Null,
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": self.code(), "message": self.to_string() },
+        })
+    }
+}
+
+impl std::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameError::TooLarge { size, limit } => {
+                write!(f, "message of {} bytes exceeds limit of {}", size, limit)
+            }
+            FrameError::InvalidJson(e) => write!(f, "parse error: {}", e),
+            FrameError::InvalidMessage { reason, .. } => write!(f, "invalid request: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+/// A message read from the stream, or the reason it was rejected.
+pub type Frame = std::result::Result<JsonValue, FrameError>;
+
+/// Check that `bytes` hold a single JSON-RPC 2.0 request, notification or
+/// response.
+pub fn parse_message(bytes: &[u8]) -> Frame {
+    let value: JsonValue =
+        serde_json::from_slice(bytes).map_err(|e| FrameError::InvalidJson(e.to_string()))?;
+    let invalid = |id: Option<&JsonValue>, reason: &str| FrameError::InvalidMessage {
+        id: id.cloned(),
+        reason: reason.to_string(),
+    };
+
+    let object = match value.as_object() {
+        Some(object) => object,
+        None if value.is_array() => return Err(invalid(None, "batches are not supported")),
+        None => return Err(invalid(None, "message must be an object")),
+    };
+    let id = object.get("id");
+    if !matches!(
+        id,
+        None | Some(JsonValue::String(_) | JsonValue::Number(_) | JsonValue::Null)
+    ) {
+        return Err(invalid(None, "id must be a string, number or null"));
+    }
+    if object.get("jsonrpc").and_then(|v| v.as_str()) != Some("2.0") {
+        return Err(invalid(id, "jsonrpc must be \"2.0\""));
+    }
+    match object.get("method") {
+        Some(JsonValue::String(_)) => {}
+        Some(_) => return Err(invalid(id, "method must be a string")),
+        None if id.is_some() && (object.contains_key("result") != object.contains_key("error")) => {
+        }
+        None => return Err(invalid(id, "missing method")),
+    }
+    if !matches!(
+        object.get("params"),
+        None | Some(JsonValue::Object(_) | JsonValue::Array(_))
+    ) {
+        return Err(invalid(id, "params must be an object or array"));
+    }
+    Ok(value)
+}
+
+/// Reads framed messages from a stream, enforcing the size limit.
+#[derive(Debug)]
+pub struct FrameReader<R> {
+    reader: R,
+    framing: Framing,
+    max_message_size: usize,
+}
+
+impl<R: BufRead> FrameReader<R> {
+    /// Create a frame reader.
+    pub fn new(reader: R, framing: Framing, max_message_size: usize) -> Self {
+        Self {
+            reader,
+            framing,
+            max_message_size,
+        }
+    }
+
+    /// Read the next message.
+    ///
+    /// Returns `Ok(None)` at the end of the stream, `Ok(Some(Err(_)))` for a
+    /// rejected message (the stream stays in sync), and `Err(_)` when the
+    /// stream fails or its framing can no longer be followed.
+    pub fn read_frame(&mut self) -> Result<Option<Frame>> {
+        match self.framing {
+            Framing::Newline => self.read_line_frame(),
+            Framing::ContentLength => self.read_content_length_frame(),
+        }
+    }
+
+    fn read_line_frame(&mut self) -> Result<Option<Frame>> {
+        loop {
+            let mut line = Vec::new();
+            let Some(size) = read_line_limited(&mut self.reader, &mut line, self.max_message_size)?
+            else {
+                return Ok(None);
+            };
+            if size > self.max_message_size {
+                return Ok(Some(Err(FrameError::TooLarge {
+                    size,
+                    limit: self.max_message_size,
+                })));
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return Ok(Some(parse_message(&line)));
+        }
+    }
+
+    fn read_content_length_frame(&mut self) -> Result<Option<Frame>> {
+        let mut content_length: Option<usize> = None;
+        let mut headers = 0;
+        loop {
+            let mut line = Vec::new();
+            let size = match read_line_limited(&mut self.reader, &mut line, MAX_HEADER_LINE)? {
+                Some(size) => size,
+                None if headers == 0 => return Ok(None),
+                None => return Err(truncated("headers")),
+            };
+            if size > MAX_HEADER_LINE {
+                return Err(framing_error("header line too long"));
+            }
+            if line.pop() != Some(b'\r') {
+                return Err(framing_error("header lines must end with CRLF"));
+            }
+            if line.is_empty() {
+                break;
+            }
+            headers += 1;
+            if headers > MAX_HEADERS {
+                return Err(framing_error("too many headers"));
+            }
+
+            let line = std::str::from_utf8(&line)
+                .map_err(|_| framing_error("header is not valid UTF-8"))?;
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| framing_error(format!("malformed header {:?}", line)))?;
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let length: usize = value.trim().parse().map_err(|_| {
+                    framing_error(format!("invalid Content-Length {:?}", value.trim()))
+                })?;
+                if content_length.is_some_and(|previous| previous != length) {
+                    return Err(framing_error("conflicting Content-Length headers"));
+                }
+                content_length = Some(length);
+            }
+        }
+        let length = content_length.ok_or_else(|| framing_error("missing Content-Length"))?;
+
+        if length > self.max_message_size {
+            let skipped = io::copy(&mut (&mut self.reader).take(length as u64), &mut io::sink())?;
+            if skipped < length as u64 {
+                return Err(truncated("body"));
+            }
+            return Ok(Some(Err(FrameError::TooLarge {
+                size: length,
+                limit: self.max_message_size,
+            })));
+        }
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                truncated("body")
+            } else {
+                Error::Io(e)
+            }
+        })?;
+        Ok(Some(parse_message(&body)))
+    }
+
+    /// Get the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Read up to and including the next `\n` into `line`, keeping at most
+/// `limit` bytes (plus the newline, which is not kept). Returns the line's
+/// full length, so a result over `limit` means the rest was discarded, or
+/// `None` at the end of the stream. A final line without a newline counts.
+fn read_line_limited<R: BufRead>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+    limit: usize,
+) -> io::Result<Option<usize>> {
+    let mut size = 0;
+    let mut started = false;
+    loop {
+        let buf = match reader.fill_buf() {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if buf.is_empty() {
+            return Ok(started.then_some(size));
+        }
+        started = true;
+        let (chunk, done) = match buf.iter().position(|b| *b == b'\n') {
+            Some(i) => (&buf[..i], true),
+            None => (buf, false),
+        };
+        let keep = chunk.len().min(limit.saturating_sub(size));
+        line.extend_from_slice(&chunk[..keep]);
+        size += chunk.len();
+        let consumed = chunk.len() + usize::from(done);
+        reader.consume(consumed);
+        if done {
+            return Ok(Some(size));
+        }
+    }
+}
+
+fn framing_error(message: impl std::fmt::Display) -> Error {
+    Error::Format(format!("mcp: {}", message))
+}
+
+fn truncated(part: &str) -> Error {
+    Error::Io(io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        format!("mcp: stream ended inside message {}", part),
+    ))
+}
+
+/// Write one message with the given framing and flush.
+pub fn write_frame<W: Write>(writer: &mut W, framing: Framing, message: &JsonValue) -> Result<()> {
+    // Compact JSON escapes every newline, so it is always a single line.
+    let body = serde_json::to_vec(message).map_err(|e| Error::Serialization(e.to_string()))?;
+    match framing {
+        Framing::Newline => {
+            writer.write_all(&body)?;
+            writer.write_all(b"\n")?;
+        }
+        Framing::ContentLength => {
+            write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+            writer.write_all(&body)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// A JSON-RPC transport over a byte stream pair, normally stdin and stdout.
+///
+/// Input is read on a background thread so reads can time out. That thread
+/// stays blocked on the stream until it closes, even after the transport is
+/// dropped.
+pub struct StdioTransport<W: Write> {
+    frames: Receiver<Result<Option<Frame>>>,
+    writer: W,
+    config: StdioConfig,
+    last_input: Instant,
+    last_ping: Option<Instant>,
+    pings_sent: u64,
+    closed: bool,
+}
+
+impl StdioTransport<io::Stdout> {
+    /// Create a transport over the process's stdin and stdout.
+    pub fn stdio(config: StdioConfig) -> Result<Self> {
+        Self::new(io::stdin(), io::stdout(), config)
+    }
+}
+
+impl<W: Write> StdioTransport<W> {
+    /// Create a transport reading from `reader` and writing to `writer`.
+    pub fn new(reader: impl Read + Send + 'static, writer: W, config: StdioConfig) -> Result<Self> {
+        let (tx, frames) = mpsc::sync_channel(READ_AHEAD);
+        let mut frame_reader = FrameReader::new(
+            BufReader::new(reader),
+            config.framing,
+            config.max_message_size,
+        );
+        std::thread::Builder::new()
+            .name("fusabi-mcp-stdio".into())
+            .spawn(move || loop {
+                let frame = frame_reader.read_frame();
+                let last = !matches!(frame, Ok(Some(_)));
+                if tx.send(frame).is_err() || last {
+                    break;
+                }
+            })?;
+
+        Ok(Self {
+            frames,
+            writer,
+            config,
+            last_input: Instant::now(),
+            last_ping: None,
+            pings_sent: 0,
+            closed: false,
+        })
+    }
+
+    /// Settings in use.
+    pub fn config(&self) -> &StdioConfig {
+        &self.config
+    }
+
+    /// Send a message.
+    pub fn send(&mut self, message: &JsonValue) -> Result<()> {
+        write_frame(&mut self.writer, self.config.framing, message)
+    }
+
+    /// Receive the next message.
+    ///
+    /// Returns `Ok(None)` once the input closes and `Ok(Some(Err(_)))` for a
+    /// rejected message, which should be answered with its
+    /// [`error_response`](FrameError::error_response). `ping` requests from
+    /// the client are answered here and responses to keepalive pings are
+    /// consumed, so neither is returned.
+    ///
+    /// # Errors
+    ///
+    /// [`Error::Timeout`] after [`read_timeout`](StdioConfig::read_timeout)
+    /// without input, and the reader's error when the input fails or its
+    /// framing is lost. After an error the transport returns `Ok(None)`.
+    pub fn recv(&mut self) -> Result<Option<Frame>> {
+        loop {
+            if self.closed {
+                return Ok(None);
+            }
+            let frame = match self.next_deadline() {
+                Some(deadline) => {
+                    let wait = deadline.saturating_duration_since(Instant::now());
+                    match self.frames.recv_timeout(wait) {
+                        Ok(frame) => frame,
+                        Err(RecvTimeoutError::Timeout) => {
+                            self.on_idle()?;
+                            continue;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => Ok(None),
+                    }
+                }
+                None => self.frames.recv().unwrap_or(Ok(None)),
+            };
+
+            let frame = match frame {
+                Ok(Some(frame)) => frame,
+                other => {
+                    self.closed = true;
+                    return other;
+                }
+            };
+            self.last_input = Instant::now();
+            self.last_ping = None;
+
+            if let Ok(message) = &frame {
+                if self.handle_ping(message)? {
+                    continue;
+                }
+            }
+            return Ok(Some(frame));
+        }
+    }
+
+    /// Next time `recv` has to wake up without input, for a keepalive ping
+    /// or the read timeout.
+    fn next_deadline(&self) -> Option<Instant> {
+        let ping = self
+            .config
+            .keepalive
+            .map(|interval| self.last_ping.unwrap_or(self.last_input) + interval);
+        let timeout = self.config.read_timeout.map(|t| self.last_input + t);
+        match (ping, timeout) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    fn on_idle(&mut self) -> Result<()> {
+        let now = Instant::now();
+        if let Some(timeout) = self.config.read_timeout {
+            if now >= self.last_input + timeout {
+                self.closed = true;
+                return Err(Error::Timeout(timeout));
+            }
+        }
+        if let Some(interval) = self.config.keepalive {
+            if now >= self.last_ping.unwrap_or(self.last_input) + interval {
+                self.pings_sent += 1;
+                let ping = json!({
+                    "jsonrpc": "2.0",
+                    "id": format!("{}{}", PING_ID_PREFIX, self.pings_sent),
+                    "method": "ping",
+                });
+                self.send(&ping)?;
+                self.last_ping = Some(now);
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer a client ping or swallow the response to one of ours.
+    /// Returns whether the message was handled.
+    fn handle_ping(&mut self, message: &JsonValue) -> Result<bool> {
+        let id = message.get("id");
+        match message.get("method").and_then(|m| m.as_str()) {
+            Some("ping") => {
+                if let Some(id) = id {
+                    let pong = json!({ "jsonrpc": "2.0", "id": id, "result": {} });
+                    self.send(&pong)?;
+                }
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Ok(id
+                .and_then(|id| id.as_str())
+                .is_some_and(|id| id.starts_with(PING_ID_PREFIX))),
+        }
+    }
+
+    /// Number of keepalive pings sent.
+    pub fn pings_sent(&self) -> u64 {
+        self.pings_sent
+    }
+
+    /// Get the writer.
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+}
+
+impl<W: Write> std::fmt::Debug for StdioTransport<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdioTransport")
+            .field("config", &self.config)
+            .field("pings_sent", &self.pings_sent)
+            .field("closed", &self.closed)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    fn frames(input: &[u8], framing: Framing, limit: usize) -> Vec<Result<Option<Frame>>> {
+        let mut reader = FrameReader::new(Cursor::new(input.to_vec()), framing, limit);
+        let mut out = Vec::new();
+        // Every read consumes input, so this terminates well within the
+        // input length.
+        for _ in 0..=input.len() {
+            let frame = reader.read_frame();
+            let last = !matches!(frame, Ok(Some(_)));
+            out.push(frame);
+            if last {
+                break;
+            }
+        }
+        assert!(matches!(out.last(), Some(Ok(None) | Err(_))));
+        out
+    }
+
+    #[test]
+    fn test_newline_framing() {
+        let input = b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/list\"}\r\n\
+            \n\
+            not json\n\
+            [1,2]\n\
+            {\"jsonrpc\":\"1.0\",\"id\":\"a\",\"method\":\"x\"}\n\
+            {\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}";
+        let out = frames(input, Framing::Newline, 1024);
+        assert_eq!(out.len(), 6);
+
+        let first = out[0].as_ref().unwrap().as_ref().unwrap().as_ref().unwrap();
+        assert_eq!(first["method"], "tools/list");
+        let parse = out[1]
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap_err();
+        assert_eq!(parse.code(), PARSE_ERROR);
+        assert_eq!(parse.error_response()["id"], JsonValue::Null);
+        assert!(matches!(
+            out[2].as_ref().unwrap().as_ref().unwrap(),
+            Err(FrameError::InvalidMessage { .. })
+        ));
+        let version = out[3]
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap_err();
+        assert_eq!(version.code(), INVALID_REQUEST);
+        assert_eq!(version.error_response()["id"], "a");
+        assert!(out[4].as_ref().unwrap().as_ref().unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_oversized_messages_are_skipped() {
+        let big = format!("{{\"jsonrpc\":\"2.0\",\"method\":\"{}\"}}", "x".repeat(100));
+        let ok = "{\"jsonrpc\":\"2.0\",\"method\":\"y\"}";
+
+        let input = format!("{}\n{}\n", big, ok);
+        let out = frames(input.as_bytes(), Framing::Newline, 64);
+        assert_eq!(
+            out[0].as_ref().unwrap().as_ref().unwrap(),
+            &Err(FrameError::TooLarge {
+                size: big.len(),
+                limit: 64
+            })
+        );
+        assert_eq!(
+            out[1].as_ref().unwrap().as_ref().unwrap().as_ref().unwrap()["method"],
+            "y"
+        );
+
+        let input = format!(
+            "Content-Length: {}\r\n\r\n{}Content-Length: {}\r\n\r\n{}",
+            big.len(),
+            big,
+            ok.len(),
+            ok
+        );
+        let out = frames(input.as_bytes(), Framing::ContentLength, 64);
+        assert!(matches!(
+            out[0].as_ref().unwrap().as_ref().unwrap(),
+            Err(FrameError::TooLarge { .. })
+        ));
+        assert!(out[1].as_ref().unwrap().as_ref().unwrap().is_ok());
+        assert!(matches!(out[2], Ok(None)));
+    }
+
+    #[test]
+    fn test_content_length_framing_errors() {
+        let ok = "{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{}}";
+        let input = format!(
+            "Content-Type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+            ok.len(),
+            ok
+        );
+        let out = frames(input.as_bytes(), Framing::ContentLength, 1024);
+        assert!(out[0].as_ref().unwrap().as_ref().unwrap().is_ok());
+
+        for bad in [
+            "Content-Length: 10\n\n{}",
+            "Content-Length: ten\r\n\r\n",
+            "Content-Type: json\r\n\r\n{}",
+            "Content-Length: 1\r\nContent-Length: 2\r\n\r\n{}",
+            "Content-Length: 100\r\n\r\n{}",
+            "Content-Length: 2\r\n",
+        ] {
+            let out = frames(bad.as_bytes(), Framing::ContentLength, 1024);
+            assert!(out[0].is_err(), "{:?} accepted", bad);
+        }
+
+        let endless_header = "X".repeat(10_000);
+        let out = frames(endless_header.as_bytes(), Framing::ContentLength, 1024);
+        assert!(out[0].is_err());
+    }
+
+    #[test]
+    fn test_fuzz_malformed_input() {
+        // Deterministic xorshift so failures reproduce.
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let seeds: [&[u8]; 4] = [
+            b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/call\",\"params\":{\"name\":\"a\"}}\n",
+            b"Content-Length: 40\r\n\r\n{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}",
+            b"\xff\xfe\x00\r\n\r\n\n\n{{{{[[[[\"\\u",
+            b"Content-Length: 18446744073709551615\r\n\r\n",
+        ];
+
+        for round in 0..2000 {
+            let mut input = seeds[round % seeds.len()].to_vec();
+            for _ in 0..(next() % 8) {
+                let at = (next() as usize) % (input.len() + 1);
+                match next() % 3 {
+                    0 if at < input.len() => input[at] = next() as u8,
+                    1 => input.insert(at, next() as u8),
+                    _ => {
+                        let n = (next() % 64) as usize;
+                        input.splice(at..at, (0..n).map(|_| next() as u8));
+                    }
+                }
+            }
+            for framing in [Framing::Newline, Framing::ContentLength] {
+                for frame in frames(&input, framing, 48).into_iter().flatten().flatten() {
+                    match frame {
+                        Ok(message) => {
+                            assert!(parse_message(message.to_string().as_bytes()).is_ok())
+                        }
+                        Err(e) => {
+                            let response = e.error_response();
+                            assert!(parse_message(response.to_string().as_bytes()).is_ok());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedWriter {
+        fn messages(&self) -> Vec<JsonValue> {
+            let out = self.0.lock().unwrap();
+            out.split(|b| *b == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(|line| serde_json::from_slice(line).unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_transport_pings_and_eof() {
+        let input = b"{\"jsonrpc\":\"2.0\",\"id\":7,\"method\":\"ping\"}\n\
+            {\"jsonrpc\":\"2.0\",\"id\":\"fusabi-keepalive-1\",\"result\":{}}\n\
+            {\"jsonrpc\":\"2.0\",\"id\":8,\"method\":\"tools/list\"}\n";
+        let out = SharedWriter::default();
+        let mut transport = StdioTransport::new(
+            Cursor::new(input.to_vec()),
+            out.clone(),
+            StdioConfig::default(),
+        )
+        .unwrap();
+
+        let message = transport.recv().unwrap().unwrap().unwrap();
+        assert_eq!(message["id"], 8);
+        assert!(transport.recv().unwrap().is_none());
+        assert!(transport.recv().unwrap().is_none());
+        assert_eq!(
+            out.messages(),
+            vec![json!({"jsonrpc": "2.0", "id": 7, "result": {}})]
+        );
+    }
+
+    #[test]
+    fn test_transport_keepalive_and_timeout() {
+        let (reader, _keep_open) = blocking_reader();
+        let out = SharedWriter::default();
+        let config = StdioConfig::default()
+            .with_keepalive(Duration::from_millis(20))
+            .with_read_timeout(Duration::from_millis(110));
+        let mut transport = StdioTransport::new(reader, out.clone(), config).unwrap();
+
+        assert!(matches!(transport.recv(), Err(Error::Timeout(_))));
+        assert!(transport.pings_sent() >= 3);
+        let pings = out.messages();
+        assert_eq!(pings.len() as u64, transport.pings_sent());
+        assert_eq!(pings[0]["method"], "ping");
+        assert_eq!(pings[0]["id"], "fusabi-keepalive-1");
+        assert!(transport.recv().unwrap().is_none());
+    }
+
+    /// A reader that blocks until the returned sender is dropped.
+    fn blocking_reader() -> (impl Read + Send + 'static, mpsc::Sender<Vec<u8>>) {
+        struct ChannelReader(mpsc::Receiver<Vec<u8>>);
+        impl Read for ChannelReader {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.recv() {
+                    Ok(bytes) => {
+                        let n = bytes.len().min(buf.len());
+                        buf[..n].copy_from_slice(&bytes[..n]);
+                        Ok(n)
+                    }
+                    Err(_) => Ok(0),
+                }
+            }
+        }
+        let (tx, rx) = mpsc::channel();
+        (ChannelReader(rx), tx)
+    }
+}
@@ -0,0 +1,508 @@
+//! Reference MCP tools backed by the stdlib modules.
+//!
+//! Each tool calls the same host function a script would, with the same
+//! [`SafetyConfig`] checks, so an embedder can expose filesystem, process and
+//! HTTP access to an MCP client without writing any handlers:
+//!
+//! ```rust,ignore
+//! use fusabi_stdlib_ext::mcp::tools::ToolSet;
+//!
+//! let tools = ToolSet::reference(Arc::new(safety));
+//! let definitions = tools.definitions(); // for tools/list
+//! let result = tools.call(&params)?;     // for tools/call
+//! ```
+//!
+//! Tools are only included when the corresponding module feature is enabled.
+//!
+//! Every call is recorded in the audit sink (the [`SafetyConfig`]'s for
+//! [`ToolSet::reference`]) with the tool name, hashed arguments, caller
+//! session, duration, and status, and with [`ToolSet::with_metrics`] counted
+//! in `mcp_tool_calls_total{tool, status}` and
+//! `mcp_tool_call_duration_seconds{tool}`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use fusabi_host::{Capabilities, ExecutionContext, Limits, Sandbox, SandboxConfig, Value};
+
+use super::{fusabi_to_json, CallToolParams, ToolDefinition};
+use crate::error::{Error, Result};
+use crate::safety::audit::fnv1a_hex;
+use crate::safety::{is_secret_key, AuditEvent, AuditSink, SafetyConfig, TracingAuditSink};
+
+/// Content item in a tool call result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ToolContent {
+    /// Plain text content.
+    #[serde(rename = "text")]
+    Text {
+        /// The text.
+        text: String,
+    },
+}
+
+/// Result of a `tools/call` request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallToolResult {
+    /// Result content.
+    pub content: Vec<ToolContent>,
+    /// Whether the tool reported an error.
+    #[serde(rename = "isError", default)]
+    pub is_error: bool,
+}
+
+impl CallToolResult {
+    /// Create a successful text result.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self {
+            content: vec![ToolContent::Text { text: text.into() }],
+            is_error: false,
+        }
+    }
+
+    /// Create an error result.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            content: vec![ToolContent::Text {
+                text: message.into(),
+            }],
+            is_error: true,
+        }
+    }
+}
+
+type ArgsBuilder = fn(&HashMap<String, JsonValue>) -> Result<Vec<Value>>;
+
+type Handler = Box<dyn Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync>;
+
+/// An MCP tool that forwards to a stdlib host function.
+pub struct ModuleTool {
+    definition: ToolDefinition,
+    build_args: ArgsBuilder,
+    handler: Handler,
+}
+
+impl ModuleTool {
+    /// Create a tool from a definition, an argument mapper, and a host function.
+    pub fn new(
+        definition: ToolDefinition,
+        build_args: ArgsBuilder,
+        handler: impl Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            definition,
+            build_args,
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Get the tool definition.
+    pub fn definition(&self) -> &ToolDefinition {
+        &self.definition
+    }
+
+    /// Invoke the tool. Host function failures become error results.
+    pub fn call(&self, arguments: &HashMap<String, JsonValue>) -> Result<CallToolResult> {
+        let args = match (self.build_args)(arguments) {
+            Ok(args) => args,
+            Err(e) => return Ok(CallToolResult::error(e.to_string())),
+        };
+
+        let ctx = ExecutionContext::new(
+            0,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default())?,
+        );
+
+        Ok(match (self.handler)(&args, &ctx) {
+            Ok(Value::String(text)) => CallToolResult::text(text),
+            Ok(value) => CallToolResult::text(fusabi_to_json(&value).to_string()),
+            Err(e) => CallToolResult::error(e.to_string()),
+        })
+    }
+}
+
+/// A collection of MCP tools.
+pub struct ToolSet {
+    tools: Vec<ModuleTool>,
+    audit_sink: Arc<dyn AuditSink>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<crate::metrics::MetricsRegistry>>,
+}
+
+impl Default for ToolSet {
+    fn default() -> Self {
+        Self {
+            tools: Vec::new(),
+            audit_sink: Arc::new(TracingAuditSink),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+}
+
+impl ToolSet {
+    /// Create an empty tool set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create the reference tools permitted by `safety`.
+    ///
+    /// - `fs.read` / `fs.list` - within the path allowlist (`fs` feature)
+    /// - `process.exec` - within the command allowlist (`process` feature)
+    ///
+    /// There is no HTTP tool yet: `net` requests are still simulated.
+    #[allow(unused_mut, unused_variables)] // Every tool may be feature-gated out
+    pub fn reference(safety: Arc<SafetyConfig>) -> Self {
+        let mut set = Self::new().with_audit_sink(safety.audit_sink.clone());
+
+        #[cfg(feature = "fs")]
+        {
+            let s = safety.clone();
+            set = set.with_tool(ModuleTool::new(
+                tool_definition(
+                    "fs.read",
+                    "Read a UTF-8 text file within the allowed roots",
+                    &[("path", "string", "Absolute file path", true)],
+                ),
+                |a| Ok(vec![required_string(a, "path")?]),
+                move |args, ctx| crate::fs::read_file(&s, args, ctx),
+            ));
+
+            let s = safety.clone();
+            set = set.with_tool(ModuleTool::new(
+                tool_definition(
+                    "fs.list",
+                    "List the entries of a directory within the allowed roots",
+                    &[("path", "string", "Absolute directory path", true)],
+                ),
+                |a| Ok(vec![required_string(a, "path")?]),
+                move |args, ctx| crate::fs::list_dir(&s, args, ctx),
+            ));
+        }
+
+        #[cfg(feature = "process")]
+        if safety.allow_process {
+            let s = safety.clone();
+            set = set.with_tool(ModuleTool::new(
+                tool_definition(
+                    "process.exec",
+                    "Run an allowlisted command and return its output",
+                    &[
+                        ("command", "string", "Command to run", true),
+                        ("args", "array", "Command arguments", false),
+                    ],
+                ),
+                |a| {
+                    let mut args = vec![required_string(a, "command")?];
+                    if let Some(list) = a.get("args") {
+                        let list = list.as_array().ok_or_else(|| {
+                            Error::InvalidArgument("'args' must be an array".to_string())
+                        })?;
+                        args.extend(list.iter().map(|v| match v {
+                            JsonValue::String(s) => Value::String(s.clone()),
+                            other => Value::String(other.to_string()),
+                        }));
+                    }
+                    Ok(args)
+                },
+                move |args, ctx| crate::process::exec(&s, None, args, ctx),
+            ));
+        }
+
+        set
+    }
+
+    /// Add a tool.
+    pub fn with_tool(mut self, tool: ModuleTool) -> Self {
+        self.tools.push(tool);
+        self
+    }
+
+    /// Definitions for a `tools/list` response.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools.iter().map(|t| t.definition.clone()).collect()
+    }
+
+    /// Record tool calls in `sink`.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = sink;
+        self
+    }
+
+    /// Count tool calls and their durations in `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<crate::metrics::MetricsRegistry>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Handle a `tools/call` request.
+    pub fn call(&self, params: &CallToolParams) -> Result<CallToolResult> {
+        self.call_from(params, None)
+    }
+
+    /// Handle a `tools/call` request from a client session, which is
+    /// recorded in the audit log.
+    pub fn call_from(
+        &self,
+        params: &CallToolParams,
+        session: Option<&str>,
+    ) -> Result<CallToolResult> {
+        let started = Instant::now();
+        let result = match self.tools.iter().find(|t| t.definition.name == params.name) {
+            Some(tool) => tool.call(&params.arguments),
+            None => Err(Error::InvalidArgument(format!(
+                "unknown tool '{}'",
+                params.name
+            ))),
+        };
+        let elapsed = started.elapsed();
+
+        let status = match &result {
+            Ok(result) if !result.is_error => "ok",
+            Ok(_) => "error",
+            Err(_) => "failed",
+        };
+
+        let mut event = AuditEvent::new("mcp.tools_call", status)
+            .with_field("tool", params.name.as_str())
+            .with_field("arguments", redact_arguments(&params.arguments).to_string())
+            .with_field("duration_ms", elapsed.as_millis().to_string());
+        if let Some(session) = session {
+            event = event.with_field("session", session);
+        }
+        if let Err(e) = &result {
+            event = event.with_field("error", e.to_string());
+        }
+        self.audit_sink.record(&event);
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            let tool = ("tool".to_string(), params.name.clone());
+            metrics.counter_inc_with(
+                "mcp_tool_calls_total",
+                &crate::metrics::Labels::from([
+                    tool.clone(),
+                    ("status".to_string(), status.to_string()),
+                ]),
+                1,
+            );
+            metrics.histogram_observe_with(
+                "mcp_tool_call_duration_seconds",
+                &crate::metrics::Labels::from([tool]),
+                elapsed.as_secs_f64(),
+            );
+        }
+
+        result
+    }
+}
+
+/// Summarize tool arguments for the audit log: each value is replaced by
+/// its hash, as for [`args_hash`](crate::safety::audit::args_hash), so calls
+/// can be correlated without storing paths, commands or payloads. Values
+/// under secret-like keys are not even hashed.
+fn redact_arguments(arguments: &HashMap<String, JsonValue>) -> JsonValue {
+    let map = arguments
+        .iter()
+        .map(|(key, value)| {
+            let summary = if is_secret_key(key) {
+                "[REDACTED]".to_string()
+            } else {
+                fnv1a_hex(&value.to_string())
+            };
+            (key.clone(), JsonValue::String(summary))
+        })
+        .collect();
+    JsonValue::Object(map)
+}
+
+#[cfg(any(feature = "fs", feature = "process", feature = "net"))]
+fn tool_definition(
+    name: &str,
+    description: &str,
+    params: &[(&str, &str, &str, bool)],
+) -> ToolDefinition {
+    let properties: serde_json::Map<String, JsonValue> = params
+        .iter()
+        .map(|(name, kind, description, _)| {
+            let mut schema = serde_json::json!({ "type": kind, "description": description });
+            if *kind == "array" {
+                schema["items"] = serde_json::json!({ "type": "string" });
+            }
+            (name.to_string(), schema)
+        })
+        .collect();
+    let required: Vec<&str> = params
+        .iter()
+        .filter(|(_, _, _, required)| *required)
+        .map(|(name, _, _, _)| *name)
+        .collect();
+
+    ToolDefinition {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        }),
+    }
+}
+
+#[cfg(any(feature = "fs", feature = "process", feature = "net"))]
+fn required_string(arguments: &HashMap<String, JsonValue>, key: &str) -> Result<Value> {
+    match arguments.get(key).map(super::json_to_fusabi) {
+        Some(Value::String(s)) => Ok(Value::String(s)),
+        _ => Err(Error::InvalidArgument(format!(
+            "missing string argument '{}'",
+            key
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "fs")]
+    fn call(tools: &ToolSet, name: &str, arguments: JsonValue) -> CallToolResult {
+        let params = CallToolParams {
+            name: name.to_string(),
+            arguments: serde_json::from_value(arguments).unwrap(),
+        };
+        tools.call(&params).unwrap()
+    }
+
+    #[test]
+    fn test_unknown_tool() {
+        let tools = ToolSet::reference(Arc::new(SafetyConfig::strict()));
+        let params = CallToolParams {
+            name: "nope".to_string(),
+            arguments: HashMap::new(),
+        };
+        assert!(tools.call(&params).is_err());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    fn test_fs_tools_respect_roots() {
+        use crate::safety::PathAllowlist;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+
+        let safety = SafetyConfig::new().with_paths(PathAllowlist::none().allow_read(dir.path()));
+        let tools = ToolSet::reference(Arc::new(safety));
+        assert!(tools.definitions().iter().any(|d| d.name == "fs.read"));
+
+        let path = dir.path().join("notes.txt");
+        let result = call(&tools, "fs.read", serde_json::json!({ "path": path }));
+        assert_eq!(result, CallToolResult::text("hello"));
+
+        let result = call(&tools, "fs.list", serde_json::json!({ "path": dir.path() }));
+        assert!(!result.is_error);
+        assert!(
+            matches!(&result.content[0], ToolContent::Text { text } if text.contains("notes.txt"))
+        );
+
+        let result = call(
+            &tools,
+            "fs.read",
+            serde_json::json!({ "path": "/etc/passwd" }),
+        );
+        assert!(result.is_error);
+
+        let result = call(&tools, "fs.read", serde_json::json!({}));
+        assert!(result.is_error);
+    }
+
+    #[test]
+    fn test_tool_calls_are_audited() {
+        use crate::safety::MemoryAuditSink;
+
+        let sink = Arc::new(MemoryAuditSink::new());
+        let tools = ToolSet::new()
+            .with_audit_sink(sink.clone())
+            .with_tool(ModuleTool::new(
+                ToolDefinition {
+                    name: "echo".to_string(),
+                    description: None,
+                    input_schema: serde_json::json!({ "type": "object" }),
+                },
+                |a| Ok(vec![super::super::json_to_fusabi(&a["text"])]),
+                |args, _ctx| Ok(args[0].clone()),
+            ));
+
+        let params = CallToolParams {
+            name: "echo".to_string(),
+            arguments: serde_json::from_value(serde_json::json!({
+                "text": "hi",
+                "api_token": "s3cr3t",
+                "nested": { "password": "hunter2" },
+            }))
+            .unwrap(),
+        };
+        assert_eq!(
+            tools.call_from(&params, Some("session-1")).unwrap(),
+            CallToolResult::text("hi")
+        );
+
+        let unknown = CallToolParams {
+            name: "nope".to_string(),
+            arguments: HashMap::new(),
+        };
+        assert!(tools.call(&unknown).is_err());
+
+        let events = sink.take();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].action, "mcp.tools_call");
+        assert_eq!(events[0].outcome, "ok");
+        assert_eq!(events[0].fields["tool"], "echo");
+        assert_eq!(events[0].fields["session"], "session-1");
+        let arguments = &events[0].fields["arguments"];
+        assert!(arguments.contains("\"text\""));
+        assert!(!arguments.contains("hi\""));
+        assert!(!arguments.contains("s3cr3t") && !arguments.contains("hunter2"));
+        assert!(events[0].fields.contains_key("duration_ms"));
+        assert_eq!(events[1].outcome, "failed");
+        assert!(!events[1].fields.contains_key("session"));
+    }
+
+    #[test]
+    fn test_redact_arguments() {
+        let arguments = HashMap::from([
+            ("path".to_string(), JsonValue::String("/etc/hosts".into())),
+            ("token".to_string(), JsonValue::String("abc".into())),
+        ]);
+        let redacted = redact_arguments(&arguments);
+        let hash = redacted["path"].as_str().unwrap();
+        assert_eq!(hash.len(), 16);
+        assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(redacted, redact_arguments(&arguments));
+        assert_eq!(redacted["token"], "[REDACTED]");
+    }
+
+    #[test]
+    fn test_call_tool_result_serialize() {
+        let json = serde_json::to_value(CallToolResult::error("denied")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "content": [{ "type": "text", "text": "denied" }],
+                "isError": true,
+            })
+        );
+    }
+}
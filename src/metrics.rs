@@ -1,34 +1,82 @@
 //! Metrics module.
 //!
-//! Provides counter, gauge, and histogram primitives.
+//! Provides counter, gauge, histogram, and summary primitives. Summaries
+//! report quantiles over a sliding time window (see [`summary`]).
+//!
+//! Each metric may carry a set of labels. A series is identified by its
+//! name plus its sorted labels, rendered in Prometheus style as
+//! `requests_total{route="/api",status="500"}`; unlabeled series keep their
+//! bare name. Snapshots and exporters see these series keys, which can be
+//! split back apart with [`parse_series_key`].
+//!
+//! Values can be pushed to external systems through [`MetricsExporter`]s;
+//! see [`exporters`] for the built-in StatsD, JSON file and OTLP exporters.
+//! Exporters only see data when the registry is flushed, either explicitly
+//! or on a [flush interval](MetricsRegistry::start_flush_interval), so
+//! embedders should call [`MetricsRegistry::shutdown`] (or
+//! `StdlibRegistry::shutdown`) before the process exits to deliver the final
+//! interval.
+//!
+//! [Derived metrics](derived) (rates, ratios and sums of other series) are
+//! computed whenever a snapshot is taken, so exporters and
+//! `metrics.snapshot` see them as gauges.
+//!
+//! Host functions take the registry they record into. Each
+//! `StdlibRegistry` owns a separate [`MetricsRegistry`] so engines in one
+//! process do not share series; [`global`] is available for sharing.
 
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
 
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 
 use fusabi_host::ExecutionContext;
 use fusabi_host::Value;
 
+pub mod derived;
+pub mod exporters;
+pub mod summary;
+
+pub use derived::DerivedMetric;
+pub use summary::{Summary, SummaryConfig, SummaryStats};
+
 /// Global metrics registry.
-static METRICS: once_cell::sync::Lazy<MetricsRegistry> =
-    once_cell::sync::Lazy::new(MetricsRegistry::new);
+static METRICS: once_cell::sync::Lazy<Arc<MetricsRegistry>> =
+    once_cell::sync::Lazy::new(|| Arc::new(MetricsRegistry::new()));
 
 /// Increment a counter.
-pub fn counter_inc(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+pub fn counter_inc(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
     let name = args
         .first()
         .and_then(|v| v.as_str())
         .ok_or_else(|| fusabi_host::Error::host_function("metrics.counter_inc: missing name"))?;
 
-    let value = args.get(1).and_then(|v| v.as_int()).unwrap_or(1);
+    // The labels map may follow the name directly when the increment is 1.
+    let (value, labels) = match args.get(1) {
+        Some(Value::Map(_)) => (1, args.get(1)),
+        other => (other.and_then(|v| v.as_int()).unwrap_or(1), args.get(2)),
+    };
+    let labels = labels_arg("metrics.counter_inc", labels)?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
 
-    METRICS.counter_inc(name, value as u64);
+    metrics.counter_inc_with(&name, &labels, value as u64);
     Ok(Value::Null)
 }
 
 /// Set a gauge value.
-pub fn gauge_set(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+pub fn gauge_set(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
     let name = args
         .first()
         .and_then(|v| v.as_str())
@@ -39,12 +87,142 @@ pub fn gauge_set(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result
         .and_then(|v| v.as_float().or_else(|| v.as_int().map(|i| i as f64)))
         .ok_or_else(|| fusabi_host::Error::host_function("metrics.gauge_set: missing value"))?;
 
-    METRICS.gauge_set(name, value);
+    let labels = labels_arg("metrics.gauge_set", args.get(2))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    metrics.gauge_set_with(&name, &labels, value);
+    Ok(Value::Null)
+}
+
+/// Atomically add to a gauge.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Amount to add
+/// * `args[2]` - Labels map (optional)
+///
+/// # Returns
+///
+/// The new gauge value
+pub fn gauge_add(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    gauge_adjust(metrics, "metrics.gauge_add", 1.0, args, ctx)
+}
+
+/// Atomically subtract from a gauge.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Amount to subtract
+/// * `args[2]` - Labels map (optional)
+///
+/// # Returns
+///
+/// The new gauge value
+pub fn gauge_sub(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    gauge_adjust(metrics, "metrics.gauge_sub", -1.0, args, ctx)
+}
+
+fn gauge_adjust(
+    metrics: &Arc<MetricsRegistry>,
+    function: &str,
+    sign: f64,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function(format!("{}: missing name", function)))?;
+
+    let delta = args
+        .get(1)
+        .and_then(|v| v.as_float().or_else(|| v.as_int().map(|i| i as f64)))
+        .ok_or_else(|| fusabi_host::Error::host_function(format!("{}: missing value", function)))?;
+
+    let labels = labels_arg(function, args.get(2))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    Ok(Value::Float(metrics.gauge_add_with(
+        &name,
+        &labels,
+        sign * delta,
+    )))
+}
+
+/// Increment a counter at an explicit timestamp, for backfilling.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Increment (non-negative)
+/// * `args[2]` - Unix timestamp in milliseconds
+/// * `args[3]` - Labels map (optional)
+pub fn counter_inc_at(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("metrics.counter_inc_at: missing name"))?;
+
+    let value = args.get(1).and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("metrics.counter_inc_at: missing value")
+    })?;
+    if value < 0 {
+        return Err(fusabi_host::Error::host_function(
+            "metrics.counter_inc_at: value must be non-negative",
+        ));
+    }
+
+    let timestamp = args.get(2).and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("metrics.counter_inc_at: missing timestamp")
+    })?;
+
+    let labels = labels_arg("metrics.counter_inc_at", args.get(3))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    metrics.counter_inc_at_with(&name, &labels, value as u64, timestamp);
+    Ok(Value::Null)
+}
+
+/// Flush all metrics to the registered exporters.
+pub fn flush(
+    metrics: &Arc<MetricsRegistry>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    metrics
+        .flush()
+        .map_err(|e| fusabi_host::Error::host_function(format!("metrics.flush: {}", e)))?;
     Ok(Value::Null)
 }
 
+/// Get the process-wide metrics registry.
+///
+/// Each `StdlibRegistry` records into its own registry by default; pass this
+/// one to `StdlibRegistry::with_metrics` to share metrics across engines.
+pub fn global() -> &'static Arc<MetricsRegistry> {
+    &METRICS
+}
+
 /// Observe a histogram value.
-pub fn histogram_observe(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+pub fn histogram_observe(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
     let name = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
         fusabi_host::Error::host_function("metrics.histogram_observe: missing name")
     })?;
@@ -56,15 +234,769 @@ pub fn histogram_observe(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host
             fusabi_host::Error::host_function("metrics.histogram_observe: missing value")
         })?;
 
-    METRICS.histogram_observe(name, value);
+    let labels = labels_arg("metrics.histogram_observe", args.get(2))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    metrics.histogram_observe_with(&name, &labels, value);
     Ok(Value::Null)
 }
 
+/// Observe a summary value.
+///
+/// Summaries report quantiles over a sliding time window, configured with
+/// the `summary_quantiles`, `summary_max_age_ms` and `summary_age_buckets`
+/// options of the metrics module.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Value
+/// * `args[2]` - Labels map (optional)
+pub fn summary_observe(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("metrics.summary_observe: missing name")
+    })?;
+
+    let value = args.get(1).and_then(|v| v.as_float()).ok_or_else(|| {
+        fusabi_host::Error::host_function("metrics.summary_observe: missing value")
+    })?;
+
+    let labels = labels_arg("metrics.summary_observe", args.get(2))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    metrics.summary_observe_with(&name, &labels, value);
+    Ok(Value::Null)
+}
+
+/// Read summary statistics.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Labels map (optional)
+///
+/// # Returns
+///
+/// Map with `count`, `sum` and `quantiles` (quantile to value, over the
+/// current window), or null if the series has not been recorded
+pub fn summary_stats(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("metrics.summary_stats: missing name"))?;
+
+    let labels = labels_arg("metrics.summary_stats", args.get(1))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    Ok(metrics
+        .summary_stats_with(&name, &labels)
+        .map_or(Value::Null, |stats| stats.to_value()))
+}
+
+/// Read a counter value.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Labels map (optional)
+///
+/// # Returns
+///
+/// Current value, or 0 if the series has not been recorded
+pub fn counter_get(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("metrics.counter_get: missing name"))?;
+
+    let labels = labels_arg("metrics.counter_get", args.get(1))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    Ok(Value::Int(metrics.counter_get_with(&name, &labels) as i64))
+}
+
+/// Read a gauge value.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Labels map (optional)
+///
+/// # Returns
+///
+/// Current value, or 0.0 if the series has not been recorded
+pub fn gauge_get(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("metrics.gauge_get: missing name"))?;
+
+    let labels = labels_arg("metrics.gauge_get", args.get(1))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    Ok(Value::Float(metrics.gauge_get_with(&name, &labels)))
+}
+
+/// Read histogram statistics.
+///
+/// # Arguments
+///
+/// * `args[0]` - Metric name
+/// * `args[1]` - Labels map (optional)
+///
+/// # Returns
+///
+/// Map with `count`, `sum`, `mean`, `min`, `max`, `p50`, `p90` and `p99`,
+/// or null if the series has not been recorded
+pub fn histogram_stats(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("metrics.histogram_stats: missing name")
+    })?;
+
+    let labels = labels_arg("metrics.histogram_stats", args.get(1))?;
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    Ok(metrics
+        .histogram_stats_with(&name, &labels)
+        .map_or(Value::Null, |stats| stats.to_value()))
+}
+
+/// List recorded metric series.
+///
+/// With [namespacing](MetricNamespace) enabled, only the calling
+/// context's series are listed, under the names the script recorded.
+///
+/// # Arguments
+///
+/// * `args[0]` - Name prefix filter (optional)
+///
+/// # Returns
+///
+/// List of maps with `name`, `type` (`counter`, `gauge`, `histogram` or
+/// `summary`) and `labels`, sorted by name and labels
+pub fn list(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let prefix = args.first().and_then(|v| v.as_str()).unwrap_or("");
+
+    let snapshot = metrics.snapshot();
+    let mut series: Vec<(&str, &String)> = Vec::new();
+    series.extend(snapshot.counters.keys().map(|k| ("counter", k)));
+    series.extend(snapshot.gauges.keys().map(|k| ("gauge", k)));
+    series.extend(snapshot.histograms.keys().map(|k| ("histogram", k)));
+    series.extend(snapshot.summaries.keys().map(|k| ("summary", k)));
+    series.sort_by(|a, b| a.1.cmp(b.1).then(a.0.cmp(b.0)));
+
+    let entries = series
+        .into_iter()
+        .filter_map(|(kind, key)| {
+            let (name, labels) = parse_series_key(key)?;
+            let (name, labels) = metrics.unscope(ctx, name, labels)?;
+            if !name.starts_with(prefix) {
+                return None;
+            }
+            let mut entry = HashMap::new();
+            entry.insert("name".to_string(), Value::String(name.to_string()));
+            entry.insert("type".to_string(), Value::String(kind.to_string()));
+            entry.insert(
+                "labels".to_string(),
+                Value::Map(
+                    labels
+                        .into_iter()
+                        .map(|(k, v)| (k, Value::String(v)))
+                        .collect(),
+                ),
+            );
+            Some(Value::Map(entry))
+        })
+        .collect();
+
+    Ok(Value::List(entries))
+}
+
+/// Take a snapshot of the recorded metrics.
+///
+/// With [namespacing](MetricNamespace) enabled, only the calling context's
+/// series are included, under the names the script recorded.
+///
+/// # Returns
+///
+/// Map with `counters`, `counter_timestamps`, `gauges`, `histograms` and
+/// `summaries`, each keyed by series key
+pub fn snapshot(
+    metrics: &Arc<MetricsRegistry>,
+    _args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    Ok(metrics.context_snapshot(ctx).to_value())
+}
+
+/// Define a metric computed from other series whenever a snapshot is
+/// taken, such as an error ratio for dashboards.
+///
+/// # Arguments
+///
+/// * `args[0]` - Name of the derived metric, reported as a gauge
+/// * `args[1]` - Expression over existing metrics using `rate(x)`,
+///   `ratio(a, b)` and `sum(a, ...)`, e.g.
+///   `ratio(rate(errors_total), rate(requests_total))`; or null to remove
+///   the derived metric
+///
+/// Metric names are namespaced like those passed to the other `metrics`
+/// functions.
+pub fn define_derived(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    const FUNCTION: &str = "metrics.define_derived";
+
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function(format!("{}: missing name", FUNCTION)))?;
+
+    let scope = |name: &str, labels: Labels| metrics.scope(ctx, name, labels);
+    match args.get(1) {
+        None | Some(Value::Null) => {
+            let (name, labels) = scope(name, Labels::new());
+            metrics.remove_derived(&series_key(&name, &labels));
+            Ok(Value::Null)
+        }
+        Some(Value::String(expression)) => {
+            let metric = DerivedMetric::parse(name, expression)
+                .map_err(|e| fusabi_host::Error::host_function(format!("{}: {}", FUNCTION, e)))?;
+            metrics.add_derived(metric.map_series(&scope));
+            Ok(Value::Null)
+        }
+        Some(_) => Err(fusabi_host::Error::host_function(format!(
+            "{}: expression must be a string or null",
+            FUNCTION
+        ))),
+    }
+}
+
+/// Compare two snapshots.
+///
+/// # Arguments
+///
+/// * `args[0]` - Earlier snapshot, from `metrics.snapshot`
+/// * `args[1]` - Later snapshot (optional, defaults to the current values)
+///
+/// # Returns
+///
+/// Map with `counters` and `gauges` deltas and `histograms` and
+/// `summaries` entries holding `count` and `sum` deltas, keyed by series
+/// key. Unchanged series are omitted.
+pub fn diff(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let parse = |value: &Value| {
+        MetricsSnapshot::from_value(value)
+            .ok_or_else(|| fusabi_host::Error::host_function("metrics.diff: invalid snapshot"))
+    };
+
+    let before = parse(
+        args.first()
+            .ok_or_else(|| fusabi_host::Error::host_function("metrics.diff: missing snapshot"))?,
+    )?;
+    let after = match args.get(1) {
+        None | Some(Value::Null) => metrics.context_snapshot(ctx),
+        Some(value) => parse(value)?,
+    };
+
+    Ok(after.diff(&before).to_value())
+}
+
+/// Extract an optional labels map argument.
+///
+/// Label values may be strings, numbers, or booleans.
+pub(crate) fn labels_arg(function: &str, arg: Option<&Value>) -> fusabi_host::Result<Labels> {
+    let map = match arg {
+        None | Some(Value::Null) => return Ok(Labels::new()),
+        Some(Value::Map(map)) => map,
+        Some(_) => {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: labels must be a map",
+                function
+            )))
+        }
+    };
+
+    let mut labels = Labels::new();
+    for (key, value) in map {
+        if !is_valid_label_name(key) {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: invalid label name: {}",
+                function, key
+            )));
+        }
+        let value = match value {
+            Value::String(s) => s.clone(),
+            Value::Int(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => {
+                return Err(fusabi_host::Error::host_function(format!(
+                    "{}: label {} must be a string, number, or bool",
+                    function, key
+                )))
+            }
+        };
+        labels.insert(key.clone(), value);
+    }
+    Ok(labels)
+}
+
+/// A metric label set, kept sorted by label name.
+pub type Labels = BTreeMap<String, String>;
+
+/// Whether a label name is valid (`[a-zA-Z_][a-zA-Z0-9_]*`).
+fn is_valid_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Build the series key for a metric name and label set.
+///
+/// Labels are rendered sorted by name, with `\`, `"` and newlines escaped.
+/// An empty label set yields the bare name.
+pub fn series_key(name: &str, labels: &Labels) -> String {
+    if labels.is_empty() {
+        return name.to_string();
+    }
+
+    let rendered: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| {
+            let escaped = v
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", k, escaped)
+        })
+        .collect();
+    format!("{}{{{}}}", name, rendered.join(","))
+}
+
+/// Split a series key back into its metric name and labels.
+///
+/// Returns `None` if the label block is malformed.
+pub fn parse_series_key(key: &str) -> Option<(&str, Labels)> {
+    let Some(open) = key.find('{') else {
+        return Some((key, Labels::new()));
+    };
+    let name = &key[..open];
+    let body = key[open + 1..].strip_suffix('}')?;
+
+    let mut labels = Labels::new();
+    let mut chars = body.chars().peekable();
+    while chars.peek().is_some() {
+        let label: String = chars.by_ref().take_while(|c| *c != '=').collect();
+        if chars.next() != Some('"') {
+            return None;
+        }
+        let mut value = String::new();
+        loop {
+            match chars.next()? {
+                '"' => break,
+                '\\' => match chars.next()? {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+        labels.insert(label, value);
+        match chars.next() {
+            None => break,
+            Some(',') => {}
+            Some(_) => return None,
+        }
+    }
+    Some((name, labels))
+}
+
+/// A destination for metric values, such as a push gateway or OTLP collector.
+pub trait MetricsExporter: Send + Sync {
+    /// Exporter name, used in error messages.
+    fn name(&self) -> &str;
+
+    /// Called once when the exporter is added to a registry.
+    fn start(&self) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// Deliver a snapshot of the current metric values.
+    fn export(&self, snapshot: &MetricsSnapshot) -> crate::Result<()>;
+
+    /// Release resources after the final export.
+    fn shutdown(&self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+/// Point-in-time copy of all metric values.
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    /// Counter values by series key.
+    pub counters: HashMap<String, u64>,
+    /// Unix timestamps (milliseconds) of counters backfilled with
+    /// [`MetricsRegistry::counter_inc_at`], by series key. Exporters that
+    /// support explicit timestamps use these instead of the export time.
+    pub counter_timestamps: HashMap<String, i64>,
+    /// Gauge values by series key.
+    pub gauges: HashMap<String, f64>,
+    /// Histogram statistics by series key.
+    pub histograms: HashMap<String, HistogramStats>,
+    /// Summary statistics by series key.
+    pub summaries: HashMap<String, SummaryStats>,
+}
+
+impl MetricsSnapshot {
+    /// Compute the changes from `before` to this snapshot.
+    ///
+    /// Only series whose value changed, or that exist in just one of the
+    /// snapshots, appear in the diff.
+    pub fn diff(&self, before: &MetricsSnapshot) -> MetricsDiff {
+        let mut diff = MetricsDiff::default();
+
+        for key in self.counters.keys().chain(before.counters.keys()) {
+            let after = self.counters.get(key).copied().unwrap_or(0) as i64;
+            let prior = before.counters.get(key).copied().unwrap_or(0) as i64;
+            if after != prior {
+                diff.counters.insert(key.clone(), after - prior);
+            }
+        }
+
+        for key in self.gauges.keys().chain(before.gauges.keys()) {
+            let after = self.gauges.get(key);
+            let prior = before.gauges.get(key);
+            if after != prior {
+                let delta = after.copied().unwrap_or(0.0) - prior.copied().unwrap_or(0.0);
+                diff.gauges.insert(key.clone(), delta);
+            }
+        }
+
+        for key in self.histograms.keys().chain(before.histograms.keys()) {
+            let after = self.histograms.get(key);
+            let prior = before.histograms.get(key);
+            let count = |s: Option<&HistogramStats>| s.map_or(0, |s| s.count) as i64;
+            let sum = |s: Option<&HistogramStats>| s.map_or(0.0, |s| s.sum);
+            if count(after) != count(prior) || sum(after) != sum(prior) {
+                diff.histograms.insert(
+                    key.clone(),
+                    HistogramDelta {
+                        count: count(after) - count(prior),
+                        sum: sum(after) - sum(prior),
+                    },
+                );
+            }
+        }
+
+        for key in self.summaries.keys().chain(before.summaries.keys()) {
+            let after = self.summaries.get(key);
+            let prior = before.summaries.get(key);
+            let count = |s: Option<&SummaryStats>| s.map_or(0, |s| s.count) as i64;
+            let sum = |s: Option<&SummaryStats>| s.map_or(0.0, |s| s.sum);
+            if count(after) != count(prior) || sum(after) != sum(prior) {
+                diff.summaries.insert(
+                    key.clone(),
+                    HistogramDelta {
+                        count: count(after) - count(prior),
+                        sum: sum(after) - sum(prior),
+                    },
+                );
+            }
+        }
+
+        diff
+    }
+
+    /// Convert to a Value map with `counters`, `counter_timestamps`,
+    /// `gauges`, `histograms` and `summaries` maps keyed by series key.
+    pub fn to_value(&self) -> Value {
+        let mut m = HashMap::new();
+        m.insert(
+            "counters".into(),
+            Value::Map(
+                self.counters
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Int(*v as i64)))
+                    .collect(),
+            ),
+        );
+        m.insert(
+            "counter_timestamps".into(),
+            Value::Map(
+                self.counter_timestamps
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Int(*v)))
+                    .collect(),
+            ),
+        );
+        m.insert(
+            "gauges".into(),
+            Value::Map(
+                self.gauges
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Float(*v)))
+                    .collect(),
+            ),
+        );
+        m.insert(
+            "histograms".into(),
+            Value::Map(
+                self.histograms
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_value()))
+                    .collect(),
+            ),
+        );
+        m.insert(
+            "summaries".into(),
+            Value::Map(
+                self.summaries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_value()))
+                    .collect(),
+            ),
+        );
+        Value::Map(m)
+    }
+
+    /// Parse a snapshot produced by [`to_value`](Self::to_value).
+    ///
+    /// Returns `None` if the value is not a snapshot map.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let map = value.as_map()?;
+        let section = |name: &str| match map.get(name) {
+            None => Some(HashMap::new()),
+            Some(section) => section.as_map().cloned(),
+        };
+        let number = |v: &Value| v.as_float().or_else(|| v.as_int().map(|i| i as f64));
+
+        let mut snapshot = MetricsSnapshot::default();
+        for (key, value) in section("counters")? {
+            snapshot.counters.insert(key, value.as_int()? as u64);
+        }
+        for (key, value) in section("counter_timestamps")? {
+            snapshot.counter_timestamps.insert(key, value.as_int()?);
+        }
+        for (key, value) in section("gauges")? {
+            snapshot.gauges.insert(key, number(&value)?);
+        }
+        for (key, value) in section("histograms")? {
+            snapshot
+                .histograms
+                .insert(key, HistogramStats::from_value(&value)?);
+        }
+        for (key, value) in section("summaries")? {
+            snapshot
+                .summaries
+                .insert(key, SummaryStats::from_value(&value)?);
+        }
+        Some(snapshot)
+    }
+}
+
+/// Changes between two [`MetricsSnapshot`]s, by series key.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsDiff {
+    /// Counter increments. Negative if the registry was cleared.
+    pub counters: HashMap<String, i64>,
+    /// Gauge changes.
+    pub gauges: HashMap<String, f64>,
+    /// Histogram observation count and sum changes.
+    pub histograms: HashMap<String, HistogramDelta>,
+    /// Summary observation count and sum changes.
+    pub summaries: HashMap<String, HistogramDelta>,
+}
+
+/// Change in a histogram or summary series between two snapshots.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HistogramDelta {
+    /// Observations added.
+    pub count: i64,
+    /// Sum of the observations added.
+    pub sum: f64,
+}
+
+impl MetricsDiff {
+    /// Whether no series changed.
+    pub fn is_empty(&self) -> bool {
+        self.counters.is_empty()
+            && self.gauges.is_empty()
+            && self.histograms.is_empty()
+            && self.summaries.is_empty()
+    }
+
+    /// Increment of a counter series, or 0 if it did not change.
+    pub fn counter(&self, name: &str, labels: &Labels) -> i64 {
+        self.counters
+            .get(&series_key(name, labels))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Change of a gauge series, or 0.0 if it did not change.
+    pub fn gauge(&self, name: &str, labels: &Labels) -> f64 {
+        self.gauges
+            .get(&series_key(name, labels))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Change of a histogram series, or a zero delta if it did not change.
+    pub fn histogram(&self, name: &str, labels: &Labels) -> HistogramDelta {
+        self.histograms
+            .get(&series_key(name, labels))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Change of a summary series, or a zero delta if it did not change.
+    pub fn summary(&self, name: &str, labels: &Labels) -> HistogramDelta {
+        self.summaries
+            .get(&series_key(name, labels))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Convert to a Value map with `counters`, `gauges`, `histograms` and
+    /// `summaries` maps keyed by series key. Histogram and summary entries
+    /// hold `count` and `sum`.
+    pub fn to_value(&self) -> Value {
+        let mut m = HashMap::new();
+        m.insert(
+            "counters".into(),
+            Value::Map(
+                self.counters
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Int(*v)))
+                    .collect(),
+            ),
+        );
+        m.insert(
+            "gauges".into(),
+            Value::Map(
+                self.gauges
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Float(*v)))
+                    .collect(),
+            ),
+        );
+        let deltas = |deltas: &HashMap<String, HistogramDelta>| {
+            Value::Map(
+                deltas
+                    .iter()
+                    .map(|(k, v)| {
+                        let mut delta = HashMap::new();
+                        delta.insert("count".to_string(), Value::Int(v.count));
+                        delta.insert("sum".to_string(), Value::Float(v.sum));
+                        (k.clone(), Value::Map(delta))
+                    })
+                    .collect(),
+            )
+        };
+        m.insert("histograms".into(), deltas(&self.histograms));
+        m.insert("summaries".into(), deltas(&self.summaries));
+        Value::Map(m)
+    }
+}
+
+/// How series recorded by scripts are attributed to the recording context.
+///
+/// The context identity is the string stored under the registry's
+/// [identity key](MetricsRegistry::with_identity_key) with
+/// `ExecutionContext::set_custom` (default `script`), falling back to
+/// `engine-<engine_id>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MetricNamespace {
+    /// Record names as given.
+    #[default]
+    None,
+    /// Prefix names with `<identity>.`.
+    Prefix,
+    /// Add a label with the given name holding the identity. Scripts cannot
+    /// override this label.
+    Label(String),
+}
+
+impl MetricNamespace {
+    /// Read the `namespace` option (`none`, `prefix` or `label`) and, for
+    /// `label`, the `namespace_label` option (default `script`).
+    pub fn from_options(options: &HashMap<String, String>) -> crate::Result<Self> {
+        match options.get("namespace").map(String::as_str) {
+            None | Some("none") => Ok(Self::None),
+            Some("prefix") => Ok(Self::Prefix),
+            Some("label") => {
+                let label = options
+                    .get("namespace_label")
+                    .cloned()
+                    .unwrap_or_else(|| "script".to_string());
+                if !is_valid_label_name(&label) {
+                    return Err(crate::Error::invalid_argument(format!(
+                        "invalid namespace_label: {}",
+                        label
+                    )));
+                }
+                Ok(Self::Label(label))
+            }
+            Some(other) => Err(crate::Error::invalid_argument(format!(
+                "unknown metrics namespace: {}",
+                other
+            ))),
+        }
+    }
+}
+
 /// A simple metrics registry.
 pub struct MetricsRegistry {
     counters: RwLock<HashMap<String, AtomicU64>>,
+    counter_timestamps: RwLock<HashMap<String, i64>>,
     gauges: RwLock<HashMap<String, AtomicI64>>,
     histograms: RwLock<HashMap<String, Histogram>>,
+    summaries: RwLock<HashMap<String, Summary>>,
+    derived: RwLock<Vec<DerivedMetric>>,
+    summary_config: SummaryConfig,
+    exporters: RwLock<Vec<Arc<dyn MetricsExporter>>>,
+    flusher: Mutex<Option<Flusher>>,
+    shut_down: AtomicBool,
+    namespace: MetricNamespace,
+    identity_key: String,
+}
+
+/// Background thread flushing a registry on an interval.
+struct Flusher {
+    // Dropping the sender wakes and stops the thread.
+    stop: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
 }
 
 impl MetricsRegistry {
@@ -72,69 +1004,297 @@ impl MetricsRegistry {
     pub fn new() -> Self {
         Self {
             counters: RwLock::new(HashMap::new()),
+            counter_timestamps: RwLock::new(HashMap::new()),
             gauges: RwLock::new(HashMap::new()),
             histograms: RwLock::new(HashMap::new()),
+            summaries: RwLock::new(HashMap::new()),
+            derived: RwLock::new(Vec::new()),
+            summary_config: SummaryConfig::default(),
+            exporters: RwLock::new(Vec::new()),
+            flusher: Mutex::new(None),
+            shut_down: AtomicBool::new(false),
+            namespace: MetricNamespace::None,
+            identity_key: "script".to_string(),
+        }
+    }
+
+    /// Attribute series recorded through host functions to the calling
+    /// context.
+    pub fn with_namespace(mut self, namespace: MetricNamespace) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    /// Set the quantiles and window of summaries created from now on.
+    pub fn with_summary_config(mut self, config: SummaryConfig) -> Self {
+        self.summary_config = config;
+        self
+    }
+
+    /// Settings of new summaries.
+    pub fn summary_config(&self) -> &SummaryConfig {
+        &self.summary_config
+    }
+
+    /// Set the `ExecutionContext` custom value holding the context identity.
+    pub fn with_identity_key(mut self, key: impl Into<String>) -> Self {
+        self.identity_key = key.into();
+        self
+    }
+
+    /// Get the namespacing mode.
+    pub fn namespace(&self) -> &MetricNamespace {
+        &self.namespace
+    }
+
+    /// Identity of the context recording a metric.
+    pub fn context_identity(&self, ctx: &ExecutionContext) -> String {
+        match ctx.get_custom(&self.identity_key) {
+            Some(Value::String(id)) if !id.is_empty() => id,
+            _ => format!("engine-{}", ctx.engine_id),
+        }
+    }
+
+    /// Apply the namespacing mode to a series recorded by `ctx`.
+    pub fn scope(
+        &self,
+        ctx: &ExecutionContext,
+        name: &str,
+        mut labels: Labels,
+    ) -> (String, Labels) {
+        match &self.namespace {
+            MetricNamespace::None => (name.to_string(), labels),
+            MetricNamespace::Prefix => (format!("{}.{}", self.context_identity(ctx), name), labels),
+            MetricNamespace::Label(label) => {
+                labels.insert(label.clone(), self.context_identity(ctx));
+                (name.to_string(), labels)
+            }
+        }
+    }
+
+    /// Reverse [`scope`](Self::scope), returning `None` for series recorded
+    /// by another context.
+    fn unscope<'a>(
+        &self,
+        ctx: &ExecutionContext,
+        name: &'a str,
+        mut labels: Labels,
+    ) -> Option<(&'a str, Labels)> {
+        match &self.namespace {
+            MetricNamespace::None => Some((name, labels)),
+            MetricNamespace::Prefix => {
+                let prefix = format!("{}.", self.context_identity(ctx));
+                Some((name.strip_prefix(prefix.as_str())?, labels))
+            }
+            MetricNamespace::Label(label) => {
+                let owner = labels.remove(label)?;
+                (owner == self.context_identity(ctx)).then_some((name, labels))
+            }
+        }
+    }
+
+    /// Snapshot of the series visible to `ctx`, keyed by the names the
+    /// context recorded them under.
+    pub fn context_snapshot(&self, ctx: &ExecutionContext) -> MetricsSnapshot {
+        let snapshot = self.snapshot();
+        if self.namespace == MetricNamespace::None {
+            return snapshot;
+        }
+
+        let rekey = |key: &str| {
+            let (name, labels) = parse_series_key(key)?;
+            let (name, labels) = self.unscope(ctx, name, labels)?;
+            Some(series_key(name, &labels))
+        };
+        fn filter<T>(
+            map: HashMap<String, T>,
+            rekey: impl Fn(&str) -> Option<String>,
+        ) -> HashMap<String, T> {
+            map.into_iter()
+                .filter_map(|(key, value)| Some((rekey(&key)?, value)))
+                .collect()
+        }
+
+        MetricsSnapshot {
+            counters: filter(snapshot.counters, rekey),
+            counter_timestamps: filter(snapshot.counter_timestamps, rekey),
+            gauges: filter(snapshot.gauges, rekey),
+            histograms: filter(snapshot.histograms, rekey),
+            summaries: filter(snapshot.summaries, rekey),
         }
     }
 
     /// Increment a counter.
     pub fn counter_inc(&self, name: &str, value: u64) {
+        self.counter_inc_key(name, value);
+    }
+
+    /// Increment a labeled counter series.
+    pub fn counter_inc_with(&self, name: &str, labels: &Labels, value: u64) {
+        self.counter_inc_key(&series_key(name, labels), value);
+    }
+
+    fn counter_inc_key(&self, key: &str, value: u64) {
         let counters = self.counters.read();
-        if let Some(counter) = counters.get(name) {
+        if let Some(counter) = counters.get(key) {
             counter.fetch_add(value, Ordering::Relaxed);
         } else {
             drop(counters);
             let mut counters = self.counters.write();
             counters
-                .entry(name.to_string())
+                .entry(key.to_string())
                 .or_insert_with(|| AtomicU64::new(0))
                 .fetch_add(value, Ordering::Relaxed);
         }
     }
 
+    /// Increment a counter for a past (or explicit) point in time.
+    ///
+    /// `timestamp_ms` is a Unix timestamp in milliseconds. The series keeps
+    /// the latest timestamp it has been incremented at.
+    pub fn counter_inc_at(&self, name: &str, value: u64, timestamp_ms: i64) {
+        self.counter_inc_at_with(name, &Labels::new(), value, timestamp_ms);
+    }
+
+    /// Increment a labeled counter series for an explicit point in time.
+    pub fn counter_inc_at_with(&self, name: &str, labels: &Labels, value: u64, timestamp_ms: i64) {
+        let key = series_key(name, labels);
+        self.counter_inc_key(&key, value);
+        let mut timestamps = self.counter_timestamps.write();
+        let latest = timestamps.entry(key).or_insert(timestamp_ms);
+        *latest = (*latest).max(timestamp_ms);
+    }
+
     /// Get a counter value.
     pub fn counter_get(&self, name: &str) -> u64 {
+        self.counter_get_key(name)
+    }
+
+    /// Get a labeled counter series value.
+    pub fn counter_get_with(&self, name: &str, labels: &Labels) -> u64 {
+        self.counter_get_key(&series_key(name, labels))
+    }
+
+    fn counter_get_key(&self, key: &str) -> u64 {
         self.counters
             .read()
-            .get(name)
+            .get(key)
             .map(|c| c.load(Ordering::Relaxed))
             .unwrap_or(0)
     }
 
     /// Set a gauge value.
     pub fn gauge_set(&self, name: &str, value: f64) {
+        self.gauge_set_key(name, value);
+    }
+
+    /// Set a labeled gauge series value.
+    pub fn gauge_set_with(&self, name: &str, labels: &Labels, value: f64) {
+        self.gauge_set_key(&series_key(name, labels), value);
+    }
+
+    fn gauge_set_key(&self, key: &str, value: f64) {
         let bits = value.to_bits() as i64;
         let gauges = self.gauges.read();
-        if let Some(gauge) = gauges.get(name) {
+        if let Some(gauge) = gauges.get(key) {
             gauge.store(bits, Ordering::Relaxed);
         } else {
             drop(gauges);
             let mut gauges = self.gauges.write();
             gauges
-                .entry(name.to_string())
+                .entry(key.to_string())
                 .or_insert_with(|| AtomicI64::new(0))
                 .store(bits, Ordering::Relaxed);
         }
     }
 
+    /// Atomically add to a gauge, returning the new value.
+    pub fn gauge_add(&self, name: &str, delta: f64) -> f64 {
+        self.gauge_add_key(name, delta)
+    }
+
+    /// Atomically add to a labeled gauge series, returning the new value.
+    pub fn gauge_add_with(&self, name: &str, labels: &Labels, delta: f64) -> f64 {
+        self.gauge_add_key(&series_key(name, labels), delta)
+    }
+
+    /// Atomically subtract from a gauge, returning the new value.
+    pub fn gauge_sub(&self, name: &str, delta: f64) -> f64 {
+        self.gauge_add_key(name, -delta)
+    }
+
+    /// Atomically subtract from a labeled gauge series, returning the new
+    /// value.
+    pub fn gauge_sub_with(&self, name: &str, labels: &Labels, delta: f64) -> f64 {
+        self.gauge_add_key(&series_key(name, labels), -delta)
+    }
+
+    fn gauge_add_key(&self, key: &str, delta: f64) -> f64 {
+        let add = |gauge: &AtomicI64| {
+            let mut current = gauge.load(Ordering::Relaxed);
+            loop {
+                let next = f64::from_bits(current as u64) + delta;
+                match gauge.compare_exchange_weak(
+                    current,
+                    next.to_bits() as i64,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return next,
+                    Err(actual) => current = actual,
+                }
+            }
+        };
+
+        let gauges = self.gauges.read();
+        if let Some(gauge) = gauges.get(key) {
+            add(gauge)
+        } else {
+            drop(gauges);
+            let mut gauges = self.gauges.write();
+            // 0.0 is stored as all-zero bits.
+            add(gauges
+                .entry(key.to_string())
+                .or_insert_with(|| AtomicI64::new(0)))
+        }
+    }
+
     /// Get a gauge value.
     pub fn gauge_get(&self, name: &str) -> f64 {
+        self.gauge_get_key(name)
+    }
+
+    /// Get a labeled gauge series value.
+    pub fn gauge_get_with(&self, name: &str, labels: &Labels) -> f64 {
+        self.gauge_get_key(&series_key(name, labels))
+    }
+
+    fn gauge_get_key(&self, key: &str) -> f64 {
         self.gauges
             .read()
-            .get(name)
+            .get(key)
             .map(|g| f64::from_bits(g.load(Ordering::Relaxed) as u64))
             .unwrap_or(0.0)
     }
 
     /// Observe a histogram value.
     pub fn histogram_observe(&self, name: &str, value: f64) {
+        self.histogram_observe_key(name, value);
+    }
+
+    /// Observe a value in a labeled histogram series.
+    pub fn histogram_observe_with(&self, name: &str, labels: &Labels, value: f64) {
+        self.histogram_observe_key(&series_key(name, labels), value);
+    }
+
+    fn histogram_observe_key(&self, key: &str, value: f64) {
         let histograms = self.histograms.read();
-        if let Some(histogram) = histograms.get(name) {
+        if let Some(histogram) = histograms.get(key) {
             histogram.observe(value);
         } else {
             drop(histograms);
             let mut histograms = self.histograms.write();
-            let histogram = histograms.entry(name.to_string()).or_default();
+            let histogram = histograms.entry(key.to_string()).or_default();
             histogram.observe(value);
         }
     }
@@ -144,20 +1304,231 @@ impl MetricsRegistry {
         self.histograms.read().get(name).map(|h| h.stats())
     }
 
-    /// Get all metric names.
+    /// Get statistics for a labeled histogram series.
+    pub fn histogram_stats_with(&self, name: &str, labels: &Labels) -> Option<HistogramStats> {
+        self.histogram_stats(&series_key(name, labels))
+    }
+
+    /// Get the last `n` observations of a histogram, oldest first.
+    pub fn histogram_recent(&self, name: &str, n: usize) -> Option<Vec<f64>> {
+        self.histograms.read().get(name).map(|h| h.recent(n))
+    }
+
+    /// Get the last `n` observations of a labeled histogram series.
+    pub fn histogram_recent_with(&self, name: &str, labels: &Labels, n: usize) -> Option<Vec<f64>> {
+        self.histogram_recent(&series_key(name, labels), n)
+    }
+
+    /// Observe a summary value.
+    pub fn summary_observe(&self, name: &str, value: f64) {
+        self.summary_observe_key(name, value);
+    }
+
+    /// Observe a value in a labeled summary series.
+    pub fn summary_observe_with(&self, name: &str, labels: &Labels, value: f64) {
+        self.summary_observe_key(&series_key(name, labels), value);
+    }
+
+    fn summary_observe_key(&self, key: &str, value: f64) {
+        let summaries = self.summaries.read();
+        if let Some(summary) = summaries.get(key) {
+            summary.observe(value);
+        } else {
+            drop(summaries);
+            let mut summaries = self.summaries.write();
+            let summary = summaries
+                .entry(key.to_string())
+                .or_insert_with(|| Summary::new(self.summary_config.clone()));
+            summary.observe(value);
+        }
+    }
+
+    /// Get summary statistics.
+    pub fn summary_stats(&self, name: &str) -> Option<SummaryStats> {
+        self.summaries.read().get(name).map(|s| s.stats())
+    }
+
+    /// Get statistics for a labeled summary series.
+    pub fn summary_stats_with(&self, name: &str, labels: &Labels) -> Option<SummaryStats> {
+        self.summary_stats(&series_key(name, labels))
+    }
+
+    /// Get all series keys.
     pub fn names(&self) -> Vec<String> {
         let mut names = Vec::new();
         names.extend(self.counters.read().keys().cloned());
         names.extend(self.gauges.read().keys().cloned());
         names.extend(self.histograms.read().keys().cloned());
+        names.extend(self.summaries.read().keys().cloned());
         names
     }
 
     /// Clear all metrics.
     pub fn clear(&self) {
         self.counters.write().clear();
+        self.counter_timestamps.write().clear();
         self.gauges.write().clear();
         self.histograms.write().clear();
+        self.summaries.write().clear();
+    }
+
+    /// Add a derived metric, replacing any with the same name.
+    pub fn add_derived(&self, metric: DerivedMetric) {
+        let mut derived = self.derived.write();
+        match derived.iter_mut().find(|d| d.name() == metric.name()) {
+            Some(existing) => *existing = metric,
+            None => derived.push(metric),
+        }
+    }
+
+    /// Define a derived metric from an expression; see [`derived`].
+    pub fn define_derived(&self, name: &str, expression: &str) -> crate::Result<()> {
+        self.add_derived(DerivedMetric::parse(name, expression)?);
+        Ok(())
+    }
+
+    /// Remove a derived metric, returning whether it existed.
+    pub fn remove_derived(&self, name: &str) -> bool {
+        let mut derived = self.derived.write();
+        let before = derived.len();
+        derived.retain(|d| d.name() != name);
+        derived.len() != before
+    }
+
+    /// Names of the derived metrics, in definition order.
+    pub fn derived_names(&self) -> Vec<String> {
+        self.derived
+            .read()
+            .iter()
+            .map(|d| d.name().to_string())
+            .collect()
+    }
+
+    /// Take a snapshot of all metric values.
+    ///
+    /// Derived metrics are evaluated in definition order and included as
+    /// gauges.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let mut snapshot = self.base_snapshot();
+        for metric in self.derived.read().iter() {
+            if let Some(value) = metric.evaluate(&snapshot) {
+                snapshot.gauges.insert(metric.name().to_string(), value);
+            }
+        }
+        snapshot
+    }
+
+    fn base_snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            counters: self
+                .counters
+                .read()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.load(Ordering::Relaxed)))
+                .collect(),
+            counter_timestamps: self.counter_timestamps.read().clone(),
+            gauges: self
+                .gauges
+                .read()
+                .iter()
+                .map(|(k, v)| (k.clone(), f64::from_bits(v.load(Ordering::Relaxed) as u64)))
+                .collect(),
+            histograms: self
+                .histograms
+                .read()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.stats()))
+                .collect(),
+            summaries: self
+                .summaries
+                .read()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.stats()))
+                .collect(),
+        }
+    }
+
+    /// Add an exporter, starting it immediately.
+    pub fn add_exporter(&self, exporter: Arc<dyn MetricsExporter>) -> crate::Result<()> {
+        exporter.start()?;
+        self.exporters.write().push(exporter);
+        Ok(())
+    }
+
+    /// Export the current values to every exporter.
+    ///
+    /// All exporters are attempted; the first error is returned.
+    pub fn flush(&self) -> crate::Result<()> {
+        let exporters = self.exporters.read().clone();
+        if exporters.is_empty() {
+            return Ok(());
+        }
+
+        let snapshot = self.snapshot();
+        let mut first_error = None;
+        for exporter in &exporters {
+            if let Err(e) = exporter.export(&snapshot) {
+                tracing::warn!(exporter = exporter.name(), error = %e, "metrics export failed");
+                first_error.get_or_insert(e);
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Flush to the exporters every `interval` on a background thread.
+    ///
+    /// Replaces any previous interval. The thread stops on
+    /// [`shutdown`](Self::shutdown) or when the registry is dropped; export
+    /// errors are logged and do not stop it.
+    pub fn start_flush_interval(self: &Arc<Self>, interval: Duration) -> crate::Result<()> {
+        let registry = Arc::downgrade(self);
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::Builder::new()
+            .name("fusabi-metrics-flush".to_string())
+            .spawn(move || {
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    let Some(registry) = registry.upgrade() else {
+                        break;
+                    };
+                    // Failures are already logged per exporter.
+                    let _ = registry.flush();
+                }
+            })?;
+
+        let previous = self.flusher.lock().replace(Flusher { stop, thread });
+        if let Some(previous) = previous {
+            drop(previous.stop);
+            let _ = previous.thread.join();
+        }
+        Ok(())
+    }
+
+    /// Flush final values and shut down all exporters.
+    ///
+    /// Stops any flush interval first. Subsequent calls are no-ops.
+    pub fn shutdown(&self) -> crate::Result<()> {
+        if self.shut_down.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        if let Some(flusher) = self.flusher.lock().take() {
+            drop(flusher.stop);
+            // The flush thread never calls shutdown, so this cannot deadlock.
+            let _ = flusher.thread.join();
+        }
+
+        let flushed = self.flush();
+        let exporters = std::mem::take(&mut *self.exporters.write());
+        let mut first_error = flushed.err();
+        for exporter in &exporters {
+            if let Err(e) = exporter.shutdown() {
+                tracing::warn!(exporter = exporter.name(), error = %e, "metrics exporter shutdown failed");
+                first_error.get_or_insert(e);
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
     }
 }
 
@@ -185,6 +1556,12 @@ impl Histogram {
         self.values.write().push(value);
     }
 
+    /// Get the last `n` observations, oldest first.
+    pub fn recent(&self, n: usize) -> Vec<f64> {
+        let values = self.values.read();
+        values[values.len().saturating_sub(n)..].to_vec()
+    }
+
     /// Get histogram statistics.
     pub fn stats(&self) -> HistogramStats {
         let values = self.values.read();
@@ -236,6 +1613,41 @@ fn percentile(sorted: &[f64], p: f64) -> f64 {
     sorted[index.min(sorted.len() - 1)]
 }
 
+impl HistogramStats {
+    /// Convert to a Value map.
+    pub fn to_value(&self) -> Value {
+        let mut m = HashMap::new();
+        m.insert("count".into(), Value::Int(self.count as i64));
+        m.insert("sum".into(), Value::Float(self.sum));
+        m.insert("mean".into(), Value::Float(self.mean));
+        m.insert("min".into(), Value::Float(self.min));
+        m.insert("max".into(), Value::Float(self.max));
+        m.insert("p50".into(), Value::Float(self.p50));
+        m.insert("p90".into(), Value::Float(self.p90));
+        m.insert("p99".into(), Value::Float(self.p99));
+        Value::Map(m)
+    }
+
+    /// Parse statistics produced by [`to_value`](Self::to_value).
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let map = value.as_map()?;
+        let field = |name: &str| {
+            let v = map.get(name)?;
+            v.as_float().or_else(|| v.as_int().map(|i| i as f64))
+        };
+        Some(Self {
+            count: map.get("count")?.as_int()? as u64,
+            sum: field("sum")?,
+            mean: field("mean")?,
+            min: field("min")?,
+            max: field("max")?,
+            p50: field("p50")?,
+            p90: field("p90")?,
+            p99: field("p99")?,
+        })
+    }
+}
+
 /// Histogram statistics.
 #[derive(Debug, Clone, Default)]
 pub struct HistogramStats {
@@ -300,8 +1712,13 @@ mod tests {
     fn test_counter_inc() {
         let ctx = create_test_ctx();
 
-        counter_inc(&[Value::String("test_counter".into())], &ctx).unwrap();
-        counter_inc(&[Value::String("test_counter".into()), Value::Int(5)], &ctx).unwrap();
+        counter_inc(global(), &[Value::String("test_counter".into())], &ctx).unwrap();
+        counter_inc(
+            global(),
+            &[Value::String("test_counter".into()), Value::Int(5)],
+            &ctx,
+        )
+        .unwrap();
 
         let value = METRICS.counter_get("test_counter");
         assert!(value >= 6); // At least 1 + 5
@@ -312,6 +1729,7 @@ mod tests {
         let ctx = create_test_ctx();
 
         gauge_set(
+            global(),
             &[Value::String("test_gauge".into()), Value::Float(42.5)],
             &ctx,
         )
@@ -327,6 +1745,7 @@ mod tests {
 
         for i in 1..=10 {
             histogram_observe(
+                global(),
                 &[
                     Value::String("test_histogram".into()),
                     Value::Float(i as f64),
@@ -360,4 +1779,495 @@ mod tests {
         let stats = registry.histogram_stats("hist1").unwrap();
         assert_eq!(stats.count, 3);
     }
+
+    #[test]
+    fn test_labeled_series() {
+        let ctx = create_test_ctx();
+        let labels = |pairs: &[(&str, &str)]| {
+            let mut map = HashMap::new();
+            for (k, v) in pairs {
+                map.insert(k.to_string(), Value::String(v.to_string()));
+            }
+            Value::Map(map)
+        };
+
+        counter_inc(
+            global(),
+            &[
+                Value::String("labeled_requests_total".into()),
+                Value::Int(2),
+                labels(&[("status", "500"), ("route", "/api")]),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        counter_inc(
+            global(),
+            &[
+                Value::String("labeled_requests_total".into()),
+                labels(&[("route", "/api"), ("status", "500")]),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        counter_inc(
+            global(),
+            &[Value::String("labeled_requests_total".into())],
+            &ctx,
+        )
+        .unwrap();
+
+        let mut expected = Labels::new();
+        expected.insert("route".into(), "/api".into());
+        expected.insert("status".into(), "500".into());
+        assert_eq!(
+            METRICS.counter_get_with("labeled_requests_total", &expected),
+            3
+        );
+        let key = series_key("labeled_requests_total", &expected);
+        assert_eq!(key, r#"labeled_requests_total{route="/api",status="500"}"#);
+        assert!(METRICS.snapshot().counters.contains_key(&key));
+        assert!(METRICS.counter_get("labeled_requests_total") >= 1);
+
+        let bad = counter_inc(
+            global(),
+            &[
+                Value::String("labeled_requests_total".into()),
+                Value::Int(1),
+                labels(&[("bad-name", "x")]),
+            ],
+            &ctx,
+        );
+        assert!(bad.is_err());
+
+        gauge_set(
+            global(),
+            &[
+                Value::String("labeled_queue_depth".into()),
+                Value::Int(7),
+                labels(&[("queue", "jobs")]),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        let mut queue = Labels::new();
+        queue.insert("queue".into(), "jobs".into());
+        assert!((METRICS.gauge_get_with("labeled_queue_depth", &queue) - 7.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_series_key_round_trip() {
+        let mut labels = Labels::new();
+        labels.insert("path".into(), "C:\\tmp \"x\"\nnext".into());
+        labels.insert("code".into(), "200".into());
+
+        let key = series_key("http_requests", &labels);
+        let (name, parsed) = parse_series_key(&key).unwrap();
+        assert_eq!(name, "http_requests");
+        assert_eq!(parsed, labels);
+
+        assert_eq!(parse_series_key("plain"), Some(("plain", Labels::new())));
+        assert_eq!(parse_series_key("broken{a=1}"), None);
+    }
+
+    struct RecordingExporter {
+        exports: parking_lot::Mutex<Vec<MetricsSnapshot>>,
+        shutdowns: AtomicU64,
+    }
+
+    impl MetricsExporter for RecordingExporter {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn export(&self, snapshot: &MetricsSnapshot) -> crate::Result<()> {
+            self.exports.lock().push(snapshot.clone());
+            Ok(())
+        }
+
+        fn shutdown(&self) -> crate::Result<()> {
+            self.shutdowns.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_flush_and_shutdown() {
+        let registry = MetricsRegistry::new();
+        let exporter = Arc::new(RecordingExporter {
+            exports: parking_lot::Mutex::new(Vec::new()),
+            shutdowns: AtomicU64::new(0),
+        });
+        registry.add_exporter(exporter.clone()).unwrap();
+
+        registry.counter_inc("requests", 2);
+        registry.flush().unwrap();
+        registry.counter_inc("requests", 1);
+        registry.shutdown().unwrap();
+        registry.shutdown().unwrap();
+
+        let exports = exporter.exports.lock();
+        assert_eq!(exports.len(), 2);
+        assert_eq!(exports[0].counters.get("requests"), Some(&2));
+        assert_eq!(exports[1].counters.get("requests"), Some(&3));
+        assert_eq!(exporter.shutdowns.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_read_back() {
+        let ctx = create_test_ctx();
+        let name = |n: &str| Value::String(n.into());
+
+        counter_inc(
+            global(),
+            &[name("readback_errors_total"), Value::Int(4)],
+            &ctx,
+        )
+        .unwrap();
+        assert!(
+            counter_get(global(), &[name("readback_errors_total")], &ctx)
+                .unwrap()
+                .as_int()
+                .unwrap()
+                >= 4
+        );
+        assert_eq!(
+            counter_get(global(), &[name("readback_never_recorded")], &ctx).unwrap(),
+            Value::Int(0)
+        );
+
+        let mut labels = HashMap::new();
+        labels.insert("pool".to_string(), Value::String("db".into()));
+        gauge_set(
+            global(),
+            &[
+                name("readback_pool_size"),
+                Value::Float(8.0),
+                Value::Map(labels.clone()),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            gauge_get(
+                global(),
+                &[name("readback_pool_size"), Value::Map(labels)],
+                &ctx
+            )
+            .unwrap(),
+            Value::Float(8.0)
+        );
+
+        histogram_observe(
+            global(),
+            &[name("readback_latency"), Value::Float(2.0)],
+            &ctx,
+        )
+        .unwrap();
+        let stats = histogram_stats(global(), &[name("readback_latency")], &ctx).unwrap();
+        assert_eq!(stats.as_map().unwrap().get("count"), Some(&Value::Int(1)));
+        assert_eq!(
+            histogram_stats(global(), &[name("readback_missing")], &ctx).unwrap(),
+            Value::Null
+        );
+
+        let listed = list(global(), &[name("readback_")], &ctx).unwrap();
+        let listed = listed.as_list().unwrap();
+        assert_eq!(listed.len(), 3);
+        let latency = listed[1].as_map().unwrap();
+        assert_eq!(latency.get("name"), Some(&name("readback_latency")));
+        assert_eq!(latency.get("type"), Some(&name("histogram")));
+        let pool = listed[2].as_map().unwrap();
+        assert_eq!(pool.get("type"), Some(&name("gauge")));
+        assert_eq!(
+            pool.get("labels").unwrap().as_map().unwrap().get("pool"),
+            Some(&name("db"))
+        );
+    }
+
+    #[test]
+    fn test_flush_interval() {
+        let registry = Arc::new(MetricsRegistry::new());
+        let exporter = Arc::new(RecordingExporter {
+            exports: parking_lot::Mutex::new(Vec::new()),
+            shutdowns: AtomicU64::new(0),
+        });
+        registry.add_exporter(exporter.clone()).unwrap();
+        registry.counter_inc("ticks", 1);
+
+        registry
+            .start_flush_interval(Duration::from_millis(10))
+            .unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while exporter.exports.lock().len() < 2 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert!(exporter.exports.lock().len() >= 2);
+
+        registry.shutdown().unwrap();
+        let exported = exporter.exports.lock().len();
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(exporter.exports.lock().len(), exported);
+        assert_eq!(exporter.shutdowns.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_registries_are_isolated() {
+        let ctx = create_test_ctx();
+        let a = Arc::new(MetricsRegistry::new());
+        let b = Arc::new(MetricsRegistry::new());
+
+        counter_inc(&a, &[Value::String("isolated_total".into())], &ctx).unwrap();
+        assert_eq!(a.counter_get("isolated_total"), 1);
+        assert_eq!(b.counter_get("isolated_total"), 0);
+    }
+
+    #[test]
+    fn test_context_namespacing() {
+        let script_a = create_test_ctx();
+        script_a.set_custom("script", Value::String("backup".into()));
+        let script_b = create_test_ctx();
+
+        let prefixed = Arc::new(MetricsRegistry::new().with_namespace(MetricNamespace::Prefix));
+        let name = [Value::String("runs_total".into())];
+        counter_inc(&prefixed, &name, &script_a).unwrap();
+        counter_inc(&prefixed, &name, &script_b).unwrap();
+        assert_eq!(prefixed.counter_get("backup.runs_total"), 1);
+        assert_eq!(prefixed.counter_get("engine-1.runs_total"), 1);
+        assert_eq!(
+            counter_get(&prefixed, &name, &script_a).unwrap(),
+            Value::Int(1)
+        );
+
+        let labeled = Arc::new(
+            MetricsRegistry::new().with_namespace(MetricNamespace::Label("script".into())),
+        );
+        // Scripts cannot claim another script's identity.
+        let spoofed = Value::Map(HashMap::from([(
+            "script".to_string(),
+            Value::String("other".into()),
+        )]));
+        counter_inc(&labeled, &[name[0].clone(), spoofed], &script_a).unwrap();
+        counter_inc(&labeled, &name, &script_b).unwrap();
+        let owner = Labels::from([("script".to_string(), "backup".to_string())]);
+        assert_eq!(labeled.counter_get_with("runs_total", &owner), 1);
+
+        let listed = list(&labeled, &[], &script_a).unwrap();
+        let Value::List(entries) = listed else {
+            panic!("expected list");
+        };
+        assert_eq!(entries.len(), 1);
+        let entry = entries[0].as_map().unwrap();
+        assert_eq!(entry["labels"], Value::Map(HashMap::new()));
+
+        let options = HashMap::from([
+            ("namespace".to_string(), "label".to_string()),
+            ("namespace_label".to_string(), "automation".to_string()),
+        ]);
+        assert_eq!(
+            MetricNamespace::from_options(&options).unwrap(),
+            MetricNamespace::Label("automation".into())
+        );
+        let options = HashMap::from([("namespace".to_string(), "bogus".to_string())]);
+        assert!(MetricNamespace::from_options(&options).is_err());
+    }
+
+    #[test]
+    fn test_gauge_add_sub() {
+        let ctx = create_test_ctx();
+        let metrics = Arc::new(MetricsRegistry::new());
+        let name = Value::String("in_flight".into());
+
+        assert_eq!(
+            gauge_add(&metrics, &[name.clone(), Value::Int(3)], &ctx).unwrap(),
+            Value::Float(3.0)
+        );
+        assert_eq!(
+            gauge_sub(&metrics, &[name.clone(), Value::Float(0.5)], &ctx).unwrap(),
+            Value::Float(2.5)
+        );
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let metrics = metrics.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        metrics.gauge_add("in_flight", 1.0);
+                        metrics.gauge_sub("in_flight", 0.5);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(metrics.gauge_get("in_flight"), 2002.5);
+    }
+
+    #[test]
+    fn test_counter_inc_at() {
+        let ctx = create_test_ctx();
+        let metrics = Arc::new(MetricsRegistry::new());
+        let name = Value::String("backfilled_total".into());
+
+        counter_inc_at(
+            &metrics,
+            &[name.clone(), Value::Int(2), Value::Int(2_000)],
+            &ctx,
+        )
+        .unwrap();
+        counter_inc_at(
+            &metrics,
+            &[name.clone(), Value::Int(3), Value::Int(1_000)],
+            &ctx,
+        )
+        .unwrap();
+        assert!(counter_inc_at(&metrics, &[name, Value::Int(-1), Value::Int(0)], &ctx).is_err());
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.counters["backfilled_total"], 5);
+        assert_eq!(snapshot.counter_timestamps["backfilled_total"], 2_000);
+
+        metrics.clear();
+        assert!(metrics.snapshot().counter_timestamps.is_empty());
+    }
+
+    #[test]
+    fn test_define_derived() {
+        let ctx = create_test_ctx();
+        let metrics = Arc::new(MetricsRegistry::new());
+        metrics.counter_inc_with(
+            "jobs_total",
+            &Labels::from([("ok".into(), "true".into())]),
+            3,
+        );
+        metrics.counter_inc_with(
+            "jobs_total",
+            &Labels::from([("ok".into(), "false".into())]),
+            1,
+        );
+
+        define_derived(
+            &metrics,
+            &[
+                Value::String("job_failure_ratio".into()),
+                Value::String(r#"ratio(jobs_total{ok="false"}, jobs_total)"#.into()),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(metrics.snapshot().gauges["job_failure_ratio"], 0.25);
+        assert!(define_derived(
+            &metrics,
+            &[Value::String("bad".into()), Value::String("ratio(".into())],
+            &ctx
+        )
+        .is_err());
+
+        define_derived(&metrics, &[Value::String("job_failure_ratio".into())], &ctx).unwrap();
+        assert!(metrics.derived_names().is_empty());
+        assert!(!metrics.snapshot().gauges.contains_key("job_failure_ratio"));
+    }
+
+    #[test]
+    fn test_snapshot_diff() {
+        let ctx = create_test_ctx();
+        let metrics = Arc::new(MetricsRegistry::new());
+        metrics.counter_inc("ops_total", 3);
+        metrics.gauge_set("queue_depth", 5.0);
+        metrics.gauge_set("unchanged", 1.0);
+
+        let before = metrics.snapshot();
+        metrics.counter_inc("ops_total", 2);
+        metrics.gauge_sub("queue_depth", 1.5);
+        metrics.histogram_observe("latency_ms", 4.0);
+        let labels = Labels::from([("route".to_string(), "/api".to_string())]);
+        metrics.counter_inc_with("ops_total", &labels, 1);
+
+        let changes = metrics.snapshot().diff(&before);
+        assert_eq!(changes.counter("ops_total", &Labels::new()), 2);
+        assert_eq!(changes.counter("ops_total", &labels), 1);
+        assert_eq!(changes.gauge("queue_depth", &Labels::new()), -1.5);
+        assert!(!changes.gauges.contains_key("unchanged"));
+        assert_eq!(
+            changes.histogram("latency_ms", &Labels::new()),
+            HistogramDelta { count: 1, sum: 4.0 }
+        );
+        assert!(metrics.snapshot().diff(&metrics.snapshot()).is_empty());
+
+        // Scripts round-trip snapshots through Values.
+        let before = snapshot(&metrics, &[], &ctx).unwrap();
+        assert_eq!(
+            MetricsSnapshot::from_value(&before).unwrap().counters["ops_total"],
+            5
+        );
+        counter_inc(&metrics, &[Value::String("ops_total".into())], &ctx).unwrap();
+        let delta = diff(&metrics, &[before], &ctx).unwrap();
+        let counters = delta.as_map().unwrap()["counters"]
+            .as_map()
+            .unwrap()
+            .clone();
+        assert_eq!(
+            counters,
+            HashMap::from([("ops_total".to_string(), Value::Int(1))])
+        );
+
+        assert!(diff(&metrics, &[Value::Int(1)], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_summary_series() {
+        let ctx = create_test_ctx();
+        let metrics = Arc::new(MetricsRegistry::new().with_summary_config(SummaryConfig {
+            quantiles: vec![0.5, 0.95],
+            ..SummaryConfig::default()
+        }));
+        let labels = Value::Map(HashMap::from([(
+            "route".to_string(),
+            Value::String("/api".into()),
+        )]));
+
+        let before = metrics.snapshot();
+        for v in 1..=20 {
+            summary_observe(
+                &metrics,
+                &[
+                    Value::String("rpc_ms".into()),
+                    Value::Int(v),
+                    labels.clone(),
+                ],
+                &ctx,
+            )
+            .unwrap();
+        }
+
+        let stats =
+            summary_stats(&metrics, &[Value::String("rpc_ms".into()), labels], &ctx).unwrap();
+        let stats = SummaryStats::from_value(&stats).unwrap();
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.sum, 210.0);
+        assert_eq!(stats.quantiles, vec![(0.5, 11.0), (0.95, 19.0)]);
+        assert_eq!(
+            summary_stats(&metrics, &[Value::String("missing".into())], &ctx).unwrap(),
+            Value::Null
+        );
+
+        let route = Labels::from([("route".to_string(), "/api".to_string())]);
+        let after = metrics.snapshot();
+        assert_eq!(
+            after.diff(&before).summary("rpc_ms", &route),
+            HistogramDelta {
+                count: 20,
+                sum: 210.0
+            }
+        );
+        let round_trip = MetricsSnapshot::from_value(&after.to_value()).unwrap();
+        assert_eq!(round_trip.summaries, after.summaries);
+
+        let listed = list(&metrics, &[], &ctx).unwrap();
+        assert_eq!(
+            listed.as_list().unwrap()[0].as_map().unwrap()["type"],
+            Value::String("summary".into())
+        );
+    }
 }
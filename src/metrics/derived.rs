@@ -0,0 +1,369 @@
+//! Derived metrics computed from other series at snapshot time.
+//!
+//! A derived metric is a named expression over existing series, evaluated
+//! each time the registry takes a [snapshot](super::MetricsRegistry::snapshot)
+//! (and so on every export) and reported as a gauge. Expressions combine:
+//!
+//! - series references: `requests_total` matches every series of that
+//!   name and `requests_total{status="500"}` every series carrying those
+//!   labels; matching values are added up. Counters and gauges contribute
+//!   their value, histograms and summaries their observation count, and a
+//!   reference matching nothing is 0.
+//! - numbers, such as `100`
+//! - `sum(a, b, ...)`: the sum of its arguments
+//! - `ratio(a, b)`: `a / b`, with no value while `b` is 0
+//! - `rate(a)`: per-second change of `a` since the previous snapshot, with
+//!   no value on the first snapshot or after a decrease (a reset)
+//!
+//! For example, `ratio(rate(http_requests_total{status="500"}),
+//! rate(http_requests_total))` is the current error ratio. A derived metric
+//! without a value is left out of the snapshot, and later definitions may
+//! refer to earlier ones.
+
+use std::time::Instant;
+
+use parking_lot::Mutex;
+
+use super::{parse_series_key, series_key, Labels, MetricsSnapshot};
+
+/// A named expression evaluated into a gauge at snapshot time.
+#[derive(Debug)]
+pub struct DerivedMetric {
+    name: String,
+    expression: String,
+    expr: Expr,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(f64),
+    Series {
+        name: String,
+        labels: Labels,
+    },
+    Sum(Vec<Expr>),
+    Ratio(Box<Expr>, Box<Expr>),
+    Rate {
+        inner: Box<Expr>,
+        last: Mutex<Option<(f64, Instant)>>,
+    },
+}
+
+impl DerivedMetric {
+    /// Parse `expression` into a derived metric reported as `name`.
+    pub fn parse(name: impl Into<String>, expression: &str) -> crate::Result<Self> {
+        let name = name.into();
+        if name.is_empty() {
+            return Err(crate::Error::invalid_argument(
+                "derived metric name is empty",
+            ));
+        }
+
+        let mut parser = Parser {
+            src: expression,
+            pos: 0,
+        };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        if parser.pos < expression.len() {
+            return Err(parser.error("unexpected input"));
+        }
+
+        Ok(Self {
+            name,
+            expression: expression.to_string(),
+            expr,
+        })
+    }
+
+    /// Series key the value is reported under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The expression as written.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Rewrite the reported name and every series reference, such as to
+    /// apply a registry's namespace.
+    pub fn map_series(mut self, f: &dyn Fn(&str, Labels) -> (String, Labels)) -> Self {
+        let (name, labels) = match parse_series_key(&self.name) {
+            Some((name, labels)) => f(name, labels),
+            None => f(&self.name, Labels::new()),
+        };
+        self.name = series_key(&name, &labels);
+        self.expr.map_series(f);
+        self
+    }
+
+    /// Evaluate against a snapshot, or `None` if there is no value yet.
+    pub fn evaluate(&self, snapshot: &MetricsSnapshot) -> Option<f64> {
+        self.expr.evaluate(snapshot, Instant::now())
+    }
+}
+
+impl Expr {
+    fn map_series(&mut self, f: &dyn Fn(&str, Labels) -> (String, Labels)) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Series { name, labels } => {
+                let (mapped, mapped_labels) = f(name, std::mem::take(labels));
+                *name = mapped;
+                *labels = mapped_labels;
+            }
+            Expr::Sum(args) => args.iter_mut().for_each(|arg| arg.map_series(f)),
+            Expr::Ratio(a, b) => {
+                a.map_series(f);
+                b.map_series(f);
+            }
+            Expr::Rate { inner, .. } => inner.map_series(f),
+        }
+    }
+
+    fn evaluate(&self, snapshot: &MetricsSnapshot, now: Instant) -> Option<f64> {
+        match self {
+            Expr::Number(n) => Some(*n),
+            Expr::Series { name, labels } => Some(series_value(snapshot, name, labels)),
+            Expr::Sum(args) => args.iter().map(|arg| arg.evaluate(snapshot, now)).sum(),
+            Expr::Ratio(a, b) => {
+                let a = a.evaluate(snapshot, now)?;
+                let b = b.evaluate(snapshot, now)?;
+                (b != 0.0).then(|| a / b)
+            }
+            Expr::Rate { inner, last } => {
+                let value = inner.evaluate(snapshot, now)?;
+                let (prior, at) = last.lock().replace((value, now))?;
+                let elapsed = now.duration_since(at).as_secs_f64();
+                (elapsed > 0.0 && value >= prior).then(|| (value - prior) / elapsed)
+            }
+        }
+    }
+}
+
+/// Total of the series named `name` that carry all of `labels`.
+fn series_value(snapshot: &MetricsSnapshot, name: &str, labels: &Labels) -> f64 {
+    let matches = |key: &str| {
+        parse_series_key(key).is_some_and(|(series, series_labels)| {
+            series == name && labels.iter().all(|(k, v)| series_labels.get(k) == Some(v))
+        })
+    };
+
+    let counters = snapshot
+        .counters
+        .iter()
+        .filter(|(key, _)| matches(key))
+        .map(|(_, v)| *v as f64);
+    let gauges = snapshot
+        .gauges
+        .iter()
+        .filter(|(key, _)| matches(key))
+        .map(|(_, v)| *v);
+    let histograms = snapshot
+        .histograms
+        .iter()
+        .filter(|(key, _)| matches(key))
+        .map(|(_, s)| s.count as f64);
+    let summaries = snapshot
+        .summaries
+        .iter()
+        .filter(|(key, _)| matches(key))
+        .map(|(_, s)| s.count as f64);
+    counters
+        .chain(gauges)
+        .chain(histograms)
+        .chain(summaries)
+        .sum()
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> crate::Error {
+        crate::Error::invalid_argument(format!(
+            "derived metric expression: {} at position {}",
+            message, self.pos
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &str {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|c| f(*c)) {
+            self.pos += c.len_utf8();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn expect(&mut self, c: char) -> crate::Result<()> {
+        self.skip_whitespace();
+        if self.peek() != Some(c) {
+            return Err(self.error(&format!("expected '{}'", c)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expr(&mut self) -> crate::Result<Expr> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' || c == '-' => {
+                let start = self.pos;
+                let number = self
+                    .take_while(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | '+' | 'e' | 'E'));
+                number.parse().map(Expr::Number).map_err(|_| {
+                    self.pos = start;
+                    self.error("invalid number")
+                })
+            }
+            _ => {
+                let name = self
+                    .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | ':' | '.'))
+                    .to_string();
+                if name.is_empty() {
+                    return Err(self.error("expected a metric, number or function"));
+                }
+                match self.peek() {
+                    Some('(') => self.call(&name),
+                    Some('{') => self.series(name),
+                    _ => Ok(Expr::Series {
+                        name,
+                        labels: Labels::new(),
+                    }),
+                }
+            }
+        }
+    }
+
+    fn call(&mut self, function: &str) -> crate::Result<Expr> {
+        let start = self.pos;
+        self.expect('(')?;
+        let mut args = vec![self.expr()?];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.pos += 1;
+                    args.push(self.expr()?);
+                }
+                Some(')') => {
+                    self.pos += 1;
+                    break;
+                }
+                _ => return Err(self.error("expected ',' or ')'")),
+            }
+        }
+
+        match (function, args.len()) {
+            ("sum", _) => Ok(Expr::Sum(args)),
+            ("ratio", 2) => {
+                let b = args.pop().expect("two arguments");
+                let a = args.pop().expect("two arguments");
+                Ok(Expr::Ratio(Box::new(a), Box::new(b)))
+            }
+            ("rate", 1) => Ok(Expr::Rate {
+                inner: Box::new(args.pop().expect("one argument")),
+                last: Mutex::new(None),
+            }),
+            ("ratio" | "rate", n) => {
+                let expected = if function == "ratio" { 2 } else { 1 };
+                self.pos = start;
+                Err(self.error(&format!(
+                    "{} expects {} argument(s), got {}",
+                    function, expected, n
+                )))
+            }
+            _ => {
+                self.pos = start;
+                Err(self.error(&format!("unknown function '{}'", function)))
+            }
+        }
+    }
+
+    fn series(&mut self, name: String) -> crate::Result<Expr> {
+        let start = self.pos;
+        let mut quoted = false;
+        let mut escaped = false;
+        for (i, c) in self.src[start..].char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if quoted => escaped = true,
+                '"' => quoted = !quoted,
+                '}' if !quoted => {
+                    self.pos = start + i + 1;
+                    let key = format!("{}{}", name, &self.src[start..self.pos]);
+                    let (_, labels) = parse_series_key(&key).ok_or_else(|| {
+                        self.pos = start;
+                        self.error("invalid labels")
+                    })?;
+                    return Ok(Expr::Series { name, labels });
+                }
+                _ => {}
+            }
+        }
+        Err(self.error("unterminated labels"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_expressions() {
+        let mut snapshot = MetricsSnapshot::default();
+        snapshot
+            .counters
+            .insert(r#"requests_total{status="200"}"#.into(), 90);
+        snapshot
+            .counters
+            .insert(r#"requests_total{status="500"}"#.into(), 10);
+        snapshot.gauges.insert("queue_depth".into(), 4.0);
+
+        let eval = |expression: &str| {
+            DerivedMetric::parse("derived", expression)
+                .unwrap()
+                .evaluate(&snapshot)
+        };
+        assert_eq!(eval("requests_total"), Some(100.0));
+        assert_eq!(
+            eval(r#"ratio(requests_total{status="500"}, requests_total)"#),
+            Some(0.1)
+        );
+        assert_eq!(eval("sum(queue_depth, 1, missing_total)"), Some(5.0));
+        assert_eq!(eval("ratio(queue_depth, missing_total)"), None);
+
+        // Rates need two snapshots.
+        let rate = DerivedMetric::parse("rps", "rate(requests_total)").unwrap();
+        assert_eq!(rate.evaluate(&snapshot), None);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        *snapshot
+            .counters
+            .get_mut(r#"requests_total{status="200"}"#)
+            .unwrap() += 5;
+        assert!(rate.evaluate(&snapshot).unwrap() > 0.0);
+
+        for bad in [
+            "",
+            "ratio(a)",
+            "rate(a, b)",
+            "avg(a)",
+            "a{status=}",
+            "sum(a",
+            "a b",
+        ] {
+            assert!(DerivedMetric::parse("x", bad).is_err(), "{}", bad);
+        }
+    }
+}
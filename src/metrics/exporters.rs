@@ -0,0 +1,633 @@
+//! Built-in metrics exporters.
+//!
+//! - [`StatsdExporter`] pushes values over UDP in StatsD format, with
+//!   labels as DogStatsD-style tags.
+//! - [`JsonFileExporter`] writes each snapshot to a JSON file, replacing it
+//!   atomically, for sidecars and node-exporter textfile-style collection.
+//! - [`OtlpExporter`] posts OTLP/HTTP JSON to a collector (`metrics-otlp`
+//!   feature).
+//!
+//! Exporters can also be built from `metrics` module options with
+//! [`from_options`].
+
+use std::collections::HashMap;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "metrics-otlp")]
+use std::time::Duration;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
+
+use fusabi_host::Value;
+
+use super::summary::quantile_suffix;
+use super::{parse_series_key, MetricsExporter, MetricsSnapshot};
+use crate::error::{Error, Result};
+
+/// Largest datagram sent to StatsD, to stay under common MTUs.
+const STATSD_MAX_PACKET: usize = 1432;
+
+/// Build exporters from `metrics` module options.
+///
+/// `exporters` is a comma-separated list of `statsd`, `json` and `otlp`.
+/// Each exporter reads its own options:
+///
+/// - `statsd`: `statsd_addr` (default `127.0.0.1:8125`), `statsd_prefix`
+/// - `json`: `json_path` (required)
+/// - `otlp`: `otlp_endpoint` (default `http://localhost:4318/v1/metrics`),
+///   `otlp_service_name`
+pub fn from_options(options: &HashMap<String, String>) -> Result<Vec<Arc<dyn MetricsExporter>>> {
+    let Some(names) = options.get("exporters") else {
+        return Ok(Vec::new());
+    };
+
+    let mut exporters: Vec<Arc<dyn MetricsExporter>> = Vec::new();
+    for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        match name {
+            "statsd" => {
+                let addr = options
+                    .get("statsd_addr")
+                    .map(String::as_str)
+                    .unwrap_or("127.0.0.1:8125");
+                let mut exporter = StatsdExporter::new(addr)?;
+                if let Some(prefix) = options.get("statsd_prefix") {
+                    exporter = exporter.with_prefix(prefix.clone());
+                }
+                exporters.push(Arc::new(exporter));
+            }
+            "json" => {
+                let path = options.get("json_path").ok_or_else(|| {
+                    Error::invalid_argument("json metrics exporter requires json_path")
+                })?;
+                exporters.push(Arc::new(JsonFileExporter::new(path)));
+            }
+            #[cfg(feature = "metrics-otlp")]
+            "otlp" => {
+                let endpoint = options
+                    .get("otlp_endpoint")
+                    .map(String::as_str)
+                    .unwrap_or("http://localhost:4318/v1/metrics");
+                let mut exporter = OtlpExporter::new(endpoint);
+                if let Some(service) = options.get("otlp_service_name") {
+                    exporter = exporter.with_service_name(service.clone());
+                }
+                exporters.push(Arc::new(exporter));
+            }
+            #[cfg(not(feature = "metrics-otlp"))]
+            "otlp" => {
+                return Err(Error::ModuleNotAvailable(
+                    "otlp metrics exporter requires the metrics-otlp feature".to_string(),
+                ))
+            }
+            other => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown metrics exporter: {}",
+                    other
+                )))
+            }
+        }
+    }
+    Ok(exporters)
+}
+
+/// Pushes metrics to a StatsD server over UDP.
+///
+/// Counters are sent as increments since the previous export, gauges as
+/// absolute values, histograms as `.count`, `.sum`, `.p50`, `.p90` and
+/// `.p99` gauges, and summaries as `.count`, `.sum` and one gauge per
+/// configured quantile (`.p50`, `.p99_9`, ...).
+pub struct StatsdExporter {
+    socket: UdpSocket,
+    prefix: Option<String>,
+    sent_counters: Mutex<HashMap<String, u64>>,
+}
+
+impl StatsdExporter {
+    /// Create an exporter sending to `addr` (for example `127.0.0.1:8125`).
+    pub fn new(addr: impl ToSocketAddrs) -> Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::network("statsd address did not resolve"))?;
+        let bind = if addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+        let socket = UdpSocket::bind(bind)?;
+        socket.connect(addr)?;
+
+        Ok(Self {
+            socket,
+            prefix: None,
+            sent_counters: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Prefix every metric name with `prefix.`.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Render a snapshot as StatsD lines.
+    fn lines(&self, snapshot: &MetricsSnapshot) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        let mut sent = self.sent_counters.lock();
+        for (key, value) in &snapshot.counters {
+            let previous = sent.insert(key.clone(), *value).unwrap_or(0);
+            // A cleared registry restarts from zero.
+            let delta = value.checked_sub(previous).unwrap_or(*value);
+            if delta > 0 {
+                lines.push(self.line(key, "", &delta.to_string(), "c"));
+            }
+        }
+        drop(sent);
+
+        for (key, value) in &snapshot.gauges {
+            lines.push(self.line(key, "", &value.to_string(), "g"));
+        }
+
+        for (key, stats) in &snapshot.histograms {
+            lines.push(self.line(key, ".count", &stats.count.to_string(), "g"));
+            lines.push(self.line(key, ".sum", &stats.sum.to_string(), "g"));
+            lines.push(self.line(key, ".p50", &stats.p50.to_string(), "g"));
+            lines.push(self.line(key, ".p90", &stats.p90.to_string(), "g"));
+            lines.push(self.line(key, ".p99", &stats.p99.to_string(), "g"));
+        }
+
+        for (key, stats) in &snapshot.summaries {
+            lines.push(self.line(key, ".count", &stats.count.to_string(), "g"));
+            lines.push(self.line(key, ".sum", &stats.sum.to_string(), "g"));
+            for (q, value) in &stats.quantiles {
+                let suffix = format!(".{}", quantile_suffix(*q));
+                lines.push(self.line(key, &suffix, &value.to_string(), "g"));
+            }
+        }
+
+        lines.sort();
+        lines
+    }
+
+    fn line(&self, key: &str, suffix: &str, value: &str, kind: &str) -> String {
+        let (name, labels) = parse_series_key(key).unwrap_or((key, Default::default()));
+        let clean = |s: &str| s.replace([':', '|', '@', ',', '#', '\n'], "_");
+
+        let mut line = String::new();
+        if let Some(prefix) = &self.prefix {
+            line.push_str(&clean(prefix));
+            line.push('.');
+        }
+        line.push_str(&clean(name));
+        line.push_str(suffix);
+        line.push(':');
+        line.push_str(value);
+        line.push('|');
+        line.push_str(kind);
+
+        if !labels.is_empty() {
+            let tags: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}:{}", clean(k), clean(v)))
+                .collect();
+            line.push_str("|#");
+            line.push_str(&tags.join(","));
+        }
+        line
+    }
+}
+
+impl MetricsExporter for StatsdExporter {
+    fn name(&self) -> &str {
+        "statsd"
+    }
+
+    fn export(&self, snapshot: &MetricsSnapshot) -> Result<()> {
+        let mut packet = String::new();
+        for line in self.lines(snapshot) {
+            if !packet.is_empty() && packet.len() + 1 + line.len() > STATSD_MAX_PACKET {
+                self.socket.send(packet.as_bytes())?;
+                packet.clear();
+            }
+            if !packet.is_empty() {
+                packet.push('\n');
+            }
+            packet.push_str(&line);
+        }
+        if !packet.is_empty() {
+            self.socket.send(packet.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes each snapshot to a JSON file.
+///
+/// The file is replaced atomically (write to a temporary sibling, then
+/// rename), so readers never see a partial snapshot.
+#[derive(Debug, Clone)]
+pub struct JsonFileExporter {
+    path: PathBuf,
+}
+
+impl JsonFileExporter {
+    /// Create an exporter writing to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The output path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl MetricsExporter for JsonFileExporter {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn export(&self, snapshot: &MetricsSnapshot) -> Result<()> {
+        let mut doc = HashMap::new();
+        doc.insert(
+            "timestamp_ms".to_string(),
+            Value::Int(unix_nanos() / 1_000_000),
+        );
+        doc.insert(
+            "counters".to_string(),
+            Value::Map(
+                snapshot
+                    .counters
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Int(*v as i64)))
+                    .collect(),
+            ),
+        );
+        doc.insert(
+            "counter_timestamps_ms".to_string(),
+            Value::Map(
+                snapshot
+                    .counter_timestamps
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Int(*v)))
+                    .collect(),
+            ),
+        );
+        doc.insert(
+            "gauges".to_string(),
+            Value::Map(
+                snapshot
+                    .gauges
+                    .iter()
+                    .map(|(k, v)| (k.clone(), Value::Float(*v)))
+                    .collect(),
+            ),
+        );
+        doc.insert(
+            "histograms".to_string(),
+            Value::Map(
+                snapshot
+                    .histograms
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_value()))
+                    .collect(),
+            ),
+        );
+        doc.insert(
+            "summaries".to_string(),
+            Value::Map(
+                snapshot
+                    .summaries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_value()))
+                    .collect(),
+            ),
+        );
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, Value::Map(doc).to_json_string())?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Posts metrics to an OpenTelemetry collector as OTLP/HTTP JSON.
+///
+/// Counters become cumulative monotonic sums, gauges become gauges,
+/// histograms become summaries with 0.5/0.9/0.99 quantiles, and summaries
+/// become summaries with their configured quantiles.
+#[cfg(feature = "metrics-otlp")]
+pub struct OtlpExporter {
+    endpoint: String,
+    service_name: String,
+    timeout: Duration,
+    start_nanos: i64,
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "metrics-otlp")]
+impl OtlpExporter {
+    /// Create an exporter posting to `endpoint`, usually
+    /// `http://<collector>:4318/v1/metrics`.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            service_name: "fusabi".to_string(),
+            timeout: Duration::from_secs(10),
+            start_nanos: unix_nanos(),
+            client: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// Set the `service.name` resource attribute.
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+
+    /// Set the request timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Render a snapshot as an OTLP `ExportMetricsServiceRequest`.
+    pub fn body(&self, snapshot: &MetricsSnapshot) -> String {
+        let now = unix_nanos().to_string();
+        let start = self.start_nanos.to_string();
+        let map = |pairs: Vec<(&str, Value)>| {
+            Value::Map(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+        };
+        let string = |s: &str| Value::String(s.to_string());
+        let attributes = |labels: super::Labels| {
+            Value::List(
+                labels
+                    .into_iter()
+                    .map(|(k, v)| {
+                        map(vec![
+                            ("key", Value::String(k)),
+                            ("value", map(vec![("stringValue", Value::String(v))])),
+                        ])
+                    })
+                    .collect(),
+            )
+        };
+
+        // Group series by metric name.
+        let mut sums: HashMap<&str, Vec<Value>> = HashMap::new();
+        for (key, value) in &snapshot.counters {
+            let (name, labels) = parse_series_key(key).unwrap_or((key, Default::default()));
+            let time = match snapshot.counter_timestamps.get(key) {
+                Some(ms) => ms.saturating_mul(1_000_000).to_string(),
+                None => now.clone(),
+            };
+            sums.entry(name).or_default().push(map(vec![
+                ("attributes", attributes(labels)),
+                ("startTimeUnixNano", string(&start)),
+                ("timeUnixNano", string(&time)),
+                ("asInt", Value::String(value.to_string())),
+            ]));
+        }
+        let mut gauges: HashMap<&str, Vec<Value>> = HashMap::new();
+        for (key, value) in &snapshot.gauges {
+            let (name, labels) = parse_series_key(key).unwrap_or((key, Default::default()));
+            gauges.entry(name).or_default().push(map(vec![
+                ("attributes", attributes(labels)),
+                ("timeUnixNano", string(&now)),
+                ("asDouble", Value::Float(*value)),
+            ]));
+        }
+        let mut summaries: HashMap<&str, Vec<Value>> = HashMap::new();
+        let summary_point = |labels, count: u64, sum: f64, quantiles: &[(f64, f64)]| {
+            let quantiles = quantiles
+                .iter()
+                .map(|(q, v)| {
+                    map(vec![
+                        ("quantile", Value::Float(*q)),
+                        ("value", Value::Float(*v)),
+                    ])
+                })
+                .collect();
+            map(vec![
+                ("attributes", attributes(labels)),
+                ("startTimeUnixNano", string(&start)),
+                ("timeUnixNano", string(&now)),
+                ("count", Value::String(count.to_string())),
+                ("sum", Value::Float(sum)),
+                ("quantileValues", Value::List(quantiles)),
+            ])
+        };
+        for (key, stats) in &snapshot.histograms {
+            let (name, labels) = parse_series_key(key).unwrap_or((key, Default::default()));
+            summaries.entry(name).or_default().push(summary_point(
+                labels,
+                stats.count,
+                stats.sum,
+                &[(0.5, stats.p50), (0.9, stats.p90), (0.99, stats.p99)],
+            ));
+        }
+        for (key, stats) in &snapshot.summaries {
+            let (name, labels) = parse_series_key(key).unwrap_or((key, Default::default()));
+            summaries.entry(name).or_default().push(summary_point(
+                labels,
+                stats.count,
+                stats.sum,
+                &stats.quantiles,
+            ));
+        }
+
+        let mut metrics = Vec::new();
+        for (name, points) in sums {
+            metrics.push(map(vec![
+                ("name", string(name)),
+                (
+                    "sum",
+                    map(vec![
+                        ("dataPoints", Value::List(points)),
+                        // AGGREGATION_TEMPORALITY_CUMULATIVE
+                        ("aggregationTemporality", Value::Int(2)),
+                        ("isMonotonic", Value::Bool(true)),
+                    ]),
+                ),
+            ]));
+        }
+        for (name, points) in gauges {
+            metrics.push(map(vec![
+                ("name", string(name)),
+                ("gauge", map(vec![("dataPoints", Value::List(points))])),
+            ]));
+        }
+        for (name, points) in summaries {
+            metrics.push(map(vec![
+                ("name", string(name)),
+                ("summary", map(vec![("dataPoints", Value::List(points))])),
+            ]));
+        }
+
+        let resource = map(vec![(
+            "attributes",
+            Value::List(vec![map(vec![
+                ("key", string("service.name")),
+                (
+                    "value",
+                    map(vec![("stringValue", string(&self.service_name))]),
+                ),
+            ])]),
+        )]);
+        let scope_metrics = map(vec![
+            ("scope", map(vec![("name", string(env!("CARGO_PKG_NAME")))])),
+            ("metrics", Value::List(metrics)),
+        ]);
+        map(vec![(
+            "resourceMetrics",
+            Value::List(vec![map(vec![
+                ("resource", resource),
+                ("scopeMetrics", Value::List(vec![scope_metrics])),
+            ])]),
+        )])
+        .to_json_string()
+    }
+}
+
+#[cfg(feature = "metrics-otlp")]
+impl MetricsExporter for OtlpExporter {
+    fn name(&self) -> &str {
+        "otlp"
+    }
+
+    fn export(&self, snapshot: &MetricsSnapshot) -> Result<()> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .header("content-type", "application/json")
+            .timeout(self.timeout)
+            .body(self.body(snapshot))
+            .send()
+            .map_err(|e| Error::network(format!("OTLP export failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(Error::network(format!(
+                "OTLP export failed: HTTP {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn unix_nanos() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::MetricsRegistry;
+
+    #[test]
+    fn test_statsd_exporter() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+            .unwrap();
+        let exporter = StatsdExporter::new(server.local_addr().unwrap())
+            .unwrap()
+            .with_prefix("app");
+
+        let registry = MetricsRegistry::new();
+        let mut labels = crate::metrics::Labels::new();
+        labels.insert("route".into(), "/api".into());
+        registry.counter_inc_with("requests", &labels, 3);
+        registry.gauge_set("queue", 2.5);
+
+        let recv = || {
+            let mut buf = [0u8; 2048];
+            let n = server.recv(&mut buf).unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        };
+
+        exporter.export(&registry.snapshot()).unwrap();
+        assert_eq!(recv(), "app.queue:2.5|g\napp.requests:3|c|#route:/api");
+
+        // Counters are sent as increments since the last export.
+        registry.counter_inc_with("requests", &labels, 2);
+        exporter.export(&registry.snapshot()).unwrap();
+        assert_eq!(recv(), "app.queue:2.5|g\napp.requests:2|c|#route:/api");
+
+        // Summaries send one gauge per quantile.
+        let summaries = MetricsRegistry::new();
+        summaries.summary_observe("rpc_ms", 8.0);
+        assert_eq!(
+            exporter.lines(&summaries.snapshot()),
+            vec![
+                "app.rpc_ms.count:1|g",
+                "app.rpc_ms.p50:8|g",
+                "app.rpc_ms.p90:8|g",
+                "app.rpc_ms.p99:8|g",
+                "app.rpc_ms.sum:8|g",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_json_file_exporter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metrics.json");
+        let mut options = HashMap::new();
+        options.insert("exporters".to_string(), "json".to_string());
+        assert!(from_options(&options).is_err());
+        options.insert("json_path".to_string(), path.to_string_lossy().into_owned());
+        let exporters = from_options(&options).unwrap();
+        assert_eq!(exporters.len(), 1);
+
+        let registry = MetricsRegistry::new();
+        registry.counter_inc("jobs_total", 4);
+        registry.histogram_observe("latency", 1.0);
+        registry.summary_observe("rpc", 2.0);
+        exporters[0].export(&registry.snapshot()).unwrap();
+
+        let written = Value::from_json_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let written = written.as_map().unwrap();
+        let counters = written.get("counters").unwrap().as_map().unwrap();
+        assert_eq!(counters.get("jobs_total"), Some(&Value::Int(4)));
+        assert!(written
+            .get("histograms")
+            .unwrap()
+            .as_map()
+            .unwrap()
+            .contains_key("latency"));
+        let summary = &written.get("summaries").unwrap().as_map().unwrap()["rpc"];
+        assert_eq!(summary.as_map().unwrap()["count"], Value::Int(1));
+
+        options.insert("exporters".to_string(), "carrier-pigeon".to_string());
+        assert!(from_options(&options).is_err());
+    }
+
+    #[cfg(feature = "metrics-otlp")]
+    #[test]
+    fn test_otlp_body() {
+        let registry = MetricsRegistry::new();
+        let mut labels = crate::metrics::Labels::new();
+        labels.insert("status".into(), "500".into());
+        registry.counter_inc_with("requests", &labels, 7);
+        registry.gauge_set("temperature", 41.5);
+        registry.summary_observe("rpc_ms", 12.0);
+
+        let exporter =
+            OtlpExporter::new("http://localhost:4318/v1/metrics").with_service_name("checkout");
+        let body = Value::from_json_str(&exporter.body(&registry.snapshot())).unwrap();
+        let json = body.to_json_string();
+        assert!(json.contains("\"checkout\""));
+        assert!(json.contains("\"asInt\":\"7\""));
+        assert!(json.contains("\"isMonotonic\":true"));
+        assert!(json.contains("\"asDouble\":41.5"));
+        assert!(json.contains("\"quantile\":0.99,\"value\":12.0"));
+    }
+}
@@ -0,0 +1,313 @@
+//! Summaries: quantiles over a sliding time window.
+//!
+//! A histogram keeps every observation, which is fine for short runs but
+//! not for long-lived processes whose recent latency is what matters. A
+//! [`Summary`] keeps only the observations of the last
+//! [`max_age`](SummaryConfig::max_age), in
+//! [`age_buckets`](SummaryConfig::age_buckets) rotating buckets, and
+//! reports the configured quantiles over them. No bucket boundaries have to
+//! be chosen up front. The count and sum are cumulative, as in Prometheus
+//! summaries.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use fusabi_host::Value;
+
+/// Settings shared by the summaries of a registry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummaryConfig {
+    /// Quantiles to report, each between 0.0 and 1.0.
+    pub quantiles: Vec<f64>,
+    /// How long observations count towards the quantiles.
+    pub max_age: Duration,
+    /// Number of buckets the window rotates through; observations drop out
+    /// one bucket (`max_age / age_buckets`) at a time.
+    pub age_buckets: u32,
+    /// Most observations kept per bucket; the oldest are dropped beyond it.
+    pub max_samples: usize,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        Self {
+            quantiles: vec![0.5, 0.9, 0.99],
+            max_age: Duration::from_secs(600),
+            age_buckets: 5,
+            max_samples: 2048,
+        }
+    }
+}
+
+impl SummaryConfig {
+    /// Read the `summary_quantiles` (comma-separated, e.g. `0.5,0.99`),
+    /// `summary_max_age_ms` and `summary_age_buckets` options.
+    pub fn from_options(options: &HashMap<String, String>) -> crate::Result<Self> {
+        let mut config = Self::default();
+
+        if let Some(quantiles) = options.get("summary_quantiles") {
+            config.quantiles = quantiles
+                .split(',')
+                .map(|q| {
+                    q.trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|q| (0.0..=1.0).contains(q))
+                        .ok_or_else(|| {
+                            crate::Error::invalid_argument(format!(
+                                "invalid summary quantile: {}",
+                                q.trim()
+                            ))
+                        })
+                })
+                .collect::<crate::Result<_>>()?;
+        }
+        if let Some(ms) = options.get("summary_max_age_ms") {
+            let ms: u64 = ms.parse().ok().filter(|ms| *ms > 0).ok_or_else(|| {
+                crate::Error::invalid_argument(format!("invalid summary_max_age_ms: {}", ms))
+            })?;
+            config.max_age = Duration::from_millis(ms);
+        }
+        if let Some(buckets) = options.get("summary_age_buckets") {
+            config.age_buckets = buckets.parse().ok().filter(|n| *n > 0).ok_or_else(|| {
+                crate::Error::invalid_argument(format!("invalid summary_age_buckets: {}", buckets))
+            })?;
+        }
+
+        config.quantiles.sort_by(f64::total_cmp);
+        config.quantiles.dedup();
+        Ok(config)
+    }
+
+    fn bucket_width(&self) -> Duration {
+        self.max_age / self.age_buckets.max(1)
+    }
+}
+
+/// A summary metric.
+#[derive(Debug)]
+pub struct Summary {
+    config: SummaryConfig,
+    state: Mutex<SummaryState>,
+}
+
+#[derive(Debug, Default)]
+struct SummaryState {
+    count: u64,
+    sum: f64,
+    /// Buckets with their start time, oldest first.
+    buckets: VecDeque<(Instant, VecDeque<f64>)>,
+}
+
+impl Summary {
+    /// Create a summary.
+    pub fn new(config: SummaryConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(SummaryState::default()),
+        }
+    }
+
+    /// Observe a value.
+    pub fn observe(&self, value: f64) {
+        self.observe_at(value, Instant::now());
+    }
+
+    /// Observe a value at `now`.
+    pub fn observe_at(&self, value: f64, now: Instant) {
+        let mut state = self.state.lock();
+        state.count += 1;
+        state.sum += value;
+
+        self.expire(&mut state, now);
+        let width = self.config.bucket_width();
+        let current = match state.buckets.back() {
+            Some((start, _)) if now < *start + width => state.buckets.back_mut(),
+            _ => {
+                state.buckets.push_back((now, VecDeque::new()));
+                state.buckets.back_mut()
+            }
+        };
+        if let Some((_, samples)) = current {
+            if samples.len() >= self.config.max_samples.max(1) {
+                samples.pop_front();
+            }
+            samples.push_back(value);
+        }
+    }
+
+    /// Get statistics.
+    pub fn stats(&self) -> SummaryStats {
+        self.stats_at(Instant::now())
+    }
+
+    /// Get statistics as of `now`.
+    pub fn stats_at(&self, now: Instant) -> SummaryStats {
+        let mut state = self.state.lock();
+        self.expire(&mut state, now);
+
+        let mut window: Vec<f64> = state
+            .buckets
+            .iter()
+            .flat_map(|(_, samples)| samples.iter().copied())
+            .collect();
+        window.sort_by(f64::total_cmp);
+
+        let quantiles = if window.is_empty() {
+            Vec::new()
+        } else {
+            self.config
+                .quantiles
+                .iter()
+                .map(|q| (*q, super::percentile(&window, *q)))
+                .collect()
+        };
+
+        SummaryStats {
+            count: state.count,
+            sum: state.sum,
+            quantiles,
+        }
+    }
+
+    fn expire(&self, state: &mut SummaryState, now: Instant) {
+        while let Some((start, _)) = state.buckets.front() {
+            if now.saturating_duration_since(*start) >= self.config.max_age {
+                state.buckets.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// Summary statistics.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SummaryStats {
+    /// Number of observations ever made.
+    pub count: u64,
+    /// Sum of all observations ever made.
+    pub sum: f64,
+    /// `(quantile, value)` pairs over the current window, by ascending
+    /// quantile; empty when the window holds no observations.
+    pub quantiles: Vec<(f64, f64)>,
+}
+
+impl SummaryStats {
+    /// Value of a quantile, if it is configured and the window is not empty.
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        self.quantiles
+            .iter()
+            .find(|(quantile, _)| *quantile == q)
+            .map(|(_, v)| *v)
+    }
+
+    /// Convert to a Value map with `count`, `sum` and `quantiles` (a map
+    /// from quantile, such as `"0.99"`, to value).
+    pub fn to_value(&self) -> Value {
+        let mut m = HashMap::new();
+        m.insert("count".into(), Value::Int(self.count as i64));
+        m.insert("sum".into(), Value::Float(self.sum));
+        m.insert(
+            "quantiles".into(),
+            Value::Map(
+                self.quantiles
+                    .iter()
+                    .map(|(q, v)| (q.to_string(), Value::Float(*v)))
+                    .collect(),
+            ),
+        );
+        Value::Map(m)
+    }
+
+    /// Parse statistics produced by [`to_value`](Self::to_value).
+    pub fn from_value(value: &Value) -> Option<Self> {
+        let map = value.as_map()?;
+        let mut quantiles: Vec<(f64, f64)> = Vec::new();
+        if let Some(values) = map.get("quantiles") {
+            for (q, v) in values.as_map()? {
+                quantiles.push((q.parse().ok()?, v.as_float()?));
+            }
+        }
+        quantiles.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Some(Self {
+            count: map.get("count")?.as_int()? as u64,
+            sum: map.get("sum")?.as_float()?,
+            quantiles,
+        })
+    }
+}
+
+/// Metric name suffix for a quantile: `p50`, `p99`, `p99_9`.
+pub fn quantile_suffix(q: f64) -> String {
+    let percent = format!("{}", (q * 1000.0).round() / 10.0);
+    format!("p{}", percent.replace('.', "_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_window_slides() {
+        let summary = Summary::new(SummaryConfig {
+            max_age: Duration::from_secs(60),
+            age_buckets: 3,
+            ..SummaryConfig::default()
+        });
+        let start = Instant::now();
+        for v in 1..=100 {
+            summary.observe_at(v as f64, start);
+        }
+
+        let stats = summary.stats_at(start);
+        assert_eq!(stats.count, 100);
+        assert_eq!(stats.sum, 5050.0);
+        assert_eq!(stats.quantile(0.5), Some(51.0));
+        assert_eq!(stats.quantile(0.99), Some(99.0));
+
+        // New slow observations land in a later bucket.
+        let later = start + Duration::from_secs(30);
+        for _ in 0..10 {
+            summary.observe_at(500.0, later);
+        }
+        assert_eq!(summary.stats_at(later).quantile(0.99), Some(500.0));
+
+        // Once the first bucket ages out only the slow ones remain, but the
+        // count and sum keep everything.
+        let stats = summary.stats_at(start + Duration::from_secs(61));
+        assert_eq!(stats.quantile(0.5), Some(500.0));
+        assert_eq!(stats.count, 110);
+
+        let stats = summary.stats_at(start + Duration::from_secs(200));
+        assert!(stats.quantiles.is_empty());
+        assert_eq!(SummaryStats::from_value(&stats.to_value()), Some(stats));
+    }
+
+    #[test]
+    fn test_summary_config_options() {
+        let options = HashMap::from([
+            (
+                "summary_quantiles".to_string(),
+                "0.99, 0.5,0.999".to_string(),
+            ),
+            ("summary_max_age_ms".to_string(), "30000".to_string()),
+        ]);
+        let config = SummaryConfig::from_options(&options).unwrap();
+        assert_eq!(config.quantiles, vec![0.5, 0.99, 0.999]);
+        assert_eq!(config.max_age, Duration::from_secs(30));
+        assert_eq!(
+            config
+                .quantiles
+                .iter()
+                .map(|q| quantile_suffix(*q))
+                .collect::<Vec<_>>(),
+            vec!["p50", "p99", "p99_9"]
+        );
+
+        let bad = HashMap::from([("summary_quantiles".to_string(), "1.5".to_string())]);
+        assert!(SummaryConfig::from_options(&bad).is_err());
+    }
+}
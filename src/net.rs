@@ -1,6 +1,18 @@
 //! Network module.
 //!
-//! Provides HTTP request functions with safety controls.
+//! Provides HTTP request functions with safety controls. Response bodies
+//! are decompressed and charset-decoded as described by [`encoding`]. Host
+//! names can be resolved over DNS-over-HTTPS via [`doh`] behind the `doh`
+//! feature.
+//!
+//! DNS rebinding protection is opt-in: it is off in [`SafetyConfig::new`]
+//! and [`SafetyConfig::permissive`] and on in [`SafetyConfig::strict`] or
+//! with `with_allow_internal_addresses(false)`. With it on, each request
+//! resolves its host once (through the DoH resolver when one is installed)
+//! and refuses internal addresses. `net.get` and `net.post` are still
+//! simulated, so they stop at that check; an embedder making real requests
+//! connects with [`pinned_client`], which only reaches the checked
+//! addresses and checks every redirect the same way.
 
 use std::sync::Arc;
 use std::time::Duration;
@@ -8,9 +20,21 @@ use std::time::Duration;
 use fusabi_host::ExecutionContext;
 use fusabi_host::Value;
 
-use crate::safety::SafetyConfig;
+use crate::safety::{PinnedHost, SafetyConfig};
+
+#[cfg(feature = "doh")]
+pub mod doh;
+pub mod encoding;
+
+pub use encoding::{decode as decode_body, DecodeOptions};
 
 /// Perform an HTTP GET request.
+///
+/// # Arguments
+///
+/// * `args[0]` - URL
+/// * `args[1]` - Options (map, optional): body decoding options, see
+///   [`DecodeOptions::from_map`]
 pub fn http_get(
     safety: &Arc<SafetyConfig>,
     timeout: Option<Duration>,
@@ -22,14 +46,13 @@ pub fn http_get(
         .and_then(|v| v.as_str())
         .ok_or_else(|| fusabi_host::Error::host_function("net.get: missing URL argument"))?;
 
+    let decode = decode_options(args.get(1), "net.get")?;
+
     // Extract host from URL
     let host = extract_host(url)?;
 
     // Check safety
-    safety
-        .hosts
-        .check(&host)
-        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+    let pinned = pin_request(safety, &host, "net.get")?;
 
     // Apply timeout
     let timeout = timeout
@@ -37,16 +60,26 @@ pub fn http_get(
         .unwrap_or(safety.default_timeout);
 
     // Perform request (simulated)
-    tracing::info!("HTTP GET {} (timeout: {:?})", url, timeout);
+    tracing::info!(
+        "HTTP GET {} (timeout: {:?}, accept-encoding: {:?}, pinned: {:?})",
+        url,
+        timeout,
+        decode.accept_encoding(),
+        pinned.as_ref().map(PinnedHost::addresses)
+    );
 
     // In real implementation, would use reqwest
+    let headers = std::collections::HashMap::new();
+    let body = encoding::decode_body(
+        format!("Response from {}", url).into_bytes(),
+        &headers,
+        &decode,
+    )
+    .map_err(|e| fusabi_host::Error::host_function(format!("net.get: {}", e)))?;
     Ok(Value::Map({
         let mut m = std::collections::HashMap::new();
         m.insert("status".into(), Value::Int(200));
-        m.insert(
-            "body".into(),
-            Value::String(format!("Response from {}", url)),
-        );
+        m.insert("body".into(), body);
         m.insert(
             "headers".into(),
             Value::Map(std::collections::HashMap::new()),
@@ -56,6 +89,13 @@ pub fn http_get(
 }
 
 /// Perform an HTTP POST request.
+///
+/// # Arguments
+///
+/// * `args[0]` - URL
+/// * `args[1]` - Request body
+/// * `args[2]` - Options (map, optional): body decoding options, see
+///   [`DecodeOptions::from_map`]
 pub fn http_post(
     safety: &Arc<SafetyConfig>,
     timeout: Option<Duration>,
@@ -68,15 +108,13 @@ pub fn http_post(
         .ok_or_else(|| fusabi_host::Error::host_function("net.post: missing URL argument"))?;
 
     let body = args.get(1).map(|v| v.to_string()).unwrap_or_default();
+    let decode = decode_options(args.get(2), "net.post")?;
 
     // Extract host from URL
     let host = extract_host(url)?;
 
     // Check safety
-    safety
-        .hosts
-        .check(&host)
-        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+    let pinned = pin_request(safety, &host, "net.post")?;
 
     // Apply timeout
     let timeout = timeout
@@ -85,17 +123,22 @@ pub fn http_post(
 
     // Perform request (simulated)
     tracing::info!(
-        "HTTP POST {} (body: {} bytes, timeout: {:?})",
+        "HTTP POST {} (body: {} bytes, timeout: {:?}, accept-encoding: {:?}, pinned: {:?})",
         url,
         body.len(),
-        timeout
+        timeout,
+        decode.accept_encoding(),
+        pinned.as_ref().map(PinnedHost::addresses)
     );
 
     // In real implementation, would use reqwest
+    let headers = std::collections::HashMap::new();
+    let body = encoding::decode_body(b"OK".to_vec(), &headers, &decode)
+        .map_err(|e| fusabi_host::Error::host_function(format!("net.post: {}", e)))?;
     Ok(Value::Map({
         let mut m = std::collections::HashMap::new();
         m.insert("status".into(), Value::Int(200));
-        m.insert("body".into(), Value::String("OK".into()));
+        m.insert("body".into(), body);
         m.insert(
             "headers".into(),
             Value::Map(std::collections::HashMap::new()),
@@ -104,6 +147,146 @@ pub fn http_post(
     }))
 }
 
+/// Look up geolocation and ASN information for an IP address.
+///
+/// Lookups are served from local MaxMind DB (MMDB) files, so no external
+/// service is contacted. Each database path must be readable under the path
+/// allowlist. Results from several databases (e.g. GeoLite2-Country and
+/// GeoLite2-ASN) are merged into a single map.
+///
+/// # Arguments
+///
+/// * `args[0]` - IP address (IPv4 or IPv6)
+/// * `args[1]` - Database path or list of paths (optional, overrides configured databases)
+///
+/// # Returns
+///
+/// Map with any of `country_code`, `country_name`, `continent_code`, `city`,
+/// `asn`, and `as_org`, or null if the address is not in any database
+#[cfg(feature = "geoip")]
+pub fn geoip(
+    safety: &Arc<SafetyConfig>,
+    databases: &[std::path::PathBuf],
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let ip_str = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("net.geoip: missing IP argument"))?;
+
+    let ip: std::net::IpAddr = ip_str.parse().map_err(|_| {
+        fusabi_host::Error::host_function(format!("net.geoip: invalid IP address '{}'", ip_str))
+    })?;
+
+    let databases: Vec<std::path::PathBuf> = match args.get(1) {
+        Some(Value::String(path)) => vec![path.into()],
+        Some(Value::List(paths)) => paths
+            .iter()
+            .filter_map(|v| v.as_str().map(std::path::PathBuf::from))
+            .collect(),
+        _ => databases.to_vec(),
+    };
+
+    if databases.is_empty() {
+        return Err(fusabi_host::Error::host_function(
+            "net.geoip: no database configured",
+        ));
+    }
+
+    let mut info = std::collections::HashMap::new();
+
+    for path in &databases {
+        // Check safety
+        safety
+            .paths
+            .check_read(path)
+            .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+        let reader = geoip_reader(path)?;
+        geoip_merge(&reader, ip, &mut info);
+    }
+
+    if info.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    info.insert("ip".into(), Value::String(ip.to_string()));
+    Ok(Value::Map(info))
+}
+
+/// Open (or reuse) a cached MMDB reader.
+#[cfg(feature = "geoip")]
+fn geoip_reader(path: &std::path::Path) -> fusabi_host::Result<Arc<maxminddb::Reader<Vec<u8>>>> {
+    use parking_lot::Mutex;
+    use std::sync::OnceLock;
+
+    type ReaderCache =
+        Mutex<std::collections::HashMap<std::path::PathBuf, Arc<maxminddb::Reader<Vec<u8>>>>>;
+    static READERS: OnceLock<ReaderCache> = OnceLock::new();
+
+    let mut readers = READERS.get_or_init(Default::default).lock();
+    if let Some(reader) = readers.get(path) {
+        return Ok(reader.clone());
+    }
+
+    let reader = maxminddb::Reader::open_readfile(path)
+        .map(Arc::new)
+        .map_err(|e| {
+            fusabi_host::Error::host_function(format!(
+                "net.geoip: failed to open {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+    readers.insert(path.to_path_buf(), reader.clone());
+    Ok(reader)
+}
+
+/// Merge location and ASN records for `ip` from a reader into `info`.
+#[cfg(feature = "geoip")]
+fn geoip_merge(
+    reader: &maxminddb::Reader<Vec<u8>>,
+    ip: std::net::IpAddr,
+    info: &mut std::collections::HashMap<String, Value>,
+) {
+    use maxminddb::geoip2;
+
+    let english = |names: &Option<std::collections::BTreeMap<&str, &str>>| {
+        names
+            .as_ref()
+            .and_then(|n| n.get("en"))
+            .map(|s| Value::String(s.to_string()))
+    };
+
+    if let Ok(city) = reader.lookup::<geoip2::City<'_>>(ip) {
+        if let Some(country) = city.country {
+            if let Some(code) = country.iso_code {
+                info.insert("country_code".into(), Value::String(code.to_string()));
+            }
+            if let Some(name) = english(&country.names) {
+                info.insert("country_name".into(), name);
+            }
+        }
+        if let Some(code) = city.continent.and_then(|c| c.code) {
+            info.insert("continent_code".into(), Value::String(code.to_string()));
+        }
+        if let Some(name) = city.city.and_then(|c| english(&c.names)) {
+            info.insert("city".into(), name);
+        }
+    }
+
+    if let Ok(asn) = reader.lookup::<geoip2::Asn<'_>>(ip) {
+        if let Some(number) = asn.autonomous_system_number {
+            info.insert("asn".into(), Value::Int(number as i64));
+        }
+        if let Some(org) = asn.autonomous_system_organization {
+            info.insert("as_org".into(), Value::String(org.to_string()));
+        }
+    }
+}
+
 /// HTTP request options.
 #[derive(Debug, Clone, Default)]
 pub struct RequestOptions {
@@ -115,6 +298,8 @@ pub struct RequestOptions {
     pub follow_redirects: bool,
     /// Maximum redirects to follow.
     pub max_redirects: usize,
+    /// Response body decompression and decoding.
+    pub decode: DecodeOptions,
 }
 
 impl RequestOptions {
@@ -125,6 +310,7 @@ impl RequestOptions {
             timeout: Some(Duration::from_secs(30)),
             follow_redirects: true,
             max_redirects: 10,
+            decode: DecodeOptions::default(),
         }
     }
 
@@ -145,6 +331,27 @@ impl RequestOptions {
         self.follow_redirects = follow;
         self
     }
+
+    /// Set how response bodies are decompressed and decoded.
+    pub fn with_decode(mut self, decode: DecodeOptions) -> Self {
+        self.decode = decode;
+        self
+    }
+
+    /// Request headers to send, including `Accept-Encoding` when
+    /// decompression is enabled and the caller has not set one.
+    pub fn request_headers(&self) -> std::collections::HashMap<String, String> {
+        let mut headers = self.headers.clone();
+        if let Some(accept) = self.decode.accept_encoding() {
+            if !headers
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case("accept-encoding"))
+            {
+                headers.insert("Accept-Encoding".into(), accept.into());
+            }
+        }
+        headers
+    }
 }
 
 /// HTTP response.
@@ -181,6 +388,116 @@ impl Response {
     }
 }
 
+/// Resolve a host name for [`SafetyConfig::pin_host`], through the DoH
+/// resolver when one is installed and the system resolver otherwise.
+pub fn resolve_host(host: &str) -> crate::Result<Vec<std::net::IpAddr>> {
+    #[cfg(feature = "doh")]
+    if let Some(resolver) = doh::resolver() {
+        return resolver.resolve_host(host);
+    }
+
+    use std::net::ToSocketAddrs;
+    let addresses = (host, 0)
+        .to_socket_addrs()
+        .map_err(|e| crate::Error::network(format!("could not resolve host {}: {}", host, e)))?;
+    let mut ips: Vec<_> = addresses.map(|a| a.ip()).collect();
+    ips.dedup();
+    Ok(ips)
+}
+
+/// Check a request's host, and resolve and pin it unless internal
+/// addresses are allowed. With a DoH resolver installed the host is always
+/// pinned to the resolver's answer.
+fn pin_request(
+    safety: &SafetyConfig,
+    host: &str,
+    function: &str,
+) -> fusabi_host::Result<Option<PinnedHost>> {
+    #[cfg(feature = "doh")]
+    let pin = !safety.allow_internal_addresses || doh::resolver().is_some();
+    #[cfg(not(feature = "doh"))]
+    let pin = !safety.allow_internal_addresses;
+
+    if !pin {
+        return safety
+            .hosts
+            .check(host)
+            .map(|()| None)
+            .map_err(|e| fusabi_host::Error::host_function(e.to_string()));
+    }
+
+    safety
+        .pin_host(host, resolve_host)
+        .map(Some)
+        .map_err(|e| fusabi_host::Error::host_function(format!("{}: {}", function, e)))
+}
+
+/// Build an HTTP client for a request to a pinned host.
+///
+/// Connections to the host go only to its pinned addresses. Redirects are
+/// followed up to `options.max_redirects`, each checked with
+/// [`SafetyConfig::check_redirect`]: the same host keeps its pin, and
+/// another host must pass the allowlist and, unless internal addresses are
+/// allowed, resolve to external addresses only.
+pub fn pinned_client(
+    safety: &Arc<SafetyConfig>,
+    pinned: &PinnedHost,
+    port: u16,
+    options: &RequestOptions,
+) -> reqwest::ClientBuilder {
+    let mut builder =
+        reqwest::Client::builder().resolve_to_addrs(pinned.host(), &pinned.socket_addrs(port));
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(safety.clamp_timeout(timeout));
+    }
+    builder.redirect(redirect_policy(safety.clone(), pinned.clone(), options))
+}
+
+fn redirect_policy(
+    safety: Arc<SafetyConfig>,
+    pinned: PinnedHost,
+    options: &RequestOptions,
+) -> reqwest::redirect::Policy {
+    use reqwest::redirect::Policy;
+
+    if !options.follow_redirects {
+        return Policy::none();
+    }
+    let max_redirects = options.max_redirects;
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() >= max_redirects {
+            return attempt.error(format!("too many redirects (max {})", max_redirects));
+        }
+        let Some(host) = attempt.url().host_str().map(str::to_string) else {
+            return attempt.error("redirect to a URL without a host");
+        };
+        let checked = if safety.allow_internal_addresses {
+            safety.hosts.check(&host)
+        } else {
+            safety
+                .check_redirect(&pinned, &host, resolve_host)
+                .map(|_| ())
+        };
+        match checked {
+            Ok(()) => attempt.follow(),
+            Err(e) => attempt.error(e),
+        }
+    })
+}
+
+// Parse the optional options map of a request function.
+fn decode_options(options: Option<&Value>, function: &str) -> fusabi_host::Result<DecodeOptions> {
+    match options {
+        None | Some(Value::Null) => Ok(DecodeOptions::default()),
+        Some(Value::Map(map)) => DecodeOptions::from_map(map)
+            .map_err(|e| fusabi_host::Error::host_function(format!("{}: {}", function, e))),
+        Some(_) => Err(fusabi_host::Error::host_function(format!(
+            "{}: options must be a map",
+            function
+        ))),
+    }
+}
+
 // Helper function to extract host from URL
 fn extract_host(url: &str) -> fusabi_host::Result<String> {
     // Simple URL parsing
@@ -259,6 +576,132 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[cfg(feature = "geoip")]
+    #[test]
+    fn test_geoip_safety_check() {
+        let safety = Arc::new(SafetyConfig::strict());
+        let ctx = create_test_ctx();
+
+        let result = geoip(
+            &safety,
+            &["/var/lib/geoip/GeoLite2-Country.mmdb".into()],
+            &[Value::String("8.8.8.8".into())],
+            &ctx,
+        );
+        assert!(result.is_err()); // Should fail - database path not allowed
+    }
+
+    #[cfg(feature = "geoip")]
+    #[test]
+    fn test_geoip_invalid_input() {
+        let safety = Arc::new(SafetyConfig::permissive());
+        let ctx = create_test_ctx();
+
+        let result = geoip(&safety, &[], &[Value::String("not-an-ip".into())], &ctx);
+        assert!(result.is_err());
+
+        // No database configured
+        let result = geoip(&safety, &[], &[Value::String("8.8.8.8".into())], &ctx);
+        assert!(result.is_err());
+    }
+
+    /// Build a minimal IPv4 MMDB whose single record covers 0.0.0.0/1.
+    #[cfg(feature = "geoip")]
+    fn geoip_fixture() -> Vec<u8> {
+        fn string(s: &str) -> Vec<u8> {
+            // Lengths of 29 and up take an extra size byte.
+            let mut out = match s.len() {
+                len @ 0..=28 => vec![0x40 | len as u8],
+                len => vec![0x40 | 29, (len - 29) as u8],
+            };
+            out.extend_from_slice(s.as_bytes());
+            out
+        }
+        fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+            let mut out = vec![0xE0 | entries.len() as u8];
+            for (key, value) in entries {
+                out.extend(string(key));
+                out.extend_from_slice(value);
+            }
+            out
+        }
+        let uint16 = |n: u16| {
+            let mut out = vec![0xA2];
+            out.extend_from_slice(&n.to_be_bytes());
+            out
+        };
+        let uint32 = |n: u32| {
+            let mut out = vec![0xC4];
+            out.extend_from_slice(&n.to_be_bytes());
+            out
+        };
+
+        // One 24-bit node: addresses with a leading 0 bit point at the data
+        // record (node_count + 16 + offset 0), the rest are not found.
+        let mut db = vec![0x00, 0x00, 0x11, 0x00, 0x00, 0x01];
+        db.extend_from_slice(&[0; 16]);
+        db.extend(map(&[
+            (
+                "country",
+                map(&[
+                    ("iso_code", string("US")),
+                    ("names", map(&[("en", string("United States"))])),
+                ]),
+            ),
+            ("continent", map(&[("code", string("NA"))])),
+            ("autonomous_system_number", uint32(15169)),
+            ("autonomous_system_organization", string("GOOGLE")),
+        ]));
+        db.extend_from_slice(b"\xAB\xCD\xEFMaxMind.com");
+        db.extend(map(&[
+            ("binary_format_major_version", uint16(2)),
+            ("binary_format_minor_version", uint16(0)),
+            ("build_epoch", vec![0x00, 0x02]),
+            ("database_type", string("Test")),
+            ("description", map(&[])),
+            ("ip_version", uint16(4)),
+            ("languages", vec![0x00, 0x04]),
+            ("node_count", uint32(1)),
+            ("record_size", uint16(24)),
+        ]));
+        db
+    }
+
+    #[cfg(feature = "geoip")]
+    #[test]
+    fn test_geoip_lookup() {
+        let safety = Arc::new(SafetyConfig::permissive());
+        let ctx = create_test_ctx();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.mmdb");
+        std::fs::write(&path, geoip_fixture()).unwrap();
+
+        let result = geoip(
+            &safety,
+            std::slice::from_ref(&path),
+            &[Value::String("8.8.8.8".into())],
+            &ctx,
+        )
+        .unwrap();
+        let info = result.as_map().unwrap();
+        assert_eq!(info.get("ip"), Some(&Value::String("8.8.8.8".into())));
+        assert_eq!(info.get("country_code"), Some(&Value::String("US".into())));
+        assert_eq!(
+            info.get("country_name"),
+            Some(&Value::String("United States".into()))
+        );
+        assert_eq!(
+            info.get("continent_code"),
+            Some(&Value::String("NA".into()))
+        );
+        assert_eq!(info.get("asn"), Some(&Value::Int(15169)));
+        assert_eq!(info.get("as_org"), Some(&Value::String("GOOGLE".into())));
+
+        // Not covered by the database
+        let result = geoip(&safety, &[path], &[Value::String("200.0.0.1".into())], &ctx);
+        assert_eq!(result.unwrap(), Value::Null);
+    }
+
     #[test]
     fn test_request_options() {
         let opts = RequestOptions::new()
@@ -272,6 +715,70 @@ mod tests {
         );
         assert_eq!(opts.timeout, Some(Duration::from_secs(10)));
         assert!(!opts.follow_redirects);
+        assert_eq!(
+            opts.request_headers().get("Accept-Encoding"),
+            Some(&encoding::ACCEPT_ENCODING.to_string())
+        );
+
+        let opts = opts.with_decode(DecodeOptions {
+            decompress: false,
+            ..DecodeOptions::default()
+        });
+        assert!(!opts.request_headers().contains_key("Accept-Encoding"));
+    }
+
+    #[test]
+    fn test_get_refuses_internal_addresses() {
+        let ctx = create_test_ctx();
+        let get = |safety: SafetyConfig, url: &str| {
+            http_get(&Arc::new(safety), None, &[Value::String(url.into())], &ctx)
+        };
+        let hosts = HostAllowlist::none().allow("localhost");
+
+        // Allowed by default, for compatibility
+        assert!(get(
+            SafetyConfig::new().with_hosts(hosts.clone()),
+            "http://localhost/"
+        )
+        .is_ok());
+
+        let safety = SafetyConfig::new()
+            .with_hosts(hosts)
+            .with_allow_internal_addresses(false);
+        let err = get(safety.clone(), "http://localhost:8080/admin").unwrap_err();
+        assert!(err.to_string().contains("resolves to internal address"));
+
+        // Addresses allowlisted literally stay reachable
+        let safety = safety.with_hosts(
+            HostAllowlist::none()
+                .allow("localhost")
+                .allow("127.0.0.1")
+                .allow("::1"),
+        );
+        assert!(get(safety.clone(), "http://127.0.0.1:8080/admin").is_ok());
+        assert!(get(safety, "http://localhost:8080/admin").is_ok());
+    }
+
+    #[test]
+    fn test_get_body_as_bytes() {
+        let safety =
+            Arc::new(SafetyConfig::new().with_hosts(HostAllowlist::none().allow("example.com")));
+        let ctx = create_test_ctx();
+        let mut options = std::collections::HashMap::new();
+        options.insert("body".to_string(), Value::String("bytes".into()));
+
+        let result = http_get(
+            &safety,
+            None,
+            &[
+                Value::String("https://example.com/api".into()),
+                Value::Map(options),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        let map = result.as_map().unwrap();
+        assert!(matches!(map.get("body"), Some(Value::Bytes(_))));
     }
 
     #[test]
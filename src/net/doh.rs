@@ -0,0 +1,587 @@
+//! DNS-over-HTTPS resolution.
+//!
+//! Resolves names through a DoH endpoint using the JSON API
+//! (`application/dns-json`) offered by Cloudflare, Google and most public
+//! resolvers, for environments where plain DNS is blocked or untrusted.
+//! Answers are cached for their TTL, clamped to the resolver's bounds, and
+//! NXDOMAIN results are cached for a short negative TTL.
+//!
+//! When a resolver is installed with [`set_resolver`], `net.get` and
+//! `net.post` resolve their target host through it, after checking it
+//! against the host allowlist, and fail if it does not resolve. Those
+//! requests are still simulated, so this is a reachability check: the
+//! addresses returned by [`resolve_for_request`] are what a real client
+//! must connect to. The resolver endpoint itself must be on the host
+//! allowlist.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Mutex, RwLock};
+
+use fusabi_host::ExecutionContext;
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+use crate::safety::SafetyConfig;
+
+/// Resolver used by the net modules, if any.
+static RESOLVER: RwLock<Option<Arc<DohResolver>>> = RwLock::new(None);
+
+/// Install (or with `None`, remove) the resolver used by the net modules.
+pub fn set_resolver(resolver: Option<Arc<DohResolver>>) {
+    *RESOLVER.write() = resolver;
+}
+
+/// The resolver used by the net modules, if one is installed.
+pub fn resolver() -> Option<Arc<DohResolver>> {
+    RESOLVER.read().clone()
+}
+
+/// DNS record types supported by the resolver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    /// IPv4 address.
+    A,
+    /// IPv6 address.
+    Aaaa,
+    /// Canonical name.
+    Cname,
+    /// Mail exchanger.
+    Mx,
+    /// Name server.
+    Ns,
+    /// Reverse pointer.
+    Ptr,
+    /// Service locator.
+    Srv,
+    /// Text record.
+    Txt,
+}
+
+impl RecordType {
+    /// Parse a record type name, case-insensitively.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "A" => Some(Self::A),
+            "AAAA" => Some(Self::Aaaa),
+            "CNAME" => Some(Self::Cname),
+            "MX" => Some(Self::Mx),
+            "NS" => Some(Self::Ns),
+            "PTR" => Some(Self::Ptr),
+            "SRV" => Some(Self::Srv),
+            "TXT" => Some(Self::Txt),
+            _ => None,
+        }
+    }
+
+    /// Record type name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+            Self::Cname => "CNAME",
+            Self::Mx => "MX",
+            Self::Ns => "NS",
+            Self::Ptr => "PTR",
+            Self::Srv => "SRV",
+            Self::Txt => "TXT",
+        }
+    }
+
+    /// Numeric record type code.
+    pub fn code(&self) -> u64 {
+        match self {
+            Self::A => 1,
+            Self::Ns => 2,
+            Self::Cname => 5,
+            Self::Ptr => 12,
+            Self::Mx => 15,
+            Self::Txt => 16,
+            Self::Aaaa => 28,
+            Self::Srv => 33,
+        }
+    }
+}
+
+/// Fetches a DoH response body for a query URL.
+///
+/// The default [`HttpsTransport`] uses a blocking HTTPS client; embedders
+/// can supply their own to reuse an existing client or for testing.
+pub trait DohTransport: Send + Sync {
+    /// Perform a GET request with `Accept: application/dns-json`.
+    fn get(&self, url: &str, timeout: Duration) -> Result<String>;
+}
+
+/// Transport using a blocking HTTPS client.
+///
+/// Must not be called from within an async runtime's worker thread.
+#[derive(Debug, Default)]
+pub struct HttpsTransport {
+    client: reqwest::blocking::Client,
+}
+
+impl HttpsTransport {
+    /// Create a new transport.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DohTransport for HttpsTransport {
+    fn get(&self, url: &str, timeout: Duration) -> Result<String> {
+        let response = self
+            .client
+            .get(url)
+            .header("accept", "application/dns-json")
+            .timeout(timeout)
+            .send()
+            .map_err(|e| Error::network(format!("DoH request failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(Error::network(format!(
+                "DoH request failed: HTTP {}",
+                response.status()
+            )));
+        }
+
+        response
+            .text()
+            .map_err(|e| Error::network(format!("DoH response unreadable: {}", e)))
+    }
+}
+
+/// A cached lookup result.
+struct CacheEntry {
+    records: Vec<String>,
+    expires: Instant,
+}
+
+/// DNS-over-HTTPS resolver with a TTL-respecting cache.
+pub struct DohResolver {
+    endpoint: String,
+    timeout: Duration,
+    min_ttl: Duration,
+    max_ttl: Duration,
+    negative_ttl: Duration,
+    transport: Arc<dyn DohTransport>,
+    cache: Mutex<HashMap<(String, RecordType), CacheEntry>>,
+}
+
+impl std::fmt::Debug for DohResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DohResolver")
+            .field("endpoint", &self.endpoint)
+            .field("timeout", &self.timeout)
+            .field("min_ttl", &self.min_ttl)
+            .field("max_ttl", &self.max_ttl)
+            .field("negative_ttl", &self.negative_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DohResolver {
+    /// Create a resolver for an `https://` endpoint such as
+    /// `https://cloudflare-dns.com/dns-query`.
+    ///
+    /// The endpoint host must be on the host allowlist.
+    pub fn new(endpoint: impl Into<String>, safety: &SafetyConfig) -> Result<Self> {
+        let endpoint = endpoint.into();
+        let rest = endpoint.strip_prefix("https://").ok_or_else(|| {
+            Error::invalid_argument(format!("DoH endpoint must use https: {}", endpoint))
+        })?;
+        let host = rest
+            .split(['/', '?'])
+            .next()
+            .and_then(|authority| authority.split(':').next())
+            .filter(|host| !host.is_empty())
+            .ok_or_else(|| {
+                Error::invalid_argument(format!("DoH endpoint has no host: {}", endpoint))
+            })?;
+        safety.hosts.check(host)?;
+
+        Ok(Self {
+            endpoint,
+            timeout: Duration::from_secs(5),
+            min_ttl: Duration::from_secs(0),
+            max_ttl: Duration::from_secs(86400),
+            negative_ttl: Duration::from_secs(30),
+            transport: Arc::new(HttpsTransport::new()),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Build a resolver from `net` module options.
+    ///
+    /// Returns `None` unless `resolver` is `doh`. Recognizes `doh_endpoint`
+    /// (required), `doh_timeout_ms`, `doh_min_ttl` and `doh_max_ttl`
+    /// (seconds).
+    pub fn from_options(
+        options: &HashMap<String, String>,
+        safety: &SafetyConfig,
+    ) -> Result<Option<Self>> {
+        match options.get("resolver").map(String::as_str) {
+            None | Some("system") => return Ok(None),
+            Some("doh") => {}
+            Some(other) => {
+                return Err(Error::invalid_argument(format!(
+                    "unknown resolver: {}",
+                    other
+                )))
+            }
+        }
+
+        let endpoint = options.get("doh_endpoint").ok_or_else(|| {
+            Error::invalid_argument("resolver = doh requires doh_endpoint".to_string())
+        })?;
+        let mut resolver = Self::new(endpoint.clone(), safety)?;
+
+        let number = |key: &str| -> Result<Option<u64>> {
+            options
+                .get(key)
+                .map(|v| {
+                    v.parse()
+                        .map_err(|_| Error::invalid_argument(format!("invalid {}: {}", key, v)))
+                })
+                .transpose()
+        };
+        if let Some(ms) = number("doh_timeout_ms")? {
+            resolver.timeout = Duration::from_millis(ms);
+        }
+        if let Some(secs) = number("doh_min_ttl")? {
+            resolver.min_ttl = Duration::from_secs(secs);
+        }
+        if let Some(secs) = number("doh_max_ttl")? {
+            resolver.max_ttl = Duration::from_secs(secs);
+        }
+
+        Ok(Some(resolver))
+    }
+
+    /// Set the request timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Clamp record TTLs to `[min, max]` when caching.
+    pub fn with_ttl_bounds(mut self, min: Duration, max: Duration) -> Self {
+        self.min_ttl = min;
+        self.max_ttl = max;
+        self
+    }
+
+    /// Set how long NXDOMAIN answers are cached.
+    pub fn with_negative_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    /// Use a custom transport.
+    pub fn with_transport(mut self, transport: Arc<dyn DohTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// The resolver endpoint.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Resolve `name`, returning the record data of the requested type.
+    ///
+    /// A name that does not exist resolves to an empty list.
+    pub fn resolve(&self, name: &str, record_type: RecordType) -> Result<Vec<String>> {
+        let name = normalize_name(name)?;
+        let key = (name.clone(), record_type);
+
+        if let Some(entry) = self.cache.lock().get(&key) {
+            if entry.expires > Instant::now() {
+                return Ok(entry.records.clone());
+            }
+        }
+
+        let separator = if self.endpoint.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+        let url = format!(
+            "{}{}name={}&type={}",
+            self.endpoint,
+            separator,
+            name,
+            record_type.as_str()
+        );
+        let body = self.transport.get(&url, self.timeout)?;
+        let (records, ttl) = parse_response(&body, record_type)?;
+
+        let ttl = match ttl {
+            Some(ttl) => ttl.clamp(self.min_ttl, self.max_ttl),
+            None => self.negative_ttl,
+        };
+        self.cache.lock().insert(
+            key,
+            CacheEntry {
+                records: records.clone(),
+                expires: Instant::now() + ttl,
+            },
+        );
+
+        Ok(records)
+    }
+
+    /// Resolve a host to its IPv4 and IPv6 addresses.
+    pub fn resolve_host(&self, host: &str) -> Result<Vec<std::net::IpAddr>> {
+        // Literal addresses need no lookup.
+        if let Ok(ip) = host.trim_matches(['[', ']']).parse() {
+            return Ok(vec![ip]);
+        }
+
+        let mut addresses = Vec::new();
+        for record_type in [RecordType::A, RecordType::Aaaa] {
+            addresses.extend(
+                self.resolve(host, record_type)?
+                    .iter()
+                    .filter_map(|data| data.parse::<std::net::IpAddr>().ok()),
+            );
+        }
+        Ok(addresses)
+    }
+
+    /// Drop all cached answers.
+    pub fn clear_cache(&self) {
+        self.cache.lock().clear();
+    }
+}
+
+/// Lowercase a DNS name, strip a trailing dot, and reject characters that
+/// do not belong in a hostname.
+fn normalize_name(name: &str) -> Result<String> {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    let valid = !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        });
+    if valid {
+        Ok(name)
+    } else {
+        Err(Error::invalid_argument(format!(
+            "invalid DNS name: {}",
+            name
+        )))
+    }
+}
+
+/// Parse a JSON DoH response into record data and the TTL to cache for.
+///
+/// The TTL is `None` for NXDOMAIN and other empty answers, which use the
+/// negative TTL instead.
+fn parse_response(body: &str, record_type: RecordType) -> Result<(Vec<String>, Option<Duration>)> {
+    let json: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| Error::network(format!("invalid DoH response: {}", e)))?;
+
+    match json.get("Status").and_then(|s| s.as_u64()) {
+        Some(0) => {}
+        // NXDOMAIN
+        Some(3) => return Ok((Vec::new(), None)),
+        Some(status) => {
+            return Err(Error::network(format!(
+                "DoH lookup failed: DNS status {}",
+                status
+            )))
+        }
+        None => return Err(Error::network("invalid DoH response: missing Status")),
+    }
+
+    let mut records = Vec::new();
+    let mut ttl: Option<u64> = None;
+    let answers = json.get("Answer").and_then(|a| a.as_array());
+    for answer in answers.into_iter().flatten() {
+        // Skip the CNAME chain unless CNAMEs were asked for.
+        if answer.get("type").and_then(|t| t.as_u64()) != Some(record_type.code()) {
+            continue;
+        }
+        let Some(data) = answer.get("data").and_then(|d| d.as_str()) else {
+            continue;
+        };
+        records.push(data.trim_matches('"').to_string());
+        if let Some(record_ttl) = answer.get("TTL").and_then(|t| t.as_u64()) {
+            ttl = Some(ttl.map_or(record_ttl, |t| t.min(record_ttl)));
+        }
+    }
+
+    if records.is_empty() {
+        return Ok((records, None));
+    }
+    Ok((records, Some(Duration::from_secs(ttl.unwrap_or(0)))))
+}
+
+/// Resolve a request's target host through the installed resolver.
+///
+/// Returns the addresses the connection should use (for example with
+/// `reqwest::ClientBuilder::resolve_to_addrs`), or `None` when no resolver
+/// is installed and the system resolver applies.
+pub fn resolve_for_request(host: &str) -> Result<Option<Vec<std::net::IpAddr>>> {
+    let Some(resolver) = resolver() else {
+        return Ok(None);
+    };
+
+    let addresses = resolver.resolve_host(host)?;
+    if addresses.is_empty() {
+        return Err(Error::network(format!("could not resolve host: {}", host)));
+    }
+    tracing::debug!(
+        host,
+        ?addresses,
+        endpoint = resolver.endpoint(),
+        "resolved via DoH"
+    );
+    Ok(Some(addresses))
+}
+
+/// Resolve a DNS name through the configured DoH resolver.
+///
+/// The queried name must itself be on the host allowlist, so lookups cannot
+/// be used to leak data through DNS.
+///
+/// # Arguments
+///
+/// * `args[0]` - Name to resolve
+/// * `args[1]` - Record type (optional, default `A`)
+///
+/// # Returns
+///
+/// List of record data strings (empty if the name does not exist)
+pub fn resolve(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("net.resolve: missing name argument"))?;
+
+    let record_type = match args.get(1).and_then(|v| v.as_str()) {
+        Some(t) => RecordType::parse(t).ok_or_else(|| {
+            fusabi_host::Error::host_function(format!("net.resolve: unknown record type: {}", t))
+        })?,
+        None => RecordType::A,
+    };
+
+    // Check safety
+    safety
+        .hosts
+        .check(name.trim_end_matches('.'))
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let resolver = resolver().ok_or_else(|| {
+        fusabi_host::Error::host_function("net.resolve: no DoH resolver configured")
+    })?;
+
+    let records = resolver
+        .resolve(name, record_type)
+        .map_err(|e| fusabi_host::Error::host_function(format!("net.resolve: {}", e)))?;
+
+    Ok(Value::List(
+        records.into_iter().map(Value::String).collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::HostAllowlist;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct FakeTransport {
+        body: String,
+        calls: AtomicUsize,
+    }
+
+    impl DohTransport for FakeTransport {
+        fn get(&self, url: &str, _timeout: Duration) -> Result<String> {
+            assert!(url.starts_with("https://dns.example.net/dns-query?name="));
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.body.clone())
+        }
+    }
+
+    fn safety() -> SafetyConfig {
+        SafetyConfig::new().with_hosts(HostAllowlist::none().allow("dns.example.net"))
+    }
+
+    #[test]
+    fn test_resolver_caches_answers() {
+        let transport = Arc::new(FakeTransport {
+            body: r#"{"Status":0,"Answer":[
+                {"name":"www.example.com.","type":5,"TTL":300,"data":"example.com."},
+                {"name":"example.com.","type":1,"TTL":120,"data":"93.184.216.34"},
+                {"name":"example.com.","type":1,"TTL":60,"data":"93.184.216.35"}]}"#
+                .to_string(),
+            calls: AtomicUsize::new(0),
+        });
+        let resolver = DohResolver::new("https://dns.example.net/dns-query", &safety())
+            .unwrap()
+            .with_transport(transport.clone());
+
+        let records = resolver.resolve("WWW.example.com.", RecordType::A).unwrap();
+        assert_eq!(records, vec!["93.184.216.34", "93.184.216.35"]);
+        resolver.resolve("www.example.com", RecordType::A).unwrap();
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 1);
+
+        // A zero max TTL disables caching.
+        let resolver = resolver.with_ttl_bounds(Duration::ZERO, Duration::ZERO);
+        resolver.clear_cache();
+        resolver.resolve("www.example.com", RecordType::A).unwrap();
+        resolver.resolve("www.example.com", RecordType::A).unwrap();
+        assert_eq!(transport.calls.load(Ordering::SeqCst), 3);
+
+        assert!(resolver.resolve("bad name", RecordType::A).is_err());
+    }
+
+    #[test]
+    fn test_response_parsing() {
+        let (records, ttl) = parse_response(r#"{"Status":3}"#, RecordType::A).unwrap();
+        assert!(records.is_empty());
+        assert_eq!(ttl, None);
+
+        assert!(parse_response(r#"{"Status":2}"#, RecordType::A).is_err());
+
+        let (records, _) = parse_response(
+            r#"{"Status":0,"Answer":[{"type":16,"TTL":10,"data":"\"v=spf1 -all\""}]}"#,
+            RecordType::Txt,
+        )
+        .unwrap();
+        assert_eq!(records, vec!["v=spf1 -all"]);
+    }
+
+    #[test]
+    fn test_resolver_endpoint_checks() {
+        assert!(DohResolver::new("http://dns.example.net/dns-query", &safety()).is_err());
+        assert!(DohResolver::new("https://dns.google/resolve", &safety()).is_err());
+
+        let mut options = HashMap::new();
+        assert!(DohResolver::from_options(&options, &safety())
+            .unwrap()
+            .is_none());
+        options.insert("resolver".to_string(), "doh".to_string());
+        assert!(DohResolver::from_options(&options, &safety()).is_err());
+        options.insert(
+            "doh_endpoint".to_string(),
+            "https://dns.example.net/dns-query".to_string(),
+        );
+        options.insert("doh_min_ttl".to_string(), "30".to_string());
+        let resolver = DohResolver::from_options(&options, &safety())
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolver.min_ttl, Duration::from_secs(30));
+    }
+}
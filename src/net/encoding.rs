@@ -0,0 +1,400 @@
+//! Response body decoding.
+//!
+//! Handles `Content-Encoding` (gzip, deflate, brotli) with a cap on the
+//! decompressed size, `Accept-Encoding` negotiation, and decoding text
+//! bodies with the charset from `Content-Type`.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use fusabi_host::{ExecutionContext, Value};
+
+use crate::error::{Error, Result};
+
+/// Default cap on the size of a decompressed body.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// `Accept-Encoding` value sent when decompression is enabled.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// A supported `Content-Encoding`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    /// No encoding.
+    Identity,
+    /// gzip (RFC 1952).
+    Gzip,
+    /// zlib-wrapped deflate (RFC 1950), or raw deflate from servers that
+    /// get it wrong.
+    Deflate,
+    /// Brotli (RFC 7932).
+    Brotli,
+}
+
+impl ContentEncoding {
+    /// Parse a single coding token, case-insensitively.
+    pub fn parse(token: &str) -> Option<Self> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => Some(Self::Identity),
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            "br" => Some(Self::Brotli),
+            _ => None,
+        }
+    }
+
+    /// The coding token.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Identity => "identity",
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+            Self::Brotli => "br",
+        }
+    }
+}
+
+/// How response bodies are decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// Advertise `Accept-Encoding` and decompress encoded bodies.
+    pub decompress: bool,
+    /// Largest decompressed body accepted, in bytes.
+    pub max_decompressed_size: u64,
+    /// Return the body as a string decoded with its charset, rather than
+    /// bytes.
+    pub text: bool,
+    /// Charset to use instead of the one in `Content-Type`.
+    pub charset: Option<String>,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            decompress: true,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            text: true,
+            charset: None,
+        }
+    }
+}
+
+impl DecodeOptions {
+    /// Read options from a script options map.
+    ///
+    /// Recognized keys: `decompress` (bool), `max_decompressed_bytes`
+    /// (int), `body` (`"text"` or `"bytes"`), and `charset` (string).
+    pub fn from_map(options: &HashMap<String, Value>) -> Result<Self> {
+        let mut decode = Self::default();
+        if let Some(value) = options.get("decompress") {
+            decode.decompress = match value {
+                Value::Bool(b) => *b,
+                _ => return Err(Error::invalid_argument("decompress must be a boolean")),
+            };
+        }
+        if let Some(value) = options.get("max_decompressed_bytes") {
+            decode.max_decompressed_size = value
+                .as_int()
+                .and_then(|n| u64::try_from(n).ok())
+                .ok_or_else(|| {
+                    Error::invalid_argument("max_decompressed_bytes must be a non-negative integer")
+                })?;
+        }
+        if let Some(value) = options.get("body") {
+            decode.text = match value.as_str() {
+                Some("text") => true,
+                Some("bytes") => false,
+                _ => {
+                    return Err(Error::invalid_argument(
+                        "body must be \"text\" or \"bytes\"",
+                    ))
+                }
+            };
+        }
+        if let Some(value) = options.get("charset") {
+            let label = value
+                .as_str()
+                .ok_or_else(|| Error::invalid_argument("charset must be a string"))?;
+            if encoding_rs::Encoding::for_label(label.as_bytes()).is_none() {
+                return Err(Error::invalid_argument(format!(
+                    "unknown charset '{}'",
+                    label
+                )));
+            }
+            decode.charset = Some(label.to_string());
+        }
+        Ok(decode)
+    }
+
+    /// The `Accept-Encoding` header to send, if any.
+    pub fn accept_encoding(&self) -> Option<&'static str> {
+        self.decompress.then_some(ACCEPT_ENCODING)
+    }
+}
+
+/// Undo a `Content-Encoding` header value.
+///
+/// Codings are listed in the order they were applied, so they are removed
+/// in reverse. Fails if a coding is unsupported, the data is corrupt, or
+/// any stage would exceed `max_size` bytes.
+pub fn decompress(body: &[u8], content_encoding: &str, max_size: u64) -> Result<Vec<u8>> {
+    let codings = content_encoding
+        .split(',')
+        .map(|token| {
+            ContentEncoding::parse(token).ok_or_else(|| {
+                Error::network(format!("unsupported content encoding '{}'", token.trim()))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut data = body.to_vec();
+    for coding in codings.into_iter().rev() {
+        data = match coding {
+            ContentEncoding::Identity => continue,
+            ContentEncoding::Gzip => read_limited(
+                flate2::read::MultiGzDecoder::new(&data[..]),
+                coding,
+                max_size,
+            )?,
+            ContentEncoding::Deflate if is_zlib_header(&data) => {
+                read_limited(flate2::read::ZlibDecoder::new(&data[..]), coding, max_size)?
+            }
+            ContentEncoding::Deflate => read_limited(
+                flate2::read::DeflateDecoder::new(&data[..]),
+                coding,
+                max_size,
+            )?,
+            ContentEncoding::Brotli => read_limited(
+                brotli_decompressor::Decompressor::new(&data[..], 4096),
+                coding,
+                max_size,
+            )?,
+        };
+    }
+    Ok(data)
+}
+
+fn is_zlib_header(data: &[u8]) -> bool {
+    match data {
+        [cmf, flg, ..] => cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0,
+        _ => false,
+    }
+}
+
+fn read_limited(reader: impl Read, coding: ContentEncoding, max_size: u64) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    reader
+        .take(max_size.saturating_add(1))
+        .read_to_end(&mut out)
+        .map_err(|e| Error::network(format!("invalid {} body: {}", coding.as_str(), e)))?;
+    if out.len() as u64 > max_size {
+        return Err(Error::network(format!(
+            "decompressed body exceeds {} bytes",
+            max_size
+        )));
+    }
+    Ok(out)
+}
+
+/// The `charset` parameter of a `Content-Type` header value.
+pub fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches('"'))
+    })
+}
+
+/// Decode a text body.
+///
+/// The charset is taken from `charset_override`, then from `content_type`,
+/// and defaults to UTF-8; unknown charsets in the header also fall back to
+/// UTF-8. A byte-order mark takes precedence over both, and malformed
+/// sequences are replaced.
+pub fn decode_text(
+    body: &[u8],
+    content_type: Option<&str>,
+    charset_override: Option<&str>,
+) -> String {
+    let encoding = charset_override
+        .or_else(|| content_type.and_then(charset))
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+/// Decode a response body according to its headers and the options.
+pub fn decode_body(
+    body: Vec<u8>,
+    headers: &HashMap<String, String>,
+    options: &DecodeOptions,
+) -> Result<Value> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+
+    let body = match header("content-encoding") {
+        Some(coding) if options.decompress => {
+            decompress(&body, coding, options.max_decompressed_size)?
+        }
+        _ => body,
+    };
+
+    if options.text {
+        Ok(Value::String(decode_text(
+            &body,
+            header("content-type"),
+            options.charset.as_deref(),
+        )))
+    } else {
+        Ok(Value::Bytes(body))
+    }
+}
+
+/// Decode a raw response body.
+///
+/// # Arguments
+///
+/// * `args[0]` - Body (bytes or string)
+/// * `args[1]` - Response headers (map, optional)
+/// * `args[2]` - Options (map, optional): `decompress`,
+///   `max_decompressed_bytes`, `body` (`"text"` or `"bytes"`), `charset`
+///
+/// # Returns
+///
+/// The decompressed body as a string or bytes
+pub fn decode(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let body = match args.first() {
+        Some(Value::Bytes(bytes)) => bytes.clone(),
+        Some(Value::String(s)) => s.clone().into_bytes(),
+        _ => {
+            return Err(fusabi_host::Error::host_function(
+                "net.decode_body: body must be bytes or a string",
+            ))
+        }
+    };
+
+    let headers: HashMap<String, String> = args
+        .get(1)
+        .and_then(|v| v.as_map())
+        .map(|m| {
+            m.iter()
+                .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let to_host = |e: Error| fusabi_host::Error::host_function(format!("net.decode_body: {}", e));
+    let options = match args.get(2).and_then(|v| v.as_map()) {
+        Some(map) => DecodeOptions::from_map(map).map_err(to_host)?,
+        None => DecodeOptions::default(),
+    };
+
+    decode_body(body, &headers, &options).map_err(to_host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    fn compress<W: Write>(mut encoder: W, data: &[u8]) -> W {
+        encoder.write_all(data).unwrap();
+        encoder
+    }
+
+    #[test]
+    fn test_decompress_codings() {
+        let data = b"hello hello hello hello".repeat(10);
+        let gzip = compress(GzEncoder::new(Vec::new(), Compression::default()), &data)
+            .finish()
+            .unwrap();
+        let zlib = compress(ZlibEncoder::new(Vec::new(), Compression::default()), &data)
+            .finish()
+            .unwrap();
+        let raw = compress(
+            DeflateEncoder::new(Vec::new(), Compression::default()),
+            &data,
+        )
+        .finish()
+        .unwrap();
+
+        assert_eq!(decompress(&gzip, "gzip", 1024).unwrap(), data);
+        assert_eq!(decompress(&zlib, "deflate", 1024).unwrap(), data);
+        assert_eq!(decompress(&raw, "Deflate", 1024).unwrap(), data);
+        assert_eq!(decompress(&data, "identity", 1024).unwrap(), data);
+
+        // Stacked codings are removed last-applied first.
+        let stacked = compress(GzEncoder::new(Vec::new(), Compression::default()), &zlib)
+            .finish()
+            .unwrap();
+        assert_eq!(decompress(&stacked, "deflate, gzip", 1024).unwrap(), data);
+
+        assert!(decompress(&gzip, "gzip", 100)
+            .unwrap_err()
+            .to_string()
+            .contains("exceeds 100 bytes"));
+        assert!(decompress(&data, "br", 1024).is_err());
+        assert!(decompress(&data, "zstd", 1024).is_err());
+    }
+
+    #[test]
+    fn test_decode_text_charsets() {
+        assert_eq!(
+            charset("text/html; charset=\"ISO-8859-1\""),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(charset("text/plain"), None);
+
+        let latin1 = [0x63, 0x61, 0x66, 0xe9];
+        assert_eq!(
+            decode_text(&latin1, Some("text/plain; charset=latin1"), None),
+            "café"
+        );
+        assert_eq!(decode_text(&latin1, None, Some("windows-1252")), "café");
+        assert_eq!(
+            decode_text("café".as_bytes(), Some("text/plain; charset=bogus"), None),
+            "café"
+        );
+    }
+
+    #[test]
+    fn test_decode_body_options() {
+        let gzip = compress(
+            GzEncoder::new(Vec::new(), Compression::default()),
+            b"{\"ok\":true}",
+        )
+        .finish()
+        .unwrap();
+        let headers = HashMap::from([
+            ("Content-Encoding".to_string(), "gzip".to_string()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ]);
+
+        assert_eq!(
+            decode_body(gzip.clone(), &headers, &DecodeOptions::default()).unwrap(),
+            Value::String("{\"ok\":true}".into())
+        );
+
+        let options = DecodeOptions::from_map(&HashMap::from([
+            ("decompress".to_string(), Value::Bool(false)),
+            ("body".to_string(), Value::String("bytes".into())),
+        ]))
+        .unwrap();
+        assert_eq!(options.accept_encoding(), None);
+        assert_eq!(
+            decode_body(gzip.clone(), &headers, &options).unwrap(),
+            Value::Bytes(gzip)
+        );
+
+        let bad = HashMap::from([("charset".to_string(), Value::String("nope".into()))]);
+        assert!(DecodeOptions::from_map(&bad).is_err());
+    }
+}
@@ -7,6 +7,7 @@
 //!
 //! - HTTP requests with custom headers and options
 //! - Automatic retries with configurable backoff
+//! - Idempotency keys that stay the same across retries
 //! - Streaming downloads and uploads
 //! - Connection pooling
 //! - Timeout controls per request
@@ -35,6 +36,9 @@ use fusabi_host::{Error, ExecutionContext, Result, Value};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Header carrying the idempotency key of a retried request.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
 /// Make an HTTP request with full control over options.
 ///
 /// # Arguments
@@ -50,10 +54,15 @@ use std::sync::Arc;
 /// - `retry_delay`: Delay between retries in ms (optional, default 1000)
 /// - `body`: Request body (optional)
 /// - `follow_redirects`: Boolean (optional, default true)
+/// - `idempotency_key`: `true` to send a generated `Idempotency-Key` header
+///   on POST and PUT requests with retries, or a string to send as the key
+///   (optional, default false). The key is the same for every attempt, and
+///   an `Idempotency-Key` entry in the headers takes precedence.
 ///
 /// # Returns
 ///
-/// Map with `status`, `headers`, and `body`
+/// Map with `status`, `headers`, and `body`, plus `idempotency_key` when
+/// one was sent
 pub fn request(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
     let method = args
         .first()
@@ -89,7 +98,18 @@ pub fn request(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 
     let _body = options.get("body").and_then(|v| v.as_str());
 
+    // Decided once, so every retry of this request carries the same key.
+    let idempotency_key = idempotency_key(method, headers, options, retries)?;
+    let mut headers = headers.clone();
+    if let Some(key) = &idempotency_key {
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER.to_string(),
+            Value::String(key.clone()),
+        );
+    }
+
     // TODO: Validate URL and check safety allowlist
+
     // TODO: Implement actual HTTP request with reqwest
 
     tracing::info!(
@@ -115,10 +135,83 @@ pub fn request(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
         Value::String("application/json".to_string()),
     );
     response.insert("headers".to_string(), Value::Map(response_headers));
+    if let Some(key) = idempotency_key {
+        response.insert("idempotency_key".to_string(), Value::String(key));
+    }
 
     Ok(Value::Map(response))
 }
 
+/// Resolve the `idempotency_key` option for a request.
+///
+/// Returns the key to add as a header, or `None` when the option is off,
+/// the caller already set the header, or a generated key would not apply
+/// (not a POST or PUT, or no retries).
+fn idempotency_key(
+    method: &str,
+    headers: &HashMap<String, Value>,
+    options: &HashMap<String, Value>,
+    retries: i64,
+) -> Result<Option<String>> {
+    if headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY_HEADER))
+    {
+        return Ok(None);
+    }
+
+    match options.get("idempotency_key") {
+        None | Some(Value::Null) | Some(Value::Bool(false)) => Ok(None),
+        Some(Value::Bool(true)) => {
+            let applies = method.eq_ignore_ascii_case("POST") || method.eq_ignore_ascii_case("PUT");
+            Ok((applies && retries > 0).then(new_idempotency_key))
+        }
+        Some(Value::String(key)) if !key.is_empty() => Ok(Some(key.clone())),
+        Some(_) => Err(Error::host_function(
+            "net_http.request: idempotency_key must be a boolean or non-empty string",
+        )),
+    }
+}
+
+/// Generate a random (version 4) UUID for an `Idempotency-Key` header.
+pub fn new_idempotency_key() -> String {
+    use std::collections::hash_map::RandomState;
+    use std::hash::{BuildHasher, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::SystemTime;
+
+    // Each `RandomState` is randomly keyed, and the counter and clock keep
+    // keys distinct even if two states happened to match.
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    let high = hasher.finish();
+    hasher.write_u64(high);
+    let low = hasher.finish();
+
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&high.to_be_bytes());
+    bytes[8..].copy_from_slice(&low.to_be_bytes());
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
 /// Download a file as a stream.
 ///
 /// Returns a stream handle that can be used to read chunks.
@@ -224,3 +317,65 @@ pub fn check_request_safety(_safety: &Arc<SafetyConfig>, _url: &str) -> Result<(
     // TODO: Validate timeout against max_timeout
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let key = new_idempotency_key();
+        assert_eq!(key.len(), 36);
+        assert_eq!(&key[14..15], "4");
+        assert!(matches!(&key[19..20], "8" | "9" | "a" | "b"));
+        assert_ne!(key, new_idempotency_key());
+
+        let none = HashMap::new();
+        let mut options = HashMap::new();
+        options.insert("idempotency_key".to_string(), Value::Bool(true));
+        assert!(idempotency_key("POST", &none, &options, 0)
+            .unwrap()
+            .is_none());
+        assert!(idempotency_key("GET", &none, &options, 3)
+            .unwrap()
+            .is_none());
+        assert!(idempotency_key("put", &none, &options, 3)
+            .unwrap()
+            .is_some());
+
+        let mut headers = HashMap::new();
+        headers.insert("idempotency-key".to_string(), Value::String("mine".into()));
+        assert!(idempotency_key("POST", &headers, &options, 3)
+            .unwrap()
+            .is_none());
+
+        options.insert("idempotency_key".to_string(), Value::Int(1));
+        assert!(idempotency_key("POST", &none, &options, 3).is_err());
+
+        let ctx = create_test_ctx();
+        options.insert(
+            "idempotency_key".to_string(),
+            Value::String("order-42".into()),
+        );
+        let response = request(
+            &[
+                Value::String("POST".into()),
+                Value::String("https://api.example.com/orders".into()),
+                Value::Map(HashMap::new()),
+                Value::Map(options),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            response.as_map().unwrap()["idempotency_key"],
+            Value::String("order-42".into())
+        );
+    }
+}
@@ -1,11 +1,40 @@
 //! Observability module for Fusabi.
 //!
 //! Provides logging, tracing, and metrics integration using OpenTelemetry.
+//!
+//! [`init`] installs a tracer provider that exports spans over OTLP when
+//! [`ObservabilityConfig::otlp`] is set; [`shutdown`] flushes it. See
+//! [`tracer`] for the `trace` host functions. [`init_metrics`] exports a
+//! [`MetricsRegistry`] to the same collector, and [`dump_bundle`] writes a
+//! diagnostic bundle for bug reports.
+
+pub mod bundle;
+pub mod health;
+pub mod log_level;
+pub mod logger;
+pub mod otlp;
+pub mod propagation;
+pub mod slo;
+pub mod tracer;
+
+pub use bundle::dump_bundle;
+pub use log_level::{layer, set_level};
+pub use logger::{RecentFilter, ScriptLogger};
+pub use otlp::{MetricReaderConfig, OtlpConfig, OtlpProtocol, Temporality};
+pub use propagation::TraceContext;
+pub use slo::{
+    AlertHook, AlertState, MemoryAlertHook, ModuleSlo, SloAlert, SloConfig, SloTracker,
+    TracingAlertHook,
+};
+pub use tracer::{init, shutdown, tracer_provider, SpanExporter, TracerProvider};
 
 use fusabi_host::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::metrics::MetricsRegistry;
+
 /// Configuration for observability features.
 #[derive(Debug, Clone)]
 pub struct ObservabilityConfig {
@@ -19,6 +48,10 @@ pub struct ObservabilityConfig {
     pub tracing_enabled: bool,
     /// Whether to enable metrics.
     pub metrics_enabled: bool,
+    /// Where to export spans and metrics; `None` keeps them in process.
+    pub otlp: Option<OtlpConfig>,
+    /// Periodic metrics export, used when `otlp` is set.
+    pub metric_reader: MetricReaderConfig,
 }
 
 impl Default for ObservabilityConfig {
@@ -29,6 +62,8 @@ impl Default for ObservabilityConfig {
             resource_attributes: HashMap::new(),
             tracing_enabled: true,
             metrics_enabled: true,
+            otlp: None,
+            metric_reader: MetricReaderConfig::default(),
         }
     }
 }
@@ -65,6 +100,51 @@ impl ObservabilityConfig {
         self.metrics_enabled = enabled;
         self
     }
+
+    /// Export spans and metrics over OTLP.
+    pub fn with_otlp(mut self, otlp: OtlpConfig) -> Self {
+        self.otlp = Some(otlp);
+        self
+    }
+
+    /// Set the interval and temporality of metrics export.
+    pub fn with_metric_reader(mut self, reader: MetricReaderConfig) -> Self {
+        self.metric_reader = reader;
+        self
+    }
+}
+
+/// Export `metrics` over OTLP every [`MetricReaderConfig::interval`],
+/// with the resource and transport of [`init`].
+///
+/// Does nothing when metrics are disabled or [`ObservabilityConfig::otlp`]
+/// is unset. The export runs on the registry's flush thread, replacing any
+/// earlier interval; `MetricsRegistry::shutdown` stops it after a final
+/// export.
+pub fn init_metrics(
+    metrics: &Arc<MetricsRegistry>,
+    config: &ObservabilityConfig,
+) -> crate::Result<()> {
+    let Some(otlp) = config.otlp.as_ref().filter(|_| config.metrics_enabled) else {
+        return Ok(());
+    };
+    #[cfg(feature = "observability-otlp")]
+    {
+        let exporter = otlp::OtlpMetricsExporter::new(
+            otlp.clone(),
+            tracer::Resource::from_config(config),
+            config.metric_reader.temporality,
+        )?;
+        metrics.add_exporter(Arc::new(exporter))?;
+        metrics.start_flush_interval(config.metric_reader.interval)
+    }
+    #[cfg(not(feature = "observability-otlp"))]
+    {
+        let _ = (metrics, otlp);
+        Err(crate::Error::ModuleNotAvailable(
+            "OTLP metrics export requires the observability-otlp feature".to_string(),
+        ))
+    }
 }
 
 /// Span context for distributed tracing.
@@ -148,6 +228,18 @@ impl LogLevel {
     }
 }
 
+impl From<LogLevel> for tracing::level_filters::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Trace => Self::TRACE,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Error => Self::ERROR,
+        }
+    }
+}
+
 /// Structured log entry.
 #[derive(Debug, Clone)]
 pub struct LogEntry {
@@ -184,17 +276,45 @@ impl LogEntry {
         self.fields.insert(key.into(), value);
         self
     }
+
+    /// Convert to a map with `timestamp_ns`, `level`, `message` and
+    /// `fields` keys.
+    pub fn to_value(&self) -> Value {
+        let mut map = HashMap::new();
+        map.insert(
+            "timestamp_ns".to_string(),
+            Value::Int(self.timestamp_ns as i64),
+        );
+        map.insert(
+            "level".to_string(),
+            Value::String(self.level.as_str().to_string()),
+        );
+        map.insert("message".to_string(), Value::String(self.message.clone()));
+        map.insert("fields".to_string(), Value::Map(self.fields.clone()));
+        Value::Map(map)
+    }
+
+    /// Encode [`to_value`](Self::to_value) as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        self.to_value().to_json_string()
+    }
 }
 
 /// Generate a random hex ID of the specified byte length.
 fn generate_id(bytes: usize) -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
     use std::time::SystemTime;
 
-    // Simple pseudo-random ID generation (not cryptographically secure)
+    // Simple pseudo-random ID generation (not cryptographically secure).
+    // The counter keeps IDs generated within the same clock tick distinct.
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
     let seed = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap_or_default()
-        .as_nanos() as u64;
+        .as_nanos() as u64
+        ^ COUNTER
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_mul(0x9e37_79b9_7f4a_7c15);
 
     let mut result = String::with_capacity(bytes * 2);
     let mut state = seed;
@@ -223,6 +343,32 @@ mod tests {
         assert!(!config.metrics_enabled);
     }
 
+    #[test]
+    fn test_init_metrics() {
+        let metrics = Arc::new(MetricsRegistry::new());
+        // Nothing to export to, or metrics disabled.
+        init_metrics(&metrics, &ObservabilityConfig::default()).unwrap();
+        let disabled = ObservabilityConfig::default()
+            .with_otlp(OtlpConfig::default())
+            .with_metrics(false);
+        init_metrics(&metrics, &disabled).unwrap();
+
+        let config = ObservabilityConfig::default()
+            .with_otlp(OtlpConfig::default())
+            .with_metric_reader(
+                MetricReaderConfig::new()
+                    .with_interval(Duration::from_secs(3600))
+                    .with_temporality(Temporality::Delta),
+            );
+        let result = init_metrics(&metrics, &config);
+        if cfg!(feature = "observability-otlp") {
+            result.unwrap();
+            metrics.shutdown().ok();
+        } else {
+            assert!(matches!(result, Err(crate::Error::ModuleNotAvailable(_))));
+        }
+    }
+
     #[test]
     fn test_span_context() {
         let span =
@@ -246,5 +392,6 @@ mod tests {
     fn test_generate_id() {
         let id = generate_id(8);
         assert_eq!(id.len(), 16); // 8 bytes = 16 hex chars
+        assert_ne!(generate_id(8), generate_id(8));
     }
 }
@@ -0,0 +1,399 @@
+//! Diagnostic bundles for bug reports.
+//!
+//! [`dump_bundle`] writes one JSON document describing a
+//! [`StdlibRegistry`] at the moment of the call:
+//!
+//! - `versions`: crate version, target platform and enabled features
+//! - `config`: execution mode, module settings and the safety policy
+//! - `recent_calls`: newest events of the registry's
+//!   [`AuditLog`](crate::AuditLog), or null when none is installed
+//! - `recent_logs`: newest script log entries
+//! - `metrics`: a metrics snapshot (null without the `metrics` feature)
+//! - `slo`: per-module SLO status
+//! - `open_handles`: streams, watchers, timers and other handles scripts
+//!   have not closed, by kind
+//!
+//! Values under secret-like keys (`password`, `token`, `authorization`,
+//! ...) in module options and log fields are replaced with
+//! [`REDACTED`]; audit events carry only argument hashes. Log messages are
+//! copied as written.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use fusabi_host::Value;
+
+use super::RecentFilter;
+use crate::config::ModuleConfig;
+use crate::registry::{ExecutionMode, StdlibRegistry};
+use crate::safety::{is_secret_key, AuditQuery, SafetyConfig};
+
+/// Format version of the bundle document.
+pub const BUNDLE_VERSION: i64 = 1;
+
+/// Replacement for redacted values.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Audit events and log entries kept in a bundle.
+const RECENT_LIMIT: usize = 200;
+
+/// Copy `value`, replacing map entries under secret-like keys.
+fn redact(value: &Value) -> Value {
+    match value {
+        Value::Map(entries) => Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| {
+                    let v = if is_secret_key(k) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact(v)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::List(items) => Value::List(items.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+/// Write a diagnostic bundle for `stdlib` to `path`, replacing any file
+/// there.
+///
+/// Fails if `path` is not writable under the registry's path allowlist.
+/// The document is written to a temporary file next to `path` and renamed
+/// into place, so readers never see a partial bundle.
+pub fn dump_bundle(stdlib: &StdlibRegistry, path: impl AsRef<Path>) -> crate::Result<()> {
+    let path = path.as_ref();
+    stdlib.safety().paths.check_write(path)?;
+
+    let json = bundle(stdlib).to_json_string();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// The bundle document for `stdlib`, as written by [`dump_bundle`].
+pub fn bundle(stdlib: &StdlibRegistry) -> Value {
+    let recent_calls = match stdlib.audit_log() {
+        Some(log) => Value::List(
+            log.query(&AuditQuery::new().with_limit(RECENT_LIMIT))
+                .iter()
+                .map(|event| event.to_value())
+                .collect(),
+        ),
+        None => Value::Null,
+    };
+    let recent_logs = stdlib
+        .logger()
+        .recent(&RecentFilter {
+            limit: Some(RECENT_LIMIT),
+            ..Default::default()
+        })
+        .iter()
+        .map(|entry| redact(&entry.to_value()))
+        .collect();
+
+    #[cfg(feature = "metrics")]
+    let metrics = stdlib.metrics().snapshot().to_value();
+    #[cfg(not(feature = "metrics"))]
+    let metrics = Value::Null;
+
+    let slo = stdlib
+        .slo_tracker()
+        .status()
+        .iter()
+        .map(|status| status.to_value())
+        .collect();
+
+    let mut m = HashMap::new();
+    m.insert("bundle_version".into(), Value::Int(BUNDLE_VERSION));
+    m.insert(
+        "generated_at_ns".into(),
+        Value::Int(super::tracer::unix_nanos() as i64),
+    );
+    m.insert("versions".into(), versions());
+    m.insert("config".into(), config(stdlib));
+    m.insert("recent_calls".into(), recent_calls);
+    m.insert("recent_logs".into(), Value::List(recent_logs));
+    m.insert("metrics".into(), metrics);
+    m.insert("slo".into(), Value::List(slo));
+    m.insert("open_handles".into(), open_handles());
+    Value::Map(m)
+}
+
+fn versions() -> Value {
+    let features: &[(&str, bool)] = &[
+        ("process", cfg!(feature = "process")),
+        ("fs", cfg!(feature = "fs")),
+        ("fs-watch", cfg!(feature = "fs-watch")),
+        ("fs-trash", cfg!(feature = "fs-trash")),
+        ("path", cfg!(feature = "path")),
+        ("env", cfg!(feature = "env")),
+        ("format", cfg!(feature = "format")),
+        ("net", cfg!(feature = "net")),
+        ("time", cfg!(feature = "time")),
+        ("metrics", cfg!(feature = "metrics")),
+        ("terminal", cfg!(feature = "terminal")),
+        ("gpu", cfg!(feature = "gpu")),
+        ("fs_stream", cfg!(feature = "fs_stream")),
+        ("net_http", cfg!(feature = "net_http")),
+        ("terminal-ui", cfg!(feature = "terminal-ui")),
+        ("observability-otlp", cfg!(feature = "observability-otlp")),
+        ("k8s", cfg!(feature = "k8s")),
+        ("mcp", cfg!(feature = "mcp")),
+    ];
+    let string = |s: &str| Value::String(s.to_string());
+
+    let mut m = HashMap::new();
+    m.insert(
+        "fusabi_stdlib_ext".into(),
+        string(env!("CARGO_PKG_VERSION")),
+    );
+    m.insert("os".into(), string(std::env::consts::OS));
+    m.insert("arch".into(), string(std::env::consts::ARCH));
+    m.insert(
+        "features".into(),
+        Value::List(
+            features
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| string(name))
+                .collect(),
+        ),
+    );
+    Value::Map(m)
+}
+
+fn config(stdlib: &StdlibRegistry) -> Value {
+    let config = stdlib.config();
+    let mode = match stdlib.mode() {
+        ExecutionMode::Normal => "normal",
+        ExecutionMode::DryRun => "dry_run",
+        ExecutionMode::Trace => "trace",
+    };
+    let modules = [
+        ("process", &config.process),
+        ("fs", &config.fs),
+        ("path", &config.path),
+        ("env", &config.env),
+        ("format", &config.format),
+        ("net", &config.net),
+        ("time", &config.time),
+        ("metrics", &config.metrics),
+    ]
+    .into_iter()
+    .map(|(name, module)| (name.to_string(), module_config(module)))
+    .collect();
+
+    let mut m = HashMap::new();
+    m.insert("mode".into(), Value::String(mode.to_string()));
+    m.insert("modules".into(), Value::Map(modules));
+    m.insert("safety".into(), safety(stdlib.safety()));
+    Value::Map(m)
+}
+
+fn module_config(module: &ModuleConfig) -> Value {
+    let options = module
+        .options
+        .iter()
+        .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+        .collect();
+
+    let mut m = HashMap::new();
+    m.insert("enabled".into(), Value::Bool(module.enabled));
+    m.insert(
+        "timeout_ms".into(),
+        module
+            .timeout
+            .map_or(Value::Null, |t| Value::Int(t.as_millis() as i64)),
+    );
+    m.insert("options".into(), redact(&Value::Map(options)));
+    Value::Map(m)
+}
+
+fn safety(safety: &SafetyConfig) -> Value {
+    let strings = |items: Vec<String>| {
+        let mut items = items;
+        items.sort();
+        Value::List(items.into_iter().map(Value::String).collect())
+    };
+    let paths = |paths: &std::collections::HashSet<std::path::PathBuf>| {
+        strings(paths.iter().map(|p| p.display().to_string()).collect())
+    };
+
+    let mut path_lists = HashMap::new();
+    path_lists.insert("read".into(), paths(&safety.paths.read));
+    path_lists.insert("write".into(), paths(&safety.paths.write));
+    path_lists.insert("deny".into(), paths(&safety.paths.deny));
+    let mut host_lists = HashMap::new();
+    host_lists.insert(
+        "allowed".into(),
+        strings(safety.hosts.allowed.iter().cloned().collect()),
+    );
+    host_lists.insert(
+        "denied".into(),
+        strings(safety.hosts.denied.iter().cloned().collect()),
+    );
+    let grants = safety
+        .temporary_grants()
+        .iter()
+        .map(|(grant, _)| {
+            let (kind, target) = grant.describe();
+            Value::String(format!("{}:{}", kind, target))
+        })
+        .collect();
+
+    let mut m = HashMap::new();
+    m.insert("paths".into(), Value::Map(path_lists));
+    m.insert("hosts".into(), Value::Map(host_lists));
+    m.insert(
+        "env_vars".into(),
+        safety
+            .env_vars
+            .as_ref()
+            .map_or(Value::Null, |vars| strings(vars.iter().cloned().collect())),
+    );
+    m.insert("allow_process".into(), Value::Bool(safety.allow_process));
+    m.insert(
+        "allowed_commands".into(),
+        safety
+            .allowed_commands
+            .as_ref()
+            .map_or(Value::Null, |commands| {
+                strings(commands.iter().cloned().collect())
+            }),
+    );
+    m.insert(
+        "allow_privilege_escalation".into(),
+        Value::Bool(safety.allow_privilege_escalation),
+    );
+    m.insert(
+        "allow_log_control".into(),
+        Value::Bool(safety.allow_log_control),
+    );
+    m.insert(
+        "allow_clipboard".into(),
+        Value::Bool(safety.allow_clipboard),
+    );
+    m.insert("allow_k8s".into(), Value::Bool(safety.allow_k8s));
+    m.insert(
+        "allow_internal_addresses".into(),
+        Value::Bool(safety.allow_internal_addresses),
+    );
+    m.insert(
+        "trash_on_remove".into(),
+        Value::Bool(safety.trash_on_remove),
+    );
+    m.insert(
+        "default_timeout_ms".into(),
+        Value::Int(safety.default_timeout.as_millis() as i64),
+    );
+    m.insert(
+        "max_timeout_ms".into(),
+        Value::Int(safety.max_timeout.as_millis() as i64),
+    );
+    m.insert("temporary_grants".into(), Value::List(grants));
+    Value::Map(m)
+}
+
+fn open_handles() -> Value {
+    #[allow(unused_mut)]
+    let mut handles: Vec<(&str, usize)> = Vec::new();
+    #[cfg(feature = "fs_stream")]
+    handles.push(("fs_stream", crate::fs_stream::open_handles()));
+    #[cfg(feature = "fs-watch")]
+    handles.push(("fs.watch", crate::fs::watch::open_handles()));
+    #[cfg(feature = "format")]
+    handles.push(("format.json_writer", crate::format::open_json_writers()));
+    #[cfg(feature = "time")]
+    {
+        handles.push(("time.limiter", crate::time::open_limiters()));
+        handles.push(("time.timer", crate::time::open_timers()));
+    }
+    #[cfg(feature = "k8s")]
+    {
+        handles.push(("k8s.pod_logs", crate::k8s::logs::open_handles()));
+        handles.push(("k8s.port_forward", crate::k8s::port_forward::open_handles()));
+        handles.push(("k8s.watch", crate::k8s::watch::open_handles()));
+    }
+    #[cfg(feature = "terminal")]
+    {
+        handles.push((
+            "terminal.progress",
+            crate::terminal::progress::open_handles(),
+        ));
+        handles.push(("terminal.events", crate::terminal::events::open_handles()));
+    }
+    #[cfg(feature = "terminal-ui")]
+    {
+        handles.push((
+            "terminal_ui.log_pane",
+            crate::terminal_ui::log_pane::open_handles(),
+        ));
+        handles.push((
+            "terminal_ui.table",
+            crate::terminal_ui::table::open_handles(),
+        ));
+        handles.push((
+            "terminal_ui.palette",
+            crate::terminal_ui::palette::open_handles(),
+        ));
+    }
+
+    Value::Map(
+        handles
+            .into_iter()
+            .map(|(kind, count)| (kind.to_string(), Value::Int(count as i64)))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observability::{LogEntry, LogLevel};
+    use crate::{AuditLog, StdlibConfig};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_dump_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.json");
+
+        let mut config = StdlibConfig::default();
+        config
+            .metrics
+            .options
+            .insert("otlp_auth_token".into(), "hunter2".into());
+        config.safety = SafetyConfig::new()
+            .with_audit_log(Arc::new(AuditLog::new()))
+            .with_paths(crate::safety::PathAllowlist::none().allow_write(dir.path()));
+        let stdlib = StdlibRegistry::new(config).unwrap();
+        stdlib
+            .logger()
+            .log(
+                &LogEntry::new(LogLevel::Info, "connecting")
+                    .with_field("password", Value::String("hunter2".into())),
+            )
+            .unwrap();
+
+        dump_bundle(&stdlib, &path).unwrap();
+        let json = std::fs::read_to_string(&path).unwrap();
+        assert!(!json.contains("hunter2"), "{}", json);
+        let bundle = Value::from_json_str(&json).unwrap();
+        let bundle = bundle.as_map().unwrap();
+        assert_eq!(bundle["bundle_version"], Value::Int(BUNDLE_VERSION));
+        assert_eq!(bundle["recent_calls"], Value::List(Vec::new()));
+        assert!(json.contains("connecting"));
+        assert!(json.contains(REDACTED));
+        assert!(bundle["open_handles"].as_map().is_some());
+
+        // The path must be writable under the allowlist.
+        let denied = tempfile::tempdir().unwrap();
+        assert!(dump_bundle(&stdlib, denied.path().join("bundle.json")).is_err());
+    }
+}
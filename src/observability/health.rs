@@ -0,0 +1,399 @@
+//! Health and readiness reporting.
+//!
+//! Components report their status with [`HealthRegistry::set`] (or the
+//! `health.set` host function), and the aggregated report is available from
+//! [`HealthRegistry::report`]. [`serve`] exposes the report over HTTP:
+//!
+//! - `GET /livez` - always `200 OK` while the server is running
+//! - `GET /readyz` - `200 OK` unless a component is unhealthy, `503` otherwise
+//! - `GET /health` - the full report as JSON
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use parking_lot::RwLock;
+
+use fusabi_host::ExecutionContext;
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+
+/// Health status of a component, ordered from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthStatus {
+    /// Operating normally.
+    Healthy,
+    /// Operating with reduced functionality.
+    Degraded,
+    /// Not operating.
+    Unhealthy,
+}
+
+impl HealthStatus {
+    /// Convert to string representation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+
+    /// Parse from string representation.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "healthy" | "ok" | "up" => Some(HealthStatus::Healthy),
+            "degraded" => Some(HealthStatus::Degraded),
+            "unhealthy" | "down" => Some(HealthStatus::Unhealthy),
+            _ => None,
+        }
+    }
+}
+
+/// Last reported health of a component.
+#[derive(Debug, Clone)]
+pub struct ComponentHealth {
+    /// Reported status.
+    pub status: HealthStatus,
+    /// Optional human-readable detail.
+    pub detail: Option<String>,
+    /// When the status was last reported.
+    pub updated_at: SystemTime,
+}
+
+/// Aggregated health of all components.
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// Worst status across all components (healthy if none are registered).
+    pub status: HealthStatus,
+    /// Per-component health, sorted by name.
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+impl HealthReport {
+    /// Whether the service should receive traffic.
+    pub fn is_ready(&self) -> bool {
+        self.status != HealthStatus::Unhealthy
+    }
+
+    /// Convert to a Fusabi value.
+    pub fn to_value(&self) -> Value {
+        let components = self
+            .components
+            .iter()
+            .map(|(name, health)| {
+                let mut map = HashMap::new();
+                map.insert(
+                    "status".to_string(),
+                    Value::String(health.status.as_str().to_string()),
+                );
+                map.insert(
+                    "detail".to_string(),
+                    health
+                        .detail
+                        .clone()
+                        .map(Value::String)
+                        .unwrap_or(Value::Null),
+                );
+                let updated_at = health
+                    .updated_at
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as i64;
+                map.insert("updated_at".to_string(), Value::Int(updated_at));
+                (name.clone(), Value::Map(map))
+            })
+            .collect();
+
+        let mut map = HashMap::new();
+        map.insert(
+            "status".to_string(),
+            Value::String(self.status.as_str().to_string()),
+        );
+        map.insert("ready".to_string(), Value::Bool(self.is_ready()));
+        map.insert("components".to_string(), Value::Map(components));
+        Value::Map(map)
+    }
+}
+
+/// Registry of component health statuses.
+#[derive(Debug, Default)]
+pub struct HealthRegistry {
+    components: RwLock<HashMap<String, ComponentHealth>>,
+}
+
+impl HealthRegistry {
+    /// Create a new, empty health registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report the status of a component.
+    pub fn set(&self, component: impl Into<String>, status: HealthStatus, detail: Option<String>) {
+        self.components.write().insert(
+            component.into(),
+            ComponentHealth {
+                status,
+                detail,
+                updated_at: SystemTime::now(),
+            },
+        );
+    }
+
+    /// Stop tracking a component.
+    pub fn remove(&self, component: &str) -> bool {
+        self.components.write().remove(component).is_some()
+    }
+
+    /// Aggregate all component statuses.
+    pub fn report(&self) -> HealthReport {
+        let components: BTreeMap<_, _> = self
+            .components
+            .read()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let status = components
+            .values()
+            .map(|c| c.status)
+            .max()
+            .unwrap_or(HealthStatus::Healthy);
+
+        HealthReport { status, components }
+    }
+}
+
+static HEALTH: OnceLock<HealthRegistry> = OnceLock::new();
+
+/// Get the global health registry used by the host functions.
+pub fn global() -> &'static HealthRegistry {
+    HEALTH.get_or_init(HealthRegistry::new)
+}
+
+/// Report the status of a component.
+///
+/// # Arguments
+///
+/// * `args[0]` - Component name
+/// * `args[1]` - Status: `"healthy"`, `"degraded"`, or `"unhealthy"`
+/// * `args[2]` - Detail message (optional)
+pub fn set(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let component = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("health.set: missing component"))?;
+
+    let status = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("health.set: missing status"))?;
+    let status = HealthStatus::parse(status).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("health.set: unknown status '{}'", status))
+    })?;
+
+    let detail = args.get(2).and_then(|v| v.as_str()).map(String::from);
+
+    global().set(component, status, detail);
+    Ok(Value::Null)
+}
+
+/// Get the aggregated health report.
+///
+/// # Returns
+///
+/// Map with `status`, `ready`, and `components` (map of component name to
+/// `{status, detail, updated_at}`)
+pub fn report(_args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    Ok(global().report().to_value())
+}
+
+/// A running health HTTP server.
+///
+/// The server stops when [`HealthServer::shutdown`] is called or the handle
+/// is dropped.
+pub struct HealthServer {
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HealthServer {
+    /// Address the server is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop the server and wait for it to exit.
+    pub fn shutdown(mut self) {
+        self.stop_and_join();
+    }
+
+    fn stop_and_join(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stop.store(true, Ordering::SeqCst);
+            // Wake the accept loop
+            let _ = TcpStream::connect_timeout(&self.addr, Duration::from_millis(200));
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for HealthServer {
+    fn drop(&mut self) {
+        self.stop_and_join();
+    }
+}
+
+/// Serve the global health report over HTTP on a background thread.
+///
+/// Pass port `0` to bind an ephemeral port; use
+/// [`HealthServer::local_addr`] to find it.
+pub fn serve(addr: impl ToSocketAddrs) -> Result<HealthServer> {
+    serve_registry(addr, global())
+}
+
+/// Serve a specific health registry over HTTP on a background thread.
+pub fn serve_registry(
+    addr: impl ToSocketAddrs,
+    registry: &'static HealthRegistry,
+) -> Result<HealthServer> {
+    let listener = TcpListener::bind(addr)?;
+    let addr = listener.local_addr()?;
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let thread_stop = stop.clone();
+    let thread = std::thread::Builder::new()
+        .name("fusabi-health".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                if thread_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    if let Err(e) = handle_connection(stream, registry) {
+                        tracing::debug!(error = %e, "health request failed");
+                    }
+                }
+            }
+        })
+        .map_err(|e| Error::Internal(format!("failed to spawn health server: {}", e)))?;
+
+    Ok(HealthServer {
+        addr,
+        stop,
+        thread: Some(thread),
+    })
+}
+
+fn handle_connection(stream: TcpStream, registry: &HealthRegistry) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain headers
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 2 {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let path = parts.next().unwrap_or("");
+
+    let report = registry.report();
+    let (status, content_type, body) = match (method, path) {
+        ("GET", "/livez") => ("200 OK", "text/plain", "ok\n".to_string()),
+        ("GET", "/readyz") if report.is_ready() => ("200 OK", "text/plain", "ready\n".to_string()),
+        ("GET", "/readyz") => (
+            "503 Service Unavailable",
+            "text/plain",
+            "not ready\n".to_string(),
+        ),
+        ("GET", "/health") => {
+            let status = if report.is_ready() {
+                "200 OK"
+            } else {
+                "503 Service Unavailable"
+            };
+            (
+                status,
+                "application/json",
+                report.to_value().to_json_string(),
+            )
+        }
+        ("GET", _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "method not allowed\n".to_string(),
+        ),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_report_aggregates_worst_status() {
+        let registry = HealthRegistry::new();
+        assert_eq!(registry.report().status, HealthStatus::Healthy);
+
+        registry.set("db", HealthStatus::Healthy, None);
+        registry.set("cache", HealthStatus::Degraded, Some("high latency".into()));
+        let report = registry.report();
+        assert_eq!(report.status, HealthStatus::Degraded);
+        assert!(report.is_ready());
+
+        registry.set("db", HealthStatus::Unhealthy, None);
+        assert!(!registry.report().is_ready());
+
+        assert!(registry.remove("db"));
+        assert_eq!(registry.report().status, HealthStatus::Degraded);
+    }
+
+    #[test]
+    fn test_http_endpoints() {
+        static REGISTRY: OnceLock<HealthRegistry> = OnceLock::new();
+        let registry = REGISTRY.get_or_init(HealthRegistry::new);
+        let server = serve_registry("127.0.0.1:0", registry).unwrap();
+        let addr = server.local_addr();
+
+        assert!(get(addr, "/livez").starts_with("HTTP/1.1 200"));
+        assert!(get(addr, "/readyz").starts_with("HTTP/1.1 200"));
+
+        registry.set("worker", HealthStatus::Unhealthy, Some("stalled".into()));
+        assert!(get(addr, "/readyz").starts_with("HTTP/1.1 503"));
+        let health = get(addr, "/health");
+        assert!(health.contains("\"stalled\""));
+        assert!(get(addr, "/nope").starts_with("HTTP/1.1 404"));
+
+        server.shutdown();
+    }
+}
@@ -0,0 +1,266 @@
+//! Runtime log level control.
+//!
+//! Levels are kept per `tracing` target and can be changed while the host is
+//! running with [`set_level`], for example to turn on debug logging for
+//! `fusabi_stdlib_ext::net_http` during an incident. A target matches its
+//! own events and those of its child modules (`a::b` matches `a::b::c`); the
+//! longest matching target wins, and targets without an override use the
+//! default level (`info` until changed with [`set_default_level`]).
+//!
+//! The levels only take effect once the host installs [`layer`] in its
+//! subscriber, in place of a static filter:
+//!
+//! ```rust,ignore
+//! use tracing_subscriber::prelude::*;
+//!
+//! tracing_subscriber::registry()
+//!     .with(fusabi_stdlib_ext::observability::log_level::layer())
+//!     .with(tracing_subscriber::fmt::layer())
+//!     .init();
+//! ```
+//!
+//! Scripts can change levels with `log.set_level` only when
+//! [`SafetyConfig::allow_log_control`](crate::SafetyConfig::allow_log_control)
+//! is set.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+
+use parking_lot::RwLock;
+use tracing::level_filters::LevelFilter;
+use tracing::subscriber::Interest;
+use tracing::{Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use fusabi_host::ExecutionContext;
+use fusabi_host::Value;
+
+use crate::safety::{AuditEvent, SafetyConfig};
+
+/// Target name that addresses the default level in the host functions.
+pub const DEFAULT_TARGET: &str = "*";
+
+struct Levels {
+    default: LevelFilter,
+    targets: BTreeMap<String, LevelFilter>,
+}
+
+static LEVELS: OnceLock<RwLock<Levels>> = OnceLock::new();
+
+fn levels_table() -> &'static RwLock<Levels> {
+    LEVELS.get_or_init(|| {
+        RwLock::new(Levels {
+            default: LevelFilter::INFO,
+            targets: BTreeMap::new(),
+        })
+    })
+}
+
+/// Parse a level name (`off`, `error`, `warn`, `info`, `debug`, `trace`).
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    level.to_ascii_lowercase().parse().ok()
+}
+
+/// Set the level for a target and its child modules.
+pub fn set_level(target: impl Into<String>, level: LevelFilter) {
+    levels_table().write().targets.insert(target.into(), level);
+}
+
+/// Remove a target's override, returning whether one was set.
+pub fn reset_level(target: &str) -> bool {
+    levels_table().write().targets.remove(target).is_some()
+}
+
+/// Set the level for targets without an override.
+pub fn set_default_level(level: LevelFilter) {
+    levels_table().write().default = level;
+}
+
+/// Get the level in effect for a target.
+pub fn level(target: &str) -> LevelFilter {
+    let levels = levels_table().read();
+    levels
+        .targets
+        .iter()
+        .filter(|(prefix, _)| matches_target(prefix, target))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map_or(levels.default, |(_, level)| *level)
+}
+
+/// List the per-target overrides, sorted by target.
+pub fn levels() -> Vec<(String, LevelFilter)> {
+    levels_table()
+        .read()
+        .targets
+        .iter()
+        .map(|(target, level)| (target.clone(), *level))
+        .collect()
+}
+
+fn matches_target(prefix: &str, target: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+/// Create the filtering layer that applies the runtime levels.
+pub fn layer() -> RuntimeLevelLayer {
+    RuntimeLevelLayer { _private: () }
+}
+
+/// A `tracing` layer that drops events and spans below their target's
+/// runtime level. Create it with [`layer`].
+#[derive(Debug, Clone)]
+pub struct RuntimeLevelLayer {
+    _private: (),
+}
+
+impl<S: Subscriber> Layer<S> for RuntimeLevelLayer {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // Levels change at runtime, so the decision cannot be cached.
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        level(metadata.target()) >= *metadata.level()
+    }
+}
+
+/// Change the log level for a target.
+///
+/// Requires `allow_log_control` in the safety configuration. Every attempt is
+/// recorded in the safety configuration's audit sink.
+///
+/// # Arguments
+///
+/// * `args[0]` - Target (module path), or `"*"` for the default level
+/// * `args[1]` - Level (`off`, `error`, `warn`, `info`, `debug`, `trace`), or
+///   null to remove the target's override
+pub fn set(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let target = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("log.set_level: missing target"))?;
+
+    let level = match args.get(1) {
+        None | Some(Value::Null) => None,
+        Some(value) => {
+            let name = value.as_str().ok_or_else(|| {
+                fusabi_host::Error::host_function("log.set_level: level must be a string")
+            })?;
+            Some(parse_level(name).ok_or_else(|| {
+                fusabi_host::Error::host_function(format!("log.set_level: unknown level: {}", name))
+            })?)
+        }
+    };
+
+    let rendered = level.map_or_else(|| "reset".to_string(), |l| l.to_string());
+    let event = AuditEvent::new(
+        "log.set_level",
+        if safety.allow_log_control {
+            "allowed"
+        } else {
+            "denied"
+        },
+    )
+    .with_field("target", target)
+    .with_field("level", rendered);
+    safety.audit(event);
+
+    if !safety.allow_log_control {
+        return Err(fusabi_host::Error::host_function(
+            crate::Error::not_permitted("log level control not allowed").to_string(),
+        ));
+    }
+
+    match (target, level) {
+        (DEFAULT_TARGET, Some(level)) => set_default_level(level),
+        (DEFAULT_TARGET, None) => set_default_level(LevelFilter::INFO),
+        (target, Some(level)) => set_level(target, level),
+        (target, None) => {
+            reset_level(target);
+        }
+    }
+    Ok(Value::Null)
+}
+
+/// Get the log level in effect for a target.
+///
+/// # Arguments
+///
+/// * `args[0]` - Target (module path), or `"*"` for the default level
+///
+/// # Returns
+///
+/// Level name, lowercase
+pub fn get(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let target = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("log.get_level: missing target"))?;
+
+    let level = if target == DEFAULT_TARGET {
+        levels_table().read().default
+    } else {
+        level(target)
+    };
+    Ok(Value::String(level.to_string().to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_target_levels() {
+        set_level("log_level_test::net", LevelFilter::DEBUG);
+        set_level("log_level_test::net::dns", LevelFilter::ERROR);
+
+        assert_eq!(level("log_level_test::net"), LevelFilter::DEBUG);
+        assert_eq!(level("log_level_test::net::http"), LevelFilter::DEBUG);
+        assert_eq!(level("log_level_test::net::dns::cache"), LevelFilter::ERROR);
+        assert_eq!(level("log_level_test::network"), level("log_level_test"));
+
+        assert!(reset_level("log_level_test::net"));
+        assert!(!reset_level("log_level_test::net"));
+        assert_eq!(level("log_level_test::net::http"), level("log_level_test"));
+        reset_level("log_level_test::net::dns");
+    }
+
+    #[test]
+    fn test_set_level_host_fn() {
+        let ctx = create_test_ctx();
+        let args = [
+            Value::String("log_level_host::module".into()),
+            Value::String("TRACE".into()),
+        ];
+
+        let denied = Arc::new(SafetyConfig::new());
+        assert!(set(&denied, &args, &ctx).is_err());
+
+        let allowed = Arc::new(SafetyConfig::new().with_allow_log_control(true));
+        set(&allowed, &args, &ctx).unwrap();
+        assert_eq!(
+            get(&args[..1], &ctx).unwrap(),
+            Value::String("trace".into())
+        );
+
+        let bogus = [args[0].clone(), Value::String("loud".into())];
+        assert!(set(&allowed, &bogus, &ctx).is_err());
+
+        set(&allowed, &[args[0].clone(), Value::Null], &ctx).unwrap();
+        assert!(levels()
+            .iter()
+            .all(|(target, _)| target != "log_level_host::module"));
+    }
+}
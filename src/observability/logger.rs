@@ -0,0 +1,539 @@
+//! Structured logging for scripts.
+//!
+//! The `log.trace`, `log.debug`, `log.info`, `log.warn` and `log.error`
+//! host functions build a [`LogEntry`] from a message and a map of fields
+//! and hand it to the registry's [`ScriptLogger`]. Entries below the
+//! logger's level are dropped; the rest are emitted as `tracing` events
+//! with target [`SCRIPT_TARGET`] (so they also pass through the runtime
+//! levels of [`log_level`](super::log_level)) and, when a file sink is
+//! open, appended to it as one JSON object per line:
+//!
+//! ```json
+//! {"fields":{"count":3},"level":"INFO","message":"synced","timestamp_ns":1700000000000000000}
+//! ```
+//!
+//! The sink path must be writable under the safety configuration's path
+//! allowlist, whether the host opens it with [`ScriptLogger::open_file`]
+//! or a script does with `log.set_file`.
+//!
+//! The logger also keeps the most recent entries in memory (the last
+//! [`DEFAULT_RECENT_CAPACITY`] by default), so dashboards and other
+//! in-process consumers can show them with [`ScriptLogger::recent`] or
+//! `log.recent(filter)` without reading the file back.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use parking_lot::{Mutex, RwLock};
+use tracing::level_filters::LevelFilter;
+
+use fusabi_host::{ExecutionContext, Value};
+
+use super::log_level::parse_level;
+use super::{LogEntry, LogLevel};
+use crate::safety::SafetyConfig;
+
+/// `tracing` target of script log events.
+pub const SCRIPT_TARGET: &str = "fusabi::script";
+
+/// Number of recent entries kept by a new logger.
+pub const DEFAULT_RECENT_CAPACITY: usize = 256;
+
+struct FileSink {
+    path: PathBuf,
+    file: File,
+}
+
+struct RingBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+}
+
+impl RingBuffer {
+    fn push(&mut self, entry: LogEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+/// Selects entries from [`ScriptLogger::recent`].
+#[derive(Debug, Clone, Default)]
+pub struct RecentFilter {
+    /// Least severe level to include.
+    pub level: Option<LevelFilter>,
+    /// Substring the message must contain.
+    pub contains: Option<String>,
+    /// Only entries at or after this timestamp (nanoseconds since epoch).
+    pub since_ns: Option<u64>,
+    /// Keep only the newest this many matches.
+    pub limit: Option<usize>,
+}
+
+impl RecentFilter {
+    /// Whether an entry passes the filter, ignoring `limit`.
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.level
+            .map_or(true, |level| LevelFilter::from(entry.level) <= level)
+            && self
+                .contains
+                .as_deref()
+                .map_or(true, |needle| entry.message.contains(needle))
+            && self
+                .since_ns
+                .map_or(true, |since| entry.timestamp_ns >= since)
+    }
+
+    /// Parse a filter map with optional `level`, `contains`, `since_ns`
+    /// and `limit` keys.
+    pub fn from_value(value: &Value) -> crate::Result<Self> {
+        let map = match value {
+            Value::Null => return Ok(Self::default()),
+            Value::Map(map) => map,
+            _ => return Err(crate::Error::invalid_argument("filter must be a map")),
+        };
+
+        let mut filter = Self::default();
+        for (key, value) in map {
+            match (key.as_str(), value) {
+                (_, Value::Null) => {}
+                ("level", Value::String(level)) => {
+                    filter.level = Some(parse_level(level).ok_or_else(|| {
+                        crate::Error::invalid_argument(format!("unknown level '{}'", level))
+                    })?);
+                }
+                ("contains", Value::String(needle)) => filter.contains = Some(needle.clone()),
+                ("since_ns", Value::Int(since)) if *since >= 0 => {
+                    filter.since_ns = Some(*since as u64)
+                }
+                ("limit", Value::Int(limit)) if *limit >= 0 => filter.limit = Some(*limit as usize),
+                ("level" | "contains" | "since_ns" | "limit", _) => {
+                    return Err(crate::Error::invalid_argument(format!(
+                        "invalid filter value for '{}'",
+                        key
+                    )))
+                }
+                _ => {
+                    return Err(crate::Error::invalid_argument(format!(
+                        "unknown filter key '{}'",
+                        key
+                    )))
+                }
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// Level filter and sinks for script log entries. Each registry has its
+/// own; see [`StdlibRegistry::with_logger`](crate::StdlibRegistry::with_logger).
+pub struct ScriptLogger {
+    level: RwLock<LevelFilter>,
+    sink: Mutex<Option<FileSink>>,
+    recent: Mutex<RingBuffer>,
+}
+
+impl std::fmt::Debug for ScriptLogger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptLogger")
+            .field("level", &self.level())
+            .field("file", &self.file_path())
+            .field("recent_capacity", &self.recent_capacity())
+            .finish()
+    }
+}
+
+impl Default for ScriptLogger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptLogger {
+    /// Create a logger at `info` level without a file sink.
+    pub fn new() -> Self {
+        Self {
+            level: RwLock::new(LevelFilter::INFO),
+            sink: Mutex::new(None),
+            recent: Mutex::new(RingBuffer {
+                entries: VecDeque::new(),
+                capacity: DEFAULT_RECENT_CAPACITY,
+            }),
+        }
+    }
+
+    /// Set the level.
+    pub fn with_level(self, level: LevelFilter) -> Self {
+        self.set_level(level);
+        self
+    }
+
+    /// Change the level.
+    pub fn set_level(&self, level: LevelFilter) {
+        *self.level.write() = level;
+    }
+
+    /// Get the level.
+    pub fn level(&self) -> LevelFilter {
+        *self.level.read()
+    }
+
+    /// Whether entries at `level` are recorded.
+    pub fn enabled(&self, level: LogLevel) -> bool {
+        LevelFilter::from(level) <= self.level()
+    }
+
+    /// Append entries to a JSON-lines file, replacing any open sink.
+    ///
+    /// The file is created if missing. Fails if `path` is not writable
+    /// under `safety`'s path allowlist.
+    pub fn open_file(&self, safety: &SafetyConfig, path: impl AsRef<Path>) -> crate::Result<()> {
+        let path = path.as_ref();
+        safety.paths.check_write(path)?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        *self.sink.lock() = Some(FileSink {
+            path: path.to_path_buf(),
+            file,
+        });
+        Ok(())
+    }
+
+    /// Close the file sink, returning its path if one was open.
+    pub fn close_file(&self) -> Option<PathBuf> {
+        self.sink.lock().take().map(|sink| sink.path)
+    }
+
+    /// Path of the open file sink.
+    pub fn file_path(&self) -> Option<PathBuf> {
+        self.sink.lock().as_ref().map(|sink| sink.path.clone())
+    }
+
+    /// Set how many recent entries are kept in memory (0 disables the
+    /// buffer).
+    pub fn with_recent_capacity(self, capacity: usize) -> Self {
+        self.set_recent_capacity(capacity);
+        self
+    }
+
+    /// Change how many recent entries are kept, dropping the oldest if the
+    /// buffer shrinks.
+    pub fn set_recent_capacity(&self, capacity: usize) {
+        let mut recent = self.recent.lock();
+        recent.capacity = capacity;
+        while recent.entries.len() > capacity {
+            recent.entries.pop_front();
+        }
+    }
+
+    /// Get how many recent entries are kept.
+    pub fn recent_capacity(&self) -> usize {
+        self.recent.lock().capacity
+    }
+
+    /// Recent entries matching `filter`, oldest first.
+    pub fn recent(&self, filter: &RecentFilter) -> Vec<LogEntry> {
+        let recent = self.recent.lock();
+        let mut entries: Vec<LogEntry> = recent
+            .entries
+            .iter()
+            .filter(|entry| filter.matches(entry))
+            .cloned()
+            .collect();
+        if let Some(limit) = filter.limit {
+            entries.drain(..entries.len().saturating_sub(limit));
+        }
+        entries
+    }
+
+    /// Drop all recent entries.
+    pub fn clear_recent(&self) {
+        self.recent.lock().entries.clear();
+    }
+
+    /// Record an entry, returning whether it passed the level filter.
+    pub fn log(&self, entry: &LogEntry) -> crate::Result<bool> {
+        if !self.enabled(entry.level) {
+            return Ok(false);
+        }
+
+        let fields = Value::Map(entry.fields.clone()).to_json_string();
+        let message = &entry.message;
+        match entry.level {
+            LogLevel::Trace => {
+                tracing::trace!(target: SCRIPT_TARGET, fields = %fields, "{}", message)
+            }
+            LogLevel::Debug => {
+                tracing::debug!(target: SCRIPT_TARGET, fields = %fields, "{}", message)
+            }
+            LogLevel::Info => {
+                tracing::info!(target: SCRIPT_TARGET, fields = %fields, "{}", message)
+            }
+            LogLevel::Warn => {
+                tracing::warn!(target: SCRIPT_TARGET, fields = %fields, "{}", message)
+            }
+            LogLevel::Error => {
+                tracing::error!(target: SCRIPT_TARGET, fields = %fields, "{}", message)
+            }
+        }
+
+        if let Some(sink) = self.sink.lock().as_mut() {
+            let mut line = entry.to_json();
+            line.push('\n');
+            sink.file.write_all(line.as_bytes())?;
+        }
+        self.recent.lock().push(entry.clone());
+        Ok(true)
+    }
+
+    /// Flush the file sink.
+    pub fn flush(&self) -> crate::Result<()> {
+        if let Some(sink) = self.sink.lock().as_mut() {
+            sink.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Log a message at `level`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Message (non-string values are logged as JSON)
+/// * `args[1]` - Optional map of structured fields
+///
+/// # Returns
+///
+/// Whether the entry passed the level filter
+pub fn log(
+    logger: &ScriptLogger,
+    level: LogLevel,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let function = format!("log.{}", level.as_str().to_ascii_lowercase());
+
+    let message = match args.first() {
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_json_string(),
+        None => {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: missing message",
+                function
+            )))
+        }
+    };
+
+    let mut entry = LogEntry::new(level, message);
+    match args.get(1) {
+        None | Some(Value::Null) => {}
+        Some(Value::Map(fields)) => entry.fields = fields.clone(),
+        Some(_) => {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: fields must be a map",
+                function
+            )))
+        }
+    }
+
+    logger
+        .log(&entry)
+        .map(Value::Bool)
+        .map_err(|e| fusabi_host::Error::host_function(format!("{}: {}", function, e)))
+}
+
+/// Send log entries to a JSON-lines file, or stop with null.
+///
+/// # Arguments
+///
+/// * `args[0]` - File path (must be writable under the path allowlist), or
+///   null to close the current file
+pub fn set_file(
+    logger: &ScriptLogger,
+    safety: &SafetyConfig,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    match args.first() {
+        None | Some(Value::Null) => {
+            logger.close_file();
+            Ok(Value::Null)
+        }
+        Some(Value::String(path)) => {
+            logger
+                .open_file(safety, path)
+                .map_err(|e| fusabi_host::Error::host_function(format!("log.set_file: {}", e)))?;
+            Ok(Value::Null)
+        }
+        Some(_) => Err(fusabi_host::Error::host_function(
+            "log.set_file: path must be a string or null",
+        )),
+    }
+}
+
+/// Recent log entries, oldest first.
+///
+/// # Arguments
+///
+/// * `args[0]` - Optional filter map:
+///   - `level`: least severe level to include (e.g. `"warn"`)
+///   - `contains`: substring the message must contain
+///   - `since_ns`: earliest timestamp, in nanoseconds since epoch
+///   - `limit`: keep only the newest this many entries
+///
+/// # Returns
+///
+/// List of maps with `timestamp_ns`, `level`, `message` and `fields` keys
+pub fn recent(
+    logger: &ScriptLogger,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let filter = RecentFilter::from_value(args.first().unwrap_or(&Value::Null))
+        .map_err(|e| fusabi_host::Error::host_function(format!("log.recent: {}", e)))?;
+
+    Ok(Value::List(
+        logger
+            .recent(&filter)
+            .iter()
+            .map(LogEntry::to_value)
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+    use std::collections::HashMap;
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_level_filter() {
+        let logger = ScriptLogger::new().with_level(LevelFilter::WARN);
+
+        assert!(logger.enabled(LogLevel::Error));
+        assert!(logger.enabled(LogLevel::Warn));
+        assert!(!logger.enabled(LogLevel::Info));
+
+        logger.set_level(LevelFilter::OFF);
+        assert!(!logger.enabled(LogLevel::Error));
+    }
+
+    #[test]
+    fn test_json_file_sink() {
+        let ctx = create_test_ctx();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.jsonl");
+        let logger = ScriptLogger::new();
+
+        let denied = SafetyConfig::new();
+        let args = [Value::String(path.display().to_string())];
+        assert!(set_file(&logger, &denied, &args, &ctx).is_err());
+
+        let safety = SafetyConfig::new()
+            .with_paths(crate::safety::PathAllowlist::none().allow_write(dir.path()));
+        set_file(&logger, &safety, &args, &ctx).unwrap();
+        assert_eq!(logger.file_path(), Some(path.clone()));
+
+        let mut fields = HashMap::new();
+        fields.insert("count".to_string(), Value::Int(3));
+        let logged = log(
+            &logger,
+            LogLevel::Info,
+            &[Value::String("synced".into()), Value::Map(fields)],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(logged, Value::Bool(true));
+
+        let skipped = log(
+            &logger,
+            LogLevel::Debug,
+            &[Value::String("noise".into())],
+            &ctx,
+        );
+        assert_eq!(skipped.unwrap(), Value::Bool(false));
+
+        assert!(log(&logger, LogLevel::Warn, &[], &ctx).is_err());
+
+        set_file(&logger, &safety, &[Value::Null], &ctx).unwrap();
+        assert!(logger.file_path().is_none());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let entry = Value::from_json_str(lines[0]).unwrap();
+        let entry = entry.as_map().unwrap();
+        assert_eq!(entry["level"], Value::String("INFO".into()));
+        assert_eq!(entry["message"], Value::String("synced".into()));
+        assert_eq!(entry["fields"].as_map().unwrap()["count"], Value::Int(3));
+    }
+
+    #[test]
+    fn test_recent_ring_buffer() {
+        let ctx = create_test_ctx();
+        let logger = ScriptLogger::new()
+            .with_level(LevelFilter::DEBUG)
+            .with_recent_capacity(3);
+
+        for (level, message) in [
+            (LogLevel::Trace, "dropped by level"),
+            (LogLevel::Info, "evicted"),
+            (LogLevel::Debug, "cache miss"),
+            (LogLevel::Warn, "slow sync"),
+            (LogLevel::Error, "sync failed"),
+        ] {
+            logger.log(&LogEntry::new(level, message)).unwrap();
+        }
+
+        let all = recent(&logger, &[], &ctx).unwrap();
+        let messages: Vec<_> = all
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.as_map().unwrap()["message"].clone())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                Value::String("cache miss".into()),
+                Value::String("slow sync".into()),
+                Value::String("sync failed".into()),
+            ]
+        );
+
+        let mut filter = HashMap::new();
+        filter.insert("level".to_string(), Value::String("warn".into()));
+        filter.insert("contains".to_string(), Value::String("sync".into()));
+        filter.insert("limit".to_string(), Value::Int(1));
+        let newest = recent(&logger, &[Value::Map(filter)], &ctx).unwrap();
+        let newest = newest.as_list().unwrap();
+        assert_eq!(newest.len(), 1);
+        assert_eq!(
+            newest[0].as_map().unwrap()["level"],
+            Value::String("ERROR".into())
+        );
+
+        let mut bad = HashMap::new();
+        bad.insert("lvl".to_string(), Value::String("warn".into()));
+        assert!(recent(&logger, &[Value::Map(bad)], &ctx).is_err());
+
+        logger.set_recent_capacity(1);
+        assert_eq!(logger.recent(&RecentFilter::default()).len(), 1);
+        logger.clear_recent();
+        assert!(logger.recent(&RecentFilter::default()).is_empty());
+    }
+}
@@ -0,0 +1,1388 @@
+//! OTLP span and metric export.
+//!
+//! Spans and metrics are sent to an OpenTelemetry collector with one of the
+//! three OTLP transports ([`OtlpProtocol`]): gRPC, HTTP with protobuf
+//! bodies, or HTTP with JSON bodies. The request bodies are encoded here
+//! directly from the OTLP `ExportTraceServiceRequest` and
+//! `ExportMetricsServiceRequest` schemas, so no protobuf toolchain is
+//! needed; the transports ([`OtlpSpanExporter`] and
+//! [`OtlpMetricsExporter`]) need the `observability-otlp` feature.
+//!
+//! [`OtlpConfig::from_env`] reads the standard `OTEL_EXPORTER_OTLP_*`
+//! variables and [`MetricReaderConfig::from_env`] the metric reader ones.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use fusabi_host::Value;
+
+use super::tracer::{BatchConfig, Resource, SpanData, SpanKind, SpanStatus, SCOPE_NAME};
+use crate::metrics::{parse_series_key, Labels, MetricsSnapshot};
+
+/// Default collector address for gRPC.
+pub const DEFAULT_GRPC_ENDPOINT: &str = "http://localhost:4317";
+
+/// Default collector address for HTTP.
+pub const DEFAULT_HTTP_ENDPOINT: &str = "http://localhost:4318";
+
+/// gRPC method that receives spans.
+const GRPC_TRACE_PATH: &str = "/opentelemetry.proto.collector.trace.v1.TraceService/Export";
+
+/// gRPC method that receives metrics.
+const GRPC_METRICS_PATH: &str = "/opentelemetry.proto.collector.metrics.v1.MetricsService/Export";
+
+/// OTLP transport.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OtlpProtocol {
+    /// gRPC over HTTP/2, usually port 4317.
+    #[default]
+    Grpc,
+    /// Protobuf bodies over HTTP, usually port 4318.
+    HttpProtobuf,
+    /// JSON bodies over HTTP, usually port 4318.
+    HttpJson,
+}
+
+impl OtlpProtocol {
+    /// Parse an `OTEL_EXPORTER_OTLP_PROTOCOL` value: `"grpc"`,
+    /// `"http/protobuf"` or `"http/json"`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "grpc" => Some(OtlpProtocol::Grpc),
+            "http/protobuf" => Some(OtlpProtocol::HttpProtobuf),
+            "http/json" => Some(OtlpProtocol::HttpJson),
+            _ => None,
+        }
+    }
+
+    /// The `OTEL_EXPORTER_OTLP_PROTOCOL` name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OtlpProtocol::Grpc => "grpc",
+            OtlpProtocol::HttpProtobuf => "http/protobuf",
+            OtlpProtocol::HttpJson => "http/json",
+        }
+    }
+}
+
+/// Where and how to export spans and metrics.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtlpConfig {
+    /// Collector base URL, e.g. `http://collector:4317`.
+    pub endpoint: String,
+    /// Full URL for spans over HTTP, overriding `{endpoint}/v1/traces`.
+    pub traces_endpoint: Option<String>,
+    /// Full URL for metrics over HTTP, overriding `{endpoint}/v1/metrics`.
+    pub metrics_endpoint: Option<String>,
+    /// Transport.
+    pub protocol: OtlpProtocol,
+    /// Extra request headers, e.g. for authentication.
+    pub headers: HashMap<String, String>,
+    /// Request timeout.
+    pub timeout: Duration,
+    /// Batching before export.
+    pub batch: BatchConfig,
+}
+
+impl Default for OtlpConfig {
+    fn default() -> Self {
+        Self::new(DEFAULT_GRPC_ENDPOINT)
+    }
+}
+
+impl OtlpConfig {
+    /// Export to the collector at `endpoint` over gRPC.
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            traces_endpoint: None,
+            metrics_endpoint: None,
+            protocol: OtlpProtocol::Grpc,
+            headers: HashMap::new(),
+            timeout: Duration::from_secs(10),
+            batch: BatchConfig::default(),
+        }
+    }
+
+    /// Set the transport.
+    pub fn with_protocol(mut self, protocol: OtlpProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Set the full URL for spans over HTTP.
+    pub fn with_traces_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.traces_endpoint = Some(url.into());
+        self
+    }
+
+    /// Set the full URL for metrics over HTTP.
+    pub fn with_metrics_endpoint(mut self, url: impl Into<String>) -> Self {
+        self.metrics_endpoint = Some(url.into());
+        self
+    }
+
+    /// Add a request header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Set the request timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the batching.
+    pub fn with_batch(mut self, batch: BatchConfig) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Read `OTEL_EXPORTER_OTLP_ENDPOINT`, `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`,
+    /// `OTEL_EXPORTER_OTLP_METRICS_ENDPOINT`,
+    /// `OTEL_EXPORTER_OTLP_PROTOCOL` (or `..._TRACES_PROTOCOL`),
+    /// `OTEL_EXPORTER_OTLP_HEADERS` (`key=value,...`) and
+    /// `OTEL_EXPORTER_OTLP_TIMEOUT` (milliseconds).
+    ///
+    /// Returns `None` when no endpoint variable is set.
+    pub fn from_env() -> crate::Result<Option<Self>> {
+        Self::from_lookup(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> Option<String>) -> crate::Result<Option<Self>> {
+        let endpoint = var("OTEL_EXPORTER_OTLP_ENDPOINT");
+        let traces_endpoint = var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT");
+        let metrics_endpoint = var("OTEL_EXPORTER_OTLP_METRICS_ENDPOINT");
+        if endpoint.is_none() && traces_endpoint.is_none() && metrics_endpoint.is_none() {
+            return Ok(None);
+        }
+
+        let protocol = match var("OTEL_EXPORTER_OTLP_TRACES_PROTOCOL")
+            .or_else(|| var("OTEL_EXPORTER_OTLP_PROTOCOL"))
+        {
+            Some(name) => OtlpProtocol::parse(&name).ok_or_else(|| {
+                crate::Error::invalid_argument(format!("unknown OTLP protocol {:?}", name))
+            })?,
+            None => OtlpProtocol::default(),
+        };
+        let default_endpoint = match protocol {
+            OtlpProtocol::Grpc => DEFAULT_GRPC_ENDPOINT,
+            _ => DEFAULT_HTTP_ENDPOINT,
+        };
+        let mut config = Self::new(endpoint.unwrap_or_else(|| default_endpoint.to_string()))
+            .with_protocol(protocol);
+        config.traces_endpoint = traces_endpoint;
+        config.metrics_endpoint = metrics_endpoint;
+
+        if let Some(headers) = var("OTEL_EXPORTER_OTLP_HEADERS") {
+            for pair in headers.split(',').filter(|p| !p.trim().is_empty()) {
+                let (name, value) = pair.split_once('=').ok_or_else(|| {
+                    crate::Error::invalid_argument(format!("invalid OTLP header {:?}", pair))
+                })?;
+                config = config.with_header(name.trim(), value.trim());
+            }
+        }
+        if let Some(timeout) = var("OTEL_EXPORTER_OTLP_TIMEOUT") {
+            let millis = timeout.trim().parse().map_err(|_| {
+                crate::Error::invalid_argument(format!("invalid OTLP timeout {:?}", timeout))
+            })?;
+            config.timeout = Duration::from_millis(millis);
+        }
+        Ok(Some(config))
+    }
+
+    /// URL spans are posted to.
+    pub fn traces_url(&self) -> String {
+        let base = self.endpoint.trim_end_matches('/');
+        match self.protocol {
+            OtlpProtocol::Grpc => format!("{}{}", base, GRPC_TRACE_PATH),
+            _ => self
+                .traces_endpoint
+                .clone()
+                .unwrap_or_else(|| format!("{}/v1/traces", base)),
+        }
+    }
+
+    /// URL metrics are posted to.
+    pub fn metrics_url(&self) -> String {
+        let base = self.endpoint.trim_end_matches('/');
+        match self.protocol {
+            OtlpProtocol::Grpc => format!("{}{}", base, GRPC_METRICS_PATH),
+            _ => self
+                .metrics_endpoint
+                .clone()
+                .unwrap_or_else(|| format!("{}/v1/metrics", base)),
+        }
+    }
+}
+
+/// How exported sums relate to earlier exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Temporality {
+    /// Each export carries the total since the exporter started.
+    #[default]
+    Cumulative,
+    /// Each export carries the change since the previous export.
+    Delta,
+}
+
+impl Temporality {
+    /// Parse an `OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE` value:
+    /// `"cumulative"` or `"delta"`, in any case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "cumulative" => Some(Temporality::Cumulative),
+            "delta" => Some(Temporality::Delta),
+            _ => None,
+        }
+    }
+
+    /// The lowercase name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Temporality::Cumulative => "cumulative",
+            Temporality::Delta => "delta",
+        }
+    }
+
+    /// OTLP `AggregationTemporality` value.
+    fn code(self) -> i64 {
+        match self {
+            Temporality::Delta => 1,
+            Temporality::Cumulative => 2,
+        }
+    }
+}
+
+/// How often metrics are read from the registry and exported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricReaderConfig {
+    /// Time between exports.
+    pub interval: Duration,
+    /// Temporality of exported counters.
+    pub temporality: Temporality,
+}
+
+impl Default for MetricReaderConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(60),
+            temporality: Temporality::default(),
+        }
+    }
+}
+
+impl MetricReaderConfig {
+    /// Export cumulative values every minute.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the time between exports.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the temporality of exported counters.
+    pub fn with_temporality(mut self, temporality: Temporality) -> Self {
+        self.temporality = temporality;
+        self
+    }
+
+    /// Read `OTEL_METRIC_EXPORT_INTERVAL` (milliseconds) and
+    /// `OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE`, keeping the
+    /// defaults for unset variables.
+    pub fn from_env() -> crate::Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> Option<String>) -> crate::Result<Self> {
+        let mut config = Self::default();
+        if let Some(interval) = var("OTEL_METRIC_EXPORT_INTERVAL") {
+            let millis: u64 = interval.trim().parse().map_err(|_| {
+                crate::Error::invalid_argument(format!(
+                    "invalid metric export interval {:?}",
+                    interval
+                ))
+            })?;
+            if millis == 0 {
+                return Err(crate::Error::invalid_argument(
+                    "metric export interval must be positive",
+                ));
+            }
+            config.interval = Duration::from_millis(millis);
+        }
+        if let Some(name) = var("OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE") {
+            config.temporality = Temporality::parse(&name).ok_or_else(|| {
+                crate::Error::invalid_argument(format!("unknown metrics temporality {:?}", name))
+            })?;
+        }
+        Ok(config)
+    }
+}
+
+fn kind_code(kind: SpanKind) -> i64 {
+    match kind {
+        SpanKind::Internal => 1,
+        SpanKind::Server => 2,
+        SpanKind::Client => 3,
+        SpanKind::Producer => 4,
+        SpanKind::Consumer => 5,
+    }
+}
+
+/// Status code and message.
+fn status_parts(status: &SpanStatus) -> (i64, &str) {
+    match status {
+        SpanStatus::Unset => (0, ""),
+        SpanStatus::Ok => (1, ""),
+        SpanStatus::Error(message) => (2, message),
+    }
+}
+
+fn sorted(attributes: &HashMap<String, Value>) -> Vec<(&String, &Value)> {
+    let mut attributes: Vec<_> = attributes.iter().collect();
+    attributes.sort_by(|a, b| a.0.cmp(b.0));
+    attributes
+}
+
+/// Encode spans as an OTLP/JSON `ExportTraceServiceRequest`.
+pub fn encode_json(resource: &Resource, spans: &[SpanData]) -> String {
+    let map = |pairs: Vec<(&str, Value)>| {
+        Value::Map(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    };
+    let string = |s: &str| Value::String(s.to_string());
+    let nanos = |n: u64| Value::String(n.to_string());
+
+    fn any_value(value: &Value) -> Value {
+        let field = |name: &str, v: Value| {
+            let mut map = HashMap::new();
+            map.insert(name.to_string(), v);
+            Value::Map(map)
+        };
+        match value {
+            Value::Null => Value::Map(HashMap::new()),
+            Value::Bool(b) => field("boolValue", Value::Bool(*b)),
+            // 64-bit integers are strings in OTLP/JSON.
+            Value::Int(i) => field("intValue", Value::String(i.to_string())),
+            Value::Float(f) => field("doubleValue", Value::Float(*f)),
+            Value::String(s) => field("stringValue", Value::String(s.clone())),
+            Value::List(items) => field(
+                "arrayValue",
+                field("values", Value::List(items.iter().map(any_value).collect())),
+            ),
+            Value::Map(entries) => {
+                field("kvlistValue", field("values", key_values(&sorted(entries))))
+            }
+            other => field("stringValue", Value::String(other.to_string())),
+        }
+    }
+    fn key_values(attributes: &[(&String, &Value)]) -> Value {
+        Value::List(
+            attributes
+                .iter()
+                .map(|(key, value)| {
+                    let mut map = HashMap::new();
+                    map.insert("key".to_string(), Value::String(key.to_string()));
+                    map.insert("value".to_string(), any_value(value));
+                    Value::Map(map)
+                })
+                .collect(),
+        )
+    }
+
+    let spans = spans
+        .iter()
+        .map(|span| {
+            let (code, message) = status_parts(&span.status);
+            let events = span
+                .events
+                .iter()
+                .map(|event| {
+                    map(vec![
+                        ("timeUnixNano", nanos(event.time_ns)),
+                        ("name", string(&event.name)),
+                        ("attributes", key_values(&sorted(&event.attributes))),
+                    ])
+                })
+                .collect();
+            let mut fields = vec![
+                ("traceId", string(&span.trace_id)),
+                ("spanId", string(&span.span_id)),
+                ("name", string(&span.name)),
+                ("kind", Value::Int(kind_code(span.kind))),
+                ("startTimeUnixNano", nanos(span.start_time_ns)),
+                ("endTimeUnixNano", nanos(span.end_time_ns)),
+                ("attributes", key_values(&sorted(&span.attributes))),
+                ("events", Value::List(events)),
+                (
+                    "status",
+                    map(vec![
+                        ("code", Value::Int(code)),
+                        ("message", string(message)),
+                    ]),
+                ),
+            ];
+            if let Some(parent) = &span.parent_span_id {
+                fields.push(("parentSpanId", string(parent)));
+            }
+            map(fields)
+        })
+        .collect();
+
+    let resource_attributes: Vec<_> = resource.attributes.iter().map(|(k, v)| (k, v)).collect();
+    let scope_spans = map(vec![
+        (
+            "scope",
+            map(vec![
+                ("name", string(SCOPE_NAME)),
+                ("version", string(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+        ("spans", Value::List(spans)),
+    ]);
+    map(vec![(
+        "resourceSpans",
+        Value::List(vec![map(vec![
+            (
+                "resource",
+                map(vec![("attributes", key_values(&resource_attributes))]),
+            ),
+            ("scopeSpans", Value::List(vec![scope_spans])),
+        ])]),
+    )])
+    .to_json_string()
+}
+
+/// Protobuf wire-format writer.
+#[derive(Default)]
+struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn tag(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn int64(&mut self, field: u32, value: i64) {
+        self.tag(field, 0);
+        // Negative values take ten bytes, as two's complement.
+        self.varint(value as u64);
+    }
+
+    fn bool(&mut self, field: u32, value: bool) {
+        self.tag(field, 0);
+        self.varint(u64::from(value));
+    }
+
+    fn fixed64(&mut self, field: u32, value: u64) {
+        self.tag(field, 1);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn double(&mut self, field: u32, value: f64) {
+        self.fixed64(field, value.to_bits());
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.tag(field, 2);
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    fn message(&mut self, field: u32, write: impl FnOnce(&mut ProtoWriter)) {
+        let mut inner = ProtoWriter::default();
+        write(&mut inner);
+        self.bytes(field, &inner.buf);
+    }
+
+    fn any_value(&mut self, value: &Value) {
+        match value {
+            Value::Null => {}
+            Value::String(s) => self.string(1, s),
+            Value::Bool(b) => self.bool(2, *b),
+            Value::Int(i) => self.int64(3, *i),
+            Value::Float(f) => self.double(4, *f),
+            Value::List(items) => self.message(5, |w| {
+                for item in items {
+                    w.message(1, |w| w.any_value(item));
+                }
+            }),
+            Value::Map(entries) => self.message(6, |w| w.key_values(1, &sorted(entries))),
+            Value::Bytes(bytes) => self.bytes(7, bytes),
+            other => self.string(1, &other.to_string()),
+        }
+    }
+
+    fn key_values(&mut self, field: u32, attributes: &[(&String, &Value)]) {
+        for (key, value) in attributes {
+            self.message(field, |w| {
+                w.string(1, key);
+                w.message(2, |w| w.any_value(value));
+            });
+        }
+    }
+}
+
+/// Decode a hex id; ids come from this crate or from validated headers, so
+/// anything else becomes zeros, which collectors reject visibly.
+fn id_bytes(hex: &str, len: usize) -> Vec<u8> {
+    let bytes: Option<Vec<u8>> = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect();
+    bytes
+        .filter(|b| b.len() == len)
+        .unwrap_or_else(|| vec![0; len])
+}
+
+/// Encode spans as a protobuf `ExportTraceServiceRequest`.
+pub fn encode_protobuf(resource: &Resource, spans: &[SpanData]) -> Vec<u8> {
+    let mut w = ProtoWriter::default();
+    // ExportTraceServiceRequest.resource_spans
+    w.message(1, |w| {
+        // ResourceSpans.resource
+        w.message(1, |w| {
+            let attributes: Vec<_> = resource.attributes.iter().map(|(k, v)| (k, v)).collect();
+            w.key_values(1, &attributes);
+        });
+        // ResourceSpans.scope_spans
+        w.message(2, |w| {
+            w.message(1, |w| {
+                w.string(1, SCOPE_NAME);
+                w.string(2, env!("CARGO_PKG_VERSION"));
+            });
+            for span in spans {
+                w.message(2, |w| {
+                    w.bytes(1, &id_bytes(&span.trace_id, 16));
+                    w.bytes(2, &id_bytes(&span.span_id, 8));
+                    if let Some(parent) = &span.parent_span_id {
+                        w.bytes(4, &id_bytes(parent, 8));
+                    }
+                    w.string(5, &span.name);
+                    w.int64(6, kind_code(span.kind));
+                    w.fixed64(7, span.start_time_ns);
+                    w.fixed64(8, span.end_time_ns);
+                    w.key_values(9, &sorted(&span.attributes));
+                    for event in &span.events {
+                        w.message(11, |w| {
+                            w.fixed64(1, event.time_ns);
+                            w.string(2, &event.name);
+                            w.key_values(3, &sorted(&event.attributes));
+                        });
+                    }
+                    let (code, message) = status_parts(&span.status);
+                    w.message(15, |w| {
+                        if !message.is_empty() {
+                            w.string(2, message);
+                        }
+                        if code != 0 {
+                            w.int64(3, code);
+                        }
+                    });
+                });
+            }
+        });
+    });
+    w.buf
+}
+
+/// Value of a metric data point.
+#[derive(Debug, Clone, PartialEq)]
+enum PointValue {
+    Int(u64),
+    Double(f64),
+    Summary {
+        count: u64,
+        sum: f64,
+        quantiles: Vec<(f64, f64)>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DataPoint {
+    attributes: Labels,
+    start_time_ns: u64,
+    time_ns: u64,
+    value: PointValue,
+}
+
+/// OTLP metric type, in export order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MetricKind {
+    Sum,
+    Gauge,
+    Summary,
+}
+
+/// Metrics of a [`MetricsSnapshot`] in the OTLP data model, ready to
+/// encode.
+///
+/// Counters become monotonic sums with the batch's temporality, gauges
+/// become gauges, histograms become summaries with 0.5/0.9/0.99 quantiles
+/// and summaries keep their configured quantiles. Summaries have no
+/// temporality in OTLP and are always cumulative.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricBatch {
+    temporality: Temporality,
+    metrics: BTreeMap<(String, MetricKind), Vec<DataPoint>>,
+}
+
+impl MetricBatch {
+    /// Convert `snapshot`, taken at `time_ns`.
+    ///
+    /// Cumulative counters count from `start_ns`, the exporter's start.
+    /// With [`Temporality::Delta`], `previous` is the snapshot of the last
+    /// export at `previous_ns`, and counters carry the change since it; a
+    /// counter that went down was reset and reports its whole value.
+    pub fn new(
+        snapshot: &MetricsSnapshot,
+        previous: Option<(&MetricsSnapshot, u64)>,
+        temporality: Temporality,
+        start_ns: u64,
+        time_ns: u64,
+    ) -> Self {
+        let mut metrics: BTreeMap<(String, MetricKind), Vec<DataPoint>> = BTreeMap::new();
+        let mut push = |key: &str, kind, start_time_ns, time_ns, value| {
+            let (name, attributes) = parse_series_key(key).unwrap_or((key, Labels::new()));
+            metrics
+                .entry((name.to_string(), kind))
+                .or_default()
+                .push(DataPoint {
+                    attributes,
+                    start_time_ns,
+                    time_ns,
+                    value,
+                });
+        };
+
+        for (key, value) in &snapshot.counters {
+            let time = snapshot
+                .counter_timestamps
+                .get(key)
+                .map_or(time_ns, |ms| (*ms).max(0) as u64 * 1_000_000);
+            let (start, value) = match (temporality, previous) {
+                (Temporality::Delta, Some((previous, previous_ns))) => {
+                    let prior = previous.counters.get(key).copied().unwrap_or(0);
+                    let delta = if *value >= prior {
+                        value - prior
+                    } else {
+                        *value
+                    };
+                    (previous_ns, delta)
+                }
+                _ => (start_ns, *value),
+            };
+            push(key, MetricKind::Sum, start, time, PointValue::Int(value));
+        }
+        for (key, value) in &snapshot.gauges {
+            push(
+                key,
+                MetricKind::Gauge,
+                0,
+                time_ns,
+                PointValue::Double(*value),
+            );
+        }
+        for (key, stats) in &snapshot.histograms {
+            let value = PointValue::Summary {
+                count: stats.count,
+                sum: stats.sum,
+                quantiles: vec![(0.5, stats.p50), (0.9, stats.p90), (0.99, stats.p99)],
+            };
+            push(key, MetricKind::Summary, start_ns, time_ns, value);
+        }
+        for (key, stats) in &snapshot.summaries {
+            let value = PointValue::Summary {
+                count: stats.count,
+                sum: stats.sum,
+                quantiles: stats.quantiles.clone(),
+            };
+            push(key, MetricKind::Summary, start_ns, time_ns, value);
+        }
+
+        for points in metrics.values_mut() {
+            points.sort_by(|a, b| a.attributes.cmp(&b.attributes));
+        }
+        Self {
+            temporality,
+            metrics,
+        }
+    }
+
+    /// Number of metrics (not series).
+    pub fn len(&self) -> usize {
+        self.metrics.len()
+    }
+
+    /// Whether there is nothing to export.
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+}
+
+/// Encode metrics as an OTLP/JSON `ExportMetricsServiceRequest`.
+pub fn encode_metrics_json(resource: &Resource, batch: &MetricBatch) -> String {
+    let map = |pairs: Vec<(&str, Value)>| {
+        Value::Map(pairs.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    };
+    let string = |s: &str| Value::String(s.to_string());
+    let nanos = |n: u64| Value::String(n.to_string());
+    let key_values = |attributes: &mut dyn Iterator<Item = (&String, String)>| {
+        Value::List(
+            attributes
+                .map(|(key, value)| {
+                    map(vec![
+                        ("key", string(key)),
+                        ("value", map(vec![("stringValue", Value::String(value))])),
+                    ])
+                })
+                .collect(),
+        )
+    };
+
+    let metrics = batch
+        .metrics
+        .iter()
+        .map(|((name, kind), points)| {
+            let points = points
+                .iter()
+                .map(|point| {
+                    let mut fields = vec![
+                        (
+                            "attributes",
+                            key_values(&mut point.attributes.iter().map(|(k, v)| (k, v.clone()))),
+                        ),
+                        ("timeUnixNano", nanos(point.time_ns)),
+                    ];
+                    if point.start_time_ns != 0 {
+                        fields.push(("startTimeUnixNano", nanos(point.start_time_ns)));
+                    }
+                    match &point.value {
+                        // 64-bit integers are strings in OTLP/JSON.
+                        PointValue::Int(i) => fields.push(("asInt", nanos(*i))),
+                        PointValue::Double(d) => fields.push(("asDouble", Value::Float(*d))),
+                        PointValue::Summary {
+                            count,
+                            sum,
+                            quantiles,
+                        } => {
+                            let quantiles = quantiles
+                                .iter()
+                                .map(|(q, v)| {
+                                    map(vec![
+                                        ("quantile", Value::Float(*q)),
+                                        ("value", Value::Float(*v)),
+                                    ])
+                                })
+                                .collect();
+                            fields.push(("count", nanos(*count)));
+                            fields.push(("sum", Value::Float(*sum)));
+                            fields.push(("quantileValues", Value::List(quantiles)));
+                        }
+                    }
+                    map(fields)
+                })
+                .collect();
+            let data = match kind {
+                MetricKind::Sum => (
+                    "sum",
+                    map(vec![
+                        ("dataPoints", Value::List(points)),
+                        (
+                            "aggregationTemporality",
+                            Value::Int(batch.temporality.code()),
+                        ),
+                        ("isMonotonic", Value::Bool(true)),
+                    ]),
+                ),
+                MetricKind::Gauge => ("gauge", map(vec![("dataPoints", Value::List(points))])),
+                MetricKind::Summary => ("summary", map(vec![("dataPoints", Value::List(points))])),
+            };
+            map(vec![("name", string(name)), data])
+        })
+        .collect();
+
+    let scope_metrics = map(vec![
+        (
+            "scope",
+            map(vec![
+                ("name", string(SCOPE_NAME)),
+                ("version", string(env!("CARGO_PKG_VERSION"))),
+            ]),
+        ),
+        ("metrics", Value::List(metrics)),
+    ]);
+    map(vec![(
+        "resourceMetrics",
+        Value::List(vec![map(vec![
+            (
+                "resource",
+                map(vec![(
+                    "attributes",
+                    key_values(&mut resource.attributes.iter().map(|(k, v)| match v {
+                        Value::String(s) => (k, s.clone()),
+                        other => (k, other.to_string()),
+                    })),
+                )]),
+            ),
+            ("scopeMetrics", Value::List(vec![scope_metrics])),
+        ])]),
+    )])
+    .to_json_string()
+}
+
+/// Encode metrics as a protobuf `ExportMetricsServiceRequest`.
+pub fn encode_metrics_protobuf(resource: &Resource, batch: &MetricBatch) -> Vec<u8> {
+    fn data_point(w: &mut ProtoWriter, point: &DataPoint) {
+        let values: Vec<_> = point
+            .attributes
+            .iter()
+            .map(|(k, v)| (k, Value::String(v.clone())))
+            .collect();
+        let attributes: Vec<_> = values.iter().map(|(k, v)| (*k, v)).collect();
+        if point.start_time_ns != 0 {
+            w.fixed64(2, point.start_time_ns);
+        }
+        w.fixed64(3, point.time_ns);
+        match &point.value {
+            // NumberDataPoint.as_int is an sfixed64.
+            PointValue::Int(i) => w.fixed64(6, *i),
+            PointValue::Double(d) => w.double(4, *d),
+            PointValue::Summary {
+                count,
+                sum,
+                quantiles,
+            } => {
+                w.fixed64(4, *count);
+                w.double(5, *sum);
+                for (q, v) in quantiles {
+                    w.message(6, |w| {
+                        w.double(1, *q);
+                        w.double(2, *v);
+                    });
+                }
+            }
+        }
+        w.key_values(7, &attributes);
+    }
+
+    let mut w = ProtoWriter::default();
+    // ExportMetricsServiceRequest.resource_metrics
+    w.message(1, |w| {
+        // ResourceMetrics.resource
+        w.message(1, |w| {
+            let attributes: Vec<_> = resource.attributes.iter().map(|(k, v)| (k, v)).collect();
+            w.key_values(1, &attributes);
+        });
+        // ResourceMetrics.scope_metrics
+        w.message(2, |w| {
+            w.message(1, |w| {
+                w.string(1, SCOPE_NAME);
+                w.string(2, env!("CARGO_PKG_VERSION"));
+            });
+            for ((name, kind), points) in &batch.metrics {
+                w.message(2, |w| {
+                    w.string(1, name);
+                    let field = match kind {
+                        MetricKind::Gauge => 5,
+                        MetricKind::Sum => 7,
+                        MetricKind::Summary => 11,
+                    };
+                    w.message(field, |w| {
+                        for point in points {
+                            w.message(1, |w| data_point(w, point));
+                        }
+                        if *kind == MetricKind::Sum {
+                            w.int64(2, batch.temporality.code());
+                            w.bool(3, true);
+                        }
+                    });
+                });
+            }
+        });
+    });
+    w.buf
+}
+
+/// Wrap an encoded message in a gRPC frame: an uncompressed flag and the
+/// big-endian length.
+pub fn grpc_frame(message: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(message.len() + 5);
+    frame.push(0);
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message);
+    frame
+}
+
+/// HTTP client for a collector; gRPC connections use HTTP/2 without
+/// upgrade.
+#[cfg(feature = "observability-otlp")]
+fn client(config: &OtlpConfig) -> crate::Result<reqwest::blocking::Client> {
+    let mut builder = reqwest::blocking::Client::builder().timeout(config.timeout);
+    if config.protocol == OtlpProtocol::Grpc {
+        builder = builder.http2_prior_knowledge();
+    }
+    builder
+        .build()
+        .map_err(|e| crate::Error::network(format!("OTLP client: {}", e)))
+}
+
+/// Post an export request, encoding it with `protobuf` or `json` as the
+/// protocol requires.
+#[cfg(feature = "observability-otlp")]
+fn send(
+    client: &reqwest::blocking::Client,
+    config: &OtlpConfig,
+    url: &str,
+    protobuf: impl FnOnce() -> Vec<u8>,
+    json: impl FnOnce() -> String,
+) -> crate::Result<()> {
+    let (content_type, body) = match config.protocol {
+        OtlpProtocol::Grpc => ("application/grpc", grpc_frame(&protobuf())),
+        OtlpProtocol::HttpProtobuf => ("application/x-protobuf", protobuf()),
+        OtlpProtocol::HttpJson => ("application/json", json().into_bytes()),
+    };
+
+    let mut request = client
+        .post(url)
+        .header("content-type", content_type)
+        .body(body);
+    if config.protocol == OtlpProtocol::Grpc {
+        request = request.header("te", "trailers");
+    }
+    for (name, value) in &config.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let response = request
+        .send()
+        .map_err(|e| crate::Error::network(format!("OTLP export failed: {}", e)))?;
+
+    if !response.status().is_success() {
+        return Err(crate::Error::network(format!(
+            "OTLP export failed: HTTP {}",
+            response.status()
+        )));
+    }
+    // Failures without a body arrive as a trailers-only response, whose
+    // status is in the headers; trailers after a body are not visible
+    // here.
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+    match header("grpc-status") {
+        Some(code) if code != "0" => Err(crate::Error::network(format!(
+            "OTLP export failed: gRPC status {}: {}",
+            code,
+            header("grpc-message").unwrap_or_default()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Sends spans to an OpenTelemetry collector.
+#[cfg(feature = "observability-otlp")]
+pub struct OtlpSpanExporter {
+    config: OtlpConfig,
+    url: String,
+    client: reqwest::blocking::Client,
+}
+
+#[cfg(feature = "observability-otlp")]
+impl OtlpSpanExporter {
+    /// Create an exporter; gRPC connections use HTTP/2 without upgrade.
+    pub fn new(config: OtlpConfig) -> crate::Result<Self> {
+        Ok(Self {
+            client: client(&config)?,
+            url: config.traces_url(),
+            config,
+        })
+    }
+
+    /// The configuration.
+    pub fn config(&self) -> &OtlpConfig {
+        &self.config
+    }
+}
+
+#[cfg(feature = "observability-otlp")]
+impl super::tracer::SpanExporter for OtlpSpanExporter {
+    fn name(&self) -> &str {
+        "otlp"
+    }
+
+    fn export(&self, resource: &Resource, spans: &[SpanData]) -> crate::Result<()> {
+        send(
+            &self.client,
+            &self.config,
+            &self.url,
+            || encode_protobuf(resource, spans),
+            || encode_json(resource, spans),
+        )
+    }
+}
+
+/// What the last successful metrics export sent, for delta temporality.
+#[cfg(feature = "observability-otlp")]
+struct LastExport {
+    snapshot: MetricsSnapshot,
+    time_ns: u64,
+}
+
+/// Sends a [`MetricsRegistry`](crate::metrics::MetricsRegistry)'s metrics
+/// to an OpenTelemetry collector, with the same resource and transport as
+/// spans.
+///
+/// Add it with `MetricsRegistry::add_exporter`; [`init_metrics`] also
+/// starts the periodic export.
+///
+/// [`init_metrics`]: super::init_metrics
+#[cfg(feature = "observability-otlp")]
+pub struct OtlpMetricsExporter {
+    config: OtlpConfig,
+    url: String,
+    client: reqwest::blocking::Client,
+    resource: Resource,
+    temporality: Temporality,
+    start_ns: u64,
+    last: parking_lot::Mutex<Option<LastExport>>,
+}
+
+#[cfg(feature = "observability-otlp")]
+impl OtlpMetricsExporter {
+    /// Create an exporter reporting `resource`.
+    pub fn new(
+        config: OtlpConfig,
+        resource: Resource,
+        temporality: Temporality,
+    ) -> crate::Result<Self> {
+        Ok(Self {
+            client: client(&config)?,
+            url: config.metrics_url(),
+            config,
+            resource,
+            temporality,
+            start_ns: super::tracer::unix_nanos(),
+            last: parking_lot::Mutex::new(None),
+        })
+    }
+
+    /// The configuration.
+    pub fn config(&self) -> &OtlpConfig {
+        &self.config
+    }
+
+    /// Temporality of exported counters.
+    pub fn temporality(&self) -> Temporality {
+        self.temporality
+    }
+}
+
+#[cfg(feature = "observability-otlp")]
+impl crate::metrics::MetricsExporter for OtlpMetricsExporter {
+    fn name(&self) -> &str {
+        "otlp"
+    }
+
+    fn export(&self, snapshot: &MetricsSnapshot) -> crate::Result<()> {
+        // Held across the request so overlapping flushes cannot report the
+        // same delta twice.
+        let mut last = self.last.lock();
+        let now = super::tracer::unix_nanos();
+        let previous = last.as_ref().map(|l| (&l.snapshot, l.time_ns));
+        let batch = MetricBatch::new(snapshot, previous, self.temporality, self.start_ns, now);
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        send(
+            &self.client,
+            &self.config,
+            &self.url,
+            || encode_metrics_protobuf(&self.resource, &batch),
+            || encode_metrics_json(&self.resource, &batch),
+        )?;
+        // A failed export leaves the baseline alone, so the next delta
+        // covers both intervals.
+        if self.temporality == Temporality::Delta {
+            *last = Some(LastExport {
+                snapshot: snapshot.clone(),
+                time_ns: now,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::observability::tracer::SpanEvent;
+
+    fn span() -> SpanData {
+        let mut attributes = HashMap::new();
+        attributes.insert("count".to_string(), Value::Int(-1));
+        SpanData {
+            trace_id: "0af7651916cd43dd8448eb211c80319c".to_string(),
+            span_id: "b7ad6b7169203331".to_string(),
+            parent_span_id: None,
+            name: "sync".to_string(),
+            kind: SpanKind::Client,
+            start_time_ns: 1,
+            end_time_ns: 2,
+            attributes,
+            events: vec![SpanEvent {
+                name: "retry".to_string(),
+                time_ns: 1,
+                attributes: HashMap::new(),
+            }],
+            status: SpanStatus::Error("boom".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_config_from_env() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(OtlpConfig::from_lookup(env(&[])).unwrap(), None);
+
+        let config = OtlpConfig::from_lookup(env(&[
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://collector:4318/"),
+            ("OTEL_EXPORTER_OTLP_PROTOCOL", "http/protobuf"),
+            (
+                "OTEL_EXPORTER_OTLP_HEADERS",
+                "authorization=Bearer x, team=obs",
+            ),
+            ("OTEL_EXPORTER_OTLP_TIMEOUT", "2500"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.protocol, OtlpProtocol::HttpProtobuf);
+        assert_eq!(config.traces_url(), "http://collector:4318/v1/traces");
+        assert_eq!(config.headers["authorization"], "Bearer x");
+        assert_eq!(config.timeout, Duration::from_millis(2500));
+
+        let config = OtlpConfig::from_lookup(env(&[(
+            "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+            "http://collector/traces",
+        )]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            config.traces_url(),
+            "http://localhost:4317/opentelemetry.proto.collector.trace.v1.TraceService/Export"
+        );
+        assert_eq!(
+            config.with_protocol(OtlpProtocol::HttpJson).traces_url(),
+            "http://collector/traces"
+        );
+
+        assert!(OtlpConfig::from_lookup(env(&[
+            ("OTEL_EXPORTER_OTLP_ENDPOINT", "http://c"),
+            ("OTEL_EXPORTER_OTLP_PROTOCOL", "thrift"),
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_encode_json() {
+        let resource = Resource {
+            attributes: vec![("service.name".to_string(), Value::String("svc".into()))],
+        };
+        let json = encode_json(&resource, &[span()]);
+        for expected in [
+            r#""traceId":"0af7651916cd43dd8448eb211c80319c""#,
+            r#""kind":3"#,
+            r#""intValue":"-1""#,
+            r#""code":2"#,
+            r#""stringValue":"svc""#,
+        ] {
+            assert!(json.contains(expected), "{} not in {}", expected, json);
+        }
+        assert!(!json.contains("parentSpanId"));
+    }
+
+    #[test]
+    fn test_encode_protobuf() {
+        let mut w = ProtoWriter::default();
+        w.varint(300);
+        assert_eq!(w.buf, [0xac, 0x02]);
+        let mut w = ProtoWriter::default();
+        w.int64(3, -1);
+        assert_eq!(w.buf.len(), 11);
+
+        let body = encode_protobuf(&Resource::default(), &[span()]);
+        let find = |needle: &[u8]| body.windows(needle.len()).any(|w| w == needle);
+        // Span.trace_id: field 1, length 16.
+        let mut trace_id = vec![0x0a, 16];
+        trace_id.extend(id_bytes("0af7651916cd43dd8448eb211c80319c", 16));
+        assert!(find(&trace_id));
+        // Span.name "sync" and Span.kind CLIENT.
+        assert!(find(b"\x2a\x04sync\x30\x03"));
+        // Status { message: "boom", code: ERROR }.
+        assert!(find(b"\x7a\x08\x12\x04boom\x18\x02"));
+
+        let frame = grpc_frame(&body);
+        assert_eq!(frame[0], 0);
+        assert_eq!(
+            u32::from_be_bytes(frame[1..5].try_into().unwrap()) as usize,
+            body.len()
+        );
+        assert_eq!(id_bytes("zz", 8), vec![0; 8]);
+    }
+
+    #[test]
+    fn test_metric_reader_config() {
+        let config =
+            OtlpConfig::new("http://collector:4318").with_protocol(OtlpProtocol::HttpProtobuf);
+        assert_eq!(config.metrics_url(), "http://collector:4318/v1/metrics");
+        assert_eq!(
+            config
+                .with_metrics_endpoint("http://other/metrics")
+                .metrics_url(),
+            "http://other/metrics"
+        );
+        assert_eq!(
+            OtlpConfig::default().metrics_url(),
+            "http://localhost:4317/opentelemetry.proto.collector.metrics.v1.MetricsService/Export"
+        );
+
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            MetricReaderConfig::from_lookup(env(&[])).unwrap(),
+            MetricReaderConfig::default()
+        );
+        let reader = MetricReaderConfig::from_lookup(env(&[
+            ("OTEL_METRIC_EXPORT_INTERVAL", "5000"),
+            ("OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE", "Delta"),
+        ]))
+        .unwrap();
+        assert_eq!(reader.interval, Duration::from_secs(5));
+        assert_eq!(reader.temporality, Temporality::Delta);
+        assert!(
+            MetricReaderConfig::from_lookup(env(&[("OTEL_METRIC_EXPORT_INTERVAL", "0")])).is_err()
+        );
+        assert!(MetricReaderConfig::from_lookup(env(&[(
+            "OTEL_EXPORTER_OTLP_METRICS_TEMPORALITY_PREFERENCE",
+            "lowmemory"
+        )]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_metric_batch_temporality() {
+        let mut before = MetricsSnapshot::default();
+        before
+            .counters
+            .insert(r#"requests_total{status="200"}"#.into(), 10);
+        before.counters.insert("resets_total".into(), 7);
+        let mut after = before.clone();
+        *after
+            .counters
+            .get_mut(r#"requests_total{status="200"}"#)
+            .unwrap() = 15;
+        *after.counters.get_mut("resets_total").unwrap() = 2;
+        after.gauges.insert("queue_depth".into(), 3.0);
+
+        let value = |batch: &MetricBatch, name: &str| {
+            let points = &batch.metrics[&(name.to_string(), MetricKind::Sum)];
+            (points[0].start_time_ns, points[0].value.clone())
+        };
+        let cumulative =
+            MetricBatch::new(&after, Some((&before, 50)), Temporality::Cumulative, 1, 100);
+        assert_eq!(
+            value(&cumulative, "requests_total"),
+            (1, PointValue::Int(15))
+        );
+        let delta = MetricBatch::new(&after, Some((&before, 50)), Temporality::Delta, 1, 100);
+        assert_eq!(value(&delta, "requests_total"), (50, PointValue::Int(5)));
+        // A counter that went down was reset.
+        assert_eq!(value(&delta, "resets_total"), (50, PointValue::Int(2)));
+        // The first delta export counts from the start.
+        let first = MetricBatch::new(&after, None, Temporality::Delta, 1, 100);
+        assert_eq!(value(&first, "requests_total"), (1, PointValue::Int(15)));
+        assert_eq!(delta.len(), 3);
+    }
+
+    #[test]
+    fn test_encode_metrics() {
+        let mut snapshot = MetricsSnapshot::default();
+        snapshot
+            .counters
+            .insert(r#"requests_total{status="200"}"#.into(), 5);
+        snapshot.gauges.insert("queue_depth".into(), 1.5);
+        let resource = Resource {
+            attributes: vec![("service.name".to_string(), Value::String("svc".into()))],
+        };
+        let batch = MetricBatch::new(&snapshot, None, Temporality::Delta, 1, 2);
+
+        let json = encode_metrics_json(&resource, &batch);
+        for expected in [
+            r#""name":"requests_total""#,
+            r#""aggregationTemporality":1"#,
+            r#""isMonotonic":true"#,
+            r#""asInt":"5""#,
+            r#""asDouble":1.5"#,
+            r#""stringValue":"200""#,
+            r#""stringValue":"svc""#,
+        ] {
+            assert!(json.contains(expected), "{} not in {}", expected, json);
+        }
+
+        let body = encode_metrics_protobuf(&resource, &batch);
+        let find = |needle: &[u8]| body.windows(needle.len()).any(|w| w == needle);
+        // Metric.name "requests_total", then Metric.sum.
+        assert!(find(b"\x0a\x0erequests_total\x3a"));
+        // Sum.aggregation_temporality DELTA and Sum.is_monotonic.
+        assert!(find(b"\x10\x01\x18\x01"));
+        // NumberDataPoint.as_int 5 as an sfixed64.
+        assert!(find(&[0x31, 5, 0, 0, 0, 0, 0, 0, 0]));
+        // NumberDataPoint.as_double 1.5.
+        let mut double = vec![0x21];
+        double.extend(1.5f64.to_le_bytes());
+        assert!(find(&double));
+        assert!(MetricBatch::new(
+            &MetricsSnapshot::default(),
+            None,
+            Temporality::Cumulative,
+            1,
+            2
+        )
+        .is_empty());
+    }
+}
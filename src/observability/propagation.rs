@@ -0,0 +1,374 @@
+//! W3C Trace Context propagation.
+//!
+//! `observability.inject_context()` returns the `traceparent` (and
+//! `tracestate`, when there is one) headers for the innermost open span,
+//! to send with outgoing requests:
+//!
+//! ```text
+//! let span = trace.span_start("fetch", {}, {"kind": "client"})
+//! net_http.request("GET", url, observability.inject_context())
+//! trace.span_end(span)
+//! ```
+//!
+//! `observability.extract_context(headers)` reads those headers from an
+//! inbound request and makes the remote span the parent of spans started
+//! while no other span is open, so they join the caller's trace. Header
+//! values follow <https://www.w3.org/TR/trace-context/>; invalid ones are
+//! ignored, as the specification requires.
+
+use std::collections::HashMap;
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use super::tracer::tracer_provider;
+
+/// Name of the header carrying the trace id, parent span id and flags.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+/// Name of the header carrying vendor-specific trace state.
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+/// Most list members kept from a `tracestate` header.
+const MAX_TRACESTATE_MEMBERS: usize = 32;
+
+/// A span context received from, or sent to, another process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    /// Trace id, 32 lowercase hex characters.
+    pub trace_id: String,
+    /// Span id of the remote span, 16 lowercase hex characters.
+    pub span_id: String,
+    /// Whether the caller sampled the trace.
+    pub sampled: bool,
+    /// `tracestate` list members, in order.
+    pub trace_state: Vec<(String, String)>,
+}
+
+impl TraceContext {
+    /// Parse a `traceparent` header value.
+    ///
+    /// Versions above `00` are accepted as long as they start with the
+    /// version 00 fields; version `ff`, all-zero ids and uppercase hex are
+    /// rejected.
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let mut parts = value.split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let span_id = parts.next()?;
+        let flags = parts.next()?;
+        let extra = parts.next().is_some();
+
+        if !is_hex(version, 2) || version == "ff" || (version == "00" && extra) {
+            return None;
+        }
+        if !is_hex(trace_id, 32) || !is_hex(span_id, 16) || !is_hex(flags, 2) {
+            return None;
+        }
+        if is_zero(trace_id) || is_zero(span_id) {
+            return None;
+        }
+        let flags = u8::from_str_radix(flags, 16).ok()?;
+
+        Some(Self {
+            trace_id: trace_id.to_string(),
+            span_id: span_id.to_string(),
+            sampled: flags & 0x01 != 0,
+            trace_state: Vec::new(),
+        })
+    }
+
+    /// Attach the members of a `tracestate` header value. An invalid
+    /// header is ignored as a whole.
+    pub fn with_tracestate(mut self, value: &str) -> Self {
+        self.trace_state = parse_tracestate(value).unwrap_or_default();
+        self
+    }
+
+    /// Format as a version 00 `traceparent` header value.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id,
+            self.span_id,
+            u8::from(self.sampled)
+        )
+    }
+
+    /// Format the trace state as a `tracestate` header value, or `None`
+    /// when it is empty.
+    pub fn tracestate(&self) -> Option<String> {
+        if self.trace_state.is_empty() {
+            return None;
+        }
+        let members: Vec<String> = self
+            .trace_state
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        Some(members.join(","))
+    }
+
+    /// Convert to a Value map with `trace_id`, `span_id`, `sampled` and
+    /// `tracestate` (string or null) keys.
+    pub fn to_value(&self) -> Value {
+        let mut map = HashMap::new();
+        map.insert("trace_id".to_string(), Value::String(self.trace_id.clone()));
+        map.insert("span_id".to_string(), Value::String(self.span_id.clone()));
+        map.insert("sampled".to_string(), Value::Bool(self.sampled));
+        map.insert(
+            "tracestate".to_string(),
+            self.tracestate().map_or(Value::Null, Value::String),
+        );
+        Value::Map(map)
+    }
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn is_zero(s: &str) -> bool {
+    s.bytes().all(|b| b == b'0')
+}
+
+/// Parse a `tracestate` header value into its list members.
+///
+/// Returns `None` if any member is malformed or a key repeats. Members past
+/// the 32nd are dropped.
+pub fn parse_tracestate(value: &str) -> Option<Vec<(String, String)>> {
+    let mut members: Vec<(String, String)> = Vec::new();
+    for member in value.split(',') {
+        let member = member.trim_matches([' ', '\t']);
+        if member.is_empty() {
+            continue;
+        }
+        let (key, value) = member.split_once('=')?;
+        if !valid_tracestate_key(key) || !valid_tracestate_value(value) {
+            return None;
+        }
+        if members.iter().any(|(k, _)| k == key) {
+            return None;
+        }
+        members.push((key.to_string(), value.to_string()));
+    }
+    members.truncate(MAX_TRACESTATE_MEMBERS);
+    Some(members)
+}
+
+fn valid_tracestate_key(key: &str) -> bool {
+    let part = |s: &str, max: usize, first_digit: bool| {
+        let mut bytes = s.bytes();
+        let first_ok = bytes
+            .next()
+            .is_some_and(|b| b.is_ascii_lowercase() || (first_digit && b.is_ascii_digit()));
+        first_ok
+            && s.len() <= max
+            && bytes.all(|b| {
+                b.is_ascii_lowercase()
+                    || b.is_ascii_digit()
+                    || matches!(b, b'_' | b'-' | b'*' | b'/')
+            })
+    };
+    match key.split_once('@') {
+        Some((tenant, system)) => part(tenant, 241, true) && part(system, 14, false),
+        None => part(key, 256, false),
+    }
+}
+
+fn valid_tracestate_value(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 256
+        && !value.ends_with(' ')
+        && value
+            .bytes()
+            .all(|b| (0x20..=0x7e).contains(&b) && b != b',' && b != b'=')
+}
+
+/// Find a header by case-insensitive name. List values (repeated headers)
+/// are joined with commas.
+fn header(headers: &HashMap<String, Value>, name: &str) -> Option<String> {
+    let value = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value)?;
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::List(items) => {
+            let parts: Vec<&str> = items.iter().filter_map(|v| v.as_str()).collect();
+            (!parts.is_empty()).then(|| parts.join(","))
+        }
+        _ => None,
+    }
+}
+
+/// Get the trace context headers for outgoing requests.
+///
+/// # Returns
+///
+/// Map with `traceparent` for the innermost open span (or the remote
+/// parent set by `observability.extract_context` when no span is open)
+/// and `tracestate` when the trace carries one; an empty map outside any
+/// trace
+pub fn inject_context(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let mut headers = HashMap::new();
+    if let Some(context) = tracer_provider().propagation_context() {
+        headers.insert(
+            TRACEPARENT_HEADER.to_string(),
+            Value::String(context.traceparent()),
+        );
+        if let Some(state) = context.tracestate() {
+            headers.insert(TRACESTATE_HEADER.to_string(), Value::String(state));
+        }
+    }
+    Ok(Value::Map(headers))
+}
+
+/// Continue a trace from inbound request headers.
+///
+/// The remote span becomes the parent of spans started while no other span
+/// is open, replacing any remote parent set before. Headers without a
+/// valid `traceparent` clear it.
+///
+/// # Arguments
+///
+/// * `args[0]` - Headers (map of name to string, or list of strings for
+///   repeated headers; names are case-insensitive)
+///
+/// # Returns
+///
+/// Map with `trace_id`, `span_id`, `sampled` and `tracestate`, or null when
+/// the headers carry no valid trace context
+pub fn extract_context(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "observability.extract_context";
+    let headers = args
+        .first()
+        .and_then(|v| v.as_map())
+        .ok_or_else(|| Error::host_function(format!("{}: headers must be a map", FUNCTION)))?;
+
+    let context = header(headers, TRACEPARENT_HEADER)
+        .and_then(|value| TraceContext::from_traceparent(&value))
+        .map(|context| match header(headers, TRACESTATE_HEADER) {
+            Some(state) => context.with_tracestate(&state),
+            None => context,
+        });
+
+    let result = context.as_ref().map_or(Value::Null, TraceContext::to_value);
+    tracer_provider().set_remote_parent(context);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    #[test]
+    fn test_traceparent_parsing() {
+        let context = TraceContext::from_traceparent(TRACEPARENT).unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.span_id, "00f067aa0ba902b7");
+        assert!(context.sampled);
+        assert_eq!(context.traceparent(), TRACEPARENT);
+
+        // Later versions may append fields; version 00 may not.
+        assert!(TraceContext::from_traceparent(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra"
+        )
+        .is_some_and(|c| !c.sampled));
+        for invalid in [
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+        ] {
+            assert!(
+                TraceContext::from_traceparent(invalid).is_none(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_tracestate_parsing() {
+        let members =
+            parse_tracestate("rojo=00f067aa0ba902b7, congo=t61rcWkgMzE,,t@vendor=x").unwrap();
+        assert_eq!(
+            members,
+            vec![
+                ("rojo".to_string(), "00f067aa0ba902b7".to_string()),
+                ("congo".to_string(), "t61rcWkgMzE".to_string()),
+                ("t@vendor".to_string(), "x".to_string()),
+            ]
+        );
+        assert!(parse_tracestate("Upper=1").is_none());
+        assert!(parse_tracestate("a=1,a=2").is_none());
+        assert!(parse_tracestate("novalue").is_none());
+
+        let many: Vec<String> = (0..40).map(|i| format!("k{}=v", i)).collect();
+        assert_eq!(parse_tracestate(&many.join(",")).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_extract_then_inject() {
+        let ctx = create_test_ctx();
+        let mut headers = HashMap::new();
+        headers.insert("TraceParent".to_string(), Value::String(TRACEPARENT.into()));
+        headers.insert(
+            "tracestate".to_string(),
+            Value::List(vec![
+                Value::String("rojo=1".into()),
+                Value::String("congo=2".into()),
+            ]),
+        );
+
+        let extracted = extract_context(&[Value::Map(headers)], &ctx).unwrap();
+        let extracted = extracted.as_map().unwrap();
+        assert_eq!(
+            extracted["tracestate"],
+            Value::String("rojo=1,congo=2".into())
+        );
+
+        let provider = tracer_provider();
+        let span = provider
+            .start_span(
+                "handle",
+                super::super::tracer::SpanKind::Server,
+                HashMap::new(),
+                None,
+            )
+            .unwrap();
+        assert_eq!(span.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        let injected = inject_context(&[], &ctx).unwrap();
+        let injected = injected.as_map().unwrap();
+        assert_eq!(
+            injected["traceparent"],
+            Value::String(format!(
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01",
+                span.span_id
+            ))
+        );
+        assert_eq!(
+            injected["tracestate"],
+            Value::String("rojo=1,congo=2".into())
+        );
+
+        let ended = provider
+            .end_span(&span.span_id, super::super::tracer::SpanStatus::Ok)
+            .unwrap();
+        assert_eq!(ended.parent_span_id.as_deref(), Some("00f067aa0ba902b7"));
+
+        let cleared = extract_context(&[Value::Map(HashMap::new())], &ctx).unwrap();
+        assert_eq!(cleared, Value::Null);
+    }
+}
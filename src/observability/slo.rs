@@ -0,0 +1,525 @@
+//! Per-module error-rate SLOs.
+//!
+//! Every stdlib host function call is recorded as a success or an error
+//! against its module (`net`, `fs`, ...) in an [`SloTracker`]. Each module
+//! has an error budget, the fraction of calls allowed to fail, and the
+//! tracker computes the burn rate over a sliding window: the observed error
+//! rate divided by the budget. A burn rate of 1 spends the budget exactly;
+//! a module whose burn rate reaches [`SloConfig::burn_rate`] fires a
+//! [`SloAlert`] through the tracker's [`AlertHook`], and fires a resolved
+//! alert once it drops back below.
+//!
+//! With the default 1% budget and a burn rate threshold of 10, an alert
+//! fires when more than one in ten calls to a module fail, so embedders
+//! learn that scripts are failing against a dependency before the scripts
+//! report it themselves.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use fusabi_host::{ExecutionContext, Value};
+
+/// SLO settings for a tracker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SloConfig {
+    /// Fraction of calls allowed to fail, for modules without their own.
+    pub error_budget: f64,
+    /// Per-module error budgets.
+    pub module_budgets: HashMap<String, f64>,
+    /// Window the error rate is computed over.
+    pub window: Duration,
+    /// Burn rate at which an alert fires.
+    pub burn_rate: f64,
+    /// Calls a module needs in the window before it can alert, so a single
+    /// early failure does not page anyone.
+    pub min_calls: u64,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            error_budget: 0.01,
+            module_budgets: HashMap::new(),
+            window: Duration::from_secs(300),
+            burn_rate: 10.0,
+            min_calls: 20,
+        }
+    }
+}
+
+impl SloConfig {
+    /// Create a configuration with the defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the default error budget.
+    pub fn with_error_budget(mut self, budget: f64) -> Self {
+        self.error_budget = budget;
+        self
+    }
+
+    /// Set the error budget of one module.
+    pub fn with_module_budget(mut self, module: impl Into<String>, budget: f64) -> Self {
+        self.module_budgets.insert(module.into(), budget);
+        self
+    }
+
+    /// Set the window.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Set the burn rate at which alerts fire.
+    pub fn with_burn_rate(mut self, burn_rate: f64) -> Self {
+        self.burn_rate = burn_rate;
+        self
+    }
+
+    /// Set the calls needed before a module can alert.
+    pub fn with_min_calls(mut self, min_calls: u64) -> Self {
+        self.min_calls = min_calls;
+        self
+    }
+
+    /// Error budget of a module.
+    pub fn budget(&self, module: &str) -> f64 {
+        self.module_budgets
+            .get(module)
+            .copied()
+            .unwrap_or(self.error_budget)
+    }
+}
+
+/// Whether an alert started or ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertState {
+    /// The burn rate reached the threshold.
+    Firing,
+    /// The burn rate dropped back below the threshold.
+    Resolved,
+}
+
+impl AlertState {
+    /// Convert to string representation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertState::Firing => "firing",
+            AlertState::Resolved => "resolved",
+        }
+    }
+}
+
+/// An SLO alert for a module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SloAlert {
+    /// Whether the alert started or ended.
+    pub state: AlertState,
+    /// SLO status of the module when the alert changed state.
+    pub status: ModuleSlo,
+}
+
+/// Destination for SLO alerts.
+pub trait AlertHook: Send + Sync {
+    /// Handle an alert.
+    fn alert(&self, alert: &SloAlert);
+}
+
+impl std::fmt::Debug for dyn AlertHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AlertHook")
+    }
+}
+
+/// Alert hook that emits alerts as `tracing` events with target
+/// `fusabi::slo`: warnings when firing, info when resolved. This is the
+/// default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAlertHook;
+
+impl AlertHook for TracingAlertHook {
+    fn alert(&self, alert: &SloAlert) {
+        let status = &alert.status;
+        match alert.state {
+            AlertState::Firing => tracing::warn!(
+                target: "fusabi::slo",
+                module = %status.module,
+                error_rate = status.error_rate,
+                burn_rate = status.burn_rate,
+                "{} error rate {:.1}% is burning its budget {:.1}x",
+                status.module,
+                status.error_rate * 100.0,
+                status.burn_rate
+            ),
+            AlertState::Resolved => tracing::info!(
+                target: "fusabi::slo",
+                module = %status.module,
+                error_rate = status.error_rate,
+                burn_rate = status.burn_rate,
+                "{} error rate recovered",
+                status.module
+            ),
+        }
+    }
+}
+
+/// Alert hook that keeps alerts in memory, for tests and for embedders that
+/// poll for alerts.
+#[derive(Debug, Default)]
+pub struct MemoryAlertHook {
+    alerts: Mutex<Vec<SloAlert>>,
+}
+
+impl MemoryAlertHook {
+    /// Create an empty hook.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Alerts received so far.
+    pub fn alerts(&self) -> Vec<SloAlert> {
+        self.alerts.lock().clone()
+    }
+
+    /// Remove and return the alerts received so far.
+    pub fn take(&self) -> Vec<SloAlert> {
+        std::mem::take(&mut *self.alerts.lock())
+    }
+}
+
+impl AlertHook for MemoryAlertHook {
+    fn alert(&self, alert: &SloAlert) {
+        self.alerts.lock().push(alert.clone());
+    }
+}
+
+/// SLO status of a module over the current window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleSlo {
+    /// Module name.
+    pub module: String,
+    /// Calls in the window.
+    pub calls: u64,
+    /// Failed calls in the window.
+    pub errors: u64,
+    /// Fraction of calls in the window that failed.
+    pub error_rate: f64,
+    /// Error budget of the module.
+    pub error_budget: f64,
+    /// Error rate divided by the error budget.
+    pub burn_rate: f64,
+    /// Whether an alert is firing for the module.
+    pub alerting: bool,
+}
+
+impl ModuleSlo {
+    /// Convert to a Value map.
+    pub fn to_value(&self) -> Value {
+        let mut m = HashMap::new();
+        m.insert("module".into(), Value::String(self.module.clone()));
+        m.insert("calls".into(), Value::Int(self.calls as i64));
+        m.insert("errors".into(), Value::Int(self.errors as i64));
+        m.insert("error_rate".into(), Value::Float(self.error_rate));
+        m.insert("error_budget".into(), Value::Float(self.error_budget));
+        m.insert("burn_rate".into(), Value::Float(self.burn_rate));
+        m.insert("alerting".into(), Value::Bool(self.alerting));
+        Value::Map(m)
+    }
+}
+
+/// Buckets the window rotates through.
+const WINDOW_BUCKETS: u32 = 10;
+
+#[derive(Debug, Default)]
+struct ModuleWindow {
+    /// `(start, calls, errors)` buckets, oldest first.
+    buckets: VecDeque<(Instant, u64, u64)>,
+    alerting: bool,
+}
+
+/// Tracks call outcomes per module and raises burn-rate alerts.
+pub struct SloTracker {
+    config: SloConfig,
+    hook: Arc<dyn AlertHook>,
+    modules: Mutex<HashMap<String, ModuleWindow>>,
+}
+
+impl Default for SloTracker {
+    fn default() -> Self {
+        Self::new(SloConfig::default())
+    }
+}
+
+impl SloTracker {
+    /// Create a tracker that alerts through [`TracingAlertHook`].
+    pub fn new(config: SloConfig) -> Self {
+        Self {
+            config,
+            hook: Arc::new(TracingAlertHook),
+            modules: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Send alerts to `hook`.
+    pub fn with_alert_hook(mut self, hook: Arc<dyn AlertHook>) -> Self {
+        self.hook = hook;
+        self
+    }
+
+    /// Get the configuration.
+    pub fn config(&self) -> &SloConfig {
+        &self.config
+    }
+
+    /// Record the outcome of a call to `module`.
+    pub fn record(&self, module: &str, ok: bool) {
+        self.record_at(module, ok, Instant::now());
+    }
+
+    /// Record the outcome of a call to `module` at `now`.
+    pub fn record_at(&self, module: &str, ok: bool, now: Instant) {
+        let alert = {
+            let mut modules = self.modules.lock();
+            let window = modules.entry(module.to_string()).or_default();
+            self.expire(window, now);
+
+            let width = self.config.window / WINDOW_BUCKETS;
+            match window.buckets.back_mut() {
+                Some((start, calls, errors)) if now < *start + width => {
+                    *calls += 1;
+                    *errors += u64::from(!ok);
+                }
+                _ => window.buckets.push_back((now, 1, u64::from(!ok))),
+            }
+
+            let status = self.status_of(module, window);
+            let breached =
+                status.calls >= self.config.min_calls && status.burn_rate >= self.config.burn_rate;
+            if breached != window.alerting {
+                window.alerting = breached;
+                Some(SloAlert {
+                    state: if breached {
+                        AlertState::Firing
+                    } else {
+                        AlertState::Resolved
+                    },
+                    status: ModuleSlo {
+                        alerting: breached,
+                        ..status
+                    },
+                })
+            } else {
+                None
+            }
+        };
+
+        // Run the hook without holding the lock, so it may query the tracker.
+        if let Some(alert) = alert {
+            self.hook.alert(&alert);
+        }
+    }
+
+    /// Status of one module, if it has been called.
+    pub fn module_status(&self, module: &str) -> Option<ModuleSlo> {
+        self.module_status_at(module, Instant::now())
+    }
+
+    /// Status of one module as of `now`.
+    pub fn module_status_at(&self, module: &str, now: Instant) -> Option<ModuleSlo> {
+        let mut modules = self.modules.lock();
+        let window = modules.get_mut(module)?;
+        self.expire(window, now);
+        Some(self.status_of(module, window))
+    }
+
+    /// Status of every module that has been called, sorted by name.
+    pub fn status(&self) -> Vec<ModuleSlo> {
+        self.status_at(Instant::now())
+    }
+
+    /// Status of every module as of `now`.
+    pub fn status_at(&self, now: Instant) -> Vec<ModuleSlo> {
+        let mut modules = self.modules.lock();
+        let sorted: BTreeMap<_, _> = modules.iter_mut().collect();
+        sorted
+            .into_iter()
+            .map(|(module, window)| {
+                self.expire(window, now);
+                self.status_of(module, window)
+            })
+            .collect()
+    }
+
+    /// Forget every recorded call and alert.
+    pub fn reset(&self) {
+        self.modules.lock().clear();
+    }
+
+    fn expire(&self, window: &mut ModuleWindow, now: Instant) {
+        while let Some((start, _, _)) = window.buckets.front() {
+            if now.saturating_duration_since(*start) >= self.config.window {
+                window.buckets.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn status_of(&self, module: &str, window: &ModuleWindow) -> ModuleSlo {
+        let (calls, errors) = window
+            .buckets
+            .iter()
+            .fold((0, 0), |(c, e), (_, calls, errors)| (c + calls, e + errors));
+        let error_rate = if calls == 0 {
+            0.0
+        } else {
+            errors as f64 / calls as f64
+        };
+        let error_budget = self.config.budget(module);
+        let burn_rate = if error_budget > 0.0 {
+            error_rate / error_budget
+        } else if errors > 0 {
+            f64::INFINITY
+        } else {
+            0.0
+        };
+        ModuleSlo {
+            module: module.to_string(),
+            calls,
+            errors,
+            error_rate,
+            error_budget,
+            burn_rate,
+            alerting: window.alerting,
+        }
+    }
+}
+
+impl std::fmt::Debug for SloTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SloTracker")
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+/// Get the SLO status of stdlib modules.
+///
+/// # Arguments
+///
+/// * `args[0]` - Module name (optional; default: every module called so far)
+///
+/// # Returns
+///
+/// Status map with `calls`, `errors`, `error_rate`, `error_budget`,
+/// `burn_rate` and `alerting`, or a map from module name to status map when
+/// no module is given. Null for a module that has not been called.
+pub fn status(
+    tracker: &Arc<SloTracker>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    match args.first() {
+        None | Some(Value::Null) => Ok(Value::Map(
+            tracker
+                .status()
+                .into_iter()
+                .map(|status| (status.module.clone(), status.to_value()))
+                .collect(),
+        )),
+        Some(module) => {
+            let module = module.as_str().ok_or_else(|| {
+                fusabi_host::Error::host_function("health.slo: module must be a string")
+            })?;
+            Ok(tracker
+                .module_status(module)
+                .map_or(Value::Null, |status| status.to_value()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burn_rate_alerts_fire_and_resolve() {
+        let hook = Arc::new(MemoryAlertHook::new());
+        let tracker = SloTracker::new(
+            SloConfig::new()
+                .with_window(Duration::from_secs(60))
+                .with_min_calls(10)
+                .with_module_budget("fs", 0.5),
+        )
+        .with_alert_hook(hook.clone());
+        let start = Instant::now();
+
+        // 3 of 10 net calls fail: 30% against a 1% budget.
+        for i in 0..10 {
+            tracker.record_at("net", i % 10 >= 3, start);
+        }
+        // The same rate is within the fs budget.
+        for i in 0..10 {
+            tracker.record_at("fs", i % 10 >= 3, start);
+        }
+
+        let alerts = hook.take();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].state, AlertState::Firing);
+        assert_eq!(alerts[0].status.module, "net");
+        assert_eq!(alerts[0].status.errors, 3);
+        assert!((alerts[0].status.burn_rate - 30.0).abs() < 1e-9);
+
+        let status = tracker.status_at(start);
+        assert_eq!(
+            status.iter().map(|s| s.module.as_str()).collect::<Vec<_>>(),
+            vec!["fs", "net"]
+        );
+        assert!(!status[0].alerting);
+        assert!(status[1].alerting);
+
+        // Firing alerts are not repeated while the module keeps failing.
+        tracker.record_at("net", false, start);
+        assert!(hook.take().is_empty());
+
+        // Once the failures leave the window, successes resolve the alert.
+        let later = start + Duration::from_secs(61);
+        tracker.record_at("net", true, later);
+        let alerts = hook.take();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].state, AlertState::Resolved);
+        assert_eq!(tracker.module_status_at("net", later).unwrap().calls, 1);
+    }
+
+    #[test]
+    fn test_min_calls_and_status_fn() {
+        let hook = Arc::new(MemoryAlertHook::new());
+        let tracker = Arc::new(SloTracker::default().with_alert_hook(hook.clone()));
+        for _ in 0..5 {
+            tracker.record("net", false);
+        }
+        assert!(hook.alerts().is_empty());
+
+        let ctx = ExecutionContext::new(
+            1,
+            fusabi_host::Capabilities::none(),
+            fusabi_host::Limits::default(),
+            fusabi_host::Sandbox::new(fusabi_host::SandboxConfig::default()).unwrap(),
+        );
+        let all = status(&tracker, &[], &ctx).unwrap();
+        let net = &all.as_map().unwrap()["net"];
+        assert_eq!(net.as_map().unwrap()["errors"], Value::Int(5));
+        assert_eq!(
+            status(&tracker, &[Value::String("net".into())], &ctx).unwrap(),
+            *net
+        );
+        assert_eq!(
+            status(&tracker, &[Value::String("fs".into())], &ctx).unwrap(),
+            Value::Null
+        );
+    }
+}
@@ -0,0 +1,920 @@
+//! Span recording and export.
+//!
+//! [`init`] installs a process-wide [`TracerProvider`] built from an
+//! [`ObservabilityConfig`]: resource attributes come from the service name,
+//! version and `resource_attributes`, and with `otlp` set, ended spans are
+//! queued for a [`BatchSpanProcessor`] that exports them over OTLP (see
+//! [`otlp`](super::otlp)). [`shutdown`] exports what is queued and stops
+//! the processor.
+//!
+//! Scripts record spans with `trace.span_start`, `trace.span_event` and
+//! `trace.span_end`. A span without an explicit parent becomes a child of
+//! the innermost span still open, so nested start/end pairs form a tree:
+//!
+//! ```text
+//! let request = trace.span_start("sync", {"repo": name})
+//! let fetch = trace.span_start("fetch")     // child of "sync"
+//! trace.span_event(fetch, "retry", {"attempt": 2})
+//! trace.span_end(fetch)
+//! trace.span_end(request, "ok")
+//! ```
+//!
+//! Before [`init`] is called, spans are still tracked so scripts run the
+//! same way, but nothing is exported.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use parking_lot::{Mutex, RwLock};
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use super::propagation::TraceContext;
+use super::{ObservabilityConfig, SpanContext};
+
+/// Instrumentation scope reported with exported spans.
+pub const SCOPE_NAME: &str = env!("CARGO_PKG_NAME");
+
+pub(super) fn unix_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+/// Role of a span in a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpanKind {
+    /// Internal operation.
+    #[default]
+    Internal,
+    /// Handling of an inbound request.
+    Server,
+    /// Outbound request.
+    Client,
+    /// Message sent for later processing.
+    Producer,
+    /// Processing of a received message.
+    Consumer,
+}
+
+impl SpanKind {
+    /// Parse `"internal"`, `"server"`, `"client"`, `"producer"` or
+    /// `"consumer"`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "internal" => Some(SpanKind::Internal),
+            "server" => Some(SpanKind::Server),
+            "client" => Some(SpanKind::Client),
+            "producer" => Some(SpanKind::Producer),
+            "consumer" => Some(SpanKind::Consumer),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of a span.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SpanStatus {
+    /// Not set.
+    #[default]
+    Unset,
+    /// Completed successfully.
+    Ok,
+    /// Failed, with a description.
+    Error(String),
+}
+
+/// A timestamped annotation on a span.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanEvent {
+    /// Event name.
+    pub name: String,
+    /// Time in nanoseconds since the epoch.
+    pub time_ns: u64,
+    /// Event attributes.
+    pub attributes: HashMap<String, Value>,
+}
+
+/// A finished span, as handed to a [`SpanExporter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanData {
+    /// Trace ID, 32 hex digits.
+    pub trace_id: String,
+    /// Span ID, 16 hex digits.
+    pub span_id: String,
+    /// Parent span ID, absent for a root span.
+    pub parent_span_id: Option<String>,
+    /// Span name.
+    pub name: String,
+    /// Span kind.
+    pub kind: SpanKind,
+    /// Start time in nanoseconds since the epoch.
+    pub start_time_ns: u64,
+    /// End time in nanoseconds since the epoch.
+    pub end_time_ns: u64,
+    /// Span attributes.
+    pub attributes: HashMap<String, Value>,
+    /// Events, oldest first.
+    pub events: Vec<SpanEvent>,
+    /// Outcome.
+    pub status: SpanStatus,
+}
+
+/// Attributes describing the process that produced the spans.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Resource {
+    /// Attributes, in export order.
+    pub attributes: Vec<(String, Value)>,
+}
+
+impl Resource {
+    /// `service.name`, `service.version` and the SDK attributes, followed
+    /// by the configured resource attributes sorted by key.
+    pub fn from_config(config: &ObservabilityConfig) -> Self {
+        let string = |s: &str| Value::String(s.to_string());
+        let mut attributes = vec![
+            ("service.name".to_string(), string(&config.service_name)),
+            (
+                "service.version".to_string(),
+                string(&config.service_version),
+            ),
+            ("telemetry.sdk.name".to_string(), string(SCOPE_NAME)),
+            ("telemetry.sdk.language".to_string(), string("rust")),
+            (
+                "telemetry.sdk.version".to_string(),
+                string(env!("CARGO_PKG_VERSION")),
+            ),
+        ];
+        let mut extra: Vec<_> = config.resource_attributes.iter().collect();
+        extra.sort();
+        for (key, value) in extra {
+            attributes.retain(|(k, _)| k != key);
+            attributes.push((key.clone(), string(value)));
+        }
+        Self { attributes }
+    }
+}
+
+/// Destination for finished spans.
+pub trait SpanExporter: Send + Sync {
+    /// Exporter name, used in log messages.
+    fn name(&self) -> &str;
+
+    /// Deliver a batch of spans.
+    fn export(&self, resource: &Resource, spans: &[SpanData]) -> crate::Result<()>;
+
+    /// Release resources after the final export.
+    fn shutdown(&self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+/// Batching of ended spans before export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchConfig {
+    /// Spans held while waiting for export; more are dropped.
+    pub max_queue_size: usize,
+    /// Most spans in one export.
+    pub max_export_batch_size: usize,
+    /// Longest a span waits before export.
+    pub scheduled_delay: Duration,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            max_queue_size: 2048,
+            max_export_batch_size: 512,
+            scheduled_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl BatchConfig {
+    /// Set the queue size.
+    pub fn with_max_queue_size(mut self, size: usize) -> Self {
+        self.max_queue_size = size.max(1);
+        self
+    }
+
+    /// Set the batch size.
+    pub fn with_max_export_batch_size(mut self, size: usize) -> Self {
+        self.max_export_batch_size = size.max(1);
+        self
+    }
+
+    /// Set the export delay.
+    pub fn with_scheduled_delay(mut self, delay: Duration) -> Self {
+        self.scheduled_delay = delay;
+        self
+    }
+}
+
+enum Command {
+    /// A full batch is waiting.
+    Export,
+    Flush(mpsc::Sender<crate::Result<()>>),
+    Shutdown(mpsc::Sender<crate::Result<()>>),
+}
+
+/// Queues ended spans and exports them in batches on a background thread,
+/// every [`scheduled_delay`](BatchConfig::scheduled_delay) or as soon as a
+/// full batch is waiting.
+pub struct BatchSpanProcessor {
+    queue: Arc<Mutex<VecDeque<SpanData>>>,
+    commands: Mutex<mpsc::Sender<Command>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    config: BatchConfig,
+    dropped: AtomicU64,
+    shut_down: AtomicBool,
+}
+
+impl std::fmt::Debug for BatchSpanProcessor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchSpanProcessor")
+            .field("queued", &self.queue.lock().len())
+            .field("config", &self.config)
+            .field("dropped", &self.dropped)
+            .finish()
+    }
+}
+
+impl BatchSpanProcessor {
+    /// Start the export thread.
+    pub fn new(
+        exporter: Arc<dyn SpanExporter>,
+        resource: Resource,
+        config: BatchConfig,
+    ) -> crate::Result<Self> {
+        let queue = Arc::new(Mutex::new(VecDeque::new()));
+        let (commands, received) = mpsc::channel();
+
+        let pending = queue.clone();
+        let thread = std::thread::Builder::new()
+            .name("fusabi-span-export".to_string())
+            .spawn(move || {
+                let export = || export_queued(&*exporter, &resource, &pending, config);
+                loop {
+                    match received.recv_timeout(config.scheduled_delay) {
+                        Ok(Command::Export) | Err(RecvTimeoutError::Timeout) => {
+                            // Failures are logged in export_queued.
+                            let _ = export();
+                        }
+                        Ok(Command::Flush(reply)) => {
+                            let _ = reply.send(export());
+                        }
+                        Ok(Command::Shutdown(reply)) => {
+                            let result = export().and(exporter.shutdown());
+                            let _ = reply.send(result);
+                            break;
+                        }
+                        Err(RecvTimeoutError::Disconnected) => {
+                            let _ = export();
+                            let _ = exporter.shutdown();
+                            break;
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            queue,
+            commands: Mutex::new(commands),
+            thread: Mutex::new(Some(thread)),
+            config,
+            dropped: AtomicU64::new(0),
+            shut_down: AtomicBool::new(false),
+        })
+    }
+
+    /// Queue an ended span, dropping it if the queue is full or the
+    /// processor has shut down.
+    pub fn on_end(&self, span: SpanData) {
+        if self.shut_down.load(Ordering::SeqCst) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let queued = {
+            let mut queue = self.queue.lock();
+            if queue.len() >= self.config.max_queue_size {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            queue.push_back(span);
+            queue.len()
+        };
+        if queued % self.config.max_export_batch_size == 0 {
+            let _ = self.commands.lock().send(Command::Export);
+        }
+    }
+
+    /// Spans dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Export every queued span now.
+    pub fn force_flush(&self) -> crate::Result<()> {
+        self.request(Command::Flush)
+    }
+
+    /// Export every queued span and shut the exporter down. Subsequent
+    /// calls are no-ops.
+    pub fn shutdown(&self) -> crate::Result<()> {
+        if self.shut_down.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = self.request(Command::Shutdown);
+        if let Some(thread) = self.thread.lock().take() {
+            let _ = thread.join();
+        }
+        result
+    }
+
+    fn request(
+        &self,
+        command: fn(mpsc::Sender<crate::Result<()>>) -> Command,
+    ) -> crate::Result<()> {
+        let (reply, replied) = mpsc::channel();
+        if self.commands.lock().send(command(reply)).is_err() {
+            return Ok(());
+        }
+        replied
+            .recv()
+            .unwrap_or_else(|_| Err(crate::Error::Internal("span export thread stopped".into())))
+    }
+}
+
+impl Drop for BatchSpanProcessor {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+/// Export queued spans in batches; the first error is returned after every
+/// batch has been attempted.
+fn export_queued(
+    exporter: &dyn SpanExporter,
+    resource: &Resource,
+    queue: &Mutex<VecDeque<SpanData>>,
+    config: BatchConfig,
+) -> crate::Result<()> {
+    let mut first_error = None;
+    loop {
+        let batch: Vec<SpanData> = {
+            let mut queue = queue.lock();
+            let n = queue.len().min(config.max_export_batch_size);
+            queue.drain(..n).collect()
+        };
+        if batch.is_empty() {
+            break;
+        }
+        if let Err(e) = exporter.export(resource, &batch) {
+            tracing::warn!(exporter = exporter.name(), error = %e, spans = batch.len(), "span export failed");
+            first_error.get_or_insert(e);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+struct OpenSpan {
+    context: SpanContext,
+    parent_span_id: Option<String>,
+    kind: SpanKind,
+    events: Vec<SpanEvent>,
+}
+
+/// Records spans and hands ended ones to a [`BatchSpanProcessor`].
+pub struct TracerProvider {
+    resource: Resource,
+    processor: Option<BatchSpanProcessor>,
+    /// Open spans, innermost (most recently started) last.
+    open: Mutex<Vec<OpenSpan>>,
+    /// Span in another process that root spans continue from.
+    remote: Mutex<Option<TraceContext>>,
+}
+
+impl std::fmt::Debug for TracerProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TracerProvider")
+            .field("resource", &self.resource)
+            .field("processor", &self.processor)
+            .field("open_spans", &self.open.lock().len())
+            .finish()
+    }
+}
+
+impl TracerProvider {
+    /// Create a provider that tracks spans without exporting them.
+    pub fn new(resource: Resource) -> Self {
+        Self {
+            resource,
+            processor: None,
+            open: Mutex::new(Vec::new()),
+            remote: Mutex::new(None),
+        }
+    }
+
+    /// Create a provider exporting ended spans through `exporter`.
+    pub fn with_exporter(
+        resource: Resource,
+        exporter: Arc<dyn SpanExporter>,
+        batch: BatchConfig,
+    ) -> crate::Result<Self> {
+        let processor = BatchSpanProcessor::new(exporter, resource.clone(), batch)?;
+        Ok(Self {
+            processor: Some(processor),
+            ..Self::new(resource)
+        })
+    }
+
+    /// The resource reported with exported spans.
+    pub fn resource(&self) -> &Resource {
+        &self.resource
+    }
+
+    /// Whether ended spans are exported.
+    pub fn is_exporting(&self) -> bool {
+        self.processor.is_some()
+    }
+
+    /// Start a span.
+    ///
+    /// The parent is the open span `parent`, or when `None` the innermost
+    /// open span. Without an open span, the span continues from the remote
+    /// parent if one is set, and otherwise starts a new trace.
+    pub fn start_span(
+        &self,
+        name: impl Into<String>,
+        kind: SpanKind,
+        attributes: HashMap<String, Value>,
+        parent: Option<&str>,
+    ) -> crate::Result<SpanContext> {
+        let mut open = self.open.lock();
+        let remote = self.remote.lock();
+        let parent = match parent {
+            Some(id) => Some(
+                open.iter()
+                    .find(|s| s.context.span_id == id)
+                    .map(|p| (&p.context.trace_id, &p.context.span_id))
+                    .or_else(|| {
+                        remote
+                            .as_ref()
+                            .filter(|r| r.span_id == id)
+                            .map(|r| (&r.trace_id, &r.span_id))
+                    })
+                    .ok_or_else(|| {
+                        crate::Error::invalid_argument(format!("no open span {}", id))
+                    })?,
+            ),
+            None => open
+                .last()
+                .map(|p| (&p.context.trace_id, &p.context.span_id))
+                .or_else(|| remote.as_ref().map(|r| (&r.trace_id, &r.span_id))),
+        };
+
+        let mut context = SpanContext::new(name);
+        context.attributes = attributes;
+        let parent_span_id = parent.map(|(trace_id, span_id)| {
+            context.trace_id = trace_id.clone();
+            span_id.clone()
+        });
+        drop(remote);
+        open.push(OpenSpan {
+            context: context.clone(),
+            parent_span_id,
+            kind,
+            events: Vec::new(),
+        });
+        Ok(context)
+    }
+
+    /// Set or clear the span in another process that spans started
+    /// without an open parent continue from.
+    pub fn set_remote_parent(&self, context: Option<TraceContext>) {
+        *self.remote.lock() = context;
+    }
+
+    /// The remote parent, if set.
+    pub fn remote_parent(&self) -> Option<TraceContext> {
+        self.remote.lock().clone()
+    }
+
+    /// The context to propagate to other processes: the innermost open
+    /// span, carrying the remote parent's sampling and trace state when it
+    /// belongs to the same trace, or else the remote parent itself.
+    pub fn propagation_context(&self) -> Option<TraceContext> {
+        let current = self.current_span();
+        let remote = self.remote_parent();
+        match current {
+            Some(span) => {
+                let inherited = remote.filter(|r| r.trace_id == span.trace_id);
+                Some(TraceContext {
+                    trace_id: span.trace_id,
+                    span_id: span.span_id,
+                    sampled: inherited.as_ref().map_or(true, |r| r.sampled),
+                    trace_state: inherited.map(|r| r.trace_state).unwrap_or_default(),
+                })
+            }
+            None => remote,
+        }
+    }
+
+    /// The innermost open span.
+    pub fn current_span(&self) -> Option<SpanContext> {
+        self.open.lock().last().map(|s| s.context.clone())
+    }
+
+    /// Number of open spans.
+    pub fn open_spans(&self) -> usize {
+        self.open.lock().len()
+    }
+
+    /// Add an event to an open span.
+    pub fn add_event(
+        &self,
+        span_id: &str,
+        name: impl Into<String>,
+        attributes: HashMap<String, Value>,
+    ) -> crate::Result<()> {
+        let mut open = self.open.lock();
+        let span = open
+            .iter_mut()
+            .find(|s| s.context.span_id == span_id)
+            .ok_or_else(|| crate::Error::invalid_argument(format!("no open span {}", span_id)))?;
+        span.events.push(SpanEvent {
+            name: name.into(),
+            time_ns: unix_nanos(),
+            attributes,
+        });
+        Ok(())
+    }
+
+    /// End an open span and queue it for export. Spans started inside it
+    /// stay open.
+    pub fn end_span(&self, span_id: &str, status: SpanStatus) -> crate::Result<SpanData> {
+        let span = {
+            let mut open = self.open.lock();
+            let index = open
+                .iter()
+                .position(|s| s.context.span_id == span_id)
+                .ok_or_else(|| {
+                    crate::Error::invalid_argument(format!("no open span {}", span_id))
+                })?;
+            open.remove(index)
+        };
+
+        let data = SpanData {
+            trace_id: span.context.trace_id,
+            span_id: span.context.span_id,
+            parent_span_id: span.parent_span_id,
+            name: span.context.name,
+            kind: span.kind,
+            start_time_ns: span.context.start_time_ns,
+            end_time_ns: unix_nanos().max(span.context.start_time_ns),
+            attributes: span.context.attributes,
+            events: span.events,
+            status,
+        };
+        if let Some(processor) = &self.processor {
+            processor.on_end(data.clone());
+        }
+        Ok(data)
+    }
+
+    /// Spans dropped because the export queue was full.
+    pub fn dropped_spans(&self) -> u64 {
+        self.processor.as_ref().map_or(0, |p| p.dropped())
+    }
+
+    /// Export every ended span now.
+    pub fn force_flush(&self) -> crate::Result<()> {
+        match &self.processor {
+            Some(processor) => processor.force_flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Export every ended span and stop exporting. Spans still open are
+    /// discarded.
+    pub fn shutdown(&self) -> crate::Result<()> {
+        let open = std::mem::take(&mut *self.open.lock());
+        if !open.is_empty() {
+            tracing::debug!(
+                spans = open.len(),
+                "discarding spans still open at shutdown"
+            );
+        }
+        match &self.processor {
+            Some(processor) => processor.shutdown(),
+            None => Ok(()),
+        }
+    }
+}
+
+static PROVIDER: RwLock<Option<Arc<TracerProvider>>> = parking_lot::const_rwlock(None);
+
+/// Install the process-wide tracer provider described by `config`.
+///
+/// Spans are exported over OTLP when `config.otlp` is set and tracing is
+/// enabled; otherwise they are only tracked. A provider installed earlier
+/// is shut down first, exporting its queued spans.
+///
+/// # Errors
+///
+/// [`crate::Error::ModuleNotAvailable`] when `config.otlp` is set without
+/// the `observability-otlp` feature.
+pub fn init(config: &ObservabilityConfig) -> crate::Result<Arc<TracerProvider>> {
+    let resource = Resource::from_config(config);
+    let provider = match &config.otlp {
+        Some(otlp) if config.tracing_enabled => {
+            #[cfg(feature = "observability-otlp")]
+            {
+                let exporter = super::otlp::OtlpSpanExporter::new(otlp.clone())?;
+                TracerProvider::with_exporter(resource, Arc::new(exporter), otlp.batch)?
+            }
+            #[cfg(not(feature = "observability-otlp"))]
+            {
+                let _ = otlp;
+                return Err(crate::Error::ModuleNotAvailable(
+                    "OTLP span export requires the observability-otlp feature".to_string(),
+                ));
+            }
+        }
+        _ => TracerProvider::new(resource),
+    };
+    Ok(install(provider))
+}
+
+/// Install a provider exporting through `exporter`, for exporters other
+/// than OTLP.
+pub fn init_with_exporter(
+    config: &ObservabilityConfig,
+    exporter: Arc<dyn SpanExporter>,
+    batch: BatchConfig,
+) -> crate::Result<Arc<TracerProvider>> {
+    let provider = TracerProvider::with_exporter(Resource::from_config(config), exporter, batch)?;
+    Ok(install(provider))
+}
+
+fn install(provider: TracerProvider) -> Arc<TracerProvider> {
+    let provider = Arc::new(provider);
+    let previous = PROVIDER.write().replace(provider.clone());
+    if let Some(previous) = previous {
+        if let Err(e) = previous.shutdown() {
+            tracing::warn!(error = %e, "shutting down the previous tracer provider failed");
+        }
+    }
+    provider
+}
+
+/// The installed provider, or a shared one that exports nothing.
+pub fn tracer_provider() -> Arc<TracerProvider> {
+    if let Some(provider) = PROVIDER.read().as_ref() {
+        return provider.clone();
+    }
+    PROVIDER
+        .write()
+        .get_or_insert_with(|| {
+            Arc::new(TracerProvider::new(Resource::from_config(
+                &ObservabilityConfig::default(),
+            )))
+        })
+        .clone()
+}
+
+/// Export every ended span and uninstall the provider.
+pub fn shutdown() -> crate::Result<()> {
+    match PROVIDER.write().take() {
+        Some(provider) => provider.shutdown(),
+        None => Ok(()),
+    }
+}
+
+fn attributes_arg(value: Option<&Value>, function: &str) -> Result<HashMap<String, Value>> {
+    match value {
+        None | Some(Value::Null) => Ok(HashMap::new()),
+        Some(Value::Map(attributes)) => Ok(attributes.clone()),
+        Some(_) => Err(Error::host_function(format!(
+            "{}: attributes must be a map",
+            function
+        ))),
+    }
+}
+
+fn span_id_arg<'a>(args: &'a [Value], function: &str) -> Result<&'a str> {
+    args.first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function(format!("{}: missing span id", function)))
+}
+
+/// Start a span.
+///
+/// # Arguments
+///
+/// * `args[0]` - Span name
+/// * `args[1]` - Attributes (map, optional)
+/// * `args[2]` - Options (map, optional): `parent` (span id; default the
+///   innermost open span), `kind` (`"internal"` (default), `"server"`,
+///   `"client"`, `"producer"` or `"consumer"`)
+///
+/// # Returns
+///
+/// The span id, for `trace.span_event` and `trace.span_end`
+pub fn span_start(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "trace.span_start";
+    let name = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function(format!("{}: missing name", FUNCTION)))?;
+    let attributes = attributes_arg(args.get(1), FUNCTION)?;
+    let options = attributes_arg(args.get(2), FUNCTION)?;
+    let parent = match options.get("parent") {
+        None | Some(Value::Null) => None,
+        Some(v) => Some(v.as_str().ok_or_else(|| {
+            Error::host_function(format!("{}: parent must be a span id", FUNCTION))
+        })?),
+    };
+    let kind = match options.get("kind") {
+        None | Some(Value::Null) => SpanKind::Internal,
+        Some(v) => v.as_str().and_then(SpanKind::parse).ok_or_else(|| {
+            Error::host_function(format!("{}: unknown span kind {}", FUNCTION, v))
+        })?,
+    };
+
+    let span = tracer_provider()
+        .start_span(name, kind, attributes, parent)
+        .map_err(|e| Error::host_function(format!("{}: {}", FUNCTION, e)))?;
+    Ok(Value::String(span.span_id))
+}
+
+/// Add an event to an open span.
+///
+/// # Arguments
+///
+/// * `args[0]` - Span id
+/// * `args[1]` - Event name
+/// * `args[2]` - Attributes (map, optional)
+///
+/// # Returns
+///
+/// Null
+pub fn span_event(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "trace.span_event";
+    let span_id = span_id_arg(args, FUNCTION)?;
+    let name = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function(format!("{}: missing event name", FUNCTION)))?;
+    let attributes = attributes_arg(args.get(2), FUNCTION)?;
+
+    tracer_provider()
+        .add_event(span_id, name, attributes)
+        .map_err(|e| Error::host_function(format!("{}: {}", FUNCTION, e)))?;
+    Ok(Value::Null)
+}
+
+/// End a span.
+///
+/// # Arguments
+///
+/// * `args[0]` - Span id
+/// * `args[1]` - Status (optional): `"ok"`, `"error"` or `"unset"`
+///   (default)
+/// * `args[2]` - Error description (optional, with `"error"`)
+///
+/// # Returns
+///
+/// The span's duration in milliseconds
+pub fn span_end(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "trace.span_end";
+    let span_id = span_id_arg(args, FUNCTION)?;
+    let status = match args.get(1).and_then(|v| v.as_str()) {
+        None | Some("unset") => SpanStatus::Unset,
+        Some("ok") => SpanStatus::Ok,
+        Some("error") => SpanStatus::Error(
+            args.get(2)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        ),
+        Some(other) => {
+            return Err(Error::host_function(format!(
+                "{}: unknown status {}",
+                FUNCTION, other
+            )))
+        }
+    };
+
+    let span = tracer_provider()
+        .end_span(span_id, status)
+        .map_err(|e| Error::host_function(format!("{}: {}", FUNCTION, e)))?;
+    let nanos = span.end_time_ns - span.start_time_ns;
+    Ok(Value::Float(nanos as f64 / 1_000_000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryExporter {
+        batches: Mutex<Vec<Vec<SpanData>>>,
+        shut_down: AtomicBool,
+    }
+
+    impl SpanExporter for MemoryExporter {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        fn export(&self, _resource: &Resource, spans: &[SpanData]) -> crate::Result<()> {
+            self.batches.lock().push(spans.to_vec());
+            Ok(())
+        }
+
+        fn shutdown(&self) -> crate::Result<()> {
+            self.shut_down.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_spans_nest_and_export_in_batches() {
+        let exporter = Arc::new(MemoryExporter::default());
+        let batch = BatchConfig::default()
+            .with_max_export_batch_size(2)
+            .with_scheduled_delay(Duration::from_secs(60));
+        let config = ObservabilityConfig::new("svc").with_attribute("env", "test");
+        let provider =
+            TracerProvider::with_exporter(Resource::from_config(&config), exporter.clone(), batch)
+                .unwrap();
+        assert!(provider
+            .resource()
+            .attributes
+            .contains(&("env".to_string(), Value::String("test".into()))));
+
+        let root = provider
+            .start_span("root", SpanKind::Server, HashMap::new(), None)
+            .unwrap();
+        let child = provider
+            .start_span("child", SpanKind::Internal, HashMap::new(), None)
+            .unwrap();
+        assert_eq!(child.trace_id, root.trace_id);
+        assert_ne!(child.span_id, root.span_id);
+        provider
+            .add_event(&child.span_id, "retry", HashMap::new())
+            .unwrap();
+
+        let ended = provider.end_span(&child.span_id, SpanStatus::Ok).unwrap();
+        assert_eq!(ended.parent_span_id.as_deref(), Some(root.span_id.as_str()));
+        assert_eq!(ended.events[0].name, "retry");
+        assert!(provider.end_span(&child.span_id, SpanStatus::Ok).is_err());
+
+        let other = provider
+            .start_span("other", SpanKind::Internal, HashMap::new(), None)
+            .unwrap();
+        assert_eq!(other.trace_id, root.trace_id);
+        provider
+            .end_span(&other.span_id, SpanStatus::Unset)
+            .unwrap();
+        provider
+            .end_span(&root.span_id, SpanStatus::Error("boom".into()))
+            .unwrap();
+        assert_eq!(provider.open_spans(), 0);
+
+        provider.force_flush().unwrap();
+        let batches = exporter.batches.lock().clone();
+        assert!(batches.iter().all(|b| b.len() <= 2));
+        let names: Vec<_> = batches.concat().into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["child", "other", "root"]);
+
+        provider.shutdown().unwrap();
+        assert!(exporter.shut_down.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_full_queue_drops_spans() {
+        let exporter = Arc::new(MemoryExporter::default());
+        let batch = BatchConfig::default()
+            .with_max_queue_size(1)
+            .with_max_export_batch_size(10)
+            .with_scheduled_delay(Duration::from_secs(60));
+        let provider =
+            TracerProvider::with_exporter(Resource::default(), exporter.clone(), batch).unwrap();
+        for _ in 0..3 {
+            let span = provider
+                .start_span("s", SpanKind::Internal, HashMap::new(), None)
+                .unwrap();
+            provider.end_span(&span.span_id, SpanStatus::Unset).unwrap();
+        }
+        assert_eq!(provider.dropped_spans(), 2);
+        provider.shutdown().unwrap();
+        assert_eq!(exporter.batches.lock().concat().len(), 1);
+    }
+}
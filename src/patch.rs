@@ -0,0 +1,236 @@
+//! Patch module.
+//!
+//! Provides unified diff creation and application for text files, so
+//! scripts can propose a minimal change, show it for review, and apply it.
+//!
+//! Applying a patch is all-or-nothing: if any hunk does not match the
+//! current file contents the file is left untouched and a conflict error
+//! names the failing hunk.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use fusabi_host::ExecutionContext;
+use fusabi_host::Value;
+
+use crate::safety::SafetyConfig;
+
+/// Default number of context lines around each hunk.
+const DEFAULT_CONTEXT: usize = 3;
+
+/// Create a unified diff between two texts.
+///
+/// # Arguments
+///
+/// * `args[0]` - Original text
+/// * `args[1]` - Modified text
+/// * `args[2]` - Options map (optional): `context` (lines, default 3),
+///   `old_name` and `new_name` (header labels, default `original`/`modified`)
+///
+/// # Returns
+///
+/// The unified diff as a string (empty when the texts are identical)
+pub fn create(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let old = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("patch.create: missing old text"))?;
+
+    let new = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("patch.create: missing new text"))?;
+
+    let options = args.get(2).and_then(|v| v.as_map());
+
+    let context = match options.and_then(|m| m.get("context")) {
+        Some(value) => value.as_int().filter(|n| *n >= 0).ok_or_else(|| {
+            fusabi_host::Error::host_function(
+                "patch.create: context must be a non-negative integer",
+            )
+        })? as usize,
+        None => DEFAULT_CONTEXT,
+    };
+
+    if old == new {
+        return Ok(Value::String(String::new()));
+    }
+
+    let mut diff_options = diffy::DiffOptions::new();
+    diff_options.set_context_len(context);
+    if let Some(name) = options
+        .and_then(|m| m.get("old_name"))
+        .and_then(|v| v.as_str())
+    {
+        diff_options.set_original_filename(name.to_string());
+    }
+    if let Some(name) = options
+        .and_then(|m| m.get("new_name"))
+        .and_then(|v| v.as_str())
+    {
+        diff_options.set_modified_filename(name.to_string());
+    }
+
+    Ok(Value::String(
+        diff_options.create_patch(old, new).to_string(),
+    ))
+}
+
+/// Apply a unified diff to a file.
+///
+/// The file must be readable and writable under the path allowlist, even
+/// for a dry run, so a dry run reliably predicts whether the real apply
+/// would be permitted.
+///
+/// # Arguments
+///
+/// * `args[0]` - Path of the file to patch
+/// * `args[1]` - Unified diff text
+/// * `args[2]` - Dry run (optional bool, default false): check and report
+///   without writing
+///
+/// # Returns
+///
+/// Map with `changed` (bool), `hunks` (count), `dry_run` (bool), and
+/// `content` (the patched text)
+pub fn apply(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let path_str = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("patch.apply: missing path argument"))?;
+
+    let diff = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("patch.apply: missing diff argument"))?;
+
+    let dry_run = args.get(2).and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let path = Path::new(path_str);
+
+    // Check safety
+    safety
+        .paths
+        .check_read(path)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+    safety
+        .paths
+        .check_write(path)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let original = std::fs::read_to_string(path)
+        .map_err(|e| fusabi_host::Error::host_function(format!("patch.apply: {}", e)))?;
+
+    let (patched, hunks) = apply_to_str(&original, diff)
+        .map_err(|e| fusabi_host::Error::host_function(format!("patch.apply: {}", e)))?;
+
+    let changed = patched != original;
+    if changed && !dry_run {
+        std::fs::write(path, &patched)
+            .map_err(|e| fusabi_host::Error::host_function(format!("patch.apply: {}", e)))?;
+    }
+
+    let mut result = HashMap::new();
+    result.insert("changed".to_string(), Value::Bool(changed));
+    result.insert("hunks".to_string(), Value::Int(hunks as i64));
+    result.insert("dry_run".to_string(), Value::Bool(dry_run));
+    result.insert("content".to_string(), Value::String(patched));
+    Ok(Value::Map(result))
+}
+
+/// Apply a unified diff to text, returning the patched text and hunk count.
+pub fn apply_to_str(original: &str, diff: &str) -> crate::Result<(String, usize)> {
+    if diff.trim().is_empty() {
+        return Ok((original.to_string(), 0));
+    }
+
+    let patch = diffy::Patch::from_str(diff)
+        .map_err(|e| crate::Error::invalid_argument(format!("invalid diff: {}", e)))?;
+
+    let patched = diffy::apply(original, &patch)
+        .map_err(|e| crate::Error::Filesystem(format!("conflict: {}", e)))?;
+
+    Ok((patched, patch.hunks().len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::PathAllowlist;
+    use fusabi_host::Capabilities;
+    use fusabi_host::Limits;
+    use fusabi_host::{Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_create_and_apply() {
+        let ctx = create_test_ctx();
+        let old = "host = localhost\nport = 8080\nmode = dev\n";
+        let new = "host = localhost\nport = 9090\nmode = dev\n";
+
+        let diff = create(
+            &[Value::String(old.into()), Value::String(new.into())],
+            &ctx,
+        )
+        .unwrap();
+        let diff = diff.as_str().unwrap();
+        assert!(diff.contains("-port = 8080"));
+        assert!(diff.contains("+port = 9090"));
+
+        let identical = create(
+            &[Value::String(old.into()), Value::String(old.into())],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(identical, Value::String(String::new()));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.conf");
+        std::fs::write(&path, old).unwrap();
+        let path_value = Value::String(path.to_string_lossy().into_owned());
+
+        let denied = Arc::new(SafetyConfig::new());
+        let args = [path_value.clone(), Value::String(diff.into())];
+        assert!(apply(&denied, &args, &ctx).is_err());
+
+        let safety =
+            Arc::new(SafetyConfig::new().with_paths(PathAllowlist::none().allow_rw(dir.path())));
+
+        // Dry run reports the result without touching the file.
+        let args = [
+            path_value.clone(),
+            Value::String(diff.into()),
+            Value::Bool(true),
+        ];
+        let result = apply(&safety, &args, &ctx).unwrap();
+        let map = result.as_map().unwrap();
+        assert_eq!(map.get("changed"), Some(&Value::Bool(true)));
+        assert_eq!(map.get("hunks"), Some(&Value::Int(1)));
+        assert_eq!(map.get("content"), Some(&Value::String(new.into())));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), old);
+
+        let args = [path_value.clone(), Value::String(diff.into())];
+        apply(&safety, &args, &ctx).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), new);
+
+        // Re-applying conflicts and leaves the file untouched.
+        let err = apply(&safety, &args, &ctx).unwrap_err();
+        assert!(err.to_string().contains("conflict"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), new);
+    }
+
+    #[test]
+    fn test_invalid_diff() {
+        assert!(apply_to_str("a\n", "@@ not a hunk").is_err());
+        assert_eq!(apply_to_str("a\n", "").unwrap(), ("a\n".to_string(), 0));
+    }
+}
@@ -1,9 +1,28 @@
 //! Process execution module.
 //!
 //! Provides functions for executing system processes with safety controls.
+//!
+//! Commands are resolved against `PATH` before they run (see
+//! [`resolve_command`]), so a missing command fails with the same
+//! "command not found" error on every platform.
+//!
+//! Commands run in their own process group on Unix and in a Job Object on
+//! Windows, so a timeout kills everything the command started rather than
+//! only the direct child. On Windows, arguments are quoted for
+//! `CommandLineToArgvW` (see [`quote_windows_arg`]), `.bat` and `.cmd`
+//! files run through `cmd.exe` (see [`batch_command_line`]), and CRLF line
+//! endings in captured output are converted to LF.
+
+#[cfg(windows)]
+mod job;
 
+use std::ffi::OsStr;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use fusabi_host::ExecutionContext;
 use fusabi_host::Value;
@@ -34,31 +53,420 @@ pub fn exec(
         .filter_map(|v| v.as_str().map(String::from))
         .collect();
 
+    safety
+        .check_privilege_escalation(command, &cmd_args)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let path = resolve_command(safety, command).ok_or_else(|| {
+        fusabi_host::Error::host_function(
+            crate::Error::process(format!("command not found: {}", command)).to_string(),
+        )
+    })?;
+
     // Apply timeout
     let timeout = timeout
         .map(|t| safety.clamp_timeout(t))
         .unwrap_or(safety.default_timeout);
 
-    // Execute command (simulated)
     tracing::info!(
         "Executing: {} {:?} (timeout: {:?})",
-        command,
+        path.display(),
         cmd_args,
         timeout
     );
 
-    // In real implementation, would use tokio::process::Command
-    let output = format!("Executed: {} {}", command, cmd_args.join(" "));
+    let output = run(&path, &cmd_args, timeout)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
 
     Ok(Value::Map({
         let mut m = std::collections::HashMap::new();
-        m.insert("stdout".into(), Value::String(output));
-        m.insert("stderr".into(), Value::String(String::new()));
-        m.insert("exit_code".into(), Value::Int(0));
+        m.insert("stdout".into(), Value::String(output.stdout));
+        m.insert("stderr".into(), Value::String(output.stderr));
+        m.insert(
+            "exit_code".into(),
+            output
+                .exit_code
+                .map_or(Value::Null, |code| Value::Int(code.into())),
+        );
         m
     }))
 }
 
+/// Captured result of a command run by [`run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecOutput {
+    /// Standard output, decoded as UTF-8 (lossily).
+    pub stdout: String,
+    /// Standard error, decoded as UTF-8 (lossily).
+    pub stderr: String,
+    /// Exit code, or `None` if the process was ended by a signal.
+    pub exit_code: Option<i32>,
+}
+
+/// How often a running command is checked for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Run an executable with arguments and capture its output.
+///
+/// Standard input is closed. If the command has not exited and closed its
+/// output within `timeout`, the command and every process it started are
+/// killed and a timeout error is returned. On Windows, processes the
+/// command leaves running in the background are also ended when it
+/// finishes, as its job is closed.
+pub fn run(program: &Path, args: &[String], timeout: Duration) -> crate::Result<ExecOutput> {
+    let deadline = Instant::now() + timeout;
+    let mut tree = ProcessTree::spawn(program, args)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut pending = 0;
+    if let Some(pipe) = tree.child.stdout.take() {
+        read_pipe(pipe, 0, tx.clone());
+        pending += 1;
+    }
+    if let Some(pipe) = tree.child.stderr.take() {
+        read_pipe(pipe, 1, tx);
+        pending += 1;
+    }
+
+    let status = loop {
+        match tree.child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) => {}
+            Err(e) => {
+                tree.kill();
+                return Err(e.into());
+            }
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            tree.kill();
+            return Err(crate::Error::timeout(timeout));
+        }
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    };
+
+    // Background processes the command started can keep its output open,
+    // so reading is bounded by the same deadline.
+    let mut captured = [Vec::new(), Vec::new()];
+    for _ in 0..pending {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(remaining) {
+            Ok((index, bytes)) => captured[index] = bytes,
+            Err(_) => {
+                tree.kill();
+                return Err(crate::Error::timeout(timeout));
+            }
+        }
+    }
+
+    let [stdout, stderr] = captured;
+    Ok(ExecOutput {
+        stdout: decode_output(stdout),
+        stderr: decode_output(stderr),
+        exit_code: status.code(),
+    })
+}
+
+/// A running command and, on Windows, the job holding its descendants.
+struct ProcessTree {
+    child: Child,
+    #[cfg(windows)]
+    job: job::JobObject,
+}
+
+impl ProcessTree {
+    fn spawn(program: &Path, args: &[String]) -> crate::Result<Self> {
+        let mut command = build_command(program, args)?;
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(job::CREATE_SUSPENDED);
+        }
+
+        let spawn_error =
+            |e: std::io::Error| crate::Error::process(format!("{}: {}", program.display(), e));
+        let child = command.spawn().map_err(spawn_error)?;
+
+        #[cfg(windows)]
+        {
+            let job = job::JobObject::new().and_then(|job| job.adopt(&child).map(|()| job));
+            match job {
+                Ok(job) => Ok(Self { child, job }),
+                Err(e) => {
+                    let mut child = child;
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    Err(spawn_error(e))
+                }
+            }
+        }
+
+        #[cfg(not(windows))]
+        Ok(Self { child })
+    }
+
+    /// Kill the command and everything it started, then reap it.
+    fn kill(&mut self) {
+        #[cfg(unix)]
+        {
+            // The child leads its own process group, so this also reaches
+            // processes it started.
+            // SAFETY: kill(2) has no memory safety requirements.
+            unsafe {
+                libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+            }
+        }
+
+        #[cfg(windows)]
+        {
+            let _ = self.job.terminate();
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(not(windows))]
+fn build_command(program: &Path, args: &[String]) -> crate::Result<Command> {
+    let mut command = Command::new(program);
+    command.args(args);
+    Ok(command)
+}
+
+#[cfg(windows)]
+fn build_command(program: &Path, args: &[String]) -> crate::Result<Command> {
+    use std::os::windows::process::CommandExt;
+
+    let is_batch = program
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("bat") || ext.eq_ignore_ascii_case("cmd"));
+
+    if is_batch {
+        let shell = std::env::var_os("ComSpec").unwrap_or_else(|| "cmd.exe".into());
+        let mut command = Command::new(shell);
+        command
+            .raw_arg("/d /v:off /s /c")
+            .raw_arg(format!("\"{}\"", batch_command_line(program, args)?));
+        return Ok(command);
+    }
+
+    let mut command = Command::new(program);
+    for arg in args {
+        command.raw_arg(quote_windows_arg(arg));
+    }
+    Ok(command)
+}
+
+/// Quote an argument so `CommandLineToArgvW` (and the MSVC runtime) parse
+/// it back unchanged.
+///
+/// Arguments without whitespace or quotes are returned as they are.
+/// Otherwise the argument is wrapped in quotes, embedded quotes are
+/// escaped with a backslash, and backslashes are doubled where they
+/// precede a quote.
+pub fn quote_windows_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.to_string();
+    }
+
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        if c == '\\' {
+            backslashes += 1;
+        } else {
+            if c == '"' {
+                quoted.extend(std::iter::repeat('\\').take(backslashes + 1));
+            }
+            backslashes = 0;
+        }
+        quoted.push(c);
+    }
+    quoted.extend(std::iter::repeat('\\').take(backslashes));
+    quoted.push('"');
+    quoted
+}
+
+/// Build the command line `cmd.exe /s /c` runs for a batch file.
+///
+/// `cmd.exe` parses batch arguments with its own rules: the script path is
+/// always quoted, arguments containing whitespace or `cmd` metacharacters
+/// are quoted with embedded quotes doubled, and arguments that `cmd` would
+/// expand or split whatever the quoting (`%`, line breaks, NUL) are
+/// rejected.
+pub fn batch_command_line(program: &Path, args: &[String]) -> crate::Result<String> {
+    const SPECIAL: &[char] = &[
+        ' ', '\t', '"', '&', '|', '<', '>', '(', ')', '^', ',', ';', '=', '!',
+    ];
+
+    let mut line = format!("\"{}\"", program.display());
+    for arg in args {
+        if arg.contains(['%', '\r', '\n', '\0']) {
+            return Err(crate::Error::invalid_argument(format!(
+                "argument cannot be passed to a batch file: {:?}",
+                arg
+            )));
+        }
+        line.push(' ');
+        if arg.is_empty() || arg.contains(SPECIAL) {
+            line.push('"');
+            line.push_str(&arg.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(arg);
+        }
+    }
+    Ok(line)
+}
+
+/// Read a pipe to the end on a thread, sending the bytes tagged with
+/// `index`.
+fn read_pipe(
+    mut pipe: impl Read + Send + 'static,
+    index: usize,
+    tx: mpsc::Sender<(usize, Vec<u8>)>,
+) {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        let _ = tx.send((index, bytes));
+    });
+}
+
+fn decode_output(bytes: Vec<u8>) -> String {
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    if cfg!(windows) {
+        normalize_newlines(text)
+    } else {
+        text
+    }
+}
+
+/// Convert CRLF line endings to LF.
+fn normalize_newlines(text: String) -> String {
+    if text.contains("\r\n") {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    }
+}
+
+/// Find the executable a command name refers to.
+///
+/// # Arguments
+///
+/// * `args[0]` - Command name, or a path to an executable
+///
+/// # Returns
+///
+/// Absolute path of the executable, or null if it is not found in an
+/// allowed directory
+pub fn which(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let command = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("which: missing command argument"))?;
+
+    safety
+        .check_execute(command)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    Ok(
+        resolve_command(safety, command).map_or(Value::Null, |path| {
+            Value::String(path.to_string_lossy().into_owned())
+        }),
+    )
+}
+
+/// Resolve a command to an executable file.
+///
+/// Names containing a path separator are checked as paths. Other names are
+/// looked up in each absolute `PATH` entry that the safety configuration
+/// allows commands to run from; relative entries such as `.` are skipped.
+/// On Windows, names without an extension are also tried with each
+/// extension in `PATHEXT` (`.COM;.EXE;.BAT;.CMD` if unset).
+pub fn resolve_command(safety: &SafetyConfig, command: &str) -> Option<PathBuf> {
+    if command.is_empty() {
+        return None;
+    }
+
+    let extensions = if cfg!(windows) {
+        windows_extensions(std::env::var_os("PATHEXT").as_deref())
+    } else {
+        Vec::new()
+    };
+
+    let as_path = Path::new(command);
+    if as_path.components().count() > 1 || command.contains('/') {
+        let dir = as_path.parent().filter(|p| !p.as_os_str().is_empty());
+        if !safety.can_run_from(dir.unwrap_or(Path::new("."))) {
+            return None;
+        }
+        return candidates(as_path, &extensions).find(|p| is_executable(p));
+    }
+
+    let path_var = std::env::var_os("PATH")?;
+    std::env::split_paths(&path_var)
+        .filter(|dir| dir.is_absolute() && safety.can_run_from(dir))
+        .find_map(|dir| candidates(&dir.join(command), &extensions).find(|p| is_executable(p)))
+}
+
+/// Extensions to try on Windows, from a `PATHEXT` value.
+fn windows_extensions(pathext: Option<&OsStr>) -> Vec<String> {
+    let pathext = pathext
+        .and_then(|v| v.to_str())
+        .filter(|v| !v.is_empty())
+        .unwrap_or(".COM;.EXE;.BAT;.CMD");
+    pathext
+        .split(';')
+        .filter(|ext| ext.starts_with('.') && ext.len() > 1)
+        .map(|ext| ext.to_ascii_lowercase())
+        .collect()
+}
+
+/// The path itself, then (when it has no extension) the path with each
+/// extension appended.
+fn candidates<'a>(path: &'a Path, extensions: &'a [String]) -> impl Iterator<Item = PathBuf> + 'a {
+    let with_extensions = extensions
+        .iter()
+        .filter(move |_| path.extension().is_none())
+        .map(move |ext| {
+            let mut name = path.as_os_str().to_os_string();
+            name.push(ext);
+            PathBuf::from(name)
+        });
+    std::iter::once(path.to_path_buf()).chain(with_extensions)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|m| m.is_file())
+}
+
 /// Spawn a command without waiting.
 pub fn spawn(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
     let command = args
@@ -150,4 +558,191 @@ mod tests {
         let result = exec(&safety, None, &[Value::String("rm".into())], &ctx);
         assert!(result.is_err()); // rm not in allowed list
     }
+
+    #[test]
+    fn test_exec_privilege_escalation_denied() {
+        // A sudo that does not exist, so the test never escalates for real.
+        let dir = tempfile::tempdir().unwrap();
+        let sudo = dir.path().join("sudo").to_string_lossy().into_owned();
+        let safety = Arc::new(
+            SafetyConfig::new()
+                .with_allow_process(true)
+                .with_allowed_commands([sudo.as_str()]),
+        );
+        let ctx = create_test_ctx();
+        let args = [
+            Value::String(sudo.clone()),
+            Value::String("systemctl".into()),
+        ];
+
+        let err = exec(&safety, None, &args, &ctx).unwrap_err();
+        assert!(err.to_string().contains("privilege escalation"));
+
+        // Once allowed, the call gets past the check to command lookup.
+        let safety = Arc::new((*safety).clone().with_allow_privilege_escalation(true));
+        let err = exec(&safety, None, &args, &ctx).unwrap_err();
+        assert!(err.to_string().contains("command not found"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_exec_privilege_escalation_allowed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in sudo that only echoes, so allowing escalation runs it
+        // instead of the real one.
+        let dir = tempfile::tempdir().unwrap();
+        let sudo = dir.path().join("sudo");
+        std::fs::write(&sudo, "#!/bin/sh\necho \"fake sudo $*\"\n").unwrap();
+        std::fs::set_permissions(&sudo, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let sudo = sudo.to_string_lossy().into_owned();
+
+        let safety = SafetyConfig::new()
+            .with_allow_process(true)
+            .with_allowed_commands([sudo.as_str()]);
+        let ctx = create_test_ctx();
+        let args = [Value::String(sudo.clone()), Value::String("id".into())];
+
+        assert!(exec(&Arc::new(safety.clone()), None, &args, &ctx).is_err());
+
+        let allowed = Arc::new(safety.with_allow_privilege_escalation(true));
+        let output = exec(&allowed, None, &args, &ctx).unwrap();
+        let output = output.as_map().unwrap();
+        assert_eq!(output["stdout"], Value::String("fake sudo id\n".into()));
+        assert_eq!(output["exit_code"], Value::Int(0));
+    }
+
+    #[test]
+    fn test_exec_command_not_found() {
+        let safety = Arc::new(SafetyConfig::new().with_allow_process(true));
+        let ctx = create_test_ctx();
+
+        let err = exec(
+            &safety,
+            None,
+            &[Value::String("fusabi-no-such-command".into())],
+            &ctx,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("command not found"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_which() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let tool = dir.path().join("tool");
+        std::fs::write(&tool, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let data = dir.path().join("data");
+        std::fs::write(&data, "").unwrap();
+
+        let safety = SafetyConfig::new().with_allow_process(true);
+        assert_eq!(
+            resolve_command(&safety, tool.to_str().unwrap()),
+            Some(tool.clone())
+        );
+        assert_eq!(resolve_command(&safety, data.to_str().unwrap()), None);
+
+        let confined = safety.with_allowed_command_dirs(["/nonexistent"]);
+        assert_eq!(resolve_command(&confined, tool.to_str().unwrap()), None);
+
+        let ctx = create_test_ctx();
+        let denied = Arc::new(SafetyConfig::strict());
+        assert!(which(&denied, &[Value::String("ls".into())], &ctx).is_err());
+        let allowed = Arc::new(SafetyConfig::new().with_allow_process(true));
+        assert_eq!(
+            which(
+                &allowed,
+                &[Value::String("fusabi-no-such-command".into())],
+                &ctx
+            )
+            .unwrap(),
+            Value::Null
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_captures_output() {
+        let args = [
+            "-c".to_string(),
+            "echo out; echo err >&2; exit 3".to_string(),
+        ];
+        let output = run(Path::new("/bin/sh"), &args, Duration::from_secs(10)).unwrap();
+        assert_eq!(output.stdout, "out\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.exit_code, Some(3));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_timeout_kills_tree() {
+        // The background sleep holds stdout open after the shell exits, so
+        // the timeout is only met if the whole process group is killed.
+        let args = ["-c".to_string(), "sleep 30 & sleep 30".to_string()];
+        let started = Instant::now();
+        let err = run(Path::new("/bin/sh"), &args, Duration::from_millis(200)).unwrap_err();
+        assert!(err.is_timeout());
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let args = [
+            "-c".to_string(),
+            "sleep 1 >/dev/null 2>&1 & echo started".to_string(),
+        ];
+        let output = run(Path::new("/bin/sh"), &args, Duration::from_secs(10)).unwrap();
+        assert_eq!(output.stdout, "started\n");
+    }
+
+    #[test]
+    fn test_quote_windows_arg() {
+        assert_eq!(quote_windows_arg("plain"), "plain");
+        assert_eq!(quote_windows_arg(r"C:\dir\file"), r"C:\dir\file");
+        assert_eq!(quote_windows_arg(""), r#""""#);
+        assert_eq!(quote_windows_arg("two words"), r#""two words""#);
+        assert_eq!(quote_windows_arg(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote_windows_arg(r"trailing dir\"), r#""trailing dir\\""#);
+        assert_eq!(quote_windows_arg(r#"a\"b"#), r#""a\\\"b""#);
+    }
+
+    #[test]
+    fn test_batch_command_line() {
+        let args = vec![
+            "plain".to_string(),
+            "a & b".to_string(),
+            r#"say "hi""#.to_string(),
+        ];
+        assert_eq!(
+            batch_command_line(Path::new(r"C:\tools\run.bat"), &args).unwrap(),
+            r#""C:\tools\run.bat" plain "a & b" "say ""hi""""#
+        );
+        assert!(batch_command_line(Path::new("run.cmd"), &["%PATH%".to_string()]).is_err());
+        assert!(batch_command_line(Path::new("run.cmd"), &["a\nb".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_normalize_newlines() {
+        assert_eq!(normalize_newlines("a\r\nb\r\n".to_string()), "a\nb\n");
+        assert_eq!(normalize_newlines("a\rb\n".to_string()), "a\rb\n");
+    }
+
+    #[test]
+    fn test_windows_candidates() {
+        let extensions = windows_extensions(Some(OsStr::new(".EXE;.Cmd;bad")));
+        assert_eq!(extensions, vec![".exe", ".cmd"]);
+        assert_eq!(windows_extensions(None).len(), 4);
+
+        let found: Vec<PathBuf> = candidates(Path::new("bin/tool"), &extensions).collect();
+        assert_eq!(
+            found,
+            vec![
+                PathBuf::from("bin/tool"),
+                PathBuf::from("bin/tool.exe"),
+                PathBuf::from("bin/tool.cmd")
+            ]
+        );
+        assert_eq!(candidates(Path::new("tool.exe"), &extensions).count(), 1);
+    }
 }
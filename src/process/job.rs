@@ -0,0 +1,137 @@
+//! Windows Job Objects for process trees.
+//!
+//! A child is started suspended, assigned to a job created with
+//! `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE` and only then resumed, so every
+//! process it starts belongs to the same job. Terminating the job (or
+//! dropping it) ends the whole tree, where `Child::kill` would leave
+//! grandchildren running.
+
+use std::io;
+use std::os::windows::io::AsRawHandle;
+use std::process::Child;
+
+use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+    CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+};
+use windows_sys::Win32::System::JobObjects::{
+    AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+    SetInformationJobObject, TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+    JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+};
+use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, THREAD_SUSPEND_RESUME};
+
+/// Creation flag for children that are resumed by [`JobObject::adopt`].
+pub(super) use windows_sys::Win32::System::Threading::CREATE_SUSPENDED;
+
+/// An anonymous job that kills its processes when closed.
+pub(super) struct JobObject {
+    handle: HANDLE,
+}
+
+// SAFETY: a job handle may be used and closed from any thread.
+unsafe impl Send for JobObject {}
+unsafe impl Sync for JobObject {}
+
+impl JobObject {
+    /// Create a job that kills its processes when its handle is closed.
+    pub(super) fn new() -> io::Result<Self> {
+        // SAFETY: null attributes and name create an anonymous job.
+        let handle = unsafe { CreateJobObjectW(std::ptr::null(), std::ptr::null()) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let job = Self { handle };
+
+        // SAFETY: the structure is plain data, valid when zeroed.
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = unsafe { std::mem::zeroed() };
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        // SAFETY: `info` outlives the call and its size is passed.
+        let ok = unsafe {
+            SetInformationJobObject(
+                job.handle,
+                JobObjectExtendedLimitInformation,
+                &info as *const _ as *const _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(job)
+    }
+
+    /// Assign a child started with [`CREATE_SUSPENDED`] to the job and
+    /// resume it.
+    pub(super) fn adopt(&self, child: &Child) -> io::Result<()> {
+        // SAFETY: both handles are open for the duration of the call.
+        let ok = unsafe { AssignProcessToJobObject(self.handle, child.as_raw_handle() as HANDLE) };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        resume_threads(child.id())
+    }
+
+    /// Terminate every process in the job.
+    pub(super) fn terminate(&self) -> io::Result<()> {
+        // SAFETY: the handle is open until drop.
+        if unsafe { TerminateJobObject(self.handle, 1) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for JobObject {
+    fn drop(&mut self) {
+        // SAFETY: the handle was returned by `CreateJobObjectW` and is
+        // closed once.
+        unsafe {
+            CloseHandle(self.handle);
+        }
+    }
+}
+
+/// Resume the threads of a suspended process.
+///
+/// `std` does not expose the primary thread handle of a child, so the
+/// process's threads are found through a ToolHelp snapshot.
+fn resume_threads(pid: u32) -> io::Result<()> {
+    // SAFETY: the snapshot handle is checked and closed below.
+    let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0) };
+    if snapshot == INVALID_HANDLE_VALUE {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut entry = THREADENTRY32 {
+        dwSize: std::mem::size_of::<THREADENTRY32>() as u32,
+        ..Default::default()
+    };
+    let mut result = Ok(());
+    // SAFETY: `entry` is initialized with its size as the API requires.
+    let mut more = unsafe { Thread32First(snapshot, &mut entry) } != 0;
+    while more {
+        if entry.th32OwnerProcessID == pid {
+            // SAFETY: the thread handle is checked and closed after use.
+            unsafe {
+                let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                if thread.is_null() {
+                    result = Err(io::Error::last_os_error());
+                } else {
+                    if ResumeThread(thread) == u32::MAX {
+                        result = Err(io::Error::last_os_error());
+                    }
+                    CloseHandle(thread);
+                }
+            }
+        }
+        // SAFETY: as above.
+        more = unsafe { Thread32Next(snapshot, &mut entry) } != 0;
+    }
+
+    // SAFETY: the snapshot handle is valid and closed once.
+    unsafe {
+        CloseHandle(snapshot);
+    }
+    result
+}
@@ -1,8 +1,10 @@
 //! Stdlib module registry for registering modules with engines.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use fusabi_host::HostRegistry;
+use fusabi_host::{ExecutionContext, HostRegistry, Value};
+use parking_lot::Mutex;
 
 use crate::config::StdlibConfig;
 use crate::error::Result;
@@ -12,14 +14,238 @@ use crate::safety::SafetyConfig;
 pub struct StdlibRegistry {
     config: StdlibConfig,
     safety: Arc<SafetyConfig>,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<crate::metrics::MetricsRegistry>,
+    shutdown_hooks: Mutex<Vec<ShutdownHook>>,
+    mode: ExecutionMode,
+    plan: Arc<Mutex<Vec<PlannedOperation>>>,
+    invoker: Option<Arc<dyn ScriptInvoker>>,
+    call_timing: bool,
+    #[cfg(feature = "observability")]
+    slo: Arc<crate::observability::SloTracker>,
+    #[cfg(feature = "observability")]
+    logger: Arc<crate::observability::ScriptLogger>,
+    #[cfg(feature = "k8s")]
+    k8s: Option<crate::k8s::K8sHost>,
+}
+
+type ShutdownHook = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Calls script functions on behalf of host functions.
+///
+/// Host functions only receive opaque function values, so those that take
+/// callbacks (such as `terminal_ui.run`) need the embedding engine to call
+/// them. Install one with [`StdlibRegistry::with_script_invoker`].
+pub trait ScriptInvoker: Send + Sync {
+    /// Call a script function value with arguments.
+    fn call(
+        &self,
+        function: &Value,
+        args: &[Value],
+        ctx: &ExecutionContext,
+    ) -> fusabi_host::Result<Value>;
+}
+
+/// How side-effecting host functions are handled.
+///
+/// Side-effecting functions are those that change state outside the
+/// script: filesystem writes and removals, process execution, non-GET
+/// network requests, and environment changes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionMode {
+    /// Execute every function.
+    #[default]
+    Normal,
+    /// Record side-effecting calls in the plan without executing them.
+    /// They return null to the script.
+    DryRun,
+    /// Execute every function and record side-effecting calls in the plan.
+    Trace,
+}
+
+/// A side-effecting host function call recorded in the plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedOperation {
+    /// Module name, such as `fs`.
+    pub module: String,
+    /// Function name, such as `write`.
+    pub function: String,
+    /// Arguments the script passed.
+    pub args: Vec<Value>,
+}
+
+impl PlannedOperation {
+    /// Convert to a Value map with `module`, `function`, and `args`.
+    pub fn to_value(&self) -> Value {
+        let mut m = HashMap::new();
+        m.insert("module".into(), Value::String(self.module.clone()));
+        m.insert("function".into(), Value::String(self.function.clone()));
+        m.insert("args".into(), Value::List(self.args.clone()));
+        Value::Map(m)
+    }
 }
 
 impl StdlibRegistry {
     /// Create a new stdlib registry.
+    ///
+    /// With the `metrics` feature, the registry gets its own metrics
+    /// registry, with exporters and flush interval taken from the `metrics`
+    /// module options (`exporters`, `flush_interval_ms`, and the exporter
+    /// settings described in [`crate::metrics::exporters::from_options`]).
+    /// Per-script namespacing is read from the `namespace`,
+    /// `namespace_label` and `namespace_key` options (see
+    /// [`crate::metrics::MetricNamespace`]), and summary quantiles and
+    /// windows from the options read by
+    /// [`crate::metrics::SummaryConfig::from_options`].
     pub fn new(config: StdlibConfig) -> Result<Self> {
         let safety = Arc::new(config.safety.clone());
 
-        Ok(Self { config, safety })
+        #[cfg(feature = "metrics")]
+        let metrics = {
+            let options = &config.metrics.options;
+            let mut metrics = crate::metrics::MetricsRegistry::new()
+                .with_namespace(crate::metrics::MetricNamespace::from_options(options)?)
+                .with_summary_config(crate::metrics::SummaryConfig::from_options(options)?);
+            if let Some(key) = options.get("namespace_key") {
+                metrics = metrics.with_identity_key(key.clone());
+            }
+            let metrics = Arc::new(metrics);
+            if config.metrics.enabled {
+                for exporter in crate::metrics::exporters::from_options(options)? {
+                    metrics.add_exporter(exporter)?;
+                }
+                if let Some(ms) = options.get("flush_interval_ms") {
+                    let ms: u64 = ms.parse().map_err(|_| {
+                        crate::Error::invalid_argument(format!("invalid flush_interval_ms: {}", ms))
+                    })?;
+                    metrics.start_flush_interval(std::time::Duration::from_millis(ms))?;
+                }
+            }
+            metrics
+        };
+
+        Ok(Self {
+            config,
+            safety,
+            #[cfg(feature = "metrics")]
+            metrics,
+            shutdown_hooks: Mutex::new(Vec::new()),
+            mode: ExecutionMode::Normal,
+            plan: Arc::new(Mutex::new(Vec::new())),
+            invoker: None,
+            call_timing: false,
+            #[cfg(feature = "observability")]
+            slo: Arc::new(crate::observability::SloTracker::default()),
+            #[cfg(feature = "observability")]
+            logger: Arc::new(crate::observability::ScriptLogger::new()),
+            #[cfg(feature = "k8s")]
+            k8s: None,
+        })
+    }
+
+    /// Set how side-effecting host functions are handled.
+    ///
+    /// Applies to functions registered after the call.
+    pub fn with_mode(mut self, mode: ExecutionMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Install the invoker host functions use to call script callbacks.
+    ///
+    /// Applies to functions registered after the call.
+    pub fn with_script_invoker(mut self, invoker: Arc<dyn ScriptInvoker>) -> Self {
+        self.invoker = Some(invoker);
+        self
+    }
+
+    /// Time every host function call.
+    ///
+    /// Each call runs in a `stdlib_call` span (target `fusabi::stdlib`)
+    /// with `module` and `function` fields, plus an `error` field when it
+    /// fails. With the `metrics` feature, its duration in seconds is
+    /// observed in the `stdlib_call_duration{module,function}` summary,
+    /// which keeps a bounded window of samples, and failures are counted in `stdlib_call_errors_total{module,function}`.
+    /// Off by default.
+    ///
+    /// Applies to functions registered after the call.
+    pub fn with_call_timing(mut self, enabled: bool) -> Self {
+        self.call_timing = enabled;
+        self
+    }
+
+    /// Get the execution mode.
+    pub fn mode(&self) -> ExecutionMode {
+        self.mode
+    }
+
+    /// Take the side-effecting operations recorded in dry-run or trace
+    /// mode, in call order, leaving the plan empty.
+    pub fn take_plan(&self) -> Vec<PlannedOperation> {
+        std::mem::take(&mut *self.plan.lock())
+    }
+
+    /// Record metrics into `metrics` instead of this registry's own.
+    ///
+    /// Use [`crate::metrics::global`] to share one registry between
+    /// engines. Exporters configured in the `metrics` module options are
+    /// not moved to the new registry.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<crate::metrics::MetricsRegistry>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Get the metrics registry that host functions record into.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &Arc<crate::metrics::MetricsRegistry> {
+        &self.metrics
+    }
+
+    /// Track per-module error rates in `tracker` instead of this
+    /// registry's own, which uses the default [`SloConfig`] and alerts
+    /// through `tracing`.
+    ///
+    /// Applies to functions registered after the call.
+    ///
+    /// [`SloConfig`]: crate::observability::SloConfig
+    #[cfg(feature = "observability")]
+    pub fn with_slo_tracker(mut self, tracker: Arc<crate::observability::SloTracker>) -> Self {
+        self.slo = tracker;
+        self
+    }
+
+    /// Get the tracker that host function outcomes are recorded in.
+    #[cfg(feature = "observability")]
+    pub fn slo_tracker(&self) -> &Arc<crate::observability::SloTracker> {
+        &self.slo
+    }
+
+    /// Send script log entries to `logger` instead of this registry's own,
+    /// which logs at `info` level to `tracing` only.
+    ///
+    /// Applies to functions registered after the call.
+    #[cfg(feature = "observability")]
+    pub fn with_logger(mut self, logger: Arc<crate::observability::ScriptLogger>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Get the logger that the `log` functions record into.
+    #[cfg(feature = "observability")]
+    pub fn logger(&self) -> &Arc<crate::observability::ScriptLogger> {
+        &self.logger
+    }
+
+    /// Make the `k8s` host functions call the Kubernetes API through
+    /// `host`, as allowed by [`SafetyConfig::check_k8s`].
+    ///
+    /// Without a client, [`register_all`](Self::register_all) skips the
+    /// `k8s` module.
+    #[cfg(feature = "k8s")]
+    pub fn with_k8s(mut self, host: crate::k8s::K8sHost) -> Self {
+        self.k8s = Some(host);
+        self
     }
 
     /// Create with default configuration.
@@ -37,6 +263,120 @@ impl StdlibRegistry {
         &self.safety
     }
 
+    /// Get the audit log that host function calls are recorded in, set
+    /// with [`SafetyConfig::with_audit_log`].
+    pub fn audit_log(&self) -> Option<&Arc<crate::safety::AuditLog>> {
+        self.safety.audit_log.as_ref()
+    }
+
+    /// Add a hook to run when the registry is shut down.
+    ///
+    /// Hooks run in reverse order of registration.
+    pub fn on_shutdown(&self, hook: impl FnOnce() -> Result<()> + Send + 'static) {
+        self.shutdown_hooks.lock().push(Box::new(hook));
+    }
+
+    /// Run shutdown hooks and flush metrics exporters.
+    ///
+    /// Call this before the host process exits so exporters deliver their
+    /// final values. Every hook runs even if an earlier one fails; the first
+    /// error is returned.
+    pub fn shutdown(&self) -> Result<()> {
+        let hooks = std::mem::take(&mut *self.shutdown_hooks.lock());
+        let mut first_error = None;
+        for hook in hooks.into_iter().rev() {
+            if let Err(e) = hook() {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Err(e) = self.metrics.shutdown() {
+            first_error.get_or_insert(e);
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Register a host function.
+    ///
+    /// A panic in the function is caught and returned to the script as an
+    /// internal error naming the function, instead of unwinding into the
+    /// engine (unless the host is built with `panic = "abort"`). With the
+    /// `observability` feature, each call's outcome is recorded against
+    /// `module` in the SLO tracker. Calls are timed when
+    /// [`with_call_timing`](Self::with_call_timing) is enabled.
+    pub fn register_fn<F>(
+        &self,
+        registry: &mut HostRegistry,
+        module: &'static str,
+        function: &'static str,
+        f: F,
+    ) where
+        F: Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static,
+    {
+        let f = isolate_panics(module, function, f);
+        let f = audit_calls(
+            self.safety.audit_log.is_some().then(|| self.safety.clone()),
+            module,
+            function,
+            f,
+        );
+        let f = time_calls(
+            self.call_timing.then_some(CallTimer {
+                module,
+                function,
+                #[cfg(feature = "metrics")]
+                metrics: self.metrics.clone(),
+            }),
+            f,
+        );
+
+        #[cfg(feature = "observability")]
+        {
+            let slo = self.slo.clone();
+            registry.register_module(module, function, move |args, ctx| {
+                let result = f(args, ctx);
+                slo.record(module, result.is_ok());
+                result
+            });
+        }
+
+        #[cfg(not(feature = "observability"))]
+        registry.register_module(module, function, f);
+    }
+
+    /// Register a side-effecting host function, honoring the execution
+    /// mode.
+    pub fn register_effect<F>(
+        &self,
+        registry: &mut HostRegistry,
+        module: &'static str,
+        function: &'static str,
+        f: F,
+    ) where
+        F: Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static,
+    {
+        if self.mode == ExecutionMode::Normal {
+            self.register_fn(registry, module, function, f);
+            return;
+        }
+
+        let mode = self.mode;
+        let plan = self.plan.clone();
+        self.register_fn(registry, module, function, move |args, ctx| {
+            plan.lock().push(PlannedOperation {
+                module: module.to_string(),
+                function: function.to_string(),
+                args: args.to_vec(),
+            });
+            match mode {
+                ExecutionMode::DryRun => Ok(Value::Null),
+                _ => f(args, ctx),
+            }
+        });
+    }
+
     /// Register all enabled modules with a host registry.
     pub fn register_all(&self, registry: &mut HostRegistry) -> Result<()> {
         #[cfg(feature = "process")]
@@ -79,6 +419,47 @@ impl StdlibRegistry {
             self.register_metrics(registry)?;
         }
 
+        #[cfg(feature = "terminal")]
+        self.register_terminal(registry)?;
+
+        #[cfg(feature = "terminal-ui")]
+        self.register_terminal_ui(registry)?;
+
+        #[cfg(feature = "gpu")]
+        self.register_gpu(registry)?;
+
+        #[cfg(feature = "observability")]
+        self.register_health(registry)?;
+
+        #[cfg(feature = "observability")]
+        self.register_log(registry)?;
+
+        #[cfg(feature = "observability")]
+        self.register_trace(registry)?;
+
+        #[cfg(feature = "patch")]
+        self.register_patch(registry)?;
+
+        #[cfg(feature = "calendar")]
+        self.register_calendar(registry)?;
+
+        #[cfg(feature = "units")]
+        self.register_units(registry)?;
+
+        #[cfg(feature = "locale")]
+        self.register_locale(registry)?;
+
+        #[cfg(feature = "system")]
+        self.register_system(registry)?;
+
+        #[cfg(feature = "stats")]
+        self.register_stats(registry)?;
+
+        #[cfg(feature = "k8s")]
+        if self.k8s.is_some() {
+            self.register_k8s(registry)?;
+        }
+
         Ok(())
     }
 
@@ -90,14 +471,19 @@ impl StdlibRegistry {
         let safety = self.safety.clone();
         let timeout = self.config.process.timeout;
 
-        registry.register_module("process", "exec", move |args, ctx| {
+        self.register_effect(registry, "process", "exec", move |args, ctx| {
             process::exec(&safety, timeout, args, ctx)
         });
 
-        registry.register_module("process", "spawn", move |args, ctx| {
+        self.register_effect(registry, "process", "spawn", move |args, ctx| {
             process::spawn(args, ctx)
         });
 
+        let s = self.safety.clone();
+        self.register_fn(registry, "process", "which", move |args, ctx| {
+            process::which(&s, args, ctx)
+        });
+
         Ok(())
     }
 
@@ -109,24 +495,68 @@ impl StdlibRegistry {
         let safety = self.safety.clone();
 
         let s = safety.clone();
-        registry.register_module("fs", "read", move |args, ctx| fs::read_file(&s, args, ctx));
+        self.register_fn(registry, "fs", "read", move |args, ctx| {
+            fs::read_file(&s, args, ctx)
+        });
 
         let s = safety.clone();
-        registry.register_module("fs", "write", move |args, ctx| {
+        self.register_fn(registry, "fs", "read_range", move |args, ctx| {
+            fs::read_range(&s, args, ctx)
+        });
+
+        let s = safety.clone();
+        self.register_fn(registry, "fs", "mmap_read", move |args, ctx| {
+            fs::mmap_read(&s, args, ctx)
+        });
+
+        let s = safety.clone();
+        self.register_effect(registry, "fs", "write", move |args, ctx| {
             fs::write_file(&s, args, ctx)
         });
 
         let s = safety.clone();
-        registry.register_module("fs", "exists", move |args, ctx| fs::exists(&s, args, ctx));
+        self.register_fn(registry, "fs", "exists", move |args, ctx| {
+            fs::exists(&s, args, ctx)
+        });
 
         let s = safety.clone();
-        registry.register_module("fs", "list", move |args, ctx| fs::list_dir(&s, args, ctx));
+        self.register_fn(registry, "fs", "list", move |args, ctx| {
+            fs::list_dir(&s, args, ctx)
+        });
 
         let s = safety.clone();
-        registry.register_module("fs", "mkdir", move |args, ctx| fs::mkdir(&s, args, ctx));
+        self.register_effect(registry, "fs", "mkdir", move |args, ctx| {
+            fs::mkdir(&s, args, ctx)
+        });
 
         let s = safety.clone();
-        registry.register_module("fs", "remove", move |args, ctx| fs::remove(&s, args, ctx));
+        self.register_effect(registry, "fs", "remove", move |args, ctx| {
+            fs::remove(&s, args, ctx)
+        });
+
+        #[cfg(feature = "fs-trash")]
+        {
+            let s = safety.clone();
+            self.register_effect(registry, "fs", "trash", move |args, ctx| {
+                fs::trash::trash(&s, args, ctx)
+            });
+        }
+
+        #[cfg(feature = "fs-watch")]
+        {
+            let defaults = Arc::new(fs::watch::WatchConfig::from_options(
+                &self.config.fs.options,
+            )?);
+
+            let s = safety.clone();
+            self.register_fn(registry, "fs", "watch", move |args, ctx| {
+                fs::watch::watch(&s, &defaults, args, ctx)
+            });
+
+            self.register_fn(registry, "fs", "watch_events", fs::watch::watch_events);
+
+            self.register_fn(registry, "fs", "unwatch", fs::watch::unwatch);
+        }
 
         Ok(())
     }
@@ -136,17 +566,17 @@ impl StdlibRegistry {
     pub fn register_path(&self, registry: &mut HostRegistry) -> Result<()> {
         use crate::path;
 
-        registry.register_module("path", "join", path::join);
+        self.register_fn(registry, "path", "join", path::join);
 
-        registry.register_module("path", "dirname", path::dirname);
+        self.register_fn(registry, "path", "dirname", path::dirname);
 
-        registry.register_module("path", "basename", path::basename);
+        self.register_fn(registry, "path", "basename", path::basename);
 
-        registry.register_module("path", "extension", path::extension);
+        self.register_fn(registry, "path", "extension", path::extension);
 
-        registry.register_module("path", "normalize", path::normalize);
+        self.register_fn(registry, "path", "normalize", path::normalize);
 
-        registry.register_module("path", "is_absolute", path::is_absolute);
+        self.register_fn(registry, "path", "is_absolute", path::is_absolute);
 
         Ok(())
     }
@@ -159,12 +589,16 @@ impl StdlibRegistry {
         let safety = self.safety.clone();
 
         let s = safety.clone();
-        registry.register_module("env", "get", move |args, ctx| env::get(&s, args, ctx));
+        self.register_fn(registry, "env", "get", move |args, ctx| {
+            env::get(&s, args, ctx)
+        });
 
         let s = safety.clone();
-        registry.register_module("env", "set", move |args, ctx| env::set(&s, args, ctx));
+        self.register_effect(registry, "env", "set", move |args, ctx| {
+            env::set(&s, args, ctx)
+        });
 
-        registry.register_module("env", "cwd", env::cwd);
+        self.register_fn(registry, "env", "cwd", env::cwd);
 
         Ok(())
     }
@@ -174,13 +608,111 @@ impl StdlibRegistry {
     pub fn register_format(&self, registry: &mut HostRegistry) -> Result<()> {
         use crate::format;
 
-        registry.register_module("format", "sprintf", format::sprintf);
+        self.register_fn(registry, "format", "sprintf", format::sprintf);
+
+        self.register_fn(registry, "format", "template", format::template);
+
+        self.register_fn(registry, "format", "table", format::table);
+
+        self.register_fn(registry, "format", "wrap", format::wrap);
+
+        self.register_fn(registry, "format", "truncate", format::truncate);
+
+        self.register_fn(
+            registry,
+            "format",
+            "display_width",
+            format::display_width_fn,
+        );
+
+        self.register_fn(registry, "format", "strip_ansi", format::strip_ansi_fn);
+
+        self.register_fn(registry, "format", "csv_decode", format::csv_decode);
+
+        self.register_fn(registry, "format", "csv_encode", format::csv_encode);
+
+        self.register_fn(registry, "format", "base64_encode", format::base64_encode);
+
+        self.register_fn(registry, "format", "base64_decode", format::base64_decode);
+
+        self.register_fn(registry, "format", "hex_encode", format::hex_encode);
+
+        self.register_fn(registry, "format", "hex_decode", format::hex_decode);
+
+        self.register_fn(registry, "format", "url_encode", format::url_encode);
+
+        self.register_fn(registry, "format", "url_decode", format::url_decode);
+
+        self.register_fn(registry, "format", "html_escape", format::html_escape);
+
+        #[cfg(feature = "qr")]
+        self.register_fn(registry, "format", "qr", format::qr);
+
+        self.register_fn(registry, "format", "json_pointer", format::json_pointer);
+
+        self.register_fn(registry, "format", "json_query", format::json_query);
+
+        let s = self.safety.clone();
+        self.register_effect(registry, "format", "jsonl_append", move |args, ctx| {
+            format::jsonl_append(&s, args, ctx)
+        });
+
+        #[cfg(feature = "fs_stream")]
+        {
+            let s = self.safety.clone();
+            self.register_fn(registry, "format", "jsonl_open", move |args, ctx| {
+                format::jsonl_open(&s, args, ctx)
+            });
+
+            self.register_fn(registry, "format", "next_record", format::next_record);
+
+            self.register_fn(registry, "format", "jsonl_close", format::jsonl_close);
+
+            let s = self.safety.clone();
+            self.register_fn(registry, "format", "json_stream_open", move |args, ctx| {
+                format::json_stream_open(&s, args, ctx)
+            });
+
+            self.register_fn(
+                registry,
+                "format",
+                "json_stream_decode",
+                format::json_stream_decode,
+            );
+
+            self.register_fn(
+                registry,
+                "format",
+                "json_stream_close",
+                format::json_stream_close,
+            );
+        }
+
+        let s = self.safety.clone();
+        self.register_effect(
+            registry,
+            "format",
+            "json_stream_writer",
+            move |args, ctx| format::json_stream_writer(&s, args, ctx),
+        );
+
+        self.register_fn(
+            registry,
+            "format",
+            "json_stream_write",
+            format::json_stream_write,
+        );
 
-        registry.register_module("format", "template", format::template);
+        self.register_fn(
+            registry,
+            "format",
+            "json_stream_finish",
+            format::json_stream_finish,
+        );
 
-        registry.register_module("format", "json_encode", format::json_encode);
+        self.register_fn(registry, "format", "json_encode", format::json_encode);
 
-        registry.register_module("format", "json_decode", format::json_decode);
+        self.register_fn(registry, "format", "json_decode", format::json_decode);
 
         Ok(())
     }
@@ -194,15 +726,47 @@ impl StdlibRegistry {
         let timeout = self.config.net.timeout;
 
         let s = safety.clone();
-        registry.register_module("net", "get", move |args, ctx| {
+        self.register_fn(registry, "net", "get", move |args, ctx| {
             net::http_get(&s, timeout, args, ctx)
         });
 
         let s = safety.clone();
-        registry.register_module("net", "post", move |args, ctx| {
+        self.register_effect(registry, "net", "post", move |args, ctx| {
             net::http_post(&s, timeout, args, ctx)
         });
 
+        self.register_fn(registry, "net", "decode_body", net::decode_body);
+
+        #[cfg(feature = "geoip")]
+        {
+            let s = safety.clone();
+            let databases: Vec<std::path::PathBuf> = self
+                .config
+                .net
+                .options
+                .get("geoip_database")
+                .map(|paths| paths.split(',').map(|p| p.trim().into()).collect())
+                .unwrap_or_default();
+            self.register_fn(registry, "net", "geoip", move |args, ctx| {
+                net::geoip(&s, &databases, args, ctx)
+            });
+        }
+
+        #[cfg(feature = "doh")]
+        {
+            // Leave any resolver installed by the embedder in place.
+            if let Some(resolver) =
+                net::doh::DohResolver::from_options(&self.config.net.options, &safety)?
+            {
+                net::doh::set_resolver(Some(Arc::new(resolver)));
+            }
+
+            let s = safety.clone();
+            self.register_fn(registry, "net", "resolve", move |args, ctx| {
+                net::doh::resolve(&s, args, ctx)
+            });
+        }
+
         Ok(())
     }
 
@@ -211,15 +775,96 @@ impl StdlibRegistry {
     pub fn register_time(&self, registry: &mut HostRegistry) -> Result<()> {
         use crate::time;
 
-        registry.register_module("time", "now", time::now);
+        self.register_fn(registry, "time", "now", time::now);
+
+        self.register_fn(registry, "time", "now_millis", time::now_millis);
+
+        let budget = self.config.time.timeout;
+        self.register_fn(registry, "time", "sleep", move |args, ctx| {
+            time::sleep_budgeted(budget, args, ctx)
+        });
+
+        self.register_fn(registry, "time", "format", time::format_time);
+
+        self.register_fn(registry, "time", "parse", time::parse_time);
+
+        self.register_fn(registry, "time", "add", time::add);
+
+        self.register_fn(registry, "time", "diff", time::diff);
+
+        self.register_fn(registry, "time", "truncate", time::truncate);
+
+        self.register_fn(registry, "time", "weekday", time::weekday);
+
+        self.register_fn(registry, "time", "days_in_month", time::days_in_month);
+
+        self.register_fn(registry, "time", "rate_limiter", time::rate_limiter);
+
+        self.register_fn(registry, "time", "acquire", time::acquire);
+
+        self.register_fn(registry, "time", "try_acquire", time::try_acquire);
+
+        self.register_fn(registry, "time", "throttle", time::throttle);
+
+        self.register_fn(registry, "time", "throttle_ready", time::throttle_ready);
+
+        self.register_fn(registry, "time", "debounce", time::debounce);
+
+        self.register_fn(registry, "time", "debounce_touch", time::debounce_touch);
+
+        self.register_fn(registry, "time", "debounce_ready", time::debounce_ready);
+
+        self.register_fn(registry, "time", "limiter_close", time::limiter_close);
+
+        self.register_fn(registry, "time", "timer_start", time::timer_start);
+
+        self.register_fn(registry, "time", "timer_elapsed", time::timer_elapsed);
+
+        self.register_fn(registry, "time", "timer_lap", time::timer_lap);
+
+        #[cfg(feature = "metrics")]
+        {
+            let m = self.metrics.clone();
+            self.register_fn(registry, "time", "timer_stop", move |args, ctx| {
+                time::timer_stop_into(&m, args, ctx)
+            });
+        }
+        #[cfg(not(feature = "metrics"))]
+        self.register_fn(registry, "time", "timer_stop", time::timer_stop);
+
+        let s = self.safety.clone();
+        self.register_fn(registry, "time", "ntp_offset", move |args, ctx| {
+            time::ntp_offset(&s, args, ctx)
+        });
+
+        #[cfg(feature = "cron")]
+        {
+            self.register_fn(registry, "time", "cron_next", time::cron_next);
+
+            self.register_fn(registry, "time", "cron_matches", time::cron_matches);
+        }
+
+        #[cfg(feature = "tz")]
+        {
+            let local_zone = match self.config.time.options.get("local_zone") {
+                Some(name) => Some(time::parse_zone(name, "time")?),
+                None => None,
+            };
 
-        registry.register_module("time", "now_millis", time::now_millis);
+            self.register_fn(registry, "time", "format_tz", time::format_tz);
 
-        registry.register_module("time", "sleep", time::sleep);
+            self.register_fn(registry, "time", "to_zone", time::to_zone);
 
-        registry.register_module("time", "format", time::format_time);
+            self.register_fn(registry, "time", "offset", time::offset);
 
-        registry.register_module("time", "parse", time::parse_time);
+            self.register_fn(registry, "time", "tz_offset", time::tz_offset);
+
+            self.register_fn(registry, "time", "timezones", time::timezones);
+
+            self.register_fn(registry, "time", "now_local", move |args, ctx| {
+                time::now_local(local_zone.as_ref(), args, ctx)
+            });
+        }
 
         Ok(())
     }
@@ -229,14 +874,1067 @@ impl StdlibRegistry {
     pub fn register_metrics(&self, registry: &mut HostRegistry) -> Result<()> {
         use crate::metrics;
 
-        registry.register_module("metrics", "counter_inc", metrics::counter_inc);
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "counter_inc", move |args, ctx| {
+            metrics::counter_inc(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "gauge_set", move |args, ctx| {
+            metrics::gauge_set(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "gauge_add", move |args, ctx| {
+            metrics::gauge_add(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "gauge_sub", move |args, ctx| {
+            metrics::gauge_sub(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "counter_inc_at", move |args, ctx| {
+            metrics::counter_inc_at(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(
+            registry,
+            "metrics",
+            "histogram_observe",
+            move |args, ctx| metrics::histogram_observe(&m, args, ctx),
+        );
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "summary_observe", move |args, ctx| {
+            metrics::summary_observe(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "flush", move |args, ctx| {
+            metrics::flush(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "counter_get", move |args, ctx| {
+            metrics::counter_get(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "gauge_get", move |args, ctx| {
+            metrics::gauge_get(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "histogram_stats", move |args, ctx| {
+            metrics::histogram_stats(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "summary_stats", move |args, ctx| {
+            metrics::summary_stats(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "list", move |args, ctx| {
+            metrics::list(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "snapshot", move |args, ctx| {
+            metrics::snapshot(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "diff", move |args, ctx| {
+            metrics::diff(&m, args, ctx)
+        });
+
+        let m = self.metrics.clone();
+        self.register_fn(registry, "metrics", "define_derived", move |args, ctx| {
+            metrics::define_derived(&m, args, ctx)
+        });
+
+        Ok(())
+    }
+
+    /// Register the terminal module.
+    #[cfg(feature = "terminal")]
+    pub fn register_terminal(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::terminal;
+
+        self.register_fn(registry, "terminal", "read_key", terminal::read_key);
+
+        self.register_fn(
+            registry,
+            "terminal",
+            "read_key_timeout",
+            terminal::read_key_timeout,
+        );
+
+        self.register_fn(registry, "terminal", "next_event", terminal::next_event);
+
+        self.register_fn(registry, "terminal", "events_open", terminal::events_open);
+        self.register_fn(registry, "terminal", "events_poll", terminal::events_poll);
+        self.register_fn(registry, "terminal", "events_next", terminal::events_next);
+        self.register_fn(registry, "terminal", "events_drain", terminal::events_drain);
+        self.register_fn(registry, "terminal", "events_close", terminal::events_close);
+
+        // Dropping the host registry releases the last handle, which
+        // restores the terminal if a script left raw mode, a hidden cursor,
+        // or the alternate screen on.
+        let guard = Arc::new(terminal::TerminalGuard::new());
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "enable_raw_mode", move |args, ctx| {
+            terminal::enable_raw_mode(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(
+            registry,
+            "terminal",
+            "disable_raw_mode",
+            move |args, ctx| terminal::disable_raw_mode(&g, args, ctx),
+        );
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "hide_cursor", move |args, ctx| {
+            terminal::hide_cursor(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "show_cursor", move |args, ctx| {
+            terminal::show_cursor(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(
+            registry,
+            "terminal",
+            "alternate_screen_enter",
+            move |args, ctx| terminal::alternate_screen_enter(&g, args, ctx),
+        );
+
+        let g = guard.clone();
+        self.register_fn(
+            registry,
+            "terminal",
+            "alternate_screen_leave",
+            move |args, ctx| terminal::alternate_screen_leave(&g, args, ctx),
+        );
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "enable_mouse", move |args, ctx| {
+            terminal::enable_mouse(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "disable_mouse", move |args, ctx| {
+            terminal::disable_mouse(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "enable_paste", move |args, ctx| {
+            terminal::enable_paste(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "disable_paste", move |args, ctx| {
+            terminal::disable_paste(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "prompt", move |args, ctx| {
+            terminal::prompt(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "confirm", move |args, ctx| {
+            terminal::confirm(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "select", move |args, ctx| {
+            terminal::select(&g, args, ctx)
+        });
+
+        let g = guard.clone();
+        self.register_fn(registry, "terminal", "password", move |args, ctx| {
+            terminal::password(&g, args, ctx)
+        });
+
+        let s = self.safety.clone();
+        self.register_fn(registry, "terminal", "clipboard_read", move |args, ctx| {
+            terminal::clipboard_read(&s, args, ctx)
+        });
+
+        let s = self.safety.clone();
+        self.register_effect(registry, "terminal", "clipboard_write", move |args, ctx| {
+            terminal::clipboard_write(&s, args, ctx)
+        });
+
+        self.register_fn(registry, "terminal", "size", terminal::size);
+
+        self.register_fn(registry, "terminal", "colorize", terminal::colorize);
+
+        self.register_fn(registry, "terminal", "strip_ansi", terminal::strip_ansi);
+
+        self.register_fn(registry, "terminal", "progress_new", terminal::progress_new);
+
+        self.register_fn(registry, "terminal", "spinner_new", terminal::spinner_new);
+
+        self.register_fn(registry, "terminal", "progress_set", terminal::progress_set);
+
+        self.register_fn(registry, "terminal", "progress_inc", terminal::progress_inc);
+
+        self.register_fn(
+            registry,
+            "terminal",
+            "progress_tick",
+            terminal::progress_tick,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal",
+            "progress_message",
+            terminal::progress_message,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal",
+            "progress_finish",
+            terminal::progress_finish,
+        );
+
+        self.register_fn(registry, "terminal", "clear", terminal::clear);
+
+        self.register_fn(registry, "terminal", "set_cursor", terminal::set_cursor);
+
+        #[cfg(feature = "terminal-images")]
+        {
+            let s = self.safety.clone();
+            self.register_fn(registry, "terminal", "show_image", move |args, ctx| {
+                terminal::graphics::show_image(&s, args, ctx)
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Register the terminal_ui module.
+    ///
+    /// `terminal_ui.run` calls script callbacks through the invoker
+    /// installed with [`Self::with_script_invoker`] and fails without one.
+    #[cfg(feature = "terminal-ui")]
+    pub fn register_terminal_ui(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::terminal::TerminalGuard;
+        use crate::terminal_ui::{app, log_pane, palette, table};
+
+        let guard = Arc::new(TerminalGuard::new());
+
+        let invoker = self.invoker.clone();
+        let g = guard.clone();
+        self.register_fn(registry, "terminal_ui", "run", move |args, ctx| {
+            app::run(invoker.as_ref(), &g, args, ctx)
+        });
+
+        self.register_fn(registry, "terminal_ui", "palette_pick", move |args, ctx| {
+            palette::palette_pick(&guard, args, ctx)
+        });
+
+        self.register_fn(registry, "terminal_ui", "table_state", table::table_state);
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "table_set_rows",
+            table::table_set_rows,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "table_select_next",
+            table::table_select_next,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "table_select_prev",
+            table::table_select_prev,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "table_selected_row",
+            table::table_selected_row,
+        );
+
+        self.register_fn(registry, "terminal_ui", "table_sort", table::table_sort);
+
+        self.register_fn(registry, "terminal_ui", "table_view", table::table_view);
+
+        self.register_fn(registry, "terminal_ui", "table_close", table::table_close);
+
+        self.register_fn(registry, "terminal_ui", "log_pane", log_pane::log_pane);
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "log_pane_push",
+            log_pane::log_pane_push,
+        );
+
+        #[cfg(feature = "fs_stream")]
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "log_pane_pull",
+            log_pane::log_pane_pull,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "log_pane_scroll",
+            log_pane::log_pane_scroll,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "log_pane_event",
+            log_pane::log_pane_event,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "log_pane_view",
+            log_pane::log_pane_view,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "log_pane_close",
+            log_pane::log_pane_close,
+        );
+
+        self.register_fn(registry, "terminal_ui", "palette", palette::palette);
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "palette_set_query",
+            palette::palette_set_query,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "palette_event",
+            palette::palette_event,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "palette_selected",
+            palette::palette_selected,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "palette_view",
+            palette::palette_view,
+        );
+
+        self.register_fn(
+            registry,
+            "terminal_ui",
+            "palette_close",
+            palette::palette_close,
+        );
+
+        #[cfg(feature = "metrics")]
+        {
+            use crate::terminal_ui::charts;
+
+            let m = self.metrics.clone();
+            self.register_fn(
+                registry,
+                "terminal_ui",
+                "metric_sparkline",
+                move |args, ctx| charts::metric_sparkline_tree(&m, args, ctx),
+            );
+
+            let m = self.metrics.clone();
+            self.register_fn(registry, "terminal_ui", "metric_chart", move |args, ctx| {
+                charts::metric_chart_tree(&m, args, ctx)
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Register the health module.
+    #[cfg(feature = "observability")]
+    pub fn register_health(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::observability::{health, slo};
+
+        self.register_fn(registry, "health", "set", health::set);
+
+        self.register_fn(registry, "health", "report", health::report);
+
+        let tracker = self.slo.clone();
+        self.register_fn(registry, "health", "slo", move |args, ctx| {
+            slo::status(&tracker, args, ctx)
+        });
+
+        Ok(())
+    }
+
+    /// Register the log module.
+    ///
+    /// Entries go to this registry's [`logger`](Self::logger), whose file
+    /// sink is flushed on [`shutdown`](Self::shutdown).
+    #[cfg(feature = "observability")]
+    pub fn register_log(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::observability::{log_level, logger, LogLevel};
+
+        for (function, level) in [
+            ("trace", LogLevel::Trace),
+            ("debug", LogLevel::Debug),
+            ("info", LogLevel::Info),
+            ("warn", LogLevel::Warn),
+            ("error", LogLevel::Error),
+        ] {
+            let l = self.logger.clone();
+            self.register_fn(registry, "log", function, move |args, ctx| {
+                logger::log(&l, level, args, ctx)
+            });
+        }
+
+        let l = self.logger.clone();
+        let s = self.safety.clone();
+        self.register_effect(registry, "log", "set_file", move |args, ctx| {
+            logger::set_file(&l, &s, args, ctx)
+        });
+
+        let l = self.logger.clone();
+        self.register_fn(registry, "log", "recent", move |args, ctx| {
+            logger::recent(&l, args, ctx)
+        });
+
+        let s = self.safety.clone();
+        self.register_fn(registry, "log", "set_level", move |args, ctx| {
+            log_level::set(&s, args, ctx)
+        });
 
-        registry.register_module("metrics", "gauge_set", metrics::gauge_set);
+        self.register_fn(registry, "log", "get_level", log_level::get);
 
-        registry.register_module("metrics", "histogram_observe", metrics::histogram_observe);
+        let l = self.logger.clone();
+        self.on_shutdown(move || l.flush());
 
         Ok(())
     }
+
+    /// Register the trace module.
+    ///
+    /// Spans go to the process-wide tracer provider (see
+    /// [`observability::init`](crate::observability::init)), which is
+    /// flushed on [`shutdown`](Self::shutdown). The trace context helpers
+    /// are registered as `observability.inject_context` and
+    /// `observability.extract_context`.
+    #[cfg(feature = "observability")]
+    pub fn register_trace(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::observability::{propagation, tracer};
+
+        self.register_fn(registry, "trace", "span_start", tracer::span_start);
+
+        self.register_fn(registry, "trace", "span_event", tracer::span_event);
+
+        self.register_fn(registry, "trace", "span_end", tracer::span_end);
+
+        self.register_fn(
+            registry,
+            "observability",
+            "inject_context",
+            propagation::inject_context,
+        );
+
+        self.register_fn(
+            registry,
+            "observability",
+            "extract_context",
+            propagation::extract_context,
+        );
+
+        self.on_shutdown(|| tracer::tracer_provider().force_flush());
+
+        Ok(())
+    }
+
+    /// Register the gpu module.
+    ///
+    /// Power readings are integrated into a per-registry energy meter; with
+    /// the `metrics` feature the energy is also counted into
+    /// `gpu_energy_joules_total{device}` in this registry's metrics.
+    #[cfg(feature = "gpu")]
+    pub fn register_gpu(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::gpu;
+        use crate::gpu::energy::EnergyMeter;
+
+        let meter = EnergyMeter::new();
+        #[cfg(feature = "metrics")]
+        let meter = meter.with_metrics(self.metrics.clone());
+        let meter = Arc::new(meter);
+
+        self.register_fn(registry, "gpu", "is_available", gpu::is_available);
+
+        self.register_fn(registry, "gpu", "backend", gpu::backend_name);
+
+        self.register_fn(registry, "gpu", "list_devices", gpu::list_devices);
+
+        self.register_fn(registry, "gpu", "utilization", gpu::utilization);
+
+        self.register_fn(registry, "gpu", "memory_info", gpu::memory_info);
+
+        self.register_fn(registry, "gpu", "temperature", gpu::temperature);
+
+        let m = meter.clone();
+        self.register_fn(registry, "gpu", "power_usage", move |args, ctx| {
+            gpu::power_usage(&m, args, ctx)
+        });
+
+        let m = meter.clone();
+        self.register_fn(registry, "gpu", "energy", move |args, ctx| {
+            gpu::energy::energy(&m, args, ctx)
+        });
+
+        #[cfg(feature = "metrics")]
+        {
+            let sampler = Arc::new(gpu::sampler::GpuSampler::new(self.metrics.clone(), meter));
+
+            let s = sampler.clone();
+            self.register_fn(registry, "gpu", "start_sampler", move |args, ctx| {
+                gpu::sampler::start_sampler(&s, args, ctx)
+            });
+
+            let s = sampler.clone();
+            self.register_fn(registry, "gpu", "stop_sampler", move |args, ctx| {
+                gpu::sampler::stop_sampler(&s, args, ctx)
+            });
+
+            self.on_shutdown(move || {
+                sampler.stop_all();
+                Ok(())
+            });
+        }
+
+        self.register_fn(registry, "gpu", "clock_speeds", gpu::clock_speeds);
+
+        self.register_fn(registry, "gpu", "driver_info", gpu::driver_info);
+
+        self.register_fn(registry, "gpu", "device_info", gpu::device_info);
+
+        self.register_fn(registry, "gpu", "processes", gpu::processes);
+
+        self.register_fn(registry, "gpu", "topology", gpu::topology);
+
+        Ok(())
+    }
+
+    /// Register the patch module.
+    #[cfg(feature = "patch")]
+    pub fn register_patch(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::patch;
+
+        self.register_fn(registry, "patch", "create", patch::create);
+
+        let s = self.safety.clone();
+        self.register_effect(registry, "patch", "apply", move |args, ctx| {
+            patch::apply(&s, args, ctx)
+        });
+
+        Ok(())
+    }
+
+    /// Register the calendar module.
+    #[cfg(feature = "calendar")]
+    pub fn register_calendar(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::calendar;
+
+        self.register_fn(registry, "calendar", "parse_ics", calendar::parse_ics);
+
+        self.register_fn(registry, "calendar", "next_events", calendar::next_events);
+
+        Ok(())
+    }
+
+    /// Register the units module.
+    #[cfg(feature = "units")]
+    pub fn register_units(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::units;
+
+        self.register_fn(registry, "units", "parse_bytes", units::parse_bytes);
+
+        self.register_fn(registry, "units", "format_bytes", units::format_bytes);
+
+        self.register_fn(registry, "units", "parse_duration", units::parse_duration);
+
+        self.register_fn(registry, "units", "format_duration", units::format_duration);
+
+        self.register_fn(registry, "units", "parse_si", units::parse_si);
+
+        self.register_fn(registry, "units", "format_si", units::format_si);
+
+        Ok(())
+    }
+
+    /// Register the locale module.
+    #[cfg(feature = "locale")]
+    pub fn register_locale(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::locale::{self, Localizer};
+
+        let localizer = Arc::new(Localizer::from_env());
+
+        let l = localizer.clone();
+        self.register_fn(registry, "locale", "format_number", move |args, ctx| {
+            locale::format_number(&l, args, ctx)
+        });
+
+        let l = localizer.clone();
+        self.register_fn(registry, "locale", "format_currency", move |args, ctx| {
+            locale::format_currency(&l, args, ctx)
+        });
+
+        let l = localizer.clone();
+        self.register_fn(registry, "locale", "format_date", move |args, ctx| {
+            locale::format_date(&l, args, ctx)
+        });
+
+        let l = localizer.clone();
+        self.register_fn(registry, "locale", "t", move |args, ctx| {
+            locale::t(&l, args, ctx)
+        });
+
+        let l = localizer.clone();
+        let s = self.safety.clone();
+        self.register_fn(registry, "locale", "load", move |args, ctx| {
+            locale::load(&l, &s, args, ctx)
+        });
+
+        let l = localizer.clone();
+        self.register_fn(registry, "locale", "current", move |args, ctx| {
+            locale::current(&l, args, ctx)
+        });
+
+        let l = localizer;
+        self.register_fn(registry, "locale", "set", move |args, ctx| {
+            locale::set(&l, args, ctx)
+        });
+
+        Ok(())
+    }
+
+    /// Register the system module.
+    #[cfg(feature = "system")]
+    pub fn register_system(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::system::{self, SystemMonitor};
+
+        let monitor = Arc::new(SystemMonitor::new());
+
+        let m = monitor.clone();
+        self.register_fn(registry, "system", "cpu_percent", move |args, ctx| {
+            system::cpu_percent(&m, args, ctx)
+        });
+
+        let m = monitor.clone();
+        self.register_fn(registry, "system", "memory", move |args, ctx| {
+            system::memory(&m, args, ctx)
+        });
+
+        self.register_fn(registry, "system", "disks", system::disks);
+
+        self.register_fn(registry, "system", "load_avg", system::load_avg);
+
+        self.register_fn(registry, "system", "uptime", system::uptime);
+
+        let m = monitor;
+        self.register_fn(registry, "system", "processes", move |args, ctx| {
+            system::processes(&m, args, ctx)
+        });
+
+        Ok(())
+    }
+
+    /// Register the stats and math modules.
+    #[cfg(feature = "stats")]
+    pub fn register_stats(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::stats;
+
+        self.register_fn(registry, "stats", "mean", stats::mean);
+
+        self.register_fn(registry, "stats", "median", stats::median);
+
+        self.register_fn(registry, "stats", "stddev", stats::stddev);
+
+        self.register_fn(registry, "stats", "percentile", stats::percentile);
+
+        self.register_fn(
+            registry,
+            "stats",
+            "linear_regression",
+            stats::linear_regression,
+        );
+
+        self.register_fn(registry, "math", "clamp", stats::clamp);
+
+        self.register_fn(registry, "math", "round_to", stats::round_to);
+
+        Ok(())
+    }
+
+    /// Register the k8s module, which needs a client from
+    /// [`with_k8s`](Self::with_k8s).
+    #[cfg(feature = "k8s")]
+    pub fn register_k8s(&self, registry: &mut HostRegistry) -> Result<()> {
+        use crate::k8s;
+
+        let host = self.k8s.clone().ok_or_else(|| {
+            crate::Error::ModuleNotAvailable("k8s: no client configured".to_string())
+        })?;
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "list_pods", move |args, ctx| {
+            k8s::list_pods(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "get_configmap", move |args, ctx| {
+            k8s::get_configmap(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "get_secret", move |args, ctx| {
+            k8s::get_secret(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "list_namespaces", move |args, ctx| {
+            k8s::list_namespaces(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "pod_logs", move |args, ctx| {
+            k8s::logs::pod_logs(&h, &s, args, ctx)
+        });
+        self.register_fn(registry, "k8s", "logs_read_line", k8s::logs::read_line);
+        self.register_fn(
+            registry,
+            "k8s",
+            "logs_read_available",
+            k8s::logs::read_available,
+        );
+        self.register_fn(registry, "k8s", "logs_finished", k8s::logs::finished);
+        self.register_fn(registry, "k8s", "logs_close", k8s::logs::close);
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "cp", move |args, ctx| {
+            k8s::cp(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "run_job", move |args, ctx| {
+            k8s::run_job(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "apply", move |args, ctx| {
+            k8s::apply(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "create", move |args, ctx| {
+            k8s::create(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "delete", move |args, ctx| {
+            k8s::delete(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "exec", move |args, ctx| {
+            k8s::exec::exec(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "watch", move |args, ctx| {
+            k8s::watch::watch(&h, &s, args, ctx)
+        });
+        self.register_fn(registry, "k8s", "watch_poll", k8s::watch::watch_poll);
+        self.register_fn(registry, "k8s", "watch_drain", k8s::watch::watch_drain);
+        self.register_fn(
+            registry,
+            "k8s",
+            "watch_finished",
+            k8s::watch::watch_finished,
+        );
+        self.register_fn(registry, "k8s", "watch_close", k8s::watch::watch_close);
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "port_forward", move |args, ctx| {
+            k8s::port_forward::port_forward(&h, &s, args, ctx)
+        });
+        self.register_fn(
+            registry,
+            "k8s",
+            "port_forward_close",
+            k8s::port_forward::port_forward_close,
+        );
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "scale", move |args, ctx| {
+            k8s::workload::scale(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "rollout_status", move |args, ctx| {
+            k8s::workload::rollout_status(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "restart", move |args, ctx| {
+            k8s::workload::restart(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "create_job", move |args, ctx| {
+            k8s::workload::create_job(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "trigger_cronjob", move |args, ctx| {
+            k8s::workload::trigger_cronjob(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "capacity_report", move |args, ctx| {
+            k8s::capacity::capacity_report(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "clusters", move |args, ctx| {
+            k8s::cluster::clusters(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_effect(registry, "k8s", "use_cluster", move |args, ctx| {
+            k8s::cluster::use_cluster(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "current_cluster", move |args, ctx| {
+            k8s::cluster::current_cluster(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "get_dynamic", move |args, ctx| {
+            k8s::dynamic::get_dynamic(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host.clone(), self.safety.clone());
+        self.register_fn(registry, "k8s", "list_dynamic", move |args, ctx| {
+            k8s::dynamic::list_dynamic(&h, &s, args, ctx)
+        });
+
+        let (h, s) = (host, self.safety.clone());
+        self.register_effect(registry, "k8s", "apply_dynamic", move |args, ctx| {
+            k8s::dynamic::apply_dynamic(&h, &s, args, ctx)
+        });
+
+        Ok(())
+    }
+}
+
+/// Longest panic message, in characters, kept in the error.
+const MAX_PANIC_MESSAGE: usize = 200;
+
+/// Wrap a host function of an audited module so each call is recorded in
+/// the audit log of `safety`, if any.
+fn audit_calls<F>(
+    safety: Option<Arc<SafetyConfig>>,
+    module: &'static str,
+    function: &'static str,
+    f: F,
+) -> impl Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static
+where
+    F: Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static,
+{
+    use crate::safety::audit::{args_hash, AUDITED_MODULES};
+
+    let safety = safety.filter(|_| AUDITED_MODULES.contains(&module));
+    move |args, ctx| {
+        let result = f(args, ctx);
+        if let Some(safety) = &safety {
+            let outcome = match &result {
+                Ok(_) => "allowed",
+                Err(e) if is_denial(e) => "denied",
+                Err(_) => "error",
+            };
+            let mut event =
+                crate::safety::AuditEvent::new(format!("{}.{}", module, function), outcome)
+                    .with_context_id(ctx.engine_id)
+                    .with_field("args_hash", args_hash(args));
+            if let Err(e) = &result {
+                event = event.with_field("error", e.to_string());
+            }
+            safety.audit(event);
+        }
+        result
+    }
+}
+
+/// Whether a host function failed because a safety check refused it.
+///
+/// Host functions report safety errors by their message, so this matches
+/// the messages of [`Error::PathNotAllowed`], [`Error::HostNotAllowed`]
+/// and [`Error::NotPermitted`].
+///
+/// [`Error::PathNotAllowed`]: crate::Error::PathNotAllowed
+/// [`Error::HostNotAllowed`]: crate::Error::HostNotAllowed
+/// [`Error::NotPermitted`]: crate::Error::NotPermitted
+fn is_denial(error: &fusabi_host::Error) -> bool {
+    let message = error.to_string();
+    [
+        "path not allowed",
+        "host not allowed",
+        "operation not permitted",
+    ]
+    .iter()
+    .any(|denial| message.contains(denial))
+}
+
+/// Where a timed host function reports its calls.
+struct CallTimer {
+    module: &'static str,
+    function: &'static str,
+    #[cfg(feature = "metrics")]
+    metrics: Arc<crate::metrics::MetricsRegistry>,
+}
+
+impl CallTimer {
+    fn call(&self, f: impl FnOnce() -> fusabi_host::Result<Value>) -> fusabi_host::Result<Value> {
+        let span = tracing::info_span!(
+            target: "fusabi::stdlib",
+            "stdlib_call",
+            module = self.module,
+            function = self.function,
+            error = tracing::field::Empty,
+        );
+        let _entered = span.enter();
+        let start = std::time::Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+
+        if let Err(e) = &result {
+            span.record("error", tracing::field::display(e));
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let mut labels = crate::metrics::Labels::new();
+            labels.insert("module".into(), self.module.into());
+            labels.insert("function".into(), self.function.into());
+            self.metrics.summary_observe_with(
+                "stdlib_call_duration",
+                &labels,
+                elapsed.as_secs_f64(),
+            );
+            if result.is_err() {
+                self.metrics
+                    .counter_inc_with("stdlib_call_errors_total", &labels, 1);
+            }
+        }
+        #[cfg(not(feature = "metrics"))]
+        let _ = elapsed;
+
+        result
+    }
+}
+
+/// Wrap a host function so its calls are timed by `timer`, if any.
+fn time_calls<F>(
+    timer: Option<CallTimer>,
+    f: F,
+) -> impl Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static
+where
+    F: Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static,
+{
+    move |args, ctx| match &timer {
+        Some(timer) => timer.call(|| f(args, ctx)),
+        None => f(args, ctx),
+    }
+}
+
+/// Wrap a host function so a panic becomes an internal error.
+fn isolate_panics<F>(
+    module: &'static str,
+    function: &'static str,
+    f: F,
+) -> impl Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static
+where
+    F: Fn(&[Value], &ExecutionContext) -> fusabi_host::Result<Value> + Send + Sync + 'static,
+{
+    move |args, ctx| {
+        // Host functions only share state through locks that do not poison
+        // or through their own handle tables, so a call that panicked
+        // leaves nothing half-updated that later calls rely on.
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(args, ctx))).unwrap_or_else(
+            |payload| {
+                let message = panic_message(payload.as_ref());
+                tracing::error!(
+                    target: "fusabi::panic",
+                    module,
+                    function,
+                    "host function {}.{} panicked: {}",
+                    module,
+                    function,
+                    message
+                );
+                Err(fusabi_host::Error::Internal(format!(
+                    "{}.{}: panicked: {}",
+                    module, function, message
+                )))
+            },
+        )
+    }
+}
+
+/// Text of a panic payload, cut to [`MAX_PANIC_MESSAGE`] characters.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "unknown panic payload"
+    };
+    match message.char_indices().nth(MAX_PANIC_MESSAGE) {
+        Some((end, _)) => format!("{}...", &message[..end]),
+        None => message.to_string(),
+    }
 }
 
 impl std::fmt::Debug for StdlibRegistry {
@@ -274,4 +1972,235 @@ mod tests {
         assert!(!registry.config().process.enabled);
         assert!(!registry.config().fs.enabled);
     }
+
+    #[cfg(feature = "fs")]
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn test_call_timing() {
+        use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+        let stdlib = StdlibRegistry::default_config()
+            .unwrap()
+            .with_call_timing(true);
+        let mut registry = HostRegistry::new();
+        stdlib.register_fn(&mut registry, "demo", "echo", |args, _ctx| {
+            args.first()
+                .cloned()
+                .ok_or_else(|| fusabi_host::Error::host_function("demo.echo: missing value"))
+        });
+
+        let echo = registry.get_module("demo", "echo").unwrap();
+        echo(&[Value::Int(1)], &ctx).unwrap();
+        echo(&[Value::Int(2)], &ctx).unwrap();
+        assert!(echo(&[], &ctx).is_err());
+
+        let mut labels = crate::metrics::Labels::new();
+        labels.insert("module".into(), "demo".into());
+        labels.insert("function".into(), "echo".into());
+        let snapshot = stdlib.metrics().snapshot();
+        let diff = snapshot.diff(&crate::metrics::MetricsSnapshot::default());
+        assert_eq!(diff.summary("stdlib_call_duration", &labels).count, 3);
+        assert_eq!(diff.counter("stdlib_call_errors_total", &labels), 1);
+    }
+
+    #[test]
+    fn test_audit_log_records_calls() {
+        use crate::safety::{AuditLog, AuditQuery};
+        use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+        let dir = tempfile::tempdir().unwrap();
+        let log = Arc::new(AuditLog::new());
+        let safety = SafetyConfig::new()
+            .with_paths(crate::safety::PathAllowlist::none().allow_rw(dir.path()))
+            .with_audit_log(log.clone());
+        let stdlib = StdlibRegistry::new(StdlibConfig::new().with_safety(safety)).unwrap();
+        let mut registry = HostRegistry::new();
+        stdlib.register_all(&mut registry).unwrap();
+        let ctx = ExecutionContext::new(
+            7,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+
+        let write = registry.get_module("fs", "write").unwrap();
+        let inside = Value::String(dir.path().join("a.txt").to_string_lossy().into_owned());
+        write(&[inside, Value::String("x".into())], &ctx).unwrap();
+        let outside = Value::String("/etc/fusabi-audit-test".into());
+        assert!(write(&[outside, Value::String("x".into())], &ctx).is_err());
+        let read = registry.get_module("fs", "read").unwrap();
+        let missing = Value::String(dir.path().join("missing").to_string_lossy().into_owned());
+        assert!(read(&[missing], &ctx).is_err());
+        // Modules outside the audited set are not recorded.
+        let join = registry.get_module("path", "join").unwrap();
+        join(
+            &[Value::String("a".into()), Value::String("b".into())],
+            &ctx,
+        )
+        .unwrap();
+
+        let events = stdlib.audit_log().unwrap().query(&AuditQuery::new());
+        let outcomes: Vec<_> = events
+            .iter()
+            .map(|e| (e.action.as_str(), e.outcome.as_str()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("fs.write", "allowed"),
+                ("fs.write", "denied"),
+                ("fs.read", "error")
+            ]
+        );
+        assert!(events.iter().all(|e| e.context_id == Some(7)));
+        assert_ne!(events[0].fields["args_hash"], events[1].fields["args_hash"]);
+    }
+
+    #[test]
+    fn test_dry_run_plan() {
+        use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("out.txt");
+        let safety = SafetyConfig::new()
+            .with_paths(crate::safety::PathAllowlist::none().allow_rw(dir.path()));
+        let config = StdlibConfig::new().with_safety(safety);
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+        let args = [
+            Value::String(target.to_string_lossy().into_owned()),
+            Value::String("hello".into()),
+        ];
+
+        let stdlib = StdlibRegistry::new(config.clone())
+            .unwrap()
+            .with_mode(ExecutionMode::DryRun);
+        let mut registry = HostRegistry::new();
+        stdlib.register_all(&mut registry).unwrap();
+
+        let write = registry.get_module("fs", "write").unwrap();
+        assert_eq!(write(&args, &ctx).unwrap(), Value::Null);
+        assert!(!target.exists());
+        // Reads are not side effects and run normally.
+        let exists = registry.get_module("fs", "exists").unwrap();
+        exists(&args[..1], &ctx).unwrap();
+        let writer = registry.get_module("format", "json_stream_writer").unwrap();
+        assert_eq!(writer(&args[..1], &ctx).unwrap(), Value::Null);
+        assert!(!target.exists());
+
+        let plan = stdlib.take_plan();
+        assert_eq!(
+            plan,
+            vec![
+                PlannedOperation {
+                    module: "fs".into(),
+                    function: "write".into(),
+                    args: args.to_vec(),
+                },
+                PlannedOperation {
+                    module: "format".into(),
+                    function: "json_stream_writer".into(),
+                    args: args[..1].to_vec(),
+                },
+            ]
+        );
+        assert!(stdlib.take_plan().is_empty());
+
+        let stdlib = StdlibRegistry::new(config)
+            .unwrap()
+            .with_mode(ExecutionMode::Trace);
+        let mut registry = HostRegistry::new();
+        stdlib.register_all(&mut registry).unwrap();
+        registry.get_module("fs", "write").unwrap()(&args, &ctx).unwrap();
+        assert!(target.exists());
+        assert_eq!(stdlib.take_plan().len(), 1);
+    }
+
+    #[cfg(feature = "observability")]
+    #[test]
+    fn test_slo_tracks_module_outcomes() {
+        use crate::observability::{AlertState, MemoryAlertHook, SloConfig, SloTracker};
+        use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+        let hook = Arc::new(MemoryAlertHook::new());
+        let tracker =
+            SloTracker::new(SloConfig::new().with_min_calls(4)).with_alert_hook(hook.clone());
+        let stdlib = StdlibRegistry::default_config()
+            .unwrap()
+            .with_slo_tracker(Arc::new(tracker));
+        let mut registry = HostRegistry::new();
+        stdlib.register_fn(&mut registry, "net", "get", |args, _ctx| {
+            match args.first() {
+                Some(Value::Bool(true)) => Ok(Value::Null),
+                _ => Err(fusabi_host::Error::host_function("net.get: refused")),
+            }
+        });
+        stdlib.register_health(&mut registry).unwrap();
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+
+        let get = registry.get_module("net", "get").unwrap();
+        for ok in [true, false, true, true] {
+            let _ = get(&[Value::Bool(ok)], &ctx);
+        }
+
+        let alerts = hook.take();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].state, AlertState::Firing);
+        assert_eq!(alerts[0].status.module, "net");
+
+        let status =
+            registry.get_module("health", "slo").unwrap()(&[Value::String("net".into())], &ctx)
+                .unwrap();
+        assert_eq!(status.as_map().unwrap()["errors"], Value::Int(1));
+        assert_eq!(stdlib.slo_tracker().module_status("net").unwrap().calls, 4);
+    }
+
+    #[test]
+    fn test_host_function_panics_are_isolated() {
+        use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+        let stdlib = StdlibRegistry::default_config().unwrap();
+        let mut registry = HostRegistry::new();
+        stdlib.register_fn(&mut registry, "buggy", "explode", |args, _ctx| {
+            match args.first().and_then(|v| v.as_int()) {
+                Some(n) => Ok(Value::Int(n)),
+                None => panic!("{}", "x".repeat(500)),
+            }
+        });
+        let ctx = ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        );
+
+        let explode = registry.get_module("buggy", "explode").unwrap();
+        match explode(&[], &ctx) {
+            Err(fusabi_host::Error::Internal(message)) => {
+                assert!(message.starts_with("buggy.explode: panicked: xxx"));
+                assert_eq!(
+                    message.len(),
+                    "buggy.explode: panicked: ".len() + MAX_PANIC_MESSAGE + 3
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        // The function keeps working after a panic.
+        assert_eq!(explode(&[Value::Int(7)], &ctx).unwrap(), Value::Int(7));
+    }
 }
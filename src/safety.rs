@@ -1,11 +1,123 @@
 //! Safety controls for stdlib operations.
+//!
+//! Permission decisions and other security-relevant events are reported as
+//! [`AuditEvent`]s to the configuration's [`AuditSink`]. An [`AuditLog`]
+//! (see [`audit`]) additionally records every `env`, `fs`, `net` and
+//! `process` call and keeps recent events for querying.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
 
 use crate::error::{Error, Result};
 
+pub mod audit;
+
+pub use audit::{AuditLog, AuditQuery, CallbackAuditSink, JsonlAuditSink, DEFAULT_AUDIT_CAPACITY};
+
+/// Allowlist entries that lapse at a deadline.
+///
+/// Copies are independent snapshots, unless the entries belong to a
+/// [`GrantStore`], whose copies share them.
+#[derive(Debug)]
+struct Expiring<T> {
+    entries: Arc<Mutex<Vec<(T, Instant)>>>,
+    shared: bool,
+}
+
+impl<T: Clone> Clone for Expiring<T> {
+    fn clone(&self) -> Self {
+        if self.shared {
+            return Self {
+                entries: self.entries.clone(),
+                shared: true,
+            };
+        }
+        Self {
+            entries: Arc::new(Mutex::new(self.entries.lock().clone())),
+            shared: false,
+        }
+    }
+}
+
+impl<T> Default for Expiring<T> {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Vec::new())),
+            shared: false,
+        }
+    }
+}
+
+impl<T: Clone> Expiring<T> {
+    fn shared() -> Self {
+        Self {
+            shared: true,
+            ..Self::default()
+        }
+    }
+
+    fn insert(&self, entry: T, until: Instant) {
+        self.entries.lock().push((entry, until));
+    }
+
+    /// Whether a live entry matches, dropping lapsed ones.
+    fn any(&self, matches: impl Fn(&T) -> bool) -> bool {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        entries.retain(|(_, until)| *until > now);
+        entries.iter().any(|(entry, _)| matches(entry))
+    }
+
+    fn live(&self) -> Vec<(T, Instant)> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        entries.retain(|(_, until)| *until > now);
+        entries.clone()
+    }
+
+    fn clear(&self) {
+        self.entries.lock().clear();
+    }
+}
+
+/// Temporary grants shared between configurations.
+///
+/// Each [`SafetyConfig`] keeps its own temporary grants, and a clone starts
+/// with a snapshot of them, so a grant made for one tenant's configuration
+/// never reaches another's. Configurations given the same store with
+/// [`SafetyConfig::with_grant_store`] (and their clones) see each other's
+/// grants instead, for example the caller's copy that answers "allow for 15
+/// minutes" prompts and the one a [`StdlibRegistry`](crate::StdlibRegistry)
+/// was built from. Copies of a store are handles to the same grants.
+#[derive(Debug, Clone)]
+pub struct GrantStore {
+    paths: Expiring<(PathBuf, bool)>,
+    hosts: Expiring<String>,
+    commands: Expiring<String>,
+}
+
+impl GrantStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self {
+            paths: Expiring::shared(),
+            hosts: Expiring::shared(),
+            commands: Expiring::shared(),
+        }
+    }
+}
+
+impl Default for GrantStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Allowlist for filesystem paths.
 #[derive(Debug, Clone, Default)]
 pub struct PathAllowlist {
@@ -15,6 +127,8 @@ pub struct PathAllowlist {
     pub write: HashSet<PathBuf>,
     /// Denied paths (overrides allowlist).
     pub deny: HashSet<PathBuf>,
+    /// Temporary grants: path, whether writing is allowed, and deadline.
+    temporary: Expiring<(PathBuf, bool)>,
 }
 
 impl PathAllowlist {
@@ -29,6 +143,7 @@ impl PathAllowlist {
             read: [PathBuf::from("/")].into_iter().collect(),
             write: [PathBuf::from("/")].into_iter().collect(),
             deny: HashSet::new(),
+            temporary: Expiring::default(),
         }
     }
 
@@ -56,12 +171,25 @@ impl PathAllowlist {
         self
     }
 
+    /// Allow reading a path until `until`, without changing the permanent
+    /// entries. Denied paths stay denied.
+    pub fn grant_read_until(&self, path: impl Into<PathBuf>, until: Instant) {
+        self.temporary.insert((path.into(), false), until);
+    }
+
+    /// Allow reading and writing a path until `until`, without changing the
+    /// permanent entries. Denied paths stay denied.
+    pub fn grant_rw_until(&self, path: impl Into<PathBuf>, until: Instant) {
+        self.temporary.insert((path.into(), true), until);
+    }
+
     /// Check if a path is allowed for reading.
     pub fn can_read(&self, path: &Path) -> bool {
         if self.is_denied(path) {
             return false;
         }
         self.read.iter().any(|allowed| path.starts_with(allowed))
+            || self.temporary.any(|(granted, _)| path.starts_with(granted))
     }
 
     /// Check if a path is allowed for writing.
@@ -70,6 +198,9 @@ impl PathAllowlist {
             return false;
         }
         self.write.iter().any(|allowed| path.starts_with(allowed))
+            || self
+                .temporary
+                .any(|(granted, write)| *write && path.starts_with(granted))
     }
 
     /// Check if a path is denied.
@@ -103,6 +234,8 @@ pub struct HostAllowlist {
     pub allowed: HashSet<String>,
     /// Denied hosts.
     pub denied: HashSet<String>,
+    /// Temporarily allowed host patterns with their deadlines.
+    temporary: Expiring<String>,
 }
 
 impl HostAllowlist {
@@ -116,6 +249,7 @@ impl HostAllowlist {
         Self {
             allowed: ["*".to_string()].into_iter().collect(),
             denied: HashSet::new(),
+            temporary: Expiring::default(),
         }
     }
 
@@ -131,6 +265,12 @@ impl HostAllowlist {
         self
     }
 
+    /// Allow a host (or `*.domain` pattern) until `until`, without changing
+    /// the permanent entries. Denied hosts stay denied.
+    pub fn grant_until(&self, host: impl Into<String>, until: Instant) {
+        self.temporary.insert(host.into(), until);
+    }
+
     /// Check if a host is allowed.
     pub fn can_access(&self, host: &str) -> bool {
         let host = host.to_lowercase();
@@ -149,7 +289,8 @@ impl HostAllowlist {
             }
         }
 
-        false
+        self.temporary
+            .any(|granted| Self::host_matches(&host, granted))
     }
 
     fn host_matches(host: &str, pattern: &str) -> bool {
@@ -177,6 +318,621 @@ impl HostAllowlist {
     }
 }
 
+/// Whether an address is internal: loopback, private, link-local, shared
+/// (carrier-grade NAT), unspecified, broadcast or multicast, including
+/// IPv4 addresses mapped into IPv6.
+///
+/// Such addresses reach the host itself or its local network, and are
+/// what DNS rebinding attacks point an allowlisted name at.
+pub fn is_internal_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                // 100.64.0.0/10, shared address space
+                || (a == 100 && (b & 0xc0) == 64)
+                // 0.0.0.0/8, "this network"
+                || a == 0
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_internal_address(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // fc00::/7, unique local
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10, link-local
+                || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// A host name with the addresses it resolved to when a request started.
+///
+/// Created by [`SafetyConfig::pin_host`]. It does not restrict connections
+/// by itself: a client must connect to these addresses only, as one built
+/// with `net::pinned_client` does, so the name cannot be re-resolved to a
+/// different address mid-session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedHost {
+    host: String,
+    addresses: Vec<IpAddr>,
+}
+
+impl PinnedHost {
+    /// The host name, lowercased.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The addresses connections may use.
+    pub fn addresses(&self) -> &[IpAddr] {
+        &self.addresses
+    }
+
+    /// The addresses with a port, for a connector's resolve override.
+    pub fn socket_addrs(&self, port: u16) -> Vec<std::net::SocketAddr> {
+        self.addresses
+            .iter()
+            .map(|ip| std::net::SocketAddr::new(*ip, port))
+            .collect()
+    }
+
+    /// Check that a connection's peer is one of the pinned addresses.
+    pub fn check_peer(&self, peer: IpAddr) -> Result<()> {
+        if self.addresses.contains(&peer) {
+            Ok(())
+        } else {
+            Err(Error::not_permitted(format!(
+                "{} connected to {}, which it was not pinned to",
+                self.host, peer
+            )))
+        }
+    }
+}
+
+/// Key fragments, matched case-insensitively, that mark a value as secret
+/// wherever keyed values are logged or exported.
+pub const SECRET_KEY_PARTS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "credential",
+    "private_key",
+];
+
+/// Whether values under `key` must be redacted.
+pub fn is_secret_key(key: &str) -> bool {
+    let lower = key.to_ascii_lowercase();
+    SECRET_KEY_PARTS.iter().any(|part| lower.contains(part))
+}
+
+/// Programs that run another command with elevated privileges.
+pub const PRIVILEGE_ESCALATION_COMMANDS: &[&str] = &["sudo", "doas", "pkexec", "runas", "su"];
+
+/// Launchers that run their first non-option argument as a command, with
+/// the options of each that take a separate value.
+const COMMAND_WRAPPERS: &[(&str, &[&str])] = &[
+    ("env", &["-u", "--unset", "-C", "--chdir"]),
+    ("nohup", &[]),
+    ("nice", &["-n", "--adjustment"]),
+    (
+        "ionice",
+        &[
+            "-c",
+            "--class",
+            "-n",
+            "--classdata",
+            "-p",
+            "--pid",
+            "-P",
+            "--pgid",
+            "-u",
+            "--uid",
+        ],
+    ),
+    (
+        "stdbuf",
+        &["-i", "--input", "-o", "--output", "-e", "--error"],
+    ),
+    ("timeout", &["-s", "--signal", "-k", "--kill-after"]),
+    ("time", &["-f", "--format", "-o", "--output"]),
+    ("command", &[]),
+    ("exec", &["-a"]),
+    (
+        "xargs",
+        &[
+            "-a",
+            "--arg-file",
+            "-d",
+            "--delimiter",
+            "-E",
+            "-I",
+            "-L",
+            "-n",
+            "--max-args",
+            "-P",
+            "--max-procs",
+            "-s",
+            "--max-chars",
+        ],
+    ),
+];
+
+/// Shells whose `-c` argument is a command line.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash", "ksh", "fish"];
+
+/// Shell options that take a separate value.
+const SHELL_OPTIONS: &[&str] = &["-o", "+o", "-O", "+O", "--rcfile", "--init-file"];
+
+/// Reserved words that may start a shell command without being the program.
+const SHELL_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "do", "done", "while", "until", "!",
+];
+
+/// Normalize a program path to a lowercase basename without `.exe`.
+fn program_name(command: &str) -> String {
+    let name = command
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(command)
+        .to_ascii_lowercase();
+    name.strip_suffix(".exe").map(String::from).unwrap_or(name)
+}
+
+/// Whether a program is a known privilege-escalation wrapper.
+pub fn is_privilege_escalation(command: &str) -> bool {
+    PRIVILEGE_ESCALATION_COMMANDS.contains(&program_name(command).as_str())
+}
+
+/// Find a privilege-escalation program in a command line, looking through
+/// launcher wrappers and shell `-c` strings.
+fn find_privilege_escalation(command: &str, args: &[String]) -> Option<String> {
+    let name = program_name(command);
+    if PRIVILEGE_ESCALATION_COMMANDS.contains(&name.as_str()) {
+        return Some(name);
+    }
+
+    if let Some((_, value_options)) = COMMAND_WRAPPERS.iter().find(|(w, _)| *w == name) {
+        return find_in_wrapper(&name, value_options, args);
+    }
+
+    if SHELLS.contains(&name.as_str()) {
+        return find_in_script(shell_command_string(args)?);
+    }
+
+    None
+}
+
+/// Find a privilege-escalation program in the command a wrapper runs.
+fn find_in_wrapper(name: &str, value_options: &[&str], args: &[String]) -> Option<String> {
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        if arg == "--" {
+            i += 1;
+            break;
+        }
+        if name == "env" {
+            // `env -S` splits its value into the rest of the command line.
+            let split = match arg.as_str() {
+                "-S" | "--split-string" => args.get(i + 1).map(|v| (v.as_str(), i + 2)),
+                _ => arg
+                    .strip_prefix("--split-string=")
+                    .or_else(|| arg.strip_prefix("-S"))
+                    .map(|v| (v, i + 1)),
+            };
+            if let Some((value, next)) = split {
+                let words: Vec<String> = value
+                    .split_whitespace()
+                    .map(String::from)
+                    .chain(args[next.min(args.len())..].iter().cloned())
+                    .collect();
+                return find_in_wrapper(name, value_options, &words);
+            }
+            if is_assignment(arg) {
+                i += 1;
+                continue;
+            }
+        }
+        if arg.len() > 1 && arg.starts_with('-') {
+            i += if value_options.contains(&arg.as_str()) {
+                2
+            } else {
+                1
+            };
+            continue;
+        }
+        break;
+    }
+
+    // `timeout` takes a duration before the command.
+    if name == "timeout" {
+        i += 1;
+    }
+    let (next, tail) = args.get(i..)?.split_first()?;
+    find_privilege_escalation(next, tail)
+}
+
+/// Whether a word is a `VAR=value` assignment.
+fn is_assignment(word: &str) -> bool {
+    !word.starts_with('-') && word.contains('=')
+}
+
+/// The command string passed to a shell with `-c` (alone or combined with
+/// other flags, as in `-lc`), or `None` if the shell runs a script file.
+fn shell_command_string(args: &[String]) -> Option<&str> {
+    let mut command = false;
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        i += 1;
+        if arg == "--" {
+            break;
+        }
+        if arg == "--command" {
+            return args.get(i).map(String::as_str);
+        }
+        if let Some(value) = arg.strip_prefix("--command=") {
+            return Some(value);
+        }
+        if SHELL_OPTIONS.contains(&arg.as_str()) {
+            i += 1;
+        } else if let Some(flags) = arg.strip_prefix('-') {
+            if !flags.starts_with('-') && flags.contains('c') {
+                command = true;
+            }
+        } else if !arg.starts_with('+') {
+            return command.then_some(arg.as_str());
+        }
+    }
+    if command {
+        args.get(i).map(String::as_str)
+    } else {
+        None
+    }
+}
+
+/// Find a privilege-escalation program in any simple command of a shell
+/// command line.
+fn find_in_script(script: &str) -> Option<String> {
+    script
+        .split(['&', '|', ';', '\n', '(', ')', '`', '{', '}'])
+        .find_map(|segment| {
+            let words: Vec<String> = segment
+                .split_whitespace()
+                .map(|w| {
+                    w.chars()
+                        .filter(|c| !matches!(c, '\'' | '"' | '\\'))
+                        .collect()
+                })
+                .collect();
+            let start = words
+                .iter()
+                .position(|w| !SHELL_KEYWORDS.contains(&w.as_str()) && !is_assignment(w))?;
+            let (first, tail) = words[start..].split_first()?;
+            find_privilege_escalation(first, tail)
+        })
+}
+
+/// A security-relevant event, such as a permission decision or an MCP tool
+/// call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    /// What was attempted, e.g. `"process.privilege_escalation"`.
+    pub action: String,
+    /// How it ended, e.g. `"allowed"`, `"denied"`, `"ok"`, or `"error"`.
+    pub outcome: String,
+    /// Details of the event.
+    pub fields: BTreeMap<String, String>,
+    /// When the event happened, in nanoseconds since epoch.
+    pub timestamp_ns: u64,
+    /// Engine ID of the execution context that caused the event, if any.
+    pub context_id: Option<u64>,
+}
+
+impl AuditEvent {
+    /// Create an event without details, timestamped now.
+    pub fn new(action: impl Into<String>, outcome: impl Into<String>) -> Self {
+        let timestamp_ns = std::time::SystemTime::now()
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+
+        Self {
+            action: action.into(),
+            outcome: outcome.into(),
+            fields: BTreeMap::new(),
+            timestamp_ns,
+            context_id: None,
+        }
+    }
+
+    /// Add a detail.
+    pub fn with_field(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set the engine ID of the execution context that caused the event.
+    pub fn with_context_id(mut self, id: u64) -> Self {
+        self.context_id = Some(id);
+        self
+    }
+
+    /// Convert to a map with `timestamp_ns`, `context_id` (null if
+    /// unknown), `action`, `outcome` and `fields` keys.
+    pub fn to_value(&self) -> fusabi_host::Value {
+        use fusabi_host::Value;
+
+        let fields = self
+            .fields
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        let mut map = std::collections::HashMap::new();
+        map.insert(
+            "timestamp_ns".to_string(),
+            Value::Int(self.timestamp_ns as i64),
+        );
+        map.insert(
+            "context_id".to_string(),
+            self.context_id
+                .map_or(Value::Null, |id| Value::Int(id as i64)),
+        );
+        map.insert("action".to_string(), Value::String(self.action.clone()));
+        map.insert("outcome".to_string(), Value::String(self.outcome.clone()));
+        map.insert("fields".to_string(), Value::Map(fields));
+        Value::Map(map)
+    }
+}
+
+/// Destination for audit events.
+pub trait AuditSink: Send + Sync {
+    /// Record an event.
+    fn record(&self, event: &AuditEvent);
+}
+
+impl std::fmt::Debug for dyn AuditSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AuditSink")
+    }
+}
+
+/// Audit sink that emits events as `tracing` warnings with target
+/// `fusabi::audit`. This is the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        tracing::warn!(
+            target: "fusabi::audit",
+            action = %event.action,
+            outcome = %event.outcome,
+            fields = ?event.fields,
+            "{} {}",
+            event.action,
+            event.outcome
+        );
+    }
+}
+
+/// Audit sink that keeps events in memory, for tests and for embedders that
+/// forward events themselves.
+#[derive(Debug, Default)]
+pub struct MemoryAuditSink {
+    events: Mutex<Vec<AuditEvent>>,
+}
+
+impl MemoryAuditSink {
+    /// Create an empty sink.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events recorded so far.
+    pub fn events(&self) -> Vec<AuditEvent> {
+        self.events.lock().clone()
+    }
+
+    /// Remove and return the events recorded so far.
+    pub fn take(&self) -> Vec<AuditEvent> {
+        std::mem::take(&mut *self.events.lock())
+    }
+}
+
+impl AuditSink for MemoryAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        self.events.lock().push(event.clone());
+    }
+}
+
+/// Kubernetes operations scripts may perform once
+/// [`SafetyConfig::allow_k8s`] is set.
+///
+/// Verbs and resources use the RBAC names, such as `"list"` and `"pods"`.
+/// Each list is `None` to allow anything; namespaces are not checked for
+/// cluster-scoped resources.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct K8sAllowlist {
+    /// Allowed verbs.
+    pub verbs: Option<HashSet<String>>,
+    /// Allowed resources.
+    pub resources: Option<HashSet<String>>,
+    /// Allowed namespaces. A `"*"` entry allows every namespace, including
+    /// operations across all namespaces at once.
+    pub namespaces: Option<HashSet<String>>,
+    /// Allowed clusters, by registered name.
+    pub contexts: Option<HashSet<String>>,
+}
+
+impl K8sAllowlist {
+    /// Create an allowlist that allows every operation.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Create an allowlist that allows nothing.
+    pub fn none() -> Self {
+        Self {
+            verbs: Some(HashSet::new()),
+            resources: Some(HashSet::new()),
+            namespaces: Some(HashSet::new()),
+            contexts: Some(HashSet::new()),
+        }
+    }
+
+    /// Allow only these verbs.
+    pub fn with_verbs<I, S>(mut self, verbs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.verbs = Some(verbs.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Allow only these resources.
+    pub fn with_resources<I, S>(mut self, resources: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.resources = Some(resources.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Allow only these namespaces.
+    pub fn with_namespaces<I, S>(mut self, namespaces: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.namespaces = Some(namespaces.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Allow only these clusters to be selected with `k8s.use_cluster`.
+    pub fn with_contexts<I, S>(mut self, contexts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.contexts = Some(contexts.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check that a cluster may be selected.
+    pub fn check_context(&self, context: &str) -> Result<()> {
+        if !self
+            .contexts
+            .as_ref()
+            .map_or(true, |contexts| contexts.contains(context))
+        {
+            return Err(Error::not_permitted(format!(
+                "kubernetes context not allowed: {}",
+                context
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check a verb alone, for operations whose resource is only known
+    /// after a discovery request.
+    pub fn check_verb(&self, verb: &str) -> Result<()> {
+        if !self
+            .verbs
+            .as_ref()
+            .map_or(true, |verbs| verbs.contains(verb))
+        {
+            return Err(Error::not_permitted(format!(
+                "kubernetes verb not allowed: {}",
+                verb
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check an operation, returning an error naming the first part that
+    /// is not allowed.
+    pub fn check(&self, verb: &str, resource: &str, namespace: Option<&str>) -> Result<()> {
+        let allows = |list: &Option<HashSet<String>>, item: &str| {
+            list.as_ref().map_or(true, |list| list.contains(item))
+        };
+        self.check_verb(verb)?;
+        if !allows(&self.resources, resource) {
+            return Err(Error::not_permitted(format!(
+                "kubernetes resource not allowed: {}",
+                resource
+            )));
+        }
+        if let Some(namespace) = namespace {
+            if !allows(&self.namespaces, namespace) && !allows(&self.namespaces, "*") {
+                return Err(Error::not_permitted(format!(
+                    "kubernetes namespace not allowed: {}",
+                    namespace
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check an operation on a namespaced resource across all namespaces.
+    ///
+    /// Allowed only when namespaces are unrestricted or have a `"*"` entry,
+    /// since the operation reaches namespaces outside the allowlist.
+    pub fn check_all_namespaces(&self, verb: &str, resource: &str) -> Result<()> {
+        self.check(verb, resource, None)?;
+        if !self
+            .namespaces
+            .as_ref()
+            .map_or(true, |namespaces| namespaces.contains("*"))
+        {
+            return Err(Error::not_permitted(format!(
+                "kubernetes namespaces are restricted: cannot {} {} across all namespaces",
+                verb, resource
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Access granted for a limited time with [`SafetyConfig::grant_temporary`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Grant {
+    /// Read a path and everything under it.
+    Read(PathBuf),
+    /// Read and write a path and everything under it.
+    ReadWrite(PathBuf),
+    /// Connect to a host or `*.domain` pattern.
+    Host(String),
+    /// Run a command not in [`SafetyConfig::allowed_commands`]. Process
+    /// execution itself must still be allowed.
+    Command(String),
+}
+
+impl Grant {
+    pub(crate) fn describe(&self) -> (&'static str, String) {
+        match self {
+            Grant::Read(path) => ("read", path.display().to_string()),
+            Grant::ReadWrite(path) => ("read_write", path.display().to_string()),
+            Grant::Host(host) => ("host", host.clone()),
+            Grant::Command(command) => ("command", command.clone()),
+        }
+    }
+}
+
 /// Safety configuration for stdlib operations.
 #[derive(Debug, Clone)]
 pub struct SafetyConfig {
@@ -190,10 +946,38 @@ pub struct SafetyConfig {
     pub allow_process: bool,
     /// Allowed process commands (None = all allowed if allow_process is true).
     pub allowed_commands: Option<HashSet<String>>,
+    /// Directories commands may be resolved from (None = any `PATH` entry).
+    pub allowed_command_dirs: Option<Vec<PathBuf>>,
+    /// Whether privilege-escalation wrappers (sudo, doas, ...) may be run.
+    pub allow_privilege_escalation: bool,
+    /// Whether scripts may change runtime log levels.
+    pub allow_log_control: bool,
+    /// Whether scripts may read or write the system clipboard.
+    pub allow_clipboard: bool,
+    /// Whether scripts may call the Kubernetes API.
+    pub allow_k8s: bool,
+    /// Kubernetes operations allowed when `allow_k8s` is set.
+    pub k8s: K8sAllowlist,
+    /// Whether allowlisted host names may resolve to internal addresses
+    /// (see [`is_internal_address`]). When unset, requests resolve and pin
+    /// their host with [`pin_host`](Self::pin_host) first. Set by default
+    /// except in [`strict`](Self::strict), so DNS rebinding protection is
+    /// opt-in.
+    pub allow_internal_addresses: bool,
+    /// Whether `fs.remove` moves paths to the trash instead of deleting them.
+    pub trash_on_remove: bool,
+    /// Quarantine directory used as the trash instead of the platform one.
+    pub trash_dir: Option<PathBuf>,
     /// Default timeout for operations.
     pub default_timeout: Duration,
     /// Maximum timeout allowed.
     pub max_timeout: Duration,
+    /// Where audit events are recorded.
+    pub audit_sink: Arc<dyn AuditSink>,
+    /// Audit log that host function calls are recorded in, if any.
+    pub audit_log: Option<Arc<AuditLog>>,
+    /// Commands temporarily allowed beyond `allowed_commands`.
+    command_grants: Expiring<String>,
 }
 
 impl Default for SafetyConfig {
@@ -204,8 +988,20 @@ impl Default for SafetyConfig {
             env_vars: Some(HashSet::new()),
             allow_process: false,
             allowed_commands: None,
+            allowed_command_dirs: None,
+            allow_privilege_escalation: false,
+            allow_log_control: false,
+            allow_clipboard: false,
+            allow_k8s: false,
+            k8s: K8sAllowlist::all(),
+            allow_internal_addresses: true,
+            trash_on_remove: false,
+            trash_dir: None,
             default_timeout: Duration::from_secs(30),
             max_timeout: Duration::from_secs(300),
+            audit_sink: Arc::new(TracingAuditSink),
+            audit_log: None,
+            command_grants: Expiring::default(),
         }
     }
 }
@@ -224,8 +1020,20 @@ impl SafetyConfig {
             env_vars: None,
             allow_process: true,
             allowed_commands: None,
+            allowed_command_dirs: None,
+            allow_privilege_escalation: false,
+            allow_log_control: true,
+            allow_clipboard: true,
+            allow_k8s: true,
+            k8s: K8sAllowlist::all(),
+            allow_internal_addresses: true,
+            trash_on_remove: false,
+            trash_dir: None,
             default_timeout: Duration::from_secs(60),
             max_timeout: Duration::from_secs(3600),
+            audit_sink: Arc::new(TracingAuditSink),
+            audit_log: None,
+            command_grants: Expiring::default(),
         }
     }
 
@@ -237,23 +1045,50 @@ impl SafetyConfig {
             env_vars: Some(HashSet::new()),
             allow_process: false,
             allowed_commands: Some(HashSet::new()),
+            allowed_command_dirs: None,
+            allow_privilege_escalation: false,
+            allow_log_control: false,
+            allow_clipboard: false,
+            allow_k8s: false,
+            k8s: K8sAllowlist::none(),
+            allow_internal_addresses: false,
+            trash_on_remove: false,
+            trash_dir: None,
             default_timeout: Duration::from_secs(10),
             max_timeout: Duration::from_secs(30),
+            audit_sink: Arc::new(TracingAuditSink),
+            audit_log: None,
+            command_grants: Expiring::default(),
         }
     }
 
     /// Set path allowlist.
-    pub fn with_paths(mut self, paths: PathAllowlist) -> Self {
+    pub fn with_paths(mut self, mut paths: PathAllowlist) -> Self {
+        if self.paths.temporary.shared {
+            paths.temporary = self.paths.temporary.clone();
+        }
         self.paths = paths;
         self
     }
 
     /// Set host allowlist.
-    pub fn with_hosts(mut self, hosts: HostAllowlist) -> Self {
+    pub fn with_hosts(mut self, mut hosts: HostAllowlist) -> Self {
+        if self.hosts.temporary.shared {
+            hosts.temporary = self.hosts.temporary.clone();
+        }
         self.hosts = hosts;
         self
     }
 
+    /// Keep temporary grants in `store`, shared with every configuration
+    /// using it. Grants made on this configuration before are dropped.
+    pub fn with_grant_store(mut self, store: &GrantStore) -> Self {
+        self.paths.temporary = store.paths.clone();
+        self.hosts.temporary = store.hosts.clone();
+        self.command_grants = store.commands.clone();
+        self
+    }
+
     /// Allow specific environment variables.
     pub fn with_env_vars<I, S>(mut self, vars: I) -> Self
     where
@@ -286,6 +1121,101 @@ impl SafetyConfig {
         self
     }
 
+    /// Only resolve commands from these directories.
+    ///
+    /// `PATH` entries outside them are skipped, as are commands given as a
+    /// path outside them.
+    pub fn with_allowed_command_dirs<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.allowed_command_dirs = Some(dirs.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Check whether commands may be run from a directory.
+    pub fn can_run_from(&self, dir: &Path) -> bool {
+        match &self.allowed_command_dirs {
+            None => true,
+            Some(allowed) => {
+                let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+                allowed.iter().any(|root| {
+                    let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+                    dir.starts_with(root)
+                })
+            }
+        }
+    }
+
+    /// Allow privilege-escalation wrappers such as `sudo`.
+    ///
+    /// Denied by default in every preset, including [`SafetyConfig::permissive`],
+    /// so that allowlisting a command never implicitly allows running it as root.
+    pub fn with_allow_privilege_escalation(mut self, allow: bool) -> Self {
+        self.allow_privilege_escalation = allow;
+        self
+    }
+
+    /// Allow scripts to change runtime log levels.
+    pub fn with_allow_log_control(mut self, allow: bool) -> Self {
+        self.allow_log_control = allow;
+        self
+    }
+
+    /// Allow scripts to access the system clipboard.
+    ///
+    /// Clipboard contents frequently include secrets such as passwords and
+    /// tokens, so access is denied by default.
+    pub fn with_allow_clipboard(mut self, allow: bool) -> Self {
+        self.allow_clipboard = allow;
+        self
+    }
+
+    /// Allow scripts to call the Kubernetes API, within the
+    /// [`k8s`](Self::k8s) allowlist.
+    pub fn with_allow_k8s(mut self, allow: bool) -> Self {
+        self.allow_k8s = allow;
+        self
+    }
+
+    /// Set the Kubernetes allowlist.
+    pub fn with_k8s(mut self, k8s: K8sAllowlist) -> Self {
+        self.k8s = k8s;
+        self
+    }
+
+    /// Allow or block allowlisted host names that resolve to internal
+    /// addresses.
+    ///
+    /// Blocking protects against DNS rebinding: a name that passes the
+    /// allowlist is resolved once per request and rejected if any address
+    /// is internal, and clients built with `net::pinned_client` connect to
+    /// the checked addresses only. Internal addresses allowlisted literally,
+    /// such as `127.0.0.1`, stay reachable.
+    ///
+    /// Internal addresses are allowed by default, except in
+    /// [`strict`](Self::strict); call this with `false` to opt in.
+    pub fn with_allow_internal_addresses(mut self, allow: bool) -> Self {
+        self.allow_internal_addresses = allow;
+        self
+    }
+
+    /// Make `fs.remove` move paths to the trash instead of deleting them.
+    ///
+    /// Requires the `fs-trash` feature; without it `fs.remove` fails rather
+    /// than deleting.
+    pub fn with_trash_on_remove(mut self, trash: bool) -> Self {
+        self.trash_on_remove = trash;
+        self
+    }
+
+    /// Use a quarantine directory as the trash.
+    pub fn with_trash_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.trash_dir = Some(dir.into());
+        self
+    }
+
     /// Set default timeout.
     pub fn with_default_timeout(mut self, timeout: Duration) -> Self {
         self.default_timeout = timeout;
@@ -298,6 +1228,97 @@ impl SafetyConfig {
         self
     }
 
+    /// Record audit events in `sink` instead of the `tracing` log.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit_sink = sink;
+        self
+    }
+
+    /// Record audit events in `log`, and record every `env`, `fs`, `net`
+    /// and `process` host function call there with its decision.
+    ///
+    /// This replaces the audit sink; add other destinations to the log with
+    /// [`AuditLog::with_sink`].
+    pub fn with_audit_log(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit_sink = log.clone();
+        self.audit_log = Some(log);
+        self
+    }
+
+    /// Record an audit event.
+    pub fn audit(&self, event: AuditEvent) {
+        self.audit_sink.record(&event);
+    }
+
+    /// Allow something for `ttl`, such as after a user answers "allow for
+    /// 15 minutes" to a prompt.
+    ///
+    /// The grant lapses on its own and never overrides a deny entry. It
+    /// applies to this configuration and, through a [`GrantStore`], to
+    /// every configuration sharing the store, such as the one a
+    /// [`StdlibRegistry`](crate::StdlibRegistry) was built from. It is
+    /// recorded as a `safety.grant` audit event.
+    pub fn grant_temporary(&self, grant: Grant, ttl: Duration) {
+        self.grant_until(grant, Instant::now() + ttl);
+    }
+
+    /// Allow something until `until`; see
+    /// [`grant_temporary`](Self::grant_temporary).
+    pub fn grant_until(&self, grant: Grant, until: Instant) {
+        let (kind, target) = grant.describe();
+        let ttl = until.saturating_duration_since(Instant::now());
+        self.audit(
+            AuditEvent::new("safety.grant", "granted")
+                .with_field("kind", kind)
+                .with_field("target", target)
+                .with_field("ttl_ms", ttl.as_millis().to_string()),
+        );
+
+        match grant {
+            Grant::Read(path) => self.paths.grant_read_until(path, until),
+            Grant::ReadWrite(path) => self.paths.grant_rw_until(path, until),
+            Grant::Host(host) => self.hosts.grant_until(host, until),
+            Grant::Command(command) => self.command_grants.insert(command, until),
+        }
+    }
+
+    /// Temporary grants that have not lapsed, with their deadlines.
+    pub fn temporary_grants(&self) -> Vec<(Grant, Instant)> {
+        let paths = self
+            .paths
+            .temporary
+            .live()
+            .into_iter()
+            .map(|((path, write), until)| {
+                let grant = if write {
+                    Grant::ReadWrite(path)
+                } else {
+                    Grant::Read(path)
+                };
+                (grant, until)
+            });
+        let hosts = self
+            .hosts
+            .temporary
+            .live()
+            .into_iter()
+            .map(|(host, until)| (Grant::Host(host), until));
+        let commands = self
+            .command_grants
+            .live()
+            .into_iter()
+            .map(|(command, until)| (Grant::Command(command), until));
+        paths.chain(hosts).chain(commands).collect()
+    }
+
+    /// Withdraw every temporary grant before it lapses.
+    pub fn revoke_temporary(&self) {
+        self.paths.temporary.clear();
+        self.hosts.temporary.clear();
+        self.command_grants.clear();
+        self.audit(AuditEvent::new("safety.grant", "revoked"));
+    }
+
     /// Check if an environment variable is accessible.
     pub fn can_access_env(&self, name: &str) -> bool {
         match &self.env_vars {
@@ -326,7 +1347,9 @@ impl SafetyConfig {
 
         match &self.allowed_commands {
             None => true,
-            Some(allowed) => allowed.contains(command),
+            Some(allowed) => {
+                allowed.contains(command) || self.command_grants.any(|granted| granted == command)
+            }
         }
     }
 
@@ -337,7 +1360,8 @@ impl SafetyConfig {
         }
 
         if let Some(ref allowed) = self.allowed_commands {
-            if !allowed.contains(command) {
+            if !allowed.contains(command) && !self.command_grants.any(|granted| granted == command)
+            {
                 return Err(Error::not_permitted(format!(
                     "command not allowed: {}",
                     command
@@ -348,6 +1372,139 @@ impl SafetyConfig {
         Ok(())
     }
 
+    /// Check a host against the allowlist and resolve it once, for the
+    /// connections of one request.
+    ///
+    /// Unless [`allow_internal_addresses`](Self::allow_internal_addresses)
+    /// is set, the host is rejected if it resolves to an internal address
+    /// that is not itself on the allowlist. IP literals are not resolved.
+    pub fn pin_host<F>(&self, host: &str, resolve: F) -> Result<PinnedHost>
+    where
+        F: FnOnce(&str) -> Result<Vec<IpAddr>>,
+    {
+        self.hosts.check(host)?;
+        let host = host.to_lowercase();
+        let literal = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>();
+        let addresses = match literal {
+            Ok(ip) => vec![ip],
+            Err(_) => resolve(&host)?,
+        };
+        if addresses.is_empty() {
+            return Err(Error::network(format!("could not resolve host: {}", host)));
+        }
+
+        if !self.allow_internal_addresses {
+            let internal = addresses.iter().find(|ip| {
+                is_internal_address(**ip) && !self.hosts.allowed.contains(&ip.to_string())
+            });
+            if let Some(ip) = internal {
+                self.audit(
+                    AuditEvent::new("net.internal_address", "denied")
+                        .with_field("host", host.clone())
+                        .with_field("address", ip.to_string()),
+                );
+                return Err(Error::not_permitted(format!(
+                    "{} resolves to internal address {}",
+                    host, ip
+                )));
+            }
+        }
+        Ok(PinnedHost { host, addresses })
+    }
+
+    /// Check a redirect from a pinned request to `host`.
+    ///
+    /// The target must pass the allowlist. A redirect to the same host
+    /// keeps the original pin and returns `None`; another host is pinned
+    /// afresh with [`pin_host`](Self::pin_host).
+    pub fn check_redirect<F>(
+        &self,
+        pinned: &PinnedHost,
+        host: &str,
+        resolve: F,
+    ) -> Result<Option<PinnedHost>>
+    where
+        F: FnOnce(&str) -> Result<Vec<IpAddr>>,
+    {
+        if host.eq_ignore_ascii_case(&pinned.host) {
+            self.hosts.check(host)?;
+            return Ok(None);
+        }
+        self.pin_host(host, resolve).map(Some)
+    }
+
+    /// Check a Kubernetes operation, returning error if denied.
+    ///
+    /// `namespace` is `None` for cluster-scoped resources.
+    pub fn check_k8s(&self, verb: &str, resource: &str, namespace: Option<&str>) -> Result<()> {
+        if !self.allow_k8s {
+            return Err(Error::not_permitted("kubernetes access not allowed"));
+        }
+        self.k8s.check(verb, resource, namespace)
+    }
+
+    /// Check a Kubernetes operation on a namespaced resource across all
+    /// namespaces, returning error if denied.
+    pub fn check_k8s_all_namespaces(&self, verb: &str, resource: &str) -> Result<()> {
+        if !self.allow_k8s {
+            return Err(Error::not_permitted("kubernetes access not allowed"));
+        }
+        self.k8s.check_all_namespaces(verb, resource)
+    }
+
+    /// Check that Kubernetes access and `verb` are allowed, before the
+    /// resource of an operation is known.
+    pub fn check_k8s_verb(&self, verb: &str) -> Result<()> {
+        if !self.allow_k8s {
+            return Err(Error::not_permitted("kubernetes access not allowed"));
+        }
+        self.k8s.check_verb(verb)
+    }
+
+    /// Check that Kubernetes access is allowed and that the cluster
+    /// registered as `context` may be selected.
+    pub fn check_k8s_context(&self, context: &str) -> Result<()> {
+        if !self.allow_k8s {
+            return Err(Error::not_permitted("kubernetes access not allowed"));
+        }
+        self.k8s.check_context(context)
+    }
+
+    /// Check a full command line for privilege escalation.
+    ///
+    /// Looks through the command and any launcher wrappers in front of it
+    /// (`env`, `nohup`, `sh -c`, ...) for a privilege-escalation program.
+    /// Every attempt is recorded as an audit event, whether or not it is
+    /// permitted.
+    pub fn check_privilege_escalation(&self, command: &str, args: &[String]) -> Result<()> {
+        let Some(wrapper) = find_privilege_escalation(command, args) else {
+            return Ok(());
+        };
+
+        let outcome = if self.allow_privilege_escalation {
+            "allowed"
+        } else {
+            "denied"
+        };
+        self.audit(
+            AuditEvent::new("process.privilege_escalation", outcome)
+                .with_field("command", command)
+                .with_field("args", format!("{:?}", args))
+                .with_field("wrapper", wrapper.as_str()),
+        );
+        if self.allow_privilege_escalation {
+            return Ok(());
+        }
+
+        Err(Error::not_permitted(format!(
+            "privilege escalation not allowed: {}",
+            wrapper
+        )))
+    }
+
     /// Clamp a timeout to the maximum allowed.
     pub fn clamp_timeout(&self, timeout: Duration) -> Duration {
         timeout.min(self.max_timeout)
@@ -391,6 +1548,145 @@ mod tests {
         assert!(!hosts.can_access("other.com"));
     }
 
+    #[test]
+    fn test_k8s_allowlist() {
+        let config = SafetyConfig::new();
+        assert!(config.check_k8s("list", "pods", Some("default")).is_err());
+
+        let config = config.with_allow_k8s(true).with_k8s(
+            K8sAllowlist::all()
+                .with_verbs(["get", "list"])
+                .with_resources(["pods", "namespaces"])
+                .with_namespaces(["apps"]),
+        );
+        assert!(config.check_k8s("list", "pods", Some("apps")).is_ok());
+        assert!(config.check_k8s("list", "namespaces", None).is_ok());
+        assert!(config.check_k8s("delete", "pods", Some("apps")).is_err());
+        assert!(config.check_k8s("get", "secrets", Some("apps")).is_err());
+        assert!(config
+            .check_k8s("get", "pods", Some("kube-system"))
+            .is_err());
+        assert!(config.check_k8s_all_namespaces("list", "pods").is_err());
+        assert!(SafetyConfig::new()
+            .with_allow_k8s(true)
+            .check_k8s_all_namespaces("list", "pods")
+            .is_ok());
+        let wildcard = config.with_k8s(K8sAllowlist::all().with_namespaces(["*"]));
+        assert!(wildcard.check_k8s_all_namespaces("list", "pods").is_ok());
+        assert!(wildcard
+            .check_k8s("list", "pods", Some("kube-system"))
+            .is_ok());
+        assert!(SafetyConfig::strict()
+            .with_allow_k8s(true)
+            .check_k8s("get", "pods", Some("apps"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_k8s_context_allowlist() {
+        let config = SafetyConfig::new().with_allow_k8s(true);
+        assert!(config.check_k8s_context("prod").is_ok());
+
+        let config = config.with_k8s(K8sAllowlist::all().with_contexts(["staging"]));
+        assert!(config.check_k8s_context("staging").is_ok());
+        assert!(config.check_k8s_context("prod").is_err());
+        assert!(config
+            .clone()
+            .with_allow_k8s(false)
+            .check_k8s_context("staging")
+            .is_err());
+        assert!(SafetyConfig::strict()
+            .with_allow_k8s(true)
+            .check_k8s_context("staging")
+            .is_err());
+    }
+
+    #[test]
+    fn test_internal_addresses() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(is_internal_address(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "100.128.0.1", "2606:2800:220:1::"] {
+            assert!(!is_internal_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_pin_host() {
+        let resolve_to = |ip: &'static str| move |_: &str| Ok(vec![ip.parse().unwrap()]);
+        let config = SafetyConfig::new()
+            .with_hosts(
+                HostAllowlist::none()
+                    .allow("api.example.com")
+                    .allow("*.example.org")
+                    .allow("127.0.0.1"),
+            )
+            .with_allow_internal_addresses(false);
+
+        let pinned = config
+            .pin_host("API.example.com", resolve_to("93.184.216.34"))
+            .unwrap();
+        assert_eq!(pinned.host(), "api.example.com");
+        assert_eq!(
+            pinned.addresses(),
+            ["93.184.216.34".parse::<IpAddr>().unwrap()]
+        );
+        assert!(pinned.check_peer("10.0.0.1".parse().unwrap()).is_err());
+
+        // An allowlisted name rebound to an internal address is refused
+        let err = config
+            .pin_host("api.example.com", resolve_to("169.254.169.254"))
+            .unwrap_err();
+        assert!(err.to_string().contains("internal address 169.254.169.254"));
+        assert!(config.pin_host("evil.com", resolve_to("1.1.1.1")).is_err());
+        assert!(config
+            .pin_host("api.example.com", |_: &str| Ok(Vec::new()))
+            .is_err());
+
+        // Literal addresses on the allowlist are not resolved
+        let local = config
+            .pin_host("127.0.0.1", |_: &str| -> Result<Vec<IpAddr>> {
+                unreachable!()
+            })
+            .unwrap();
+        assert_eq!(local.addresses().len(), 1);
+
+        // Same-host redirects keep the pin; others are checked afresh
+        assert_eq!(
+            config
+                .check_redirect(&pinned, "api.example.com", resolve_to("10.0.0.1"))
+                .unwrap(),
+            None
+        );
+        assert!(config
+            .check_redirect(&pinned, "cdn.example.org", resolve_to("10.0.0.1"))
+            .is_err());
+        assert!(config
+            .check_redirect(&pinned, "cdn.example.org", resolve_to("93.184.216.35"))
+            .unwrap()
+            .is_some());
+        assert!(config
+            .check_redirect(&pinned, "evil.com", resolve_to("93.184.216.35"))
+            .is_err());
+
+        let config = config.with_allow_internal_addresses(true);
+        assert!(config
+            .pin_host("api.example.com", resolve_to("10.0.0.1"))
+            .is_ok());
+    }
+
     #[test]
     fn test_safety_config() {
         let config = SafetyConfig::new()
@@ -405,6 +1701,144 @@ mod tests {
         assert!(!config.can_execute("rm"));
     }
 
+    #[test]
+    fn test_privilege_escalation() {
+        let config = SafetyConfig::new()
+            .with_allow_process(true)
+            .with_allowed_commands(["sudo", "env", "sh", "systemctl"]);
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert!(is_privilege_escalation("/usr/bin/sudo"));
+        assert!(is_privilege_escalation("RUNAS.EXE"));
+        assert!(!is_privilege_escalation("systemctl"));
+
+        // Allowlisting sudo itself is not enough.
+        assert!(config
+            .check_privilege_escalation("sudo", &args(&["systemctl", "restart", "nginx"]))
+            .is_err());
+        assert!(config
+            .check_privilege_escalation("env", &args(&["-i", "A=1", "doas", "reboot"]))
+            .is_err());
+        assert!(config
+            .check_privilege_escalation("sh", &args(&["-c", "echo hi && pkexec id"]))
+            .is_err());
+        assert!(config
+            .check_privilege_escalation("systemctl", &args(&["status"]))
+            .is_ok());
+        assert!(config
+            .check_privilege_escalation("sh", &args(&["-c", "echo sudo"]))
+            .is_ok());
+        assert!(config
+            .check_privilege_escalation("timeout", &args(&["-s", "KILL", "5", "echo", "sudo"]))
+            .is_ok());
+        assert!(config
+            .check_privilege_escalation("bash", &args(&["script.sh", "-c", "sudo id"]))
+            .is_ok());
+
+        // Option values, combined shell flags, keywords and `env -S` do not
+        // hide the command.
+        for (command, argv) in [
+            ("nice", &["-n", "10", "sudo", "id"][..]),
+            ("ionice", &["-c", "3", "sudo"]),
+            ("timeout", &["-s", "KILL", "5", "sudo"]),
+            ("timeout", &["--signal=KILL", "5", "sudo"]),
+            ("xargs", &["-I", "{}", "sudo"]),
+            ("stdbuf", &["-o", "L", "nohup", "doas", "id"]),
+            ("bash", &["-lc", "sudo id"]),
+            ("sh", &["-ec", "sudo id"]),
+            ("bash", &["-o", "pipefail", "-c", "true; sudo id"]),
+            ("fish", &["--command", "sudo id"]),
+            ("sh", &["-c", "if true; then sudo id; fi"]),
+            ("sh", &["-c", "{ sudo id; }"]),
+            ("sh", &["-c", "X=1 'sudo' id"]),
+            ("env", &["-S", "sudo id"]),
+            ("env", &["-Ssudo id"]),
+            ("env", &["--split-string=A=1 sudo", "id"]),
+        ] {
+            assert!(
+                config
+                    .check_privilege_escalation(command, &args(argv))
+                    .is_err(),
+                "{} {:?}",
+                command,
+                argv
+            );
+        }
+
+        let config = config.with_allow_privilege_escalation(true);
+        assert!(config
+            .check_privilege_escalation("sudo", &args(&["systemctl", "restart", "nginx"]))
+            .is_ok());
+        assert!(!SafetyConfig::permissive().allow_privilege_escalation);
+    }
+
+    #[test]
+    fn test_temporary_grants() {
+        let sink = Arc::new(MemoryAuditSink::new());
+        let config = SafetyConfig::new()
+            .with_paths(PathAllowlist::none().deny("/data/secret"))
+            .with_allow_process(true)
+            .with_allowed_commands(["ls"])
+            .with_audit_sink(sink.clone());
+        let tenant_copy = config.clone();
+        // Grants made through the caller's copy reach the registry's copy
+        // when they share a store.
+        let store = GrantStore::new();
+        let config = config.with_grant_store(&store);
+        let registry_copy = config
+            .clone()
+            .with_paths(PathAllowlist::none().deny("/data/secret"));
+
+        config.grant_temporary(Grant::ReadWrite("/data".into()), Duration::from_secs(900));
+        config.grant_temporary(
+            Grant::Host("*.example.com".into()),
+            Duration::from_secs(900),
+        );
+        config.grant_temporary(Grant::Command("git".into()), Duration::from_secs(900));
+        config.grant_until(Grant::Read("/old".into()), Instant::now());
+
+        assert!(registry_copy.paths.can_write(Path::new("/data/out.txt")));
+        assert!(!registry_copy.paths.can_read(Path::new("/data/secret/key")));
+        assert!(!registry_copy.paths.can_read(Path::new("/old/file")));
+        assert!(registry_copy.hosts.check("api.example.com").is_ok());
+        assert!(registry_copy.check_execute("git").is_ok());
+        assert!(registry_copy.check_execute("rm").is_err());
+        assert_eq!(registry_copy.temporary_grants().len(), 3);
+        assert!(tenant_copy.temporary_grants().is_empty());
+        assert!(!tenant_copy.paths.can_write(Path::new("/data/out.txt")));
+        assert!(!tenant_copy.hosts.can_access("api.example.com"));
+
+        let events = sink.take();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[2].fields["kind"], "command");
+        assert_eq!(events[2].fields["target"], "git");
+
+        registry_copy.revoke_temporary();
+        assert!(!config.paths.can_read(Path::new("/data/out.txt")));
+        assert!(!config.hosts.can_access("api.example.com"));
+        assert!(!config.can_execute("git"));
+        assert!(config.temporary_grants().is_empty());
+
+        // Without a store, clones are independent snapshots.
+        let snapshot = tenant_copy.clone();
+        tenant_copy.grant_temporary(Grant::Command("ls".into()), Duration::from_secs(60));
+        assert!(snapshot.temporary_grants().is_empty());
+        assert_eq!(tenant_copy.clone().temporary_grants().len(), 1);
+
+        // Process execution stays a master switch.
+        let config = SafetyConfig::new().with_allowed_commands(["ls"]);
+        config.grant_temporary(Grant::Command("git".into()), Duration::from_secs(60));
+        assert!(config.check_execute("git").is_err());
+    }
+
+    #[test]
+    fn test_is_secret_key() {
+        assert!(is_secret_key("password"));
+        assert!(is_secret_key("X-API-Token"));
+        assert!(is_secret_key("Authorization"));
+        assert!(!is_secret_key("path"));
+    }
+
     #[test]
     fn test_timeout_clamping() {
         let config = SafetyConfig::new().with_max_timeout(Duration::from_secs(60));
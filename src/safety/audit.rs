@@ -0,0 +1,362 @@
+//! Audit log of safety-relevant operations.
+//!
+//! An [`AuditLog`] installed with
+//! [`SafetyConfig::with_audit_log`](super::SafetyConfig::with_audit_log)
+//! receives the configuration's audit events, and the
+//! [`StdlibRegistry`](crate::StdlibRegistry) also records every call to an
+//! [audited module](AUDITED_MODULES) in it:
+//!
+//! - `action`: the function, e.g. `"fs.write"`
+//! - `outcome`: `"allowed"`, `"denied"` when a safety check refused it, or
+//!   `"error"` when it was allowed but failed
+//! - `context_id`: the engine ID of the calling context
+//! - `fields.args_hash`: [`args_hash`] of the arguments, so calls can be
+//!   correlated without storing paths, URLs or payloads
+//!
+//! The log keeps the most recent events in memory for [`AuditLog::query`]
+//! and forwards every event to its sinks, such as a [`JsonlAuditSink`] file
+//! or a [`CallbackAuditSink`].
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::{Mutex, RwLock};
+
+use fusabi_host::Value;
+
+use super::{AuditEvent, AuditSink};
+
+/// Modules whose host function calls are recorded in an [`AuditLog`].
+pub const AUDITED_MODULES: &[&str] = &[
+    "env",
+    "fs",
+    "fs_stream",
+    "k8s",
+    "net",
+    "net_http",
+    "process",
+];
+
+/// Number of events an [`AuditLog`] keeps for queries by default.
+pub const DEFAULT_AUDIT_CAPACITY: usize = 1024;
+
+/// Hash of host function arguments: FNV-1a (64-bit) of their JSON
+/// encoding, as 16 hex digits.
+///
+/// Stable across runs and platforms, so it identifies repeated calls with
+/// the same arguments. It is not a cryptographic digest.
+pub fn args_hash(args: &[Value]) -> String {
+    fnv1a_hex(&Value::List(args.to_vec()).to_json_string())
+}
+
+/// FNV-1a (64-bit) of `text`, as 16 hex digits.
+pub(crate) fn fnv1a_hex(text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// Selects events from [`AuditLog::query`].
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    /// Action to match: exact (`"fs.write"`), or a module when it ends in
+    /// a dot (`"fs."`).
+    pub action: Option<String>,
+    /// Outcome to match, e.g. `"denied"`.
+    pub outcome: Option<String>,
+    /// Engine ID of the context that caused the event.
+    pub context_id: Option<u64>,
+    /// Only events at or after this timestamp (nanoseconds since epoch).
+    pub since_ns: Option<u64>,
+    /// Keep only the newest this many matches.
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    /// Create a query matching every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match an action, or a module with a trailing dot.
+    pub fn with_action(mut self, action: impl Into<String>) -> Self {
+        self.action = Some(action.into());
+        self
+    }
+
+    /// Match an outcome.
+    pub fn with_outcome(mut self, outcome: impl Into<String>) -> Self {
+        self.outcome = Some(outcome.into());
+        self
+    }
+
+    /// Match a context.
+    pub fn with_context_id(mut self, id: u64) -> Self {
+        self.context_id = Some(id);
+        self
+    }
+
+    /// Match events at or after a timestamp.
+    pub fn with_since_ns(mut self, since_ns: u64) -> Self {
+        self.since_ns = Some(since_ns);
+        self
+    }
+
+    /// Keep only the newest `limit` matches.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Whether an event passes the query, ignoring `limit`.
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        let action = match self.action.as_deref() {
+            None => true,
+            Some(module) if module.ends_with('.') => event.action.starts_with(module),
+            Some(action) => event.action == action,
+        };
+        action
+            && self
+                .outcome
+                .as_deref()
+                .map_or(true, |outcome| event.outcome == outcome)
+            && self
+                .context_id
+                .map_or(true, |id| event.context_id == Some(id))
+            && self
+                .since_ns
+                .map_or(true, |since| event.timestamp_ns >= since)
+    }
+}
+
+/// Audit sink that keeps recent events for queries and forwards every
+/// event to further sinks.
+pub struct AuditLog {
+    recent: Mutex<VecDeque<AuditEvent>>,
+    capacity: usize,
+    sinks: RwLock<Vec<Arc<dyn AuditSink>>>,
+}
+
+impl std::fmt::Debug for AuditLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuditLog")
+            .field("len", &self.len())
+            .field("capacity", &self.capacity)
+            .field("sinks", &self.sinks.read().len())
+            .finish()
+    }
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditLog {
+    /// Create a log keeping the last [`DEFAULT_AUDIT_CAPACITY`] events and
+    /// forwarding nowhere.
+    pub fn new() -> Self {
+        Self::with_capacity(DEFAULT_AUDIT_CAPACITY)
+    }
+
+    /// Create a log keeping the last `capacity` events (0 keeps none).
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            recent: Mutex::new(VecDeque::new()),
+            capacity,
+            sinks: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Forward events to `sink`.
+    pub fn with_sink(self, sink: Arc<dyn AuditSink>) -> Self {
+        self.add_sink(sink);
+        self
+    }
+
+    /// Forward events to `sink` from now on.
+    pub fn add_sink(&self, sink: Arc<dyn AuditSink>) {
+        self.sinks.write().push(sink);
+    }
+
+    /// Number of events kept.
+    pub fn len(&self) -> usize {
+        self.recent.lock().len()
+    }
+
+    /// Whether no events are kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Kept events matching `query`, oldest first.
+    pub fn query(&self, query: &AuditQuery) -> Vec<AuditEvent> {
+        let recent = self.recent.lock();
+        let mut events: Vec<AuditEvent> = recent
+            .iter()
+            .filter(|event| query.matches(event))
+            .cloned()
+            .collect();
+        if let Some(limit) = query.limit {
+            events.drain(..events.len().saturating_sub(limit));
+        }
+        events
+    }
+
+    /// Drop the kept events. Sinks are unaffected.
+    pub fn clear(&self) {
+        self.recent.lock().clear();
+    }
+}
+
+impl AuditSink for AuditLog {
+    fn record(&self, event: &AuditEvent) {
+        if self.capacity > 0 {
+            let mut recent = self.recent.lock();
+            while recent.len() >= self.capacity {
+                recent.pop_front();
+            }
+            recent.push_back(event.clone());
+        }
+        for sink in self.sinks.read().iter() {
+            sink.record(event);
+        }
+    }
+}
+
+/// Audit sink that appends events to a file, one JSON object per line in
+/// the shape of [`AuditEvent::to_value`].
+///
+/// The file is chosen by the host, not a script, so it is not checked
+/// against the path allowlist. Write failures are reported through
+/// `tracing`, since recording cannot fail the audited operation.
+#[derive(Debug)]
+pub struct JsonlAuditSink {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl JsonlAuditSink {
+    /// Open `path` for appending, creating it if missing.
+    pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AuditSink for JsonlAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        let mut line = event.to_value().to_json_string();
+        line.push('\n');
+        let mut file = self.file.lock();
+        if let Err(e) = file.write_all(line.as_bytes()).and_then(|_| file.flush()) {
+            tracing::error!(
+                target: "fusabi::audit",
+                path = %self.path.display(),
+                "failed to write audit event: {}",
+                e
+            );
+        }
+    }
+}
+
+type AuditCallback = Box<dyn Fn(&AuditEvent) + Send + Sync>;
+
+/// Audit sink that calls a function with each event.
+pub struct CallbackAuditSink {
+    callback: AuditCallback,
+}
+
+impl std::fmt::Debug for CallbackAuditSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CallbackAuditSink")
+    }
+}
+
+impl CallbackAuditSink {
+    /// Create a sink calling `callback`.
+    pub fn new(callback: impl Fn(&AuditEvent) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Box::new(callback),
+        }
+    }
+}
+
+impl AuditSink for CallbackAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        (self.callback)(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_audit_log_query_and_sinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let log = AuditLog::with_capacity(3)
+            .with_sink(Arc::new(JsonlAuditSink::open(&path).unwrap()))
+            .with_sink(Arc::new(CallbackAuditSink::new(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })));
+
+        log.record(&AuditEvent::new("env.get", "allowed").with_context_id(1));
+        log.record(&AuditEvent::new("fs.read", "allowed").with_context_id(1));
+        log.record(&AuditEvent::new("fs.write", "denied").with_context_id(2));
+        log.record(&AuditEvent::new("fs.remove", "allowed").with_context_id(2));
+
+        // The oldest event was evicted from memory but reached the sinks.
+        assert_eq!(log.len(), 3);
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+        let lines = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(lines.lines().count(), 4);
+        let first = Value::from_json_str(lines.lines().next().unwrap()).unwrap();
+        assert_eq!(
+            first.as_map().unwrap()["action"],
+            Value::String("env.get".into())
+        );
+
+        let fs = log.query(&AuditQuery::new().with_action("fs."));
+        assert_eq!(fs.len(), 3);
+        let denied = log.query(&AuditQuery::new().with_outcome("denied"));
+        assert_eq!(denied[0].action, "fs.write");
+        let newest = log.query(&AuditQuery::new().with_context_id(2).with_limit(1));
+        assert_eq!(newest[0].action, "fs.remove");
+        assert!(log.query(&AuditQuery::new().with_action("fs")).is_empty());
+
+        log.clear();
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn test_args_hash() {
+        let a = args_hash(&[Value::String("/tmp/a".into())]);
+        assert_eq!(a.len(), 16);
+        assert_eq!(a, args_hash(&[Value::String("/tmp/a".into())]));
+        assert_ne!(a, args_hash(&[Value::String("/tmp/b".into())]));
+    }
+}
@@ -8,9 +8,22 @@
 //! - `sigilforge.get_token(service, account)` - Get an OAuth access token
 //! - `sigilforge.resolve(auth_uri)` - Resolve an auth:// URI to its secret value
 //! - `sigilforge.is_available()` - Check if the Sigilforge daemon is available
+//! - `sigilforge.release()` - Drop every token leased by the calling context
+//!
+//! # Leases
+//!
+//! Tokens and secrets fetched by a script are leased to its
+//! [`ExecutionContext`] (keyed by `engine_id`) and served from that lease
+//! until they expire. Hosts call [`release_context`] (or hold a
+//! [`ContextLease`] guard) when the context ends so nothing outlives it.
+//! [`set_max_token_requests`] caps how many daemon requests one context may
+//! make, limiting the blast radius of a misbehaving script.
 
 use fusabi_host::{ExecutionContext, Result, Value};
-use sigilforge_client::{SigilforgeClient, TokenProvider};
+use parking_lot::Mutex;
+use sigilforge_client::{AccessToken, SigilforgeClient, TokenProvider};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::OnceLock;
 
 // Global client instance - created lazily on first use
@@ -20,6 +33,168 @@ fn get_client() -> &'static SigilforgeClient {
     CLIENT.get_or_init(SigilforgeClient::new)
 }
 
+// Per-context leases, keyed by `ExecutionContext::engine_id`.
+static LEASES: OnceLock<Mutex<HashMap<u64, ContextLeases>>> = OnceLock::new();
+
+// Daemon requests allowed per context; 0 means unlimited.
+static MAX_TOKEN_REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Default)]
+struct ContextLeases {
+    requests: usize,
+    tokens: HashMap<(String, String), AccessToken>,
+    secrets: HashMap<String, String>,
+}
+
+impl ContextLeases {
+    fn len(&self) -> usize {
+        self.tokens.len() + self.secrets.len()
+    }
+}
+
+fn leases() -> &'static Mutex<HashMap<u64, ContextLeases>> {
+    LEASES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Set the maximum number of daemon requests a single context may make.
+///
+/// `None` removes the limit. Requests served from a context's existing
+/// leases do not count against the quota.
+pub fn set_max_token_requests(max: Option<usize>) {
+    MAX_TOKEN_REQUESTS.store(max.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// Get the current per-context request quota.
+pub fn max_token_requests() -> Option<usize> {
+    match MAX_TOKEN_REQUESTS.load(Ordering::Relaxed) {
+        0 => None,
+        max => Some(max),
+    }
+}
+
+/// Number of daemon requests made so far by a context.
+pub fn token_requests(engine_id: u64) -> usize {
+    leases()
+        .lock()
+        .get(&engine_id)
+        .map(|l| l.requests)
+        .unwrap_or(0)
+}
+
+/// Service/account pairs currently leased to a context.
+pub fn leased_tokens(engine_id: u64) -> Vec<(String, String)> {
+    let mut pairs: Vec<_> = leases()
+        .lock()
+        .get(&engine_id)
+        .map(|l| l.tokens.keys().cloned().collect())
+        .unwrap_or_default();
+    pairs.sort();
+    pairs
+}
+
+/// Drop all tokens and secrets leased to a context and reset its quota.
+///
+/// Returns the number of leases that were released.
+pub fn release_context(engine_id: u64) -> usize {
+    leases()
+        .lock()
+        .remove(&engine_id)
+        .map(|l| l.len())
+        .unwrap_or(0)
+}
+
+/// Guard that releases a context's leases when dropped.
+///
+/// ```ignore
+/// let _lease = ContextLease::new(&ctx);
+/// engine.run(&ctx)?;
+/// // leases released here
+/// ```
+#[derive(Debug)]
+pub struct ContextLease {
+    engine_id: u64,
+}
+
+impl ContextLease {
+    /// Create a guard for the given context.
+    pub fn new(ctx: &ExecutionContext) -> Self {
+        Self {
+            engine_id: ctx.engine_id,
+        }
+    }
+
+    /// Engine ID whose leases this guard owns.
+    pub fn engine_id(&self) -> u64 {
+        self.engine_id
+    }
+}
+
+impl Drop for ContextLease {
+    fn drop(&mut self) {
+        let released = release_context(self.engine_id);
+        if released > 0 {
+            tracing::debug!(
+                "sigilforge: released {} lease(s) for context {}",
+                released,
+                self.engine_id
+            );
+        }
+    }
+}
+
+/// Count a daemon request against the context's quota.
+fn charge(engine_id: u64, function: &str) -> Result<()> {
+    let max = max_token_requests();
+    let mut table = leases().lock();
+    let entry = table.entry(engine_id).or_default();
+    if let Some(max) = max {
+        if entry.requests >= max {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: token request quota exceeded ({} per context)",
+                function, max
+            )));
+        }
+    }
+    entry.requests += 1;
+    Ok(())
+}
+
+fn leased_token(engine_id: u64, service: &str, account: &str) -> Option<AccessToken> {
+    let table = leases().lock();
+    let token = table
+        .get(&engine_id)?
+        .tokens
+        .get(&(service.to_string(), account.to_string()))?;
+    (!token.is_expired()).then(|| token.clone())
+}
+
+fn lease_token(engine_id: u64, service: &str, account: &str, token: AccessToken) {
+    leases()
+        .lock()
+        .entry(engine_id)
+        .or_default()
+        .tokens
+        .insert((service.to_string(), account.to_string()), token);
+}
+
+fn leased_secret(engine_id: u64, reference: &str) -> Option<String> {
+    leases()
+        .lock()
+        .get(&engine_id)?
+        .secrets
+        .get(reference)
+        .cloned()
+}
+
+fn lease_secret(engine_id: u64, reference: &str, value: String) {
+    leases()
+        .lock()
+        .entry(engine_id)
+        .or_default()
+        .secrets
+        .insert(reference.to_string(), value);
+}
+
 /// Get an OAuth access token for a service/account.
 ///
 /// # Arguments
@@ -27,13 +202,14 @@ fn get_client() -> &'static SigilforgeClient {
 /// - `args[1]`: Account name (string, e.g., "personal")
 ///
 /// # Returns
-/// The access token as a string.
+/// The access token as a string. A valid token already leased to the
+/// calling context is returned without contacting the daemon.
 ///
 /// # Example (Fusabi script)
 /// ```fsharp
 /// let! token = Sigilforge.getToken "spotify" "personal"
 /// ```
-pub fn get_token(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+pub fn get_token(args: &[Value], ctx: &ExecutionContext) -> Result<Value> {
     let service = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
         fusabi_host::Error::host_function("sigilforge.get_token: service must be a string")
     })?;
@@ -42,14 +218,23 @@ pub fn get_token(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
         fusabi_host::Error::host_function("sigilforge.get_token: account must be a string")
     })?;
 
+    if let Some(token) = leased_token(ctx.engine_id, service, account) {
+        return Ok(Value::String(token.token));
+    }
+
     // Get the tokio runtime handle
     let rt = tokio::runtime::Handle::try_current()
         .map_err(|_| fusabi_host::Error::runtime("no tokio runtime available"))?;
 
+    charge(ctx.engine_id, "sigilforge.get_token")?;
     let result = rt.block_on(async { get_client().get_token(service, account).await });
 
     match result {
-        Ok(token) => Ok(Value::String(token.token)),
+        Ok(token) => {
+            let value = token.token.clone();
+            lease_token(ctx.engine_id, service, account, token);
+            Ok(Value::String(value))
+        }
         Err(e) => Err(fusabi_host::Error::runtime(e.to_string())),
     }
 }
@@ -61,8 +246,9 @@ pub fn get_token(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 /// - `args[1]`: Account name (string)
 ///
 /// # Returns
-/// A fresh access token as a string.
-pub fn ensure_token(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+/// A fresh access token as a string. Always contacts the daemon and
+/// replaces the context's existing lease.
+pub fn ensure_token(args: &[Value], ctx: &ExecutionContext) -> Result<Value> {
     let service = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
         fusabi_host::Error::host_function("sigilforge.ensure_token: service must be a string")
     })?;
@@ -74,10 +260,15 @@ pub fn ensure_token(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
     let rt = tokio::runtime::Handle::try_current()
         .map_err(|_| fusabi_host::Error::runtime("no tokio runtime available"))?;
 
+    charge(ctx.engine_id, "sigilforge.ensure_token")?;
     let result = rt.block_on(async { get_client().ensure_token(service, account).await });
 
     match result {
-        Ok(token) => Ok(Value::String(token.token)),
+        Ok(token) => {
+            let value = token.token.clone();
+            lease_token(ctx.engine_id, service, account, token);
+            Ok(Value::String(value))
+        }
         Err(e) => Err(fusabi_host::Error::runtime(e.to_string())),
     }
 }
@@ -94,18 +285,26 @@ pub fn ensure_token(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 /// ```fsharp
 /// let! apiKey = Sigilforge.resolve "auth://openai/default/api_key"
 /// ```
-pub fn resolve(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+pub fn resolve(args: &[Value], ctx: &ExecutionContext) -> Result<Value> {
     let reference = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
         fusabi_host::Error::host_function("sigilforge.resolve: reference must be a string")
     })?;
 
+    if let Some(value) = leased_secret(ctx.engine_id, reference) {
+        return Ok(Value::String(value));
+    }
+
     let rt = tokio::runtime::Handle::try_current()
         .map_err(|_| fusabi_host::Error::runtime("no tokio runtime available"))?;
 
+    charge(ctx.engine_id, "sigilforge.resolve")?;
     let result = rt.block_on(async { get_client().resolve(reference).await });
 
     match result {
-        Ok(secret) => Ok(Value::String(secret.value)),
+        Ok(secret) => {
+            lease_secret(ctx.engine_id, reference, secret.value.clone());
+            Ok(Value::String(secret.value))
+        }
         Err(e) => Err(fusabi_host::Error::runtime(e.to_string())),
     }
 }
@@ -129,3 +328,78 @@ pub fn is_available(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
 
     Ok(Value::Bool(available))
 }
+
+/// Release every token and secret leased to the calling context.
+///
+/// # Returns
+/// The number of leases released.
+pub fn release(_args: &[Value], ctx: &ExecutionContext) -> Result<Value> {
+    Ok(Value::Int(release_context(ctx.engine_id) as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Engine IDs are unique per test so the shared lease table does not
+    // leak state between tests running in parallel.
+
+    #[test]
+    fn test_leases_released_with_context() {
+        let engine_id = 9_101;
+        lease_token(engine_id, "spotify", "personal", AccessToken::bearer("t1"));
+        lease_secret(engine_id, "auth://openai/default/api_key", "sk".into());
+
+        assert_eq!(
+            leased_token(engine_id, "spotify", "personal").map(|t| t.token),
+            Some("t1".to_string())
+        );
+        assert_eq!(
+            leased_tokens(engine_id),
+            vec![("spotify".to_string(), "personal".to_string())]
+        );
+        assert!(leased_token(engine_id + 1, "spotify", "personal").is_none());
+
+        {
+            let _lease = ContextLease { engine_id };
+        }
+        assert!(leased_token(engine_id, "spotify", "personal").is_none());
+        assert!(leased_secret(engine_id, "auth://openai/default/api_key").is_none());
+        assert_eq!(release_context(engine_id), 0);
+    }
+
+    #[test]
+    fn test_expired_lease_not_served() {
+        let engine_id = 9_102;
+        let expired = AccessToken::bearer("old")
+            .with_expiry(chrono::Utc::now() - chrono::Duration::seconds(1));
+        lease_token(engine_id, "github", "work", expired);
+
+        assert!(leased_token(engine_id, "github", "work").is_none());
+        assert_eq!(release_context(engine_id), 1);
+    }
+
+    #[test]
+    fn test_token_request_quota() {
+        let engine_id = 9_103;
+        set_max_token_requests(Some(2));
+        assert_eq!(max_token_requests(), Some(2));
+
+        assert!(charge(engine_id, "sigilforge.get_token").is_ok());
+        assert!(charge(engine_id, "sigilforge.get_token").is_ok());
+        let err = charge(engine_id, "sigilforge.get_token").unwrap_err();
+        assert!(err.to_string().contains("quota exceeded"));
+        assert_eq!(token_requests(engine_id), 2);
+
+        // Other contexts have their own budget.
+        assert!(charge(engine_id + 1, "sigilforge.resolve").is_ok());
+
+        // Releasing the context resets its quota.
+        release_context(engine_id);
+        release_context(engine_id + 1);
+        assert_eq!(token_requests(engine_id), 0);
+
+        set_max_token_requests(None);
+        assert_eq!(max_token_requests(), None);
+    }
+}
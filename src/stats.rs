@@ -0,0 +1,401 @@
+//! Statistics module.
+//!
+//! Summary statistics and small numeric helpers that monitoring scripts
+//! would otherwise compute element by element in script code:
+//!
+//! ```text
+//! stats.mean(latencies)                   // 12.5
+//! stats.percentile(latencies, [50, 99])   // [11.0, 48.2]
+//! stats.linear_regression(times, disk)    // {slope, intercept, r2}
+//! math.clamp(x, 0, 100)
+//! math.round_to(1.23456, 2)               // 1.23
+//! ```
+//!
+//! Lists may mix integers and floats; any other element, or NaN, is an
+//! error. Percentiles interpolate linearly between the closest ranks, as
+//! NumPy and most spreadsheets do by default.
+
+use std::collections::HashMap;
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+/// Read `args[index]` as a non-empty list of numbers.
+fn numbers(args: &[Value], index: usize, function: &str) -> Result<Vec<f64>> {
+    let list = match args.get(index) {
+        Some(Value::List(list)) => list,
+        _ => {
+            return Err(Error::host_function(format!(
+                "{}: expected a list of numbers",
+                function
+            )))
+        }
+    };
+    if list.is_empty() {
+        return Err(Error::host_function(format!("{}: empty list", function)));
+    }
+    list.iter()
+        .enumerate()
+        .map(|(i, value)| {
+            value.as_float().filter(|n| !n.is_nan()).ok_or_else(|| {
+                Error::host_function(format!("{}: element {} is not a number", function, i))
+            })
+        })
+        .collect()
+}
+
+fn number(args: &[Value], index: usize, name: &str, function: &str) -> Result<f64> {
+    args.get(index)
+        .and_then(|v| v.as_float())
+        .filter(|n| !n.is_nan())
+        .ok_or_else(|| Error::host_function(format!("{}: {} must be a number", function, name)))
+}
+
+fn mean_of(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Percentile `p` (0 - 100) of sorted values, interpolating between ranks.
+fn percentile_of(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// Arithmetic mean of a list.
+///
+/// # Arguments
+///
+/// * `args[0]` - List of numbers
+///
+/// # Returns
+///
+/// Mean as a float
+pub fn mean(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let values = numbers(args, 0, "stats.mean")?;
+    Ok(Value::Float(mean_of(&values)))
+}
+
+/// Median of a list; the mean of the middle two for even lengths.
+///
+/// # Arguments
+///
+/// * `args[0]` - List of numbers
+///
+/// # Returns
+///
+/// Median as a float
+pub fn median(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let mut values = numbers(args, 0, "stats.median")?;
+    let even = values.len() % 2 == 0;
+    let mid = values.len() / 2;
+    let (lower, upper, _) = values.select_nth_unstable_by(mid, f64::total_cmp);
+    let median = if even {
+        // The other middle value is the largest of the lower half.
+        let below = lower.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        (below + *upper) / 2.0
+    } else {
+        *upper
+    };
+    Ok(Value::Float(median))
+}
+
+/// Standard deviation of a list.
+///
+/// # Arguments
+///
+/// * `args[0]` - List of numbers
+/// * `args[1]` - Sample standard deviation, dividing by n - 1 (bool,
+///   optional, default false: population, dividing by n)
+///
+/// # Returns
+///
+/// Standard deviation as a float
+pub fn stddev(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "stats.stddev";
+    let values = numbers(args, 0, FUNCTION)?;
+    let sample = match args.get(1) {
+        None | Some(Value::Null) => false,
+        Some(v) => v
+            .as_bool()
+            .ok_or_else(|| Error::host_function(format!("{}: sample must be a bool", FUNCTION)))?,
+    };
+    let divisor = if sample {
+        if values.len() < 2 {
+            return Err(Error::host_function(format!(
+                "{}: a sample needs at least two values",
+                FUNCTION
+            )));
+        }
+        values.len() - 1
+    } else {
+        values.len()
+    };
+
+    let mean = mean_of(&values);
+    let squares: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+    Ok(Value::Float((squares / divisor as f64).sqrt()))
+}
+
+/// Percentile of a list.
+///
+/// # Arguments
+///
+/// * `args[0]` - List of numbers
+/// * `args[1]` - Percentile from 0 to 100, or a list of them
+///
+/// # Returns
+///
+/// The percentile as a float, or a list of them when given a list; the
+/// input is sorted once for all of them
+pub fn percentile(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "stats.percentile";
+    let mut values = numbers(args, 0, FUNCTION)?;
+    values.sort_unstable_by(f64::total_cmp);
+
+    let check = |p: Option<f64>| {
+        p.filter(|p| (0.0..=100.0).contains(p)).ok_or_else(|| {
+            Error::host_function(format!(
+                "{}: percentile must be a number from 0 to 100",
+                FUNCTION
+            ))
+        })
+    };
+    match args.get(1) {
+        Some(Value::List(ps)) => ps
+            .iter()
+            .map(|p| Ok(Value::Float(percentile_of(&values, check(p.as_float())?))))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::List),
+        p => Ok(Value::Float(percentile_of(
+            &values,
+            check(p.and_then(|p| p.as_float()))?,
+        ))),
+    }
+}
+
+/// Least-squares line through points.
+///
+/// # Arguments
+///
+/// * `args[0]` - List of x values
+/// * `args[1]` - List of y values, the same length
+///
+/// # Returns
+///
+/// Map with `slope`, `intercept` and `r2` (coefficient of determination;
+/// 1.0 when all y values are equal)
+pub fn linear_regression(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "stats.linear_regression";
+    let xs = numbers(args, 0, FUNCTION)?;
+    let ys = numbers(args, 1, FUNCTION)?;
+    if xs.len() != ys.len() {
+        return Err(Error::host_function(format!(
+            "{}: xs has {} values but ys has {}",
+            FUNCTION,
+            xs.len(),
+            ys.len()
+        )));
+    }
+
+    let (mean_x, mean_y) = (mean_of(&xs), mean_of(&ys));
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(&ys) {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        sxx += dx * dx;
+        sxy += dx * dy;
+        syy += dy * dy;
+    }
+    if sxx == 0.0 {
+        return Err(Error::host_function(format!(
+            "{}: xs must contain at least two distinct values",
+            FUNCTION
+        )));
+    }
+    let slope = sxy / sxx;
+    let r2 = if syy == 0.0 {
+        1.0
+    } else {
+        sxy * sxy / (sxx * syy)
+    };
+
+    let mut result = HashMap::new();
+    result.insert("slope".to_string(), Value::Float(slope));
+    result.insert(
+        "intercept".to_string(),
+        Value::Float(mean_y - slope * mean_x),
+    );
+    result.insert("r2".to_string(), Value::Float(r2));
+    Ok(Value::Map(result))
+}
+
+/// Limit a number to a range.
+///
+/// # Arguments
+///
+/// * `args[0]` - Number
+/// * `args[1]` - Minimum
+/// * `args[2]` - Maximum
+///
+/// # Returns
+///
+/// The number, or the nearest bound; an integer when all three are
+/// integers
+pub fn clamp(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "math.clamp";
+    if let [Value::Int(x), Value::Int(min), Value::Int(max), ..] = args {
+        if min > max {
+            return Err(Error::host_function(format!(
+                "{}: min {} is greater than max {}",
+                FUNCTION, min, max
+            )));
+        }
+        return Ok(Value::Int(*x.clamp(min, max)));
+    }
+
+    let x = number(args, 0, "value", FUNCTION)?;
+    let min = number(args, 1, "min", FUNCTION)?;
+    let max = number(args, 2, "max", FUNCTION)?;
+    if min > max {
+        return Err(Error::host_function(format!(
+            "{}: min {} is greater than max {}",
+            FUNCTION, min, max
+        )));
+    }
+    Ok(Value::Float(x.clamp(min, max)))
+}
+
+/// Round a number to a number of decimal places.
+///
+/// # Arguments
+///
+/// * `args[0]` - Number
+/// * `args[1]` - Decimal places (integer, optional, default 0); negative
+///   values round to tens, hundreds, ...
+///
+/// # Returns
+///
+/// The rounded number as a float, halves rounded away from zero
+pub fn round_to(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "math.round_to";
+    let x = number(args, 0, "value", FUNCTION)?;
+    let places = match args.get(1) {
+        None | Some(Value::Null) => 0,
+        Some(v) => v
+            .as_int()
+            .filter(|p| (-15..=15).contains(p))
+            .ok_or_else(|| {
+                Error::host_function(format!(
+                    "{}: places must be an integer from -15 to 15",
+                    FUNCTION
+                ))
+            })?,
+    };
+
+    let factor = 10f64.powi(places as i32);
+    let rounded = (x * factor).round() / factor;
+    // Scaling overflows for huge values, which have no fractional part
+    // anyway.
+    Ok(Value::Float(if rounded.is_finite() { rounded } else { x }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    fn list(values: &[f64]) -> Value {
+        Value::List(values.iter().map(|v| Value::Float(*v)).collect())
+    }
+
+    fn float(value: Value) -> f64 {
+        value.as_float().unwrap()
+    }
+
+    #[test]
+    fn test_summary_statistics() {
+        let ctx = create_test_ctx();
+        let data = [Value::List(vec![
+            Value::Int(2),
+            Value::Int(4),
+            Value::Int(4),
+            Value::Int(4),
+            Value::Float(5.0),
+            Value::Int(5),
+            Value::Int(7),
+            Value::Int(9),
+        ])];
+
+        assert_eq!(float(mean(&data, &ctx).unwrap()), 5.0);
+        assert_eq!(float(median(&data, &ctx).unwrap()), 4.5);
+        assert_eq!(float(median(&[list(&[3.0, 1.0, 2.0])], &ctx).unwrap()), 2.0);
+        assert_eq!(float(stddev(&data, &ctx).unwrap()), 2.0);
+        let sample = float(stddev(&[data[0].clone(), Value::Bool(true)], &ctx).unwrap());
+        assert!((sample - 2.138).abs() < 1e-3);
+
+        let data = list(&[15.0, 20.0, 35.0, 40.0, 50.0]);
+        assert_eq!(
+            float(percentile(&[data.clone(), Value::Int(40)], &ctx).unwrap()),
+            29.0
+        );
+        assert_eq!(
+            percentile(&[data.clone(), list(&[0.0, 100.0])], &ctx).unwrap(),
+            list(&[15.0, 50.0])
+        );
+        assert!(percentile(&[data, Value::Int(101)], &ctx).is_err());
+
+        assert!(mean(&[Value::List(vec![])], &ctx).is_err());
+        assert!(mean(&[list(&[1.0, f64::NAN])], &ctx).is_err());
+        assert!(mean(&[Value::List(vec![Value::String("1".into())])], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_linear_regression() {
+        let ctx = create_test_ctx();
+        let Value::Map(fit) = linear_regression(
+            &[list(&[1.0, 2.0, 3.0, 4.0]), list(&[3.0, 5.0, 7.0, 9.0])],
+            &ctx,
+        )
+        .unwrap() else {
+            panic!("expected a map");
+        };
+        assert_eq!(fit["slope"], Value::Float(2.0));
+        assert_eq!(fit["intercept"], Value::Float(1.0));
+        assert_eq!(fit["r2"], Value::Float(1.0));
+
+        assert!(linear_regression(&[list(&[1.0, 2.0]), list(&[1.0])], &ctx).is_err());
+        assert!(linear_regression(&[list(&[1.0, 1.0]), list(&[1.0, 2.0])], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_math() {
+        let ctx = create_test_ctx();
+        assert_eq!(
+            clamp(&[Value::Int(150), Value::Int(0), Value::Int(100)], &ctx).unwrap(),
+            Value::Int(100)
+        );
+        assert_eq!(
+            clamp(&[Value::Float(-0.5), Value::Int(0), Value::Int(1)], &ctx).unwrap(),
+            Value::Float(0.0)
+        );
+        assert!(clamp(&[Value::Int(1), Value::Int(5), Value::Int(0)], &ctx).is_err());
+
+        assert_eq!(
+            round_to(&[Value::Float(1.23456), Value::Int(2)], &ctx).unwrap(),
+            Value::Float(1.23)
+        );
+        assert_eq!(
+            round_to(&[Value::Int(1250), Value::Int(-2)], &ctx).unwrap(),
+            Value::Float(1300.0)
+        );
+        assert_eq!(
+            round_to(&[Value::Float(2.5)], &ctx).unwrap(),
+            Value::Float(3.0)
+        );
+    }
+}
@@ -0,0 +1,597 @@
+//! System module.
+//!
+//! Reads host metrics through `sysinfo`, so monitoring scripts do not need
+//! process permissions to shell out to `top`, `free` or `df`:
+//!
+//! ```text
+//! system.cpu_percent()                  // 12.5
+//! system.memory()                       // {total, used, available, ...}
+//! system.disks()                        // [{mount_point, total, available, ...}]
+//! system.load_avg()                     // {one, five, fifteen}
+//! system.uptime()                       // seconds since boot
+//! system.processes({name: "postgres", sort: "memory", limit: 5})
+//! ```
+//!
+//! CPU usage is measured between two refreshes. The first call waits
+//! [`MINIMUM_CPU_UPDATE_INTERVAL`] to take its second sample; later calls
+//! report usage since the previous call, so a script polling every few
+//! seconds gets the average over each interval. Process CPU usage works the
+//! same way.
+//!
+//! Process entries carry the name, not the command line or environment,
+//! which may hold secrets.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::Instant;
+
+use parking_lot::Mutex;
+use sysinfo::{Disks, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+pub use sysinfo::MINIMUM_CPU_UPDATE_INTERVAL;
+
+/// Default number of entries returned by `system.processes`.
+pub const DEFAULT_PROCESS_LIMIT: usize = 50;
+
+/// Shared `sysinfo` state, kept between calls so CPU usage can be measured
+/// against the previous refresh.
+#[derive(Debug)]
+pub struct SystemMonitor {
+    system: Mutex<System>,
+    cpu_refreshed: Mutex<Option<Instant>>,
+    processes_refreshed: Mutex<Option<Instant>>,
+}
+
+impl Default for SystemMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemMonitor {
+    /// Create a monitor; nothing is read until the first call.
+    pub fn new() -> Self {
+        Self {
+            system: Mutex::new(System::new()),
+            cpu_refreshed: Mutex::new(None),
+            processes_refreshed: Mutex::new(None),
+        }
+    }
+
+    /// Overall and per-CPU usage percentages since the previous call.
+    ///
+    /// Calls closer together than [`MINIMUM_CPU_UPDATE_INTERVAL`] return the
+    /// previous reading.
+    pub fn cpu_usage(&self) -> (f64, Vec<f64>) {
+        let mut system = self.system.lock();
+        let mut refreshed = self.cpu_refreshed.lock();
+        match *refreshed {
+            Some(at) if at.elapsed() < MINIMUM_CPU_UPDATE_INTERVAL => {}
+            Some(_) => system.refresh_cpu_usage(),
+            None => {
+                system.refresh_cpu_usage();
+                std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+                system.refresh_cpu_usage();
+            }
+        }
+        *refreshed = Some(Instant::now());
+
+        let per_cpu = system
+            .cpus()
+            .iter()
+            .map(|cpu| f64::from(cpu.cpu_usage()))
+            .collect();
+        (f64::from(system.global_cpu_usage()), per_cpu)
+    }
+
+    /// Current memory and swap usage.
+    pub fn memory(&self) -> MemoryUsage {
+        let mut system = self.system.lock();
+        system.refresh_memory();
+        MemoryUsage {
+            total: system.total_memory(),
+            used: system.used_memory(),
+            free: system.free_memory(),
+            available: system.available_memory(),
+            swap_total: system.total_swap(),
+            swap_used: system.used_swap(),
+        }
+    }
+
+    /// Processes matching `filter`, sorted and truncated as it asks.
+    pub fn processes(&self, filter: &ProcessFilter) -> Vec<ProcessInfo> {
+        let mut system = self.system.lock();
+        let mut refreshed = self.processes_refreshed.lock();
+        let refresh = ProcessRefreshKind::nothing()
+            .with_cpu()
+            .with_memory()
+            .with_user(UpdateKind::OnlyIfNotSet);
+        match *refreshed {
+            Some(at) if at.elapsed() < MINIMUM_CPU_UPDATE_INTERVAL => {}
+            Some(_) => {
+                system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+            }
+            None => {
+                // Usage needs two samples.
+                system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+                if filter.sort == ProcessSort::Cpu || filter.min_cpu.is_some() {
+                    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+                    system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+                }
+            }
+        }
+        *refreshed = Some(Instant::now());
+
+        let mut processes: Vec<ProcessInfo> = system
+            .processes()
+            .values()
+            .map(|process| ProcessInfo {
+                pid: process.pid().as_u32(),
+                parent: process.parent().map(|pid| pid.as_u32()),
+                name: process.name().to_string_lossy().into_owned(),
+                cpu_percent: f64::from(process.cpu_usage()),
+                memory: process.memory(),
+                status: process.status().to_string(),
+                start_time: process.start_time(),
+                user_id: process.user_id().map(|uid| uid.to_string()),
+            })
+            .filter(|process| filter.matches(process))
+            .collect();
+        drop(system);
+
+        processes.sort_by(|a, b| filter.sort.compare(a, b));
+        processes.truncate(filter.limit);
+        processes
+    }
+}
+
+/// Memory and swap, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Installed memory.
+    pub total: u64,
+    /// Memory in use.
+    pub used: u64,
+    /// Unused memory.
+    pub free: u64,
+    /// Memory available to new allocations, including reclaimable cache.
+    pub available: u64,
+    /// Swap size.
+    pub swap_total: u64,
+    /// Swap in use.
+    pub swap_used: u64,
+}
+
+/// A running process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    /// Process ID.
+    pub pid: u32,
+    /// Parent process ID.
+    pub parent: Option<u32>,
+    /// Executable name.
+    pub name: String,
+    /// CPU usage since the previous refresh; may exceed 100 on several
+    /// cores.
+    pub cpu_percent: f64,
+    /// Resident memory in bytes.
+    pub memory: u64,
+    /// Scheduler state, e.g. `"Runnable"` or `"Sleeping"`.
+    pub status: String,
+    /// Start time, in seconds since the Unix epoch.
+    pub start_time: u64,
+    /// Owner's user ID.
+    pub user_id: Option<String>,
+}
+
+/// Sort order of `system.processes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProcessSort {
+    /// Highest CPU usage first.
+    #[default]
+    Cpu,
+    /// Largest resident memory first.
+    Memory,
+    /// Lowest process ID first.
+    Pid,
+    /// By name.
+    Name,
+}
+
+impl ProcessSort {
+    /// Parse `"cpu"`, `"memory"`, `"pid"` or `"name"`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "cpu" => Some(ProcessSort::Cpu),
+            "memory" => Some(ProcessSort::Memory),
+            "pid" => Some(ProcessSort::Pid),
+            "name" => Some(ProcessSort::Name),
+            _ => None,
+        }
+    }
+
+    fn compare(self, a: &ProcessInfo, b: &ProcessInfo) -> Ordering {
+        let order = match self {
+            ProcessSort::Cpu => b
+                .cpu_percent
+                .partial_cmp(&a.cpu_percent)
+                .unwrap_or(Ordering::Equal),
+            ProcessSort::Memory => b.memory.cmp(&a.memory),
+            ProcessSort::Pid => Ordering::Equal,
+            ProcessSort::Name => a.name.cmp(&b.name),
+        };
+        order.then(a.pid.cmp(&b.pid))
+    }
+}
+
+/// Which processes `system.processes` returns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessFilter {
+    /// Case-insensitive substring of the name.
+    pub name: Option<String>,
+    /// Only this process ID.
+    pub pid: Option<u32>,
+    /// Only processes using at least this CPU percentage.
+    pub min_cpu: Option<f64>,
+    /// Only processes with at least this many resident bytes.
+    pub min_memory: Option<u64>,
+    /// Sort order.
+    pub sort: ProcessSort,
+    /// Most entries to return.
+    pub limit: usize,
+}
+
+impl Default for ProcessFilter {
+    fn default() -> Self {
+        Self {
+            name: None,
+            pid: None,
+            min_cpu: None,
+            min_memory: None,
+            sort: ProcessSort::default(),
+            limit: DEFAULT_PROCESS_LIMIT,
+        }
+    }
+}
+
+impl ProcessFilter {
+    /// Read a filter from a script value: `null`, a name substring, or a
+    /// map with `name`, `pid`, `min_cpu`, `min_memory`, `sort` and `limit`.
+    pub fn from_value(value: Option<&Value>) -> crate::Result<Self> {
+        let mut filter = Self::default();
+        let options = match value {
+            None | Some(Value::Null) => return Ok(filter),
+            Some(Value::String(name)) => {
+                filter.name = Some(name.to_lowercase());
+                return Ok(filter);
+            }
+            Some(Value::Map(options)) => options,
+            Some(_) => {
+                return Err(crate::Error::invalid_argument(
+                    "filter must be a name or a map",
+                ))
+            }
+        };
+
+        let get = |key: &str| options.get(key).filter(|v| !matches!(v, Value::Null));
+        if let Some(name) = get("name") {
+            let name = name
+                .as_str()
+                .ok_or_else(|| crate::Error::invalid_argument("name must be a string"))?;
+            filter.name = Some(name.to_lowercase());
+        }
+        if let Some(pid) = get("pid") {
+            filter.pid = Some(
+                pid.as_int()
+                    .and_then(|pid| u32::try_from(pid).ok())
+                    .ok_or_else(|| crate::Error::invalid_argument("invalid pid"))?,
+            );
+        }
+        if let Some(min_cpu) = get("min_cpu") {
+            filter.min_cpu = Some(
+                min_cpu
+                    .as_float()
+                    .ok_or_else(|| crate::Error::invalid_argument("min_cpu must be a number"))?,
+            );
+        }
+        if let Some(min_memory) = get("min_memory") {
+            filter.min_memory = Some(
+                min_memory
+                    .as_int()
+                    .and_then(|bytes| u64::try_from(bytes).ok())
+                    .ok_or_else(|| crate::Error::invalid_argument("invalid min_memory"))?,
+            );
+        }
+        if let Some(sort) = get("sort") {
+            filter.sort = sort.as_str().and_then(ProcessSort::parse).ok_or_else(|| {
+                crate::Error::invalid_argument(
+                    "sort must be \"cpu\", \"memory\", \"pid\" or \"name\"",
+                )
+            })?;
+        }
+        if let Some(limit) = get("limit") {
+            filter.limit = limit
+                .as_int()
+                .and_then(|limit| usize::try_from(limit).ok())
+                .ok_or_else(|| crate::Error::invalid_argument("invalid limit"))?;
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, process: &ProcessInfo) -> bool {
+        self.name
+            .as_ref()
+            .map_or(true, |name| process.name.to_lowercase().contains(name))
+            && self.pid.map_or(true, |pid| process.pid == pid)
+            && self.min_cpu.map_or(true, |min| process.cpu_percent >= min)
+            && self.min_memory.map_or(true, |min| process.memory >= min)
+    }
+}
+
+fn int(value: u64) -> Value {
+    Value::Int(i64::try_from(value).unwrap_or(i64::MAX))
+}
+
+/// Get CPU usage.
+///
+/// # Arguments
+///
+/// * `args[0]` - Return per-CPU usage too (bool, optional, default false)
+///
+/// # Returns
+///
+/// Overall usage percentage (0.0 - 100.0) since the previous call, or with
+/// `per_cpu` a map with `total` and `per_cpu` (list of percentages)
+pub fn cpu_percent(
+    monitor: &SystemMonitor,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let per_cpu = match args.first() {
+        None | Some(Value::Null) => false,
+        Some(v) => v
+            .as_bool()
+            .ok_or_else(|| Error::host_function("system.cpu_percent: per_cpu must be a bool"))?,
+    };
+
+    let (total, cpus) = monitor.cpu_usage();
+    if !per_cpu {
+        return Ok(Value::Float(total));
+    }
+    let mut result = HashMap::new();
+    result.insert("total".to_string(), Value::Float(total));
+    result.insert(
+        "per_cpu".to_string(),
+        Value::List(cpus.into_iter().map(Value::Float).collect()),
+    );
+    Ok(Value::Map(result))
+}
+
+/// Get memory and swap usage.
+///
+/// # Returns
+///
+/// Map with `total`, `used`, `free`, `available`, `swap_total` and
+/// `swap_used` in bytes, and `percent` (used / total)
+pub fn memory(monitor: &SystemMonitor, _args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let usage = monitor.memory();
+    let percent = if usage.total == 0 {
+        0.0
+    } else {
+        usage.used as f64 / usage.total as f64 * 100.0
+    };
+
+    let mut result = HashMap::new();
+    result.insert("total".to_string(), int(usage.total));
+    result.insert("used".to_string(), int(usage.used));
+    result.insert("free".to_string(), int(usage.free));
+    result.insert("available".to_string(), int(usage.available));
+    result.insert("swap_total".to_string(), int(usage.swap_total));
+    result.insert("swap_used".to_string(), int(usage.swap_used));
+    result.insert("percent".to_string(), Value::Float(percent));
+    Ok(Value::Map(result))
+}
+
+/// List mounted disks.
+///
+/// # Returns
+///
+/// List of maps with `name`, `mount_point`, `file_system`, `kind` (`"SSD"`,
+/// `"HDD"` or `"Unknown"`), `total`, `available` and `used` in bytes,
+/// `percent` used, `removable` and `read_only`
+pub fn disks(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let disks = Disks::new_with_refreshed_list();
+    let list = disks
+        .list()
+        .iter()
+        .map(|disk| {
+            let total = disk.total_space();
+            let available = disk.available_space();
+            let used = total.saturating_sub(available);
+            let percent = if total == 0 {
+                0.0
+            } else {
+                used as f64 / total as f64 * 100.0
+            };
+
+            let mut entry = HashMap::new();
+            entry.insert(
+                "name".to_string(),
+                Value::String(disk.name().to_string_lossy().into_owned()),
+            );
+            entry.insert(
+                "mount_point".to_string(),
+                Value::String(disk.mount_point().display().to_string()),
+            );
+            entry.insert(
+                "file_system".to_string(),
+                Value::String(disk.file_system().to_string_lossy().into_owned()),
+            );
+            entry.insert("kind".to_string(), Value::String(disk.kind().to_string()));
+            entry.insert("total".to_string(), int(total));
+            entry.insert("available".to_string(), int(available));
+            entry.insert("used".to_string(), int(used));
+            entry.insert("percent".to_string(), Value::Float(percent));
+            entry.insert("removable".to_string(), Value::Bool(disk.is_removable()));
+            entry.insert("read_only".to_string(), Value::Bool(disk.is_read_only()));
+            Value::Map(entry)
+        })
+        .collect();
+    Ok(Value::List(list))
+}
+
+/// Get the load average.
+///
+/// # Returns
+///
+/// Map with `one`, `five` and `fifteen` minute averages (zero on Windows,
+/// which has no load average)
+pub fn load_avg(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let load = System::load_average();
+    let mut result = HashMap::new();
+    result.insert("one".to_string(), Value::Float(load.one));
+    result.insert("five".to_string(), Value::Float(load.five));
+    result.insert("fifteen".to_string(), Value::Float(load.fifteen));
+    Ok(Value::Map(result))
+}
+
+/// Get the time since boot.
+///
+/// # Returns
+///
+/// Uptime in seconds
+pub fn uptime(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    Ok(int(System::uptime()))
+}
+
+/// List running processes.
+///
+/// # Arguments
+///
+/// * `args[0]` - Filter (optional): a name substring, or a map with `name`,
+///   `pid`, `min_cpu` (percent), `min_memory` (bytes), `sort` (`"cpu"`
+///   (default), `"memory"`, `"pid"` or `"name"`) and `limit` (default 50)
+///
+/// # Returns
+///
+/// List of maps with `pid`, `parent`, `name`, `cpu_percent`, `memory`
+/// (resident bytes), `status`, `start_time` and `user_id`
+pub fn processes(
+    monitor: &SystemMonitor,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let filter = ProcessFilter::from_value(args.first())
+        .map_err(|e| Error::host_function(format!("system.processes: {}", e)))?;
+
+    let list = monitor
+        .processes(&filter)
+        .into_iter()
+        .map(|process| {
+            let mut entry = HashMap::new();
+            entry.insert("pid".to_string(), Value::Int(i64::from(process.pid)));
+            entry.insert(
+                "parent".to_string(),
+                process
+                    .parent
+                    .map_or(Value::Null, |pid| Value::Int(i64::from(pid))),
+            );
+            entry.insert("name".to_string(), Value::String(process.name));
+            entry.insert("cpu_percent".to_string(), Value::Float(process.cpu_percent));
+            entry.insert("memory".to_string(), int(process.memory));
+            entry.insert("status".to_string(), Value::String(process.status));
+            entry.insert("start_time".to_string(), int(process.start_time));
+            entry.insert(
+                "user_id".to_string(),
+                process.user_id.map_or(Value::Null, Value::String),
+            );
+            Value::Map(entry)
+        })
+        .collect();
+    Ok(Value::List(list))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_process_filter() {
+        let filter = ProcessFilter::from_value(Some(&Value::String("Postgres".into()))).unwrap();
+        assert_eq!(filter.name.as_deref(), Some("postgres"));
+        assert_eq!(filter.limit, DEFAULT_PROCESS_LIMIT);
+
+        let mut options = HashMap::new();
+        options.insert("sort".to_string(), Value::String("memory".into()));
+        options.insert("limit".to_string(), Value::Int(3));
+        options.insert("min_cpu".to_string(), Value::Int(5));
+        let filter = ProcessFilter::from_value(Some(&Value::Map(options))).unwrap();
+        assert_eq!(filter.sort, ProcessSort::Memory);
+        assert_eq!(filter.limit, 3);
+        assert_eq!(filter.min_cpu, Some(5.0));
+
+        let mut options = HashMap::new();
+        options.insert("sort".to_string(), Value::String("size".into()));
+        assert!(ProcessFilter::from_value(Some(&Value::Map(options))).is_err());
+        assert!(ProcessFilter::from_value(Some(&Value::Int(1))).is_err());
+
+        let process = ProcessInfo {
+            pid: 42,
+            parent: Some(1),
+            name: "postgres".to_string(),
+            cpu_percent: 2.0,
+            memory: 1 << 20,
+            status: "Sleeping".to_string(),
+            start_time: 0,
+            user_id: None,
+        };
+        let filter = ProcessFilter {
+            name: Some("gres".to_string()),
+            min_memory: Some(1 << 20),
+            ..ProcessFilter::default()
+        };
+        assert!(filter.matches(&process));
+        let filter = ProcessFilter {
+            min_cpu: Some(5.0),
+            ..ProcessFilter::default()
+        };
+        assert!(!filter.matches(&process));
+    }
+
+    #[test]
+    fn test_host_functions() {
+        let ctx = create_test_ctx();
+        let monitor = SystemMonitor::new();
+
+        let Value::Map(memory) = memory(&monitor, &[], &ctx).unwrap() else {
+            panic!("expected a map");
+        };
+        assert!(memory["total"].as_int().unwrap() > 0);
+
+        let Value::Map(cpu) = cpu_percent(&monitor, &[Value::Bool(true)], &ctx).unwrap() else {
+            panic!("expected a map");
+        };
+        let total = cpu["total"].as_float().unwrap();
+        assert!((0.0..=100.0).contains(&total));
+
+        let own_pid = i64::from(std::process::id());
+        let mut filter = HashMap::new();
+        filter.insert("pid".to_string(), Value::Int(own_pid));
+        let Value::List(list) = processes(&monitor, &[Value::Map(filter)], &ctx).unwrap() else {
+            panic!("expected a list");
+        };
+        assert_eq!(list.len(), 1);
+
+        assert!(load_avg(&[], &ctx).is_ok());
+        assert!(uptime(&[], &ctx).unwrap().as_int().unwrap() > 0);
+        assert!(matches!(disks(&[], &ctx).unwrap(), Value::List(_)));
+    }
+}
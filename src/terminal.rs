@@ -6,103 +6,709 @@
 //! ## Features
 //!
 //! - Read key events (blocking and non-blocking)
-//! - Get terminal dimensions
-//! - Clipboard read/write (platform-dependent)
+//! - Read mouse, resize, paste, and focus events as tagged maps
+//! - Event stream handles filled in the background, for polling input
+//!   alongside other handles in one loop ([`events`])
+//! - Get terminal dimensions, clear the screen, and move or hide the cursor
+//! - Raw mode and the alternate screen, restored by [`TerminalGuard`] on
+//!   drop or panic
+//! - Clipboard read/write (`clipboard` feature, gated by
+//!   `SafetyConfig::allow_clipboard`)
 //! - ANSI color utilities
+//! - Progress bars and spinners with throttled redraws ([`progress`])
+//! - Prompts for text, passwords, confirmation, and selection ([`prompt`](mod@prompt))
+//! - Inline images via kitty, iTerm2, or sixel graphics, with a text
+//!   fallback ([`graphics`], `terminal-images` feature)
 //!
 //! ## Example
 //!
 //! ```rust,ignore
 //! use fusabi_stdlib_ext::terminal;
 //!
-//! // Read a single keypress
+//! // Read a single keypress: {code, modifiers, kind}
 //! let key = terminal::read_key(&[], &ctx)?;
 //!
+//! // Wait up to 100ms for a keypress (null on timeout)
+//! let key = terminal::read_key_timeout(&[Value::Int(100)], &ctx)?;
+//!
 //! // Get terminal size
 //! let size = terminal::size(&[], &ctx)?;
 //!
-//! // Clipboard operations
-//! terminal::clipboard_write(&[Value::String("text".into())], &ctx)?;
-//! let text = terminal::clipboard_read(&[], &ctx)?;
+//! // Clipboard operations (denied unless the safety config allows them)
+//! terminal::clipboard_write(&safety, &[Value::String("text".into())], &ctx)?;
+//! let text = terminal::clipboard_read(&safety, &[], &ctx)?;
 //! ```
 
+use std::collections::HashMap;
+use std::sync::{Arc, Once};
+use std::time::{Duration, Instant};
+
+use crossterm::cursor;
+use crossterm::event::{
+    self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+    Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use parking_lot::Mutex;
+
 use fusabi_host::{Error, ExecutionContext, Result, Value};
 
+use crate::safety::SafetyConfig;
+
+pub mod events;
+#[cfg(feature = "terminal-images")]
+pub mod graphics;
+pub mod progress;
+pub mod prompt;
+
+pub use events::{events_close, events_drain, events_next, events_open, events_poll};
+pub use progress::{
+    progress_finish, progress_inc, progress_message, progress_new, progress_set, progress_tick,
+    spinner_new,
+};
+pub use prompt::{confirm, password, prompt, select};
+
 /// Read a single key event (blocking).
 ///
-/// Returns the key name as a string (e.g., "Enter", "Ctrl+C", "a", "ArrowUp").
+/// Non-key events (mouse, resize, focus) are skipped. Enable raw mode first
+/// to receive keys as they are pressed rather than after Enter.
+///
+/// # Returns
+///
+/// Map with `code` (e.g. `"a"`, `"Enter"`, `"ArrowUp"`, `"F5"`),
+/// `modifiers` (list of `"shift"`, `"ctrl"`, `"alt"`, `"super"`, `"hyper"`,
+/// `"meta"`), and `kind` (`"press"`, `"repeat"`, or `"release"`)
+pub fn read_key(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    loop {
+        let event =
+            event::read().map_err(|e| Error::host_function(format!("terminal.read_key: {}", e)))?;
+        if let Event::Key(key) = event {
+            return Ok(key_event_to_value(&key));
+        }
+    }
+}
+
+/// Read a single key event, waiting at most the given time.
 ///
 /// # Arguments
 ///
-/// * `args` - No arguments required
-/// * `ctx` - Execution context
+/// * `args[0]` - Timeout in milliseconds (0 polls without waiting)
 ///
 /// # Returns
 ///
-/// String representing the key pressed
-pub fn read_key(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    // TODO: Implement using crossterm
-    // For now, return a placeholder
-    tracing::warn!("terminal.read_key: not yet implemented");
-    Err(Error::host_function(
-        "terminal.read_key: not yet implemented",
-    ))
+/// Key map as returned by [`read_key`], or null if no key was pressed in
+/// time
+pub fn read_key_timeout(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let ms = args
+        .first()
+        .and_then(|v| v.as_int())
+        .filter(|ms| *ms >= 0)
+        .ok_or_else(|| {
+            Error::host_function("terminal.read_key_timeout: missing timeout argument")
+        })?;
+
+    let deadline = Instant::now() + Duration::from_millis(ms as u64);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let ready = event::poll(remaining)
+            .map_err(|e| Error::host_function(format!("terminal.read_key_timeout: {}", e)))?;
+        if !ready {
+            return Ok(Value::Null);
+        }
+        let event = event::read()
+            .map_err(|e| Error::host_function(format!("terminal.read_key_timeout: {}", e)))?;
+        if let Event::Key(key) = event {
+            return Ok(key_event_to_value(&key));
+        }
+    }
+}
+
+/// Convert a crossterm key event to the map returned by [`read_key`].
+pub fn key_event_to_value(key: &KeyEvent) -> Value {
+    let code = match key.code {
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::Up => "ArrowUp".to_string(),
+        KeyCode::Down => "ArrowDown".to_string(),
+        KeyCode::Left => "ArrowLeft".to_string(),
+        KeyCode::Right => "ArrowRight".to_string(),
+        KeyCode::Media(media) => format!("{:?}", media),
+        KeyCode::Modifier(modifier) => format!("{:?}", modifier),
+        other => format!("{:?}", other),
+    };
+
+    let modifiers = [
+        (KeyModifiers::SHIFT, "shift"),
+        (KeyModifiers::CONTROL, "ctrl"),
+        (KeyModifiers::ALT, "alt"),
+        (KeyModifiers::SUPER, "super"),
+        (KeyModifiers::HYPER, "hyper"),
+        (KeyModifiers::META, "meta"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| key.modifiers.contains(*flag))
+    .map(|(_, name)| Value::String(name.to_string()))
+    .collect();
+
+    let kind = match key.kind {
+        KeyEventKind::Press => "press",
+        KeyEventKind::Repeat => "repeat",
+        KeyEventKind::Release => "release",
+    };
+
+    let mut m = HashMap::new();
+    m.insert("code".to_string(), Value::String(code));
+    m.insert("modifiers".to_string(), Value::List(modifiers));
+    m.insert("kind".to_string(), Value::String(kind.to_string()));
+    Value::Map(m)
+}
+
+/// Read the next terminal event.
+///
+/// Mouse events are only reported after `terminal.enable_mouse`, and paste
+/// events after `terminal.enable_paste`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Timeout in milliseconds (optional, default: wait
+///   indefinitely; 0 polls without waiting)
+///
+/// # Returns
+///
+/// Map tagged by `type`, or null if no event arrived in time:
+/// - `"key"`: the fields returned by [`read_key`]
+/// - `"mouse"`: `kind` (`"down"`, `"up"`, `"drag"`, `"move"`,
+///   `"scroll_up"`, `"scroll_down"`, `"scroll_left"`, `"scroll_right"`),
+///   `button` (`"left"`, `"right"`, `"middle"`, or null), zero-based `x` and
+///   `y`, and `modifiers`
+/// - `"resize"`: `width` and `height`
+/// - `"paste"`: `text`
+/// - `"focus"`: `focused` (boolean)
+pub fn next_event(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let timeout = match args.first() {
+        None | Some(Value::Null) => None,
+        Some(value) => {
+            let ms = value.as_int().filter(|ms| *ms >= 0).ok_or_else(|| {
+                Error::host_function("terminal.next_event: timeout must be a non-negative integer")
+            })?;
+            Some(Duration::from_millis(ms as u64))
+        }
+    };
+
+    if let Some(timeout) = timeout {
+        let ready = event::poll(timeout)
+            .map_err(|e| Error::host_function(format!("terminal.next_event: {}", e)))?;
+        if !ready {
+            return Ok(Value::Null);
+        }
+    }
+    let event =
+        event::read().map_err(|e| Error::host_function(format!("terminal.next_event: {}", e)))?;
+    Ok(event_to_value(&event))
+}
+
+/// Convert a crossterm event to the tagged map returned by [`next_event`].
+pub fn event_to_value(event: &Event) -> Value {
+    let tagged = |kind: &str, mut fields: HashMap<String, Value>| {
+        fields.insert("type".to_string(), Value::String(kind.to_string()));
+        Value::Map(fields)
+    };
+
+    match event {
+        Event::Key(key) => match key_event_to_value(key) {
+            Value::Map(fields) => tagged("key", fields),
+            other => other,
+        },
+        Event::Mouse(mouse) => tagged("mouse", mouse_event_fields(mouse)),
+        Event::Resize(width, height) => tagged(
+            "resize",
+            HashMap::from([
+                ("width".to_string(), Value::Int(i64::from(*width))),
+                ("height".to_string(), Value::Int(i64::from(*height))),
+            ]),
+        ),
+        Event::Paste(text) => tagged(
+            "paste",
+            HashMap::from([("text".to_string(), Value::String(text.clone()))]),
+        ),
+        Event::FocusGained | Event::FocusLost => tagged(
+            "focus",
+            HashMap::from([(
+                "focused".to_string(),
+                Value::Bool(matches!(event, Event::FocusGained)),
+            )]),
+        ),
+    }
+}
+
+fn mouse_event_fields(mouse: &MouseEvent) -> HashMap<String, Value> {
+    let button_name = |button: &MouseButton| match button {
+        MouseButton::Left => "left",
+        MouseButton::Right => "right",
+        MouseButton::Middle => "middle",
+    };
+    let (kind, button) = match &mouse.kind {
+        MouseEventKind::Down(b) => ("down", Some(button_name(b))),
+        MouseEventKind::Up(b) => ("up", Some(button_name(b))),
+        MouseEventKind::Drag(b) => ("drag", Some(button_name(b))),
+        MouseEventKind::Moved => ("move", None),
+        MouseEventKind::ScrollUp => ("scroll_up", None),
+        MouseEventKind::ScrollDown => ("scroll_down", None),
+        MouseEventKind::ScrollLeft => ("scroll_left", None),
+        MouseEventKind::ScrollRight => ("scroll_right", None),
+    };
+
+    let modifiers = match key_event_to_value(&KeyEvent::new(KeyCode::Null, mouse.modifiers)) {
+        Value::Map(mut fields) => fields
+            .remove("modifiers")
+            .unwrap_or(Value::List(Vec::new())),
+        _ => Value::List(Vec::new()),
+    };
+
+    HashMap::from([
+        ("kind".to_string(), Value::String(kind.to_string())),
+        (
+            "button".to_string(),
+            button.map_or(Value::Null, |b| Value::String(b.to_string())),
+        ),
+        ("x".to_string(), Value::Int(i64::from(mouse.column))),
+        ("y".to_string(), Value::Int(i64::from(mouse.row))),
+        ("modifiers".to_string(), modifiers),
+    ])
+}
+
+/// Terminal modes that scripts can change and that must be undone.
+#[derive(Debug)]
+struct Modes {
+    raw: bool,
+    cursor_hidden: bool,
+    alternate_screen: bool,
+    mouse: bool,
+    paste: bool,
+}
+
+// The terminal is process-wide, so its modes are too.
+static MODES: Mutex<Modes> = parking_lot::const_mutex(Modes {
+    raw: false,
+    cursor_hidden: false,
+    alternate_screen: false,
+    mouse: false,
+    paste: false,
+});
+
+static PANIC_HOOK: Once = Once::new();
+
+/// Undo every terminal mode changed through this module: stop mouse
+/// capture and bracketed paste, leave the alternate screen, show the cursor,
+/// and disable raw mode.
+pub fn restore() -> std::io::Result<()> {
+    let mut modes = MODES.lock();
+    let mut stdout = std::io::stdout();
+    if modes.mouse {
+        execute!(stdout, DisableMouseCapture)?;
+        modes.mouse = false;
+    }
+    if modes.paste {
+        execute!(stdout, DisableBracketedPaste)?;
+        modes.paste = false;
+    }
+    if modes.alternate_screen {
+        execute!(stdout, LeaveAlternateScreen)?;
+        modes.alternate_screen = false;
+    }
+    if modes.cursor_hidden {
+        execute!(stdout, cursor::Show)?;
+        modes.cursor_hidden = false;
+    }
+    if modes.raw {
+        crossterm::terminal::disable_raw_mode()?;
+        modes.raw = false;
+    }
+    Ok(())
+}
+
+/// Guard over the terminal modes scripts can change (raw mode, hidden
+/// cursor, alternate screen, mouse capture, bracketed paste).
+///
+/// The terminal is restored when the guard is dropped, so a script that
+/// exits without undoing its changes does not leave the terminal unusable
+/// once the host registry holding the guard is dropped. Creating a guard
+/// also installs a panic hook that restores the terminal before the panic
+/// message is printed.
+#[derive(Debug)]
+pub struct TerminalGuard {
+    _private: (),
+}
+
+impl TerminalGuard {
+    /// Create a guard, installing the restoring panic hook on first use.
+    pub fn new() -> Self {
+        PANIC_HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                // A panic while the lock is held must not deadlock the hook.
+                if !MODES.is_locked() {
+                    let _ = restore();
+                }
+                previous(info);
+            }));
+        });
+        Self { _private: () }
+    }
+
+    /// Whether raw mode is enabled.
+    pub fn is_raw_mode(&self) -> bool {
+        MODES.lock().raw
+    }
+
+    /// Enable raw mode. Does nothing if already enabled.
+    pub fn enable_raw_mode(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if !modes.raw {
+            crossterm::terminal::enable_raw_mode()?;
+            modes.raw = true;
+        }
+        Ok(())
+    }
+
+    /// Disable raw mode. Does nothing if not enabled.
+    pub fn disable_raw_mode(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if modes.raw {
+            crossterm::terminal::disable_raw_mode()?;
+            modes.raw = false;
+        }
+        Ok(())
+    }
+
+    /// Hide the cursor.
+    pub fn hide_cursor(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        execute!(std::io::stdout(), cursor::Hide)?;
+        modes.cursor_hidden = true;
+        Ok(())
+    }
+
+    /// Show the cursor.
+    pub fn show_cursor(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        execute!(std::io::stdout(), cursor::Show)?;
+        modes.cursor_hidden = false;
+        Ok(())
+    }
+
+    /// Switch to the alternate screen. Does nothing if already there.
+    pub fn enter_alternate_screen(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if !modes.alternate_screen {
+            execute!(std::io::stdout(), EnterAlternateScreen)?;
+            modes.alternate_screen = true;
+        }
+        Ok(())
+    }
+
+    /// Return to the main screen. Does nothing if not on the alternate
+    /// screen.
+    pub fn leave_alternate_screen(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if modes.alternate_screen {
+            execute!(std::io::stdout(), LeaveAlternateScreen)?;
+            modes.alternate_screen = false;
+        }
+        Ok(())
+    }
+
+    /// Report mouse clicks, scrolling, and movement as events. Does nothing
+    /// if already enabled.
+    pub fn enable_mouse(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if !modes.mouse {
+            execute!(std::io::stdout(), EnableMouseCapture)?;
+            modes.mouse = true;
+        }
+        Ok(())
+    }
+
+    /// Stop reporting mouse events. Does nothing if not enabled.
+    pub fn disable_mouse(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if modes.mouse {
+            execute!(std::io::stdout(), DisableMouseCapture)?;
+            modes.mouse = false;
+        }
+        Ok(())
+    }
+
+    /// Report pasted text as a single paste event instead of key presses.
+    /// Does nothing if already enabled.
+    pub fn enable_paste(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if !modes.paste {
+            execute!(std::io::stdout(), EnableBracketedPaste)?;
+            modes.paste = true;
+        }
+        Ok(())
+    }
+
+    /// Stop reporting paste events. Does nothing if not enabled.
+    pub fn disable_paste(&self) -> std::io::Result<()> {
+        let mut modes = MODES.lock();
+        if modes.paste {
+            execute!(std::io::stdout(), DisableBracketedPaste)?;
+            modes.paste = false;
+        }
+        Ok(())
+    }
+}
+
+impl Default for TerminalGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        if let Err(e) = restore() {
+            tracing::warn!("terminal: failed to restore terminal state: {}", e);
+        }
+    }
+}
+
+/// Put the terminal in raw mode.
+///
+/// Raw mode is restored by `terminal.disable_raw_mode`, or automatically
+/// when the host registry is dropped.
+pub fn enable_raw_mode(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .enable_raw_mode()
+        .map_err(|e| Error::host_function(format!("terminal.enable_raw_mode: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Restore the terminal from raw mode.
+pub fn disable_raw_mode(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .disable_raw_mode()
+        .map_err(|e| Error::host_function(format!("terminal.disable_raw_mode: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Hide the cursor until `terminal.show_cursor` or the host registry is
+/// dropped.
+pub fn hide_cursor(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .hide_cursor()
+        .map_err(|e| Error::host_function(format!("terminal.hide_cursor: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Show the cursor.
+pub fn show_cursor(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .show_cursor()
+        .map_err(|e| Error::host_function(format!("terminal.show_cursor: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Switch to the alternate screen until `terminal.alternate_screen_leave`
+/// or the host registry is dropped.
+pub fn alternate_screen_enter(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .enter_alternate_screen()
+        .map_err(|e| Error::host_function(format!("terminal.alternate_screen_enter: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Return from the alternate screen.
+pub fn alternate_screen_leave(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .leave_alternate_screen()
+        .map_err(|e| Error::host_function(format!("terminal.alternate_screen_leave: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Report mouse events from `terminal.next_event` until
+/// `terminal.disable_mouse` or the host registry is dropped.
+pub fn enable_mouse(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .enable_mouse()
+        .map_err(|e| Error::host_function(format!("terminal.enable_mouse: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Stop reporting mouse events.
+pub fn disable_mouse(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .disable_mouse()
+        .map_err(|e| Error::host_function(format!("terminal.disable_mouse: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Report pasted text from `terminal.next_event` as paste events until
+/// `terminal.disable_paste` or the host registry is dropped.
+pub fn enable_paste(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .enable_paste()
+        .map_err(|e| Error::host_function(format!("terminal.enable_paste: {}", e)))?;
+    Ok(Value::Null)
+}
+
+/// Stop reporting paste events.
+pub fn disable_paste(
+    guard: &Arc<TerminalGuard>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    guard
+        .disable_paste()
+        .map_err(|e| Error::host_function(format!("terminal.disable_paste: {}", e)))?;
+    Ok(Value::Null)
 }
 
 /// Get terminal dimensions.
 ///
-/// Returns a list `[width, height]` in columns and rows.
+/// Falls back to the `COLUMNS` and `LINES` environment variables when
+/// output is not a terminal.
 ///
 /// # Returns
 ///
 /// List with two integers: [width_columns, height_rows]
 pub fn size(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    // TODO: Implement using crossterm
-    // For now, return default terminal size
-    tracing::debug!("terminal.size: returning default 80x24");
-    Ok(Value::List(vec![Value::Int(80), Value::Int(24)]))
+    let (cols, rows) = crossterm::terminal::size()
+        .map(|(cols, rows)| (i64::from(cols), i64::from(rows)))
+        .or_else(|e| {
+            let env = |name: &str| std::env::var(name).ok()?.parse::<i64>().ok();
+            env("COLUMNS")
+                .zip(env("LINES"))
+                .ok_or_else(|| Error::host_function(format!("terminal.size: {}", e)))
+        })?;
+    Ok(Value::List(vec![Value::Int(cols), Value::Int(rows)]))
 }
 
 /// Read text from system clipboard.
 ///
+/// Requires `allow_clipboard` in the safety configuration, since clipboard
+/// contents frequently include secrets.
+///
 /// # Returns
 ///
 /// String containing clipboard contents
-pub fn clipboard_read(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    // TODO: Implement using clipboard crate
-    tracing::warn!("terminal.clipboard_read: not yet implemented");
+pub fn clipboard_read(
+    safety: &Arc<SafetyConfig>,
+    _args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    check_clipboard(safety)?;
+
+    #[cfg(feature = "clipboard")]
+    {
+        let text = arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map_err(|e| Error::host_function(format!("terminal.clipboard_read: {}", e)))?;
+        Ok(Value::String(text))
+    }
+
+    #[cfg(not(feature = "clipboard"))]
     Err(Error::host_function(
-        "terminal.clipboard_read: not yet implemented",
+        "terminal.clipboard_read: requires the clipboard feature",
     ))
 }
 
 /// Write text to system clipboard.
 ///
+/// Requires `allow_clipboard` in the safety configuration.
+///
 /// # Arguments
 ///
 /// * `args[0]` - Text to write to clipboard
-pub fn clipboard_write(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+pub fn clipboard_write(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
     let text = args
         .first()
         .and_then(|v| v.as_str())
         .ok_or_else(|| Error::host_function("terminal.clipboard_write: missing text argument"))?;
 
-    // TODO: Implement using clipboard crate
-    tracing::warn!(
-        "terminal.clipboard_write: not yet implemented (text: {})",
-        text
-    );
-    Err(Error::host_function(
-        "terminal.clipboard_write: not yet implemented",
-    ))
+    check_clipboard(safety)?;
+
+    #[cfg(feature = "clipboard")]
+    {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(text))
+            .map_err(|e| Error::host_function(format!("terminal.clipboard_write: {}", e)))?;
+        Ok(Value::Null)
+    }
+
+    #[cfg(not(feature = "clipboard"))]
+    {
+        let _ = text;
+        Err(Error::host_function(
+            "terminal.clipboard_write: requires the clipboard feature",
+        ))
+    }
+}
+
+fn check_clipboard(safety: &SafetyConfig) -> Result<()> {
+    if safety.allow_clipboard {
+        Ok(())
+    } else {
+        Err(Error::host_function(
+            crate::Error::not_permitted("clipboard access not allowed").to_string(),
+        ))
+    }
 }
 
-/// Apply ANSI color to text.
+/// Apply ANSI colors and styles to text.
+///
+/// Colors are a name (`black`, `red`, `green`, `yellow`, `blue`, `magenta`,
+/// `cyan`, `white`, or a `bright_` variant), a 256-color index (0-255), or a
+/// hex truecolor (`#ff8800` or `#f80`).
 ///
 /// # Arguments
 ///
 /// * `args[0]` - Text to colorize
-/// * `args[1]` - Color name (red, green, blue, yellow, etc.)
+/// * `args[1]` - Foreground color, or an options map with `fg`, `bg`, and
+///   boolean `bold`, `dim`, `italic`, `underline`, `reverse`
 ///
 /// # Returns
 ///
@@ -113,59 +719,425 @@ pub fn colorize(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
         .and_then(|v| v.as_str())
         .ok_or_else(|| Error::host_function("terminal.colorize: missing text argument"))?;
 
-    let color = args
+    let style = args
         .get(1)
-        .and_then(|v| v.as_str())
         .ok_or_else(|| Error::host_function("terminal.colorize: missing color argument"))?;
 
-    // Simple ANSI color codes
-    let color_code = match color.to_lowercase().as_str() {
-        "red" => "31",
-        "green" => "32",
-        "yellow" => "33",
-        "blue" => "34",
-        "magenta" => "35",
-        "cyan" => "36",
-        "white" => "37",
-        _ => {
-            return Err(Error::host_function(format!(
-                "terminal.colorize: unknown color '{}'",
-                color
-            )))
+    let codes = style_codes(style)
+        .map_err(|e| Error::host_function(format!("terminal.colorize: {}", e)))?;
+    if codes.is_empty() {
+        return Ok(Value::String(text.to_string()));
+    }
+
+    let colored = format!("\x1b[{}m{}\x1b[0m", codes.join(";"), text);
+    Ok(Value::String(colored))
+}
+
+/// SGR parameters for a color or an options map.
+fn style_codes(style: &Value) -> std::result::Result<Vec<String>, String> {
+    let Some(options) = style.as_map() else {
+        return Ok(vec![color_code(style, false)?]);
+    };
+
+    let mut codes = Vec::new();
+    for (name, code) in [
+        ("bold", "1"),
+        ("dim", "2"),
+        ("italic", "3"),
+        ("underline", "4"),
+        ("reverse", "7"),
+    ] {
+        match options.get(name) {
+            None | Some(Value::Null) => {}
+            Some(Value::Bool(enabled)) => {
+                if *enabled {
+                    codes.push(code.to_string());
+                }
+            }
+            Some(_) => return Err(format!("{} must be a boolean", name)),
         }
+    }
+    for (name, background) in [("fg", false), ("bg", true)] {
+        match options.get(name) {
+            None | Some(Value::Null) => {}
+            Some(color) => codes.push(color_code(color, background)?),
+        }
+    }
+    Ok(codes)
+}
+
+/// SGR parameter for a foreground or background color.
+fn color_code(color: &Value, background: bool) -> std::result::Result<String, String> {
+    let base = if background { 40 } else { 30 };
+
+    if let Some(index) = color.as_int() {
+        return u8::try_from(index)
+            .map(|index| format!("{};5;{}", base + 8, index))
+            .map_err(|_| format!("color index out of range: {}", index));
+    }
+
+    let name = color
+        .as_str()
+        .ok_or_else(|| "color must be a name, index, or hex string".to_string())?;
+
+    if let Some(hex) = name.strip_prefix('#') {
+        let (r, g, b) =
+            parse_hex_color(hex).ok_or_else(|| format!("invalid hex color '{}'", name))?;
+        return Ok(format!("{};2;{};{};{}", base + 8, r, g, b));
+    }
+
+    let lower = name.to_lowercase();
+    let (offset, bright) = match lower.strip_prefix("bright_") {
+        Some(rest) => (named_color(rest), true),
+        None => (named_color(&lower), false),
     };
+    let offset = offset.ok_or_else(|| format!("unknown color '{}'", name))?;
+    let base = if bright { base + 60 } else { base };
+    Ok((base + offset).to_string())
+}
 
-    let colored = format!("\x1b[{}m{}\x1b[0m", color_code, text);
-    Ok(Value::String(colored))
+fn named_color(name: &str) -> Option<u8> {
+    match name {
+        "black" => Some(0),
+        "red" => Some(1),
+        "green" => Some(2),
+        "yellow" => Some(3),
+        "blue" => Some(4),
+        "magenta" => Some(5),
+        "cyan" => Some(6),
+        "white" => Some(7),
+        _ => None,
+    }
+}
+
+fn parse_hex_color(hex: &str) -> Option<(u8, u8, u8)> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |s: &str| u8::from_str_radix(s, 16).ok();
+    match hex.len() {
+        6 => Some((
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        3 => {
+            let short = |i: usize| channel(&hex[i..i + 1]).map(|v| v * 17);
+            Some((short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
+}
+
+/// Remove ANSI escape sequences from text.
+///
+/// # Arguments
+///
+/// * `args[0]` - Text
+///
+/// # Returns
+///
+/// The text without CSI (colors, cursor movement), OSC (titles,
+/// hyperlinks), or other escape sequences
+pub fn strip_ansi(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let text = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function("terminal.strip_ansi: missing text argument"))?;
+
+    Ok(Value::String(strip_ansi_codes(text)))
+}
+
+/// Remove ANSI escape sequences from a string.
+pub fn strip_ansi_codes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters and intermediates up to a final byte in @..~.
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC, DCS, SOS, PM, APC: up to BEL or ST (ESC \).
+            Some(']' | 'P' | 'X' | '^' | '_') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Two-character sequences, with intermediates (e.g. ESC ( B).
+            Some(c) if (' '..='/').contains(&c) => {
+                chars.next();
+            }
+            Some(_) | None => {}
+        }
+    }
+    out
 }
 
 /// Clear the terminal screen.
-pub fn clear(_args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    // TODO: Implement using crossterm
-    tracing::debug!("terminal.clear: not yet implemented");
-    Err(Error::host_function("terminal.clear: not yet implemented"))
+///
+/// # Arguments
+///
+/// * `args[0]` - What to clear (optional): `"all"` (default, also moves the
+///   cursor home), `"purge"` (all plus scrollback), `"below"`, `"above"`,
+///   `"line"`, or `"line_end"`
+pub fn clear(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let mode = args.first().and_then(|v| v.as_str()).unwrap_or("all");
+    let clear_type = parse_clear_type(mode)
+        .ok_or_else(|| Error::host_function(format!("terminal.clear: unknown mode '{}'", mode)))?;
+
+    let mut stdout = std::io::stdout();
+    let result = match clear_type {
+        ClearType::All | ClearType::Purge => {
+            execute!(stdout, Clear(clear_type), cursor::MoveTo(0, 0))
+        }
+        _ => execute!(stdout, Clear(clear_type)),
+    };
+    result.map_err(|e| Error::host_function(format!("terminal.clear: {}", e)))?;
+    Ok(Value::Null)
+}
+
+fn parse_clear_type(mode: &str) -> Option<ClearType> {
+    match mode {
+        "all" => Some(ClearType::All),
+        "purge" => Some(ClearType::Purge),
+        "below" => Some(ClearType::FromCursorDown),
+        "above" => Some(ClearType::FromCursorUp),
+        "line" => Some(ClearType::CurrentLine),
+        "line_end" => Some(ClearType::UntilNewLine),
+        _ => None,
+    }
 }
 
 /// Set cursor position.
 ///
 /// # Arguments
 ///
-/// * `args[0]` - Column (x)
-/// * `args[1]` - Row (y)
+/// * `args[0]` - Column (x), zero-based
+/// * `args[1]` - Row (y), zero-based
 pub fn set_cursor(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
-    let _x = args
-        .first()
-        .and_then(|v| v.as_int())
-        .ok_or_else(|| Error::host_function("terminal.set_cursor: missing x argument"))?;
+    let coordinate = |index: usize, name: &str| {
+        let value = args.get(index).and_then(|v| v.as_int()).ok_or_else(|| {
+            Error::host_function(format!("terminal.set_cursor: missing {} argument", name))
+        })?;
+        u16::try_from(value).map_err(|_| {
+            Error::host_function(format!(
+                "terminal.set_cursor: {} out of range: {}",
+                name, value
+            ))
+        })
+    };
+    let x = coordinate(0, "x")?;
+    let y = coordinate(1, "y")?;
 
-    let _y = args
-        .get(1)
-        .and_then(|v| v.as_int())
-        .ok_or_else(|| Error::host_function("terminal.set_cursor: missing y argument"))?;
+    execute!(std::io::stdout(), cursor::MoveTo(x, y))
+        .map_err(|e| Error::host_function(format!("terminal.set_cursor: {}", e)))?;
+    Ok(Value::Null)
+}
 
-    // TODO: Implement using crossterm
-    tracing::debug!("terminal.set_cursor: not yet implemented");
-    Err(Error::host_function(
-        "terminal.set_cursor: not yet implemented",
-    ))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_event_to_value() {
+        let key = KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL | KeyModifiers::SHIFT,
+        );
+        let value = key_event_to_value(&key);
+        let map = value.as_map().unwrap();
+        assert_eq!(map["code"], Value::String("c".into()));
+        assert_eq!(
+            map["modifiers"],
+            Value::List(vec![
+                Value::String("shift".into()),
+                Value::String("ctrl".into())
+            ])
+        );
+        assert_eq!(map["kind"], Value::String("press".into()));
+
+        let key = KeyEvent::new(KeyCode::Up, KeyModifiers::NONE);
+        let value = key_event_to_value(&key);
+        assert_eq!(
+            value.as_map().unwrap()["code"],
+            Value::String("ArrowUp".into())
+        );
+
+        let key = KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE);
+        let value = key_event_to_value(&key);
+        assert_eq!(value.as_map().unwrap()["code"], Value::String("F5".into()));
+    }
+
+    #[test]
+    fn test_clipboard_denied_by_default() {
+        let sandbox = fusabi_host::Sandbox::new(fusabi_host::SandboxConfig::default()).unwrap();
+        let ctx = ExecutionContext::new(
+            1,
+            fusabi_host::Capabilities::none(),
+            fusabi_host::Limits::default(),
+            sandbox,
+        );
+
+        for safety in [SafetyConfig::default(), SafetyConfig::strict()] {
+            let safety = Arc::new(safety);
+            let err = clipboard_read(&safety, &[], &ctx).unwrap_err();
+            assert!(err.to_string().contains("clipboard access not allowed"));
+
+            let err =
+                clipboard_write(&safety, &[Value::String("secret".into())], &ctx).unwrap_err();
+            assert!(err.to_string().contains("clipboard access not allowed"));
+        }
+
+        let allowed = SafetyConfig::new().with_allow_clipboard(true);
+        assert!(allowed.allow_clipboard);
+    }
+
+    #[test]
+    fn test_guard_restore_without_changes() {
+        let guard = TerminalGuard::new();
+        assert!(!guard.is_raw_mode());
+        guard.disable_raw_mode().unwrap();
+        guard.leave_alternate_screen().unwrap();
+        drop(guard);
+        restore().unwrap();
+    }
+
+    #[test]
+    fn test_clear_and_cursor_arguments() {
+        assert_eq!(parse_clear_type("below"), Some(ClearType::FromCursorDown));
+        assert_eq!(parse_clear_type("sideways"), None);
+
+        let sandbox = fusabi_host::Sandbox::new(fusabi_host::SandboxConfig::default()).unwrap();
+        let ctx = ExecutionContext::new(
+            1,
+            fusabi_host::Capabilities::none(),
+            fusabi_host::Limits::default(),
+            sandbox,
+        );
+        assert!(clear(&[Value::String("sideways".into())], &ctx).is_err());
+        assert!(set_cursor(&[Value::Int(1)], &ctx).is_err());
+        assert!(set_cursor(&[Value::Int(-1), Value::Int(0)], &ctx).is_err());
+        assert!(set_cursor(&[Value::Int(70_000), Value::Int(0)], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_colorize_styles() {
+        let sandbox = fusabi_host::Sandbox::new(fusabi_host::SandboxConfig::default()).unwrap();
+        let ctx = ExecutionContext::new(
+            1,
+            fusabi_host::Capabilities::none(),
+            fusabi_host::Limits::default(),
+            sandbox,
+        );
+        let paint = |style: Value| colorize(&[Value::String("hi".into()), style], &ctx);
+
+        assert_eq!(
+            paint(Value::String("Red".into())).unwrap(),
+            Value::String("\x1b[31mhi\x1b[0m".into())
+        );
+        assert_eq!(
+            paint(Value::Int(208)).unwrap(),
+            Value::String("\x1b[38;5;208mhi\x1b[0m".into())
+        );
+
+        let mut options = HashMap::new();
+        options.insert("fg".to_string(), Value::String("#ff8800".into()));
+        options.insert("bg".to_string(), Value::String("bright_black".into()));
+        options.insert("bold".to_string(), Value::Bool(true));
+        options.insert("underline".to_string(), Value::Bool(false));
+        let styled = paint(Value::Map(options)).unwrap();
+        assert_eq!(
+            styled,
+            Value::String("\x1b[1;38;2;255;136;0;100mhi\x1b[0m".into())
+        );
+        assert_eq!(
+            strip_ansi(&[styled], &ctx).unwrap(),
+            Value::String("hi".into())
+        );
+
+        assert!(paint(Value::String("#12345".into())).is_err());
+        assert!(paint(Value::String("mauve".into())).is_err());
+        assert!(paint(Value::Int(256)).is_err());
+    }
+
+    #[test]
+    fn test_strip_ansi_codes() {
+        assert_eq!(strip_ansi_codes("plain"), "plain");
+        assert_eq!(strip_ansi_codes("\x1b[1;31mred\x1b[0m \x1b[2K"), "red ");
+        assert_eq!(
+            strip_ansi_codes("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07"),
+            "link"
+        );
+        assert_eq!(strip_ansi_codes("\x1b(Bab\x1b7c"), "abc");
+    }
+
+    #[test]
+    fn test_event_to_value() {
+        let mouse = Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Drag(MouseButton::Left),
+            column: 4,
+            row: 7,
+            modifiers: KeyModifiers::SHIFT,
+        });
+        let Value::Map(fields) = event_to_value(&mouse) else {
+            panic!("expected map");
+        };
+        assert_eq!(fields["type"], Value::String("mouse".into()));
+        assert_eq!(fields["kind"], Value::String("drag".into()));
+        assert_eq!(fields["button"], Value::String("left".into()));
+        assert_eq!(
+            (&fields["x"], &fields["y"]),
+            (&Value::Int(4), &Value::Int(7))
+        );
+        assert_eq!(
+            fields["modifiers"],
+            Value::List(vec![Value::String("shift".into())])
+        );
+
+        let scroll = Event::Mouse(MouseEvent {
+            kind: MouseEventKind::ScrollDown,
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        });
+        let Value::Map(fields) = event_to_value(&scroll) else {
+            panic!("expected map");
+        };
+        assert_eq!(fields["kind"], Value::String("scroll_down".into()));
+        assert_eq!(fields["button"], Value::Null);
+
+        let Value::Map(fields) = event_to_value(&Event::Resize(120, 40)) else {
+            panic!("expected map");
+        };
+        assert_eq!(fields["type"], Value::String("resize".into()));
+        assert_eq!(fields["width"], Value::Int(120));
+
+        let Value::Map(fields) = event_to_value(&Event::Paste("hi".into())) else {
+            panic!("expected map");
+        };
+        assert_eq!(fields["text"], Value::String("hi".into()));
+
+        let key = Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+        let Value::Map(fields) = event_to_value(&key) else {
+            panic!("expected map");
+        };
+        assert_eq!(fields["type"], Value::String("key".into()));
+        assert_eq!(fields["code"], Value::String("Enter".into()));
+    }
 }
@@ -0,0 +1,422 @@
+//! Terminal input as stream handles.
+//!
+//! An event stream is fed by a background reader thread, so a script can
+//! check for input without blocking and service other handles, such as
+//! `fs_stream` tails, `k8s.pod_logs` streams and `time` timers, in the
+//! same loop:
+//!
+//! ```text
+//! let keys = terminal.events_open({ types: ["key", "resize"] })
+//! let log = fs_stream.tail("/var/log/app.log", 100)
+//! loop {
+//!     let event = terminal.events_next(keys, 50)
+//!     if event != null && event.code == "q" { break }
+//!     let line = fs_stream.read_line(log)
+//!     if line != null { print(line) }
+//! }
+//! terminal.events_close(keys)
+//! ```
+//!
+//! Every open stream receives every event of the types it asked for. While
+//! any stream is open the reader thread owns terminal input, so
+//! `terminal.read_key` and `terminal.next_event` should not be used at the
+//! same time. The thread stops once the last stream is closed.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event};
+use parking_lot::{Condvar, Mutex};
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use super::event_to_value;
+
+/// Event types a stream can subscribe to, as in the `type` field of
+/// [`next_event`](super::next_event) maps.
+pub const EVENT_TYPES: &[&str] = &["key", "mouse", "resize", "paste", "focus"];
+
+/// Events a stream buffers before dropping the oldest.
+pub const DEFAULT_BUFFER_EVENTS: usize = 1024;
+
+/// How long the reader thread waits for input before checking whether any
+/// stream is still open.
+const READER_POLL: Duration = Duration::from_millis(100);
+
+/// Events delivered to one stream and not yet read.
+struct EventQueue {
+    types: Option<HashSet<String>>,
+    events: Mutex<VecDeque<Value>>,
+    capacity: usize,
+    ready: Condvar,
+    dropped: AtomicI64,
+}
+
+impl EventQueue {
+    fn accepts(&self, kind: &str) -> bool {
+        self.types
+            .as_ref()
+            .map_or(true, |types| types.contains(kind))
+    }
+
+    fn push(&self, event: Value) {
+        let mut events = self.events.lock();
+        if events.len() == self.capacity {
+            events.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        events.push_back(event);
+        self.ready.notify_all();
+    }
+
+    /// Take the next event, waiting until `deadline` (if any) for one.
+    fn pop(&self, deadline: Option<Instant>) -> Option<Value> {
+        let mut events = self.events.lock();
+        loop {
+            if let Some(event) = events.pop_front() {
+                return Some(event);
+            }
+            match deadline {
+                Some(deadline) if Instant::now() < deadline => {
+                    self.ready.wait_until(&mut events, deadline);
+                }
+                Some(_) => return None,
+                None => self.ready.wait(&mut events),
+            }
+        }
+    }
+}
+
+static STREAMS: OnceLock<Mutex<HashMap<i64, Arc<EventQueue>>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+/// Whether the reader thread is running.
+static READER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+fn streams() -> &'static Mutex<HashMap<i64, Arc<EventQueue>>> {
+    STREAMS.get_or_init(Default::default)
+}
+
+/// Number of open event streams.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    streams().lock().len()
+}
+
+/// Open a stream receiving events of `types` (all types if `None`).
+fn subscribe(types: Option<HashSet<String>>, capacity: usize) -> i64 {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    let queue = EventQueue {
+        types,
+        events: Mutex::new(VecDeque::new()),
+        capacity: capacity.max(1),
+        ready: Condvar::new(),
+        dropped: AtomicI64::new(0),
+    };
+    streams().lock().insert(handle, Arc::new(queue));
+    handle
+}
+
+/// Deliver an event to every stream subscribed to its type.
+fn dispatch(event: &Event) {
+    let value = event_to_value(event);
+    let kind = match value.as_map().and_then(|m| m.get("type")) {
+        Some(Value::String(kind)) => kind.clone(),
+        _ => return,
+    };
+    for queue in streams().lock().values() {
+        if queue.accepts(&kind) {
+            queue.push(value.clone());
+        }
+    }
+}
+
+/// Start the reader thread unless it is running.
+fn ensure_reader() -> std::io::Result<()> {
+    if READER_RUNNING.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let spawned = std::thread::Builder::new()
+        .name("fusabi-terminal-events".to_string())
+        .spawn(|| loop {
+            if streams().lock().is_empty() {
+                READER_RUNNING.store(false, Ordering::SeqCst);
+                // Keep going if a stream was opened meanwhile and no other
+                // reader has started for it.
+                if streams().lock().is_empty() || READER_RUNNING.swap(true, Ordering::SeqCst) {
+                    return;
+                }
+            }
+            let event = match event::poll(READER_POLL) {
+                Ok(true) => event::read(),
+                Ok(false) => continue,
+                Err(e) => Err(e),
+            };
+            match event {
+                Ok(event) => dispatch(&event),
+                Err(e) => {
+                    tracing::warn!("terminal event reader stopped: {}", e);
+                    READER_RUNNING.store(false, Ordering::SeqCst);
+                    return;
+                }
+            }
+        });
+    if let Err(e) = spawned {
+        READER_RUNNING.store(false, Ordering::SeqCst);
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn queue(args: &[Value], function: &str) -> Result<Arc<EventQueue>> {
+    let handle = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing handle argument", function)))?;
+    streams()
+        .lock()
+        .get(&handle)
+        .cloned()
+        .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))
+}
+
+/// Open a stream of terminal events.
+///
+/// Enable raw mode first to receive keys as they are pressed.
+///
+/// # Arguments
+///
+/// * `args[0]` - Options map (optional):
+///   - `types`: list of event types to receive (`"key"`, `"mouse"`,
+///     `"resize"`, `"paste"`, `"focus"`; default all)
+///   - `buffer`: events kept before the oldest are dropped (default 1024)
+///
+/// # Returns
+///
+/// Stream handle (integer)
+pub fn events_open(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "terminal.events_open";
+
+    let options = match args.first() {
+        None | Some(Value::Null) => None,
+        Some(Value::Map(map)) => Some(map),
+        Some(_) => {
+            return Err(Error::host_function(format!(
+                "{}: options must be a map",
+                FUNCTION
+            )))
+        }
+    };
+
+    let types = match options.and_then(|o| o.get("types")) {
+        None | Some(Value::Null) => None,
+        Some(Value::List(items)) => {
+            let mut types = HashSet::new();
+            for item in items {
+                match item.as_str() {
+                    Some(kind) if EVENT_TYPES.contains(&kind) => {
+                        types.insert(kind.to_string());
+                    }
+                    _ => {
+                        return Err(Error::host_function(format!(
+                            "{}: unknown event type: {}",
+                            FUNCTION, item
+                        )))
+                    }
+                }
+            }
+            Some(types)
+        }
+        Some(_) => {
+            return Err(Error::host_function(format!(
+                "{}: types must be a list",
+                FUNCTION
+            )))
+        }
+    };
+    let capacity = match options.and_then(|o| o.get("buffer")) {
+        None | Some(Value::Null) => DEFAULT_BUFFER_EVENTS,
+        Some(Value::Int(n)) if *n > 0 => *n as usize,
+        Some(_) => {
+            return Err(Error::host_function(format!(
+                "{}: buffer must be a positive integer",
+                FUNCTION
+            )))
+        }
+    };
+
+    let handle = subscribe(types, capacity);
+    if let Err(e) = ensure_reader() {
+        streams().lock().remove(&handle);
+        return Err(Error::host_function(format!("{}: {}", FUNCTION, e)));
+    }
+    Ok(Value::Int(handle))
+}
+
+/// Take the next event of a stream without blocking.
+///
+/// # Arguments
+///
+/// * `args[0]` - Stream handle
+///
+/// # Returns
+///
+/// Event map as returned by `terminal.next_event`, or null if none is
+/// waiting
+pub fn events_poll(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let queue = queue(args, "terminal.events_poll")?;
+    Ok(queue.pop(Some(Instant::now())).unwrap_or(Value::Null))
+}
+
+/// Take the next event of a stream, waiting at most the given time.
+///
+/// # Arguments
+///
+/// * `args[0]` - Stream handle
+/// * `args[1]` - Timeout in milliseconds (optional, default: wait
+///   indefinitely; 0 polls without waiting)
+///
+/// # Returns
+///
+/// Event map, or null if none arrived in time
+pub fn events_next(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    const FUNCTION: &str = "terminal.events_next";
+
+    let queue = queue(args, FUNCTION)?;
+    let deadline = match args.get(1) {
+        None | Some(Value::Null) => None,
+        Some(value) => {
+            let ms = value.as_int().filter(|ms| *ms >= 0).ok_or_else(|| {
+                Error::host_function(format!(
+                    "{}: timeout must be a non-negative integer",
+                    FUNCTION
+                ))
+            })?;
+            Some(Instant::now() + Duration::from_millis(ms as u64))
+        }
+    };
+    Ok(queue.pop(deadline).unwrap_or(Value::Null))
+}
+
+/// Take every waiting event of a stream without blocking.
+///
+/// # Arguments
+///
+/// * `args[0]` - Stream handle
+///
+/// # Returns
+///
+/// List of event maps (may be empty)
+pub fn events_drain(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let queue = queue(args, "terminal.events_drain")?;
+    let events: Vec<Value> = queue.events.lock().drain(..).collect();
+    Ok(Value::List(events))
+}
+
+/// Close an event stream.
+///
+/// # Arguments
+///
+/// * `args[0]` - Stream handle
+///
+/// # Returns
+///
+/// Number of events dropped because the buffer was full
+pub fn events_close(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let queue = queue(args, "terminal.events_close")?;
+    if let Some(handle) = args.first().and_then(|v| v.as_int()) {
+        streams().lock().remove(&handle);
+    }
+    // Wake a waiter so it does not outlive the handle by long.
+    queue.ready.notify_all();
+    Ok(Value::Int(queue.dropped.load(Ordering::Relaxed)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+
+    fn test_ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn test_event_streams() {
+        let ctx = test_ctx();
+        let keys = [Value::Int(subscribe(
+            Some(HashSet::from(["key".to_string()])),
+            2,
+        ))];
+        let all = [Value::Int(subscribe(None, 10))];
+
+        dispatch(&key('a'));
+        dispatch(&Event::Resize(80, 24));
+        dispatch(&key('b'));
+        dispatch(&key('c'));
+
+        // The key stream skipped the resize and kept its newest two keys
+        let code = |event: Value| match event.as_map().and_then(|m| m.get("code")) {
+            Some(Value::String(code)) => code.clone(),
+            _ => panic!("expected a key event"),
+        };
+        assert_eq!(code(events_poll(&keys, &ctx).unwrap()), "b");
+        assert_eq!(code(events_next(&keys, &ctx).unwrap()), "c");
+        assert_eq!(events_poll(&keys, &ctx).unwrap(), Value::Null);
+        assert_eq!(events_close(&keys, &ctx).unwrap(), Value::Int(1));
+        assert!(events_poll(&keys, &ctx).is_err());
+
+        let Value::List(events) = events_drain(&all, &ctx).unwrap() else {
+            panic!("expected a list");
+        };
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[1].as_map().unwrap().get("type"),
+            Some(&Value::String("resize".into()))
+        );
+
+        // A waiting reader is woken by the next event
+        let waiter = std::thread::spawn({
+            let all = all.clone();
+            move || events_next(&[all[0].clone(), Value::Int(5000)], &test_ctx())
+        });
+        std::thread::sleep(Duration::from_millis(20));
+        dispatch(&key('d'));
+        assert_eq!(code(waiter.join().unwrap().unwrap()), "d");
+
+        let start = Instant::now();
+        assert_eq!(
+            events_next(&[all[0].clone(), Value::Int(10)], &ctx).unwrap(),
+            Value::Null
+        );
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        events_close(&all, &ctx).unwrap();
+    }
+
+    #[test]
+    fn test_events_open_options() {
+        let ctx = test_ctx();
+        let options = |key: &str, value: Value| [Value::Map(HashMap::from([(key.into(), value)]))];
+        assert!(events_open(
+            &options("types", Value::List(vec![Value::String("keys".into())])),
+            &ctx
+        )
+        .is_err());
+        assert!(events_open(&options("buffer", Value::Int(0)), &ctx).is_err());
+        assert!(events_open(&[Value::Int(1)], &ctx).is_err());
+    }
+}
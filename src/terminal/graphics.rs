@@ -0,0 +1,533 @@
+//! Inline image output.
+//!
+//! Images are drawn with the best graphics protocol the terminal supports:
+//!
+//! - [kitty graphics protocol](https://sw.kovidgoyal.net/kitty/graphics-protocol/)
+//!   (kitty, WezTerm, Ghostty)
+//! - iTerm2 inline images (iTerm2, WezTerm)
+//! - sixel (foot, mlterm, and terminals advertising sixel in `TERM`)
+//! - Unicode braille dots, or ASCII shading where UTF-8 is unavailable
+//!
+//! Support is detected from the environment (`TERM`, `TERM_PROGRAM`,
+//! `LC_TERMINAL`, `KITTY_WINDOW_ID`, locale variables), and can be
+//! overridden with the `protocol` option.
+
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat, RgbaImage};
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use crate::safety::SafetyConfig;
+
+/// Approximate cell size in pixels, used to size sixel output.
+const CELL_WIDTH_PX: u32 = 10;
+const CELL_HEIGHT_PX: u32 = 20;
+
+/// Width in cells used when none is given and the terminal size is unknown.
+const DEFAULT_WIDTH: u32 = 80;
+
+/// Terminal graphics protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    /// Kitty graphics protocol.
+    Kitty,
+    /// iTerm2 inline images.
+    Iterm2,
+    /// DEC sixel graphics.
+    Sixel,
+    /// Unicode braille dots (2x4 pixels per cell).
+    Braille,
+    /// ASCII shading (1x2 pixels per cell).
+    Ascii,
+}
+
+impl ImageProtocol {
+    /// Convert to string representation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImageProtocol::Kitty => "kitty",
+            ImageProtocol::Iterm2 => "iterm2",
+            ImageProtocol::Sixel => "sixel",
+            ImageProtocol::Braille => "braille",
+            ImageProtocol::Ascii => "ascii",
+        }
+    }
+
+    /// Parse from string representation.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "kitty" => Some(ImageProtocol::Kitty),
+            "iterm2" | "iterm" => Some(ImageProtocol::Iterm2),
+            "sixel" => Some(ImageProtocol::Sixel),
+            "braille" => Some(ImageProtocol::Braille),
+            "ascii" => Some(ImageProtocol::Ascii),
+            _ => None,
+        }
+    }
+
+    /// Detect the best protocol from environment variables.
+    pub fn detect() -> Self {
+        Self::detect_from(|name| std::env::var(name).ok())
+    }
+
+    /// Detect the best protocol using `env` to look up variables.
+    pub fn detect_from(env: impl Fn(&str) -> Option<String>) -> Self {
+        let term = env("TERM").unwrap_or_default().to_ascii_lowercase();
+        let program = env("TERM_PROGRAM").unwrap_or_default();
+
+        if env("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "WezTerm"
+            || program == "ghostty"
+        {
+            return ImageProtocol::Kitty;
+        }
+        if program == "iTerm.app" || env("LC_TERMINAL").as_deref() == Some("iTerm2") {
+            return ImageProtocol::Iterm2;
+        }
+        if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            return ImageProtocol::Sixel;
+        }
+
+        let utf8 = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .find_map(|name| env(name).filter(|v| !v.is_empty()))
+            .is_some_and(|locale| {
+                let locale = locale.to_ascii_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            });
+        if utf8 {
+            ImageProtocol::Braille
+        } else {
+            ImageProtocol::Ascii
+        }
+    }
+}
+
+/// Options for rendering an image.
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// Protocol to use.
+    pub protocol: ImageProtocol,
+    /// Width in terminal cells.
+    pub width: u32,
+    /// Maximum height in terminal cells (None = unbounded).
+    pub height: Option<u32>,
+}
+
+impl ImageOptions {
+    /// Create options for the given protocol and width in cells.
+    pub fn new(protocol: ImageProtocol, width: u32) -> Self {
+        Self {
+            protocol,
+            width: width.max(1),
+            height: None,
+        }
+    }
+
+    /// Limit the height in cells.
+    pub fn with_height(mut self, height: u32) -> Self {
+        self.height = Some(height.max(1));
+        self
+    }
+}
+
+/// Render an image as terminal output.
+pub fn render(image: &DynamicImage, options: &ImageOptions) -> crate::Result<String> {
+    let (cols, rows) = cell_size(image, options);
+
+    match options.protocol {
+        ImageProtocol::Kitty => Ok(render_kitty(&encode_png(image)?, cols, rows)),
+        ImageProtocol::Iterm2 => Ok(render_iterm2(&encode_png(image)?, cols, rows)),
+        ImageProtocol::Sixel => {
+            let scaled = image.resize_exact(
+                cols * CELL_WIDTH_PX,
+                rows * CELL_HEIGHT_PX,
+                FilterType::Triangle,
+            );
+            Ok(render_sixel(&scaled.to_rgba8()))
+        }
+        ImageProtocol::Braille => {
+            let scaled = image.resize_exact(cols * 2, rows * 4, FilterType::Triangle);
+            Ok(render_braille(&scaled.to_rgba8()))
+        }
+        ImageProtocol::Ascii => {
+            let scaled = image.resize_exact(cols, rows * 2, FilterType::Triangle);
+            Ok(render_ascii(&scaled.to_rgba8()))
+        }
+    }
+}
+
+/// Output size in cells, keeping the aspect ratio (cells are twice as tall
+/// as they are wide).
+fn cell_size(image: &DynamicImage, options: &ImageOptions) -> (u32, u32) {
+    let (w, h) = image.dimensions();
+    let mut cols = options.width.max(1);
+    let mut rows = ((h as f64 / w.max(1) as f64) * cols as f64 / 2.0).round() as u32;
+    if let Some(max_rows) = options.height {
+        if rows > max_rows {
+            cols = ((cols as f64 * max_rows as f64 / rows as f64).round() as u32).max(1);
+            rows = max_rows;
+        }
+    }
+    (cols, rows.max(1))
+}
+
+fn encode_png(image: &DynamicImage) -> crate::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| crate::Error::format(format!("png encoding failed: {}", e)))?;
+    Ok(png)
+}
+
+fn render_kitty(png: &[u8], cols: u32, rows: u32) -> String {
+    let data = base64(png);
+    let chunks: Vec<&str> = data
+        .as_bytes()
+        .chunks(4096)
+        .map(|c| std::str::from_utf8(c).unwrap_or_default())
+        .collect();
+
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            let _ = write!(out, "\x1b_Gf=100,a=T,c={},r={},m={};", cols, rows, more);
+        } else {
+            let _ = write!(out, "\x1b_Gm={};", more);
+        }
+        out.push_str(chunk);
+        out.push_str("\x1b\\");
+    }
+    out.push('\n');
+    out
+}
+
+fn render_iterm2(png: &[u8], cols: u32, rows: u32) -> String {
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07\n",
+        png.len(),
+        cols,
+        rows,
+        base64(png)
+    )
+}
+
+/// Encode as sixel, using a 6x6x6 color cube palette. Pixels that are
+/// mostly transparent are left unset.
+fn render_sixel(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let level = |c: u8| (c as u32 * 5 + 127) / 255;
+    let color_index = |x: u32, y: u32| {
+        let p = image.get_pixel(x, y).0;
+        (p[3] >= 128).then(|| (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as usize)
+    };
+
+    let mut out = String::new();
+    let _ = write!(out, "\x1bPq\"1;1;{};{}", width, height);
+    for i in 0..216u32 {
+        let pct = |l: u32| l * 100 / 5;
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            i,
+            pct(i / 36),
+            pct(i / 6 % 6),
+            pct(i % 6)
+        );
+    }
+
+    for band in (0..height).step_by(6) {
+        let mut used = [false; 216];
+        for y in band..(band + 6).min(height) {
+            for x in 0..width {
+                if let Some(c) = color_index(x, y) {
+                    used[c] = true;
+                }
+            }
+        }
+
+        let mut first = true;
+        for color in (0..216).filter(|c| used[*c]) {
+            if !first {
+                out.push('$');
+            }
+            first = false;
+            let _ = write!(out, "#{}", color);
+
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let mut bits = 0u8;
+                for dy in 0..6 {
+                    let y = band + dy;
+                    if y < height && color_index(x, y) == Some(color) {
+                        bits |= 1 << dy;
+                    }
+                }
+                let ch = (b'?' + bits) as char;
+                run = match run {
+                    Some((c, n)) if c == ch => Some((c, n + 1)),
+                    Some((c, n)) => {
+                        push_sixel_run(&mut out, c, n);
+                        Some((ch, 1))
+                    }
+                    None => Some((ch, 1)),
+                };
+            }
+            if let Some((c, n)) = run {
+                push_sixel_run(&mut out, c, n);
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\\n");
+    out
+}
+
+fn push_sixel_run(out: &mut String, c: char, n: usize) {
+    if n > 3 {
+        let _ = write!(out, "!{}{}", n, c);
+    } else {
+        out.extend(std::iter::repeat(c).take(n));
+    }
+}
+
+fn luminance(p: [u8; 4]) -> f64 {
+    if p[3] < 128 {
+        return 0.0;
+    }
+    0.2126 * p[0] as f64 + 0.7152 * p[1] as f64 + 0.0722 * p[2] as f64
+}
+
+/// Render with braille dots, lit where a pixel is brighter than the image
+/// average.
+fn render_braille(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let total: f64 = image.pixels().map(|p| luminance(p.0)).sum();
+    let threshold = total / (width * height).max(1) as f64;
+
+    // Dot bit for each (dx, dy) in a 2x4 cell.
+    const DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+    let mut out = String::new();
+    for cy in (0..height).step_by(4) {
+        for cx in (0..width).step_by(2) {
+            let mut bits = 0;
+            for (dx, column) in DOTS.iter().enumerate() {
+                for (dy, bit) in column.iter().enumerate() {
+                    let (x, y) = (cx + dx as u32, cy + dy as u32);
+                    if x < width && y < height && luminance(image.get_pixel(x, y).0) > threshold {
+                        bits |= bit;
+                    }
+                }
+            }
+            out.push(char::from_u32(0x2800 + bits).unwrap_or(' '));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Render with ASCII shading, one cell per horizontal pixel pair.
+fn render_ascii(image: &RgbaImage) -> String {
+    const RAMP: &[u8] = b" .:-=+*#%@";
+
+    let (width, height) = image.dimensions();
+    let mut out = String::new();
+    for cy in (0..height).step_by(2) {
+        for x in 0..width {
+            let mut sum = luminance(image.get_pixel(x, cy).0);
+            let mut n = 1.0;
+            if cy + 1 < height {
+                sum += luminance(image.get_pixel(x, cy + 1).0);
+                n += 1.0;
+            }
+            let index = ((sum / n / 255.0) * (RAMP.len() - 1) as f64).round() as usize;
+            out.push(RAMP[index.min(RAMP.len() - 1)] as char);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Display an image inline in the terminal.
+///
+/// # Arguments
+///
+/// * `args[0]` - Image file path (must be readable under the path
+///   allowlist) or image bytes (PNG or JPEG)
+/// * `args[1]` - Options map (optional):
+///   - `protocol`: `"kitty"`, `"iterm2"`, `"sixel"`, `"braille"`, or
+///     `"ascii"` (default: detected)
+///   - `width`: Width in cells (default: terminal width, at most 80)
+///   - `height`: Maximum height in cells
+///
+/// # Returns
+///
+/// Name of the protocol used
+pub fn show_image(
+    safety: &Arc<SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let image = match args.first() {
+        Some(Value::String(path)) => {
+            let path = Path::new(path);
+            safety
+                .paths
+                .check_read(path)
+                .map_err(|e| Error::host_function(e.to_string()))?;
+            image::open(path)
+                .map_err(|e| Error::host_function(format!("terminal.show_image: {}", e)))?
+        }
+        Some(Value::Bytes(bytes)) => image::load_from_memory(bytes)
+            .map_err(|e| Error::host_function(format!("terminal.show_image: {}", e)))?,
+        _ => {
+            return Err(Error::host_function(
+                "terminal.show_image: expected a path or image bytes",
+            ))
+        }
+    };
+
+    let options = args.get(1).and_then(|v| v.as_map());
+    let option = |key: &str| options.and_then(|m| m.get(key));
+
+    let protocol = match option("protocol").and_then(|v| v.as_str()) {
+        Some(name) => ImageProtocol::parse(name).ok_or_else(|| {
+            Error::host_function(format!("terminal.show_image: unknown protocol '{}'", name))
+        })?,
+        None => ImageProtocol::detect(),
+    };
+    let width = match option("width").and_then(|v| v.as_int()) {
+        Some(width) if width > 0 => width as u32,
+        Some(_) => {
+            return Err(Error::host_function(
+                "terminal.show_image: width must be positive",
+            ))
+        }
+        None => crossterm::terminal::size()
+            .map(|(cols, _)| u32::from(cols).min(DEFAULT_WIDTH))
+            .unwrap_or(DEFAULT_WIDTH),
+    };
+
+    let mut image_options = ImageOptions::new(protocol, width);
+    if let Some(height) = option("height").and_then(|v| v.as_int()) {
+        image_options = image_options.with_height(height.max(1) as u32);
+    }
+
+    let output = render(&image, &image_options)
+        .map_err(|e| Error::host_function(format!("terminal.show_image: {}", e)))?;
+    let mut stdout = std::io::stdout().lock();
+    stdout
+        .write_all(output.as_bytes())
+        .and_then(|_| stdout.flush())
+        .map_err(|e| Error::host_function(format!("terminal.show_image: {}", e)))?;
+
+    Ok(Value::String(protocol.as_str().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> DynamicImage {
+        let image = RgbaImage::from_fn(16, 8, |x, _| {
+            let v = (x * 16) as u8;
+            image::Rgba([v, v, v, 255])
+        });
+        DynamicImage::ImageRgba8(image)
+    }
+
+    #[test]
+    fn test_detect_protocol() {
+        let detect = |vars: &[(&str, &str)]| {
+            let vars: Vec<(String, String)> = vars
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            ImageProtocol::detect_from(|name| {
+                vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+            })
+        };
+
+        assert_eq!(detect(&[("TERM", "xterm-kitty")]), ImageProtocol::Kitty);
+        assert_eq!(
+            detect(&[("TERM_PROGRAM", "iTerm.app")]),
+            ImageProtocol::Iterm2
+        );
+        assert_eq!(detect(&[("TERM", "foot")]), ImageProtocol::Sixel);
+        assert_eq!(
+            detect(&[("TERM", "xterm-256color"), ("LANG", "en_US.UTF-8")]),
+            ImageProtocol::Braille
+        );
+        assert_eq!(detect(&[("TERM", "dumb")]), ImageProtocol::Ascii);
+    }
+
+    #[test]
+    fn test_render_protocols() {
+        let image = gradient();
+
+        let kitty = render(&image, &ImageOptions::new(ImageProtocol::Kitty, 8)).unwrap();
+        assert!(kitty.starts_with("\x1b_Gf=100,a=T,c=8,r=2,m=0;iVBORw0KGgo"));
+
+        let iterm = render(&image, &ImageOptions::new(ImageProtocol::Iterm2, 8)).unwrap();
+        assert!(iterm.starts_with("\x1b]1337;File=inline=1;"));
+
+        let sixel = render(&image, &ImageOptions::new(ImageProtocol::Sixel, 2)).unwrap();
+        assert!(sixel.starts_with("\x1bPq\"1;1;20;20"));
+        assert!(sixel.ends_with("\x1b\\\n"));
+
+        let braille = render(&image, &ImageOptions::new(ImageProtocol::Braille, 8)).unwrap();
+        let lines: Vec<&str> = braille.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.chars().count() == 8));
+        // The right half of the gradient is brighter than average.
+        assert!(lines[0].starts_with('\u{2800}'));
+        assert!(lines[0].ends_with('\u{28ff}'));
+
+        let ascii = render(
+            &image,
+            &ImageOptions::new(ImageProtocol::Ascii, 16).with_height(2),
+        )
+        .unwrap();
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(' '));
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+}
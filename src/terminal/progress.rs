@@ -0,0 +1,488 @@
+//! Progress bars and spinners.
+//!
+//! A progress indicator redraws a single line on stderr in place, at most
+//! once per redraw interval, so updating it in a tight loop stays cheap.
+//! When stderr is not a terminal only the final line is written on finish,
+//! keeping logs free of intermediate frames.
+
+use std::collections::HashMap;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crossterm::queue;
+use crossterm::terminal::{Clear, ClearType};
+use parking_lot::Mutex;
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+const SPINNER_FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+const SPINNER_FRAME_MS: u128 = 80;
+
+/// A progress bar, or a spinner when there is no total.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    total: Option<u64>,
+    position: u64,
+    prefix: String,
+    message: String,
+    width: usize,
+    redraw_interval: Duration,
+    started: Instant,
+    last_draw: Option<Instant>,
+    interactive: bool,
+}
+
+impl Progress {
+    /// Create a bar counting up to `total`.
+    pub fn bar(total: u64) -> Self {
+        Self::new(Some(total))
+    }
+
+    /// Create a spinner for work of unknown length.
+    pub fn spinner() -> Self {
+        Self::new(None)
+    }
+
+    fn new(total: Option<u64>) -> Self {
+        Self {
+            total,
+            position: 0,
+            prefix: String::new(),
+            message: String::new(),
+            width: 30,
+            redraw_interval: Duration::from_millis(100),
+            started: Instant::now(),
+            last_draw: None,
+            interactive: std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// Set text shown before the bar.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Set text shown after the bar.
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = message.into();
+        self
+    }
+
+    /// Set the bar width in cells (default: 30).
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width.max(1);
+        self
+    }
+
+    /// Set the minimum time between redraws (default: 100ms).
+    pub fn with_redraw_interval(mut self, interval: Duration) -> Self {
+        self.redraw_interval = interval;
+        self
+    }
+
+    /// Current position.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Set the position, clamped to the total.
+    pub fn set_position(&mut self, position: u64) {
+        self.position = match self.total {
+            Some(total) => position.min(total),
+            None => position,
+        };
+    }
+
+    /// Advance the position.
+    pub fn inc(&mut self, delta: u64) {
+        self.set_position(self.position.saturating_add(delta));
+    }
+
+    /// Replace the message.
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+    }
+
+    /// Render the current line.
+    pub fn render(&self) -> String {
+        self.render_at(self.started.elapsed())
+    }
+
+    fn render_at(&self, elapsed: Duration) -> String {
+        let mut parts = Vec::new();
+
+        match self.total {
+            Some(total) => {
+                if !self.prefix.is_empty() {
+                    parts.push(self.prefix.clone());
+                }
+                let fraction = if total == 0 {
+                    1.0
+                } else {
+                    self.position as f64 / total as f64
+                };
+                parts.push(format!("[{}]", bar(fraction, self.width)));
+                parts.push(format!("{}/{}", self.position, total));
+                parts.push(format!("{:>3}%", (fraction * 100.0).floor() as u64));
+                if self.position > 0 && self.position < total {
+                    let remaining = elapsed.mul_f64((total - self.position) as f64)
+                        / self.position.min(u32::MAX as u64) as u32;
+                    parts.push(format!("eta {}", format_elapsed(remaining)));
+                }
+            }
+            None => {
+                let frame = (elapsed.as_millis() / SPINNER_FRAME_MS) as usize;
+                parts.push(SPINNER_FRAMES[frame % SPINNER_FRAMES.len()].to_string());
+                if !self.prefix.is_empty() {
+                    parts.push(self.prefix.clone());
+                }
+                if self.position > 0 {
+                    parts.push(self.position.to_string());
+                }
+                parts.push(format_elapsed(elapsed));
+            }
+        }
+
+        if !self.message.is_empty() {
+            parts.push(self.message.clone());
+        }
+        parts.join(" ")
+    }
+
+    /// Redraw if the redraw interval has passed since the last draw.
+    pub fn tick(&mut self) -> std::io::Result<()> {
+        let now = Instant::now();
+        let due = self.last_draw.map_or(true, |last| {
+            now.duration_since(last) >= self.redraw_interval
+        });
+        if due && self.interactive {
+            self.last_draw = Some(now);
+            self.draw(false)?;
+        }
+        Ok(())
+    }
+
+    /// Draw the final line and move to the next line.
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        if let Some(total) = self.total {
+            self.position = total;
+        }
+        self.draw(true)
+    }
+
+    fn draw(&self, last: bool) -> std::io::Result<()> {
+        let mut stderr = std::io::stderr().lock();
+        if self.interactive {
+            queue!(stderr, Clear(ClearType::CurrentLine))?;
+            write!(stderr, "\r{}", self.render())?;
+        } else {
+            write!(stderr, "{}", self.render())?;
+        }
+        if last {
+            writeln!(stderr)?;
+        }
+        stderr.flush()
+    }
+}
+
+fn bar(fraction: f64, width: usize) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * width as f64).floor() as usize).min(width);
+    let mut bar = "=".repeat(filled);
+    if filled < width {
+        bar.push(if filled > 0 { '>' } else { ' ' });
+        bar.push_str(&" ".repeat(width - filled - 1));
+    }
+    bar
+}
+
+fn format_elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+static PROGRESS: OnceLock<Mutex<HashMap<i64, Progress>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn progress_table() -> &'static Mutex<HashMap<i64, Progress>> {
+    PROGRESS.get_or_init(Default::default)
+}
+
+/// Number of progress indicators not yet finished.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    progress_table().lock().len()
+}
+
+fn insert_progress(progress: Progress) -> i64 {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    progress_table().lock().insert(handle, progress);
+    handle
+}
+
+fn progress_handle(args: &[Value], function: &str) -> Result<i64> {
+    args.first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing handle argument", function)))
+}
+
+fn with_progress<T>(
+    handle: i64,
+    function: &str,
+    f: impl FnOnce(&mut Progress) -> std::io::Result<T>,
+) -> Result<T> {
+    let mut table = progress_table().lock();
+    let progress = table
+        .get_mut(&handle)
+        .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))?;
+    f(progress).map_err(|e| Error::host_function(format!("{}: {}", function, e)))
+}
+
+fn apply_options(
+    mut progress: Progress,
+    options: Option<&Value>,
+    function: &str,
+) -> Result<Progress> {
+    let Some(options) = options.and_then(|v| v.as_map()) else {
+        return Ok(progress);
+    };
+    if let Some(prefix) = options.get("prefix").and_then(|v| v.as_str()) {
+        progress = progress.with_prefix(prefix);
+    }
+    if let Some(message) = options.get("message").and_then(|v| v.as_str()) {
+        progress = progress.with_message(message);
+    }
+    if let Some(width) = options.get("width").and_then(|v| v.as_int()) {
+        let width = usize::try_from(width)
+            .map_err(|_| Error::host_function(format!("{}: invalid width: {}", function, width)))?;
+        progress = progress.with_width(width);
+    }
+    if let Some(ms) = options.get("redraw_ms").and_then(|v| v.as_int()) {
+        let ms = u64::try_from(ms).map_err(|_| {
+            Error::host_function(format!("{}: invalid redraw_ms: {}", function, ms))
+        })?;
+        progress = progress.with_redraw_interval(Duration::from_millis(ms));
+    }
+    Ok(progress)
+}
+
+/// Create a progress bar.
+///
+/// # Arguments
+///
+/// * `args[0]` - Total (integer), or null for a spinner
+/// * `args[1]` - Options map (optional): `prefix`, `message`, `width`
+///   (cells, default 30), `redraw_ms` (minimum time between redraws,
+///   default 100)
+///
+/// # Returns
+///
+/// Progress handle (integer)
+pub fn progress_new(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let progress = match args.first() {
+        None | Some(Value::Null) => Progress::spinner(),
+        Some(value) => {
+            let total = value.as_int().ok_or_else(|| {
+                Error::host_function("terminal.progress_new: total must be an integer")
+            })?;
+            let total = u64::try_from(total).map_err(|_| {
+                Error::host_function(format!("terminal.progress_new: invalid total: {}", total))
+            })?;
+            Progress::bar(total)
+        }
+    };
+    let mut progress = apply_options(progress, args.get(1), "terminal.progress_new")?;
+    progress
+        .tick()
+        .map_err(|e| Error::host_function(format!("terminal.progress_new: {}", e)))?;
+    Ok(Value::Int(insert_progress(progress)))
+}
+
+/// Create a spinner for work of unknown length.
+///
+/// The spinner animates as it is updated with `progress_inc`,
+/// `progress_tick`, or `progress_message`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Options map (optional), as for `progress_new`
+///
+/// # Returns
+///
+/// Progress handle (integer)
+pub fn spinner_new(args: &[Value], ctx: &ExecutionContext) -> Result<Value> {
+    let options = args.first().cloned().unwrap_or(Value::Null);
+    progress_new(&[Value::Null, options], ctx)
+}
+
+/// Set a progress bar's position.
+///
+/// # Arguments
+///
+/// * `args[0]` - Progress handle
+/// * `args[1]` - Position (clamped to the total)
+pub fn progress_set(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = progress_handle(args, "terminal.progress_set")?;
+    let position = args
+        .get(1)
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function("terminal.progress_set: missing position argument"))?;
+
+    with_progress(handle, "terminal.progress_set", |progress| {
+        progress.set_position(position.max(0) as u64);
+        progress.tick()
+    })?;
+    Ok(Value::Null)
+}
+
+/// Advance a progress bar or spinner.
+///
+/// # Arguments
+///
+/// * `args[0]` - Progress handle
+/// * `args[1]` - Amount to add (optional, default: 1)
+///
+/// # Returns
+///
+/// New position
+pub fn progress_inc(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = progress_handle(args, "terminal.progress_inc")?;
+    let delta = args.get(1).and_then(|v| v.as_int()).unwrap_or(1);
+
+    let position = with_progress(handle, "terminal.progress_inc", |progress| {
+        progress.inc(delta.max(0) as u64);
+        progress.tick()?;
+        Ok(progress.position())
+    })?;
+    Ok(Value::Int(position as i64))
+}
+
+/// Redraw a progress bar or spinner without changing its position.
+///
+/// # Arguments
+///
+/// * `args[0]` - Progress handle
+pub fn progress_tick(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = progress_handle(args, "terminal.progress_tick")?;
+    with_progress(handle, "terminal.progress_tick", Progress::tick)?;
+    Ok(Value::Null)
+}
+
+/// Replace the message shown after a progress bar or spinner.
+///
+/// # Arguments
+///
+/// * `args[0]` - Progress handle
+/// * `args[1]` - Message
+pub fn progress_message(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = progress_handle(args, "terminal.progress_message")?;
+    let message = args.get(1).and_then(|v| v.as_str()).ok_or_else(|| {
+        Error::host_function("terminal.progress_message: missing message argument")
+    })?;
+
+    with_progress(handle, "terminal.progress_message", |progress| {
+        progress.set_message(message);
+        progress.tick()
+    })?;
+    Ok(Value::Null)
+}
+
+/// Finish a progress bar or spinner, drawing its final line.
+///
+/// The handle is released.
+///
+/// # Arguments
+///
+/// * `args[0]` - Progress handle
+/// * `args[1]` - Final message (optional)
+pub fn progress_finish(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = progress_handle(args, "terminal.progress_finish")?;
+    let mut progress = progress_table()
+        .lock()
+        .remove(&handle)
+        .ok_or_else(|| Error::host_function("terminal.progress_finish: invalid handle"))?;
+
+    if let Some(message) = args.get(1).and_then(|v| v.as_str()) {
+        progress.set_message(message);
+    }
+    progress
+        .finish()
+        .map_err(|e| Error::host_function(format!("terminal.progress_finish: {}", e)))?;
+    Ok(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_bar() {
+        let mut progress = Progress::bar(100).with_prefix("sync").with_width(10);
+        progress.set_position(45);
+        progress.set_message("files");
+        assert_eq!(
+            progress.render_at(Duration::from_secs(9)),
+            "sync [====>     ] 45/100  45% eta 00:11 files"
+        );
+
+        progress.inc(1_000);
+        assert_eq!(progress.position(), 100);
+        assert_eq!(
+            progress.render_at(Duration::from_secs(3725)),
+            "sync [==========] 100/100 100% files"
+        );
+
+        assert_eq!(
+            Progress::bar(0).with_width(4).render_at(Duration::ZERO),
+            "[====] 0/0 100%"
+        );
+    }
+
+    #[test]
+    fn test_render_spinner() {
+        let mut spinner = Progress::spinner().with_message("waiting");
+        assert_eq!(spinner.render_at(Duration::ZERO), "⠋ 00:00 waiting");
+        spinner.inc(3);
+        assert_eq!(
+            spinner.render_at(Duration::from_millis(3_700_090)),
+            "⠙ 3 1:01:40 waiting"
+        );
+    }
+
+    #[test]
+    fn test_progress_handles() {
+        let sandbox = fusabi_host::Sandbox::new(fusabi_host::SandboxConfig::default()).unwrap();
+        let ctx = ExecutionContext::new(
+            1,
+            fusabi_host::Capabilities::none(),
+            fusabi_host::Limits::default(),
+            sandbox,
+        );
+
+        let handle = progress_new(&[Value::Int(10)], &ctx).unwrap();
+        assert_eq!(
+            progress_inc(&[handle.clone(), Value::Int(4)], &ctx).unwrap(),
+            Value::Int(4)
+        );
+        progress_set(&[handle.clone(), Value::Int(7)], &ctx).unwrap();
+        assert_eq!(
+            progress_inc(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Int(8)
+        );
+        progress_finish(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(progress_tick(&[handle], &ctx).is_err());
+
+        assert!(progress_new(&[Value::Int(-1)], &ctx).is_err());
+        let spinner = spinner_new(&[], &ctx).unwrap();
+        progress_message(&[spinner.clone(), Value::String("done".into())], &ctx).unwrap();
+        progress_finish(&[spinner], &ctx).unwrap();
+    }
+}
@@ -0,0 +1,538 @@
+//! Interactive prompts.
+//!
+//! Line input, yes/no confirmation, arrow-key selection, and hidden password
+//! input, drawn on stderr so that a script's stdout can still be piped. Raw
+//! mode is enabled for the duration of a prompt and put back as it was.
+//!
+//! When stdin is not a terminal the prompts read a plain line instead, so
+//! scripts can be driven by piped input: `select` then accepts a 1-based
+//! number or the text of a choice.
+//!
+//! Every prompt returns null when cancelled with Esc or Ctrl+C, or at end of
+//! input.
+
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::Arc;
+
+use crossterm::cursor;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::queue;
+use crossterm::terminal::{Clear, ClearType};
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use super::TerminalGuard;
+
+/// Most choices `select` shows at once.
+const MAX_VISIBLE_CHOICES: usize = 10;
+
+/// Result of handling one key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Continue,
+    Submit,
+    Cancel,
+}
+
+fn is_cancel(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Esc
+        || (key.modifiers.contains(KeyModifiers::CONTROL)
+            && matches!(key.code, KeyCode::Char('c') | KeyCode::Char('d')))
+}
+
+/// Single-line text being edited.
+#[derive(Debug, Clone, Default)]
+struct LineEditor {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl LineEditor {
+    fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    fn handle_key(&mut self, key: &KeyEvent) -> Step {
+        if is_cancel(key) {
+            return Step::Cancel;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Step::Submit,
+            KeyCode::Char('u') if ctrl => {
+                self.chars.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.chars.len(),
+            KeyCode::Char(c) if !ctrl => {
+                self.chars.insert(self.cursor, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.chars.len(),
+            _ => {}
+        }
+        Step::Continue
+    }
+}
+
+/// Highlighted choice in a selection list.
+#[derive(Debug, Clone)]
+struct Selection {
+    index: usize,
+    len: usize,
+}
+
+impl Selection {
+    fn handle_key(&mut self, key: &KeyEvent) -> Step {
+        if is_cancel(key) {
+            return Step::Cancel;
+        }
+        match key.code {
+            KeyCode::Enter => return Step::Submit,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.index = self.index.checked_sub(1).unwrap_or(self.len - 1);
+            }
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Tab => {
+                self.index = (self.index + 1) % self.len;
+            }
+            KeyCode::Home | KeyCode::PageUp => self.index = 0,
+            KeyCode::End | KeyCode::PageDown => self.index = self.len - 1,
+            _ => {}
+        }
+        Step::Continue
+    }
+
+    /// Range of choices to show so that the highlighted one is visible.
+    fn window(&self) -> std::ops::Range<usize> {
+        let height = self.len.min(MAX_VISIBLE_CHOICES);
+        let start = (self.index + 1).saturating_sub(height);
+        start..start + height
+    }
+}
+
+/// Answer to a yes/no question, from a key or typed text.
+fn parse_confirm(answer: &str, default: bool) -> Option<bool> {
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "" => Some(default),
+        "y" | "yes" => Some(true),
+        "n" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Raw mode for the duration of a prompt, returned to its previous state.
+struct RawSession<'a> {
+    guard: &'a TerminalGuard,
+    was_raw: bool,
+}
+
+impl<'a> RawSession<'a> {
+    fn start(guard: &'a TerminalGuard) -> std::io::Result<Self> {
+        let was_raw = guard.is_raw_mode();
+        guard.enable_raw_mode()?;
+        Ok(Self { guard, was_raw })
+    }
+
+    fn read_key(&self) -> std::io::Result<KeyEvent> {
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Release {
+                    return Ok(key);
+                }
+            }
+        }
+    }
+}
+
+impl Drop for RawSession<'_> {
+    fn drop(&mut self) {
+        if !self.was_raw {
+            let _ = self.guard.disable_raw_mode();
+        }
+    }
+}
+
+fn interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Print `label` and read a line from non-terminal stdin; None at end of
+/// input.
+fn read_plain_line(label: &str) -> std::io::Result<Option<String>> {
+    let mut stderr = std::io::stderr();
+    write!(stderr, "{}", label)?;
+    stderr.flush()?;
+
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+/// Edit a line in raw mode, showing it unless `hidden`.
+fn read_line_raw(
+    guard: &TerminalGuard,
+    label: &str,
+    hidden: bool,
+) -> std::io::Result<Option<String>> {
+    let session = RawSession::start(guard)?;
+    let mut stderr = std::io::stderr();
+    let mut editor = LineEditor::default();
+
+    loop {
+        queue!(
+            stderr,
+            cursor::MoveToColumn(0),
+            Clear(ClearType::CurrentLine)
+        )?;
+        write!(stderr, "{}", label)?;
+        if !hidden {
+            write!(stderr, "{}", editor.text())?;
+            let column = label.chars().count() + editor.cursor;
+            queue!(
+                stderr,
+                cursor::MoveToColumn(column.min(u16::MAX as usize) as u16)
+            )?;
+        }
+        stderr.flush()?;
+
+        match editor.handle_key(&session.read_key()?) {
+            Step::Continue => {}
+            Step::Submit => {
+                write!(stderr, "\r\n")?;
+                return Ok(Some(editor.text()));
+            }
+            Step::Cancel => {
+                write!(stderr, "\r\n")?;
+                return Ok(None);
+            }
+        }
+    }
+}
+
+fn io_error(function: &str, e: std::io::Error) -> Error {
+    Error::host_function(format!("{}: {}", function, e))
+}
+
+fn message_arg<'a>(args: &'a [Value], function: &str) -> Result<&'a str> {
+    args.first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function(format!("{}: missing message argument", function)))
+}
+
+fn choice_label(choice: &Value) -> String {
+    match choice {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Ask for a line of text.
+///
+/// # Arguments
+///
+/// * `args[0]` - Message
+/// * `args[1]` - Default returned for empty input (optional)
+///
+/// # Returns
+///
+/// The entered text, or null if cancelled
+pub fn prompt(
+    guard: &Arc<TerminalGuard>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let message = message_arg(args, "terminal.prompt")?;
+    let default = args.get(1).and_then(|v| v.as_str());
+
+    let label = match default {
+        Some(default) => format!("{} [{}]: ", message, default),
+        None => format!("{}: ", message),
+    };
+    let line = if interactive() {
+        read_line_raw(guard, &label, false)
+    } else {
+        read_plain_line(&label)
+    }
+    .map_err(|e| io_error("terminal.prompt", e))?;
+
+    Ok(match line {
+        Some(line) if line.is_empty() => {
+            default.map_or(Value::String(line), |d| Value::String(d.to_string()))
+        }
+        Some(line) => Value::String(line),
+        None => Value::Null,
+    })
+}
+
+/// Ask for a password without echoing it.
+///
+/// # Arguments
+///
+/// * `args[0]` - Message
+///
+/// # Returns
+///
+/// The entered text, or null if cancelled
+pub fn password(
+    guard: &Arc<TerminalGuard>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let message = message_arg(args, "terminal.password")?;
+    let label = format!("{}: ", message);
+
+    let line = if interactive() {
+        read_line_raw(guard, &label, true)
+    } else {
+        read_plain_line(&label)
+    }
+    .map_err(|e| io_error("terminal.password", e))?;
+
+    Ok(line.map_or(Value::Null, Value::String))
+}
+
+/// Ask a yes/no question.
+///
+/// # Arguments
+///
+/// * `args[0]` - Message
+/// * `args[1]` - Answer for Enter or empty input (optional, default: false)
+///
+/// # Returns
+///
+/// Boolean answer, or null if cancelled
+pub fn confirm(
+    guard: &Arc<TerminalGuard>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let message = message_arg(args, "terminal.confirm")?;
+    let default = args.get(1).and_then(|v| v.as_bool()).unwrap_or(false);
+    let label = format!("{} [{}]: ", message, if default { "Y/n" } else { "y/N" });
+
+    if !interactive() {
+        let line = read_plain_line(&label).map_err(|e| io_error("terminal.confirm", e))?;
+        return match line {
+            None => Ok(Value::Null),
+            Some(line) => parse_confirm(&line, default)
+                .map(Value::Bool)
+                .ok_or_else(|| {
+                    Error::host_function(format!(
+                        "terminal.confirm: expected yes or no, got '{}'",
+                        line
+                    ))
+                }),
+        };
+    }
+
+    let answer = (|| -> std::io::Result<Option<bool>> {
+        let session = RawSession::start(guard)?;
+        let mut stderr = std::io::stderr();
+        write!(stderr, "{}", label)?;
+        stderr.flush()?;
+
+        loop {
+            let key = session.read_key()?;
+            if is_cancel(&key) {
+                write!(stderr, "\r\n")?;
+                return Ok(None);
+            }
+            let answer = match key.code {
+                KeyCode::Enter => Some(default),
+                KeyCode::Char(c) => parse_confirm(&c.to_string(), default).filter(|_| c != ' '),
+                _ => None,
+            };
+            if let Some(answer) = answer {
+                write!(stderr, "{}\r\n", if answer { "yes" } else { "no" })?;
+                return Ok(Some(answer));
+            }
+        }
+    })()
+    .map_err(|e| io_error("terminal.confirm", e))?;
+
+    Ok(answer.map_or(Value::Null, Value::Bool))
+}
+
+/// Ask the user to pick one of several choices with the arrow keys.
+///
+/// # Arguments
+///
+/// * `args[0]` - Message
+/// * `args[1]` - List of choices
+/// * `args[2]` - Index highlighted initially (optional, default: 0)
+///
+/// # Returns
+///
+/// The chosen element of the list, or null if cancelled
+pub fn select(
+    guard: &Arc<TerminalGuard>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let message = message_arg(args, "terminal.select")?;
+    let choices = args
+        .get(1)
+        .and_then(|v| v.as_list())
+        .filter(|choices| !choices.is_empty())
+        .ok_or_else(|| Error::host_function("terminal.select: choices must be a non-empty list"))?;
+    let labels: Vec<String> = choices.iter().map(choice_label).collect();
+    let initial = args
+        .get(2)
+        .and_then(|v| v.as_int())
+        .map_or(0, |i| (i.max(0) as usize).min(choices.len() - 1));
+
+    let chosen = if interactive() {
+        select_raw(guard, message, &labels, initial)
+    } else {
+        select_plain(message, &labels)
+    }
+    .map_err(|e| io_error("terminal.select", e))?;
+
+    Ok(chosen.map_or(Value::Null, |index| choices[index].clone()))
+}
+
+fn select_plain(message: &str, labels: &[String]) -> std::io::Result<Option<usize>> {
+    let mut stderr = std::io::stderr();
+    writeln!(stderr, "{}", message)?;
+    for (i, label) in labels.iter().enumerate() {
+        writeln!(stderr, "  {}) {}", i + 1, label)?;
+    }
+
+    let Some(line) = read_plain_line("> ")? else {
+        return Ok(None);
+    };
+    let line = line.trim();
+    let index = match line.parse::<usize>() {
+        Ok(n) if (1..=labels.len()).contains(&n) => Some(n - 1),
+        _ => labels.iter().position(|label| label == line),
+    };
+    index.map(Some).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("no choice '{}'", line),
+        )
+    })
+}
+
+fn select_raw(
+    guard: &TerminalGuard,
+    message: &str,
+    labels: &[String],
+    initial: usize,
+) -> std::io::Result<Option<usize>> {
+    let session = RawSession::start(guard)?;
+    let mut stderr = std::io::stderr();
+    let mut selection = Selection {
+        index: initial,
+        len: labels.len(),
+    };
+    let lines = selection.window().len() as u16;
+
+    write!(stderr, "{}\r\n", message)?;
+    queue!(stderr, cursor::Hide)?;
+    let outcome = loop {
+        for index in selection.window() {
+            let marker = if index == selection.index { ">" } else { " " };
+            queue!(stderr, Clear(ClearType::CurrentLine))?;
+            write!(stderr, "{} {}\r\n", marker, labels[index])?;
+        }
+        queue!(stderr, cursor::MoveUp(lines))?;
+        stderr.flush()?;
+
+        match selection.handle_key(&session.read_key()?) {
+            Step::Continue => {}
+            Step::Submit => break Some(selection.index),
+            Step::Cancel => break None,
+        }
+    };
+
+    // Collapse the list into the answer.
+    queue!(
+        stderr,
+        cursor::MoveUp(1),
+        Clear(ClearType::FromCursorDown),
+        cursor::Show
+    )?;
+    match outcome {
+        Some(index) => write!(stderr, "{} {}\r\n", message, labels[index])?,
+        None => write!(stderr, "{}\r\n", message)?,
+    }
+    stderr.flush()?;
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn test_line_editor() {
+        let mut editor = LineEditor::default();
+        for c in "helo".chars() {
+            assert_eq!(editor.handle_key(&key(KeyCode::Char(c))), Step::Continue);
+        }
+        editor.handle_key(&key(KeyCode::Left));
+        editor.handle_key(&key(KeyCode::Char('l')));
+        assert_eq!(editor.text(), "hello");
+
+        editor.handle_key(&key(KeyCode::End));
+        editor.handle_key(&key(KeyCode::Backspace));
+        editor.handle_key(&key(KeyCode::Home));
+        editor.handle_key(&key(KeyCode::Delete));
+        assert_eq!(editor.text(), "ell");
+
+        editor.handle_key(&key(KeyCode::End));
+        editor.handle_key(&ctrl('u'));
+        assert_eq!(editor.text(), "");
+
+        assert_eq!(editor.handle_key(&key(KeyCode::Enter)), Step::Submit);
+        assert_eq!(editor.handle_key(&ctrl('c')), Step::Cancel);
+        assert_eq!(editor.handle_key(&key(KeyCode::Esc)), Step::Cancel);
+    }
+
+    #[test]
+    fn test_selection() {
+        let mut selection = Selection { index: 0, len: 3 };
+        selection.handle_key(&key(KeyCode::Up));
+        assert_eq!(selection.index, 2);
+        selection.handle_key(&key(KeyCode::Down));
+        assert_eq!(selection.index, 0);
+        selection.handle_key(&key(KeyCode::Char('j')));
+        assert_eq!(selection.index, 1);
+        assert_eq!(selection.handle_key(&key(KeyCode::Enter)), Step::Submit);
+
+        let mut long = Selection { index: 0, len: 25 };
+        assert_eq!(long.window(), 0..10);
+        long.handle_key(&key(KeyCode::End));
+        assert_eq!(long.window(), 15..25);
+    }
+
+    #[test]
+    fn test_parse_confirm() {
+        assert_eq!(parse_confirm("", true), Some(true));
+        assert_eq!(parse_confirm(" Yes ", false), Some(true));
+        assert_eq!(parse_confirm("n", true), Some(false));
+        assert_eq!(parse_confirm("maybe", true), None);
+    }
+}
@@ -1,20 +1,48 @@
 //! Terminal UI module for Fusabi.
 //!
-//! Provides Ratatui/TUI widgets and helpers for building terminal user interfaces.
+//! Provides Ratatui/TUI widgets and helpers for building terminal user interfaces,
+//! a script-driven application loop ([`app`]) that draws widget trees
+//! ([`widgets`]), sortable tables of maps ([`table`]), a scrollable log
+//! pane with follow mode ([`log_pane`]), a fuzzy command palette
+//! ([`palette`]), and sparklines and charts of metrics histograms
+//! (`charts`, with the `metrics` feature).
 
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
-    backend::CrosstermBackend,
+    backend::{Backend, CrosstermBackend},
+    buffer::Buffer,
+    layout::Rect,
     style::{Color, Modifier, Style},
     text::Span,
-    widgets::{Block, Borders, List, ListItem, Paragraph},
-    Terminal,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, StatefulWidget, Widget},
+    Frame, Terminal,
 };
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
 use std::io::Stdout;
+use std::ops::Range;
+use std::time::{Duration, Instant};
 
 use crate::error::{Error, Result};
 use fusabi_host::Value;
 
+pub mod app;
+#[cfg(feature = "metrics")]
+pub mod charts;
+pub mod log_pane;
+pub mod palette;
+pub mod table;
+pub mod widgets;
+
+pub use app::{EventSource, TerminalEvents, TuiApp};
+#[cfg(feature = "metrics")]
+pub use charts::{metric_chart, metric_sparkline};
+pub use log_pane::{LogAction, LogPane, LogView};
+pub use palette::{fuzzy_match, CommandPalette, PaletteEntry, PaletteView};
+pub use table::{value_table, ValueTable, ValueTableState};
+pub use widgets::{parse_widget, render_value};
+
 /// Terminal UI state container.
 pub struct TerminalUI {
     terminal: Terminal<CrosstermBackend<Stdout>>,
@@ -45,6 +73,125 @@ impl TerminalUI {
             .clear()
             .map_err(|e| Error::TerminalUI(e.to_string()))
     }
+
+    /// Convert into a frame-capped, change-driven render loop.
+    pub fn into_render_loop(self, max_fps: u32) -> RenderLoop<CrosstermBackend<Stdout>> {
+        RenderLoop::new(self.terminal, max_fps)
+    }
+}
+
+/// Render loop that only draws when content has changed, at most `max_fps`
+/// times per second.
+///
+/// Callers report pane content with [`RenderLoop::update`] as often as they
+/// like; bursts of updates are coalesced into a single frame. Frames are
+/// never cleared, so Ratatui's buffer diff emits only the cells that changed,
+/// which avoids full-screen flicker on slow links.
+pub struct RenderLoop<B: Backend> {
+    terminal: Terminal<B>,
+    min_interval: Duration,
+    last_frame: Option<Instant>,
+    pane_hashes: HashMap<String, u64>,
+    dirty: BTreeSet<String>,
+    full_redraw: bool,
+}
+
+impl<B: Backend> RenderLoop<B> {
+    /// Create a render loop over a terminal. A `max_fps` of 0 disables the cap.
+    pub fn new(terminal: Terminal<B>, max_fps: u32) -> Self {
+        let min_interval = if max_fps == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs(1) / max_fps
+        };
+
+        Self {
+            terminal,
+            min_interval,
+            last_frame: None,
+            pane_hashes: HashMap::new(),
+            dirty: BTreeSet::new(),
+            full_redraw: true,
+        }
+    }
+
+    /// Report the current content of a pane, marking it dirty if it changed.
+    ///
+    /// Returns whether the content differs from the last reported value.
+    pub fn update(&mut self, pane: &str, content: &impl Hash) -> bool {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if self.pane_hashes.get(pane) == Some(&hash) {
+            return false;
+        }
+        self.pane_hashes.insert(pane.to_string(), hash);
+        self.dirty.insert(pane.to_string());
+        true
+    }
+
+    /// Mark a pane dirty regardless of its content.
+    pub fn mark_dirty(&mut self, pane: &str) {
+        self.dirty.insert(pane.to_string());
+    }
+
+    /// Clear the screen and redraw everything on the next frame.
+    pub fn force_redraw(&mut self) -> Result<()> {
+        self.terminal
+            .clear()
+            .map_err(|e| Error::TerminalUI(e.to_string()))?;
+        self.full_redraw = true;
+        Ok(())
+    }
+
+    /// Whether any pane is waiting to be drawn.
+    pub fn is_dirty(&self) -> bool {
+        self.full_redraw || !self.dirty.is_empty()
+    }
+
+    /// Time until the frame cap allows the next draw.
+    ///
+    /// Useful as an event poll timeout so the loop wakes up in time to
+    /// flush pending updates.
+    pub fn time_until_next_frame(&self) -> Duration {
+        self.last_frame
+            .map(|last| self.min_interval.saturating_sub(last.elapsed()))
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Draw a frame if anything is dirty and the frame cap allows it.
+    ///
+    /// The closure receives the frame and the set of dirty panes. All panes
+    /// should still be rendered into the frame; unchanged cells are not sent
+    /// to the terminal. Returns whether a frame was drawn.
+    pub fn draw_if_needed<F>(&mut self, render: F) -> Result<bool>
+    where
+        F: FnOnce(&mut Frame<'_>, &BTreeSet<String>),
+    {
+        if !self.is_dirty() || !self.time_until_next_frame().is_zero() {
+            return Ok(false);
+        }
+
+        let dirty = std::mem::take(&mut self.dirty);
+        self.terminal
+            .draw(|frame| render(frame, &dirty))
+            .map_err(|e| Error::TerminalUI(e.to_string()))?;
+
+        self.full_redraw = false;
+        self.last_frame = Some(Instant::now());
+        Ok(true)
+    }
+
+    /// Get the underlying terminal.
+    pub fn terminal(&self) -> &Terminal<B> {
+        &self.terminal
+    }
+
+    /// Get the underlying terminal mutably.
+    pub fn terminal_mut(&mut self) -> &mut Terminal<B> {
+        &mut self.terminal
+    }
 }
 
 /// Convert a Fusabi Value to a styled text span.
@@ -95,6 +242,212 @@ pub fn value_list<'a>(values: &'a [Value]) -> List<'a> {
         .highlight_style(Style::default().add_modifier(Modifier::BOLD))
 }
 
+/// Source of rows for virtualized widgets.
+///
+/// Rows are fetched by index only when they are on screen, so a provider
+/// can front a dataset far larger than would be practical to collect into a
+/// `Vec` (a log file, a paged API, a database cursor).
+pub trait ItemProvider {
+    /// Total number of rows.
+    fn len(&self) -> usize;
+
+    /// Whether there are no rows.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Fetch a row. `None` renders as an empty row.
+    fn item(&self, index: usize) -> Option<Value>;
+}
+
+impl ItemProvider for [Value] {
+    fn len(&self) -> usize {
+        <[Value]>::len(self)
+    }
+
+    fn item(&self, index: usize) -> Option<Value> {
+        self.get(index).cloned()
+    }
+}
+
+impl ItemProvider for Vec<Value> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn item(&self, index: usize) -> Option<Value> {
+        self.get(index).cloned()
+    }
+}
+
+/// An [`ItemProvider`] backed by a callback. Create it with [`item_fn`].
+pub struct FnProvider<F> {
+    len: usize,
+    f: F,
+}
+
+/// Create an [`ItemProvider`] of `len` rows produced by `f`.
+pub fn item_fn<F>(len: usize, f: F) -> FnProvider<F>
+where
+    F: Fn(usize) -> Option<Value>,
+{
+    FnProvider { len, f }
+}
+
+impl<F> ItemProvider for FnProvider<F>
+where
+    F: Fn(usize) -> Option<Value>,
+{
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn item(&self, index: usize) -> Option<Value> {
+        (self.f)(index)
+    }
+}
+
+/// Scroll position and selection of a virtualized widget.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VirtualListState {
+    offset: usize,
+    selected: Option<usize>,
+}
+
+impl VirtualListState {
+    /// Create a state with nothing selected, scrolled to the top.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of the first visible row.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Index of the selected row.
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Select a row; the view scrolls to it on the next render.
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+    }
+
+    /// Move the selection down by `n` rows, stopping at the last of `len`.
+    pub fn select_down(&mut self, n: usize, len: usize) {
+        if len == 0 {
+            self.selected = None;
+            return;
+        }
+        let next = self.selected.map_or(0, |i| i.saturating_add(n));
+        self.selected = Some(next.min(len - 1));
+    }
+
+    /// Move the selection up by `n` rows, stopping at the first.
+    pub fn select_up(&mut self, n: usize) {
+        self.selected = Some(self.selected.map_or(0, |i| i.saturating_sub(n)));
+    }
+
+    /// Rows to draw in a viewport of `height` rows over `len` rows, scrolling
+    /// as little as possible to keep the selection visible.
+    pub fn visible_range(&mut self, len: usize, height: usize) -> Range<usize> {
+        if let Some(selected) = self.selected {
+            let selected = selected.min(len.saturating_sub(1));
+            self.selected = (len > 0).then_some(selected);
+            if selected < self.offset {
+                self.offset = selected;
+            } else if height > 0 && selected >= self.offset + height {
+                self.offset = selected + 1 - height;
+            }
+        }
+        self.offset = self.offset.min(len.saturating_sub(height));
+        self.offset..(self.offset + height).min(len)
+    }
+}
+
+/// List widget that renders only the visible window of an [`ItemProvider`].
+pub struct VirtualList<'a> {
+    provider: &'a dyn ItemProvider,
+    block: Option<Block<'a>>,
+    highlight_style: Style,
+    highlight_symbol: Option<&'a str>,
+}
+
+impl<'a> VirtualList<'a> {
+    /// Create a list over a provider.
+    pub fn new(provider: &'a dyn ItemProvider) -> Self {
+        Self {
+            provider,
+            block: None,
+            highlight_style: Style::default(),
+            highlight_symbol: None,
+        }
+    }
+
+    /// Surround the list with a block.
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Style of the selected row.
+    pub fn highlight_style(mut self, style: Style) -> Self {
+        self.highlight_style = style;
+        self
+    }
+
+    /// Symbol drawn before the selected row.
+    pub fn highlight_symbol(mut self, symbol: &'a str) -> Self {
+        self.highlight_symbol = Some(symbol);
+        self
+    }
+}
+
+impl StatefulWidget for VirtualList<'_> {
+    type State = VirtualListState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        let range = state.visible_range(self.provider.len(), inner.height as usize);
+        let start = range.start;
+        let items: Vec<ListItem<'static>> = range
+            .map(|i| match self.provider.item(i) {
+                Some(value) => ListItem::new(value_to_span(&value)),
+                None => ListItem::new(""),
+            })
+            .collect();
+
+        let mut list_state =
+            ListState::default().with_selected(state.selected.and_then(|s| s.checked_sub(start)));
+        let mut list = List::new(items).highlight_style(self.highlight_style);
+        if let Some(symbol) = self.highlight_symbol {
+            list = list.highlight_symbol(symbol);
+        }
+        StatefulWidget::render(list, inner, buf, &mut list_state);
+    }
+}
+
+/// Create a virtualized list widget over a provider, styled like
+/// [`value_list`].
+///
+/// Only the rows in view are fetched, so this stays responsive over
+/// hundreds of thousands of rows. Render it with a [`VirtualListState`].
+pub fn virtual_value_list(provider: &dyn ItemProvider) -> VirtualList<'_> {
+    VirtualList::new(provider)
+        .block(titled_block("Values"))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+}
+
 /// Create a simple status bar.
 pub fn status_bar<'a>(status: &'a str) -> Paragraph<'a> {
     Paragraph::new(status).style(Style::default().fg(Color::White).bg(Color::DarkGray))
@@ -120,7 +473,96 @@ mod tests {
 
     #[test]
     fn test_titled_block() {
-        let block = titled_block("Test");
+        let _block = titled_block("Test");
         // Just verify it doesn't panic
     }
+
+    #[test]
+    fn test_render_loop_skips_unchanged_frames() {
+        use ratatui::backend::TestBackend;
+
+        let terminal = Terminal::new(TestBackend::new(20, 3)).unwrap();
+        let mut render_loop = RenderLoop::new(terminal, 0);
+        let mut draws = 0;
+
+        // First frame always draws
+        assert!(render_loop.update("log", &"line 1"));
+        assert!(render_loop
+            .draw_if_needed(|frame, dirty| {
+                assert!(dirty.contains("log"));
+                frame.render_widget(Paragraph::new("line 1"), frame.size());
+                draws += 1;
+            })
+            .unwrap());
+
+        // Same content: nothing to draw
+        assert!(!render_loop.update("log", &"line 1"));
+        assert!(!render_loop.draw_if_needed(|_, _| draws += 1).unwrap());
+
+        assert!(render_loop.update("log", &"line 2"));
+        assert!(render_loop
+            .draw_if_needed(|frame, _| {
+                frame.render_widget(Paragraph::new("line 2"), frame.size());
+                draws += 1;
+            })
+            .unwrap());
+        assert_eq!(draws, 2);
+
+        let buffer = render_loop.terminal().backend().buffer();
+        assert_eq!(buffer.get(5, 0).symbol(), "2");
+    }
+
+    #[test]
+    fn test_render_loop_frame_cap() {
+        use ratatui::backend::TestBackend;
+
+        let terminal = Terminal::new(TestBackend::new(10, 1)).unwrap();
+        let mut render_loop = RenderLoop::new(terminal, 1);
+
+        assert!(render_loop.draw_if_needed(|_, _| {}).unwrap());
+        render_loop.mark_dirty("pane");
+        assert!(!render_loop.draw_if_needed(|_, _| {}).unwrap());
+        assert!(render_loop.is_dirty());
+        assert!(render_loop.time_until_next_frame() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_virtual_list_fetches_visible_rows_only() {
+        use std::cell::Cell;
+
+        let fetched = Cell::new(0);
+        let provider = item_fn(500_000, |i| {
+            fetched.set(fetched.get() + 1);
+            Some(Value::Int(i as i64))
+        });
+
+        let area = Rect::new(0, 0, 20, 10);
+        let mut buf = Buffer::empty(area);
+        let mut state = VirtualListState::new();
+        state.select(Some(499_999));
+        virtual_value_list(&provider).render(area, &mut buf, &mut state);
+
+        // 8 rows fit inside the borders.
+        assert_eq!(fetched.get(), 8);
+        assert_eq!(state.offset(), 499_992);
+        let last_row: String = (1..19)
+            .map(|x| buf.get(x, 8).symbol().to_string())
+            .collect();
+        assert!(last_row.trim_end().ends_with("499999"));
+
+        state.select_up(20);
+        state.select_down(1, provider.len());
+        assert_eq!(state.visible_range(provider.len(), 8), 499_980..499_988);
+    }
+
+    #[test]
+    fn test_virtual_list_state_clamps() {
+        let mut state = VirtualListState::new();
+        state.select(Some(10));
+        assert_eq!(state.visible_range(3, 5), 0..3);
+        assert_eq!(state.selected(), Some(2));
+
+        assert_eq!(state.visible_range(0, 5), 0..0);
+        assert_eq!(state.selected(), None);
+    }
 }
@@ -0,0 +1,378 @@
+//! Script-driven application loop.
+//!
+//! [`TuiApp`] runs an Elm-style loop: the script's render function turns
+//! the current model into a widget tree (see [`super::widgets`]), and its
+//! event function turns the model and an event into the next model. The
+//! loop ends when the model is a map with `quit: true`, or on Ctrl-C.
+//!
+//! Host functions cannot call script closures themselves, so
+//! `terminal_ui.run` goes through the [`ScriptInvoker`] the embedding
+//! engine installs with [`crate::StdlibRegistry::with_script_invoker`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::{backend::Backend, Terminal};
+
+use crate::error::{Error, Result};
+use crate::registry::ScriptInvoker;
+use crate::terminal::{event_to_value, TerminalGuard};
+use fusabi_host::{ExecutionContext, Value};
+
+use super::widgets::{parse_widget, Widget};
+use super::{RenderLoop, TerminalUI};
+
+/// Frame cap of the application loop.
+pub const DEFAULT_MAX_FPS: u32 = 30;
+
+/// How long to wait for input when no tick is due.
+const IDLE_WAIT: Duration = Duration::from_millis(250);
+
+const APP_PANE: &str = "app";
+
+/// Source of terminal events for [`TuiApp::run`].
+pub trait EventSource {
+    /// Wait up to `timeout` for the next event.
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<Event>>;
+}
+
+/// Events read from the terminal.
+#[derive(Debug, Default)]
+pub struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn next_event(&mut self, timeout: Duration) -> Result<Option<Event>> {
+        if !event::poll(timeout)? {
+            return Ok(None);
+        }
+        Ok(Some(event::read()?))
+    }
+}
+
+/// Elm-style application loop over a terminal.
+pub struct TuiApp<B: Backend> {
+    render_loop: RenderLoop<B>,
+    tick: Option<Duration>,
+}
+
+impl<B: Backend> TuiApp<B> {
+    /// Create an application over a terminal, sending a tick event every
+    /// `tick` if given.
+    pub fn new(terminal: Terminal<B>, tick: Option<Duration>) -> Self {
+        Self {
+            render_loop: RenderLoop::new(terminal, DEFAULT_MAX_FPS),
+            tick: tick.filter(|t| !t.is_zero()),
+        }
+    }
+
+    /// Cap redraws at `max_fps` frames per second (0 disables the cap).
+    pub fn with_max_fps(mut self, max_fps: u32) -> Self {
+        let terminal = self.render_loop.terminal;
+        self.render_loop = RenderLoop::new(terminal, max_fps);
+        self
+    }
+
+    /// Get the render loop drawing the application.
+    pub fn render_loop(&self) -> &RenderLoop<B> {
+        &self.render_loop
+    }
+
+    /// Run the loop from `model` until it asks to quit, returning the final
+    /// model.
+    ///
+    /// `render` is called with the model after every change and returns the
+    /// widget tree to draw; `update` is called with the model and each
+    /// event (a map tagged by `type`, as returned by `terminal.next_event`,
+    /// or `{type: "tick"}`) and returns the next model.
+    pub fn run<R, U>(
+        &mut self,
+        model: Value,
+        events: &mut impl EventSource,
+        mut render: R,
+        mut update: U,
+    ) -> Result<Value>
+    where
+        R: FnMut(&Value) -> Result<Value>,
+        U: FnMut(Value, Value) -> Result<Value>,
+    {
+        let mut model = model;
+        let mut tree: Option<Value> = None;
+        let mut widget: Option<Widget> = None;
+        let mut changed = true;
+        let mut next_tick = self.tick.map(|tick| Instant::now() + tick);
+
+        loop {
+            if quit_requested(&model) {
+                return Ok(model);
+            }
+
+            if changed {
+                let next = render(&model)?;
+                if tree.as_ref() != Some(&next) {
+                    widget = Some(parse_widget(&next)?);
+                    self.render_loop.mark_dirty(APP_PANE);
+                    tree = Some(next);
+                }
+                changed = false;
+            }
+
+            if let Some(widget) = &widget {
+                self.render_loop
+                    .draw_if_needed(|frame, _| widget.render(frame, frame.size()))?;
+            }
+
+            let mut wait =
+                next_tick.map_or(IDLE_WAIT, |at| at.saturating_duration_since(Instant::now()));
+            if self.render_loop.is_dirty() {
+                wait = wait.min(self.render_loop.time_until_next_frame());
+            }
+
+            match events.next_event(wait)? {
+                Some(event) => {
+                    if is_interrupt(&event) {
+                        return Ok(model);
+                    }
+                    if matches!(event, Event::Resize(..)) {
+                        self.render_loop.mark_dirty(APP_PANE);
+                    }
+                    model = update(model, event_to_value(&event))?;
+                    changed = true;
+                }
+                None => {
+                    if let (Some(at), Some(tick)) = (next_tick, self.tick) {
+                        if Instant::now() >= at {
+                            model = update(model, tick_event())?;
+                            changed = true;
+                            next_tick = Some(Instant::now() + tick);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl TerminalUI {
+    /// Convert into an application loop, sending a tick event every `tick`
+    /// if given.
+    pub fn into_app(
+        self,
+        tick: Option<Duration>,
+    ) -> TuiApp<ratatui::backend::CrosstermBackend<std::io::Stdout>> {
+        TuiApp::new(self.terminal, tick)
+    }
+}
+
+/// Whether a model asks the loop to stop: a map with `quit: true`.
+pub fn quit_requested(model: &Value) -> bool {
+    matches!(model, Value::Map(fields) if fields.get("quit") == Some(&Value::Bool(true)))
+}
+
+fn is_interrupt(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::Key(key)
+            if key.kind != KeyEventKind::Release
+                && key.code == KeyCode::Char('c')
+                && key.modifiers.contains(KeyModifiers::CONTROL)
+    )
+}
+
+fn tick_event() -> Value {
+    Value::Map(HashMap::from([(
+        "type".to_string(),
+        Value::String("tick".to_string()),
+    )]))
+}
+
+/// Run a script-driven terminal application.
+///
+/// The terminal is switched to raw mode and the alternate screen for the
+/// duration of the loop and restored afterwards.
+///
+/// # Arguments
+///
+/// * `args[0]` - Render function: `render(model)` returns a widget tree
+/// * `args[1]` - Event function: `on_event(model, event)` returns the next
+///   model; return a map with `quit: true` to stop
+/// * `args[2]` - Tick interval in milliseconds (optional, default: 0, no
+///   ticks)
+/// * `args[3]` - Initial model (optional, default: null)
+///
+/// # Returns
+///
+/// The final model
+pub fn run(
+    invoker: Option<&Arc<dyn ScriptInvoker>>,
+    guard: &Arc<TerminalGuard>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let invoker = invoker.ok_or_else(|| {
+        fusabi_host::Error::host_function(
+            "terminal_ui.run: the host has not installed a script invoker",
+        )
+    })?;
+
+    let function = |index: usize, name: &str| match args.get(index) {
+        Some(f @ Value::Function(_)) => Ok(f),
+        _ => Err(fusabi_host::Error::host_function(format!(
+            "terminal_ui.run: {} must be a function",
+            name
+        ))),
+    };
+    let render_fn = function(0, "render_fn")?;
+    let event_fn = function(1, "event_fn")?;
+
+    let tick_ms = match args.get(2) {
+        None | Some(Value::Null) => 0,
+        Some(value) => value.as_int().filter(|ms| *ms >= 0).ok_or_else(|| {
+            fusabi_host::Error::host_function(
+                "terminal_ui.run: tick_ms must be a non-negative integer",
+            )
+        })?,
+    };
+    let model = args.get(3).cloned().unwrap_or(Value::Null);
+
+    let to_host = |e: Error| fusabi_host::Error::host_function(format!("terminal_ui.run: {}", e));
+
+    let was_raw = guard.is_raw_mode();
+    let setup = || -> Result<()> {
+        guard.enable_raw_mode()?;
+        guard.enter_alternate_screen()?;
+        guard.hide_cursor()?;
+        Ok(())
+    };
+    let result = setup().and_then(|()| {
+        let mut app = TerminalUI::new()?.into_app(Some(Duration::from_millis(tick_ms as u64)));
+        app.run(
+            model,
+            &mut TerminalEvents,
+            |model| {
+                ctx.check_timeout()?;
+                Ok(invoker.call(render_fn, std::slice::from_ref(model), ctx)?)
+            },
+            |model, event| Ok(invoker.call(event_fn, &[model, event], ctx)?),
+        )
+    });
+
+    let teardown = guard
+        .show_cursor()
+        .and_then(|()| guard.leave_alternate_screen())
+        .and_then(|()| {
+            if was_raw {
+                Ok(())
+            } else {
+                guard.disable_raw_mode()
+            }
+        });
+
+    let model = result.map_err(to_host)?;
+    teardown.map_err(|e| to_host(e.into()))?;
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+    use ratatui::backend::TestBackend;
+    use std::collections::VecDeque;
+
+    struct Scripted(VecDeque<Event>);
+
+    impl EventSource for Scripted {
+        fn next_event(&mut self, _timeout: Duration) -> Result<Option<Event>> {
+            Ok(self.0.pop_front())
+        }
+    }
+
+    fn key(c: char) -> Event {
+        Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    fn counter(count: i64, quit: bool) -> Value {
+        Value::Map(HashMap::from([
+            ("count".to_string(), Value::Int(count)),
+            ("quit".to_string(), Value::Bool(quit)),
+        ]))
+    }
+
+    #[test]
+    fn test_app_renders_and_dispatches_events() {
+        let terminal = Terminal::new(TestBackend::new(20, 3)).unwrap();
+        let mut app = TuiApp::new(terminal, None).with_max_fps(0);
+        let mut events = Scripted(VecDeque::from([key('+'), key('+'), key('q')]));
+        let mut renders = 0;
+
+        let model = app
+            .run(
+                counter(0, false),
+                &mut events,
+                |model| {
+                    renders += 1;
+                    let count = match model {
+                        Value::Map(m) => m["count"].as_int().unwrap(),
+                        _ => unreachable!(),
+                    };
+                    Ok(Value::String(format!("count: {}", count)))
+                },
+                |model, event| {
+                    let count = match &model {
+                        Value::Map(m) => m["count"].as_int().unwrap(),
+                        _ => unreachable!(),
+                    };
+                    let code = match &event {
+                        Value::Map(m) => m["code"].as_str().unwrap().to_string(),
+                        _ => unreachable!(),
+                    };
+                    Ok(match code.as_str() {
+                        "+" => counter(count + 1, false),
+                        "q" => counter(count, true),
+                        _ => model,
+                    })
+                },
+            )
+            .unwrap();
+
+        assert_eq!(model, counter(2, true));
+        assert_eq!(renders, 3);
+        let buffer = app.render_loop().terminal().backend().buffer();
+        let line: String = (0..8)
+            .map(|x| buffer.get(x, 0).symbol().to_string())
+            .collect();
+        assert_eq!(line, "count: 2");
+    }
+
+    #[test]
+    fn test_app_stops_on_ctrl_c_and_reports_bad_trees() {
+        let terminal = Terminal::new(TestBackend::new(10, 2)).unwrap();
+        let mut app = TuiApp::new(terminal, None);
+        let ctrl_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        let mut events = Scripted(VecDeque::from([ctrl_c]));
+        let model = app
+            .run(
+                Value::Int(7),
+                &mut events,
+                |_| Ok(Value::String("hi".into())),
+                |_, _| unreachable!(),
+            )
+            .unwrap();
+        assert_eq!(model, Value::Int(7));
+
+        let terminal = Terminal::new(TestBackend::new(10, 2)).unwrap();
+        let mut app = TuiApp::new(terminal, None);
+        let mut events = Scripted(VecDeque::new());
+        let err = app
+            .run(
+                Value::Null,
+                &mut events,
+                |_| Ok(Value::Int(1)),
+                |m, _| Ok(m),
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("root"));
+    }
+}
@@ -0,0 +1,335 @@
+//! Sparklines and charts of metrics histograms.
+//!
+//! Histograms keep their observations in order, so the most recent ones
+//! make a time series: [`metric_sparkline`] and [`metric_chart`] turn them
+//! into widgets, and the `terminal_ui.metric_sparkline` and
+//! `terminal_ui.metric_chart` host functions return the equivalent widget
+//! trees for scripts to place in a dashboard. A series that has not been
+//! recorded yet draws empty.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use fusabi_host::{ExecutionContext, Value};
+
+use crate::metrics::{labels_arg, MetricsRegistry};
+
+use super::widgets::{data_bounds, Common, Series, Widget};
+
+/// Observations shown when no window is given.
+pub const DEFAULT_WINDOW: usize = 120;
+
+fn chart_points(values: &[f64]) -> Vec<(f64, f64)> {
+    // The newest observation is at x = 0, older ones count back from it.
+    let newest = values.len().saturating_sub(1) as f64;
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i as f64 - newest, *v))
+        .collect()
+}
+
+/// Sparkline of the last `window` observations of a histogram series,
+/// titled with the series key.
+pub fn metric_sparkline(metrics: &MetricsRegistry, series: &str, window: usize) -> Widget {
+    Widget::Sparkline {
+        data: metrics.histogram_recent(series, window).unwrap_or_default(),
+        max: None,
+        common: Common {
+            title: Some(series.to_string()),
+            ..Common::default()
+        },
+    }
+}
+
+/// Line chart of the last `window` observations of a histogram series,
+/// titled with the series key. The newest observation is at x = 0.
+pub fn metric_chart(metrics: &MetricsRegistry, series: &str, window: usize) -> Widget {
+    let points = chart_points(&metrics.histogram_recent(series, window).unwrap_or_default());
+    Widget::Chart {
+        x_bounds: data_bounds(points.iter().map(|p| p.0)),
+        y_bounds: data_bounds(points.iter().map(|p| p.1)),
+        datasets: vec![Series {
+            name: Some(series.to_string()),
+            points,
+            style: Default::default(),
+        }],
+        common: Common {
+            title: Some(series.to_string()),
+            ..Common::default()
+        },
+    }
+}
+
+/// Arguments shared by the metric widget functions.
+struct MetricArgs {
+    series: String,
+    title: String,
+    window: usize,
+    options: HashMap<String, Value>,
+}
+
+fn metric_args(
+    metrics: &MetricsRegistry,
+    function: &str,
+    name: Option<&Value>,
+    window: Option<&Value>,
+    options: Option<&Value>,
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<MetricArgs> {
+    let name = name
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function(format!("{}: missing name", function)))?;
+
+    let options = match options {
+        None | Some(Value::Null) => HashMap::new(),
+        Some(Value::Map(options)) => options.clone(),
+        Some(_) => {
+            return Err(fusabi_host::Error::host_function(format!(
+                "{}: options must be a map",
+                function
+            )))
+        }
+    };
+
+    let window = match window.or_else(|| options.get("window")) {
+        None | Some(Value::Null) => DEFAULT_WINDOW,
+        Some(value) => value
+            .as_int()
+            .and_then(|n| usize::try_from(n).ok())
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                fusabi_host::Error::host_function(format!(
+                    "{}: window must be a positive integer",
+                    function
+                ))
+            })?,
+    };
+
+    let labels = labels_arg(function, options.get("labels"))?;
+    let title = match options.get("title").and_then(|v| v.as_str()) {
+        Some(title) => title.to_string(),
+        None => crate::metrics::series_key(name, &labels),
+    };
+    let (name, labels) = metrics.scope(ctx, name, labels);
+
+    Ok(MetricArgs {
+        series: crate::metrics::series_key(&name, &labels),
+        title,
+        window,
+        options,
+    })
+}
+
+fn widget_tree(kind: &str, args: &MetricArgs, fields: Vec<(&str, Value)>) -> Value {
+    let mut tree = HashMap::from([
+        ("type".to_string(), Value::String(kind.to_string())),
+        ("title".to_string(), Value::String(args.title.clone())),
+    ]);
+    tree.extend(fields.into_iter().map(|(k, v)| (k.to_string(), v)));
+    Value::Map(tree)
+}
+
+/// Build a sparkline widget tree from a histogram's recent observations.
+///
+/// # Arguments
+///
+/// * `args[0]` - Histogram name
+/// * `args[1]` - Options map (optional):
+///   - `labels`: Labels map of the series
+///   - `window`: Observations to include (default: 120)
+///   - `max`: Value drawn as a full bar (default: the largest shown)
+///   - `title`: Block title (default: the series key)
+///   - `style`: Style map
+///
+/// # Returns
+///
+/// Widget map with `type: "sparkline"` for `terminal_ui.run`
+pub fn metric_sparkline_tree(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let args = metric_args(
+        metrics,
+        "terminal_ui.metric_sparkline",
+        args.first(),
+        None,
+        args.get(1),
+        ctx,
+    )?;
+    let data = metrics
+        .histogram_recent(&args.series, args.window)
+        .unwrap_or_default();
+
+    let mut fields = vec![(
+        "data",
+        Value::List(data.into_iter().map(Value::Float).collect()),
+    )];
+    for key in ["max", "style"] {
+        if let Some(value) = args.options.get(key) {
+            fields.push((key, value.clone()));
+        }
+    }
+    Ok(widget_tree("sparkline", &args, fields))
+}
+
+/// Build a line chart widget tree from a histogram's recent observations.
+///
+/// The newest observation is plotted at x = 0 and older ones at negative
+/// x, counting back by observation.
+///
+/// # Arguments
+///
+/// * `args[0]` - Histogram name
+/// * `args[1]` - Observations to include (optional, default: 120)
+/// * `args[2]` - Options map (optional):
+///   - `labels`: Labels map of the series
+///   - `title`: Block title (default: the series key)
+///   - `style`: Style map of the line
+///
+/// # Returns
+///
+/// Widget map with `type: "chart"` for `terminal_ui.run`
+pub fn metric_chart_tree(
+    metrics: &Arc<MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let args = metric_args(
+        metrics,
+        "terminal_ui.metric_chart",
+        args.first(),
+        args.get(1),
+        args.get(2),
+        ctx,
+    )?;
+    let data = metrics
+        .histogram_recent(&args.series, args.window)
+        .unwrap_or_default();
+
+    let points = chart_points(&data)
+        .into_iter()
+        .map(|(x, y)| Value::List(vec![Value::Float(x), Value::Float(y)]))
+        .collect();
+    let mut dataset = HashMap::from([
+        ("name".to_string(), Value::String(args.title.clone())),
+        ("data".to_string(), Value::List(points)),
+    ]);
+    if let Some(style) = args.options.get("style") {
+        dataset.insert("style".to_string(), style.clone());
+    }
+
+    Ok(widget_tree(
+        "chart",
+        &args,
+        vec![("datasets", Value::List(vec![Value::Map(dataset)]))],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal_ui::parse_widget;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_metric_widgets_follow_recent_observations() {
+        let metrics = MetricsRegistry::new();
+        for v in [1.0, 2.0, 4.0, 8.0] {
+            metrics.histogram_observe("latency", v);
+        }
+
+        match metric_chart(&metrics, "latency", 3) {
+            Widget::Chart {
+                datasets,
+                x_bounds,
+                y_bounds,
+                ..
+            } => {
+                assert_eq!(
+                    datasets[0].points,
+                    vec![(-2.0, 2.0), (-1.0, 4.0), (0.0, 8.0)]
+                );
+                assert_eq!(x_bounds, [-2.0, 0.0]);
+                assert_eq!(y_bounds, [2.0, 8.0]);
+            }
+            other => panic!("unexpected widget {:?}", other),
+        }
+
+        // The sparkline keeps only the newest values that fit: 4 and 8.
+        let sparkline = metric_sparkline(&metrics, "latency", DEFAULT_WINDOW);
+        let mut terminal = Terminal::new(TestBackend::new(4, 3)).unwrap();
+        terminal
+            .draw(|frame| sparkline.render(frame, frame.size()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        assert_eq!(buffer.get(1, 1).symbol(), "▄");
+        assert_eq!(buffer.get(2, 1).symbol(), "█");
+
+        let empty = metric_sparkline(&metrics, "missing", DEFAULT_WINDOW);
+        assert!(matches!(empty, Widget::Sparkline { data, .. } if data.is_empty()));
+    }
+
+    #[test]
+    fn test_metric_tree_host_functions() {
+        let metrics = Arc::new(MetricsRegistry::new());
+        let labels = crate::metrics::Labels::from([("route".to_string(), "/api".to_string())]);
+        metrics.histogram_observe_with("latency", &labels, 0.5);
+        metrics.histogram_observe_with("latency", &labels, 1.5);
+
+        let options = Value::Map(HashMap::from([(
+            "labels".to_string(),
+            Value::Map(HashMap::from([(
+                "route".to_string(),
+                Value::String("/api".into()),
+            )])),
+        )]));
+        let ctx = ctx();
+
+        let tree = metric_sparkline_tree(
+            &metrics,
+            &[Value::String("latency".into()), options.clone()],
+            &ctx,
+        )
+        .unwrap();
+        let fields = tree.as_map().unwrap();
+        assert_eq!(
+            fields["data"],
+            Value::List(vec![Value::Float(0.5), Value::Float(1.5)])
+        );
+        assert_eq!(
+            fields["title"],
+            Value::String("latency{route=\"/api\"}".into())
+        );
+        parse_widget(&tree).unwrap();
+
+        let tree = metric_chart_tree(
+            &metrics,
+            &[Value::String("latency".into()), Value::Int(1), options],
+            &ctx,
+        )
+        .unwrap();
+        match parse_widget(&tree).unwrap() {
+            Widget::Chart { datasets, .. } => assert_eq!(datasets[0].points, vec![(0.0, 1.5)]),
+            other => panic!("unexpected widget {:?}", other),
+        }
+
+        assert!(metric_chart_tree(
+            &metrics,
+            &[Value::String("latency".into()), Value::Int(0)],
+            &ctx
+        )
+        .is_err());
+    }
+}
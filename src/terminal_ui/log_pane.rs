@@ -0,0 +1,723 @@
+//! Scrollable log pane with follow mode.
+//!
+//! [`LogPane`] keeps the newest lines in a ring buffer and a scroll
+//! position over them. In follow mode the view sticks to the end as lines
+//! arrive, like `less +F`; scrolling up leaves follow mode and `End` (or
+//! `F`) resumes it. Scripts drive panes through handles and can feed them
+//! from `fs_stream.tail` handles.
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::Range;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::OnceLock;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind};
+use parking_lot::Mutex;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::Line,
+    widgets::{Block, Paragraph, StatefulWidget, Widget},
+};
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+/// Lines kept when no maximum is given.
+pub const DEFAULT_MAX_LINES: usize = 10_000;
+
+/// Viewport height assumed for paging before the pane is first drawn.
+const DEFAULT_PAGE: usize = 20;
+
+/// A scroll or follow command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogAction {
+    /// Scroll towards older lines.
+    Up(usize),
+    /// Scroll towards newer lines.
+    Down(usize),
+    /// Scroll up one viewport.
+    PageUp,
+    /// Scroll down one viewport.
+    PageDown,
+    /// Jump to the oldest line.
+    Home,
+    /// Jump to the newest line and follow.
+    End,
+    /// Toggle follow mode.
+    ToggleFollow,
+}
+
+impl LogAction {
+    /// Parse an action name: `up`, `down`, `page_up`, `page_down`, `home`,
+    /// `end` or `follow`. `n` applies to `up` and `down`.
+    pub fn from_name(name: &str, n: usize) -> Option<Self> {
+        Some(match name {
+            "up" => Self::Up(n),
+            "down" => Self::Down(n),
+            "page_up" => Self::PageUp,
+            "page_down" => Self::PageDown,
+            "home" => Self::Home,
+            "end" => Self::End,
+            "follow" => Self::ToggleFollow,
+            _ => return None,
+        })
+    }
+
+    /// Map a key code, as named by `terminal.next_event`, to its `less`
+    /// binding: arrows and `j`/`k` scroll a line, `PageUp`/`b` and
+    /// `PageDown`/space a page, `Home`/`g` and `End`/`G` jump, and `f`/`F`
+    /// toggle follow mode.
+    pub fn from_key_code(code: &str) -> Option<Self> {
+        Some(match code {
+            "ArrowUp" | "k" => Self::Up(1),
+            "ArrowDown" | "j" => Self::Down(1),
+            "PageUp" | "b" => Self::PageUp,
+            "PageDown" | " " => Self::PageDown,
+            "Home" | "g" => Self::Home,
+            "End" | "G" => Self::End,
+            "f" | "F" => Self::ToggleFollow,
+            _ => return None,
+        })
+    }
+
+    /// Map a key event with the bindings of [`from_key_code`](Self::from_key_code).
+    pub fn from_key(key: &KeyEvent) -> Option<Self> {
+        if key.kind == KeyEventKind::Release {
+            return None;
+        }
+        match key.code {
+            KeyCode::Up => Some(Self::Up(1)),
+            KeyCode::Down => Some(Self::Down(1)),
+            KeyCode::PageUp => Some(Self::PageUp),
+            KeyCode::PageDown => Some(Self::PageDown),
+            KeyCode::Home => Some(Self::Home),
+            KeyCode::End => Some(Self::End),
+            KeyCode::Char(c) => Self::from_key_code(c.encode_utf8(&mut [0; 4])),
+            _ => None,
+        }
+    }
+}
+
+/// Ring buffer of lines with a scroll position and follow mode.
+#[derive(Debug, Clone)]
+pub struct LogPane {
+    lines: VecDeque<String>,
+    max_lines: usize,
+    top: usize,
+    follow: bool,
+    height: usize,
+    dropped: u64,
+}
+
+impl Default for LogPane {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_LINES)
+    }
+}
+
+impl LogPane {
+    /// Create an empty pane keeping at most `max_lines`, following.
+    pub fn new(max_lines: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            max_lines: max_lines.max(1),
+            top: 0,
+            follow: true,
+            height: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Append a line; text with newlines is split into several lines.
+    pub fn push(&mut self, text: &str) {
+        for line in text.split('\n') {
+            self.push_line(line.trim_end_matches('\r').to_string());
+        }
+    }
+
+    fn push_line(&mut self, line: String) {
+        if self.lines.len() == self.max_lines {
+            self.lines.pop_front();
+            self.dropped += 1;
+            // Keep the same lines in view while scrolled back.
+            self.top = self.top.saturating_sub(1);
+        }
+        self.lines.push_back(line);
+    }
+
+    /// Append lines.
+    pub fn extend<I, S>(&mut self, lines: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for line in lines {
+            self.push(line.as_ref());
+        }
+    }
+
+    /// Remove every line.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.top = 0;
+    }
+
+    /// Buffered lines, oldest first.
+    pub fn lines(&self) -> &VecDeque<String> {
+        &self.lines
+    }
+
+    /// Number of buffered lines.
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Whether no lines are buffered.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Lines dropped from the front because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Whether the view follows new lines.
+    pub fn is_following(&self) -> bool {
+        self.follow
+    }
+
+    /// Turn follow mode on or off; turning it on jumps to the end.
+    pub fn set_follow(&mut self, follow: bool) {
+        self.follow = follow;
+    }
+
+    /// Lines below the view, which arrived while not following.
+    pub fn unseen(&self) -> usize {
+        if self.follow {
+            0
+        } else {
+            self.lines.len().saturating_sub(self.top + self.page())
+        }
+    }
+
+    fn page(&self) -> usize {
+        if self.height == 0 {
+            DEFAULT_PAGE
+        } else {
+            self.height
+        }
+    }
+
+    fn bottom(&self) -> usize {
+        self.lines.len().saturating_sub(self.page())
+    }
+
+    /// Apply a scroll or follow command.
+    pub fn apply(&mut self, action: LogAction) {
+        if self.follow {
+            self.top = self.bottom();
+        }
+        match action {
+            LogAction::Up(n) => {
+                self.follow = false;
+                self.top = self.top.saturating_sub(n);
+            }
+            LogAction::Down(n) => self.top = (self.top + n).min(self.bottom()),
+            LogAction::PageUp => {
+                self.follow = false;
+                self.top = self.top.saturating_sub(self.page());
+            }
+            LogAction::PageDown => self.top = (self.top + self.page()).min(self.bottom()),
+            LogAction::Home => {
+                self.follow = false;
+                self.top = 0;
+            }
+            LogAction::End => {
+                self.follow = true;
+                self.top = self.bottom();
+            }
+            LogAction::ToggleFollow => self.follow = !self.follow,
+        }
+    }
+
+    /// Handle a key with the `less` bindings of [`LogAction::from_key`],
+    /// returning whether it was one of them.
+    pub fn handle_key(&mut self, key: &KeyEvent) -> bool {
+        match LogAction::from_key(key) {
+            Some(action) => {
+                self.apply(action);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lines to draw in a viewport of `height` rows. Paging uses the last
+    /// height given.
+    pub fn visible_range(&mut self, height: usize) -> Range<usize> {
+        self.height = height;
+        if self.follow {
+            self.top = self.bottom();
+        } else {
+            self.top = self.top.min(self.bottom());
+        }
+        self.top..(self.top + height).min(self.lines.len())
+    }
+}
+
+/// Widget drawing the visible lines of a [`LogPane`].
+#[derive(Default)]
+pub struct LogView<'a> {
+    block: Option<Block<'a>>,
+}
+
+impl<'a> LogView<'a> {
+    /// Create a log view.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Surround the view with a block.
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl StatefulWidget for LogView<'_> {
+    type State = LogPane;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        let range = state.visible_range(inner.height as usize);
+        let lines: Vec<Line<'_>> = state
+            .lines
+            .range(range)
+            .map(|line| Line::raw(line.as_str()))
+            .collect();
+        Paragraph::new(lines).render(inner, buf);
+    }
+}
+
+static PANES: OnceLock<Mutex<HashMap<i64, LogPane>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn panes() -> &'static Mutex<HashMap<i64, LogPane>> {
+    PANES.get_or_init(Default::default)
+}
+
+/// Number of open log panes.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    panes().lock().len()
+}
+
+fn pane_handle(args: &[Value], function: &str) -> Result<i64> {
+    args.first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing handle argument", function)))
+}
+
+fn with_pane<T>(
+    args: &[Value],
+    function: &str,
+    f: impl FnOnce(&mut LogPane) -> Result<T>,
+) -> Result<T> {
+    let handle = pane_handle(args, function)?;
+    let mut panes = panes().lock();
+    let pane = panes
+        .get_mut(&handle)
+        .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))?;
+    f(pane)
+}
+
+fn count_arg(value: Option<&Value>, default: usize, name: &str, function: &str) -> Result<usize> {
+    match value {
+        None | Some(Value::Null) => Ok(default),
+        Some(value) => value
+            .as_int()
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(|| {
+                Error::host_function(format!(
+                    "{}: {} must be a non-negative integer",
+                    function, name
+                ))
+            }),
+    }
+}
+
+/// Create a log pane.
+///
+/// # Arguments
+///
+/// * `args[0]` - Options map (optional):
+///   - `max_lines`: Lines kept before the oldest are dropped (default:
+///     10000)
+///   - `follow`: Start in follow mode (default: true)
+///
+/// # Returns
+///
+/// Handle (integer)
+pub fn log_pane(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.log_pane";
+    let options = args.first().and_then(|v| v.as_map());
+    let option = |key: &str| options.and_then(|m| m.get(key));
+
+    let max_lines = count_arg(
+        option("max_lines"),
+        DEFAULT_MAX_LINES,
+        "max_lines",
+        function,
+    )?;
+    if max_lines == 0 {
+        return Err(Error::host_function(format!(
+            "{}: max_lines must be positive",
+            function
+        )));
+    }
+    let mut pane = LogPane::new(max_lines);
+    if let Some(follow) = option("follow").and_then(|v| v.as_bool()) {
+        pane.set_follow(follow);
+    }
+
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    panes().lock().insert(handle, pane);
+    Ok(Value::Int(handle))
+}
+
+/// Append lines to a log pane.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pane handle
+/// * `args[1]` - Line (string, split at newlines) or list of lines
+pub fn log_pane_push(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.log_pane_push";
+    with_pane(args, function, |pane| {
+        match args.get(1) {
+            Some(Value::String(text)) => pane.push(text),
+            Some(Value::List(lines)) => {
+                for line in lines {
+                    match line {
+                        Value::String(text) => pane.push(text),
+                        other => pane.push(&other.to_string()),
+                    }
+                }
+            }
+            _ => {
+                return Err(Error::host_function(format!(
+                    "{}: expected a string or list of lines",
+                    function
+                )))
+            }
+        }
+        Ok(Value::Null)
+    })
+}
+
+/// Move lines from an `fs_stream` handle (such as one from
+/// `fs_stream.tail`) into a log pane without blocking.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pane handle
+/// * `args[1]` - Stream handle
+/// * `args[2]` - Most lines to move (optional, default: 1000)
+///
+/// # Returns
+///
+/// Number of lines moved
+#[cfg(feature = "fs_stream")]
+pub fn log_pane_pull(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.log_pane_pull";
+    let stream = args
+        .get(1)
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing stream handle", function)))?;
+    let max = count_arg(args.get(2), 1000, "max", function)?;
+
+    // Read before locking the pane table.
+    let lines = crate::fs_stream::poll_lines(stream, max, function)?;
+    with_pane(args, function, |pane| {
+        pane.extend(&lines);
+        Ok(Value::Int(lines.len() as i64))
+    })
+}
+
+/// Scroll a log pane.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pane handle
+/// * `args[1]` - Action: `"up"`, `"down"`, `"page_up"`, `"page_down"`,
+///   `"home"`, `"end"` (jump to the end and follow) or `"follow"` (toggle
+///   follow mode)
+/// * `args[2]` - Lines for `up` and `down` (optional, default: 1)
+///
+/// # Returns
+///
+/// Whether the pane is following
+pub fn log_pane_scroll(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.log_pane_scroll";
+    let name = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function(format!("{}: missing action argument", function)))?;
+    let n = count_arg(args.get(2), 1, "count", function)?;
+    let action = LogAction::from_name(name, n)
+        .ok_or_else(|| Error::host_function(format!("{}: unknown action '{}'", function, name)))?;
+
+    with_pane(args, function, |pane| {
+        pane.apply(action);
+        Ok(Value::Bool(pane.is_following()))
+    })
+}
+
+/// Apply the `less` key bindings to an event from `terminal.next_event` or
+/// `terminal_ui.run`: arrows and `j`/`k` scroll a line, `PageUp`/`b` and
+/// `PageDown`/space a page, `Home`/`g` and `End`/`G` jump, `f`/`F` toggle
+/// follow mode, and the mouse wheel scrolls three lines.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pane handle
+/// * `args[1]` - Event map
+///
+/// # Returns
+///
+/// Whether the event was handled
+pub fn log_pane_event(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.log_pane_event";
+    let event = args
+        .get(1)
+        .and_then(|v| v.as_map())
+        .ok_or_else(|| Error::host_function(format!("{}: missing event argument", function)))?;
+    let field = |key: &str| event.get(key).and_then(|v| v.as_str());
+
+    let action = match field("type") {
+        Some("key") if field("kind") != Some("release") => {
+            field("code").and_then(LogAction::from_key_code)
+        }
+        Some("mouse") => match field("kind") {
+            Some("scroll_up") => Some(LogAction::Up(3)),
+            Some("scroll_down") => Some(LogAction::Down(3)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    with_pane(args, function, |pane| {
+        if let Some(action) = action {
+            pane.apply(action);
+        }
+        Ok(Value::Bool(action.is_some()))
+    })
+}
+
+/// Build a `paragraph` widget tree for `terminal_ui.run` from a log pane.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pane handle
+/// * `args[1]` - Options map (optional):
+///   - `height`: Rows in view, used for paging (default: 20); subtract
+///     2 when the pane has a title, for its border
+///   - `title`: Block title; `[follow]` or the count of unseen lines is
+///     appended to it
+///
+/// # Returns
+///
+/// Widget map with `type: "paragraph"` and the visible lines
+pub fn log_pane_view(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.log_pane_view";
+    let options = args.get(1).and_then(|v| v.as_map());
+    let option = |key: &str| options.and_then(|m| m.get(key));
+    let height = count_arg(option("height"), DEFAULT_PAGE, "height", function)?;
+    let title = option("title").and_then(|v| v.as_str());
+
+    with_pane(args, function, |pane| {
+        let range = pane.visible_range(height);
+        let text = pane
+            .lines
+            .range(range)
+            .map(|line| Value::String(line.clone()))
+            .collect();
+
+        let mut tree = HashMap::from([
+            ("type".to_string(), Value::String("paragraph".into())),
+            ("text".to_string(), Value::List(text)),
+        ]);
+        if let Some(title) = title {
+            let status = match pane.unseen() {
+                _ if pane.is_following() => " [follow]".to_string(),
+                0 => String::new(),
+                n => format!(" [{} more]", n),
+            };
+            tree.insert(
+                "title".to_string(),
+                Value::String(format!("{}{}", title, status)),
+            );
+        }
+        Ok(Value::Map(tree))
+    })
+}
+
+/// Release a log pane.
+///
+/// # Arguments
+///
+/// * `args[0]` - Pane handle
+pub fn log_pane_close(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = pane_handle(args, "terminal_ui.log_pane_close")?;
+    panes().lock().remove(&handle);
+    Ok(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    fn numbered(pane: &mut LogPane, range: Range<usize>) {
+        pane.extend(range.map(|i| format!("line {}", i)));
+    }
+
+    #[test]
+    fn test_log_pane_follow_and_scroll() {
+        let mut pane = LogPane::new(100);
+        numbered(&mut pane, 0..10);
+        assert_eq!(pane.visible_range(4), 6..10);
+
+        pane.handle_key(&KeyEvent::new(KeyCode::Up, KeyModifiers::NONE));
+        assert!(!pane.is_following());
+        assert_eq!(pane.visible_range(4), 5..9);
+
+        // New lines do not move the view while scrolled back.
+        numbered(&mut pane, 10..12);
+        assert_eq!(pane.visible_range(4), 5..9);
+        assert_eq!(pane.unseen(), 3);
+
+        pane.apply(LogAction::PageUp);
+        assert_eq!(pane.visible_range(4), 1..5);
+        pane.apply(LogAction::Home);
+        assert_eq!(pane.visible_range(4), 0..4);
+        pane.apply(LogAction::PageDown);
+        pane.apply(LogAction::Down(100));
+        assert_eq!(pane.visible_range(4), 8..12);
+        assert!(!pane.is_following());
+
+        pane.handle_key(&KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT));
+        assert!(pane.is_following());
+        numbered(&mut pane, 12..13);
+        assert_eq!(pane.visible_range(4), 9..13);
+    }
+
+    #[test]
+    fn test_log_pane_ring_buffer_keeps_view() {
+        let mut pane = LogPane::new(5);
+        numbered(&mut pane, 0..5);
+        pane.visible_range(2);
+        pane.apply(LogAction::Home);
+        pane.apply(LogAction::Down(1));
+        assert_eq!(pane.visible_range(2), 1..3);
+
+        numbered(&mut pane, 5..7);
+        assert_eq!(pane.dropped(), 2);
+        assert_eq!(pane.lines()[0], "line 2");
+        // "line 1" was dropped; the view stays on the oldest kept line.
+        assert_eq!(pane.visible_range(2), 0..2);
+
+        let mut terminal = Terminal::new(TestBackend::new(8, 2)).unwrap();
+        terminal
+            .draw(|frame| frame.render_stateful_widget(LogView::new(), frame.size(), &mut pane))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let row: String = (0..6)
+            .map(|x| buffer.get(x, 1).symbol().to_string())
+            .collect();
+        assert_eq!(row, "line 3");
+    }
+
+    #[test]
+    fn test_log_pane_host_functions() {
+        let ctx = ctx();
+        let options = Value::Map(HashMap::from([("max_lines".to_string(), Value::Int(50))]));
+        let handle = log_pane(&[options], &ctx).unwrap();
+
+        log_pane_push(&[handle.clone(), Value::String("a\nb\nc".into())], &ctx).unwrap();
+        log_pane_push(
+            &[
+                handle.clone(),
+                Value::List(vec![Value::String("d".into()), Value::Int(5)]),
+            ],
+            &ctx,
+        )
+        .unwrap();
+
+        let view_options = Value::Map(HashMap::from([
+            ("height".to_string(), Value::Int(2)),
+            ("title".to_string(), Value::String("app.log".into())),
+        ]));
+        let view = log_pane_view(&[handle.clone(), view_options.clone()], &ctx).unwrap();
+        let fields = view.as_map().unwrap();
+        assert_eq!(
+            fields["text"],
+            Value::List(vec![Value::String("d".into()), Value::String("5".into())])
+        );
+        assert_eq!(fields["title"], Value::String("app.log [follow]".into()));
+        crate::terminal_ui::parse_widget(&view).unwrap();
+
+        let key = |code: &str| {
+            Value::Map(HashMap::from([
+                ("type".to_string(), Value::String("key".into())),
+                ("code".to_string(), Value::String(code.into())),
+                ("kind".to_string(), Value::String("press".into())),
+            ]))
+        };
+        assert_eq!(
+            log_pane_event(&[handle.clone(), key("k")], &ctx).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            log_pane_event(&[handle.clone(), key("x")], &ctx).unwrap(),
+            Value::Bool(false)
+        );
+        let view = log_pane_view(&[handle.clone(), view_options], &ctx).unwrap();
+        assert_eq!(
+            view.as_map().unwrap()["title"],
+            Value::String("app.log [1 more]".into())
+        );
+
+        assert_eq!(
+            log_pane_scroll(&[handle.clone(), Value::String("end".into())], &ctx).unwrap(),
+            Value::Bool(true)
+        );
+        assert!(
+            log_pane_scroll(&[handle.clone(), Value::String("sideways".into())], &ctx).is_err()
+        );
+
+        log_pane_close(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(log_pane_view(&[handle], &ctx).is_err());
+    }
+}
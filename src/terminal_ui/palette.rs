@@ -0,0 +1,1111 @@
+//! Fuzzy command palette.
+//!
+//! [`CommandPalette`] holds a list of `{name, description, action}` entries
+//! and a query, and keeps the entries matching the query ranked by
+//! [`fuzzy_match`], like the Ctrl+P palette of an editor. Typing edits the
+//! query, arrows move the selection, Enter picks the selected entry's
+//! action and Esc cancels. Scripts either run a palette on its own with
+//! `terminal_ui.palette_pick`, or drive one from a `terminal_ui.run` loop
+//! through a handle:
+//!
+//! ```text
+//! let palette = terminal_ui.palette(commands)
+//! // in on_event:
+//! let result = terminal_ui.palette_event(palette, event)
+//! if result.status == "selected" { run_command(result.action) }
+//! // in render:
+//! terminal_ui.palette_view(palette, { height: 10, title: "Commands" })
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use parking_lot::Mutex;
+use ratatui::{
+    backend::Backend,
+    buffer::Buffer,
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, StatefulWidget, Widget},
+    Terminal,
+};
+
+use crate::terminal::TerminalGuard;
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use super::app::{EventSource, TerminalEvents};
+use super::{titled_block, VirtualListState};
+
+/// Rows of entries assumed for paging before the palette is first drawn.
+const DEFAULT_PAGE: usize = 10;
+
+/// Score of each matched character.
+const SCORE_MATCH: i64 = 16;
+/// Bonus for a match at the start of a word.
+const BONUS_BOUNDARY: i64 = 8;
+/// Bonus for a match right after the previous one.
+const BONUS_CONSECUTIVE: i64 = 12;
+/// Bonus for matching the first character.
+const BONUS_FIRST: i64 = 8;
+/// Largest gap penalty between two matches.
+const MAX_GAP_PENALTY: i64 = 8;
+
+/// A command in a palette.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteEntry {
+    /// Name shown and matched against the query.
+    pub name: String,
+    /// Description shown after the name; matches in it rank below name
+    /// matches.
+    pub description: String,
+    /// Value returned when the entry is picked.
+    pub action: Value,
+}
+
+impl PaletteEntry {
+    /// Create an entry whose action is its name.
+    pub fn new(name: impl Into<String>) -> Self {
+        let name = name.into();
+        Self {
+            action: Value::String(name.clone()),
+            name,
+            description: String::new(),
+        }
+    }
+
+    /// Set the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Set the action.
+    pub fn with_action(mut self, action: Value) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Read an entry from a string (the name) or a map with `name` and
+    /// optional `description` and `action` (default: the name).
+    pub fn from_value(value: &Value) -> std::result::Result<Self, String> {
+        match value {
+            Value::String(name) => Ok(Self::new(name.as_str())),
+            Value::Map(fields) => {
+                let name = fields
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .ok_or("entry name must be a string")?;
+                let mut entry = Self::new(name);
+                match fields.get("description") {
+                    None | Some(Value::Null) => {}
+                    Some(Value::String(description)) => entry.description = description.clone(),
+                    Some(_) => return Err("entry description must be a string".to_string()),
+                }
+                if let Some(action) = fields.get("action") {
+                    entry.action = action.clone();
+                }
+                Ok(entry)
+            }
+            _ => Err("entries must be strings or maps with a name".to_string()),
+        }
+    }
+}
+
+fn is_boundary(prev: Option<char>, c: char) -> bool {
+    match prev {
+        None => true,
+        Some(prev) => !prev.is_alphanumeric() || (prev.is_lowercase() && c.is_uppercase()),
+    }
+}
+
+/// Match `query` as a case-insensitive subsequence of `text`.
+///
+/// Returns the score, higher for matches at word starts, in runs and near
+/// the start, and the character indexes of `text` that matched, or `None`
+/// if some query character is missing. Spaces in the query are ignored.
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let chars: Vec<char> = text.chars().collect();
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    // Try each place the first character matches and match the rest
+    // greedily from there, keeping the best score.
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in (0..lower.len()).filter(|&i| lower[i] == query[0]) {
+        let mut positions = Vec::with_capacity(query.len());
+        let mut next = start;
+        for &q in &query {
+            match (next..lower.len()).find(|&i| lower[i] == q) {
+                Some(i) => {
+                    positions.push(i);
+                    next = i + 1;
+                }
+                None => break,
+            }
+        }
+        if positions.len() < query.len() {
+            // Later starts cannot match more.
+            break;
+        }
+
+        let mut score = 0;
+        let mut last: Option<usize> = None;
+        for &i in &positions {
+            score += SCORE_MATCH;
+            if is_boundary(i.checked_sub(1).map(|p| chars[p]), chars[i]) {
+                score += BONUS_BOUNDARY;
+            }
+            match last {
+                Some(last) if i == last + 1 => score += BONUS_CONSECUTIVE,
+                Some(last) => score -= ((i - last - 1) as i64).min(MAX_GAP_PENALTY),
+                None => score -= (i as i64).min(MAX_GAP_PENALTY),
+            }
+            last = Some(i);
+        }
+        if positions[0] == 0 {
+            score += BONUS_FIRST;
+        }
+        if best.as_ref().map_or(true, |(s, _)| score > *s) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+/// An entry matching the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteMatch {
+    /// Index of the entry.
+    pub index: usize,
+    /// Match score; higher ranks first.
+    pub score: i64,
+    /// Character indexes of the name that matched, for highlighting.
+    pub positions: Vec<usize>,
+}
+
+/// An editing or selection command for a [`CommandPalette`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteInput {
+    /// Append a character to the query.
+    Insert(char),
+    /// Delete the last character of the query.
+    Backspace,
+    /// Clear the query.
+    Clear,
+    /// Move the selection down.
+    Next(usize),
+    /// Move the selection up.
+    Prev(usize),
+    /// Move the selection down one page.
+    PageDown,
+    /// Move the selection up one page.
+    PageUp,
+    /// Pick the selected entry.
+    Accept,
+    /// Close without picking.
+    Cancel,
+}
+
+impl PaletteInput {
+    /// Map a key code, as named by `terminal.next_event`, with whether
+    /// Ctrl was held: `Enter` picks, `Esc` and Ctrl+C cancel, arrows and
+    /// Ctrl+N/Ctrl+P move, `PageUp`/`PageDown` page, `Backspace` deletes,
+    /// Ctrl+U clears and other characters are typed.
+    pub fn from_key_code(code: &str, ctrl: bool) -> Option<Self> {
+        Some(match code {
+            "Enter" => Self::Accept,
+            "Esc" => Self::Cancel,
+            "ArrowDown" | "Tab" => Self::Next(1),
+            "ArrowUp" | "BackTab" => Self::Prev(1),
+            "PageDown" => Self::PageDown,
+            "PageUp" => Self::PageUp,
+            "Backspace" => Self::Backspace,
+            "c" if ctrl => Self::Cancel,
+            "n" if ctrl => Self::Next(1),
+            "p" if ctrl => Self::Prev(1),
+            "u" if ctrl => Self::Clear,
+            _ if ctrl => return None,
+            _ => {
+                let mut chars = code.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Self::Insert(c),
+                    _ => return None,
+                }
+            }
+        })
+    }
+
+    /// Map a key event with the bindings of
+    /// [`from_key_code`](Self::from_key_code).
+    pub fn from_key(key: &KeyEvent) -> Option<Self> {
+        if key.kind == KeyEventKind::Release || key.modifiers.contains(KeyModifiers::ALT) {
+            return None;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => Some(Self::Accept),
+            KeyCode::Esc => Some(Self::Cancel),
+            KeyCode::Down | KeyCode::Tab => Some(Self::Next(1)),
+            KeyCode::Up | KeyCode::BackTab => Some(Self::Prev(1)),
+            KeyCode::PageDown => Some(Self::PageDown),
+            KeyCode::PageUp => Some(Self::PageUp),
+            KeyCode::Backspace => Some(Self::Backspace),
+            KeyCode::Char(c) => Self::from_key_code(c.encode_utf8(&mut [0; 4]), ctrl),
+            _ => None,
+        }
+    }
+}
+
+/// Result of input to a [`CommandPalette`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaletteOutcome {
+    /// The palette is still open.
+    Open,
+    /// An entry was picked; holds its action.
+    Selected(Value),
+    /// The palette was cancelled.
+    Cancelled,
+}
+
+/// Entries, query and ranked matches of a command palette.
+#[derive(Debug, Clone, Default)]
+pub struct CommandPalette {
+    entries: Vec<PaletteEntry>,
+    query: String,
+    matches: Vec<PaletteMatch>,
+    view: VirtualListState,
+    height: usize,
+}
+
+impl CommandPalette {
+    /// Create a palette over entries with an empty query, selecting the
+    /// first entry.
+    pub fn new(entries: Vec<PaletteEntry>) -> Self {
+        let mut palette = Self {
+            entries,
+            ..Self::default()
+        };
+        palette.refilter();
+        palette
+    }
+
+    /// All entries.
+    pub fn entries(&self) -> &[PaletteEntry] {
+        &self.entries
+    }
+
+    /// The query.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Entries matching the query, best first.
+    pub fn matches(&self) -> &[PaletteMatch] {
+        &self.matches
+    }
+
+    /// Replace the query and re-rank, selecting the best match.
+    pub fn set_query(&mut self, query: impl Into<String>) {
+        self.query = query.into();
+        self.refilter();
+    }
+
+    fn refilter(&mut self) {
+        let mut matches: Vec<PaletteMatch> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                if let Some((score, positions)) = fuzzy_match(&self.query, &entry.name) {
+                    return Some(PaletteMatch {
+                        index,
+                        score,
+                        positions,
+                    });
+                }
+                // Description matches rank below any name match.
+                fuzzy_match(&self.query, &entry.description).map(|(score, _)| PaletteMatch {
+                    index,
+                    score: score / 2 - SCORE_MATCH * self.query.chars().count() as i64,
+                    positions: Vec::new(),
+                })
+            })
+            .collect();
+        if !self.query.trim().is_empty() {
+            let entries = &self.entries;
+            matches.sort_by(|a, b| {
+                b.score
+                    .cmp(&a.score)
+                    .then_with(|| {
+                        entries[a.index]
+                            .name
+                            .len()
+                            .cmp(&entries[b.index].name.len())
+                    })
+                    .then_with(|| a.index.cmp(&b.index))
+            });
+        }
+        self.matches = matches;
+        self.view = VirtualListState::new();
+        self.view.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    /// Position of the selection among the matches.
+    pub fn selected(&self) -> Option<usize> {
+        self.view.selected()
+    }
+
+    /// The selected entry.
+    pub fn selected_entry(&self) -> Option<&PaletteEntry> {
+        self.view
+            .selected()
+            .and_then(|i| self.matches.get(i))
+            .map(|m| &self.entries[m.index])
+    }
+
+    fn page(&self) -> usize {
+        if self.height == 0 {
+            DEFAULT_PAGE
+        } else {
+            self.height
+        }
+    }
+
+    /// Apply an editing or selection command.
+    pub fn apply(&mut self, input: PaletteInput) -> PaletteOutcome {
+        match input {
+            PaletteInput::Insert(c) => {
+                self.query.push(c);
+                self.refilter();
+            }
+            PaletteInput::Backspace => {
+                if self.query.pop().is_some() {
+                    self.refilter();
+                }
+            }
+            PaletteInput::Clear => {
+                if !self.query.is_empty() {
+                    self.query.clear();
+                    self.refilter();
+                }
+            }
+            PaletteInput::Next(n) => self.view.select_down(n, self.matches.len()),
+            PaletteInput::Prev(n) => {
+                if !self.matches.is_empty() {
+                    self.view.select_up(n);
+                }
+            }
+            PaletteInput::PageDown => self.view.select_down(self.page(), self.matches.len()),
+            PaletteInput::PageUp => {
+                if !self.matches.is_empty() {
+                    self.view.select_up(self.page());
+                }
+            }
+            PaletteInput::Accept => {
+                if let Some(entry) = self.selected_entry() {
+                    return PaletteOutcome::Selected(entry.action.clone());
+                }
+            }
+            PaletteInput::Cancel => return PaletteOutcome::Cancelled,
+        }
+        PaletteOutcome::Open
+    }
+
+    /// Handle a key with the bindings of [`PaletteInput::from_key`].
+    pub fn handle_key(&mut self, key: &KeyEvent) -> PaletteOutcome {
+        match PaletteInput::from_key(key) {
+            Some(input) => self.apply(input),
+            None => PaletteOutcome::Open,
+        }
+    }
+
+    /// Matches to draw in a viewport of `height` rows, scrolling to keep
+    /// the selection visible. Paging uses the last height given.
+    pub fn visible_range(&mut self, height: usize) -> std::ops::Range<usize> {
+        self.height = height;
+        self.view.visible_range(self.matches.len(), height)
+    }
+
+    /// Show the palette on `terminal` and wait for an entry to be picked.
+    ///
+    /// Returns the picked entry's action, or `None` if the palette was
+    /// cancelled with Esc or Ctrl+C.
+    pub fn pick<B: Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        events: &mut impl EventSource,
+        title: Option<&str>,
+    ) -> crate::error::Result<Option<Value>> {
+        loop {
+            terminal.draw(|frame| {
+                let mut view = PaletteView::new();
+                if let Some(title) = title {
+                    view = view.block(titled_block(title));
+                }
+                frame.render_stateful_widget(view, frame.size(), self);
+            })?;
+            let Some(Event::Key(key)) = events.next_event(Duration::from_millis(250))? else {
+                continue;
+            };
+            match self.handle_key(&key) {
+                PaletteOutcome::Open => {}
+                PaletteOutcome::Selected(action) => return Ok(Some(action)),
+                PaletteOutcome::Cancelled => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Widget drawing a [`CommandPalette`]: the query on the first line and
+/// the visible matches below it, with matched characters underlined and
+/// the selection reversed.
+#[derive(Default)]
+pub struct PaletteView<'a> {
+    block: Option<Block<'a>>,
+    placeholder: Option<&'a str>,
+}
+
+impl<'a> PaletteView<'a> {
+    /// Create a palette view.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Surround the view with a block.
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    /// Text shown dimmed while the query is empty.
+    pub fn placeholder(mut self, placeholder: &'a str) -> Self {
+        self.placeholder = Some(placeholder);
+        self
+    }
+}
+
+fn match_line<'a>(entry: &'a PaletteEntry, positions: &[usize], selected: bool) -> Line<'a> {
+    let base = if selected {
+        Style::default().add_modifier(Modifier::REVERSED)
+    } else {
+        Style::default()
+    };
+    let matched = base.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let mut spans: Vec<Span<'a>> = entry
+        .name
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let style = if positions.contains(&i) {
+                matched
+            } else {
+                base
+            };
+            Span::styled(c.to_string(), style)
+        })
+        .collect();
+    if !entry.description.is_empty() {
+        spans.push(Span::styled("  ", base));
+        spans.push(Span::styled(
+            entry.description.as_str(),
+            base.add_modifier(Modifier::DIM),
+        ));
+    }
+    Line::from(spans)
+}
+
+impl StatefulWidget for PaletteView<'_> {
+    type State = CommandPalette;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        if inner.height == 0 {
+            return;
+        }
+
+        let input = match self.placeholder {
+            Some(placeholder) if state.query.is_empty() => Line::from(vec![
+                Span::raw("> "),
+                Span::styled(placeholder, Style::default().add_modifier(Modifier::DIM)),
+            ]),
+            _ => Line::raw(format!("> {}", state.query)),
+        };
+        Paragraph::new(input).render(Rect { height: 1, ..inner }, buf);
+
+        // One line goes to the query.
+        let range = state.visible_range(inner.height.saturating_sub(1) as usize);
+        let selected = state.selected();
+        let lines: Vec<Line<'_>> = range
+            .map(|i| {
+                let m = &state.matches[i];
+                match_line(&state.entries[m.index], &m.positions, selected == Some(i))
+            })
+            .collect();
+        Paragraph::new(lines).render(
+            Rect {
+                y: inner.y + 1,
+                height: inner.height - 1,
+                ..inner
+            },
+            buf,
+        );
+    }
+}
+
+static PALETTES: OnceLock<Mutex<HashMap<i64, CommandPalette>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn palettes() -> &'static Mutex<HashMap<i64, CommandPalette>> {
+    PALETTES.get_or_init(Default::default)
+}
+
+/// Number of open palettes.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    palettes().lock().len()
+}
+
+fn palette_handle(args: &[Value], function: &str) -> Result<i64> {
+    args.first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing handle argument", function)))
+}
+
+fn with_palette<T>(
+    args: &[Value],
+    function: &str,
+    f: impl FnOnce(&mut CommandPalette) -> Result<T>,
+) -> Result<T> {
+    let handle = palette_handle(args, function)?;
+    let mut palettes = palettes().lock();
+    let palette = palettes
+        .get_mut(&handle)
+        .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))?;
+    f(palette)
+}
+
+fn entries_arg(value: Option<&Value>, function: &str) -> Result<Vec<PaletteEntry>> {
+    value
+        .and_then(|v| v.as_list())
+        .ok_or_else(|| Error::host_function(format!("{}: entries must be a list", function)))?
+        .iter()
+        .map(|entry| {
+            PaletteEntry::from_value(entry)
+                .map_err(|e| Error::host_function(format!("{}: {}", function, e)))
+        })
+        .collect()
+}
+
+fn string_option<'a>(
+    options: Option<&'a HashMap<String, Value>>,
+    key: &str,
+    function: &str,
+) -> Result<Option<&'a str>> {
+    match options.and_then(|m| m.get(key)) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(Error::host_function(format!(
+            "{}: {} must be a string",
+            function, key
+        ))),
+    }
+}
+
+fn outcome_value(outcome: PaletteOutcome) -> Value {
+    let (status, action) = match outcome {
+        PaletteOutcome::Open => ("open", None),
+        PaletteOutcome::Selected(action) => ("selected", Some(action)),
+        PaletteOutcome::Cancelled => ("cancelled", None),
+    };
+    let mut fields = HashMap::from([("status".to_string(), Value::String(status.into()))]);
+    if let Some(action) = action {
+        fields.insert("action".to_string(), action);
+    }
+    Value::Map(fields)
+}
+
+/// Create a command palette.
+///
+/// # Arguments
+///
+/// * `args[0]` - Entries: names, or maps with `name`, `description`
+///   (optional) and `action` (optional, default: the name)
+/// * `args[1]` - Options map (optional):
+///   - `query`: Initial query
+///
+/// # Returns
+///
+/// Handle (integer)
+pub fn palette(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.palette";
+    let mut palette = CommandPalette::new(entries_arg(args.first(), function)?);
+    if let Some(query) = string_option(args.get(1).and_then(|v| v.as_map()), "query", function)? {
+        palette.set_query(query);
+    }
+
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::Relaxed);
+    palettes().lock().insert(handle, palette);
+    Ok(Value::Int(handle))
+}
+
+/// Replace the query of a palette.
+///
+/// # Arguments
+///
+/// * `args[0]` - Palette handle
+/// * `args[1]` - Query
+///
+/// # Returns
+///
+/// Number of matching entries
+pub fn palette_set_query(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.palette_set_query";
+    let query = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function(format!("{}: missing query argument", function)))?;
+    with_palette(args, function, |palette| {
+        palette.set_query(query);
+        Ok(Value::Int(palette.matches().len() as i64))
+    })
+}
+
+/// Apply the palette key bindings to an event from `terminal.next_event`
+/// or `terminal_ui.run`: characters edit the query, `Backspace` deletes
+/// and Ctrl+U clears it, arrows, Tab and Ctrl+N/Ctrl+P move the selection,
+/// `Enter` picks and `Esc` cancels. Pasted text is appended to the query.
+///
+/// # Arguments
+///
+/// * `args[0]` - Palette handle
+/// * `args[1]` - Event map
+///
+/// # Returns
+///
+/// Map with `status` (`"open"`, `"selected"` or `"cancelled"`) and, when
+/// selected, the entry's `action`
+pub fn palette_event(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.palette_event";
+    let event = args
+        .get(1)
+        .and_then(|v| v.as_map())
+        .ok_or_else(|| Error::host_function(format!("{}: missing event argument", function)))?;
+    let field = |key: &str| event.get(key).and_then(|v| v.as_str());
+    let modifier = |name: &str| {
+        event
+            .get("modifiers")
+            .and_then(|v| v.as_list())
+            .is_some_and(|m| m.iter().any(|v| v.as_str() == Some(name)))
+    };
+
+    with_palette(args, function, |palette| {
+        let outcome = match field("type") {
+            Some("key") if field("kind") != Some("release") && !modifier("alt") => field("code")
+                .and_then(|code| PaletteInput::from_key_code(code, modifier("ctrl")))
+                .map_or(PaletteOutcome::Open, |input| palette.apply(input)),
+            Some("paste") => {
+                let text = field("text").unwrap_or_default();
+                let query = format!("{}{}", palette.query(), text.replace(['\r', '\n'], " "));
+                palette.set_query(query);
+                PaletteOutcome::Open
+            }
+            _ => PaletteOutcome::Open,
+        };
+        Ok(outcome_value(outcome))
+    })
+}
+
+/// Get the selected entry's action.
+///
+/// # Arguments
+///
+/// * `args[0]` - Palette handle
+///
+/// # Returns
+///
+/// The action, or null if nothing matches
+pub fn palette_selected(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    with_palette(args, "terminal_ui.palette_selected", |palette| {
+        Ok(palette
+            .selected_entry()
+            .map_or(Value::Null, |entry| entry.action.clone()))
+    })
+}
+
+/// Build a widget tree for `terminal_ui.run` from a palette: a `column`
+/// with the query line above a `list` of matches, each its name and
+/// description.
+///
+/// # Arguments
+///
+/// * `args[0]` - Palette handle
+/// * `args[1]` - Options map (optional):
+///   - `height`: Rows of matches in view (default: 10); only that window
+///     is included, scrolled to keep the selection visible
+///   - `title`: Block title
+///   - `placeholder`: Text shown while the query is empty
+///
+/// # Returns
+///
+/// Widget map with `type: "column"`
+pub fn palette_view(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.palette_view";
+    let options = args.get(1).and_then(|v| v.as_map());
+    let height = match options.and_then(|m| m.get("height")) {
+        None | Some(Value::Null) => DEFAULT_PAGE,
+        Some(value) => value
+            .as_int()
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(|| {
+                Error::host_function(format!(
+                    "{}: height must be a non-negative integer",
+                    function
+                ))
+            })?,
+    };
+    let title = string_option(options, "title", function)?;
+    let placeholder = string_option(options, "placeholder", function)?;
+
+    with_palette(args, function, |palette| {
+        let range = palette.visible_range(height);
+        let start = range.start;
+        let items = palette.matches[range]
+            .iter()
+            .map(|m| {
+                let entry = &palette.entries[m.index];
+                Value::String(if entry.description.is_empty() {
+                    entry.name.clone()
+                } else {
+                    format!("{}  {}", entry.name, entry.description)
+                })
+            })
+            .collect();
+        let input = match placeholder {
+            Some(placeholder) if palette.query.is_empty() => Value::Map(HashMap::from([
+                ("type".to_string(), Value::String("paragraph".into())),
+                (
+                    "text".to_string(),
+                    Value::String(format!("> {}", placeholder)),
+                ),
+                (
+                    "style".to_string(),
+                    Value::Map(HashMap::from([("dim".to_string(), Value::Bool(true))])),
+                ),
+            ])),
+            _ => Value::String(format!("> {}", palette.query)),
+        };
+        let list = Value::Map(HashMap::from([
+            ("type".to_string(), Value::String("list".into())),
+            ("items".to_string(), Value::List(items)),
+            (
+                "selected".to_string(),
+                palette
+                    .selected()
+                    .and_then(|s| s.checked_sub(start))
+                    .map_or(Value::Null, |i| Value::Int(i as i64)),
+            ),
+        ]));
+
+        let column = HashMap::from([
+            ("type".to_string(), Value::String("column".into())),
+            ("children".to_string(), Value::List(vec![input, list])),
+            (
+                "constraints".to_string(),
+                Value::List(vec![Value::Int(1), Value::String("*".into())]),
+            ),
+        ]);
+        Ok(match title {
+            Some(title) => Value::Map(HashMap::from([
+                ("type".to_string(), Value::String("block".into())),
+                ("title".to_string(), Value::String(title.to_string())),
+                ("child".to_string(), Value::Map(column)),
+            ])),
+            None => Value::Map(column),
+        })
+    })
+}
+
+/// Release a palette.
+///
+/// # Arguments
+///
+/// * `args[0]` - Palette handle
+pub fn palette_close(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = palette_handle(args, "terminal_ui.palette_close")?;
+    palettes().lock().remove(&handle);
+    Ok(Value::Null)
+}
+
+/// Show a full-screen command palette and wait for an entry to be picked.
+///
+/// The terminal is switched to raw mode and the alternate screen while the
+/// palette is open and restored afterwards.
+///
+/// # Arguments
+///
+/// * `args[0]` - Entries, as for `terminal_ui.palette`
+/// * `args[1]` - Options map (optional):
+///   - `title`: Block title
+///   - `query`: Initial query
+///
+/// # Returns
+///
+/// The picked entry's action, or null if the palette was cancelled
+pub fn palette_pick(
+    guard: &Arc<TerminalGuard>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> Result<Value> {
+    let function = "terminal_ui.palette_pick";
+    let mut palette = CommandPalette::new(entries_arg(args.first(), function)?);
+    let options = args.get(1).and_then(|v| v.as_map());
+    if let Some(query) = string_option(options, "query", function)? {
+        palette.set_query(query);
+    }
+    let title = string_option(options, "title", function)?;
+
+    let to_host = |e: crate::error::Error| Error::host_function(format!("{}: {}", function, e));
+
+    let was_raw = guard.is_raw_mode();
+    let setup = || -> crate::error::Result<()> {
+        guard.enable_raw_mode()?;
+        guard.enter_alternate_screen()?;
+        Ok(())
+    };
+    let result = setup().and_then(|()| {
+        let mut terminal = super::TerminalUI::new()?.terminal;
+        palette.pick(&mut terminal, &mut TerminalEvents, title)
+    });
+
+    let teardown = guard.leave_alternate_screen().and_then(|()| {
+        if was_raw {
+            Ok(())
+        } else {
+            guard.disable_raw_mode()
+        }
+    });
+
+    let action = result.map_err(to_host)?;
+    teardown.map_err(|e| to_host(e.into()))?;
+    Ok(action.unwrap_or(Value::Null))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+    use ratatui::backend::TestBackend;
+    use std::collections::VecDeque;
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    fn commands() -> Vec<PaletteEntry> {
+        vec![
+            PaletteEntry::new("Open File").with_description("Open a file from disk"),
+            PaletteEntry::new("Save File"),
+            PaletteEntry::new("Toggle Sidebar").with_action(Value::Int(3)),
+            PaletteEntry::new("Go to Line").with_description("Jump within the file"),
+            PaletteEntry::new("Reload Window"),
+        ]
+    }
+
+    fn names(palette: &CommandPalette) -> Vec<&str> {
+        palette
+            .matches()
+            .iter()
+            .map(|m| palette.entries()[m.index].name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert_eq!(fuzzy_match("", "anything"), Some((0, vec![])));
+        assert_eq!(fuzzy_match("xyz", "Open File"), None);
+
+        let (_, positions) = fuzzy_match("of", "Open File").unwrap();
+        assert_eq!(positions, vec![0, 5]);
+        let (_, positions) = fuzzy_match("sb", "Toggle Sidebar").unwrap();
+        assert_eq!(positions, vec![7, 11]);
+
+        let prefix = fuzzy_match("save", "Save File").unwrap().0;
+        let scattered = fuzzy_match("save", "Show all variables everywhere")
+            .unwrap()
+            .0;
+        assert!(prefix > scattered);
+        assert!(
+            fuzzy_match("fs", "fileSystem").unwrap().0 > fuzzy_match("fs", "offset").unwrap().0
+        );
+    }
+
+    #[test]
+    fn test_palette_filters_and_ranks() {
+        let mut palette = CommandPalette::new(commands());
+        assert_eq!(palette.matches().len(), 5);
+        assert_eq!(palette.selected_entry().unwrap().name, "Open File");
+
+        palette.set_query("file");
+        // Name matches first, then the description-only match.
+        assert_eq!(
+            names(&palette),
+            vec!["Open File", "Save File", "Go to Line"]
+        );
+
+        palette.apply(PaletteInput::Clear);
+        palette.apply(PaletteInput::Insert('s'));
+        palette.apply(PaletteInput::Insert('f'));
+        assert_eq!(names(&palette)[0], "Save File");
+
+        palette.set_query("zzz");
+        assert!(palette.matches().is_empty());
+        assert_eq!(palette.apply(PaletteInput::Accept), PaletteOutcome::Open);
+        assert_eq!(palette.apply(PaletteInput::Next(1)), PaletteOutcome::Open);
+        assert_eq!(palette.selected(), None);
+    }
+
+    #[test]
+    fn test_palette_keys_and_pick() {
+        struct Scripted(VecDeque<Event>);
+
+        impl EventSource for Scripted {
+            fn next_event(&mut self, _timeout: Duration) -> crate::error::Result<Option<Event>> {
+                Ok(self.0.pop_front())
+            }
+        }
+
+        let key = |code: KeyCode| Event::Key(KeyEvent::new(code, KeyModifiers::NONE));
+        let mut events = Scripted(VecDeque::from([
+            key(KeyCode::Char('t')),
+            key(KeyCode::Char('x')),
+            key(KeyCode::Backspace),
+            key(KeyCode::Down),
+            key(KeyCode::Up),
+            key(KeyCode::Enter),
+        ]));
+        let mut terminal = Terminal::new(TestBackend::new(30, 4)).unwrap();
+        let mut palette = CommandPalette::new(commands());
+        let action = palette.pick(&mut terminal, &mut events, None).unwrap();
+        assert_eq!(action, Some(Value::Int(3)));
+
+        let buffer = terminal.backend().buffer();
+        let line = |y: u16| -> String {
+            (0..14)
+                .map(|x| buffer.get(x, y).symbol().to_string())
+                .collect()
+        };
+        assert_eq!(line(0), "> t           ");
+        assert_eq!(line(1), "Toggle Sidebar");
+        assert!(buffer.get(0, 1).modifier.contains(Modifier::REVERSED));
+        assert!(buffer.get(0, 1).modifier.contains(Modifier::UNDERLINED));
+
+        let ctrl_c = Event::Key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        let mut events = Scripted(VecDeque::from([ctrl_c]));
+        assert_eq!(
+            palette
+                .pick(&mut terminal, &mut events, Some("Go"))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_palette_host_functions() {
+        let ctx = ctx();
+        let entries = Value::List(vec![
+            Value::String("deploy".into()),
+            Value::Map(HashMap::from([
+                ("name".to_string(), Value::String("describe pod".into())),
+                ("description".to_string(), Value::String("kubectl".into())),
+                ("action".to_string(), Value::Int(7)),
+            ])),
+        ]);
+        let handle = palette(&[entries], &ctx).unwrap();
+        assert!(palette(&[Value::List(vec![Value::Int(1)])], &ctx).is_err());
+
+        assert_eq!(
+            palette_set_query(&[handle.clone(), Value::String("dp".into())], &ctx).unwrap(),
+            Value::Int(2)
+        );
+
+        let key = |code: &str, modifiers: &[&str]| {
+            Value::Map(HashMap::from([
+                ("type".to_string(), Value::String("key".into())),
+                ("code".to_string(), Value::String(code.into())),
+                ("kind".to_string(), Value::String("press".into())),
+                (
+                    "modifiers".to_string(),
+                    Value::List(
+                        modifiers
+                            .iter()
+                            .map(|m| Value::String(m.to_string()))
+                            .collect(),
+                    ),
+                ),
+            ]))
+        };
+        let status = |result: Value| result.as_map().unwrap()["status"].clone();
+        assert_eq!(
+            status(palette_event(&[handle.clone(), key("u", &["ctrl"])], &ctx).unwrap()),
+            Value::String("open".into())
+        );
+        for code in ["d", "e", "s"] {
+            palette_event(&[handle.clone(), key(code, &[])], &ctx).unwrap();
+        }
+        assert_eq!(
+            palette_selected(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Int(7)
+        );
+
+        let options = Value::Map(HashMap::from([
+            ("height".to_string(), Value::Int(3)),
+            ("title".to_string(), Value::String("Commands".into())),
+        ]));
+        let view = palette_view(&[handle.clone(), options], &ctx).unwrap();
+        crate::terminal_ui::parse_widget(&view).unwrap();
+        let column = view.as_map().unwrap()["child"].as_map().unwrap().clone();
+        let children = column["children"].as_list().unwrap();
+        assert_eq!(children[0], Value::String("> des".into()));
+        assert_eq!(
+            children[1].as_map().unwrap()["items"],
+            Value::List(vec![Value::String("describe pod  kubectl".into())])
+        );
+
+        let result = palette_event(&[handle.clone(), key("Enter", &[])], &ctx).unwrap();
+        assert_eq!(result.as_map().unwrap()["action"], Value::Int(7));
+        assert_eq!(
+            status(palette_event(&[handle.clone(), key("Esc", &[])], &ctx).unwrap()),
+            Value::String("cancelled".into())
+        );
+
+        palette_close(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(palette_selected(&[handle], &ctx).is_err());
+    }
+}
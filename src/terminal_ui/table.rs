@@ -0,0 +1,762 @@
+//! Tables of maps.
+//!
+//! [`value_table`] turns a list of maps into a ratatui [`Table`], inferring
+//! columns and widths. [`ValueTableState`] holds the rows with their sort
+//! order and selection, windowed with [`VirtualListState`] so only the rows
+//! in view are converted to cells; scripts drive it through handles.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::sync::atomic::{AtomicI64, Ordering as AtomicOrdering};
+use std::sync::OnceLock;
+
+use parking_lot::Mutex;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Modifier, Style},
+    text::Span,
+    widgets::{Block, Row, StatefulWidget, Table, TableState, Widget},
+};
+
+use fusabi_host::{Error, ExecutionContext, Result, Value};
+
+use super::VirtualListState;
+
+/// Widest column produced by width inference.
+pub const MAX_COLUMN_WIDTH: u16 = 40;
+
+/// Columns of a list of maps: every key that appears, sorted.
+pub fn infer_columns(rows: &[Value]) -> Vec<String> {
+    let keys: BTreeSet<&String> = rows
+        .iter()
+        .filter_map(|row| row.as_map())
+        .flat_map(|row| row.keys())
+        .collect();
+    keys.into_iter().cloned().collect()
+}
+
+/// Text of a cell.
+pub fn cell_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Column widths fitting the header and cells, capped at `max_width`.
+pub fn column_widths(columns: &[String], rows: &[Value], max_width: u16) -> Vec<Constraint> {
+    fit_widths(columns, columns, rows, max_width)
+}
+
+fn fit_widths(
+    headers: &[String],
+    columns: &[String],
+    rows: &[Value],
+    max_width: u16,
+) -> Vec<Constraint> {
+    headers
+        .iter()
+        .zip(columns)
+        .map(|(header, column)| {
+            let cells = rows
+                .iter()
+                .map(|row| Span::raw(cell_text(row.as_map().and_then(|m| m.get(column)))).width());
+            let width = cells.fold(Span::raw(header.as_str()).width(), usize::max);
+            Constraint::Length(u16::try_from(width).unwrap_or(u16::MAX).min(max_width))
+        })
+        .collect()
+}
+
+fn to_row<'a>(row: &Value, columns: &[String]) -> Row<'a> {
+    let fields = row.as_map();
+    Row::new(
+        columns
+            .iter()
+            .map(|column| cell_text(fields.and_then(|m| m.get(column))))
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn header_text(columns: &[String], sort: Option<&(String, bool)>) -> Vec<String> {
+    columns
+        .iter()
+        .map(|column| match sort {
+            Some((sorted, descending)) if sorted == column => {
+                format!("{} {}", column, if *descending { "▼" } else { "▲" })
+            }
+            _ => column.clone(),
+        })
+        .collect()
+}
+
+fn header_row<'a>(headers: Vec<String>) -> Row<'a> {
+    Row::new(headers).style(Style::default().add_modifier(Modifier::BOLD))
+}
+
+/// Create a table from a list of maps.
+///
+/// Columns default to every key, sorted, and widths fit the content up to
+/// [`MAX_COLUMN_WIDTH`]. The selected row is drawn reversed when rendered
+/// with a [`TableState`].
+pub fn value_table<'a>(rows: &[Value], columns: Option<&[String]>) -> Table<'a> {
+    let inferred;
+    let columns = match columns {
+        Some(columns) => columns,
+        None => {
+            inferred = infer_columns(rows);
+            &inferred
+        }
+    };
+
+    Table::new(
+        rows.iter()
+            .map(|row| to_row(row, columns))
+            .collect::<Vec<_>>(),
+        column_widths(columns, rows, MAX_COLUMN_WIDTH),
+    )
+    .header(header_row(columns.to_vec()))
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+/// Order two cells: numbers numerically, then strings, with missing
+/// values last.
+pub fn compare_cells(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    let rank = |v: Option<&Value>| match v {
+        Some(Value::Int(_) | Value::Float(_)) => 0,
+        Some(Value::Bool(_)) => 1,
+        Some(Value::String(_)) => 2,
+        None | Some(Value::Null) => 4,
+        Some(_) => 3,
+    };
+    match (a, b) {
+        (Some(Value::Int(x)), Some(Value::Int(y))) => x.cmp(y),
+        (
+            Some(x @ (Value::Int(_) | Value::Float(_))),
+            Some(y @ (Value::Int(_) | Value::Float(_))),
+        ) => {
+            let (x, y) = (x.as_float().unwrap_or(0.0), y.as_float().unwrap_or(0.0));
+            x.total_cmp(&y)
+        }
+        (Some(Value::Bool(x)), Some(Value::Bool(y))) => x.cmp(y),
+        (Some(Value::String(x)), Some(Value::String(y))) => x.cmp(y),
+        _ => rank(a)
+            .cmp(&rank(b))
+            .then_with(|| cell_text(a).cmp(&cell_text(b))),
+    }
+}
+
+/// Rows of a table with sort order and selection.
+#[derive(Debug, Clone, Default)]
+pub struct ValueTableState {
+    rows: Vec<Value>,
+    columns: Vec<String>,
+    explicit_columns: bool,
+    sort: Option<(String, bool)>,
+    view: VirtualListState,
+}
+
+impl ValueTableState {
+    /// Create a state over rows with inferred columns and nothing selected.
+    pub fn new(rows: Vec<Value>) -> Self {
+        let mut state = Self::default();
+        state.set_rows(rows);
+        state
+    }
+
+    /// Show only these columns, in this order.
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = columns;
+        self.explicit_columns = true;
+        self
+    }
+
+    /// Rows in display order.
+    pub fn rows(&self) -> &[Value] {
+        &self.rows
+    }
+
+    /// Displayed columns.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Sort column and whether it is descending.
+    pub fn sort(&self) -> Option<(&str, bool)> {
+        self.sort.as_ref().map(|(c, d)| (c.as_str(), *d))
+    }
+
+    /// Replace the rows, keeping the sort order and selection index.
+    pub fn set_rows(&mut self, rows: Vec<Value>) {
+        self.rows = rows;
+        if !self.explicit_columns {
+            self.columns = infer_columns(&self.rows);
+        }
+        self.apply_sort();
+        if let Some(selected) = self.view.selected() {
+            let len = self.rows.len();
+            self.view.select((len > 0).then(|| selected.min(len - 1)));
+        }
+    }
+
+    /// Sort by a column; the selection follows the selected row.
+    pub fn sort_by(&mut self, column: &str, descending: bool) {
+        let selected = self.selected_row().cloned();
+        self.sort = Some((column.to_string(), descending));
+        self.apply_sort();
+        if let Some(selected) = selected {
+            self.view
+                .select(self.rows.iter().position(|row| *row == selected));
+        }
+    }
+
+    fn apply_sort(&mut self) {
+        if let Some((column, descending)) = &self.sort {
+            let cell = |row: &Value| row.as_map().and_then(|m| m.get(column.as_str())).cloned();
+            let missing = |v: &Option<Value>| matches!(v, None | Some(Value::Null));
+            self.rows.sort_by(|a, b| {
+                let (a, b) = (cell(a), cell(b));
+                // Missing values stay last in either direction.
+                missing(&a).cmp(&missing(&b)).then_with(|| {
+                    let order = compare_cells(a.as_ref(), b.as_ref());
+                    if *descending {
+                        order.reverse()
+                    } else {
+                        order
+                    }
+                })
+            });
+        }
+    }
+
+    /// Move the selection down by `n` rows.
+    pub fn select_next(&mut self, n: usize) {
+        self.view.select_down(n, self.rows.len());
+    }
+
+    /// Move the selection up by `n` rows.
+    pub fn select_prev(&mut self, n: usize) {
+        if !self.rows.is_empty() {
+            self.view.select_up(n);
+        }
+    }
+
+    /// Select a row by display index.
+    pub fn select(&mut self, index: Option<usize>) {
+        self.view.select(index.filter(|i| *i < self.rows.len()));
+    }
+
+    /// Display index of the selected row.
+    pub fn selected(&self) -> Option<usize> {
+        self.view.selected()
+    }
+
+    /// The selected row.
+    pub fn selected_row(&self) -> Option<&Value> {
+        self.view.selected().and_then(|i| self.rows.get(i))
+    }
+
+    /// Rows to draw in a viewport of `height` rows, scrolling to keep the
+    /// selection visible.
+    pub fn visible_range(&mut self, height: usize) -> Range<usize> {
+        self.view.visible_range(self.rows.len(), height)
+    }
+}
+
+/// Table widget over a [`ValueTableState`] that converts only the visible
+/// rows.
+#[derive(Default)]
+pub struct ValueTable<'a> {
+    block: Option<Block<'a>>,
+}
+
+impl<'a> ValueTable<'a> {
+    /// Create a table widget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Surround the table with a block.
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl StatefulWidget for ValueTable<'_> {
+    type State = ValueTableState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+
+        // One line goes to the header.
+        let range = state.visible_range(inner.height.saturating_sub(1) as usize);
+        let start = range.start;
+        let visible = &state.rows[range];
+        let headers = header_text(&state.columns, state.sort.as_ref());
+        let table = Table::new(
+            visible
+                .iter()
+                .map(|row| to_row(row, &state.columns))
+                .collect::<Vec<_>>(),
+            fit_widths(&headers, &state.columns, visible, MAX_COLUMN_WIDTH),
+        )
+        .header(header_row(headers))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        let mut table_state = TableState::default()
+            .with_selected(state.selected().and_then(|s| s.checked_sub(start)));
+        StatefulWidget::render(table, inner, buf, &mut table_state);
+    }
+}
+
+static TABLES: OnceLock<Mutex<HashMap<i64, ValueTableState>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn tables() -> &'static Mutex<HashMap<i64, ValueTableState>> {
+    TABLES.get_or_init(Default::default)
+}
+
+/// Number of open table states.
+#[cfg(feature = "observability")]
+pub(crate) fn open_handles() -> usize {
+    tables().lock().len()
+}
+
+fn table_handle(args: &[Value], function: &str) -> Result<i64> {
+    args.first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| Error::host_function(format!("{}: missing handle argument", function)))
+}
+
+fn with_table<T>(
+    args: &[Value],
+    function: &str,
+    f: impl FnOnce(&mut ValueTableState) -> Result<T>,
+) -> Result<T> {
+    let handle = table_handle(args, function)?;
+    let mut tables = tables().lock();
+    let state = tables
+        .get_mut(&handle)
+        .ok_or_else(|| Error::host_function(format!("{}: invalid handle", function)))?;
+    f(state)
+}
+
+fn rows_arg(value: Option<&Value>, function: &str) -> Result<Vec<Value>> {
+    match value {
+        Some(Value::List(rows)) => Ok(rows.clone()),
+        _ => Err(Error::host_function(format!(
+            "{}: rows must be a list of maps",
+            function
+        ))),
+    }
+}
+
+fn count_arg(value: Option<&Value>, function: &str) -> Result<usize> {
+    match value {
+        None | Some(Value::Null) => Ok(1),
+        Some(value) => value
+            .as_int()
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(|| {
+                Error::host_function(format!(
+                    "{}: count must be a non-negative integer",
+                    function
+                ))
+            }),
+    }
+}
+
+/// Create a table state.
+///
+/// # Arguments
+///
+/// * `args[0]` - Rows (list of maps)
+/// * `args[1]` - Options map (optional):
+///   - `columns`: Columns to show, in order (default: every key, sorted)
+///   - `sort_by`: Column to sort by
+///   - `descending`: Sort in descending order (default false)
+///
+/// # Returns
+///
+/// Handle (integer)
+pub fn table_state(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.table_state";
+    let mut state = ValueTableState::new(rows_arg(args.first(), function)?);
+
+    if let Some(options) = args.get(1).and_then(|v| v.as_map()) {
+        if let Some(columns) = options.get("columns") {
+            let columns = columns
+                .as_list()
+                .and_then(|c| {
+                    c.iter()
+                        .map(|v| v.as_str().map(str::to_string))
+                        .collect::<Option<Vec<_>>>()
+                })
+                .ok_or_else(|| {
+                    Error::host_function(format!("{}: columns must be a list of strings", function))
+                })?;
+            state = state.with_columns(columns);
+        }
+        if let Some(column) = options.get("sort_by").and_then(|v| v.as_str()) {
+            let descending = options
+                .get("descending")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            state.sort_by(column, descending);
+        }
+    }
+
+    let handle = NEXT_HANDLE.fetch_add(1, AtomicOrdering::Relaxed);
+    tables().lock().insert(handle, state);
+    Ok(Value::Int(handle))
+}
+
+/// Replace the rows of a table, keeping its sort order and selection.
+///
+/// # Arguments
+///
+/// * `args[0]` - Table handle
+/// * `args[1]` - Rows (list of maps)
+pub fn table_set_rows(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.table_set_rows";
+    let rows = rows_arg(args.get(1), function)?;
+    with_table(args, function, |state| {
+        state.set_rows(rows);
+        Ok(Value::Null)
+    })
+}
+
+/// Move the selection down, selecting the first row if none is selected.
+///
+/// # Arguments
+///
+/// * `args[0]` - Table handle
+/// * `args[1]` - Number of rows (optional, default: 1)
+///
+/// # Returns
+///
+/// The selected row index, or null if the table is empty
+pub fn table_select_next(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.table_select_next";
+    let n = count_arg(args.get(1), function)?;
+    with_table(args, function, |state| {
+        state.select_next(n);
+        Ok(index_value(state.selected()))
+    })
+}
+
+/// Move the selection up, selecting the first row if none is selected.
+///
+/// # Arguments
+///
+/// * `args[0]` - Table handle
+/// * `args[1]` - Number of rows (optional, default: 1)
+///
+/// # Returns
+///
+/// The selected row index, or null if the table is empty
+pub fn table_select_prev(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.table_select_prev";
+    let n = count_arg(args.get(1), function)?;
+    with_table(args, function, |state| {
+        state.select_prev(n);
+        Ok(index_value(state.selected()))
+    })
+}
+
+/// Get the selected row.
+///
+/// # Arguments
+///
+/// * `args[0]` - Table handle
+///
+/// # Returns
+///
+/// The selected row map, or null if nothing is selected
+pub fn table_selected_row(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    with_table(args, "terminal_ui.table_selected_row", |state| {
+        Ok(state.selected_row().cloned().unwrap_or(Value::Null))
+    })
+}
+
+/// Sort a table by a column.
+///
+/// Numbers sort numerically and missing values last. The selection stays
+/// on the same row.
+///
+/// # Arguments
+///
+/// * `args[0]` - Table handle
+/// * `args[1]` - Column name
+/// * `args[2]` - Descending (optional, default: toggle if already sorted
+///   by this column ascending, otherwise false)
+pub fn table_sort(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.table_sort";
+    let column = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::host_function(format!("{}: missing column argument", function)))?;
+    let descending = args.get(2).and_then(|v| v.as_bool());
+    with_table(args, function, |state| {
+        let descending = descending
+            .unwrap_or_else(|| state.sort().is_some_and(|(c, desc)| c == column && !desc));
+        state.sort_by(column, descending);
+        Ok(Value::Null)
+    })
+}
+
+/// Build a `table` widget tree for `terminal_ui.run` from a table state.
+///
+/// # Arguments
+///
+/// * `args[0]` - Table handle
+/// * `args[1]` - Options map (optional):
+///   - `height`: Rows in view; only that window of rows is included,
+///     scrolled to keep the selection visible
+///   - `title`: Block title
+///
+/// # Returns
+///
+/// Widget map with `type: "table"`, `header`, `rows`, `widths`, and
+/// `selected`
+pub fn table_view(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let function = "terminal_ui.table_view";
+    let options = args.get(1).and_then(|v| v.as_map());
+    let option = |key: &str| options.and_then(|m| m.get(key));
+    let height = match option("height") {
+        None | Some(Value::Null) => None,
+        Some(value) => Some(
+            value
+                .as_int()
+                .and_then(|n| usize::try_from(n).ok())
+                .ok_or_else(|| {
+                    Error::host_function(format!(
+                        "{}: height must be a non-negative integer",
+                        function
+                    ))
+                })?,
+        ),
+    };
+
+    with_table(args, function, |state| {
+        let range = match height {
+            Some(height) => state.visible_range(height),
+            None => 0..state.rows.len(),
+        };
+        let start = range.start;
+        let visible = &state.rows[range];
+
+        let strings =
+            |items: Vec<String>| Value::List(items.into_iter().map(Value::String).collect());
+        let headers = header_text(&state.columns, state.sort.as_ref());
+        let rows = visible
+            .iter()
+            .map(|row| {
+                let fields = row.as_map();
+                strings(
+                    state
+                        .columns
+                        .iter()
+                        .map(|c| cell_text(fields.and_then(|m| m.get(c))))
+                        .collect(),
+                )
+            })
+            .collect();
+        let widths = fit_widths(&headers, &state.columns, visible, MAX_COLUMN_WIDTH)
+            .into_iter()
+            .map(|c| match c {
+                Constraint::Length(n) => Value::Int(i64::from(n)),
+                _ => Value::String("*".into()),
+            })
+            .collect();
+
+        let mut tree = HashMap::from([
+            ("type".to_string(), Value::String("table".into())),
+            ("header".to_string(), strings(headers)),
+            ("rows".to_string(), Value::List(rows)),
+            ("widths".to_string(), Value::List(widths)),
+            (
+                "selected".to_string(),
+                index_value(state.selected().and_then(|s| s.checked_sub(start))),
+            ),
+        ]);
+        if let Some(title) = option("title").filter(|v| !matches!(v, Value::Null)) {
+            tree.insert("title".to_string(), title.clone());
+        }
+        Ok(Value::Map(tree))
+    })
+}
+
+/// Release a table state.
+///
+/// # Arguments
+///
+/// * `args[0]` - Table handle
+pub fn table_close(args: &[Value], _ctx: &ExecutionContext) -> Result<Value> {
+    let handle = table_handle(args, "terminal_ui.table_close")?;
+    tables().lock().remove(&handle);
+    Ok(Value::Null)
+}
+
+fn index_value(index: Option<usize>) -> Value {
+    index.map_or(Value::Null, |i| Value::Int(i as i64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::{Capabilities, Limits, Sandbox, SandboxConfig};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn ctx() -> ExecutionContext {
+        ExecutionContext::new(
+            1,
+            Capabilities::none(),
+            Limits::default(),
+            Sandbox::new(SandboxConfig::default()).unwrap(),
+        )
+    }
+
+    fn process(name: &str, cpu: Value) -> Value {
+        Value::Map(HashMap::from([
+            ("name".to_string(), Value::String(name.into())),
+            ("cpu".to_string(), cpu),
+        ]))
+    }
+
+    fn processes() -> Vec<Value> {
+        vec![
+            process("web", Value::Int(42)),
+            process("db", Value::Float(7.5)),
+            process("cron", Value::Null),
+            process("api", Value::Int(100)),
+        ]
+    }
+
+    fn names(state: &ValueTableState) -> Vec<&str> {
+        state
+            .rows()
+            .iter()
+            .map(|row| row.as_map().unwrap()["name"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_infer_columns_and_widths() {
+        let rows = processes();
+        let columns = infer_columns(&rows);
+        assert_eq!(columns, vec!["cpu", "name"]);
+        assert_eq!(
+            column_widths(&columns, &rows, MAX_COLUMN_WIDTH),
+            vec![Constraint::Length(3), Constraint::Length(4)]
+        );
+    }
+
+    #[test]
+    fn test_sort_keeps_selected_row() {
+        let mut state = ValueTableState::new(processes());
+        state.select_next(1);
+        state.select_next(1);
+        assert_eq!(state.selected(), Some(1));
+
+        state.sort_by("cpu", true);
+        assert_eq!(names(&state), vec!["api", "web", "db", "cron"]);
+        assert_eq!(state.selected(), Some(2));
+        assert_eq!(
+            state.selected_row().unwrap().as_map().unwrap()["name"],
+            Value::String("db".into())
+        );
+
+        state.sort_by("cpu", false);
+        assert_eq!(names(&state), vec!["db", "web", "api", "cron"]);
+
+        state.select_prev(5);
+        assert_eq!(state.selected(), Some(0));
+        state.select_next(10);
+        assert_eq!(state.selected(), Some(3));
+    }
+
+    #[test]
+    fn test_value_table_renders_visible_rows() {
+        let mut state = ValueTableState::new(processes()).with_columns(vec!["name".into()]);
+        state.sort_by("name", false);
+        state.select(Some(3));
+
+        let mut terminal = Terminal::new(TestBackend::new(8, 3)).unwrap();
+        terminal
+            .draw(|frame| frame.render_stateful_widget(ValueTable::new(), frame.size(), &mut state))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let line = |y: u16| -> String {
+            (0..6)
+                .map(|x| buffer.get(x, y).symbol().to_string())
+                .collect()
+        };
+        assert_eq!(line(0), "name ▲");
+        assert_eq!(line(1), "db    ");
+        assert_eq!(line(2), "web   ");
+        assert!(buffer.get(0, 2).modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn test_table_host_functions() {
+        let ctx = ctx();
+        let options = Value::Map(HashMap::from([(
+            "sort_by".to_string(),
+            Value::String("name".into()),
+        )]));
+        let handle = table_state(&[Value::List(processes()), options], &ctx).unwrap();
+
+        assert_eq!(
+            table_selected_row(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Null
+        );
+        assert_eq!(
+            table_select_next(&[handle.clone(), Value::Int(2)], &ctx).unwrap(),
+            Value::Int(0)
+        );
+        assert_eq!(
+            table_select_next(&[handle.clone(), Value::Int(2)], &ctx).unwrap(),
+            Value::Int(2)
+        );
+        assert_eq!(
+            table_selected_row(std::slice::from_ref(&handle), &ctx).unwrap(),
+            process("db", Value::Float(7.5))
+        );
+
+        // Sorting the same column again flips the order.
+        table_sort(&[handle.clone(), Value::String("name".into())], &ctx).unwrap();
+        assert_eq!(
+            table_selected_row(std::slice::from_ref(&handle), &ctx).unwrap(),
+            process("db", Value::Float(7.5))
+        );
+
+        let options = Value::Map(HashMap::from([("height".to_string(), Value::Int(2))]));
+        let view = table_view(&[handle.clone(), options], &ctx).unwrap();
+        let fields = view.as_map().unwrap();
+        assert_eq!(
+            fields["header"],
+            Value::List(vec![
+                Value::String("cpu".into()),
+                Value::String("name ▼".into())
+            ])
+        );
+        assert_eq!(fields["rows"].as_list().unwrap().len(), 2);
+        assert_eq!(fields["selected"], Value::Int(1));
+        super::super::parse_widget(&view).unwrap();
+
+        table_close(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(table_selected_row(&[handle], &ctx).is_err());
+    }
+}
@@ -0,0 +1,1087 @@
+//! Declarative widget trees built by scripts.
+//!
+//! A tree is a plain string (rendered as a paragraph) or a map tagged by
+//! `type`. Every widget except `layout` accepts an optional `title`, which
+//! draws a bordered block around it, and most accept a `style` map
+//! (`fg`, `bg`, `bold`, `dim`, `italic`, `underline`, `reversed`).
+//!
+//! | type | fields |
+//! |------|--------|
+//! | `paragraph` | `text` (string or list of lines), `wrap` (bool), `align` (`"left"`, `"center"`, `"right"`) |
+//! | `block` | `child` (widget) |
+//! | `list` | `items`, `selected` (index) |
+//! | `table` | `rows` (list of lists), `header` (list), `widths` (constraints), `selected` (index) |
+//! | `gauge` | `ratio` (0.0 to 1.0) or `percent` (0 to 100), `label` |
+//! | `chart` | `datasets` (list of `{name, data: [[x, y], ...], style}`), `x_bounds`, `y_bounds` (`[min, max]`) |
+//! | `sparkline` | `data` (non-negative numbers, newest last), `max` |
+//! | `tabs` | `titles`, `selected` (index) |
+//! | `layout` | `direction` (`"vertical"` or `"horizontal"`), `children`, `constraints` |
+//!
+//! `row` and `column` are shorthands for horizontal and vertical layouts.
+//! Constraints are an integer (cells), `"N%"`, `"A:B"` (ratio), `"min:N"`,
+//! `"max:N"`, or `"*"` (fill); children without one share the space left.
+//!
+//! Colors are names (`"red"`, `"light_blue"`, ...), `"#rrggbb"`, or a 256
+//! color index.
+
+use std::collections::HashMap;
+
+use ratatui::{
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    symbols::Marker,
+    text::{Line, Span},
+    widgets::{
+        Axis, Block, Borders, Chart, Dataset, Gauge, GraphType, List, ListItem, ListState,
+        Paragraph, Row, Sparkline, Table, TableState, Tabs, Wrap,
+    },
+    Frame,
+};
+
+use crate::error::{Error, Result};
+use fusabi_host::Value;
+
+use super::{titled_block, value_to_span};
+
+/// A validated widget tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Widget {
+    /// Text, optionally wrapped and aligned.
+    Paragraph {
+        /// Lines of text.
+        lines: Vec<String>,
+        /// Wrap long lines.
+        wrap: bool,
+        /// Horizontal alignment.
+        align: Alignment,
+        /// Common options.
+        common: Common,
+    },
+    /// Bordered block around an optional child.
+    Block {
+        /// Widget inside the block.
+        child: Option<Box<Widget>>,
+        /// Common options.
+        common: Common,
+    },
+    /// Selectable list.
+    List {
+        /// Items.
+        items: Vec<Span<'static>>,
+        /// Selected item.
+        selected: Option<usize>,
+        /// Common options.
+        common: Common,
+    },
+    /// Table with an optional header row.
+    Table {
+        /// Header cells.
+        header: Option<Vec<String>>,
+        /// Rows of cells.
+        rows: Vec<Vec<String>>,
+        /// Column widths.
+        widths: Vec<Constraint>,
+        /// Selected row.
+        selected: Option<usize>,
+        /// Common options.
+        common: Common,
+    },
+    /// Progress gauge.
+    Gauge {
+        /// Filled fraction, 0.0 to 1.0.
+        ratio: f64,
+        /// Label drawn over the gauge.
+        label: Option<String>,
+        /// Common options.
+        common: Common,
+    },
+    /// Line chart.
+    Chart {
+        /// Series to plot.
+        datasets: Vec<Series>,
+        /// X axis bounds.
+        x_bounds: [f64; 2],
+        /// Y axis bounds.
+        y_bounds: [f64; 2],
+        /// Common options.
+        common: Common,
+    },
+    /// Bar sparkline showing the newest values that fit.
+    Sparkline {
+        /// Values, oldest first.
+        data: Vec<f64>,
+        /// Value drawn as a full bar (default: the largest shown).
+        max: Option<f64>,
+        /// Common options.
+        common: Common,
+    },
+    /// Tab bar.
+    Tabs {
+        /// Tab titles.
+        titles: Vec<String>,
+        /// Selected tab.
+        selected: usize,
+        /// Common options.
+        common: Common,
+    },
+    /// Children split along a direction.
+    Layout {
+        /// Split direction.
+        direction: Direction,
+        /// Size of each child.
+        constraints: Vec<Constraint>,
+        /// Child widgets.
+        children: Vec<Widget>,
+    },
+}
+
+/// Options shared by most widgets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Common {
+    /// Title of the surrounding block; no block is drawn without one.
+    pub title: Option<String>,
+    /// Widget style.
+    pub style: Style,
+}
+
+/// One series of a chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    /// Legend name.
+    pub name: Option<String>,
+    /// Points.
+    pub points: Vec<(f64, f64)>,
+    /// Line style.
+    pub style: Style,
+}
+
+/// Render a widget tree into the whole frame.
+///
+/// Returns an error naming the offending node if the tree is malformed;
+/// nothing is drawn in that case.
+pub fn render_value(frame: &mut Frame<'_>, tree: &Value) -> Result<()> {
+    let widget = parse_widget(tree)?;
+    widget.render(frame, frame.size());
+    Ok(())
+}
+
+/// Validate a widget tree.
+///
+/// Errors name the offending node by path, such as
+/// `root.children[1].rows[2]`.
+pub fn parse_widget(tree: &Value) -> Result<Widget> {
+    parse_node(tree, "root")
+}
+
+impl Widget {
+    /// Draw the widget into `area` of a frame.
+    pub fn render(&self, frame: &mut Frame<'_>, area: Rect) {
+        match self {
+            Widget::Paragraph {
+                lines,
+                wrap,
+                align,
+                common,
+            } => {
+                let lines: Vec<Line<'_>> = lines.iter().map(|l| Line::from(l.as_str())).collect();
+                let mut paragraph = Paragraph::new(lines).alignment(*align).style(common.style);
+                if *wrap {
+                    paragraph = paragraph.wrap(Wrap { trim: false });
+                }
+                if let Some(block) = common.block() {
+                    paragraph = paragraph.block(block);
+                }
+                frame.render_widget(paragraph, area);
+            }
+            Widget::Block { child, common } => {
+                let block = common
+                    .block()
+                    .unwrap_or_else(|| Block::default().borders(Borders::ALL))
+                    .style(common.style);
+                let inner = block.inner(area);
+                frame.render_widget(block, area);
+                if let Some(child) = child {
+                    child.render(frame, inner);
+                }
+            }
+            Widget::List {
+                items,
+                selected,
+                common,
+            } => {
+                let items: Vec<ListItem<'_>> = items.iter().cloned().map(ListItem::new).collect();
+                let mut list = List::new(items)
+                    .style(common.style)
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                if let Some(block) = common.block() {
+                    list = list.block(block);
+                }
+                let mut state = ListState::default().with_selected(*selected);
+                frame.render_stateful_widget(list, area, &mut state);
+            }
+            Widget::Table {
+                header,
+                rows,
+                widths,
+                selected,
+                common,
+            } => {
+                let rows: Vec<Row<'_>> = rows
+                    .iter()
+                    .map(|cells| Row::new(cells.iter().map(String::as_str)))
+                    .collect();
+                let mut table = Table::new(rows, widths.clone())
+                    .style(common.style)
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                if let Some(header) = header {
+                    table = table.header(
+                        Row::new(header.iter().map(String::as_str))
+                            .style(Style::default().add_modifier(Modifier::BOLD)),
+                    );
+                }
+                if let Some(block) = common.block() {
+                    table = table.block(block);
+                }
+                let mut state = TableState::default().with_selected(*selected);
+                frame.render_stateful_widget(table, area, &mut state);
+            }
+            Widget::Gauge {
+                ratio,
+                label,
+                common,
+            } => {
+                let mut gauge = Gauge::default().ratio(*ratio).gauge_style(common.style);
+                if let Some(label) = label {
+                    gauge = gauge.label(label.as_str());
+                }
+                if let Some(block) = common.block() {
+                    gauge = gauge.block(block);
+                }
+                frame.render_widget(gauge, area);
+            }
+            Widget::Chart {
+                datasets,
+                x_bounds,
+                y_bounds,
+                common,
+            } => {
+                let datasets: Vec<Dataset<'_>> = datasets
+                    .iter()
+                    .map(|series| {
+                        let mut dataset = Dataset::default()
+                            .data(&series.points)
+                            .marker(Marker::Braille)
+                            .graph_type(GraphType::Line)
+                            .style(series.style);
+                        if let Some(name) = &series.name {
+                            dataset = dataset.name(name.as_str());
+                        }
+                        dataset
+                    })
+                    .collect();
+                let axis_labels = |[min, max]: [f64; 2]| {
+                    vec![Span::raw(format_bound(min)), Span::raw(format_bound(max))]
+                };
+                let mut chart = Chart::new(datasets)
+                    .style(common.style)
+                    .x_axis(
+                        Axis::default()
+                            .bounds(*x_bounds)
+                            .labels(axis_labels(*x_bounds)),
+                    )
+                    .y_axis(
+                        Axis::default()
+                            .bounds(*y_bounds)
+                            .labels(axis_labels(*y_bounds)),
+                    );
+                if let Some(block) = common.block() {
+                    chart = chart.block(block);
+                }
+                frame.render_widget(chart, area);
+            }
+            Widget::Sparkline { data, max, common } => {
+                let block = common.block();
+                let width = block.as_ref().map_or(area, |b| b.inner(area)).width as usize;
+                let shown = &data[data.len().saturating_sub(width)..];
+                let top = max.unwrap_or_else(|| shown.iter().copied().fold(0.0, f64::max));
+                // Sparkline bars are integers; scale so `top` is
+                // SPARKLINE_SCALE and clamp anything above it.
+                let bars: Vec<u64> = shown
+                    .iter()
+                    .map(|v| {
+                        if top > 0.0 {
+                            ((v / top).min(1.0) * SPARKLINE_SCALE as f64).round() as u64
+                        } else {
+                            0
+                        }
+                    })
+                    .collect();
+                let mut sparkline = Sparkline::default()
+                    .data(&bars)
+                    .max(SPARKLINE_SCALE)
+                    .style(common.style);
+                if let Some(block) = block {
+                    sparkline = sparkline.block(block);
+                }
+                frame.render_widget(sparkline, area);
+            }
+            Widget::Tabs {
+                titles,
+                selected,
+                common,
+            } => {
+                let mut tabs = Tabs::new(titles.iter().map(String::as_str))
+                    .select(*selected)
+                    .style(common.style)
+                    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+                if let Some(block) = common.block() {
+                    tabs = tabs.block(block);
+                }
+                frame.render_widget(tabs, area);
+            }
+            Widget::Layout {
+                direction,
+                constraints,
+                children,
+            } => {
+                let areas = Layout::default()
+                    .direction(*direction)
+                    .constraints(constraints.clone())
+                    .split(area);
+                for (child, child_area) in children.iter().zip(areas.iter()) {
+                    child.render(frame, *child_area);
+                }
+            }
+        }
+    }
+}
+
+impl Common {
+    fn block(&self) -> Option<Block<'_>> {
+        self.title.as_deref().map(titled_block)
+    }
+}
+
+const SPARKLINE_SCALE: u64 = 1000;
+
+fn format_bound(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Fields of a widget map, with the path used in error messages.
+struct Node<'a> {
+    fields: &'a HashMap<String, Value>,
+    path: &'a str,
+}
+
+impl<'a> Node<'a> {
+    fn error(&self, key: &str, message: impl std::fmt::Display) -> Error {
+        Error::InvalidValue(format!("{}.{}: {}", self.path, key, message))
+    }
+
+    fn expected(&self, key: &str, what: &str, got: &Value) -> Error {
+        self.error(key, format!("expected {}, got {}", what, got.value_type()))
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.fields.get(key).filter(|v| !matches!(v, Value::Null))
+    }
+
+    fn string(&self, key: &str) -> Result<Option<String>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(s)) => Ok(Some(s.clone())),
+            Some(other) => Err(self.expected(key, "a string", other)),
+        }
+    }
+
+    fn bool(&self, key: &str) -> Result<bool> {
+        match self.get(key) {
+            None => Ok(false),
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(other) => Err(self.expected(key, "a boolean", other)),
+        }
+    }
+
+    fn index(&self, key: &str) -> Result<Option<usize>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Int(i)) => usize::try_from(*i)
+                .map(Some)
+                .map_err(|_| self.error(key, "must not be negative")),
+            Some(other) => Err(self.expected(key, "an integer", other)),
+        }
+    }
+
+    fn number(&self, key: &str) -> Result<Option<f64>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => value
+                .as_float()
+                .map(Some)
+                .ok_or_else(|| self.expected(key, "a number", value)),
+        }
+    }
+
+    fn list(&self, key: &str) -> Result<Option<&'a [Value]>> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::List(items)) => Ok(Some(items)),
+            Some(other) => Err(self.expected(key, "a list", other)),
+        }
+    }
+
+    fn required_list(&self, key: &str) -> Result<&'a [Value]> {
+        self.list(key)?
+            .ok_or_else(|| self.error(key, "is required"))
+    }
+
+    fn strings(&self, key: &str) -> Result<Option<Vec<String>>> {
+        self.list(key)
+            .map(|items| items.map(|items| items.iter().map(cell_text).collect()))
+    }
+
+    fn bounds(&self, key: &str) -> Result<Option<[f64; 2]>> {
+        let Some(items) = self.list(key)? else {
+            return Ok(None);
+        };
+        match items {
+            [min, max] => match (min.as_float(), max.as_float()) {
+                (Some(min), Some(max)) if min < max => Ok(Some([min, max])),
+                (Some(_), Some(_)) => Err(self.error(key, "min must be less than max")),
+                _ => Err(self.error(key, "expected two numbers")),
+            },
+            _ => Err(self.error(key, "expected [min, max]")),
+        }
+    }
+
+    fn common(&self) -> Result<Common> {
+        Ok(Common {
+            title: self.string("title")?,
+            style: match self.get("style") {
+                None => Style::default(),
+                Some(style) => parse_style(style, &format!("{}.style", self.path))?,
+            },
+        })
+    }
+}
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_node(value: &Value, path: &str) -> Result<Widget> {
+    let fields = match value {
+        Value::String(text) => {
+            return Ok(Widget::Paragraph {
+                lines: text.lines().map(str::to_string).collect(),
+                wrap: false,
+                align: Alignment::Left,
+                common: Common::default(),
+            })
+        }
+        Value::Map(fields) => fields,
+        other => {
+            return Err(Error::InvalidValue(format!(
+                "{}: expected a widget map or string, got {}",
+                path,
+                other.value_type()
+            )))
+        }
+    };
+    let node = Node { fields, path };
+
+    let kind = match node.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        Some(other) => return Err(node.expected("type", "a string", other)),
+        None => return Err(node.error("type", "is required")),
+    };
+
+    match kind {
+        "paragraph" | "text" => {
+            let lines = match node.get("text") {
+                None => Vec::new(),
+                Some(Value::String(text)) => text.lines().map(str::to_string).collect(),
+                Some(Value::List(lines)) => lines.iter().map(cell_text).collect(),
+                Some(other) => return Err(node.expected("text", "a string or list", other)),
+            };
+            let align = match node.string("align")?.as_deref() {
+                None | Some("left") => Alignment::Left,
+                Some("center") => Alignment::Center,
+                Some("right") => Alignment::Right,
+                Some(other) => {
+                    return Err(node.error(
+                        "align",
+                        format!(
+                            "expected \"left\", \"center\" or \"right\", got \"{}\"",
+                            other
+                        ),
+                    ))
+                }
+            };
+            Ok(Widget::Paragraph {
+                lines,
+                wrap: node.bool("wrap")?,
+                align,
+                common: node.common()?,
+            })
+        }
+        "block" => {
+            let child = match node.get("child") {
+                None => None,
+                Some(child) => Some(Box::new(parse_node(child, &format!("{}.child", path))?)),
+            };
+            Ok(Widget::Block {
+                child,
+                common: node.common()?,
+            })
+        }
+        "list" => {
+            let items = node
+                .list("items")?
+                .unwrap_or_default()
+                .iter()
+                .map(|item| match item {
+                    Value::String(s) => Span::raw(s.clone()),
+                    other => value_to_span(other),
+                })
+                .collect();
+            Ok(Widget::List {
+                items,
+                selected: node.index("selected")?,
+                common: node.common()?,
+            })
+        }
+        "table" => {
+            let header = node.strings("header")?;
+            let mut rows = Vec::new();
+            for (i, row) in node.list("rows")?.unwrap_or_default().iter().enumerate() {
+                match row {
+                    Value::List(cells) => {
+                        rows.push(cells.iter().map(cell_text).collect::<Vec<_>>())
+                    }
+                    other => return Err(node.expected(&format!("rows[{}]", i), "a list", other)),
+                }
+            }
+            let columns = header
+                .as_ref()
+                .map(Vec::len)
+                .into_iter()
+                .chain(rows.iter().map(Vec::len))
+                .max()
+                .unwrap_or(0);
+            let widths = match node.list("widths")? {
+                Some(widths) => {
+                    if widths.len() != columns {
+                        return Err(node.error(
+                            "widths",
+                            format!("expected {} constraints, got {}", columns, widths.len()),
+                        ));
+                    }
+                    parse_constraints(&node, "widths", widths)?
+                }
+                None => equal_shares(columns),
+            };
+            Ok(Widget::Table {
+                header,
+                rows,
+                widths,
+                selected: node.index("selected")?,
+                common: node.common()?,
+            })
+        }
+        "gauge" => {
+            let ratio = match (node.number("ratio")?, node.number("percent")?) {
+                (Some(ratio), None) => ratio,
+                (None, Some(percent)) => percent / 100.0,
+                (None, None) => return Err(node.error("ratio", "ratio or percent is required")),
+                (Some(_), Some(_)) => {
+                    return Err(node.error("ratio", "give either ratio or percent, not both"))
+                }
+            };
+            if !(0.0..=1.0).contains(&ratio) {
+                return Err(node.error("ratio", format!("{} is outside 0.0 to 1.0", ratio)));
+            }
+            Ok(Widget::Gauge {
+                ratio,
+                label: node.string("label")?,
+                common: node.common()?,
+            })
+        }
+        "chart" => {
+            let mut datasets = Vec::new();
+            for (i, dataset) in node.required_list("datasets")?.iter().enumerate() {
+                datasets.push(parse_series(dataset, &format!("{}.datasets[{}]", path, i))?);
+            }
+            let points = || datasets.iter().flat_map(|s| s.points.iter());
+            let x_bounds = match node.bounds("x_bounds")? {
+                Some(bounds) => bounds,
+                None => data_bounds(points().map(|p| p.0)),
+            };
+            let y_bounds = match node.bounds("y_bounds")? {
+                Some(bounds) => bounds,
+                None => data_bounds(points().map(|p| p.1)),
+            };
+            Ok(Widget::Chart {
+                datasets,
+                x_bounds,
+                y_bounds,
+                common: node.common()?,
+            })
+        }
+        "sparkline" => {
+            let mut data = Vec::new();
+            for (i, value) in node.required_list("data")?.iter().enumerate() {
+                let key = format!("data[{}]", i);
+                match value.as_float() {
+                    Some(v) if v.is_finite() && v >= 0.0 => data.push(v),
+                    Some(v) => {
+                        return Err(node.error(&key, format!("{} is not a non-negative number", v)))
+                    }
+                    None => return Err(node.expected(&key, "a number", value)),
+                }
+            }
+            let max = node.number("max")?;
+            if let Some(max) = max.filter(|m| !(m.is_finite() && *m > 0.0)) {
+                return Err(node.error("max", format!("{} is not a positive number", max)));
+            }
+            Ok(Widget::Sparkline {
+                data,
+                max,
+                common: node.common()?,
+            })
+        }
+        "tabs" => {
+            let titles = node
+                .strings("titles")?
+                .ok_or_else(|| node.error("titles", "is required"))?;
+            let selected = node.index("selected")?.unwrap_or(0);
+            if selected >= titles.len().max(1) {
+                return Err(node.error(
+                    "selected",
+                    format!("{} is out of range for {} tabs", selected, titles.len()),
+                ));
+            }
+            Ok(Widget::Tabs {
+                titles,
+                selected,
+                common: node.common()?,
+            })
+        }
+        "layout" | "row" | "column" => {
+            let direction = match kind {
+                "row" => Direction::Horizontal,
+                "column" => Direction::Vertical,
+                _ => match node.string("direction")?.as_deref() {
+                    None | Some("vertical") => Direction::Vertical,
+                    Some("horizontal") => Direction::Horizontal,
+                    Some(other) => {
+                        return Err(node.error(
+                            "direction",
+                            format!("expected \"vertical\" or \"horizontal\", got \"{}\"", other),
+                        ))
+                    }
+                },
+            };
+            let mut children = Vec::new();
+            for (i, child) in node.required_list("children")?.iter().enumerate() {
+                children.push(parse_node(child, &format!("{}.children[{}]", path, i))?);
+            }
+            let constraints = match node.list("constraints")? {
+                Some(constraints) => {
+                    if constraints.len() != children.len() {
+                        return Err(node.error(
+                            "constraints",
+                            format!(
+                                "expected {} constraints, got {}",
+                                children.len(),
+                                constraints.len()
+                            ),
+                        ));
+                    }
+                    parse_constraints(&node, "constraints", constraints)?
+                }
+                None => equal_shares(children.len()),
+            };
+            Ok(Widget::Layout {
+                direction,
+                constraints,
+                children,
+            })
+        }
+        other => Err(node.error("type", format!("unknown widget type \"{}\"", other))),
+    }
+}
+
+fn parse_series(value: &Value, path: &str) -> Result<Series> {
+    let fields = value.as_map().ok_or_else(|| {
+        Error::InvalidValue(format!(
+            "{}: expected a map, got {}",
+            path,
+            value.value_type()
+        ))
+    })?;
+    let node = Node { fields, path };
+
+    let mut points = Vec::new();
+    for (i, point) in node.required_list("data")?.iter().enumerate() {
+        let point = match point.as_list() {
+            Some([x, y]) => x.as_float().zip(y.as_float()),
+            _ => None,
+        };
+        points.push(point.ok_or_else(|| {
+            node.error(&format!("data[{}]", i), "expected a pair of numbers [x, y]")
+        })?);
+    }
+
+    Ok(Series {
+        name: node.string("name")?,
+        points,
+        style: match node.get("style") {
+            None => Style::default(),
+            Some(style) => parse_style(style, &format!("{}.style", path))?,
+        },
+    })
+}
+
+/// Axis bounds covering `values`, widened when empty or constant.
+pub(crate) fn data_bounds(values: impl Iterator<Item = f64>) -> [f64; 2] {
+    let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+        (lo.min(v), hi.max(v))
+    });
+    if !min.is_finite() {
+        [0.0, 1.0]
+    } else if min == max {
+        [min - 1.0, max + 1.0]
+    } else {
+        [min, max]
+    }
+}
+
+fn equal_shares(count: usize) -> Vec<Constraint> {
+    let share = u32::try_from(count).unwrap_or(u32::MAX);
+    vec![Constraint::Ratio(1, share); count]
+}
+
+fn parse_constraints(node: &Node<'_>, key: &str, values: &[Value]) -> Result<Vec<Constraint>> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            parse_constraint(value)
+                .ok_or_else(|| node.error(&format!("{}[{}]", key, i), invalid_constraint(value)))
+        })
+        .collect()
+}
+
+fn invalid_constraint(value: &Value) -> String {
+    format!(
+        "invalid constraint {}; expected cells, \"N%\", \"A:B\", \"min:N\", \"max:N\" or \"*\"",
+        value
+    )
+}
+
+/// Parse a layout constraint: cells, `"N%"`, `"A:B"`, `"min:N"`, `"max:N"`,
+/// or `"*"`.
+pub fn parse_constraint(value: &Value) -> Option<Constraint> {
+    let text = match value {
+        Value::Int(n) => return u16::try_from(*n).ok().map(Constraint::Length),
+        Value::String(s) => s.trim(),
+        _ => return None,
+    };
+
+    if text == "*" {
+        return Some(Constraint::Fill(1));
+    }
+    if let Some(percent) = text.strip_suffix('%') {
+        return percent
+            .trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|p| *p <= 100)
+            .map(Constraint::Percentage);
+    }
+    if let Some(n) = text.strip_prefix("min:") {
+        return n.trim().parse().ok().map(Constraint::Min);
+    }
+    if let Some(n) = text.strip_prefix("max:") {
+        return n.trim().parse().ok().map(Constraint::Max);
+    }
+    if let Some((a, b)) = text.split_once(':') {
+        let (a, b) = (a.trim().parse::<u32>().ok()?, b.trim().parse::<u32>().ok()?);
+        return (b > 0).then_some(Constraint::Ratio(a, b));
+    }
+    text.parse().ok().map(Constraint::Length)
+}
+
+fn parse_style(value: &Value, path: &str) -> Result<Style> {
+    let fields = value.as_map().ok_or_else(|| {
+        Error::InvalidValue(format!(
+            "{}: expected a map, got {}",
+            path,
+            value.value_type()
+        ))
+    })?;
+    let node = Node { fields, path };
+
+    let mut style = Style::default();
+    for (key, slot) in [("fg", 0), ("bg", 1)] {
+        if let Some(color) = node.get(key) {
+            let color = parse_color(color)
+                .ok_or_else(|| node.error(key, format!("unknown color {}", color)))?;
+            style = if slot == 0 {
+                style.fg(color)
+            } else {
+                style.bg(color)
+            };
+        }
+    }
+    for (key, modifier) in [
+        ("bold", Modifier::BOLD),
+        ("dim", Modifier::DIM),
+        ("italic", Modifier::ITALIC),
+        ("underline", Modifier::UNDERLINED),
+        ("reversed", Modifier::REVERSED),
+    ] {
+        if node.bool(key)? {
+            style = style.add_modifier(modifier);
+        }
+    }
+    Ok(style)
+}
+
+/// Parse a color name, `"#rrggbb"`, or 256-color index.
+pub fn parse_color(value: &Value) -> Option<Color> {
+    let name = match value {
+        Value::Int(n) => return u8::try_from(*n).ok().map(Color::Indexed),
+        Value::String(s) => s.trim().to_ascii_lowercase().replace(['-', ' '], "_"),
+        _ => return None,
+    };
+
+    if let Some(hex) = name.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        return Some(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8));
+    }
+
+    Some(match name.as_str() {
+        "reset" | "default" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark_gray" | "dark_grey" => Color::DarkGray,
+        "light_red" => Color::LightRed,
+        "light_green" => Color::LightGreen,
+        "light_yellow" => Color::LightYellow,
+        "light_blue" => Color::LightBlue,
+        "light_magenta" => Color::LightMagenta,
+        "light_cyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn map(entries: &[(&str, Value)]) -> Value {
+        Value::Map(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        )
+    }
+
+    fn s(text: &str) -> Value {
+        Value::String(text.into())
+    }
+
+    fn row_text(terminal: &Terminal<TestBackend>, y: u16) -> String {
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.width)
+            .map(|x| buffer.get(x, y).symbol().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_render_dashboard() {
+        let tree = map(&[
+            ("type", s("layout")),
+            (
+                "constraints",
+                Value::List(vec![Value::Int(1), Value::Int(3), s("*")]),
+            ),
+            (
+                "children",
+                Value::List(vec![
+                    map(&[
+                        ("type", s("tabs")),
+                        ("titles", Value::List(vec![s("Pods"), s("Nodes")])),
+                        ("selected", Value::Int(1)),
+                    ]),
+                    map(&[
+                        ("type", s("gauge")),
+                        ("title", s("CPU")),
+                        ("percent", Value::Int(50)),
+                        ("label", s("half")),
+                        ("style", map(&[("fg", s("green"))])),
+                    ]),
+                    map(&[
+                        ("type", s("table")),
+                        ("header", Value::List(vec![s("name"), s("cpu")])),
+                        (
+                            "rows",
+                            Value::List(vec![Value::List(vec![s("web"), Value::Int(42)])]),
+                        ),
+                        ("widths", Value::List(vec![Value::Int(6), s("*")])),
+                    ]),
+                ]),
+            ),
+        ]);
+
+        let mut terminal = Terminal::new(TestBackend::new(20, 7)).unwrap();
+        terminal
+            .draw(|frame| render_value(frame, &tree).unwrap())
+            .unwrap();
+
+        assert!(row_text(&terminal, 0).contains("Pods"));
+        assert!(row_text(&terminal, 1).contains("CPU"));
+        assert!(row_text(&terminal, 2).contains("half"));
+        assert!(row_text(&terminal, 4).starts_with("name   cpu"));
+        assert!(row_text(&terminal, 5).starts_with("web    42"));
+    }
+
+    #[test]
+    fn test_chart_bounds_and_paragraph() {
+        let chart = map(&[
+            ("type", s("chart")),
+            (
+                "datasets",
+                Value::List(vec![map(&[
+                    ("name", s("load")),
+                    (
+                        "data",
+                        Value::List(vec![
+                            Value::List(vec![Value::Int(0), Value::Float(1.5)]),
+                            Value::List(vec![Value::Int(10), Value::Float(3.0)]),
+                        ]),
+                    ),
+                ])]),
+            ),
+        ]);
+        match parse_widget(&chart).unwrap() {
+            Widget::Chart {
+                x_bounds, y_bounds, ..
+            } => {
+                assert_eq!(x_bounds, [0.0, 10.0]);
+                assert_eq!(y_bounds, [1.5, 3.0]);
+            }
+            other => panic!("unexpected widget {:?}", other),
+        }
+
+        let paragraph = map(&[
+            ("type", s("paragraph")),
+            ("text", s("one\ntwo")),
+            ("align", s("right")),
+        ]);
+        let mut terminal = Terminal::new(TestBackend::new(6, 2)).unwrap();
+        terminal
+            .draw(|frame| render_value(frame, &paragraph).unwrap())
+            .unwrap();
+        assert_eq!(row_text(&terminal, 1), "   two");
+    }
+
+    #[test]
+    fn test_malformed_trees_name_the_node() {
+        let cases = [
+            (
+                Value::Int(3),
+                "root: expected a widget map or string, got int",
+            ),
+            (map(&[("text", s("x"))]), "root.type: is required"),
+            (
+                map(&[("type", s("sparkles"))]),
+                "unknown widget type \"sparkles\"",
+            ),
+            (
+                map(&[
+                    ("type", s("column")),
+                    (
+                        "children",
+                        Value::List(vec![
+                            s("ok"),
+                            map(&[("type", s("gauge")), ("ratio", Value::Float(1.5))]),
+                        ]),
+                    ),
+                ]),
+                "root.children[1].ratio: 1.5 is outside 0.0 to 1.0",
+            ),
+            (
+                map(&[
+                    ("type", s("row")),
+                    ("children", Value::List(vec![s("a")])),
+                    ("constraints", Value::List(vec![s("lots")])),
+                ]),
+                "root.constraints[0]: invalid constraint",
+            ),
+            (
+                map(&[
+                    ("type", s("table")),
+                    ("rows", Value::List(vec![s("not a row")])),
+                ]),
+                "root.rows[0]: expected a list, got string",
+            ),
+            (
+                map(&[("type", s("list")), ("style", map(&[("fg", s("plaid"))]))]),
+                "root.style.fg: unknown color",
+            ),
+        ];
+
+        for (tree, expected) in cases {
+            let err = parse_widget(&tree).unwrap_err().to_string();
+            assert!(
+                err.contains(expected),
+                "{} does not contain {}",
+                err,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_constraint() {
+        assert_eq!(
+            parse_constraint(&Value::Int(4)),
+            Some(Constraint::Length(4))
+        );
+        assert_eq!(
+            parse_constraint(&s("30%")),
+            Some(Constraint::Percentage(30))
+        );
+        assert_eq!(parse_constraint(&s("1:3")), Some(Constraint::Ratio(1, 3)));
+        assert_eq!(parse_constraint(&s("min:2")), Some(Constraint::Min(2)));
+        assert_eq!(parse_constraint(&s("max:9")), Some(Constraint::Max(9)));
+        assert_eq!(parse_constraint(&s("*")), Some(Constraint::Fill(1)));
+        assert_eq!(parse_constraint(&s("150%")), None);
+        assert_eq!(parse_constraint(&s("1:0")), None);
+    }
+}
@@ -2,7 +2,12 @@
 //!
 //! Provides time and duration utilities.
 
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use parking_lot::Mutex;
 
 use fusabi_host::ExecutionContext;
 use fusabi_host::Value;
@@ -43,6 +48,93 @@ pub fn sleep(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Val
     Ok(Value::Null)
 }
 
+/// Interval at which budgeted sleeps re-check for cancellation.
+const SLEEP_SLICE: Duration = Duration::from_millis(50);
+
+/// Sleep for a duration in milliseconds within the context's time budget.
+///
+/// `budget` is the total execution time allowed for the context (normally
+/// the time module's configured timeout). If less time remains than was
+/// requested, this fails immediately with `Error::Timeout` instead of
+/// sleeping past the deadline. The sleep wakes periodically and returns
+/// `Error::Cancelled` if the context is cancelled.
+///
+/// # Arguments
+///
+/// * `args[0]` - Milliseconds to sleep
+pub fn sleep_budgeted(
+    budget: Option<Duration>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let millis = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.sleep: missing milliseconds argument")
+    })?;
+
+    let deadline = sleep_deadline(budget, millis, ctx)?;
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(Value::Null);
+        }
+        std::thread::sleep((deadline - now).min(SLEEP_SLICE));
+        if ctx.is_cancelled() {
+            return Err(fusabi_host::Error::Cancelled);
+        }
+    }
+}
+
+/// Async variant of [`sleep_budgeted`] for engines running on tokio.
+///
+/// Yields to the runtime instead of blocking an executor thread.
+#[cfg(feature = "time-async")]
+pub async fn sleep_async(
+    budget: Option<Duration>,
+    millis: i64,
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<()> {
+    let deadline = sleep_deadline(budget, millis, ctx)?;
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(());
+        }
+        tokio::time::sleep((deadline - now).min(SLEEP_SLICE)).await;
+        if ctx.is_cancelled() {
+            return Err(fusabi_host::Error::Cancelled);
+        }
+    }
+}
+
+/// Validate a sleep request against the context and compute its deadline.
+fn sleep_deadline(
+    budget: Option<Duration>,
+    millis: i64,
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Instant> {
+    if millis < 0 {
+        return Err(fusabi_host::Error::host_function(
+            "time.sleep: milliseconds must be non-negative",
+        ));
+    }
+
+    if ctx.is_cancelled() {
+        return Err(fusabi_host::Error::Cancelled);
+    }
+    ctx.check_timeout()?;
+
+    let requested = Duration::from_millis(millis as u64);
+    if let Some(budget) = budget {
+        if requested > budget.saturating_sub(ctx.elapsed()) {
+            return Err(fusabi_host::Error::Timeout(budget));
+        }
+    }
+
+    Ok(Instant::now() + requested)
+}
+
 /// Format a Unix timestamp.
 pub fn format_time(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
     let timestamp = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
@@ -78,6 +170,1489 @@ pub fn parse_time(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Resul
     )))
 }
 
+/// Add an amount of calendar units to a Unix timestamp.
+///
+/// Months and years are calendar-aware: the day of month is clamped, so
+/// adding one month to Jan 31 yields the last day of February.
+///
+/// # Arguments
+///
+/// * `args[0]` - Unix timestamp in seconds
+/// * `args[1]` - Amount to add (may be negative)
+/// * `args[2]` - Unit: `second`, `minute`, `hour`, `day`, `week`, `month` or `year`
+///
+/// # Returns
+///
+/// The resulting Unix timestamp
+pub fn add(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let timestamp = args
+        .first()
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| fusabi_host::Error::host_function("time.add: missing timestamp argument"))?;
+
+    let amount = args
+        .get(1)
+        .and_then(|v| v.as_int())
+        .ok_or_else(|| fusabi_host::Error::host_function("time.add: missing amount argument"))?;
+
+    let unit = calendar_unit(args.get(2), "time.add")?;
+
+    add_units(timestamp, amount, unit)
+        .map(Value::Int)
+        .ok_or_else(|| fusabi_host::Error::host_function("time.add: timestamp out of range"))
+}
+
+/// Difference between two Unix timestamps in whole calendar units.
+///
+/// The result is `b - a`, truncated toward zero, so it is positive when `b`
+/// is later than `a`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Start timestamp in seconds
+/// * `args[1]` - End timestamp in seconds
+/// * `args[2]` - Unit (optional, default `"second"`)
+///
+/// # Returns
+///
+/// The number of complete units between the timestamps
+pub fn diff(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let a = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.diff: missing start timestamp argument")
+    })?;
+
+    let b = args.get(1).and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.diff: missing end timestamp argument")
+    })?;
+
+    let unit = match args.get(2) {
+        Some(Value::Null) | None => CalendarUnit::Second,
+        value => calendar_unit(value, "time.diff")?,
+    };
+
+    let result = match unit {
+        CalendarUnit::Month => diff_months(a, b),
+        CalendarUnit::Year => diff_months(a, b).map(|months| months / 12),
+        fixed => b.checked_sub(a).map(|secs| secs / fixed.seconds()),
+    };
+
+    result
+        .map(Value::Int)
+        .ok_or_else(|| fusabi_host::Error::host_function("time.diff: timestamp out of range"))
+}
+
+/// Truncate a Unix timestamp to the start of a calendar unit (UTC).
+///
+/// Weeks start on Monday.
+///
+/// # Arguments
+///
+/// * `args[0]` - Unix timestamp in seconds
+/// * `args[1]` - Unit, e.g. `"day"`
+///
+/// # Returns
+///
+/// The timestamp of the start of the unit containing `args[0]`
+pub fn truncate(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let timestamp = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.truncate: missing timestamp argument")
+    })?;
+
+    let unit = calendar_unit(args.get(1), "time.truncate")?;
+
+    let days = timestamp.div_euclid(SECS_PER_DAY);
+    let truncated = match unit {
+        CalendarUnit::Week => (days - (weekday_of(days) - 1)) * SECS_PER_DAY,
+        CalendarUnit::Month => {
+            let (year, month, _) = civil_from_days(days);
+            days_from_civil(year, month, 1) * SECS_PER_DAY
+        }
+        CalendarUnit::Year => {
+            let (year, _, _) = civil_from_days(days);
+            days_from_civil(year, 1, 1) * SECS_PER_DAY
+        }
+        fixed => timestamp - timestamp.rem_euclid(fixed.seconds()),
+    };
+
+    Ok(Value::Int(truncated))
+}
+
+/// ISO weekday of a Unix timestamp (UTC), from 1 (Monday) to 7 (Sunday).
+pub fn weekday(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let timestamp = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.weekday: missing timestamp argument")
+    })?;
+
+    Ok(Value::Int(weekday_of(timestamp.div_euclid(SECS_PER_DAY))))
+}
+
+/// Number of days in the month containing a Unix timestamp (UTC).
+pub fn days_in_month(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let timestamp = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.days_in_month: missing timestamp argument")
+    })?;
+
+    let (year, month, _) = civil_from_days(timestamp.div_euclid(SECS_PER_DAY));
+    Ok(Value::Int(month_length(year, month)))
+}
+
+/// Format a Unix timestamp in an IANA time zone.
+///
+/// # Arguments
+///
+/// * `args[0]` - Unix timestamp in seconds
+/// * `args[1]` - Zone name, e.g. `"Europe/Berlin"`
+/// * `args[2]` - strftime format (optional, default `"%Y-%m-%d %H:%M:%S %Z"`)
+///
+/// # Returns
+///
+/// The formatted local time string
+#[cfg(feature = "tz")]
+pub fn format_tz(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let timestamp = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.format_tz: missing timestamp argument")
+    })?;
+
+    let zone = args.get(1).and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.format_tz: missing zone argument")
+    })?;
+    let zone = parse_zone(zone, "time.format_tz")?;
+
+    let format_str = args
+        .get(2)
+        .and_then(|v| v.as_str())
+        .unwrap_or("%Y-%m-%d %H:%M:%S %Z");
+
+    let local = zoned(timestamp, &zone, "time.format_tz")?;
+    Ok(Value::String(format_checked(
+        &local,
+        format_str,
+        "time.format_tz",
+    )?))
+}
+
+/// Convert a Unix timestamp to civil time fields in an IANA time zone.
+///
+/// # Arguments
+///
+/// * `args[0]` - Unix timestamp in seconds
+/// * `args[1]` - Zone name, e.g. `"America/New_York"`
+///
+/// # Returns
+///
+/// Map with `year`, `month`, `day`, `hour`, `minute`, `second`, `weekday`
+/// (ISO, 1 = Monday to 7 = Sunday, as from `time.weekday`), `offset` (seconds east of UTC), `abbreviation`, and `zone`
+#[cfg(feature = "tz")]
+pub fn to_zone(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    use chrono::{Datelike, Offset, Timelike};
+
+    let timestamp = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.to_zone: missing timestamp argument")
+    })?;
+
+    let zone_name = args
+        .get(1)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("time.to_zone: missing zone argument"))?;
+    let zone = parse_zone(zone_name, "time.to_zone")?;
+
+    let local = zoned(timestamp, &zone, "time.to_zone")?;
+
+    let mut map = HashMap::new();
+    map.insert("year".to_string(), Value::Int(local.year() as i64));
+    map.insert("month".to_string(), Value::Int(local.month() as i64));
+    map.insert("day".to_string(), Value::Int(local.day() as i64));
+    map.insert("hour".to_string(), Value::Int(local.hour() as i64));
+    map.insert("minute".to_string(), Value::Int(local.minute() as i64));
+    map.insert("second".to_string(), Value::Int(local.second() as i64));
+    map.insert(
+        "weekday".to_string(),
+        Value::Int(local.weekday().number_from_monday() as i64),
+    );
+    map.insert(
+        "offset".to_string(),
+        Value::Int(local.offset().fix().local_minus_utc() as i64),
+    );
+    map.insert(
+        "abbreviation".to_string(),
+        Value::String(local.format("%Z").to_string()),
+    );
+    map.insert("zone".to_string(), Value::String(zone.name().to_string()));
+
+    Ok(Value::Map(map))
+}
+
+/// Get the UTC offset of an IANA time zone.
+///
+/// # Arguments
+///
+/// * `args[0]` - Zone name
+/// * `args[1]` - Unix timestamp in seconds at which to evaluate the offset
+///   (optional, default now); offsets change with daylight saving time
+///
+/// # Returns
+///
+/// Offset in seconds east of UTC
+#[cfg(feature = "tz")]
+pub fn offset(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    use chrono::Offset;
+
+    let zone = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("time.offset: missing zone argument"))?;
+    let zone = parse_zone(zone, "time.offset")?;
+
+    let timestamp = match args.get(1).and_then(|v| v.as_int()) {
+        Some(ts) => ts,
+        None => chrono::Utc::now().timestamp(),
+    };
+
+    let local = zoned(timestamp, &zone, "time.offset")?;
+    Ok(Value::Int(local.offset().fix().local_minus_utc() as i64))
+}
+
+/// List the IANA time zones known to the bundled time zone database.
+///
+/// Zone data is compiled in from chrono-tz, so the list does not depend on
+/// the host having `/usr/share/zoneinfo`; updating tzdata means updating
+/// that dependency ([`TZDB_VERSION`] names the release in use).
+///
+/// # Arguments
+///
+/// * `args[0]` - Name prefix to filter by, e.g. `"Europe/"` (optional)
+///
+/// # Returns
+///
+/// Sorted list of zone names
+#[cfg(feature = "tz")]
+pub fn timezones(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let prefix = args.first().and_then(|v| v.as_str()).unwrap_or("");
+
+    let mut names: Vec<&str> = chrono_tz::TZ_VARIANTS
+        .iter()
+        .map(|zone| zone.name())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.sort_unstable();
+
+    Ok(Value::List(
+        names
+            .into_iter()
+            .map(|name| Value::String(name.to_string()))
+            .collect(),
+    ))
+}
+
+/// Release of the IANA time zone database bundled with the `tz` feature,
+/// e.g. `"2025b"`.
+#[cfg(feature = "tz")]
+pub const TZDB_VERSION: &str = chrono_tz::IANA_TZDB_VERSION;
+
+/// Describe the UTC offset of an IANA time zone at an instant.
+///
+/// # Arguments
+///
+/// * `args[0]` - Zone name
+/// * `args[1]` - Unix timestamp in seconds (optional, default now)
+///
+/// # Returns
+///
+/// Map with `offset` (total seconds east of UTC), `base_offset` (standard
+/// time offset), `dst_offset` (daylight saving adjustment), `dst` (bool),
+/// `abbreviation`, and `zone`
+#[cfg(feature = "tz")]
+pub fn tz_offset(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    use chrono::Offset;
+    use chrono_tz::OffsetComponents;
+
+    let zone_name = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.tz_offset: missing zone argument")
+    })?;
+    let zone = parse_zone(zone_name, "time.tz_offset")?;
+
+    let timestamp = match args.get(1) {
+        None | Some(Value::Null) => chrono::Utc::now().timestamp(),
+        Some(value) => value.as_int().ok_or_else(|| {
+            fusabi_host::Error::host_function("time.tz_offset: timestamp must be an integer")
+        })?,
+    };
+
+    let local = zoned(timestamp, &zone, "time.tz_offset")?;
+    let offset = local.offset();
+    let dst_offset = offset.dst_offset().num_seconds();
+
+    let mut map = HashMap::new();
+    map.insert(
+        "offset".to_string(),
+        Value::Int(offset.fix().local_minus_utc() as i64),
+    );
+    map.insert(
+        "base_offset".to_string(),
+        Value::Int(offset.base_utc_offset().num_seconds()),
+    );
+    map.insert("dst_offset".to_string(), Value::Int(dst_offset));
+    map.insert("dst".to_string(), Value::Bool(dst_offset != 0));
+    map.insert(
+        "abbreviation".to_string(),
+        Value::String(local.format("%Z").to_string()),
+    );
+    map.insert("zone".to_string(), Value::String(zone.name().to_string()));
+
+    Ok(Value::Map(map))
+}
+
+/// Format the current time in the operator's local time zone.
+///
+/// The zone is `local_zone` if configured (the `local_zone` option of the
+/// time module), otherwise the `TZ` environment variable if it names an
+/// IANA zone (resolved from the bundled database, so it works in
+/// containers without zoneinfo), otherwise the system zone.
+///
+/// # Arguments
+///
+/// * `args[0]` - strftime format (optional, default RFC 3339)
+///
+/// # Returns
+///
+/// The formatted local time string
+#[cfg(feature = "tz")]
+pub fn now_local(
+    local_zone: Option<&chrono_tz::Tz>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let format_str = args.first().and_then(|v| v.as_str());
+    let now = chrono::Utc::now();
+
+    let env_zone;
+    let local_zone = match local_zone {
+        Some(zone) => Some(zone),
+        None => {
+            env_zone = env_zone_name().and_then(|name| name.parse::<chrono_tz::Tz>().ok());
+            env_zone.as_ref()
+        }
+    };
+
+    let formatted = match local_zone {
+        Some(zone) => {
+            let local = now.with_timezone(zone);
+            match format_str {
+                Some(f) => format_checked(&local, f, "time.now_local")?,
+                None => local.to_rfc3339(),
+            }
+        }
+        None => {
+            let local = now.with_timezone(&chrono::Local);
+            match format_str {
+                Some(f) => format_checked(&local, f, "time.now_local")?,
+                None => local.to_rfc3339(),
+            }
+        }
+    };
+
+    Ok(Value::String(formatted))
+}
+
+/// Zone name from the `TZ` environment variable, without the POSIX `:`
+/// prefix.
+#[cfg(feature = "tz")]
+fn env_zone_name() -> Option<String> {
+    let tz = std::env::var("TZ").ok()?;
+    let name = tz.strip_prefix(':').unwrap_or(&tz).trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Parse an IANA zone name.
+#[cfg(feature = "tz")]
+pub(crate) fn parse_zone(name: &str, function: &str) -> fusabi_host::Result<chrono_tz::Tz> {
+    name.parse::<chrono_tz::Tz>().map_err(|_| {
+        fusabi_host::Error::host_function(format!("{}: unknown time zone '{}'", function, name))
+    })
+}
+
+#[cfg(feature = "tz")]
+fn zoned(
+    timestamp: i64,
+    zone: &chrono_tz::Tz,
+    function: &str,
+) -> fusabi_host::Result<chrono::DateTime<chrono_tz::Tz>> {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|utc| utc.with_timezone(zone))
+        .ok_or_else(|| {
+            fusabi_host::Error::host_function(format!("{}: timestamp out of range", function))
+        })
+}
+
+/// Format with a user-supplied strftime string, rejecting invalid specifiers
+/// instead of panicking.
+#[cfg(feature = "tz")]
+fn format_checked<Tz: chrono::TimeZone>(
+    time: &chrono::DateTime<Tz>,
+    format_str: &str,
+    function: &str,
+) -> fusabi_host::Result<String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    use chrono::format::{Item, StrftimeItems};
+
+    let items: Vec<Item<'_>> = StrftimeItems::new(format_str).collect();
+    if items.iter().any(|item| matches!(item, Item::Error)) {
+        return Err(fusabi_host::Error::host_function(format!(
+            "{}: invalid format string '{}'",
+            function, format_str
+        )));
+    }
+
+    Ok(time.format_with_items(items.into_iter()).to_string())
+}
+
+/// Get the next time a cron expression fires.
+///
+/// Accepts standard five-field expressions (`min hour dom mon dow`, with
+/// days of week numbered from 0 or 7 for Sunday, as in crontab), the
+/// six- and seven-field forms with seconds and years, and macros such as
+/// `@daily`. Times are evaluated in UTC.
+///
+/// # Arguments
+///
+/// * `args[0]` - Cron expression
+/// * `args[1]` - Unix timestamp in seconds to search after (optional,
+///   default now)
+///
+/// # Returns
+///
+/// The next Unix timestamp strictly after `args[1]`, or null if the
+/// schedule never fires again
+#[cfg(feature = "cron")]
+pub fn cron_next(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let expr = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.cron_next: missing expression argument")
+    })?;
+
+    let (schedule, _) = parse_cron(expr, "time.cron_next")?;
+
+    let after = match args.get(1) {
+        Some(Value::Null) | None => chrono::Utc::now(),
+        Some(value) => {
+            let ts = value.as_int().ok_or_else(|| {
+                fusabi_host::Error::host_function("time.cron_next: timestamp must be an integer")
+            })?;
+            utc_datetime(ts, "time.cron_next")?
+        }
+    };
+
+    Ok(schedule
+        .after(&after)
+        .next()
+        .map(|next| Value::Int(next.timestamp()))
+        .unwrap_or(Value::Null))
+}
+
+/// Check whether a cron expression fires at a given time.
+///
+/// Five-field expressions have minute resolution, so any second within a
+/// matching minute matches.
+///
+/// # Arguments
+///
+/// * `args[0]` - Cron expression
+/// * `args[1]` - Unix timestamp in seconds
+///
+/// # Returns
+///
+/// Boolean
+#[cfg(feature = "cron")]
+pub fn cron_matches(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let expr = args.first().and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.cron_matches: missing expression argument")
+    })?;
+
+    let timestamp = args.get(1).and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.cron_matches: missing timestamp argument")
+    })?;
+
+    let (schedule, minute_resolution) = parse_cron(expr, "time.cron_matches")?;
+    let timestamp = if minute_resolution {
+        timestamp - timestamp.rem_euclid(60)
+    } else {
+        timestamp
+    };
+
+    Ok(Value::Bool(
+        schedule.includes(utc_datetime(timestamp, "time.cron_matches")?),
+    ))
+}
+
+/// Parse a cron expression, returning the schedule and whether it was a
+/// five-field (minute resolution) expression.
+#[cfg(feature = "cron")]
+pub(crate) fn parse_cron(
+    expr: &str,
+    function: &str,
+) -> fusabi_host::Result<(cron::Schedule, bool)> {
+    use std::str::FromStr;
+
+    let expr = expr.trim();
+    let five_field = !expr.starts_with('@') && expr.split_whitespace().count() == 5;
+    let invalid = |e: &dyn std::fmt::Display| {
+        fusabi_host::Error::host_function(format!(
+            "{}: invalid cron expression '{}': {}",
+            function, expr, e
+        ))
+    };
+    let source = if five_field {
+        let mut fields: Vec<&str> = expr.split_whitespace().collect();
+        let days = crontab_days(fields[4]).map_err(|e| invalid(&e))?;
+        fields[4] = &days;
+        format!("0 {}", fields.join(" "))
+    } else {
+        expr.to_string()
+    };
+
+    let schedule = cron::Schedule::from_str(&source).map_err(|e| invalid(&e))?;
+
+    Ok((schedule, five_field))
+}
+
+/// Translate a crontab day-of-week field, where 0 and 7 are Sunday, into
+/// day names: the `cron` crate numbers days from 1 for Sunday.
+#[cfg(feature = "cron")]
+fn crontab_days(field: &str) -> std::result::Result<String, String> {
+    const DAYS: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+    let day = |text: &str| match text.parse::<usize>() {
+        Ok(day) if day <= 7 => Ok(day),
+        _ => Err(format!("invalid day of week '{}'", text)),
+    };
+
+    let mut items = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (item, None),
+        };
+        // `*` keeps its meaning and names are already unambiguous
+        if range == "*" || range == "?" || range.chars().any(|c| c.is_ascii_alphabetic()) {
+            items.push(item.to_string());
+            continue;
+        }
+        let step = match step {
+            Some(step) => match step.parse::<usize>() {
+                Ok(step) if step > 0 => step,
+                _ => return Err(format!("invalid day of week step '{}'", step)),
+            },
+            None => 1,
+        };
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (day(start)?, day(end)?),
+            None if step > 1 => (day(range)?, 6),
+            None => (day(range)?, day(range)?),
+        };
+        if start > end {
+            return Err(format!("invalid day of week range '{}'", range));
+        }
+        for day in (start..=end).step_by(step) {
+            let name = DAYS[day % 7];
+            if !items.iter().any(|item| item == name) {
+                items.push(name.to_string());
+            }
+        }
+    }
+    Ok(items.join(","))
+}
+
+#[cfg(feature = "cron")]
+fn utc_datetime(
+    timestamp: i64,
+    function: &str,
+) -> fusabi_host::Result<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::from_timestamp(timestamp, 0).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("{}: timestamp out of range", function))
+    })
+}
+
+/// Create a token-bucket rate limiter.
+///
+/// The limiter holds up to `n` tokens and refills `n` tokens every `per_ms`
+/// milliseconds, so bursts of `n` are allowed but the long-run rate is capped.
+///
+/// # Arguments
+///
+/// * `args[0]` - Number of permits per window
+/// * `args[1]` - Window length in milliseconds
+///
+/// # Returns
+///
+/// Handle (integer) for use with `acquire`, `try_acquire`, and `limiter_close`
+pub fn rate_limiter(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let permits = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.rate_limiter: missing permits argument")
+    })?;
+
+    let per_ms = args.get(1).and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.rate_limiter: missing per_ms argument")
+    })?;
+
+    if permits <= 0 || per_ms <= 0 {
+        return Err(fusabi_host::Error::host_function(
+            "time.rate_limiter: permits and per_ms must be positive",
+        ));
+    }
+    let permits = u32::try_from(permits).map_err(|_| {
+        fusabi_host::Error::host_function(format!(
+            "time.rate_limiter: permits must be at most {}",
+            u32::MAX
+        ))
+    })?;
+
+    let limiter = RateLimiter::new(permits, Duration::from_millis(per_ms as u64));
+    Ok(Value::Int(insert_limiter(Limiter::Rate(limiter))))
+}
+
+/// Block until a permit is available from a rate limiter.
+///
+/// The wait wakes periodically and returns early with an error if the
+/// execution is cancelled or times out.
+///
+/// # Arguments
+///
+/// * `args[0]` - Rate limiter handle
+pub fn acquire(args: &[Value], ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.acquire: missing handle argument")
+    })?;
+
+    loop {
+        let wait = with_rate_limiter(handle, "time.acquire", |limiter| {
+            if limiter.try_acquire() {
+                Duration::ZERO
+            } else {
+                limiter.time_until_available()
+            }
+        })?;
+
+        if wait.is_zero() {
+            return Ok(Value::Null);
+        }
+
+        if ctx.is_cancelled() {
+            return Err(fusabi_host::Error::Cancelled);
+        }
+        ctx.check_timeout()?;
+
+        std::thread::sleep(wait.min(SLEEP_SLICE));
+    }
+}
+
+/// Take a permit from a rate limiter without blocking.
+///
+/// # Arguments
+///
+/// * `args[0]` - Rate limiter handle
+///
+/// # Returns
+///
+/// Boolean indicating whether a permit was acquired
+pub fn try_acquire(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.try_acquire: missing handle argument")
+    })?;
+
+    let acquired = with_rate_limiter(handle, "time.try_acquire", RateLimiter::try_acquire)?;
+    Ok(Value::Bool(acquired))
+}
+
+/// Create a throttle gate.
+///
+/// Host functions cannot invoke script closures, so throttling is expressed
+/// as a gate: the script calls `throttle_ready` before running the guarded
+/// code and skips it when the gate returns false.
+///
+/// # Arguments
+///
+/// * `args[0]` - Minimum interval between permitted calls in milliseconds
+///
+/// # Returns
+///
+/// Handle (integer)
+pub fn throttle(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let interval_ms = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.throttle: missing interval_ms argument")
+    })?;
+
+    if interval_ms < 0 {
+        return Err(fusabi_host::Error::host_function(
+            "time.throttle: interval_ms must be non-negative",
+        ));
+    }
+
+    let throttle = Throttle::new(Duration::from_millis(interval_ms as u64));
+    Ok(Value::Int(insert_limiter(Limiter::Throttle(throttle))))
+}
+
+/// Check a throttle gate, recording a call if it is open.
+///
+/// # Arguments
+///
+/// * `args[0]` - Throttle handle
+///
+/// # Returns
+///
+/// Boolean indicating whether the guarded call should run now
+pub fn throttle_ready(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.throttle_ready: missing handle argument")
+    })?;
+
+    let mut limiters = limiters().lock();
+    match limiters.get_mut(&handle) {
+        Some(Limiter::Throttle(throttle)) => Ok(Value::Bool(throttle.ready())),
+        _ => Err(fusabi_host::Error::host_function(
+            "time.throttle_ready: invalid handle",
+        )),
+    }
+}
+
+/// Create a debounce gate.
+///
+/// Events are recorded with `debounce_touch`; `debounce_ready` returns true
+/// once no event has been recorded for the quiet period, and then resets.
+///
+/// # Arguments
+///
+/// * `args[0]` - Quiet period in milliseconds
+///
+/// # Returns
+///
+/// Handle (integer)
+pub fn debounce(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let wait_ms = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.debounce: missing wait_ms argument")
+    })?;
+
+    if wait_ms < 0 {
+        return Err(fusabi_host::Error::host_function(
+            "time.debounce: wait_ms must be non-negative",
+        ));
+    }
+
+    let debounce = Debounce::new(Duration::from_millis(wait_ms as u64));
+    Ok(Value::Int(insert_limiter(Limiter::Debounce(debounce))))
+}
+
+/// Record an event on a debounce gate.
+///
+/// # Arguments
+///
+/// * `args[0]` - Debounce handle
+pub fn debounce_touch(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.debounce_touch: missing handle argument")
+    })?;
+
+    let mut limiters = limiters().lock();
+    match limiters.get_mut(&handle) {
+        Some(Limiter::Debounce(debounce)) => {
+            debounce.touch();
+            Ok(Value::Null)
+        }
+        _ => Err(fusabi_host::Error::host_function(
+            "time.debounce_touch: invalid handle",
+        )),
+    }
+}
+
+/// Check whether a debounce gate has settled.
+///
+/// # Arguments
+///
+/// * `args[0]` - Debounce handle
+///
+/// # Returns
+///
+/// Boolean that is true once per burst of events, after the quiet period
+pub fn debounce_ready(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.debounce_ready: missing handle argument")
+    })?;
+
+    let mut limiters = limiters().lock();
+    match limiters.get_mut(&handle) {
+        Some(Limiter::Debounce(debounce)) => Ok(Value::Bool(debounce.ready())),
+        _ => Err(fusabi_host::Error::host_function(
+            "time.debounce_ready: invalid handle",
+        )),
+    }
+}
+
+/// Release a rate limiter, throttle, or debounce handle.
+///
+/// # Arguments
+///
+/// * `args[0]` - Handle
+pub fn limiter_close(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function("time.limiter_close: missing handle argument")
+    })?;
+
+    if limiters().lock().remove(&handle).is_some() {
+        Ok(Value::Null)
+    } else {
+        Err(fusabi_host::Error::host_function(
+            "time.limiter_close: invalid handle",
+        ))
+    }
+}
+
+/// Start a monotonic stopwatch.
+///
+/// Unlike `now_millis` differences, stopwatch readings are backed by
+/// `Instant` and are unaffected by wall-clock adjustments.
+///
+/// # Returns
+///
+/// Handle (integer) for use with `timer_elapsed`, `timer_lap`, and `timer_stop`
+pub fn timer_start(_args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    timers().lock().insert(handle, Stopwatch::new());
+    Ok(Value::Int(handle))
+}
+
+/// Read a stopwatch without stopping it.
+///
+/// # Arguments
+///
+/// * `args[0]` - Timer handle
+///
+/// # Returns
+///
+/// Milliseconds (float) since `timer_start`
+pub fn timer_elapsed(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = timer_handle(args, "time.timer_elapsed")?;
+
+    match timers().lock().get(&handle) {
+        Some(timer) => Ok(Value::Float(as_millis_f64(timer.elapsed()))),
+        None => Err(fusabi_host::Error::host_function(
+            "time.timer_elapsed: invalid handle",
+        )),
+    }
+}
+
+/// Record a lap on a stopwatch.
+///
+/// # Arguments
+///
+/// * `args[0]` - Timer handle
+///
+/// # Returns
+///
+/// Milliseconds (float) since the previous lap, or since `timer_start` for
+/// the first lap
+pub fn timer_lap(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let handle = timer_handle(args, "time.timer_lap")?;
+
+    match timers().lock().get_mut(&handle) {
+        Some(timer) => Ok(Value::Float(as_millis_f64(timer.lap()))),
+        None => Err(fusabi_host::Error::host_function(
+            "time.timer_lap: invalid handle",
+        )),
+    }
+}
+
+/// Stop a stopwatch and release its handle.
+///
+/// When a histogram name is given, the elapsed milliseconds are also
+/// observed into that metric in the global metrics registry (requires the
+/// `metrics` feature); see [`timer_stop_into`] to choose the registry.
+///
+/// # Arguments
+///
+/// * `args[0]` - Timer handle
+/// * `args[1]` - Histogram name (optional)
+///
+/// # Returns
+///
+/// Milliseconds (float) since `timer_start`
+pub fn timer_stop(args: &[Value], ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    #[cfg(feature = "metrics")]
+    return timer_stop_into(crate::metrics::global(), args, ctx);
+
+    #[cfg(not(feature = "metrics"))]
+    {
+        let _ = ctx;
+        if args.get(1).and_then(|v| v.as_str()).is_some() {
+            return Err(fusabi_host::Error::host_function(
+                "time.timer_stop: recording to a histogram requires the metrics feature",
+            ));
+        }
+        stop_timer(args).map(Value::Float)
+    }
+}
+
+/// Stop a stopwatch, recording into the given metrics registry.
+///
+/// Same as [`timer_stop`], but an optional histogram is observed in
+/// `metrics` rather than the global registry.
+#[cfg(feature = "metrics")]
+pub fn timer_stop_into(
+    metrics: &std::sync::Arc<crate::metrics::MetricsRegistry>,
+    args: &[Value],
+    ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let elapsed = stop_timer(args)?;
+    if let Some(name) = args.get(1).and_then(|v| v.as_str()) {
+        let (name, labels) = metrics.scope(ctx, name, Default::default());
+        metrics.histogram_observe_with(&name, &labels, elapsed);
+    }
+    Ok(Value::Float(elapsed))
+}
+
+/// Remove a timer, returning its elapsed milliseconds.
+fn stop_timer(args: &[Value]) -> fusabi_host::Result<f64> {
+    let handle = timer_handle(args, "time.timer_stop")?;
+    let timer = timers()
+        .lock()
+        .remove(&handle)
+        .ok_or_else(|| fusabi_host::Error::host_function("time.timer_stop: invalid handle"))?;
+    Ok(as_millis_f64(timer.elapsed()))
+}
+
+/// Default NTP port.
+const NTP_PORT: u16 = 123;
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch (1970).
+const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+
+/// Measure the local clock's offset from an NTP server.
+///
+/// Sends a single SNTP query over UDP. The server host must be on the host
+/// allowlist.
+///
+/// # Arguments
+///
+/// * `args[0]` - Server host, optionally with `:port` (default port 123)
+/// * `args[1]` - Timeout in milliseconds (optional, clamped to the safety maximum)
+///
+/// # Returns
+///
+/// Map with `offset_ms` (server clock minus local clock), `roundtrip_ms`,
+/// `stratum`, and `server`
+pub fn ntp_offset(
+    safety: &std::sync::Arc<crate::safety::SafetyConfig>,
+    args: &[Value],
+    _ctx: &ExecutionContext,
+) -> fusabi_host::Result<Value> {
+    let server = args
+        .first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function("time.ntp_offset: missing server"))?;
+
+    let (host, port) = match server.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') || host.starts_with('[') => {
+            let port = port.parse().map_err(|_| {
+                fusabi_host::Error::host_function(format!(
+                    "time.ntp_offset: invalid port: {}",
+                    port
+                ))
+            })?;
+            (host.trim_matches(['[', ']']), port)
+        }
+        _ => (server.trim_matches(['[', ']']), NTP_PORT),
+    };
+
+    // Check safety
+    safety
+        .hosts
+        .check(host)
+        .map_err(|e| fusabi_host::Error::host_function(e.to_string()))?;
+
+    let timeout = args
+        .get(1)
+        .and_then(|v| v.as_int())
+        .map(|ms| safety.clamp_timeout(Duration::from_millis(ms.max(1) as u64)))
+        .unwrap_or_else(|| safety.clamp_timeout(Duration::from_secs(5)));
+
+    let sample = query_ntp(host, port, timeout)
+        .map_err(|e| fusabi_host::Error::host_function(format!("time.ntp_offset: {}", e)))?;
+
+    let mut result = HashMap::new();
+    result.insert(
+        "offset_ms".to_string(),
+        Value::Float(sample.offset * 1000.0),
+    );
+    result.insert(
+        "roundtrip_ms".to_string(),
+        Value::Float(sample.roundtrip * 1000.0),
+    );
+    result.insert("stratum".to_string(), Value::Int(sample.stratum as i64));
+    result.insert("server".to_string(), Value::String(server.to_string()));
+    Ok(Value::Map(result))
+}
+
+/// Result of an SNTP exchange, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct NtpSample {
+    offset: f64,
+    roundtrip: f64,
+    stratum: u8,
+}
+
+/// Perform one SNTP exchange with `host:port`.
+fn query_ntp(host: &str, port: u16, timeout: Duration) -> crate::Result<NtpSample> {
+    use std::net::{ToSocketAddrs, UdpSocket};
+
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| crate::Error::network(format!("could not resolve {}", host)))?;
+    let bind = if addr.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(addr)?;
+
+    let t1 = ntp_now();
+    socket.send(&ntp_request(t1))?;
+
+    let mut response = [0u8; 48];
+    let received = socket.recv(&mut response).map_err(|e| {
+        if matches!(
+            e.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        ) {
+            crate::Error::timeout(timeout)
+        } else {
+            e.into()
+        }
+    })?;
+    let t4 = ntp_now();
+
+    if received < 48 {
+        return Err(crate::Error::network("short NTP response"));
+    }
+    parse_ntp_response(&response, t1, t4)
+}
+
+/// Current time as NTP seconds.
+fn ntp_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+        + NTP_UNIX_OFFSET
+}
+
+fn to_ntp_timestamp(seconds: f64) -> [u8; 8] {
+    let secs = seconds.trunc() as u32;
+    let frac = (seconds.fract() * 4_294_967_296.0) as u32;
+    let mut out = [0u8; 8];
+    out[..4].copy_from_slice(&secs.to_be_bytes());
+    out[4..].copy_from_slice(&frac.to_be_bytes());
+    out
+}
+
+fn from_ntp_timestamp(bytes: &[u8]) -> f64 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    let frac = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    secs as f64 + frac as f64 / 4_294_967_296.0
+}
+
+/// Build an SNTP client request carrying `t1` as its transmit timestamp.
+fn ntp_request(t1: f64) -> [u8; 48] {
+    let mut packet = [0u8; 48];
+    // LI = 0, VN = 4, Mode = 3 (client)
+    packet[0] = 0x23;
+    packet[40..48].copy_from_slice(&to_ntp_timestamp(t1));
+    packet
+}
+
+/// Compute offset and roundtrip from a server response (RFC 4330).
+fn parse_ntp_response(response: &[u8; 48], t1: f64, t4: f64) -> crate::Result<NtpSample> {
+    let mode = response[0] & 0x07;
+    if mode != 4 && mode != 5 {
+        return Err(crate::Error::network(format!(
+            "unexpected NTP mode {}",
+            mode
+        )));
+    }
+
+    let stratum = response[1];
+    if stratum == 0 {
+        let code = String::from_utf8_lossy(&response[12..16]).into_owned();
+        return Err(crate::Error::network(format!(
+            "NTP server sent kiss-o'-death: {}",
+            code.trim_end_matches('\0')
+        )));
+    }
+
+    // The server echoes our transmit time as its originate time.
+    if response[24..32] != to_ntp_timestamp(t1) {
+        return Err(crate::Error::network("NTP response does not match request"));
+    }
+
+    let t2 = from_ntp_timestamp(&response[32..40]);
+    let t3 = from_ntp_timestamp(&response[40..48]);
+
+    Ok(NtpSample {
+        offset: ((t2 - t1) + (t3 - t4)) / 2.0,
+        roundtrip: ((t4 - t1) - (t3 - t2)).max(0.0),
+        stratum,
+    })
+}
+
+/// A token-bucket rate limiter.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `permits` acquisitions per `per` window.
+    pub fn new(permits: u32, per: Duration) -> Self {
+        let capacity = permits.max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / per.as_secs_f64().max(f64::EPSILON),
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take a permit if one is available.
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Time until the next permit becomes available.
+    pub fn time_until_available(&mut self) -> Duration {
+        self.refill();
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec)
+        }
+    }
+}
+
+/// A gate that opens at most once per interval.
+#[derive(Debug, Clone)]
+pub struct Throttle {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    /// Create a throttle with the given minimum interval.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: None,
+        }
+    }
+
+    /// Returns true (and records the call) if the interval has elapsed.
+    pub fn ready(&mut self) -> bool {
+        let now = Instant::now();
+        match self.last {
+            Some(last) if now.duration_since(last) < self.interval => false,
+            _ => {
+                self.last = Some(now);
+                true
+            }
+        }
+    }
+}
+
+/// A gate that opens once events have stopped for a quiet period.
+#[derive(Debug, Clone)]
+pub struct Debounce {
+    wait: Duration,
+    pending: Option<Instant>,
+}
+
+impl Debounce {
+    /// Create a debounce gate with the given quiet period.
+    pub fn new(wait: Duration) -> Self {
+        Self {
+            wait,
+            pending: None,
+        }
+    }
+
+    /// Record an event, restarting the quiet period.
+    pub fn touch(&mut self) {
+        self.pending = Some(Instant::now());
+    }
+
+    /// Returns true once per burst, after the quiet period has elapsed.
+    pub fn ready(&mut self) -> bool {
+        match self.pending {
+            Some(last) if last.elapsed() >= self.wait => {
+                self.pending = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A monotonic stopwatch with lap support.
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    start: Instant,
+    last_lap: Instant,
+}
+
+impl Stopwatch {
+    /// Start a new stopwatch.
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            start: now,
+            last_lap: now,
+        }
+    }
+
+    /// Time since the stopwatch was started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Time since the previous lap (or start), beginning a new lap.
+    pub fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let lap = now.duration_since(self.last_lap);
+        self.last_lap = now;
+        lap
+    }
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+enum Limiter {
+    Rate(RateLimiter),
+    Throttle(Throttle),
+    Debounce(Debounce),
+}
+
+static LIMITERS: OnceLock<Mutex<HashMap<i64, Limiter>>> = OnceLock::new();
+
+static NEXT_HANDLE: AtomicI64 = AtomicI64::new(1);
+
+fn limiters() -> &'static Mutex<HashMap<i64, Limiter>> {
+    LIMITERS.get_or_init(Default::default)
+}
+
+fn insert_limiter(limiter: Limiter) -> i64 {
+    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    limiters().lock().insert(handle, limiter);
+    handle
+}
+
+static TIMERS: OnceLock<Mutex<HashMap<i64, Stopwatch>>> = OnceLock::new();
+
+fn timers() -> &'static Mutex<HashMap<i64, Stopwatch>> {
+    TIMERS.get_or_init(Default::default)
+}
+
+/// Number of open rate limiters, throttles and debouncers.
+#[cfg(feature = "observability")]
+pub(crate) fn open_limiters() -> usize {
+    limiters().lock().len()
+}
+
+/// Number of running timers.
+#[cfg(feature = "observability")]
+pub(crate) fn open_timers() -> usize {
+    timers().lock().len()
+}
+
+fn timer_handle(args: &[Value], function: &str) -> fusabi_host::Result<i64> {
+    args.first().and_then(|v| v.as_int()).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("{}: missing handle argument", function))
+    })
+}
+
+fn as_millis_f64(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn with_rate_limiter<T>(
+    handle: i64,
+    function: &str,
+    f: impl FnOnce(&mut RateLimiter) -> T,
+) -> fusabi_host::Result<T> {
+    let mut limiters = limiters().lock();
+    match limiters.get_mut(&handle) {
+        Some(Limiter::Rate(limiter)) => Ok(f(limiter)),
+        _ => Err(fusabi_host::Error::host_function(format!(
+            "{}: invalid handle",
+            function
+        ))),
+    }
+}
+
+pub(crate) const SECS_PER_DAY: i64 = 86_400;
+
+/// Calendar units accepted by `add`, `diff` and `truncate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CalendarUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl CalendarUnit {
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim_end_matches('s') {
+            "second" | "sec" => Some(Self::Second),
+            "minute" | "min" => Some(Self::Minute),
+            "hour" => Some(Self::Hour),
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "year" => Some(Self::Year),
+            _ => None,
+        }
+    }
+
+    /// Length in seconds of a fixed-size unit (months and years are not).
+    fn seconds(self) -> i64 {
+        match self {
+            Self::Second => 1,
+            Self::Minute => 60,
+            Self::Hour => 3600,
+            Self::Day => SECS_PER_DAY,
+            Self::Week => 7 * SECS_PER_DAY,
+            Self::Month | Self::Year => unreachable!("calendar units have no fixed length"),
+        }
+    }
+}
+
+fn calendar_unit(value: Option<&Value>, function: &str) -> fusabi_host::Result<CalendarUnit> {
+    let name = value.and_then(|v| v.as_str()).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("{}: missing unit argument", function))
+    })?;
+
+    CalendarUnit::parse(name).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("{}: unknown unit '{}'", function, name))
+    })
+}
+
+fn add_units(timestamp: i64, amount: i64, unit: CalendarUnit) -> Option<i64> {
+    match unit {
+        CalendarUnit::Month => add_months(timestamp, amount),
+        CalendarUnit::Year => add_months(timestamp, amount.checked_mul(12)?),
+        fixed => timestamp.checked_add(amount.checked_mul(fixed.seconds())?),
+    }
+}
+
+fn add_months(timestamp: i64, months: i64) -> Option<i64> {
+    let days = timestamp.div_euclid(SECS_PER_DAY);
+    let secs_of_day = timestamp.rem_euclid(SECS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+
+    let total = (year.checked_mul(12)? + (month as i64 - 1)).checked_add(months)?;
+    let year = total.div_euclid(12);
+    let month = (total.rem_euclid(12) + 1) as u32;
+    let day = day.min(month_length(year, month) as u32);
+
+    days_from_civil(year, month, day)
+        .checked_mul(SECS_PER_DAY)?
+        .checked_add(secs_of_day)
+}
+
+/// Whole calendar months from `a` to `b`, truncated toward zero.
+///
+/// Computed from the earlier timestamp so that swapping the arguments only
+/// flips the sign, despite day-of-month clamping.
+fn diff_months(a: i64, b: i64) -> Option<i64> {
+    if b < a {
+        return diff_months(b, a).map(|months| -months);
+    }
+
+    let (ya, ma, _) = civil_from_days(a.div_euclid(SECS_PER_DAY));
+    let (yb, mb, _) = civil_from_days(b.div_euclid(SECS_PER_DAY));
+
+    let mut months = (yb - ya).checked_mul(12)? + (mb as i64 - ma as i64);
+    if months > 0 && add_months(a, months)? > b {
+        months -= 1;
+    }
+    Some(months)
+}
+
+/// ISO weekday (1 = Monday) for days since the Unix epoch.
+pub(crate) fn weekday_of(days: i64) -> i64 {
+    // 1970-01-01 was a Thursday.
+    (days + 3).rem_euclid(7) + 1
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub(crate) fn month_length(year: i64, month: u32) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Proleptic Gregorian conversions, after Howard Hinnant's `days_from_civil`.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 // Helper function for simple timestamp formatting
 fn format_timestamp(timestamp: i64, _format: &str) -> String {
     // Very simple formatting - real implementation would use chrono
@@ -179,6 +1754,273 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_sleep_budgeted() {
+        let ctx = create_test_ctx();
+        let budget = Some(Duration::from_millis(500));
+
+        assert!(sleep_budgeted(budget, &[Value::Int(5)], &ctx).is_ok());
+        assert!(sleep_budgeted(None, &[Value::Int(-1)], &ctx).is_err());
+
+        // Longer than the remaining budget fails fast instead of sleeping.
+        let start = Instant::now();
+        let result = sleep_budgeted(budget, &[Value::Int(60_000)], &ctx);
+        assert!(matches!(result, Err(fusabi_host::Error::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        ctx.cancel();
+        let result = sleep_budgeted(None, &[Value::Int(5)], &ctx);
+        assert!(matches!(result, Err(fusabi_host::Error::Cancelled)));
+    }
+
+    #[cfg(feature = "time-async")]
+    #[tokio::test]
+    async fn test_sleep_async() {
+        let ctx = create_test_ctx();
+        let budget = Some(Duration::from_millis(500));
+
+        assert!(sleep_async(budget, 5, &ctx).await.is_ok());
+        assert!(matches!(
+            sleep_async(budget, 60_000, &ctx).await,
+            Err(fusabi_host::Error::Timeout(_))
+        ));
+    }
+
+    #[test]
+    fn test_date_arithmetic() {
+        let ctx = create_test_ctx();
+        let ts = Value::Int(1706708710); // 2024-01-31 13:45:10 UTC
+        let unit = |u: &str| Value::String(u.into());
+
+        // Month addition clamps to the end of February in a leap year.
+        let result = add(&[ts.clone(), Value::Int(1), unit("month")], &ctx).unwrap();
+        assert_eq!(result, Value::Int(1709214310));
+        let result = add(&[ts.clone(), Value::Int(13), unit("months")], &ctx).unwrap();
+        assert_eq!(result, Value::Int(1740750310));
+        let result = add(&[ts.clone(), Value::Int(-2), unit("hours")], &ctx).unwrap();
+        assert_eq!(result, Value::Int(1706708710 - 7200));
+        assert!(add(&[ts.clone(), Value::Int(1), unit("fortnight")], &ctx).is_err());
+
+        let feb = Value::Int(1709214310);
+        assert_eq!(
+            diff(&[ts.clone(), feb.clone(), unit("day")], &ctx).unwrap(),
+            Value::Int(29)
+        );
+        assert_eq!(
+            diff(&[ts.clone(), feb.clone(), unit("month")], &ctx).unwrap(),
+            Value::Int(1)
+        );
+        assert_eq!(
+            diff(&[feb, ts.clone(), unit("month")], &ctx).unwrap(),
+            Value::Int(-1)
+        );
+        // Jan 31 -> Mar 15 is one complete month, not two.
+        assert_eq!(
+            diff(&[ts.clone(), Value::Int(1710460800), unit("month")], &ctx).unwrap(),
+            Value::Int(1)
+        );
+        assert_eq!(
+            diff(&[ts.clone(), Value::Int(1740750310), unit("year")], &ctx).unwrap(),
+            Value::Int(1)
+        );
+    }
+
+    #[test]
+    fn test_calendar_helpers() {
+        let ctx = create_test_ctx();
+        let ts = Value::Int(1706708710); // Wednesday 2024-01-31 13:45:10 UTC
+        let unit = |u: &str| Value::String(u.into());
+
+        let truncated = |u: &str| truncate(&[ts.clone(), unit(u)], &ctx).unwrap();
+        assert_eq!(truncated("day"), Value::Int(1706659200));
+        assert_eq!(truncated("week"), Value::Int(1706486400));
+        assert_eq!(truncated("month"), Value::Int(1704067200));
+        assert_eq!(truncated("year"), Value::Int(1704067200));
+        assert_eq!(truncated("hour"), Value::Int(1706706000));
+
+        assert_eq!(
+            weekday(std::slice::from_ref(&ts), &ctx).unwrap(),
+            Value::Int(3)
+        );
+        assert_eq!(weekday(&[Value::Int(0)], &ctx).unwrap(), Value::Int(4));
+        assert_eq!(
+            weekday(&[Value::Int(1704063600)], &ctx).unwrap(),
+            Value::Int(7)
+        );
+
+        assert_eq!(
+            days_in_month(&[Value::Int(1709214310)], &ctx).unwrap(),
+            Value::Int(29)
+        );
+        assert_eq!(
+            days_in_month(&[Value::Int(1740750310)], &ctx).unwrap(),
+            Value::Int(28)
+        );
+        assert_eq!(days_in_month(&[ts], &ctx).unwrap(), Value::Int(31));
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let ctx = create_test_ctx();
+
+        let handle = rate_limiter(&[Value::Int(2), Value::Int(60_000)], &ctx).unwrap();
+
+        // Burst of two permits, then empty
+        assert_eq!(
+            try_acquire(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            try_acquire(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            try_acquire(std::slice::from_ref(&handle), &ctx).unwrap(),
+            Value::Bool(false)
+        );
+
+        limiter_close(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(try_acquire(&[handle], &ctx).is_err());
+
+        // Permit counts past u32::MAX are rejected rather than truncated
+        let result = rate_limiter(&[Value::Int(u32::MAX as i64 + 1), Value::Int(1_000)], &ctx);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_acquire_blocks_until_refill() {
+        let ctx = create_test_ctx();
+
+        let handle = rate_limiter(&[Value::Int(1), Value::Int(20)], &ctx).unwrap();
+        let start = Instant::now();
+        acquire(std::slice::from_ref(&handle), &ctx).unwrap();
+        acquire(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(15));
+
+        limiter_close(&[handle], &ctx).unwrap();
+    }
+
+    #[test]
+    fn test_acquire_cancelled_while_waiting() {
+        let ctx = create_test_ctx();
+
+        let handle = rate_limiter(&[Value::Int(1), Value::Int(60_000)], &ctx).unwrap();
+        acquire(std::slice::from_ref(&handle), &ctx).unwrap();
+
+        let start = Instant::now();
+        let result = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                ctx.cancel();
+            });
+            acquire(std::slice::from_ref(&handle), &ctx)
+        });
+        assert!(matches!(result, Err(fusabi_host::Error::Cancelled)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        limiter_close(&[handle], &ctx).unwrap();
+    }
+
+    #[test]
+    fn test_throttle_and_debounce() {
+        let mut throttle = Throttle::new(Duration::from_secs(60));
+        assert!(throttle.ready());
+        assert!(!throttle.ready());
+
+        let mut debounce = Debounce::new(Duration::from_millis(10));
+        assert!(!debounce.ready());
+        debounce.touch();
+        assert!(!debounce.ready());
+        std::thread::sleep(Duration::from_millis(15));
+        assert!(debounce.ready());
+        assert!(!debounce.ready()); // Fires once per burst
+    }
+
+    #[test]
+    fn test_stopwatch_timers() {
+        let ctx = create_test_ctx();
+        let handle = timer_start(&[], &ctx).unwrap();
+
+        std::thread::sleep(Duration::from_millis(5));
+        let lap = timer_lap(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(matches!(lap, Value::Float(ms) if ms >= 5.0));
+
+        let elapsed = timer_elapsed(std::slice::from_ref(&handle), &ctx).unwrap();
+        let Value::Float(elapsed) = elapsed else {
+            panic!("expected float")
+        };
+        let second_lap = timer_lap(std::slice::from_ref(&handle), &ctx).unwrap();
+        assert!(matches!(second_lap, Value::Float(ms) if ms < elapsed));
+
+        #[cfg(feature = "metrics")]
+        {
+            let args = [handle.clone(), Value::String("test.timer_ms".into())];
+            let stopped = timer_stop(&args, &ctx).unwrap();
+            let stats = crate::metrics::global()
+                .histogram_stats("test.timer_ms")
+                .unwrap();
+            assert_eq!(stats.count, 1);
+            assert_eq!(Value::Float(stats.sum), stopped);
+        }
+        #[cfg(not(feature = "metrics"))]
+        timer_stop(std::slice::from_ref(&handle), &ctx).unwrap();
+
+        assert!(timer_elapsed(&[handle], &ctx).is_err());
+    }
+
+    #[cfg(feature = "cron")]
+    #[test]
+    fn test_cron() {
+        let ctx = create_test_ctx();
+        let expr = Value::String("*/15 9-17 * * MON-FRI".into());
+
+        // Wednesday 2024-01-31 13:45:10 UTC -> 14:00:00
+        let next = cron_next(&[expr.clone(), Value::Int(1706708710)], &ctx).unwrap();
+        assert_eq!(next, Value::Int(1706709600));
+
+        // Friday 2024-02-02 17:50 UTC -> Monday 2024-02-05 09:00
+        let next = cron_next(&[expr.clone(), Value::Int(1706896200)], &ctx).unwrap();
+        assert_eq!(next, Value::Int(1707123600));
+
+        let matches = |ts: i64| cron_matches(&[expr.clone(), Value::Int(ts)], &ctx).unwrap();
+        assert_eq!(matches(1706709600 + 30), Value::Bool(true));
+        assert_eq!(matches(1706708770), Value::Bool(false)); // 13:46:10
+
+        let daily = Value::String("@daily".into());
+        let next = cron_next(&[daily, Value::Int(1706708710)], &ctx).unwrap();
+        assert_eq!(next, Value::Int(1706745600));
+
+        assert!(cron_next(&[Value::String("61 * * * *".into())], &ctx).is_err());
+
+        // Numeric days follow crontab: 0 and 7 are Sunday, 1 is Monday
+        let matches = |expr: &str, ts: i64| {
+            cron_matches(&[Value::String(expr.into()), Value::Int(ts)], &ctx).unwrap()
+        };
+        let sunday = 1707048000; // 2024-02-04 12:00 UTC
+        let monday = sunday + 86400;
+        let saturday = sunday - 86400;
+        assert_eq!(matches("0 12 * * 1", monday), Value::Bool(true));
+        assert_eq!(matches("0 12 * * 1", sunday), Value::Bool(false));
+        assert_eq!(matches("0 12 * * 0", sunday), Value::Bool(true));
+        assert_eq!(matches("0 12 * * 7", sunday), Value::Bool(true));
+        assert_eq!(matches("0 12 * * 7", saturday), Value::Bool(false));
+        assert_eq!(matches("0 12 * * 1-5", monday), Value::Bool(true));
+        assert_eq!(matches("0 12 * * 1-5", saturday), Value::Bool(false));
+        assert_eq!(matches("0 12 * * 5-7", sunday), Value::Bool(true));
+        assert_eq!(matches("0 12 * * 0,6", saturday), Value::Bool(true));
+        assert_eq!(matches("0 12 * * 0,6", monday), Value::Bool(false));
+        assert_eq!(matches("0 12 * * */2", sunday), Value::Bool(true));
+        assert_eq!(matches("0 12 * * */2", monday), Value::Bool(false));
+        let next = cron_next(
+            &[Value::String("0 9 * * 1".into()), Value::Int(saturday)],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(next, Value::Int(monday - 3 * 3600));
+        assert!(cron_next(&[Value::String("0 12 * * 8".into())], &ctx).is_err());
+        assert!(cron_next(&[Value::String("0 12 * * 5-1".into())], &ctx).is_err());
+    }
+
     #[test]
     fn test_duration_helpers() {
         assert_eq!(duration::seconds_to_millis(5), 5000);
@@ -187,4 +2029,176 @@ mod tests {
         assert_eq!(duration::hours_to_seconds(1), 3600);
         assert_eq!(duration::days_to_seconds(1), 86400);
     }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_timezone_conversion() {
+        let ctx = create_test_ctx();
+
+        // 2024-07-01 12:00:00 UTC (CEST, UTC+2)
+        let summer = Value::Int(1_719_835_200);
+        let formatted = format_tz(
+            &[
+                summer.clone(),
+                Value::String("Europe/Berlin".into()),
+                Value::String("%Y-%m-%d %H:%M %Z".into()),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(formatted, Value::String("2024-07-01 14:00 CEST".into()));
+
+        let fields = to_zone(
+            &[summer.clone(), Value::String("America/New_York".into())],
+            &ctx,
+        )
+        .unwrap();
+        let fields = fields.as_map().unwrap();
+        assert_eq!(fields.get("hour"), Some(&Value::Int(8)));
+        assert_eq!(fields.get("offset"), Some(&Value::Int(-4 * 3600)));
+        assert_eq!(fields.get("weekday"), Some(&Value::Int(1)));
+        // 2024-06-30 00:00:00 UTC is Sunday morning in Tokyo
+        let sunday = to_zone(
+            &[
+                Value::Int(1_719_705_600),
+                Value::String("Asia/Tokyo".into()),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(
+            sunday.as_map().unwrap().get("weekday"),
+            Some(&Value::Int(7))
+        );
+
+        // 2024-01-15 12:00:00 UTC (CET, UTC+1)
+        let winter = Value::Int(1_705_320_000);
+        let offset_value = offset(&[Value::String("Europe/Berlin".into()), winter], &ctx).unwrap();
+        assert_eq!(offset_value, Value::Int(3600));
+
+        assert!(offset(&[Value::String("Mars/Olympus".into())], &ctx).is_err());
+        assert!(format_tz(
+            &[
+                summer,
+                Value::String("UTC".into()),
+                Value::String("%Q".into())
+            ],
+            &ctx
+        )
+        .is_err());
+
+        let zone = parse_zone("Asia/Tokyo", "test").unwrap();
+        let local = now_local(Some(&zone), &[], &ctx).unwrap();
+        assert!(local.as_str().unwrap().ends_with("+09:00"));
+    }
+
+    #[cfg(feature = "tz")]
+    #[test]
+    fn test_timezone_listing_and_offsets() {
+        let ctx = create_test_ctx();
+
+        let all = timezones(&[], &ctx).unwrap();
+        let all = all.as_list().unwrap();
+        assert!(all.len() > 300);
+        assert!(all.contains(&Value::String("UTC".into())));
+        assert!(all
+            .windows(2)
+            .all(|pair| pair[0].as_str() <= pair[1].as_str()));
+
+        let europe = timezones(&[Value::String("Europe/".into())], &ctx).unwrap();
+        let europe = europe.as_list().unwrap();
+        assert!(europe.contains(&Value::String("Europe/Berlin".into())));
+        assert!(europe
+            .iter()
+            .all(|name| name.as_str().unwrap().starts_with("Europe/")));
+
+        // 2024-07-01 12:00:00 UTC: New York is on EDT.
+        let summer = tz_offset(
+            &[
+                Value::String("America/New_York".into()),
+                Value::Int(1_719_835_200),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        let summer = summer.as_map().unwrap();
+        assert_eq!(summer["offset"], Value::Int(-4 * 3600));
+        assert_eq!(summer["base_offset"], Value::Int(-5 * 3600));
+        assert_eq!(summer["dst_offset"], Value::Int(3600));
+        assert_eq!(summer["dst"], Value::Bool(true));
+        assert_eq!(summer["abbreviation"], Value::String("EDT".into()));
+
+        let winter = tz_offset(
+            &[
+                Value::String("America/New_York".into()),
+                Value::Int(1_705_320_000),
+            ],
+            &ctx,
+        )
+        .unwrap();
+        assert_eq!(winter.as_map().unwrap()["dst"], Value::Bool(false));
+
+        assert!(tz_offset(&[Value::String("Nowhere/Atlantis".into())], &ctx).is_err());
+        assert_eq!(TZDB_VERSION.len(), 5);
+    }
+
+    #[test]
+    fn test_ntp_response_parsing() {
+        let t1 = 3_900_000_000.25;
+        let t4 = t1 + 0.100;
+        let mut response = [0u8; 48];
+        response[0] = 0x24; // VN 4, server mode
+        response[1] = 2;
+        response[24..32].copy_from_slice(&to_ntp_timestamp(t1));
+        // Server is 1.5s ahead; 20ms spent on the server.
+        response[32..40].copy_from_slice(&to_ntp_timestamp(t1 + 0.040 + 1.5));
+        response[40..48].copy_from_slice(&to_ntp_timestamp(t1 + 0.060 + 1.5));
+
+        let sample = parse_ntp_response(&response, t1, t4).unwrap();
+        assert!((sample.offset - 1.5).abs() < 0.001);
+        assert!((sample.roundtrip - 0.080).abs() < 0.001);
+        assert_eq!(sample.stratum, 2);
+
+        let mismatched = parse_ntp_response(&response, t1 + 1.0, t4);
+        assert!(mismatched.is_err());
+
+        response[1] = 0;
+        response[12..16].copy_from_slice(b"RATE");
+        let err = parse_ntp_response(&response, t1, t4).unwrap_err();
+        assert!(err.to_string().contains("RATE"));
+    }
+
+    #[test]
+    fn test_ntp_offset_local_server() {
+        use crate::safety::{HostAllowlist, SafetyConfig};
+        use std::sync::Arc;
+
+        let server = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = server.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut request = [0u8; 48];
+            let (_, peer) = server.recv_from(&mut request).unwrap();
+            let mut response = [0u8; 48];
+            response[0] = 0x24;
+            response[1] = 1;
+            response[24..32].copy_from_slice(&request[40..48]);
+            let now = to_ntp_timestamp(ntp_now());
+            response[32..40].copy_from_slice(&now);
+            response[40..48].copy_from_slice(&now);
+            server.send_to(&response, peer).unwrap();
+        });
+
+        let ctx = create_test_ctx();
+        let args = [Value::String(format!("127.0.0.1:{}", port))];
+
+        let denied = Arc::new(SafetyConfig::new());
+        assert!(ntp_offset(&denied, &args, &ctx).is_err());
+
+        let safety =
+            Arc::new(SafetyConfig::new().with_hosts(HostAllowlist::none().allow("127.0.0.1")));
+        let result = ntp_offset(&safety, &args, &ctx).unwrap();
+        let map = result.as_map().unwrap();
+        assert!(map.get("offset_ms").unwrap().as_float().unwrap().abs() < 1000.0);
+        assert_eq!(map.get("stratum"), Some(&Value::Int(1)));
+    }
 }
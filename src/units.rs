@@ -0,0 +1,379 @@
+//! Units module.
+//!
+//! Parses and formats human-entered quantities: byte sizes (`"1.5GiB"`),
+//! durations (`"1h30m"`, `"250ms"`), and SI-prefixed numbers (`"3.2k"`).
+//!
+//! Byte units follow the IEC/SI split: `KiB`/`Ki` are powers of 1024 and
+//! `KB`/`kB`/`K` are powers of 1000. Unit letters are case-insensitive
+//! except for SI prefixes, where `m` (milli) and `M` (mega) differ.
+
+use std::collections::HashMap;
+
+use fusabi_host::ExecutionContext;
+use fusabi_host::Value;
+
+const BINARY_UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+const DECIMAL_UNITS: &[&str] = &["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+
+/// Duration units in milliseconds, largest first (used for formatting).
+const DURATION_UNITS: &[(&str, f64)] = &[
+    ("w", 604_800_000.0),
+    ("d", 86_400_000.0),
+    ("h", 3_600_000.0),
+    ("m", 60_000.0),
+    ("s", 1_000.0),
+    ("ms", 1.0),
+];
+
+/// SI prefixes and their multipliers, smallest first.
+const SI_PREFIXES: &[(&str, f64)] = &[
+    ("p", 1e-12),
+    ("n", 1e-9),
+    ("u", 1e-6),
+    ("m", 1e-3),
+    ("", 1.0),
+    ("k", 1e3),
+    ("M", 1e6),
+    ("G", 1e9),
+    ("T", 1e12),
+    ("P", 1e15),
+    ("E", 1e18),
+];
+
+/// Parse a byte size such as `"1.5GiB"`, `"512 KB"` or `"100"`.
+///
+/// # Returns
+///
+/// Number of bytes (integer, rounded)
+pub fn parse_bytes(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = string_arg(args, "units.parse_bytes")?;
+    let bytes = parse_bytes_str(text).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("units.parse_bytes: invalid size '{}'", text))
+    })?;
+    Ok(Value::Int(bytes))
+}
+
+/// Format a byte count for display.
+///
+/// # Arguments
+///
+/// * `args[0]` - Number of bytes
+/// * `args[1]` - Options map (optional): `binary` (default true, IEC units),
+///   `precision` (decimal places, default 1)
+pub fn format_bytes(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let bytes = number_arg(args, "units.format_bytes")?;
+    let options = args.get(1).and_then(|v| v.as_map());
+    let binary = options
+        .and_then(|m| m.get("binary"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+    let precision = precision_option(options, "units.format_bytes")?;
+
+    let (base, units) = if binary {
+        (1024.0, BINARY_UNITS)
+    } else {
+        (1000.0, DECIMAL_UNITS)
+    };
+
+    let mut value = bytes;
+    let mut unit = 0;
+    while value.abs() >= base && unit < units.len() - 1 {
+        value /= base;
+        unit += 1;
+    }
+
+    let formatted = if unit == 0 {
+        format!("{} B", bytes.round())
+    } else {
+        format!("{:.*} {}", precision, value, units[unit])
+    };
+    Ok(Value::String(formatted))
+}
+
+/// Parse a duration such as `"250ms"`, `"1.5s"` or `"1h30m"`.
+///
+/// Units are `ns`, `us`, `ms`, `s`, `m`, `h`, `d` and `w`; a bare number is
+/// taken as milliseconds.
+///
+/// # Returns
+///
+/// Milliseconds (integer, rounded)
+pub fn parse_duration(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = string_arg(args, "units.parse_duration")?;
+    let millis = parse_duration_str(text).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!(
+            "units.parse_duration: invalid duration '{}'",
+            text
+        ))
+    })?;
+    Ok(Value::Int(millis.round() as i64))
+}
+
+/// Format milliseconds as a compact duration such as `"1h30m"`.
+///
+/// The output is accepted by `parse_duration`.
+pub fn format_duration(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let millis = number_arg(args, "units.format_duration")?.round();
+
+    if millis == 0.0 {
+        return Ok(Value::String("0s".to_string()));
+    }
+
+    let mut out = String::new();
+    if millis < 0.0 {
+        out.push('-');
+    }
+    let mut rest = millis.abs();
+    for (unit, size) in DURATION_UNITS {
+        let count = (rest / size).floor();
+        if count > 0.0 {
+            out.push_str(&format!("{}{}", count, unit));
+            rest -= count * size;
+        }
+    }
+    Ok(Value::String(out))
+}
+
+/// Parse an SI-prefixed number such as `"3.2k"`, `"450m"` or `"1.5G"`.
+///
+/// # Returns
+///
+/// The value as a float
+pub fn parse_si(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let text = string_arg(args, "units.parse_si")?;
+    let value = parse_si_str(text).ok_or_else(|| {
+        fusabi_host::Error::host_function(format!("units.parse_si: invalid number '{}'", text))
+    })?;
+    Ok(Value::Float(value))
+}
+
+/// Format a number with an SI prefix, e.g. `3200` as `"3.2k"`.
+///
+/// # Arguments
+///
+/// * `args[0]` - Number
+/// * `args[1]` - Options map (optional): `precision` (decimal places,
+///   default 1)
+pub fn format_si(args: &[Value], _ctx: &ExecutionContext) -> fusabi_host::Result<Value> {
+    let value = number_arg(args, "units.format_si")?;
+    let precision = precision_option(args.get(1).and_then(|v| v.as_map()), "units.format_si")?;
+
+    if value == 0.0 || !value.is_finite() {
+        return Ok(Value::String(format!("{:.*}", precision, value)));
+    }
+
+    let (prefix, scale) = SI_PREFIXES
+        .iter()
+        .rev()
+        .find(|(_, scale)| value.abs() >= *scale)
+        .unwrap_or(&SI_PREFIXES[0]);
+
+    Ok(Value::String(format!(
+        "{:.*}{}",
+        precision,
+        value / scale,
+        prefix
+    )))
+}
+
+fn string_arg<'a>(args: &'a [Value], function: &str) -> fusabi_host::Result<&'a str> {
+    args.first()
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| fusabi_host::Error::host_function(format!("{}: missing string", function)))
+}
+
+fn number_arg(args: &[Value], function: &str) -> fusabi_host::Result<f64> {
+    args.first()
+        .and_then(|v| v.as_float().or_else(|| v.as_int().map(|i| i as f64)))
+        .ok_or_else(|| fusabi_host::Error::host_function(format!("{}: missing number", function)))
+}
+
+fn precision_option(
+    options: Option<&HashMap<String, Value>>,
+    function: &str,
+) -> fusabi_host::Result<usize> {
+    match options.and_then(|m| m.get("precision")) {
+        None => Ok(1),
+        Some(value) => value
+            .as_int()
+            .filter(|p| (0..=12).contains(p))
+            .map(|p| p as usize)
+            .ok_or_else(|| {
+                fusabi_host::Error::host_function(format!(
+                    "{}: precision must be between 0 and 12",
+                    function
+                ))
+            }),
+    }
+}
+
+/// Split a leading decimal number from its unit suffix.
+fn split_number(text: &str) -> Option<(f64, &str)> {
+    let text = text.trim();
+    let end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(text.len());
+    let number: f64 = text[..end].parse().ok()?;
+    number.is_finite().then_some((number, text[end..].trim()))
+}
+
+fn parse_bytes_str(text: &str) -> Option<i64> {
+    let (number, unit) = split_number(text)?;
+    if number < 0.0 {
+        return None;
+    }
+
+    let unit = unit.to_ascii_lowercase();
+    let unit = unit.strip_suffix('b').unwrap_or(&unit);
+    let (prefix, base) = match unit.strip_suffix('i') {
+        Some(prefix) if !prefix.is_empty() => (prefix, 1024f64),
+        _ => (unit, 1000f64),
+    };
+    let exponent = match prefix {
+        "" => 0,
+        "k" => 1,
+        "m" => 2,
+        "g" => 3,
+        "t" => 4,
+        "p" => 5,
+        "e" => 6,
+        _ => return None,
+    };
+
+    let bytes = (number * base.powi(exponent)).round();
+    (bytes <= i64::MAX as f64).then_some(bytes as i64)
+}
+
+fn parse_duration_str(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (negative, mut rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    if rest.is_empty() {
+        return None;
+    }
+
+    if let Ok(bare) = rest.parse::<f64>() {
+        return bare
+            .is_finite()
+            .then_some(if negative { -bare } else { bare });
+    }
+
+    let mut total = 0.0;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let scale = match rest[..unit_len].trim() {
+            "ns" => 1e-6,
+            "us" | "µs" => 1e-3,
+            "ms" => 1.0,
+            "s" | "sec" => 1_000.0,
+            "m" | "min" => 60_000.0,
+            "h" | "hr" => 3_600_000.0,
+            "d" => 86_400_000.0,
+            "w" => 604_800_000.0,
+            _ => return None,
+        };
+        rest = rest[unit_len..].trim_start();
+        total += number * scale;
+    }
+
+    Some(if negative { -total } else { total })
+}
+
+fn parse_si_str(text: &str) -> Option<f64> {
+    let (number, suffix) = split_number(text)?;
+    let suffix = match suffix {
+        "K" => "k",
+        "µ" => "u",
+        other => other,
+    };
+    SI_PREFIXES
+        .iter()
+        .find(|(prefix, _)| *prefix == suffix)
+        .map(|(_, scale)| number * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fusabi_host::Capabilities;
+    use fusabi_host::Limits;
+    use fusabi_host::{Sandbox, SandboxConfig};
+
+    fn create_test_ctx() -> ExecutionContext {
+        let sandbox = Sandbox::new(SandboxConfig::default()).unwrap();
+        ExecutionContext::new(1, Capabilities::none(), Limits::default(), sandbox)
+    }
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(parse_bytes_str("1.5GiB"), Some(1_610_612_736));
+        assert_eq!(parse_bytes_str("512 KB"), Some(512_000));
+        assert_eq!(parse_bytes_str("2Mi"), Some(2_097_152));
+        assert_eq!(parse_bytes_str("100"), Some(100));
+        assert_eq!(parse_bytes_str("1 gb"), Some(1_000_000_000));
+        assert_eq!(parse_bytes_str("-1KB"), None);
+        assert_eq!(parse_bytes_str("3 XB"), None);
+
+        let ctx = create_test_ctx();
+        let formatted = format_bytes(&[Value::Int(1_610_612_736)], &ctx).unwrap();
+        assert_eq!(formatted, Value::String("1.5 GiB".into()));
+
+        let mut options = HashMap::new();
+        options.insert("binary".to_string(), Value::Bool(false));
+        options.insert("precision".to_string(), Value::Int(2));
+        let formatted = format_bytes(&[Value::Int(1_234_567), Value::Map(options)], &ctx).unwrap();
+        assert_eq!(formatted, Value::String("1.23 MB".into()));
+
+        let formatted = format_bytes(&[Value::Int(512)], &ctx).unwrap();
+        assert_eq!(formatted, Value::String("512 B".into()));
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration_str("250ms"), Some(250.0));
+        assert_eq!(parse_duration_str("1.5s"), Some(1500.0));
+        assert_eq!(parse_duration_str("1h30m"), Some(5_400_000.0));
+        assert_eq!(parse_duration_str("2m 10s"), Some(130_000.0));
+        assert_eq!(parse_duration_str("500"), Some(500.0));
+        assert_eq!(parse_duration_str("-1s"), Some(-1000.0));
+        assert_eq!(parse_duration_str("5 parsecs"), None);
+        assert_eq!(parse_duration_str(""), None);
+
+        let ctx = create_test_ctx();
+        let formatted = format_duration(&[Value::Int(5_410_250)], &ctx).unwrap();
+        assert_eq!(formatted, Value::String("1h30m10s250ms".into()));
+        let parsed = parse_duration(&[formatted], &ctx).unwrap();
+        assert_eq!(parsed, Value::Int(5_410_250));
+
+        let zero = format_duration(&[Value::Int(0)], &ctx).unwrap();
+        assert_eq!(zero, Value::String("0s".into()));
+    }
+
+    #[test]
+    fn test_si() {
+        assert_eq!(parse_si_str("3.2k"), Some(3200.0));
+        assert_eq!(parse_si_str("3.2K"), Some(3200.0));
+        assert_eq!(parse_si_str("450m"), Some(0.45));
+        assert_eq!(parse_si_str("1.5G"), Some(1.5e9));
+        assert_eq!(parse_si_str("42"), Some(42.0));
+        assert_eq!(parse_si_str("1x"), None);
+
+        let ctx = create_test_ctx();
+        let formatted = format_si(&[Value::Int(3200)], &ctx).unwrap();
+        assert_eq!(formatted, Value::String("3.2k".into()));
+        let formatted = format_si(&[Value::Float(0.045)], &ctx).unwrap();
+        assert_eq!(formatted, Value::String("45.0m".into()));
+        let formatted = format_si(&[Value::Int(12)], &ctx).unwrap();
+        assert_eq!(formatted, Value::String("12.0".into()));
+    }
+}